@@ -0,0 +1,34 @@
+/// A value animated over time by interpolating linearly between keyframes.
+pub struct Track {
+    // (time in seconds, value), in time order
+    keys: &'static [(f32, f32)],
+}
+
+impl Track {
+    pub const fn new(keys: &'static [(f32, f32)]) -> Self {
+        Self { keys }
+    }
+
+    /// Time of the last keyframe, in seconds.
+    pub fn duration(&self) -> f32 {
+        self.keys.last().map_or(0.0, |&(time, _)| time)
+    }
+
+    /// The value at `time`, holding the first and last values outside the keyframes.
+    pub fn sample(&self, time: f32) -> f32 {
+        let Some(&(first_time, first_value)) = self.keys.first() else {
+            return 0.0;
+        };
+        if time <= first_time {
+            return first_value;
+        }
+        for pair in self.keys.windows(2) {
+            let ((start, from), (end, to)) = (pair[0], pair[1]);
+            if time <= end {
+                let t = if end > start { (time - start) / (end - start) } else { 1.0 };
+                return from + (to - from) * t;
+            }
+        }
+        self.keys[self.keys.len() - 1].1
+    }
+}
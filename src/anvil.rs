@@ -0,0 +1,210 @@
+use crate::block::BlockType;
+use crate::nbt::{self, Tag};
+use crate::schematic::from_minecraft_name;
+use crate::world::{empty_blocks, ChunkBlocks, CHUNK_SIZE};
+use crate::world_generator::{ChunkData, WorldGenerator};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+// Region files hold 32 by 32 chunk columns, stored in 4 KiB sectors
+const REGION_CHUNKS: i32 = 32;
+const SECTOR_SIZE: usize = 4096;
+// Chunk compression schemes
+const GZIP: u8 = 1;
+const ZLIB: u8 = 2;
+const UNCOMPRESSED: u8 = 3;
+// 1.16, from which block state indices no longer straddle two longs
+const NON_SPANNING_DATA_VERSION: i64 = 2566;
+const SECTION_BLOCKS: usize = CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE;
+
+// Contents of a region file, None for those the save doesn't have
+type Region = Option<Arc<[u8]>>;
+
+/// A Minecraft Java save read one chunk at a time from its `.mca` region
+/// files, so it can be explored in place of generated terrain. Saves from 1.13
+/// on are understood. Nothing is ever written back.
+///
+/// Minecraft sections are 16 blocks cubed like this engine's chunks, and keep
+/// their coordinates: section y 0 holds blocks 0 to 15, and so on. Blocks
+/// this engine has nothing like become placeholders.
+pub struct AnvilWorld {
+    region_directory: PathBuf,
+    spawn: Option<(i32, i32, i32)>,
+    // Region files read so far
+    regions: Mutex<HashMap<(i32, i32), Region>>,
+    // Unknown block names already warned about
+    reported: Mutex<BTreeSet<String>>,
+}
+
+impl AnvilWorld {
+    /// Opens the save folder, the one holding `level.dat` and `region`.
+    pub fn open(save: &Path) -> Result<Self, String> {
+        let region_directory = save.join("region");
+        if !region_directory.is_dir() {
+            return Err(format!("No region folder in {}", save.display()));
+        }
+
+        // Spawn is a nicety, so a missing or odd level.dat is no reason to fail
+        let spawn = fs::read(save.join("level.dat")).ok()
+            .and_then(|data| nbt::read(&data).ok())
+            .and_then(|(_, root)| {
+                let data = root.get("Data")?;
+                let coordinate = |name: &str| data.get(name).and_then(Tag::as_i64).map(|v| v as i32);
+                Some((coordinate("SpawnX")?, coordinate("SpawnY")?, coordinate("SpawnZ")?))
+            });
+        log::info!(target: "io", "Opened Minecraft save {}", save.display());
+
+        Ok(Self {
+            region_directory,
+            spawn,
+            regions: Mutex::new(HashMap::new()),
+            reported: Mutex::new(BTreeSet::new()),
+        })
+    }
+
+    /// Where players spawn in the save, if it says.
+    pub fn spawn(&self) -> Option<(i32, i32, i32)> {
+        self.spawn
+    }
+
+    /// Blocks of the chunk at a chunk position, all air where the save has
+    /// nothing. Chunks that can't be read are reported and left empty.
+    pub fn chunk(&self, position: (i32, i32, i32)) -> ChunkBlocks {
+        let mut blocks = empty_blocks();
+        match self.read_section(position, &mut blocks) {
+            Ok(()) => {},
+            Err(e) => log::warn!(target: "io", "Failed to read Minecraft chunk {:?}: {}", position, e),
+        }
+        blocks
+    }
+
+    fn region(&self, region: (i32, i32)) -> Region {
+        let mut regions = self.regions.lock().unwrap();
+        regions.entry(region)
+            .or_insert_with(|| {
+                let path = self.region_directory.join(format!("r.{}.{}.mca", region.0, region.1));
+                fs::read(path).ok().map(Arc::from)
+            })
+            .clone()
+    }
+
+    // The NBT of a chunk column, if the save has it
+    fn column(&self, x: i32, z: i32) -> Result<Option<Tag>, String> {
+        let Some(region) = self.region((x.div_euclid(REGION_CHUNKS), z.div_euclid(REGION_CHUNKS))) else {
+            return Ok(None);
+        };
+        // The header starts with a location per column: a 3-byte sector offset and a sector count
+        let index = (x.rem_euclid(REGION_CHUNKS) + z.rem_euclid(REGION_CHUNKS) * REGION_CHUNKS) as usize * 4;
+        let location = region.get(index..index + 4).ok_or("Truncated region header")?;
+        let offset = u32::from_be_bytes([0, location[0], location[1], location[2]]) as usize * SECTOR_SIZE;
+        if offset == 0 {
+            return Ok(None);
+        }
+
+        let header = region.get(offset..offset + 5).ok_or("Chunk outside the region file")?;
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let compressed = region.get(offset + 5..offset + 4 + length).ok_or("Truncated chunk")?;
+        let mut data = Vec::new();
+        match header[4] {
+            GZIP => GzDecoder::new(compressed).read_to_end(&mut data).map_err(|e| e.to_string())?,
+            ZLIB => ZlibDecoder::new(compressed).read_to_end(&mut data).map_err(|e| e.to_string())?,
+            UNCOMPRESSED => {
+                data.extend_from_slice(compressed);
+                data.len()
+            },
+            scheme => return Err(format!("Unsupported chunk compression: {}", scheme)),
+        };
+        nbt::read(&data).map(|(_, root)| Some(root))
+    }
+
+    // Fills in the blocks of one section from its palette and packed indices
+    fn read_section(&self, position: (i32, i32, i32), blocks: &mut ChunkBlocks) -> Result<(), String> {
+        let Some(column) = self.column(position.0, position.2)? else {
+            return Ok(());
+        };
+        let data_version = column.get("DataVersion").and_then(Tag::as_i64).unwrap_or(0);
+        // Before 1.18 everything sat in a Level compound, with differently named fields
+        let (sections, palette_name, states_name) = match column.get("Level") {
+            Some(level) => (level.get("Sections"), "Palette", "BlockStates"),
+            None => (column.get("sections"), "palette", "data"),
+        };
+        let Some(sections) = sections.and_then(Tag::as_list) else {
+            return Ok(());
+        };
+        let section = sections.iter()
+            .find(|section| section.get("Y").and_then(Tag::as_i64) == Some(position.1 as i64));
+        let Some(section) = section else {
+            return Ok(());
+        };
+        let states = section.get("block_states").unwrap_or(section);
+        let Some(palette) = states.get(palette_name).and_then(Tag::as_list) else {
+            // Pre-1.13 sections number their blocks instead
+            return Ok(());
+        };
+
+        let palette = palette.iter()
+            .map(|state| {
+                let name = state.get("Name").and_then(Tag::as_str).ok_or("Palette entry without a name")?;
+                Ok(from_minecraft_name(name).unwrap_or_else(|| {
+                    if self.reported.lock().unwrap().insert(name.to_string()) {
+                        log::debug!(target: "io", "No block like {}, using a placeholder", name);
+                    }
+                    BlockType::Placeholder
+                }))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let indices = match states.get(states_name) {
+            Some(Tag::LongArray(longs)) => unpack(longs, palette.len(), data_version >= NON_SPANNING_DATA_VERSION)?,
+            // A single-entry palette has no data: the whole section is that block
+            _ => vec![0; SECTION_BLOCKS],
+        };
+
+        for (i, &index) in indices.iter().enumerate() {
+            let block = *palette.get(index).ok_or_else(|| format!("Palette index out of range: {}", index))?;
+            // Indices run through x, then z, then y
+            blocks[i % CHUNK_SIZE][i / (CHUNK_SIZE * CHUNK_SIZE)][i / CHUNK_SIZE % CHUNK_SIZE] = block;
+        }
+        Ok(())
+    }
+}
+
+// Imported chunks are read rather than generated, so the seed doesn't matter,
+// and they already have whatever was built in them
+impl WorldGenerator for AnvilWorld {
+    fn generate_chunk(&self, _seed: u32, position: (i32, i32, i32)) -> ChunkData {
+        ChunkData { blocks: self.chunk(position), structures: false }
+    }
+}
+
+// Block state indices are packed into longs with just enough bits for the
+// palette, at least four. Since 1.16 an index never straddles two longs and
+// the leftover bits of each long go unused.
+fn unpack(longs: &[i64], palette_size: usize, non_spanning: bool) -> Result<Vec<usize>, String> {
+    let bits = (usize::BITS - palette_size.saturating_sub(1).leading_zeros()).max(4) as usize;
+    let mask = (1u64 << bits) - 1;
+    let per_long = 64 / bits;
+    let needed = if non_spanning { SECTION_BLOCKS.div_ceil(per_long) } else { (SECTION_BLOCKS * bits).div_ceil(64) };
+    if longs.len() < needed {
+        return Err(format!("Block states hold {} longs, expected {}", longs.len(), needed));
+    }
+
+    Ok((0..SECTION_BLOCKS)
+        .map(|i| {
+            let value = if non_spanning {
+                longs[i / per_long] as u64 >> (i % per_long * bits)
+            } else {
+                let (long, shift) = (i * bits / 64, i * bits % 64);
+                let mut value = longs[long] as u64 >> shift;
+                if shift + bits > 64 {
+                    value |= (longs[long + 1] as u64) << (64 - shift);
+                }
+                value
+            };
+            (value & mask) as usize
+        })
+        .collect())
+}
@@ -0,0 +1,206 @@
+use crate::math::Vec3;
+use crate::mesh_cache::MeshCache;
+use crate::mesher::unpack_vertex;
+use crate::world::{parallel_map, World, CHUNK_SIZE};
+
+// Rays cast from every vertex, spread over the hemisphere in front of its face
+const RAYS_PER_VERTEX: usize = 48;
+// How far rays look for blocks. No more than a chunk, so what a chunk's bake
+// sees is all in the chunks around it
+const RAY_LENGTH: f32 = 8.0;
+// How far ray origins move off their face and towards its middle, so they
+// don't start inside the blocks along its edges
+const ORIGIN_OFFSET: f32 = 0.01;
+// Blocks along each side of the chunks a bake reads
+const SPAN: i32 = 3 * CHUNK_SIZE as i32;
+
+/// Ambient occlusion baked for the vertices of a chunk mesh, in the mesh's
+/// vertex order.
+pub struct BakedOcclusion {
+    /// How much of the sky each vertex sees, from 0 when enclosed to 1 in the open.
+    pub visibility: Vec<f32>,
+    /// Average direction of the rays that got away from each vertex, or its
+    /// face normal when none did.
+    pub bent_normals: Vec<[f32; 3]>,
+}
+
+/// Bakes every loaded chunk whose bake isn't in the cache yet, casting rays
+/// against the blocks on all cores, and stores the results next to the cached
+/// meshes. Returns how many chunks were baked and how many were already.
+pub fn bake_world(world: &World, cache: &MeshCache) -> (usize, usize) {
+    let mut positions: Vec<_> = world.chunks()
+        .filter(|chunk| !chunk.mesh.vertices.is_empty())
+        .map(|chunk| chunk.position)
+        .collect();
+    positions.sort_unstable();
+
+    let baked = parallel_map(&positions, |&position| {
+        let key = bake_key(world, position)?;
+        let vertex_count = world.chunk(position)?.mesh.vertices.len();
+        if cache.load_occlusion(world.seed, position, key, vertex_count).is_some() {
+            return Some(false);
+        }
+        cache.store_occlusion(world.seed, position, key, &bake_chunk(world, position)?);
+        Some(true)
+    });
+    let fresh = baked.iter().filter(|&&baked| baked == Some(true)).count();
+    let cached = baked.iter().filter(|&&baked| baked == Some(false)).count();
+    (fresh, cached)
+}
+
+/// Hashes everything a chunk's bake depends on: what its mesh was built from,
+/// and which blocks stop rays in it and the chunks around it. `None` for
+/// chunks that aren't loaded.
+pub fn bake_key(world: &World, position: (i32, i32, i32)) -> Option<u64> {
+    // 64-bit FNV-1a, continuing from the mesh's hash
+    const PRIME: u64 = 0x100000001b3;
+    let mut hash = world.neighborhood(position)?.block_hash();
+    for (index, &solid) in Occluders::new(world, position).solid.iter().enumerate() {
+        // Packs eight blocks per step
+        hash ^= u64::from(solid) << (index % 8);
+        if index % 8 == 7 {
+            hash = hash.wrapping_mul(PRIME);
+        }
+    }
+    Some(hash)
+}
+
+/// Bakes the ambient occlusion of a loaded chunk's mesh.
+pub fn bake_chunk(world: &World, position: (i32, i32, i32)) -> Option<BakedOcclusion> {
+    let mesh = &world.chunk(position)?.mesh;
+    let occluders = Occluders::new(world, position);
+    let directions = hemisphere();
+    let origin = Vec3::new(mesh.origin[0], mesh.origin[1], mesh.origin[2]);
+
+    let mut baked = BakedOcclusion {
+        visibility: Vec::with_capacity(mesh.vertices.len()),
+        bent_normals: Vec::with_capacity(mesh.vertices.len()),
+    };
+    // Meshes are made of quads with their corners counter-clockwise from the front
+    for quad in mesh.vertices.chunks_exact(4) {
+        let corners = [0, 1, 2, 3].map(|corner| {
+            let ([x, y, z], _, _) = unpack_vertex(quad[corner]);
+            origin + Vec3::new(x, y, z)
+        });
+        let normal = (corners[1] - corners[0]).cross(&(corners[2] - corners[0])).normalize();
+        let middle = (corners[0] + corners[1] + corners[2] + corners[3]) * 0.25;
+        // Any direction across the face will do to turn the hemisphere towards it
+        let across = if normal.y.abs() < 0.9 { Vec3::new(0.0, 1.0, 0.0) } else { Vec3::new(1.0, 0.0, 0.0) };
+        let tangent = across.cross(&normal).normalize();
+        let bitangent = normal.cross(&tangent);
+
+        for corner in corners {
+            let start = corner + normal * ORIGIN_OFFSET + (middle - corner) * ORIGIN_OFFSET;
+            let mut open = 0;
+            let mut bent = Vec3::zero();
+            for &[x, y, z] in &directions {
+                let direction = tangent * x + bitangent * y + normal * z;
+                if !occluders.hits(start, direction) {
+                    open += 1;
+                    bent = bent + direction;
+                }
+            }
+            let bent = if open > 0 { bent.normalize() } else { normal };
+            baked.visibility.push(open as f32 / directions.len() as f32);
+            baked.bent_normals.push([bent.x, bent.y, bent.z]);
+        }
+    }
+    Some(baked)
+}
+
+// Directions around +z, spread evenly on a golden angle spiral and weighted
+// by the cosine of their angle to it, so the share of rays that get away is
+// the light an open sky would give
+fn hemisphere() -> Vec<[f32; 3]> {
+    let golden_angle = std::f32::consts::PI * (3.0 - 5.0f32.sqrt());
+    (0..RAYS_PER_VERTEX).map(|ray| {
+        let height = (ray as f32 + 0.5) / RAYS_PER_VERTEX as f32;
+        let radius = height.sqrt();
+        let angle = ray as f32 * golden_angle;
+        [radius * angle.cos(), radius * angle.sin(), (1.0 - height).sqrt()]
+    }).collect()
+}
+
+// Which blocks stop rays in a chunk and the chunks around it, read once so
+// rays don't go through the world's chunk map
+struct Occluders {
+    // World position of the first block
+    origin: (i32, i32, i32),
+    // Whether each block is a full cube, by x, then y, then z
+    solid: Vec<bool>,
+}
+
+impl Occluders {
+    fn new(world: &World, (x, y, z): (i32, i32, i32)) -> Self {
+        let size = CHUNK_SIZE as i32;
+        let mut solid = vec![false; (SPAN * SPAN * SPAN) as usize];
+        for dx in 0..3 {
+            for dy in 0..3 {
+                for dz in 0..3 {
+                    let Some(chunk) = world.chunk((x + dx - 1, y + dy - 1, z + dz - 1)) else {
+                        continue;
+                    };
+                    for (bx, plane) in chunk.blocks.iter().enumerate() {
+                        for (by, row) in plane.iter().enumerate() {
+                            for (bz, block) in row.iter().enumerate() {
+                                let index = Self::index(dx * size + bx as i32, dy * size + by as i32, dz * size + bz as i32);
+                                solid[index] = block.occludes();
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Self { origin: ((x - 1) * size, (y - 1) * size, (z - 1) * size), solid }
+    }
+
+    fn index(x: i32, y: i32, z: i32) -> usize {
+        ((x * SPAN + y) * SPAN + z) as usize
+    }
+
+    // Whether the block at a world position stops rays; anything outside
+    // the chunks read lets them through
+    fn is_solid(&self, x: i32, y: i32, z: i32) -> bool {
+        let (x, y, z) = (x - self.origin.0, y - self.origin.1, z - self.origin.2);
+        let inside = |v: i32| (0..SPAN).contains(&v);
+        inside(x) && inside(y) && inside(z) && self.solid[Self::index(x, y, z)]
+    }
+
+    // Whether a ray runs into a block within `RAY_LENGTH`, stepping from block
+    // to block along it. The block it starts in is in front of its face, so
+    // it isn't checked.
+    fn hits(&self, start: Vec3, direction: Vec3) -> bool {
+        // Blocks are centered on integer coordinates
+        let start = [start.x + 0.5, start.y + 0.5, start.z + 0.5];
+        let direction = [direction.x, direction.y, direction.z];
+        let mut block = start.map(|v| v.floor() as i32);
+        let mut step = [0; 3];
+        // Distance along the ray to the next block boundary on each axis, and
+        // between boundaries
+        let mut next = [f32::INFINITY; 3];
+        let mut spacing = [f32::INFINITY; 3];
+        for axis in 0..3 {
+            if direction[axis] > 0.0 {
+                step[axis] = 1;
+                next[axis] = (block[axis] as f32 + 1.0 - start[axis]) / direction[axis];
+                spacing[axis] = 1.0 / direction[axis];
+            } else if direction[axis] < 0.0 {
+                step[axis] = -1;
+                next[axis] = (start[axis] - block[axis] as f32) / -direction[axis];
+                spacing[axis] = -1.0 / direction[axis];
+            }
+        }
+
+        loop {
+            let axis = if next[0] <= next[1] && next[0] <= next[2] { 0 } else if next[1] <= next[2] { 1 } else { 2 };
+            if next[axis] > RAY_LENGTH {
+                return false;
+            }
+            block[axis] += step[axis];
+            next[axis] += spacing[axis];
+            if self.is_solid(block[0], block[1], block[2]) {
+                return true;
+            }
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use std::io;
+
+#[cfg(feature = "web")]
+use std::cell::RefCell;
+#[cfg(feature = "web")]
+use std::collections::HashMap;
+#[cfg(feature = "web")]
+use wasm_bindgen::JsCast;
+#[cfg(feature = "web")]
+use wasm_bindgen_futures::JsFuture;
+
+#[cfg(feature = "web")]
+thread_local! {
+    // Assets fetched by `fetch`, by path
+    static FETCHED: RefCell<HashMap<String, Vec<u8>>> = RefCell::new(HashMap::new());
+}
+
+/// Reads an asset file by its path from the game's folder. The browser has no
+/// files, so there it gives the copy `fetch` downloaded from the page's folder.
+#[cfg(not(feature = "web"))]
+pub fn read(path: &str) -> io::Result<Vec<u8>> {
+    std::fs::read(path)
+}
+
+#[cfg(feature = "web")]
+pub fn read(path: &str) -> io::Result<Vec<u8>> {
+    FETCHED.with_borrow(|fetched| fetched.get(path).cloned())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{} wasn't fetched", path)))
+}
+
+pub fn read_to_string(path: &str) -> io::Result<String> {
+    String::from_utf8(read(path)?).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Downloads assets for `read`, all at once, relative to the page.
+#[cfg(feature = "web")]
+pub async fn fetch(paths: &[&str]) -> io::Result<()> {
+    let describe = |path: &str, error: wasm_bindgen::JsValue| io::Error::other(format!("Failed to fetch {}: {:?}", path, error));
+    let window = web_sys::window().ok_or_else(|| io::Error::other("No browser window"))?;
+    // Every download starts before the first is waited on
+    let requests = paths.iter().map(|&path| (path, JsFuture::from(window.fetch_with_str(path)))).collect::<Vec<_>>();
+    for (path, request) in requests {
+        let response = request.await
+            .and_then(|response| response.dyn_into::<web_sys::Response>())
+            .map_err(|e| describe(path, e))?;
+        if !response.ok() {
+            return Err(io::Error::other(format!("Failed to fetch {}: HTTP {}", path, response.status())));
+        }
+        let body = response.array_buffer().map_err(|e| describe(path, e))?;
+        let body = JsFuture::from(body).await.map_err(|e| describe(path, e))?;
+        let data = js_sys::Uint8Array::new(&body).to_vec();
+        FETCHED.with_borrow_mut(|fetched| fetched.insert(path.to_string(), data));
+    }
+    Ok(())
+}
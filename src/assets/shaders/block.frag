@@ -1,42 +1,95 @@
 #version 330 core
+in vec3 WorldPos;
 in vec2 TexCoord;
-in float Position;
 in float TextureIndex;
-in float TextSize;
 
 out vec4 FragColor;
 
-uniform sampler2D grassTopTexture;    // texture unit 0
-uniform sampler2D grassSideTexture;   // texture unit 1
-uniform sampler2D dirtTexture;        // texture unit 2
-uniform sampler2D colormapTexture;    // texture unit 3
-uniform sampler2D grassSideOverlayTexture;  // texture unit 4
-uniform sampler2D stoneTexture;       // texture unit 5
-uniform sampler2D waterTexture;       // texture unit 6
+uniform sampler2DArray blockTextures;  // texture unit 0, one layer per BlockTexture
+uniform sampler2D colormapTexture;     // texture unit 1
+uniform float chunkAlpha;              // ramps from 0 to 1 as a chunk fades in
+uniform bool fastLeaves;               // leaves drawn as solid cubes, gaps filled in
+
+// Per-frame data shared by every program, see frame_uniforms.rs
+layout (std140) uniform FrameUniforms {
+    mat4 view;
+    mat4 projection;
+    mat4 viewProjection;
+    vec3 cameraPosition;
+    float time;
+    vec3 sunDirection;
+    float daylight;
+    vec3 fogColor;
+    float fogStart;
+    float fogEnd;
+};
+
+// Point lights from glowing blocks, nearest first; keep in sync with MAX_POINT_LIGHTS
+const int MAX_LIGHTS = 16;
+uniform int lightCount;
+uniform vec3 lightPositions[MAX_LIGHTS];
+uniform vec4 lightColors[MAX_LIGHTS];  // rgb color, radius in blocks in a
+
+// Darkest the world gets when there is no light nearby
+const float MIN_AMBIENT = 0.25;
+
+// Layers that need special treatment, in BlockTexture order
+const float GRASS_TOP = 0.0;
+const float GRASS_SIDE = 1.0;
+const float WATER = 4.0;
+const float GRASS_SIDE_OVERLAY = 8.0;
+const float SHORT_GRASS = 12.0;
+const float OAK_LEAVES = 18.0;
+const float GLOWSTONE = 19.0;
+const float LIT_LAMP = 26.0;
+
+// Daylight plus the colored light reaching this point, fading out with the square of the distance
+vec3 lighting() {
+    vec3 light = vec3(mix(MIN_AMBIENT, 1.0, daylight));
+    for (int i = 0; i < lightCount; i++) {
+        float distance = length(lightPositions[i] - WorldPos);
+        float falloff = max(1.0 - distance / lightColors[i].a, 0.0);
+        light += lightColors[i].rgb * falloff * falloff;
+    }
+    return min(light, vec3(1.5));
+}
 
 void main() {
     vec4 biomeColor = texture(colormapTexture, vec2(0.5, 0.5));  // For now using center of colormap
+    float layer = floor(TextureIndex + 0.5);
+    vec4 color = texture(blockTextures, vec3(TexCoord, layer));
+
+    if (layer == GRASS_TOP || layer == SHORT_GRASS || layer == OAK_LEAVES) {
+        color.rgb *= biomeColor.rgb;
+        if (layer == OAK_LEAVES && fastLeaves) {
+            // Fill the gaps with a dark shade of the foliage, like the inside of the tree
+            color.rgb = mix(biomeColor.rgb * 0.2, color.rgb, color.a);
+            color.a = 1.0;
+        }
+    } else if (layer == GRASS_SIDE) {
+        vec4 overlayTexture = texture(blockTextures, vec3(TexCoord, GRASS_SIDE_OVERLAY));
 
-    if (TextureIndex < 0.5) {  // grass top
-        vec4 grassColor = texture(grassTopTexture, TexCoord);
-        FragColor = grassColor * biomeColor;
-    } else if (TextureIndex < 1.5) {  // grass side
-        vec4 sideTexture = texture(grassSideTexture, TexCoord);
-        vec4 overlayTexture = texture(grassSideOverlayTexture, TexCoord);
-        
         // Apply the biome color to the overlay and blend it with the side texture
         vec4 coloredOverlay = overlayTexture * biomeColor;
-        FragColor = mix(sideTexture, coloredOverlay, overlayTexture.a);
-    } else if (TextureIndex < 2.5) {  // dirt
-        FragColor = texture(dirtTexture, TexCoord);
-    } else if (TextureIndex < 3.5) {  // stone
-        FragColor = texture(stoneTexture, TexCoord);
-    } else {  // water
-        vec4 waterColor = texture(waterTexture, TexCoord);
+        color = mix(color, coloredOverlay, overlayTexture.a);
+    } else if (layer == WATER) {
         // Apply a blue tint to the water
         vec4 waterTint = vec4(0.0, 0.3, 0.8, 1.0);  // Blue color with some green for a natural look
-        waterColor = waterColor * waterTint;
-        waterColor.a = 0.6;  // Make water transparent
-        FragColor = waterColor;
+        color = color * waterTint;
+        color.a = 0.6;  // Make water transparent
+    }
+
+    // Cut out the empty parts of plant and leaf textures
+    if (color.a < 0.1) {
+        discard;
+    }
+    // Glowing blocks are lit by themselves
+    if (layer != GLOWSTONE && layer != LIT_LAMP) {
+        color.rgb *= lighting();
     }
-} 
\ No newline at end of file
+    // Fade into the sky towards the edge of the render distance
+    float fog = smoothstep(fogStart, fogEnd, length(WorldPos - cameraPosition));
+    color.rgb = mix(color.rgb, fogColor, fog);
+    color.a *= chunkAlpha;
+    FragColor = color;
+}
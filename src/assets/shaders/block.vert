@@ -1,21 +1,40 @@
 #version 330 core
-layout (location = 0) in vec3 aPos;
-layout (location = 1) in vec2 aTexCoord;
-layout (location = 2) in float aPosition;
-layout (location = 3) in float aTextureIndex;
-layout (location = 4) in float aTextSize;
+layout (location = 0) in uvec2 aPacked;  // see mesher::pack_vertex
 
-uniform mat4 transform;
+// Steps per block of packed positions and texture coordinates, and how far
+// positions are shifted; keep in sync with mesher.rs
+const float POSITION_STEPS = 32.0;
+const float POSITION_OFFSET = 1.0;
+const float TEX_COORD_STEPS = 32.0;
 
+// Per-frame data shared by every program, see frame_uniforms.rs
+layout (std140) uniform FrameUniforms {
+    mat4 view;
+    mat4 projection;
+    mat4 viewProjection;
+    vec3 cameraPosition;
+    float time;
+    vec3 sunDirection;
+    float daylight;
+    vec3 fogColor;
+    float fogStart;
+    float fogEnd;
+};
+uniform mat4 model;  // places the chunk's mesh in the world
+
+out vec3 WorldPos;
 out vec2 TexCoord;
-out float Position;
 out float TextureIndex;
-out float TextSize;
 
 void main() {
-    gl_Position = transform * vec4(aPos, 1.0);
-    TexCoord = aTexCoord;
-    Position = aPosition;
-    TextureIndex = aTextureIndex;
-    TextSize = aTextSize;
+    vec3 position = vec3(aPacked.x & 1023u, (aPacked.x >> 10) & 1023u, (aPacked.x >> 20) & 1023u) / POSITION_STEPS - POSITION_OFFSET;
+    TexCoord = vec2(aPacked.y & 63u, (aPacked.y >> 6) & 63u) / TEX_COORD_STEPS;
+    TextureIndex = float(aPacked.y >> 12);
+
+    // Work relative to the camera, where the numbers stay small, instead of
+    // going through world positions that lose precision far from the origin
+    vec3 chunkOffset = model[3].xyz - cameraPosition;
+    vec3 relative = chunkOffset + mat3(model) * position;
+    gl_Position = projection * mat4(mat3(view)) * vec4(relative, 1.0);
+    WorldPos = relative + cameraPosition;
 } 
\ No newline at end of file
@@ -0,0 +1,132 @@
+// The block program of the wgpu backend; keep in sync with block.vert and
+// block.frag
+
+// Steps per block of packed positions and texture coordinates, and how far
+// positions are shifted; keep in sync with mesher.rs
+const POSITION_STEPS: f32 = 32.0;
+const POSITION_OFFSET: f32 = 1.0;
+const TEX_COORD_STEPS: f32 = 32.0;
+
+// Point lights from glowing blocks, nearest first; keep in sync with MAX_POINT_LIGHTS
+const MAX_LIGHTS: u32 = 16u;
+
+// Darkest the world gets when there is no light nearby
+const MIN_AMBIENT: f32 = 0.25;
+
+// Layers that need special treatment, in BlockTexture order
+const GRASS_TOP: u32 = 0u;
+const GRASS_SIDE: u32 = 1u;
+const WATER: u32 = 4u;
+const GRASS_SIDE_OVERLAY: u32 = 8u;
+const SHORT_GRASS: u32 = 12u;
+const OAK_LEAVES: u32 = 18u;
+const GLOWSTONE: u32 = 19u;
+const LIT_LAMP: u32 = 26u;
+
+// Per-frame data, laid out like the uniform block in frame_uniforms.rs
+struct FrameUniforms {
+    view: mat4x4<f32>,
+    projection: mat4x4<f32>,
+    view_projection: mat4x4<f32>,
+    camera_position: vec3<f32>,
+    time: f32,
+    sun_direction: vec3<f32>,
+    daylight: f32,
+    fog_color: vec3<f32>,
+    fog_start: f32,
+    fog_end: f32,
+};
+
+// What block.frag takes as plain uniforms, laid out like `BlockUniforms` in
+// wgpu_renderer.rs
+struct BlockUniforms {
+    light_count: u32,
+    fast_leaves: u32,  // leaves drawn as solid cubes, gaps filled in
+    light_positions: array<vec4<f32>, MAX_LIGHTS>,  // w unused
+    light_colors: array<vec4<f32>, MAX_LIGHTS>,     // rgb color, radius in blocks in a
+};
+
+struct ChunkUniforms {
+    origin: vec3<f32>,  // world position of the chunk's first block
+    alpha: f32,         // ramps from 0 to 1 as the chunk fades in
+};
+
+@group(0) @binding(0) var<uniform> frame: FrameUniforms;
+@group(0) @binding(1) var block_textures: texture_2d_array<f32>;  // one layer per BlockTexture
+@group(0) @binding(2) var colormap: texture_2d_array<f32>;        // a single layer
+@group(0) @binding(3) var texture_sampler: sampler;
+@group(0) @binding(4) var<uniform> block: BlockUniforms;
+@group(1) @binding(0) var<uniform> chunk: ChunkUniforms;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world_position: vec3<f32>,
+    @location(1) tex_coord: vec2<f32>,
+    @location(2) @interpolate(flat) layer: u32,
+};
+
+@vertex
+fn vs_main(@location(0) packed: vec2<u32>) -> VertexOutput {
+    let position = vec3<f32>(vec3<u32>(packed.x & 1023u, (packed.x >> 10u) & 1023u, (packed.x >> 20u) & 1023u)) / POSITION_STEPS - POSITION_OFFSET;
+    var out: VertexOutput;
+    out.tex_coord = vec2<f32>(vec2<u32>(packed.y & 63u, (packed.y >> 6u) & 63u)) / TEX_COORD_STEPS;
+    out.layer = packed.y >> 12u;
+
+    // Work relative to the camera, where the numbers stay small
+    let relative = chunk.origin - frame.camera_position + position;
+    let rotation = mat4x4<f32>(
+        vec4<f32>(frame.view[0].xyz, 0.0),
+        vec4<f32>(frame.view[1].xyz, 0.0),
+        vec4<f32>(frame.view[2].xyz, 0.0),
+        vec4<f32>(0.0, 0.0, 0.0, 1.0),
+    );
+    var clip = frame.projection * rotation * vec4<f32>(relative, 1.0);
+    // The projection is OpenGL's, with depths from -1 to 1 rather than 0 to 1
+    clip.z = (clip.z + clip.w) * 0.5;
+    out.clip_position = clip;
+    out.world_position = relative + frame.camera_position;
+    return out;
+}
+
+// Daylight plus the colored light reaching this point, fading out with the square of the distance
+fn lighting(world_position: vec3<f32>) -> vec3<f32> {
+    var light = vec3<f32>(mix(MIN_AMBIENT, 1.0, frame.daylight));
+    for (var i = 0u; i < min(block.light_count, MAX_LIGHTS); i++) {
+        let distance = length(block.light_positions[i].xyz - world_position);
+        let falloff = max(1.0 - distance / block.light_colors[i].a, 0.0);
+        light += block.light_colors[i].rgb * falloff * falloff;
+    }
+    return min(light, vec3<f32>(1.5));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Sampled up front, as textures can't be sampled in branches that depend on the layer
+    let biome_color = textureSample(colormap, texture_sampler, vec2<f32>(0.5, 0.5), 0);
+    let overlay = textureSample(block_textures, texture_sampler, in.tex_coord, GRASS_SIDE_OVERLAY);
+    var color = textureSample(block_textures, texture_sampler, in.tex_coord, in.layer);
+
+    if (in.layer == GRASS_TOP || in.layer == SHORT_GRASS || in.layer == OAK_LEAVES) {
+        color = vec4<f32>(color.rgb * biome_color.rgb, color.a);
+        if (in.layer == OAK_LEAVES && block.fast_leaves != 0u) {
+            // Fill the gaps with a dark shade of the foliage, like the inside of the tree
+            color = vec4<f32>(mix(biome_color.rgb * 0.2, color.rgb, color.a), 1.0);
+        }
+    } else if (in.layer == GRASS_SIDE) {
+        color = mix(color, overlay * biome_color, overlay.a);
+    } else if (in.layer == WATER) {
+        color = vec4<f32>(color.rgb * vec3<f32>(0.0, 0.3, 0.8), 0.6);
+    }
+
+    // Cut out the empty parts of plant and leaf textures
+    if (color.a < 0.1) {
+        discard;
+    }
+    // Glowing blocks are lit by themselves
+    if (in.layer != GLOWSTONE && in.layer != LIT_LAMP) {
+        color = vec4<f32>(color.rgb * lighting(in.world_position), color.a);
+    }
+    // Fade into the sky towards the edge of the render distance
+    let fog = smoothstep(frame.fog_start, frame.fog_end, length(in.world_position - frame.camera_position));
+    return vec4<f32>(mix(color.rgb, frame.fog_color, fog), color.a * chunk.alpha);
+}
@@ -0,0 +1,36 @@
+#version 330 core
+in vec2 WorldPosition;
+in float Distance;
+
+out vec4 FragColor;
+
+uniform sampler2D cloudTexture;  // texture unit 2, one texel per cloud cell
+uniform vec2 windOffset;         // how far the clouds have drifted, in blocks
+uniform float cellSize;          // blocks per texel
+uniform float radius;
+
+// Per-frame data shared by every program, see frame_uniforms.rs
+layout (std140) uniform FrameUniforms {
+    mat4 view;
+    mat4 projection;
+    mat4 viewProjection;
+    vec3 cameraPosition;
+    float time;
+    vec3 sunDirection;
+    float daylight;
+    vec3 fogColor;
+    float fogStart;
+    float fogEnd;
+};
+
+void main() {
+    vec2 uv = (WorldPosition - windOffset) / (cellSize * vec2(textureSize(cloudTexture, 0)));
+    float cover = texture(cloudTexture, uv).r;
+    if (cover < 0.5) {
+        discard;
+    }
+
+    // Fade out towards the edge of the plane instead of ending in a hard line
+    float fade = 1.0 - smoothstep(radius * 0.6, radius, Distance);
+    FragColor = vec4(vec3(daylight), 0.8 * fade);
+}
@@ -0,0 +1,30 @@
+#version 330 core
+layout (location = 0) in vec2 aCorner;  // -1 to 1 across the cloud plane
+
+// Per-frame data shared by every program, see frame_uniforms.rs
+layout (std140) uniform FrameUniforms {
+    mat4 view;
+    mat4 projection;
+    mat4 viewProjection;
+    vec3 cameraPosition;
+    float time;
+    vec3 sunDirection;
+    float daylight;
+    vec3 fogColor;
+    float fogStart;
+    float fogEnd;
+};
+
+uniform float radius;
+uniform float height;
+
+out vec2 WorldPosition;
+out float Distance;
+
+void main() {
+    // The plane follows the camera; the cloud pattern stays put in the world
+    vec2 offset = aCorner * radius;
+    WorldPosition = cameraPosition.xz + offset;
+    Distance = length(offset);
+    gl_Position = viewProjection * vec4(WorldPosition.x, height, WorldPosition.y, 1.0);
+}
@@ -0,0 +1,24 @@
+#version 330 core
+layout (location = 0) in vec3 aPos;
+layout (location = 1) in vec4 aColor;
+
+// Per-frame data shared by every program, see frame_uniforms.rs
+layout (std140) uniform FrameUniforms {
+    mat4 view;
+    mat4 projection;
+    mat4 viewProjection;
+    vec3 cameraPosition;
+    float time;
+    vec3 sunDirection;
+    float daylight;
+    vec3 fogColor;
+    float fogStart;
+    float fogEnd;
+};
+
+out vec4 Color;
+
+void main() {
+    gl_Position = viewProjection * vec4(aPos, 1.0);
+    Color = aColor;
+}
@@ -0,0 +1,40 @@
+#version 330 core
+in vec2 TexCoord;
+in float TextureIndex;
+in vec3 Color;
+
+out vec4 FragColor;
+
+uniform sampler2DArray blockTextures;  // texture unit 0, one layer per BlockTexture
+uniform sampler2D colormapTexture;     // texture unit 1
+
+// Layers that need special treatment, in BlockTexture order
+const float GRASS_TOP = 0.0;
+const float GRASS_SIDE = 1.0;
+const float GRASS_SIDE_OVERLAY = 8.0;
+const float SHORT_GRASS = 12.0;
+const float OAK_LEAVES = 18.0;
+
+void main() {
+    // Untextured meshes use their vertex color only
+    if (TextureIndex < 0.0) {
+        FragColor = vec4(Color, 1.0);
+        return;
+    }
+
+    vec4 biomeColor = texture(colormapTexture, vec2(0.5, 0.5));
+    float layer = floor(TextureIndex + 0.5);
+    vec4 color = texture(blockTextures, vec3(TexCoord, layer));
+
+    if (layer == GRASS_TOP || layer == SHORT_GRASS || layer == OAK_LEAVES) {
+        color.rgb *= biomeColor.rgb;
+    } else if (layer == GRASS_SIDE) {
+        vec4 overlayTexture = texture(blockTextures, vec3(TexCoord, GRASS_SIDE_OVERLAY));
+        color = mix(color, overlayTexture * biomeColor, overlayTexture.a);
+    }
+
+    if (color.a < 0.1) {
+        discard;
+    }
+    FragColor = vec4(color.rgb * Color, color.a);
+}
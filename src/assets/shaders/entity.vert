@@ -0,0 +1,19 @@
+#version 330 core
+layout (location = 0) in vec3 aPos;
+layout (location = 1) in vec2 aTexCoord;
+layout (location = 2) in float aTextureIndex;
+layout (location = 3) in vec3 aColor;
+
+uniform mat4 viewProjection;
+uniform mat4 model;
+
+out vec2 TexCoord;
+out float TextureIndex;
+out vec3 Color;
+
+void main() {
+    gl_Position = viewProjection * model * vec4(aPos, 1.0);
+    TexCoord = aTexCoord;
+    TextureIndex = aTextureIndex;
+    Color = aColor;
+}
@@ -0,0 +1,39 @@
+#version 330 core
+in vec2 TexCoord;
+in float TextureIndex;
+in vec4 Color;
+
+out vec4 FragColor;
+
+uniform sampler2DArray blockTextures;  // texture unit 0, one layer per BlockTexture
+uniform sampler2D colormapTexture;     // texture unit 1
+uniform sampler2D fontAtlas;           // texture unit 3, glyph coverage in red
+
+// Layers tinted with the biome color, in BlockTexture order
+const float GRASS_TOP = 0.0;
+const float SHORT_GRASS = 12.0;
+const float OAK_LEAVES = 18.0;
+
+void main() {
+    // Glyphs take their color from the quad and their shape from the atlas
+    if (TextureIndex < -1.5) {
+        FragColor = vec4(Color.rgb, Color.a * texture(fontAtlas, TexCoord).r);
+        return;
+    }
+    // Untextured quads use their color only
+    if (TextureIndex < 0.0) {
+        FragColor = Color;
+        return;
+    }
+
+    float layer = floor(TextureIndex + 0.5);
+    vec4 color = texture(blockTextures, vec3(TexCoord, layer)) * Color;
+    if (layer == GRASS_TOP || layer == SHORT_GRASS || layer == OAK_LEAVES) {
+        color.rgb *= texture(colormapTexture, vec2(0.5, 0.5)).rgb;
+    }
+
+    if (color.a < 0.1) {
+        discard;
+    }
+    FragColor = color;
+}
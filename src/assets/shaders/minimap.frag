@@ -0,0 +1,38 @@
+#version 330 core
+in vec2 TexCoord;
+
+out vec4 FragColor;
+
+uniform sampler2D mapTexture;  // texture unit 4, transparent where nothing is loaded
+uniform vec2 facing;           // direction the player faces, in map coordinates
+
+const vec4 BACKGROUND = vec4(0.0, 0.0, 0.0, 0.5);
+const vec4 FRAME_COLOR = vec4(0.1, 0.1, 0.1, 0.9);
+const vec4 ARROW_COLOR = vec4(1.0, 1.0, 1.0, 1.0);
+// Widths as fractions of the map: the frame, and the arrow's length ahead of
+// and behind the player and its half-width at the back
+const float FRAME_WIDTH = 0.012;
+const float ARROW_FRONT = 0.05;
+const float ARROW_BACK = 0.03;
+const float ARROW_HALF_WIDTH = 0.03;
+
+void main() {
+    float edge = min(min(TexCoord.x, TexCoord.y), min(1.0 - TexCoord.x, 1.0 - TexCoord.y));
+    if (edge < FRAME_WIDTH) {
+        FragColor = FRAME_COLOR;
+        return;
+    }
+
+    // A triangle around the center, pointing the way the player faces
+    vec2 p = TexCoord - 0.5;
+    float along = dot(p, facing);
+    float across = abs(dot(p, vec2(-facing.y, facing.x)));
+    float taper = (ARROW_FRONT - along) / (ARROW_FRONT + ARROW_BACK);
+    if (along > -ARROW_BACK && along < ARROW_FRONT && across < ARROW_HALF_WIDTH * taper) {
+        FragColor = ARROW_COLOR;
+        return;
+    }
+
+    vec4 color = texture(mapTexture, TexCoord);
+    FragColor = color.a > 0.0 ? color : BACKGROUND;
+}
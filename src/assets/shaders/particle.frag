@@ -0,0 +1,21 @@
+#version 330 core
+in vec2 TexCoord;
+in float TextureIndex;
+in vec4 Color;
+
+out vec4 FragColor;
+
+uniform sampler2DArray blockTextures;  // texture unit 0, one layer per BlockTexture
+
+void main() {
+    vec4 color = Color;
+    // Untextured particles use their color only
+    if (TextureIndex >= 0.0) {
+        color *= texture(blockTextures, vec3(TexCoord, floor(TextureIndex + 0.5)));
+    }
+
+    if (color.a < 0.1) {
+        discard;
+    }
+    FragColor = color;
+}
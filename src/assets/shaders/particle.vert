@@ -0,0 +1,37 @@
+#version 330 core
+layout (location = 0) in vec2 aCorner;
+layout (location = 1) in vec3 aCenter;
+layout (location = 2) in float aSize;
+layout (location = 3) in float aTextureIndex;
+layout (location = 4) in vec3 aUv;  // offset and span of the texture region
+layout (location = 5) in vec4 aColor;
+
+// Per-frame data shared by every program, see frame_uniforms.rs
+layout (std140) uniform FrameUniforms {
+    mat4 view;
+    mat4 projection;
+    mat4 viewProjection;
+    vec3 cameraPosition;
+    float time;
+    vec3 sunDirection;
+    float daylight;
+    vec3 fogColor;
+    float fogStart;
+    float fogEnd;
+};
+
+uniform vec3 cameraRight;
+uniform vec3 cameraUp;
+
+out vec2 TexCoord;
+out float TextureIndex;
+out vec4 Color;
+
+void main() {
+    // Billboard the quad so it always faces the camera
+    vec3 position = aCenter + (cameraRight * aCorner.x + cameraUp * aCorner.y) * aSize;
+    gl_Position = viewProjection * vec4(position, 1.0);
+    TexCoord = aUv.xy + (vec2(aCorner.x, -aCorner.y) + 0.5) * aUv.z;
+    TextureIndex = aTextureIndex;
+    Color = aColor;
+}
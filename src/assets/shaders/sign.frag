@@ -0,0 +1,15 @@
+#version 330 core
+in vec2 TexCoord;
+
+out vec4 FragColor;
+
+uniform sampler2D fontAtlas;  // texture unit 3, glyph coverage in red
+uniform vec3 textColor;
+
+void main() {
+    float coverage = texture(fontAtlas, TexCoord).r;
+    if (coverage < 0.05) {
+        discard;
+    }
+    FragColor = vec4(textColor, coverage);
+}
@@ -0,0 +1,16 @@
+#version 330 core
+in vec2 TexCoord;
+in vec4 Color;
+in float Glow;
+
+out vec4 FragColor;
+
+void main() {
+    vec4 color = Color;
+    // Glows fade out from the middle; everything else is a solid square
+    if (Glow > 0.5) {
+        float distance = length(TexCoord * 2.0 - 1.0);
+        color.a *= 1.0 - smoothstep(0.0, 1.0, distance);
+    }
+    FragColor = color;
+}
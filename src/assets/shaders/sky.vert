@@ -0,0 +1,30 @@
+#version 330 core
+layout (location = 0) in vec3 aPos;
+layout (location = 1) in vec2 aTexCoord;
+layout (location = 2) in vec4 aColor;
+layout (location = 3) in float aGlow;
+
+// Per-frame data shared by every program, see frame_uniforms.rs
+layout (std140) uniform FrameUniforms {
+    mat4 view;
+    mat4 projection;
+    mat4 viewProjection;
+    vec3 cameraPosition;
+    float time;
+    vec3 sunDirection;
+    float daylight;
+    vec3 fogColor;
+    float fogStart;
+    float fogEnd;
+};
+
+out vec2 TexCoord;
+out vec4 Color;
+out float Glow;
+
+void main() {
+    gl_Position = viewProjection * vec4(aPos, 1.0);
+    TexCoord = aTexCoord;
+    Color = aColor;
+    Glow = aGlow;
+}
@@ -0,0 +1,38 @@
+#version 330 core
+in vec3 WorldPos;
+
+out vec4 FragColor;
+
+uniform float fadeDistance;  // the wall is invisible this far from the camera
+
+// Per-frame data shared by every program, see frame_uniforms.rs
+layout (std140) uniform FrameUniforms {
+    mat4 view;
+    mat4 projection;
+    mat4 viewProjection;
+    vec3 cameraPosition;
+    float time;
+    vec3 sunDirection;
+    float daylight;
+    vec3 fogColor;
+    float fogStart;
+    float fogEnd;
+};
+
+const vec3 BORDER_COLOR = vec3(0.3, 0.6, 1.0);
+// Stripes per block along the wall, and how fast they scroll up it
+const float STRIPE_DENSITY = 0.5;
+const float STRIPE_SPEED = 0.6;
+
+void main() {
+    // Diagonal stripes that scroll upwards, the same on every side
+    float along = WorldPos.x + WorldPos.z;
+    float stripe = fract((along + WorldPos.y) * STRIPE_DENSITY - time * STRIPE_SPEED);
+    if (stripe > 0.5) {
+        discard;
+    }
+
+    // Strongest right in front of the camera
+    float fade = 1.0 - smoothstep(0.0, fadeDistance, length(WorldPos - cameraPosition));
+    FragColor = vec4(BORDER_COLOR, 0.6 * fade);
+}
@@ -0,0 +1,15 @@
+#version 330 core
+in vec2 TexCoord;
+
+out vec4 FragColor;
+
+uniform sampler2D mapTexture;  // texture unit 4, a tile of the map
+
+void main() {
+    vec4 color = texture(mapTexture, TexCoord);
+    // Columns not seen yet show the background
+    if (color.a == 0.0) {
+        discard;
+    }
+    FragColor = color;
+}
@@ -0,0 +1,23 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Writes `data` to `path` like `fs::write`, but so that a crash at any point
+/// leaves either the old file or the new one, never half of each: the data
+/// goes to a temporary file beside it, which is flushed to the disk and then
+/// renamed over it. Creates the file's directory if needed.
+pub fn write(path: &Path, data: &[u8]) -> io::Result<()> {
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory)?;
+    }
+    // Left behind by a crash mid-write, but readers only look for their own
+    // extensions so it's never mistaken for a save
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    let temporary = PathBuf::from(temporary);
+    let mut file = File::create(&temporary)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    drop(file);
+    fs::rename(&temporary, path)
+}
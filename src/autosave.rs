@@ -0,0 +1,59 @@
+use crate::hud::Hud;
+use crate::language::text;
+use crate::world_io::{SaveJob, WorldIo};
+
+/// Seconds between autosaves while playing.
+pub const AUTOSAVE_INTERVAL: f32 = 60.0;
+// Seconds the indicator stays up after a save is written, so a quick one is
+// still noticed
+const INDICATOR_LINGER: f32 = 1.5;
+const TEXT_SIZE: f32 = 16.0;
+const MARGIN: f32 = 8.0;
+const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.8];
+
+/// Saves the world every `AUTOSAVE_INTERVAL` while playing. The game encodes
+/// what changed and the files are written by the `WorldIo` thread.
+pub struct Autosaver {
+    until_next: f32,
+    indicator: f32,
+}
+
+impl Autosaver {
+    pub fn new() -> Self {
+        Self { until_next: AUTOSAVE_INTERVAL, indicator: 0.0 }
+    }
+
+    /// Whether it's time for the next save. It waits for what was written
+    /// before to be done, so a slow disk doesn't pile saves up.
+    pub fn is_due(&self, io: &WorldIo) -> bool {
+        self.until_next <= 0.0 && io.pending_writes() == 0
+    }
+
+    /// Writes the files of a save in the background.
+    pub fn save(&mut self, io: &mut WorldIo, job: SaveJob) {
+        self.until_next = AUTOSAVE_INTERVAL;
+        io.write(job);
+    }
+
+    /// Counts down to the next save, and keeps the indicator up while one is
+    /// being written.
+    pub fn update(&mut self, delta_time: f32, io: &WorldIo) {
+        self.until_next -= delta_time;
+        if io.pending_writes() > 0 {
+            self.indicator = INDICATOR_LINGER;
+        } else {
+            self.indicator = (self.indicator - delta_time).max(0.0);
+        }
+    }
+
+    /// Queues the saving indicator in the bottom right corner while a save is
+    /// being written.
+    pub fn draw(&self, hud: &mut Hud, screen_width: f32, screen_height: f32) {
+        if self.indicator <= 0.0 {
+            return;
+        }
+        let label = text("hud.saving", &[]);
+        let x = screen_width - MARGIN - hud.measure_text(TEXT_SIZE, &label);
+        hud.draw_text(x, screen_height - MARGIN - TEXT_SIZE, TEXT_SIZE, TEXT_COLOR, &label);
+    }
+}
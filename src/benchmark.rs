@@ -0,0 +1,134 @@
+use crate::math::Vec3;
+use crate::screenshot::timestamp;
+use crate::streaming::StreamUpdate;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// How long a benchmark runs when no duration is given, in seconds.
+pub const DEFAULT_BENCHMARK_DURATION: f32 = 30.0;
+// Camera path: a circle around the origin, slowly rising and falling
+const PATH_RADIUS: f32 = 160.0;
+const PATH_SPEED: f32 = 20.0;
+const PATH_HEIGHT: f32 = 100.0;
+const PATH_HEIGHT_VARIATION: f32 = 15.0;
+
+/// Measurements for one frame.
+struct Sample {
+    time: f32,
+    frame_ms: f32,
+    generation_ms: f32,
+    meshing_ms: f32,
+    remeshed: usize,
+}
+
+/// Flies the camera along a fixed path and records how long frames, chunk
+/// generation and meshing take, so runs before and after a change compare.
+pub struct Benchmark {
+    duration: f32,
+    elapsed: f32,
+    samples: Vec<Sample>,
+    // Streaming done before the first frame
+    startup: (Duration, Duration),
+}
+
+impl Benchmark {
+    pub fn new(duration: f32) -> Self {
+        Self {
+            duration,
+            elapsed: 0.0,
+            samples: Vec::new(),
+            startup: (Duration::ZERO, Duration::ZERO),
+        }
+    }
+
+    /// Camera position, yaw and pitch (in degrees) at a time along the path.
+    pub fn camera_at(time: f32) -> (Vec3, f32, f32) {
+        let angle = time * PATH_SPEED / PATH_RADIUS;
+        let position = Vec3::new(
+            angle.cos() * PATH_RADIUS,
+            PATH_HEIGHT + (time * 0.3).sin() * PATH_HEIGHT_VARIATION,
+            angle.sin() * PATH_RADIUS,
+        );
+        // Face along the circle, looking a little down
+        let yaw = angle.to_degrees() + 90.0;
+        (position, yaw, -20.0)
+    }
+
+    /// Where the camera is now.
+    pub fn camera(&self) -> (Vec3, f32, f32) {
+        Self::camera_at(self.elapsed)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+
+    /// Records the streaming done before the first frame.
+    pub fn record_startup(&mut self, update: &StreamUpdate) {
+        self.startup = (update.generation_time, update.meshing_time);
+    }
+
+    /// Records a frame: how long it took to run and draw, and the streaming it did.
+    pub fn record_frame(&mut self, delta_time: f32, frame_time: Duration, update: &StreamUpdate) {
+        self.elapsed += delta_time;
+        self.samples.push(Sample {
+            time: self.elapsed,
+            frame_ms: frame_time.as_secs_f32() * 1000.0,
+            generation_ms: update.generation_time.as_secs_f32() * 1000.0,
+            meshing_ms: update.meshing_time.as_secs_f32() * 1000.0,
+            remeshed: update.remeshed.len(),
+        });
+    }
+
+    /// Writes every frame to a CSV file and a summary to a JSON file next to
+    /// it, both named after the current time. Returns the path of the summary.
+    pub fn write_report(&self, directory: &Path) -> Result<PathBuf, String> {
+        let name = format!("benchmark_{}", timestamp());
+
+        let mut csv = String::from("time,frame_ms,generation_ms,meshing_ms,remeshed\n");
+        for s in &self.samples {
+            let _ = writeln!(csv, "{:.4},{:.3},{:.3},{:.3},{}", s.time, s.frame_ms, s.generation_ms, s.meshing_ms, s.remeshed);
+        }
+
+        let mut frame_times = self.samples.iter().map(|s| s.frame_ms).collect::<Vec<_>>();
+        frame_times.sort_by(f32::total_cmp);
+        let percentile = |p: f32| match frame_times.len() {
+            0 => 0.0,
+            n => frame_times[((n - 1) as f32 * p).round() as usize],
+        };
+        let frames = frame_times.len().max(1) as f32;
+        let average = frame_times.iter().sum::<f32>() / frames;
+        let generation = self.samples.iter().map(|s| s.generation_ms).sum::<f32>();
+        let meshing = self.samples.iter().map(|s| s.meshing_ms).sum::<f32>();
+        let remeshed = self.samples.iter().map(|s| s.remeshed).sum::<usize>();
+
+        let json = format!(
+            "{{\n  \"duration_s\": {:.3},\n  \"frames\": {},\n  \"average_fps\": {:.2},\n  \
+             \"frame_ms\": {{ \"average\": {:.3}, \"min\": {:.3}, \"p50\": {:.3}, \"p95\": {:.3}, \"p99\": {:.3}, \"max\": {:.3} }},\n  \
+             \"startup_generation_ms\": {:.3},\n  \"startup_meshing_ms\": {:.3},\n  \
+             \"generation_ms_total\": {:.3},\n  \"meshing_ms_total\": {:.3},\n  \"chunks_remeshed\": {}\n}}\n",
+            self.elapsed,
+            frame_times.len(),
+            if average > 0.0 { 1000.0 / average } else { 0.0 },
+            average,
+            percentile(0.0),
+            percentile(0.5),
+            percentile(0.95),
+            percentile(0.99),
+            percentile(1.0),
+            self.startup.0.as_secs_f32() * 1000.0,
+            self.startup.1.as_secs_f32() * 1000.0,
+            generation,
+            meshing,
+            remeshed,
+        );
+
+        fs::create_dir_all(directory).map_err(|e| e.to_string())?;
+        fs::write(directory.join(format!("{}.csv", name)), csv).map_err(|e| e.to_string())?;
+        let summary = directory.join(format!("{}.json", name));
+        fs::write(&summary, json).map_err(|e| e.to_string())?;
+        Ok(summary)
+    }
+}
@@ -0,0 +1,752 @@
+/// Every kind of block that can exist in the world.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlockType {
+    Air,
+    Grass,
+    Dirt,
+    Stone,
+    Water,
+    Snow,
+    Ice,
+    SmoothStoneSlab,
+    OakStairs,
+    ShortGrass,
+    Poppy,
+    Dandelion,
+    OakSapling,
+    OakLog,
+    OakLeaves,
+    Glowstone,
+    Placeholder,
+    Tnt,
+    Sign,
+    PowerSource,
+    Wire,
+    Lamp,
+    LitLamp,
+    Glass,
+}
+
+/// The geometry a block is meshed with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BlockShape {
+    /// Nothing is rendered.
+    Empty,
+    /// A full unit cube.
+    Cube,
+    /// A full unit cube with see-through gaps, like leaves or glass: solid to entities,
+    /// but the faces of its neighbours stay visible through it.
+    CutoutCube,
+    /// A box covering the bottom part of the cell, `height` is a fraction of a full block.
+    Slab { height: f32 },
+    /// A half slab with a second half-height step on the back (-z) half.
+    Stairs,
+    /// Two diagonal quads crossing in an X, used for plants.
+    Cross,
+    /// Only the top surface is rendered, slightly below the top of the cell.
+    Fluid,
+}
+
+impl BlockShape {
+    /// Whether the shape fills the whole of the given face of its cell.
+    pub fn covers(self, face: Face) -> bool {
+        match self {
+            BlockShape::Cube | BlockShape::CutoutCube => true,
+            BlockShape::Slab { height } => face == Face::Bottom || height >= 1.0,
+            BlockShape::Stairs => matches!(face, Face::Bottom | Face::Back),
+            BlockShape::Empty | BlockShape::Cross | BlockShape::Fluid => false,
+        }
+    }
+
+    // Whether the shape fills the given face of its cell just like the
+    // opposite one, so two of them side by side fill each other's faces
+    fn matches_opposite(self, face: Face) -> bool {
+        match self {
+            BlockShape::Cube | BlockShape::CutoutCube => true,
+            BlockShape::Slab { .. } => face.is_side(),
+            BlockShape::Stairs => matches!(face, Face::Right | Face::Left),
+            BlockShape::Empty | BlockShape::Cross | BlockShape::Fluid => false,
+        }
+    }
+}
+
+/// One of the six faces of a block cell. The front faces +z, the right +x.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Face {
+    Front,
+    Back,
+    Top,
+    Bottom,
+    Right,
+    Left,
+}
+
+impl Face {
+    pub const ALL: [Face; 6] = [Face::Front, Face::Back, Face::Top, Face::Bottom, Face::Right, Face::Left];
+
+    /// Step from a cell to its neighbour on this side.
+    pub fn offset(self) -> (i32, i32, i32) {
+        match self {
+            Face::Front => (0, 0, 1),
+            Face::Back => (0, 0, -1),
+            Face::Top => (0, 1, 0),
+            Face::Bottom => (0, -1, 0),
+            Face::Right => (1, 0, 0),
+            Face::Left => (-1, 0, 0),
+        }
+    }
+
+    /// The face of the neighbour on this side that touches this one.
+    pub fn opposite(self) -> Face {
+        match self {
+            Face::Front => Face::Back,
+            Face::Back => Face::Front,
+            Face::Top => Face::Bottom,
+            Face::Bottom => Face::Top,
+            Face::Right => Face::Left,
+            Face::Left => Face::Right,
+        }
+    }
+
+    fn is_side(self) -> bool {
+        !matches!(self, Face::Top | Face::Bottom)
+    }
+}
+
+/// The layers of the block texture array, in load order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockTexture {
+    GrassTop,
+    GrassSide,
+    Dirt,
+    Stone,
+    Water,
+    Snow,
+    Ice,
+    GrassSnow,
+    GrassSideOverlay,
+    OakPlanks,
+    SmoothStone,
+    SmoothStoneSlabSide,
+    ShortGrass,
+    Poppy,
+    Dandelion,
+    OakSapling,
+    OakLog,
+    OakLogTop,
+    OakLeaves,
+    Glowstone,
+    Placeholder,
+    TntSide,
+    TntTop,
+    TntBottom,
+    RedstoneBlock,
+    RedstoneLamp,
+    RedstoneLampOn,
+    Glass,
+}
+
+impl BlockTexture {
+    /// All texture layers, indexed by their layer number.
+    pub const ALL: [BlockTexture; 28] = [
+        BlockTexture::GrassTop,
+        BlockTexture::GrassSide,
+        BlockTexture::Dirt,
+        BlockTexture::Stone,
+        BlockTexture::Water,
+        BlockTexture::Snow,
+        BlockTexture::Ice,
+        BlockTexture::GrassSnow,
+        BlockTexture::GrassSideOverlay,
+        BlockTexture::OakPlanks,
+        BlockTexture::SmoothStone,
+        BlockTexture::SmoothStoneSlabSide,
+        BlockTexture::ShortGrass,
+        BlockTexture::Poppy,
+        BlockTexture::Dandelion,
+        BlockTexture::OakSapling,
+        BlockTexture::OakLog,
+        BlockTexture::OakLogTop,
+        BlockTexture::OakLeaves,
+        BlockTexture::Glowstone,
+        BlockTexture::Placeholder,
+        BlockTexture::TntSide,
+        BlockTexture::TntTop,
+        BlockTexture::TntBottom,
+        BlockTexture::RedstoneBlock,
+        BlockTexture::RedstoneLamp,
+        BlockTexture::RedstoneLampOn,
+        BlockTexture::Glass,
+    ];
+
+    /// Path of the image backing this layer.
+    pub fn path(self) -> &'static str {
+        match self {
+            BlockTexture::GrassTop => "src/assets/textures/block/grass_block_top.png",
+            BlockTexture::GrassSide => "src/assets/textures/block/grass_block_side.png",
+            BlockTexture::Dirt => "src/assets/textures/block/dirt.png",
+            BlockTexture::Stone => "src/assets/textures/block/stone.png",
+            BlockTexture::Water => "src/assets/textures/block/water_still.png",
+            BlockTexture::Snow => "src/assets/textures/block/snow.png",
+            BlockTexture::Ice => "src/assets/textures/block/ice.png",
+            BlockTexture::GrassSnow => "src/assets/textures/block/grass_block_snow.png",
+            BlockTexture::GrassSideOverlay => "src/assets/textures/block/grass_block_side_overlay.png",
+            BlockTexture::OakPlanks => "src/assets/textures/block/oak_planks.png",
+            BlockTexture::SmoothStone => "src/assets/textures/block/smooth_stone.png",
+            BlockTexture::SmoothStoneSlabSide => "src/assets/textures/block/smooth_stone_slab_side.png",
+            BlockTexture::ShortGrass => "src/assets/textures/block/short_grass.png",
+            BlockTexture::Poppy => "src/assets/textures/block/poppy.png",
+            BlockTexture::Dandelion => "src/assets/textures/block/dandelion.png",
+            BlockTexture::OakSapling => "src/assets/textures/block/oak_sapling.png",
+            BlockTexture::OakLog => "src/assets/textures/block/oak_log.png",
+            BlockTexture::OakLogTop => "src/assets/textures/block/oak_log_top.png",
+            BlockTexture::OakLeaves => "src/assets/textures/block/oak_leaves.png",
+            BlockTexture::Glowstone => "src/assets/textures/block/glowstone.png",
+            BlockTexture::Placeholder => "src/assets/textures/block/debug.png",
+            BlockTexture::TntSide => "src/assets/textures/block/tnt_side.png",
+            BlockTexture::TntTop => "src/assets/textures/block/tnt_top.png",
+            BlockTexture::TntBottom => "src/assets/textures/block/tnt_bottom.png",
+            BlockTexture::RedstoneBlock => "src/assets/textures/block/redstone_block.png",
+            BlockTexture::RedstoneLamp => "src/assets/textures/block/redstone_lamp.png",
+            BlockTexture::RedstoneLampOn => "src/assets/textures/block/redstone_lamp_on.png",
+            BlockTexture::Glass => "src/assets/textures/block/glass.png",
+        }
+    }
+
+    /// The value stored in the vertex texture index attribute.
+    pub fn layer(self) -> f32 {
+        self as u32 as f32
+    }
+}
+
+/// The colormap grass and leaves are tinted with.
+pub const BIOME_COLORMAP_PATH: &str = "src/assets/textures/colormap/grass.png";
+
+/// Textures used for the faces of a block.
+#[derive(Debug, Clone, Copy)]
+pub struct FaceTextures {
+    pub top: BlockTexture,
+    pub bottom: BlockTexture,
+    pub side: BlockTexture,
+}
+
+impl FaceTextures {
+    const fn all(texture: BlockTexture) -> Self {
+        Self { top: texture, bottom: texture, side: texture }
+    }
+}
+
+/// The material a block sounds like when broken, placed or walked on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlockSound {
+    Grass,
+    Gravel,
+    Stone,
+    Wood,
+    Snow,
+    Glass,
+    Water,
+}
+
+/// Colored light given off by a glowing block.
+#[derive(Debug, Clone, Copy)]
+pub struct BlockLight {
+    pub color: [f32; 3],
+    /// Distance in blocks at which the light has faded out completely.
+    pub radius: f32,
+}
+
+/// Static description of a block type.
+#[derive(Debug)]
+pub struct BlockInfo {
+    pub name: &'static str,
+    pub shape: BlockShape,
+    pub textures: FaceTextures,
+    pub sound: BlockSound,
+    pub light: Option<BlockLight>,
+}
+
+// Block registry, indexed by `BlockType as usize`
+static BLOCKS: [BlockInfo; 24] = [
+    BlockInfo {
+        name: "air",
+        shape: BlockShape::Empty,
+        textures: FaceTextures::all(BlockTexture::Stone),
+        sound: BlockSound::Stone,
+        light: None,
+    },
+    BlockInfo {
+        name: "grass",
+        shape: BlockShape::Cube,
+        textures: FaceTextures {
+            top: BlockTexture::GrassTop,
+            bottom: BlockTexture::Dirt,
+            side: BlockTexture::GrassSide,
+        },
+        sound: BlockSound::Grass,
+        light: None,
+    },
+    BlockInfo {
+        name: "dirt",
+        shape: BlockShape::Cube,
+        textures: FaceTextures::all(BlockTexture::Dirt),
+        sound: BlockSound::Gravel,
+        light: None,
+    },
+    BlockInfo {
+        name: "stone",
+        shape: BlockShape::Cube,
+        textures: FaceTextures::all(BlockTexture::Stone),
+        sound: BlockSound::Stone,
+        light: None,
+    },
+    BlockInfo {
+        name: "water",
+        shape: BlockShape::Fluid,
+        textures: FaceTextures::all(BlockTexture::Water),
+        sound: BlockSound::Water,
+        light: None,
+    },
+    BlockInfo {
+        name: "snow",
+        shape: BlockShape::Slab { height: 0.125 },
+        textures: FaceTextures::all(BlockTexture::Snow),
+        sound: BlockSound::Snow,
+        light: None,
+    },
+    BlockInfo {
+        name: "ice",
+        shape: BlockShape::Cube,
+        textures: FaceTextures::all(BlockTexture::Ice),
+        sound: BlockSound::Glass,
+        light: None,
+    },
+    BlockInfo {
+        name: "smooth_stone_slab",
+        shape: BlockShape::Slab { height: 0.5 },
+        textures: FaceTextures {
+            top: BlockTexture::SmoothStone,
+            bottom: BlockTexture::SmoothStone,
+            side: BlockTexture::SmoothStoneSlabSide,
+        },
+        sound: BlockSound::Stone,
+        light: None,
+    },
+    BlockInfo {
+        name: "oak_stairs",
+        shape: BlockShape::Stairs,
+        textures: FaceTextures::all(BlockTexture::OakPlanks),
+        sound: BlockSound::Wood,
+        light: None,
+    },
+    BlockInfo {
+        name: "short_grass",
+        shape: BlockShape::Cross,
+        textures: FaceTextures::all(BlockTexture::ShortGrass),
+        sound: BlockSound::Grass,
+        light: None,
+    },
+    BlockInfo {
+        name: "poppy",
+        shape: BlockShape::Cross,
+        textures: FaceTextures::all(BlockTexture::Poppy),
+        sound: BlockSound::Grass,
+        light: None,
+    },
+    BlockInfo {
+        name: "dandelion",
+        shape: BlockShape::Cross,
+        textures: FaceTextures::all(BlockTexture::Dandelion),
+        sound: BlockSound::Grass,
+        light: None,
+    },
+    BlockInfo {
+        name: "oak_sapling",
+        shape: BlockShape::Cross,
+        textures: FaceTextures::all(BlockTexture::OakSapling),
+        sound: BlockSound::Grass,
+        light: None,
+    },
+    BlockInfo {
+        name: "oak_log",
+        shape: BlockShape::Cube,
+        textures: FaceTextures {
+            top: BlockTexture::OakLogTop,
+            bottom: BlockTexture::OakLogTop,
+            side: BlockTexture::OakLog,
+        },
+        sound: BlockSound::Wood,
+        light: None,
+    },
+    BlockInfo {
+        name: "oak_leaves",
+        shape: BlockShape::CutoutCube,
+        textures: FaceTextures::all(BlockTexture::OakLeaves),
+        sound: BlockSound::Grass,
+        light: None,
+    },
+    BlockInfo {
+        name: "glowstone",
+        shape: BlockShape::Cube,
+        textures: FaceTextures::all(BlockTexture::Glowstone),
+        sound: BlockSound::Glass,
+        light: Some(BlockLight { color: [1.0, 0.75, 0.4], radius: 10.0 }),
+    },
+    // Stands in for blocks of imported worlds and schematics that have no equivalent here
+    BlockInfo {
+        name: "placeholder",
+        shape: BlockShape::Cube,
+        textures: FaceTextures::all(BlockTexture::Placeholder),
+        sound: BlockSound::Stone,
+        light: None,
+    },
+    // Lit by using it, or by an explosion, and blows up after a fuse
+    BlockInfo {
+        name: "tnt",
+        shape: BlockShape::Cube,
+        textures: FaceTextures {
+            top: BlockTexture::TntTop,
+            bottom: BlockTexture::TntBottom,
+            side: BlockTexture::TntSide,
+        },
+        sound: BlockSound::Grass,
+        light: None,
+    },
+    // Holds a few lines of text written on one side, kept in a block entity
+    BlockInfo {
+        name: "sign",
+        shape: BlockShape::Cube,
+        textures: FaceTextures::all(BlockTexture::OakPlanks),
+        sound: BlockSound::Wood,
+        light: None,
+    },
+    // Circuits: a source of signal, wire carrying it a few blocks, and a lamp
+    // that lights up while powered. See circuits.rs.
+    BlockInfo {
+        name: "power_source",
+        shape: BlockShape::Cube,
+        textures: FaceTextures::all(BlockTexture::RedstoneBlock),
+        sound: BlockSound::Stone,
+        light: None,
+    },
+    BlockInfo {
+        name: "wire",
+        shape: BlockShape::Slab { height: 0.0625 },
+        textures: FaceTextures::all(BlockTexture::RedstoneBlock),
+        sound: BlockSound::Stone,
+        light: None,
+    },
+    BlockInfo {
+        name: "lamp",
+        shape: BlockShape::Cube,
+        textures: FaceTextures::all(BlockTexture::RedstoneLamp),
+        sound: BlockSound::Glass,
+        light: None,
+    },
+    BlockInfo {
+        name: "lit_lamp",
+        shape: BlockShape::Cube,
+        textures: FaceTextures::all(BlockTexture::RedstoneLampOn),
+        sound: BlockSound::Glass,
+        light: Some(BlockLight { color: [1.0, 0.7, 0.4], radius: 8.0 }),
+    },
+    BlockInfo {
+        name: "glass",
+        shape: BlockShape::CutoutCube,
+        textures: FaceTextures::all(BlockTexture::Glass),
+        sound: BlockSound::Glass,
+        light: None,
+    },
+];
+
+/// Names of the blocks by the numeric ids saves stored them by before they
+/// went by name, which were their places in the registry of the time. Frozen,
+/// so those saves load whatever the registry becomes.
+pub const SAVED_IDS: [&str; 24] = [
+    "air",
+    "grass",
+    "dirt",
+    "stone",
+    "water",
+    "snow",
+    "ice",
+    "smooth_stone_slab",
+    "oak_stairs",
+    "short_grass",
+    "poppy",
+    "dandelion",
+    "oak_sapling",
+    "oak_log",
+    "oak_leaves",
+    "glowstone",
+    "placeholder",
+    "tnt",
+    "sign",
+    "power_source",
+    "wire",
+    "lamp",
+    "lit_lamp",
+    "glass",
+];
+
+/// Blocks renamed or removed since worlds were saved with them, by their old
+/// name, with the name of the block they load as now.
+pub const RENAMED_BLOCKS: &[(&str, &str)] = &[];
+
+impl BlockType {
+    /// All block types, indexed by their numeric id.
+    pub const ALL: [BlockType; 24] = [
+        BlockType::Air,
+        BlockType::Grass,
+        BlockType::Dirt,
+        BlockType::Stone,
+        BlockType::Water,
+        BlockType::Snow,
+        BlockType::Ice,
+        BlockType::SmoothStoneSlab,
+        BlockType::OakStairs,
+        BlockType::ShortGrass,
+        BlockType::Poppy,
+        BlockType::Dandelion,
+        BlockType::OakSapling,
+        BlockType::OakLog,
+        BlockType::OakLeaves,
+        BlockType::Glowstone,
+        BlockType::Placeholder,
+        BlockType::Tnt,
+        BlockType::Sign,
+        BlockType::PowerSource,
+        BlockType::Wire,
+        BlockType::Lamp,
+        BlockType::LitLamp,
+        BlockType::Glass,
+    ];
+
+    /// Looks up a block type by the numeric id the running game knows it by.
+    pub fn from_id(id: u8) -> Option<BlockType> {
+        Self::ALL.get(id as usize).copied()
+    }
+
+    /// Looks up a block type by its registry name.
+    pub fn from_name(name: &str) -> Option<BlockType> {
+        Self::ALL.iter().copied().find(|block| block.info().name == name)
+    }
+
+    /// Looks up a block type by a name it was saved with, following `renames`
+    /// from old names to new ones. Saves pass `RENAMED_BLOCKS`.
+    pub fn from_saved_name(name: &str, renames: &[(&str, &str)]) -> Option<BlockType> {
+        let name = renames.iter().find(|(old, _)| *old == name).map_or(name, |&(_, new)| new);
+        Self::from_name(name)
+    }
+
+    /// Looks up a block type by the numeric id older saves stored it by.
+    pub fn from_saved_id(id: u8) -> Option<BlockType> {
+        Self::from_saved_name(SAVED_IDS.get(id as usize)?, RENAMED_BLOCKS)
+    }
+
+    /// Numeric id the running game knows this block type by, which changes
+    /// as blocks are added. Saves store names instead.
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    /// Looks up this block's entry in the block registry.
+    pub fn info(self) -> &'static BlockInfo {
+        &BLOCKS[self as usize]
+    }
+
+    /// Whether this block is a full cube that hides the faces of its neighbours.
+    pub fn occludes(self) -> bool {
+        self.info().shape == BlockShape::Cube
+    }
+
+    /// Whether this block is a liquid entities can swim in.
+    pub fn is_fluid(self) -> bool {
+        self.info().shape == BlockShape::Fluid
+    }
+
+    /// Whether nothing behind this block shows through the part of its cell
+    /// it fills.
+    pub fn is_opaque(self) -> bool {
+        matches!(self.info().shape, BlockShape::Cube | BlockShape::Slab { .. } | BlockShape::Stairs)
+    }
+
+    /// Whether this block is leaves, which are only seen through when drawn
+    /// fancy.
+    pub fn is_leaves(self) -> bool {
+        self == BlockType::OakLeaves
+    }
+
+    /// Whether both blocks are the same fluid, which shows no surface between
+    /// them.
+    pub fn is_same_fluid(self, other: BlockType) -> bool {
+        self.is_fluid() && self == other
+    }
+
+    /// Whether the neighbour on the given side hides this block's face there.
+    /// Leaves only hide what's behind them when they aren't drawn fancy.
+    pub fn cull_against(self, face: Face, neighbor: BlockType, fancy_leaves: bool) -> bool {
+        if self.is_same_fluid(neighbor) {
+            return true;
+        }
+        let shape = neighbor.info().shape;
+        let hides = neighbor.is_opaque() || (neighbor.is_leaves() && !fancy_leaves);
+        if hides && shape.covers(face.opposite()) {
+            return true;
+        }
+        // Blocks of the same kind side by side hide the faces they share, like
+        // panes of glass, except for fancy leaves
+        neighbor == self && (hides || !self.is_leaves()) && shape.matches_opposite(face)
+    }
+
+    /// How much explosion power it takes to blow this block away, or `None`
+    /// for air and fluids, which explosions leave alone.
+    pub fn blast_resistance(self) -> Option<f32> {
+        match self {
+            BlockType::Air | BlockType::Water => None,
+            BlockType::ShortGrass | BlockType::Poppy | BlockType::Dandelion | BlockType::OakSapling | BlockType::Tnt
+                | BlockType::Wire => Some(0.0),
+            BlockType::Snow | BlockType::OakLeaves => Some(0.2),
+            BlockType::Glass => Some(0.3),
+            BlockType::Ice | BlockType::Glowstone | BlockType::Lamp | BlockType::LitLamp => Some(0.5),
+            BlockType::Grass | BlockType::Dirt => Some(0.6),
+            BlockType::OakLog | BlockType::OakStairs | BlockType::Sign => Some(2.0),
+            BlockType::Stone | BlockType::SmoothStoneSlab | BlockType::Placeholder | BlockType::PowerSource => Some(3.0),
+        }
+    }
+
+    /// Seconds of digging it takes to break this block in survival, or `None`
+    /// for air and fluids, which can't be dug.
+    pub fn break_time(self) -> Option<f32> {
+        match self {
+            BlockType::Air | BlockType::Water => None,
+            BlockType::ShortGrass | BlockType::Poppy | BlockType::Dandelion | BlockType::OakSapling | BlockType::Tnt
+                | BlockType::Wire => Some(0.0),
+            BlockType::Snow => Some(0.15),
+            BlockType::OakLeaves => Some(0.3),
+            BlockType::Glowstone | BlockType::Lamp | BlockType::LitLamp | BlockType::Glass => Some(0.45),
+            BlockType::Ice | BlockType::Dirt => Some(0.75),
+            BlockType::Grass => Some(0.9),
+            BlockType::Sign => Some(1.5),
+            BlockType::Stone | BlockType::SmoothStoneSlab | BlockType::Placeholder => Some(2.25),
+            BlockType::OakLog | BlockType::OakStairs => Some(3.0),
+            BlockType::PowerSource => Some(7.5),
+        }
+    }
+
+    /// Color the block shows as on maps seen from above, or `None` for air.
+    pub fn map_color(self) -> Option<[u8; 3]> {
+        match self {
+            BlockType::Air => None,
+            BlockType::Grass | BlockType::ShortGrass => Some([95, 159, 53]),
+            BlockType::Dirt => Some([134, 96, 67]),
+            BlockType::Stone | BlockType::Placeholder => Some([125, 125, 125]),
+            BlockType::SmoothStoneSlab => Some([160, 160, 160]),
+            BlockType::Water => Some([52, 95, 218]),
+            BlockType::Snow => Some([249, 254, 254]),
+            BlockType::Ice => Some([145, 183, 253]),
+            BlockType::OakStairs | BlockType::Sign => Some([162, 130, 78]),
+            BlockType::OakLog => Some([102, 81, 51]),
+            BlockType::OakLeaves | BlockType::OakSapling => Some([60, 120, 30]),
+            BlockType::Poppy => Some([200, 30, 30]),
+            BlockType::Dandelion => Some([240, 220, 40]),
+            BlockType::Glowstone | BlockType::LitLamp => Some([250, 210, 120]),
+            BlockType::Glass => Some([200, 225, 230]),
+            BlockType::Lamp => Some([120, 70, 40]),
+            BlockType::Tnt => Some([200, 60, 40]),
+            BlockType::PowerSource | BlockType::Wire => Some([170, 20, 10]),
+        }
+    }
+
+    /// Height of the part of the cell entities collide with, as a fraction of a
+    /// full block, or `None` for blocks that can be walked through.
+    pub fn collision_height(self) -> Option<f32> {
+        match self.info().shape {
+            BlockShape::Cube | BlockShape::CutoutCube | BlockShape::Stairs => Some(1.0),
+            BlockShape::Slab { height } => Some(height),
+            BlockShape::Empty | BlockShape::Cross | BlockShape::Fluid => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slabs_fill_only_their_bottom_face() {
+        let slab = BlockShape::Slab { height: 0.5 };
+        assert!(slab.covers(Face::Bottom));
+        assert!(!slab.covers(Face::Top));
+        assert!(!slab.covers(Face::Left));
+        assert!(BlockShape::Slab { height: 1.0 }.covers(Face::Top));
+    }
+
+    #[test]
+    fn a_slab_under_a_full_block_hides_only_its_top() {
+        assert!(BlockType::SmoothStoneSlab.cull_against(Face::Top, BlockType::Stone, true));
+        // The slab doesn't reach the block above, so it leaves its bottom face showing
+        assert!(!BlockType::Stone.cull_against(Face::Bottom, BlockType::SmoothStoneSlab, true));
+        // But a block on top of a slab is hidden by the slab's full bottom
+        assert!(BlockType::Stone.cull_against(Face::Top, BlockType::SmoothStoneSlab, true));
+    }
+
+    #[test]
+    fn slabs_side_by_side_hide_their_sides() {
+        let slab = BlockType::SmoothStoneSlab;
+        assert!(slab.cull_against(Face::Left, slab, true));
+        assert!(slab.cull_against(Face::Front, slab, true));
+        assert!(!slab.cull_against(Face::Bottom, slab, true));
+    }
+
+    #[test]
+    fn stairs_fill_their_back_and_bottom_faces() {
+        assert!(BlockShape::Stairs.covers(Face::Back));
+        assert!(BlockShape::Stairs.covers(Face::Bottom));
+        assert!(!BlockShape::Stairs.covers(Face::Front));
+        assert!(!BlockShape::Stairs.covers(Face::Top));
+
+        // Against a solid block, the stairs' faces on those sides are hidden
+        assert!(BlockType::OakStairs.cull_against(Face::Back, BlockType::Stone, true));
+        assert!(BlockType::OakStairs.cull_against(Face::Bottom, BlockType::Stone, true));
+        // And the stairs hide the faces of the blocks they touch there
+        assert!(BlockType::Stone.cull_against(Face::Front, BlockType::OakStairs, true));
+        assert!(BlockType::Stone.cull_against(Face::Top, BlockType::OakStairs, true));
+        assert!(!BlockType::Stone.cull_against(Face::Back, BlockType::OakStairs, true));
+    }
+
+    #[test]
+    fn leaves_hide_what_is_behind_them_only_when_fast() {
+        let leaves = BlockType::OakLeaves;
+        assert!(!BlockType::Stone.cull_against(Face::Left, leaves, true));
+        assert!(BlockType::Stone.cull_against(Face::Left, leaves, false));
+        assert!(!leaves.cull_against(Face::Left, leaves, true));
+        assert!(leaves.cull_against(Face::Left, leaves, false));
+        // Solid blocks hide leaves either way
+        assert!(leaves.cull_against(Face::Left, BlockType::Stone, true));
+    }
+
+    #[test]
+    fn water_hides_only_faces_of_the_same_fluid() {
+        assert!(BlockType::Water.cull_against(Face::Top, BlockType::Water, true));
+        assert!(!BlockType::Water.cull_against(Face::Top, BlockType::Glass, true));
+        assert!(!BlockType::Water.cull_against(Face::Top, BlockType::Air, true));
+        assert!(!BlockType::Glass.cull_against(Face::Bottom, BlockType::Water, true));
+        assert!(!BlockType::Stone.cull_against(Face::Top, BlockType::Water, true));
+    }
+
+    #[test]
+    fn glass_hides_glass_but_not_what_is_behind_it() {
+        let glass = BlockType::Glass;
+        assert!(glass.cull_against(Face::Right, glass, true));
+        assert!(glass.cull_against(Face::Right, glass, false));
+        assert!(!BlockType::Stone.cull_against(Face::Right, glass, false));
+        assert!(glass.cull_against(Face::Right, BlockType::Stone, true));
+    }
+
+    #[test]
+    fn plants_never_hide_faces() {
+        assert!(!BlockType::Stone.cull_against(Face::Top, BlockType::Poppy, true));
+        assert!(!BlockType::Poppy.cull_against(Face::Top, BlockType::Poppy, true));
+    }
+}
@@ -0,0 +1,150 @@
+use crate::atomic_file;
+use crate::block::BlockType;
+use crate::circuits::{Wire, WIRE_KIND};
+use crate::nbt::{self, Tag};
+use crate::sign::{Sign, SIGN_KIND};
+use crate::ticks::TickContext;
+use std::any::Any;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Extra state of a block beyond its type, such as a container's contents or
+/// a sign's text. Each kind of block entity is a type implementing this.
+pub trait BlockEntity: Any + Send + Sync {
+    /// Name the block entity is saved under, which picks its loader.
+    fn kind(&self) -> &'static str;
+
+    /// Its state, as saved with the world.
+    fn save(&self) -> Tag;
+
+    /// Runs one world tick. Does nothing unless the block entity works over time.
+    fn tick(&mut self, _context: &mut TickContext) {}
+
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+/// Reads a block entity of one kind back from what `BlockEntity::save` wrote.
+pub type Loader = fn(&Tag) -> Option<Box<dyn BlockEntity>>;
+/// A block entity taken out of the world, with its position and block.
+pub type Placed = ((i32, i32, i32), BlockType, Box<dyn BlockEntity>);
+
+// A block entity and the block it belongs to, which it goes away with
+struct Entry {
+    block: BlockType,
+    entity: Box<dyn BlockEntity>,
+}
+
+/// The block entities of the world by block position. A block entity is
+/// removed as soon as its block is replaced by another type, however that
+/// happens.
+#[derive(Default)]
+pub struct BlockEntities {
+    entries: HashMap<(i32, i32, i32), Entry>,
+    loaders: HashMap<&'static str, Loader>,
+}
+
+impl BlockEntities {
+    /// No block entities, with loaders for every built-in kind registered.
+    pub fn with_builtins() -> Self {
+        let mut block_entities = Self::default();
+        block_entities.register(SIGN_KIND, Sign::load);
+        block_entities.register(WIRE_KIND, Wire::load);
+        block_entities
+    }
+
+    /// Registers how to load a kind of block entity, replacing any previous loader.
+    pub fn register(&mut self, kind: &'static str, loader: Loader) {
+        self.loaders.insert(kind, loader);
+    }
+
+    /// Gives the block at `position`, which is `block`, a block entity,
+    /// replacing any it had.
+    pub fn insert(&mut self, position: (i32, i32, i32), block: BlockType, entity: Box<dyn BlockEntity>) {
+        self.entries.insert(position, Entry { block, entity });
+    }
+
+    /// The block entity at `position`, if it is a `T`.
+    pub fn get<T: BlockEntity>(&self, position: (i32, i32, i32)) -> Option<&T> {
+        self.entries.get(&position)?.entity.as_any().downcast_ref()
+    }
+
+    pub fn get_mut<T: BlockEntity>(&mut self, position: (i32, i32, i32)) -> Option<&mut T> {
+        self.entries.get_mut(&position)?.entity.as_any_mut().downcast_mut()
+    }
+
+    /// Every block entity that is a `T`, with its position.
+    pub fn iter<T: BlockEntity>(&self) -> impl Iterator<Item = ((i32, i32, i32), &T)> {
+        self.entries.iter().filter_map(|(&position, entry)| Some((position, entry.entity.as_any().downcast_ref()?)))
+    }
+
+    /// Drops the block entity at `position` if the block there became `block`
+    /// and it belongs to another. Called for every block set in the world.
+    pub fn block_changed(&mut self, position: (i32, i32, i32), block: BlockType) {
+        if self.entries.get(&position).is_some_and(|entry| entry.block != block) {
+            self.entries.remove(&position);
+        }
+    }
+
+    /// Takes the block entities out for ticking, so they can change the world
+    /// they're stored in.
+    pub(crate) fn take(&mut self) -> Vec<Placed> {
+        self.entries.drain().map(|(position, entry)| (position, entry.block, entry.entity)).collect()
+    }
+
+    /// Puts back a block entity `take` took, unless the block was given a new
+    /// one meanwhile.
+    pub(crate) fn restore(&mut self, position: (i32, i32, i32), block: BlockType, entity: Box<dyn BlockEntity>) {
+        self.entries.entry(position).or_insert(Entry { block, entity });
+    }
+
+    /// Writes every block entity to a gzipped NBT file, creating its directory if needed.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        atomic_file::write(path, &self.encode()).map_err(|e| e.to_string())
+    }
+
+    /// Every block entity in the format `save` writes.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut positions: Vec<_> = self.entries.keys().copied().collect();
+        positions.sort_unstable();
+        let list = positions.into_iter()
+            .map(|position| {
+                let entry = &self.entries[&position];
+                Tag::compound([
+                    ("Pos", Tag::IntArray(vec![position.0, position.1, position.2])),
+                    ("Block", Tag::String(entry.block.info().name.to_string())),
+                    ("Id", Tag::String(entry.entity.kind().to_string())),
+                    ("Data", entry.entity.save()),
+                ])
+            })
+            .collect();
+        nbt::write("", &Tag::compound([("BlockEntities", Tag::List(list))]))
+    }
+
+    /// Reads block entities written by `save`, using the registered loaders.
+    /// Those of unknown kinds, or that can't be read, are left out. Returns
+    /// how many were loaded.
+    pub fn load(&mut self, path: &Path) -> Result<usize, String> {
+        let data = fs::read(path).map_err(|e| e.to_string())?;
+        let (_, root) = nbt::read(&data)?;
+        let list = root.get("BlockEntities").and_then(Tag::as_list).ok_or("Missing block entity list")?;
+
+        let mut loaded = 0;
+        for tag in list {
+            let kind = tag.get("Id").and_then(Tag::as_str).unwrap_or("?");
+            let position = tag.get("Pos").and_then(Tag::as_ints).filter(|pos| pos.len() == 3);
+            let block = tag.get("Block").and_then(Tag::as_str).and_then(BlockType::from_name);
+            let loader = self.loaders.get(kind);
+            let entity = loader.zip(tag.get("Data")).and_then(|(loader, data)| loader(data));
+            match (position, block, entity) {
+                (Some(pos), Some(block), Some(entity)) => {
+                    self.insert((pos[0], pos[1], pos[2]), block, entity);
+                    loaded += 1;
+                },
+                _ => log::warn!(target: "io", "Skipping unreadable block entity of kind {} in {}", kind, path.display()),
+            }
+        }
+        Ok(loaded)
+    }
+}
@@ -0,0 +1,125 @@
+use crate::math::Vec3;
+
+// Bobbing: how far the view sways per step, up and down and side to side,
+// and how many steps a block walked makes
+const BOB_HEIGHT: f32 = 0.05;
+const BOB_WIDTH: f32 = 0.03;
+const STEPS_PER_BLOCK: f32 = 0.6;
+// Walking speed the bobbing reaches its full size at, in blocks per second
+const FULL_BOB_SPEED: f32 = 4.3;
+// How quickly the bobbing grows and fades when starting and stopping, per second
+const BOB_FADE: f32 = 8.0;
+
+// Landing dip: the view drops on a spring pushed by the landing speed,
+// critically damped so it comes back up without bouncing
+const DIP_PER_SPEED: f32 = 0.15;
+const MAX_DIP_SPEED: f32 = 3.0;
+const DIP_STIFFNESS: f32 = 150.0;
+// Longest time the spring is moved in one go, so it stays steady on slow frames
+const MAX_DIP_STEP: f32 = 1.0 / 30.0;
+// Landings slower than this, in blocks per second, don't dip at all: falls
+// shorter than two blocks, including plain jumps
+const MIN_DIP_LANDING: f32 = 10.0;
+// and harder ones also shake the view
+const SHAKE_LANDING: f32 = 20.0;
+const SHAKE_PER_LANDING_SPEED: f32 = 0.02;
+
+// Screen shake: furthest the view is thrown at full strength, how fast it
+// jitters, and how much strength fades per second
+const MAX_SHAKE: f32 = 0.15;
+const SHAKE_FREQUENCY: f32 = 25.0;
+const SHAKE_DECAY: f32 = 1.5;
+
+/// Small movements of the view on top of where the camera is: bobbing while
+/// walking, a dip when landing and a shake for violent events. Each effect can
+/// be switched off on its own, and they add up into a single offset for the
+/// view matrix. Only for show, so updated at the frame rate.
+pub struct CameraEffects {
+    pub bobbing: bool,
+    pub landing_dip: bool,
+    pub shake: bool,
+    // Distance walked in steps, and how much of the bobbing shows
+    bob_phase: f32,
+    bob_amount: f32,
+    // How far the view is dipped, and how fast it's moving back
+    dip: f32,
+    dip_speed: f32,
+    // Shake strength from 0 to 1, and time for the jitter
+    trauma: f32,
+    time: f32,
+}
+
+impl CameraEffects {
+    pub fn new(bobbing: bool, landing_dip: bool, shake: bool) -> Self {
+        Self {
+            bobbing,
+            landing_dip,
+            shake,
+            bob_phase: 0.0,
+            bob_amount: 0.0,
+            dip: 0.0,
+            dip_speed: 0.0,
+            trauma: 0.0,
+            time: 0.0,
+        }
+    }
+
+    /// Advances the effects by a frame, given how fast the player walks along
+    /// the ground, which is 0 while they're in the air, swimming or flying.
+    pub fn update(&mut self, walking_speed: f32, delta_time: f32) {
+        self.time += delta_time;
+
+        self.bob_phase = (self.bob_phase + walking_speed * STEPS_PER_BLOCK * delta_time) % 2.0;
+        let target = (walking_speed / FULL_BOB_SPEED).min(1.0);
+        self.bob_amount += (target - self.bob_amount) * (BOB_FADE * delta_time).min(1.0);
+
+        // Spring back up, damped just enough not to overshoot
+        let damping = 2.0 * DIP_STIFFNESS.sqrt();
+        let step = delta_time.min(MAX_DIP_STEP);
+        self.dip_speed += (-DIP_STIFFNESS * self.dip - damping * self.dip_speed) * step;
+        self.dip += self.dip_speed * step;
+
+        self.trauma = (self.trauma - SHAKE_DECAY * delta_time).max(0.0);
+    }
+
+    /// Dips the view for a landing at `speed` blocks per second, shaking it
+    /// too after a long fall.
+    pub fn land(&mut self, speed: f32) {
+        if speed < MIN_DIP_LANDING {
+            return;
+        }
+        self.dip_speed -= (speed * DIP_PER_SPEED).min(MAX_DIP_SPEED);
+        if speed > SHAKE_LANDING {
+            self.add_shake((speed - SHAKE_LANDING) * SHAKE_PER_LANDING_SPEED);
+        }
+    }
+
+    /// Shakes the view, such as for an explosion nearby. `strength` adds up
+    /// to at most 1, and fades over time.
+    pub fn add_shake(&mut self, strength: f32) {
+        self.trauma = (self.trauma + strength).clamp(0.0, 1.0);
+    }
+
+    /// How far to move the eye from the camera position, given the camera's
+    /// right and up directions.
+    pub fn offset(&self, right: Vec3, up: Vec3) -> Vec3 {
+        let mut offset = Vec3::zero();
+        if self.bobbing {
+            // Down at every step, swaying to one side then the other
+            let angle = self.bob_phase * std::f32::consts::PI;
+            offset = offset + up * (-angle.sin().abs() * BOB_HEIGHT * self.bob_amount)
+                + right * (angle.cos() * BOB_WIDTH * self.bob_amount);
+        }
+        if self.landing_dip {
+            offset = offset + up * self.dip;
+        }
+        if self.shake && self.trauma > 0.0 {
+            // Stronger shakes grow faster than their strength, so small ones stay subtle
+            let size = self.trauma * self.trauma * MAX_SHAKE;
+            let t = self.time * SHAKE_FREQUENCY;
+            let jitter = |seed: f32| (t + seed).sin() * 0.6 + (t * 2.3 + seed * 1.7).sin() * 0.4;
+            offset = offset + right * (jitter(0.0) * size) + up * (jitter(5.0) * size);
+        }
+        offset
+    }
+}
@@ -0,0 +1,135 @@
+use crate::block::BlockType;
+use crate::column_heights::ColumnHeights;
+use crate::structures::Placement;
+use crate::world::{Chunk, CHUNK_SIZE};
+use std::collections::BTreeMap;
+
+/// Climate of every block column of a chunk column, from the world's generator.
+#[derive(Debug, Clone)]
+pub struct ColumnBiome {
+    // From -1 for the coldest to 1 for the warmest, by local z * CHUNK_SIZE + local x
+    temperatures: [f32; CHUNK_SIZE * CHUNK_SIZE],
+}
+
+impl ColumnBiome {
+    /// The same temperature all over the column.
+    pub fn uniform(temperature: f32) -> Self {
+        Self { temperatures: [temperature; CHUNK_SIZE * CHUNK_SIZE] }
+    }
+
+    pub fn temperature(&self, x: usize, z: usize) -> f32 {
+        self.temperatures[z * CHUNK_SIZE + x]
+    }
+
+    pub fn set_temperature(&mut self, x: usize, z: usize, temperature: f32) {
+        self.temperatures[z * CHUNK_SIZE + x] = temperature;
+    }
+}
+
+/// The chunks stacked at a chunk x and z, which are generated, loaded and
+/// unloaded together, with what belongs to the whole column rather than to
+/// one of its chunks.
+pub struct ChunkColumn {
+    // By chunk y
+    chunks: BTreeMap<i32, Chunk>,
+    /// Highest block of every block column, kept up to date with the chunks.
+    /// Everything above it is open to the sky.
+    pub heights: ColumnHeights,
+    pub biome: ColumnBiome,
+    /// The structure that may reach into the column, worked out once for all
+    /// of its generated chunks to be decorated with.
+    pub structure: Option<Placement>,
+}
+
+impl ChunkColumn {
+    pub fn new(biome: ColumnBiome, structure: Option<Placement>) -> Self {
+        Self {
+            chunks: BTreeMap::new(),
+            heights: ColumnHeights::new(),
+            biome,
+            structure,
+        }
+    }
+
+    /// The chunk at a chunk y, if it's loaded.
+    pub fn chunk(&self, y: i32) -> Option<&Chunk> {
+        self.chunks.get(&y)
+    }
+
+    pub fn chunk_mut(&mut self, y: i32) -> Option<&mut Chunk> {
+        self.chunks.get_mut(&y)
+    }
+
+    /// The loaded chunks, from the bottom up.
+    pub fn chunks(&self) -> impl Iterator<Item = &Chunk> {
+        self.chunks.values()
+    }
+
+    pub fn chunks_mut(&mut self) -> impl Iterator<Item = &mut Chunk> {
+        self.chunks.values_mut()
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Adds a chunk, returning the one it replaces, if any.
+    pub fn insert(&mut self, chunk: Chunk) -> Option<Chunk> {
+        // A new chunk only adds blocks where there was air, but a replaced one
+        // may also take some away
+        self.heights.add_chunk(&chunk);
+        let replaced = self.chunks.insert(chunk.position.1, chunk);
+        if replaced.is_some() {
+            self.recompute_heights();
+        }
+        replaced
+    }
+
+    /// Replaces a block of a loaded chunk, at local x and z and world y, and
+    /// keeps the heights up to date. Returns the block it replaced, or `None`
+    /// if its chunk isn't loaded.
+    pub fn set_block(&mut self, x: usize, y: i32, z: usize, block: BlockType) -> Option<BlockType> {
+        let size = CHUNK_SIZE as i32;
+        let chunk = self.chunks.get_mut(&y.div_euclid(size))?;
+        let replaced = std::mem::replace(&mut chunk.blocks[x][y.rem_euclid(size) as usize][z], block);
+        self.update_height(x, y, z, block);
+        Some(replaced)
+    }
+
+    /// Takes the chunks out of the column as it's unloaded.
+    pub fn into_chunks(self) -> impl Iterator<Item = Chunk> {
+        self.chunks.into_values()
+    }
+
+    /// Rebuilds the heights from the chunks, after some were replaced.
+    pub fn recompute_heights(&mut self) {
+        self.heights = ColumnHeights::new();
+        for chunk in self.chunks.values() {
+            self.heights.add_chunk(chunk);
+        }
+    }
+
+    // Keeps the height of a block column right after the block at local x and
+    // z and world y changed
+    fn update_height(&mut self, x: usize, y: i32, z: usize, block: BlockType) {
+        let current = self.heights.get(x, z);
+        let height = match current {
+            _ if block != BlockType::Air => Some(current.map_or(y, |current| current.max(y))),
+            // The top block was removed, so the new top is somewhere below it
+            Some(current) if current == y => self.scan_down(x, z, y - 1),
+            _ => return,
+        };
+        self.heights.set(x, z, height);
+    }
+
+    // Highest block that isn't air at local x and z, at or below world y `from`
+    fn scan_down(&self, x: usize, z: usize, from: i32) -> Option<i32> {
+        let size = CHUNK_SIZE as i32;
+        self.chunks.range(..=from.div_euclid(size)).rev().find_map(|(&chunk_y, chunk)| {
+            let top = (from - chunk_y * size).min(size - 1);
+            (0..=top).rev()
+                .find(|&y| chunk.blocks[x][y as usize][z] != BlockType::Air)
+                .map(|y| chunk_y * size + y)
+        })
+    }
+}
@@ -0,0 +1,394 @@
+use crate::atomic_file;
+use crate::block::{BlockType, RENAMED_BLOCKS, SAVED_IDS};
+use crate::byte_reader::ByteReader;
+use crate::world::{empty_blocks, ChunkBlocks, CHUNK_SIZE};
+use flate2::Crc;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Identifies encoded chunk data
+const MAGIC: &[u8; 4] = b"CHNK";
+// Bump whenever the layout changes, and teach `read` the new version while
+// it keeps reading the older ones
+const VERSION: u32 = 2;
+// Version 1 saved blocks by their ids in SAVED_IDS rather than by name
+const VERSION_WITH_IDS: u32 = 1;
+const BLOCKS_PER_CHUNK: usize = CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE;
+
+/// Encodes the blocks of a chunk, both to send them to clients and to save them.
+///
+/// After the magic and version come the chunk position, a palette of the
+/// names of the blocks used in the chunk, each after its length in a byte,
+/// then runs of identical blocks as a `u16` length and a palette index, going
+/// through the chunk x, then y, then z. A CRC-32 of everything before it ends
+/// the data. All numbers are little-endian. Terrain has long runs of air,
+/// stone and water, so a chunk usually takes a few hundred bytes instead of
+/// 4096.
+pub fn encode(position: (i32, i32, i32), blocks: &ChunkBlocks) -> Vec<u8> {
+    let mut palette: Vec<BlockType> = Vec::new();
+    let mut runs: Vec<(u16, u8)> = Vec::new();
+    for column in blocks.iter() {
+        for row in column {
+            for &block in row {
+                let index = match palette.iter().position(|&b| b == block) {
+                    Some(index) => index,
+                    None => {
+                        palette.push(block);
+                        palette.len() - 1
+                    },
+                } as u8;
+                match runs.last_mut() {
+                    Some((length, last)) if *last == index => *length += 1,
+                    _ => runs.push((1, index)),
+                }
+            }
+        }
+    }
+
+    let mut data = Vec::with_capacity(4 + 4 + 12 + 1 + palette.len() * 12 + 4 + runs.len() * 3 + 4);
+    data.extend_from_slice(MAGIC);
+    data.extend_from_slice(&VERSION.to_le_bytes());
+    for v in [position.0, position.1, position.2] {
+        data.extend_from_slice(&v.to_le_bytes());
+    }
+    data.push(palette.len() as u8);
+    for block in palette {
+        let name = block.info().name;
+        data.push(name.len() as u8);
+        data.extend_from_slice(name.as_bytes());
+    }
+    data.extend_from_slice(&(runs.len() as u32).to_le_bytes());
+    for (length, index) in runs {
+        data.extend_from_slice(&length.to_le_bytes());
+        data.push(index);
+    }
+    let checksum = checksum(&data);
+    data.extend_from_slice(&checksum.to_le_bytes());
+    data
+}
+
+/// Decodes chunk data written by `encode`, of this version or an older one,
+/// returning the chunk position and its blocks.
+pub fn decode(data: &[u8]) -> Result<((i32, i32, i32), ChunkBlocks), String> {
+    let chunk = read(data, true)?;
+    Ok((chunk.position, chunk.blocks(true, RENAMED_BLOCKS)?))
+}
+
+/// The format version of encoded chunk data, if it is chunk data at all.
+pub fn version(data: &[u8]) -> Option<u32> {
+    let mut reader = ByteReader::new(data);
+    (reader.take(4)? == MAGIC).then(|| reader.u32()).flatten()
+}
+
+// A chunk as read from data of any version, before its blocks are looked up
+struct RawChunk {
+    position: (i32, i32, i32),
+    // Name of each block the runs refer to
+    palette: Vec<String>,
+    runs: Vec<(u16, u8)>,
+}
+
+impl RawChunk {
+    // Looks up the blocks by name, through `renames`. Unless `strict`, blocks
+    // missing from the end are air and extra ones are dropped
+    fn blocks(&self, strict: bool, renames: &[(&str, &str)]) -> Result<ChunkBlocks, String> {
+        let palette = self.palette.iter()
+            .map(|name| BlockType::from_saved_name(name, renames).ok_or_else(|| format!("Unknown block: {}", name)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut blocks = Vec::with_capacity(BLOCKS_PER_CHUNK);
+        for &(length, index) in &self.runs {
+            let block = *palette.get(index as usize).ok_or("Palette index out of range")?;
+            if blocks.len() + length as usize > BLOCKS_PER_CHUNK && strict {
+                return Err("Too many blocks in chunk data".to_string());
+            }
+            blocks.extend(std::iter::repeat_n(block, length as usize));
+        }
+        if blocks.len() != BLOCKS_PER_CHUNK && strict {
+            return Err(format!("Chunk data has {} blocks, expected {}", blocks.len(), BLOCKS_PER_CHUNK));
+        }
+        blocks.resize(BLOCKS_PER_CHUNK, BlockType::Air);
+
+        let mut chunk = empty_blocks();
+        for (cell, block) in chunk.iter_mut().flatten().flatten().zip(blocks) {
+            *cell = block;
+        }
+        Ok(chunk)
+    }
+}
+
+// Reads chunk data of any version. Unless `strict`, a wrong checksum is let
+// through and the runs end wherever the data does, to save what can be saved
+// of damaged files
+fn read(data: &[u8], strict: bool) -> Result<RawChunk, String> {
+    let truncated = || "Truncated chunk data".to_string();
+    let version = version(data).ok_or("Not chunk data")?;
+    let body = match version {
+        VERSION_WITH_IDS => data,
+        // Damaged data may have lost its end, checksum and all. The run count
+        // says where the runs end anyway
+        VERSION if !strict => data,
+        VERSION => {
+            let (body, checksum_bytes) = data.split_at(data.len().saturating_sub(4));
+            if checksum_bytes != checksum(body).to_le_bytes() {
+                return Err("Chunk data doesn't match its checksum".to_string());
+            }
+            body
+        },
+        _ => return Err(format!("Unsupported chunk data version: {}", version)),
+    };
+
+    let mut reader = ByteReader::new(body);
+    // Past the magic and version, which data too short to have a checksum
+    // may not even have
+    reader.take(8).ok_or_else(truncated)?;
+    let position = (
+        reader.i32().ok_or_else(truncated)?,
+        reader.i32().ok_or_else(truncated)?,
+        reader.i32().ok_or_else(truncated)?,
+    );
+    let palette_size = reader.u8().ok_or_else(truncated)?;
+    let mut palette = Vec::with_capacity(palette_size as usize);
+    for _ in 0..palette_size {
+        let name = if version == VERSION_WITH_IDS {
+            let id = reader.u8().ok_or_else(truncated)?;
+            SAVED_IDS.get(id as usize).ok_or_else(|| format!("Unknown block id: {}", id))?.to_string()
+        } else {
+            let length = reader.u8().ok_or_else(truncated)?;
+            let name = reader.take(length as usize).ok_or_else(truncated)?;
+            String::from_utf8(name.to_vec()).map_err(|_| "Block name isn't UTF-8".to_string())?
+        };
+        palette.push(name);
+    }
+
+    let run_count = reader.u32().ok_or_else(truncated)?;
+    let mut runs = Vec::new();
+    for _ in 0..run_count {
+        match (reader.u16(), reader.u8()) {
+            (Some(length), Some(index)) => runs.push((length, index)),
+            _ if strict => return Err(truncated()),
+            _ => break,
+        }
+    }
+    Ok(RawChunk { position, palette, runs })
+}
+
+fn checksum(data: &[u8]) -> u32 {
+    let mut crc = Crc::new();
+    crc.update(data);
+    crc.sum()
+}
+
+/// Saved chunks of a world, one file per chunk in the chunk data format.
+#[derive(Clone)]
+pub struct ChunkStore {
+    directory: PathBuf,
+}
+
+impl ChunkStore {
+    /// Uses the given directory to store chunks, creating it on the first write.
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self { directory: directory.into() }
+    }
+
+    pub fn save(&self, position: (i32, i32, i32), blocks: &ChunkBlocks) -> io::Result<()> {
+        atomic_file::write(&self.path(position), &encode(position, blocks))
+    }
+
+    /// Reads the saved blocks of a chunk.
+    pub fn load(&self, position: (i32, i32, i32)) -> Result<ChunkBlocks, String> {
+        let (_, blocks) = fs::read(self.path(position)).map_err(|e| e.to_string()).and_then(|data| decode(&data))?;
+        Ok(blocks)
+    }
+
+    /// Positions of the saved chunks, going by the names of their files
+    /// rather than reading them.
+    pub fn saved_chunks(&self) -> Vec<(i32, i32, i32)> {
+        let Ok(entries) = fs::read_dir(&self.directory) else {
+            return Vec::new();
+        };
+        entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "chunk"))
+            .filter_map(|path| file_position(&path))
+            .collect()
+    }
+
+    /// Loads every saved chunk. Unreadable files are reported and skipped.
+    pub fn load_all(&self) -> Vec<((i32, i32, i32), ChunkBlocks)> {
+        let Ok(entries) = fs::read_dir(&self.directory) else {
+            return Vec::new();
+        };
+        entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "chunk"))
+            .filter_map(|entry| {
+                let path = entry.path();
+                match fs::read(&path).map_err(|e| e.to_string()).and_then(|data| decode(&data)) {
+                    Ok(chunk) => Some(chunk),
+                    Err(e) => {
+                        log::warn!(target: "io", "Failed to load chunk {}: {}", path.display(), e);
+                        None
+                    },
+                }
+            })
+            .collect()
+    }
+
+    /// Checks every saved chunk: files of older versions or named for another
+    /// chunk are rewritten, what can be read of damaged files is saved with
+    /// air for the rest, and unreadable files are moved to a `corrupt` folder
+    /// beside the chunks, so their chunks generate afresh.
+    pub fn repair(&self) -> io::Result<RepairReport> {
+        let mut report = RepairReport::default();
+        let Ok(entries) = fs::read_dir(&self.directory) else {
+            return Ok(report);
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "chunk") {
+                continue;
+            }
+            let data = fs::read(&path)?;
+            let named = file_position(&path);
+            match decode(&data) {
+                Ok((position, _)) if version(&data) == Some(VERSION) && named == Some(position) => report.intact += 1,
+                Ok((position, blocks)) => {
+                    log::info!(target: "io", "Rewriting chunk {}", path.display());
+                    self.replace(&path, position, &blocks)?;
+                    report.upgraded += 1;
+                },
+                Err(e) => match read(&data, false).and_then(|chunk| Ok((chunk.position, chunk.blocks(false, RENAMED_BLOCKS)?))) {
+                    Ok((position, blocks)) => {
+                        log::warn!(target: "io", "Salvaged chunk {}: {}", path.display(), e);
+                        // The name is more likely right than a damaged header
+                        self.replace(&path, named.unwrap_or(position), &blocks)?;
+                        report.salvaged += 1;
+                    },
+                    Err(_) => {
+                        log::warn!(target: "io", "Moving unreadable chunk {} aside: {}", path.display(), e);
+                        let corrupt = self.directory.join("corrupt");
+                        fs::create_dir_all(&corrupt)?;
+                        fs::rename(&path, corrupt.join(entry.file_name()))?;
+                        report.quarantined += 1;
+                    },
+                },
+            }
+        }
+        Ok(report)
+    }
+
+    /// The file a chunk is saved to.
+    pub fn path(&self, position: (i32, i32, i32)) -> PathBuf {
+        self.directory.join(format!("{}_{}_{}.chunk", position.0, position.1, position.2))
+    }
+
+    // Saves a chunk read from `path` in the current version, under its own name
+    fn replace(&self, path: &Path, position: (i32, i32, i32), blocks: &ChunkBlocks) -> io::Result<()> {
+        self.save(position, blocks)?;
+        if path != self.path(position) {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+/// What `ChunkStore::repair` found, as counts of chunk files.
+#[derive(Debug, Default)]
+pub struct RepairReport {
+    pub intact: usize,
+    /// Of older versions or under the wrong name, and rewritten.
+    pub upgraded: usize,
+    /// Damaged, with what could be read of them kept.
+    pub salvaged: usize,
+    /// Unreadable, and moved to the `corrupt` folder.
+    pub quarantined: usize,
+}
+
+// The chunk position a save file is named for
+fn file_position(path: &Path) -> Option<(i32, i32, i32)> {
+    let mut parts = path.file_stem()?.to_str()?.split('_').map(|part| part.parse().ok());
+    let position = (parts.next()??, parts.next()??, parts.next()??);
+    parts.next().is_none().then_some(position)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Stone below the middle of the chunk and air above it
+    fn half_stone() -> ChunkBlocks {
+        let mut blocks = empty_blocks();
+        for column in blocks.iter_mut() {
+            for row in column.iter_mut().take(CHUNK_SIZE / 2) {
+                row.fill(BlockType::Stone);
+            }
+        }
+        blocks
+    }
+
+    #[test]
+    fn version_1_saves_load_through_their_frozen_ids() {
+        // Palette of stone and water by their version 1 ids, then a run of each
+        let mut data = MAGIC.to_vec();
+        data.extend_from_slice(&VERSION_WITH_IDS.to_le_bytes());
+        for v in [1i32, -2, 3] {
+            data.extend_from_slice(&v.to_le_bytes());
+        }
+        data.extend_from_slice(&[2, 3, 4]);
+        data.extend_from_slice(&2u32.to_le_bytes());
+        for (length, index) in [(4000u16, 0u8), (96, 1)] {
+            data.extend_from_slice(&length.to_le_bytes());
+            data.push(index);
+        }
+
+        let (position, blocks) = decode(&data).unwrap();
+        assert_eq!(position, (1, -2, 3));
+        let flat: Vec<_> = blocks.iter().flatten().flatten().copied().collect();
+        assert!(flat[..4000].iter().all(|&block| block == BlockType::Stone));
+        assert!(flat[4000..].iter().all(|&block| block == BlockType::Water));
+
+        // Saving it again writes the current version, which reads back the same
+        let upgraded = encode(position, &blocks);
+        assert_eq!(version(&upgraded), Some(VERSION));
+        assert!(decode(&upgraded).unwrap().1 == blocks);
+    }
+
+    #[test]
+    fn renamed_blocks_load_as_their_new_name() {
+        let data = encode((0, 0, 0), &half_stone());
+        let mut chunk = read(&data, true).unwrap();
+        let stone = chunk.palette.iter_mut().find(|name| *name == "stone").unwrap();
+        *stone = "old_stone".to_string();
+
+        assert!(chunk.blocks(true, &[]).is_err());
+        assert!(chunk.blocks(true, &[("old_stone", "stone")]).unwrap() == half_stone());
+    }
+
+    #[test]
+    fn short_data_is_an_error() {
+        let mut data = MAGIC.to_vec();
+        data.extend_from_slice(&VERSION.to_le_bytes());
+        assert!(decode(&data).is_err());
+        assert!(decode(&data[..6]).is_err());
+    }
+
+    #[test]
+    fn repair_salvages_chunks_with_a_bad_checksum() {
+        let directory = std::env::temp_dir().join(format!("chunk_repair_{}", std::process::id()));
+        let store = ChunkStore::new(&directory);
+        let position = (4, 0, -7);
+        let mut data = encode(position, &half_stone());
+        *data.last_mut().unwrap() ^= 0xff;
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(store.path(position), &data).unwrap();
+        assert!(store.load(position).is_err());
+
+        let report = store.repair().unwrap();
+        assert_eq!((report.intact, report.salvaged, report.quarantined), (0, 1, 0));
+        assert!(store.load(position).unwrap() == half_stone());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
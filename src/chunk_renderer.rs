@@ -0,0 +1,330 @@
+use crate::gl_utils::{BufferType, ShaderProgram, StreamBuffer, VertexArray};
+use crate::math::{Mat4, Vec3};
+use crate::mesher::ChunkMesh;
+use crate::profiler::{Profiler, System};
+use crate::world::World;
+use crate::{TriIndexes, Vertex};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
+
+// Smallest size of each streaming buffer, so a small world still has room for edits
+const MIN_STREAM_CAPACITY: usize = 4 * 1024 * 1024;
+// How long a newly loaded chunk takes to fade in
+const FADE_IN_DURATION: Duration = Duration::from_millis(400);
+
+// A range of a chunk's triangles in the index buffer
+#[derive(Clone, Copy)]
+struct IndexRange {
+    offset: usize,
+    count: i32,
+}
+
+// Where a chunk's mesh lives in the streaming buffers
+struct ChunkDraw {
+    opaque: Option<IndexRange>,
+    water: Option<IndexRange>,
+    base_vertex: i32,
+    // Moves the mesh from its origin into the world
+    model: Mat4,
+    // Size of the vertices and indices written
+    bytes: usize,
+}
+
+/// Keeps chunk meshes on the GPU and draws them.
+///
+/// Meshes are appended into a pair of stream buffers, so a remeshed chunk is
+/// uploaded without stalling on draws that still use its previous mesh. When
+/// the buffers wrap around, every chunk is uploaded again. Chunks fade in
+/// over a short time after their first upload to hide pop-in.
+///
+/// Opaque faces and water are drawn in separate passes, so water blends over
+/// the terrain of every chunk behind it.
+///
+/// Streamed chunks can be queued rather than uploaded at once, and are then
+/// uploaded a few per frame within a budget of bytes, so a burst of new
+/// meshes doesn't make one frame hitch.
+///
+/// Chunks whose mesh the world evicted can't go up again when the buffers
+/// wrap around, and are handed back by `take_lost_uploads` to be remeshed.
+pub struct ChunkRenderer {
+    vao: VertexArray,
+    vertices: StreamBuffer,
+    indices: StreamBuffer,
+    draws: HashMap<(i32, i32, i32), ChunkDraw>,
+    // When each chunk was first uploaded
+    loaded_at: HashMap<(i32, i32, i32), Instant>,
+    // Chunks waiting for an upload, in the order they were queued
+    queue: VecDeque<(i32, i32, i32)>,
+    queued: HashSet<(i32, i32, i32)>,
+    // Evicted chunks that weren't uploaded again
+    lost: Vec<(i32, i32, i32)>,
+}
+
+impl ChunkRenderer {
+    /// Creates the buffers, sized to hold twice the geometry currently in the world.
+    pub fn new(world: &World) -> Option<Self> {
+        let vertex_bytes: usize = world.chunks()
+            .map(|chunk| std::mem::size_of_val(chunk.mesh.vertices.as_slice()))
+            .sum();
+        let index_bytes: usize = world.chunks()
+            .map(|chunk| std::mem::size_of_val(chunk.mesh.indices.as_slice()) + std::mem::size_of_val(chunk.mesh.water_indices.as_slice()))
+            .sum();
+
+        let vao = VertexArray::new()?;
+        let (vertices, indices) = Self::create_streams(
+            &vao,
+            (vertex_bytes * 2).max(MIN_STREAM_CAPACITY),
+            (index_bytes * 2).max(MIN_STREAM_CAPACITY),
+        )?;
+        let streaming = if vertices.is_persistent() { "persistently mapped" } else { "orphaned" };
+        log::info!(target: "render", "Chunk meshes are streamed through {} buffers", streaming);
+
+        Some(Self {
+            vao,
+            vertices,
+            indices,
+            draws: HashMap::new(),
+            loaded_at: HashMap::new(),
+            queue: VecDeque::new(),
+            queued: HashSet::new(),
+            lost: Vec::new(),
+        })
+    }
+
+    // Allocates the stream buffers and points the vertex array at them
+    fn create_streams(vao: &VertexArray, vertex_capacity: usize, index_capacity: usize) -> Option<(StreamBuffer, StreamBuffer)> {
+        vao.bind();
+        let vertices = StreamBuffer::new(BufferType::Array, vertex_capacity)?;
+        let indices = StreamBuffer::new(BufferType::ElementArray, index_capacity)?;
+
+        vertices.bind();
+        unsafe {
+            // Packed vertex, read as integers and unpacked by the shader
+            gl::VertexAttribIPointer(
+                0,
+                2,
+                gl::UNSIGNED_INT,
+                std::mem::size_of::<Vertex>() as gl::types::GLsizei,
+                std::ptr::null(),
+            );
+            gl::EnableVertexAttribArray(0);
+        }
+
+        Some((vertices, indices))
+    }
+
+    /// Uploads the mesh of every chunk in the world, replacing all previous
+    /// uploads. The buffers are grown if the world no longer fits. Chunks
+    /// whose mesh was evicted are lost until they are remeshed.
+    pub fn upload_all(&mut self, world: &World) {
+        let _scope = Profiler::scope(System::Upload);
+        self.queue.clear();
+        self.queued.clear();
+        loop {
+            self.vao.bind();
+            self.vertices.restart();
+            self.indices.restart();
+            self.draws.clear();
+
+            let mut lost = Vec::new();
+            let fits = world.chunks().all(|chunk| {
+                if world.is_mesh_evicted(chunk.position) {
+                    lost.push(chunk.position);
+                    return true;
+                }
+                self.write_mesh(chunk.position, &chunk.mesh)
+            });
+            if fits {
+                self.lost.extend(lost);
+                break;
+            }
+
+            let grown = Self::create_streams(&self.vao, self.vertices.capacity() * 2, self.indices.capacity() * 2);
+            match grown {
+                Some((vertices, indices)) => {
+                    self.vertices = vertices;
+                    self.indices = indices;
+                },
+                // Out of memory, draw what made it in
+                None => break,
+            }
+        }
+    }
+
+    /// Uploads the current mesh of one chunk, e.g. after it was remeshed.
+    pub fn upload_chunk(&mut self, world: &World, pos: (i32, i32, i32)) {
+        let _scope = Profiler::scope(System::Upload);
+        if self.queued.remove(&pos) {
+            self.queue.retain(|&queued| queued != pos);
+        }
+        self.vao.bind();
+        match world.chunk(pos) {
+            Some(chunk) => {
+                if !self.write_mesh(pos, &chunk.mesh) {
+                    self.upload_all(world);
+                }
+            },
+            None => self.remove_chunk(pos),
+        }
+    }
+
+    /// Queues a chunk for `upload_queued`, once however many times it's queued.
+    pub fn queue_upload(&mut self, pos: (i32, i32, i32)) {
+        if self.queued.insert(pos) {
+            self.queue.push_back(pos);
+        }
+    }
+
+    /// How many chunks are waiting to be uploaded.
+    pub fn queued_uploads(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Whether a chunk is waiting to be uploaded.
+    pub fn is_upload_queued(&self, pos: (i32, i32, i32)) -> bool {
+        self.queued.contains(&pos)
+    }
+
+    /// Uploads queued chunks in the order they were queued, stopping before
+    /// the meshes sent add up to more than `byte_budget` bytes, or going
+    /// through all of them for a budget of 0. At least one chunk is uploaded
+    /// per call, however big, so the queue always moves. Returns how many
+    /// chunks were uploaded.
+    pub fn upload_queued(&mut self, world: &World, byte_budget: usize) -> usize {
+        let _scope = Profiler::scope(System::Upload);
+        let mut uploaded = 0;
+        let mut bytes = 0;
+        while let Some(&pos) = self.queue.front() {
+            let Some(chunk) = world.chunk(pos) else {
+                self.queue.pop_front();
+                self.queued.remove(&pos);
+                self.remove_chunk(pos);
+                continue;
+            };
+            let mesh = &chunk.mesh;
+            let size = std::mem::size_of_val(mesh.vertices.as_slice())
+                + std::mem::size_of_val(mesh.indices.as_slice())
+                + std::mem::size_of_val(mesh.water_indices.as_slice());
+            if byte_budget > 0 && uploaded > 0 && bytes + size > byte_budget {
+                break;
+            }
+
+            self.queue.pop_front();
+            self.queued.remove(&pos);
+            self.vao.bind();
+            if !self.write_mesh(pos, mesh) {
+                // Everything went up again, the rest of the queue included
+                self.upload_all(world);
+                return uploaded + 1;
+            }
+            uploaded += 1;
+            bytes += size;
+        }
+        uploaded
+    }
+
+    /// Chunks that stopped being drawn when everything was uploaded again,
+    /// because their mesh was evicted. They need remeshing and uploading.
+    pub fn take_lost_uploads(&mut self) -> Vec<(i32, i32, i32)> {
+        std::mem::take(&mut self.lost)
+    }
+
+    /// Bytes of the meshes drawn from the buffers. Space the buffers hold for
+    /// meshes yet to come isn't counted.
+    pub fn uploaded_bytes(&self) -> usize {
+        self.draws.values().map(|draw| draw.bytes).sum()
+    }
+
+    /// Stops drawing a chunk that was unloaded.
+    pub fn remove_chunk(&mut self, pos: (i32, i32, i32)) {
+        self.draws.remove(&pos);
+        self.loaded_at.remove(&pos);
+        if self.queued.remove(&pos) {
+            self.queue.retain(|&queued| queued != pos);
+        }
+    }
+
+    // Appends a mesh to the buffers. Returns false if they wrapped around, which
+    // invalidates the other chunks' uploads.
+    fn write_mesh(&mut self, pos: (i32, i32, i32), mesh: &ChunkMesh) -> bool {
+        self.draws.remove(&pos);
+        self.loaded_at.entry(pos).or_insert_with(Instant::now);
+        if mesh.indices.is_empty() && mesh.water_indices.is_empty() {
+            return true;
+        }
+
+        let generations = (self.vertices.generation(), self.indices.generation());
+        let vertex_offset = self.vertices.write(bytemuck::cast_slice(&mesh.vertices), std::mem::size_of::<Vertex>());
+        let opaque = self.write_indices(&mesh.indices);
+        let water = self.write_indices(&mesh.water_indices);
+        if generations != (self.vertices.generation(), self.indices.generation()) {
+            return false;
+        }
+
+        if let (Some(vertex_offset), Some(opaque), Some(water)) = (vertex_offset, opaque, water) {
+            self.draws.insert(pos, ChunkDraw {
+                opaque,
+                water,
+                base_vertex: (vertex_offset / std::mem::size_of::<Vertex>()) as i32,
+                model: Mat4::translate(Vec3::new(mesh.origin[0], mesh.origin[1], mesh.origin[2])),
+                bytes: std::mem::size_of_val(mesh.vertices.as_slice())
+                    + std::mem::size_of_val(mesh.indices.as_slice())
+                    + std::mem::size_of_val(mesh.water_indices.as_slice()),
+            });
+        }
+        true
+    }
+
+    // Appends triangles to the index buffer. None if they didn't fit, Some(None)
+    // if there were none to write.
+    fn write_indices(&mut self, indices: &[TriIndexes]) -> Option<Option<IndexRange>> {
+        if indices.is_empty() {
+            return Some(None);
+        }
+        let offset = self.indices.write(bytemuck::cast_slice(indices), std::mem::size_of::<u32>())?;
+        Some(Some(IndexRange { offset, count: (indices.len() * 3) as i32 }))
+    }
+
+    /// Draws the opaque faces of every uploaded chunk with the given program,
+    /// which must be in use.
+    pub fn draw_opaque(&self, program: &ShaderProgram) {
+        self.draw_ranges(program, |draw| draw.opaque);
+    }
+
+    /// Draws the water of every uploaded chunk with the given program, which
+    /// must be in use. Comes after `draw_opaque`, as the last use of this
+    /// frame's buffers.
+    pub fn draw_water(&mut self, program: &ShaderProgram) {
+        self.draw_ranges(program, |draw| draw.water);
+
+        // Protect this frame's data from being overwritten while the GPU reads it
+        self.vertices.fence();
+        self.indices.fence();
+    }
+
+    fn draw_ranges(&self, program: &ShaderProgram, range: impl Fn(&ChunkDraw) -> Option<IndexRange>) {
+        let alpha_loc = unsafe { gl::GetUniformLocation(program.0, b"chunkAlpha\0".as_ptr() as *const i8) };
+        let model_loc = unsafe { gl::GetUniformLocation(program.0, b"model\0".as_ptr() as *const i8) };
+        let now = Instant::now();
+
+        self.vao.bind();
+        for (pos, draw) in &self.draws {
+            let Some(range) = range(draw) else {
+                continue;
+            };
+            let age = self.loaded_at.get(pos).map_or(FADE_IN_DURATION, |&loaded_at| now - loaded_at);
+            let alpha = (age.as_secs_f32() / FADE_IN_DURATION.as_secs_f32()).min(1.0);
+            unsafe {
+                gl::Uniform1f(alpha_loc, alpha);
+                gl::UniformMatrix4fv(model_loc, 1, gl::FALSE, draw.model.as_ptr());
+                gl::DrawElementsBaseVertex(
+                    gl::TRIANGLES,
+                    range.count,
+                    gl::UNSIGNED_INT,
+                    range.offset as *const _,
+                    draw.base_vertex,
+                );
+            }
+        }
+    }
+}
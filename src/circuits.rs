@@ -0,0 +1,106 @@
+use crate::block::BlockType;
+use crate::block_entity::BlockEntity;
+use crate::nbt::Tag;
+use crate::ticks::{TickContext, TickKind};
+use std::any::Any;
+
+/// Name wire block entities are saved under.
+pub const WIRE_KIND: &str = "wire";
+/// Signal strength next to a power source. It drops by one for every block of
+/// wire it goes through.
+pub const MAX_POWER: u8 = 15;
+// Ticks for a change in signal to move one block along
+const SIGNAL_DELAY: u64 = 1;
+
+const NEIGHBORS: [(i32, i32, i32); 6] = [(1, 0, 0), (-1, 0, 0), (0, 1, 0), (0, -1, 0), (0, 0, 1), (0, 0, -1)];
+
+/// Block entity of a piece of wire: the strength of the signal in it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Wire {
+    pub power: u8,
+}
+
+impl Wire {
+    /// Reads a wire back from what `save` wrote.
+    pub fn load(tag: &Tag) -> Option<Box<dyn BlockEntity>> {
+        let power = tag.get("Power")?.as_i64()?.clamp(0, MAX_POWER as i64) as u8;
+        Some(Box::new(Wire { power }))
+    }
+}
+
+impl BlockEntity for Wire {
+    fn kind(&self) -> &'static str {
+        WIRE_KIND
+    }
+
+    fn save(&self) -> Tag {
+        Tag::compound([("Power", Tag::Byte(self.power as i8))])
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+// Signal strength a block gives to the blocks next to it
+fn output(context: &TickContext, position: (i32, i32, i32)) -> u8 {
+    match context.get_block(position) {
+        BlockType::PowerSource => MAX_POWER,
+        BlockType::Wire => context.world.block_entities.get::<Wire>(position).map_or(0, |wire| wire.power),
+        _ => 0,
+    }
+}
+
+fn neighbors((x, y, z): (i32, i32, i32)) -> impl Iterator<Item = (i32, i32, i32)> {
+    NEIGHBORS.iter().map(move |&(dx, dy, dz)| (x + dx, y + dy, z + dz))
+}
+
+/// Wire takes the strongest signal around it, one weaker. When that changes,
+/// the blocks around it are updated on a later tick, so a signal travels a
+/// block per tick and dies out when its source is gone.
+pub fn wire_tick(context: &mut TickContext) {
+    if !matches!(context.kind, TickKind::Scheduled(_)) {
+        return;
+    }
+    let position = context.position;
+    let power = neighbors(position)
+        .map(|neighbor| match context.get_block(neighbor) {
+            BlockType::PowerSource => MAX_POWER,
+            _ => output(context, neighbor).saturating_sub(1),
+        })
+        .max()
+        .unwrap_or(0);
+
+    // New wire has no block entity until its first update
+    let block_entities = &mut context.world.block_entities;
+    if block_entities.get::<Wire>(position).is_none() {
+        block_entities.insert(position, BlockType::Wire, Box::new(Wire::default()));
+    }
+    let wire = block_entities.get_mut::<Wire>(position).expect("wire was just inserted");
+    if wire.power == power {
+        return;
+    }
+    wire.power = power;
+    for neighbor in neighbors(position) {
+        if matches!(context.get_block(neighbor), BlockType::Wire | BlockType::Lamp | BlockType::LitLamp) {
+            context.schedule(neighbor, SIGNAL_DELAY, 0);
+        }
+    }
+}
+
+/// Lamps light up while a power source or powered wire is next to them.
+pub fn lamp_tick(context: &mut TickContext) {
+    if !matches!(context.kind, TickKind::Scheduled(_)) {
+        return;
+    }
+    let position = context.position;
+    let powered = neighbors(position).any(|neighbor| output(context, neighbor) > 0);
+    let block = if powered { BlockType::LitLamp } else { BlockType::Lamp };
+    if context.get_block(position) != block {
+        context.set_block(position, block);
+    }
+}
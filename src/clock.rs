@@ -0,0 +1,6 @@
+/// The clock frame times and timings are measured with. std's panics in the
+/// browser, where `web_time` reads the page's clock instead.
+#[cfg(not(target_arch = "wasm32"))]
+pub use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+pub use web_time::Instant;
@@ -0,0 +1,128 @@
+use crate::gl_utils::{buffer_data, Buffer, BufferType, GlState, ShaderProgram, VertexArray};
+use noise::{NoiseFn, Perlin};
+
+// Altitude of the cloud layer, above the highest mountains
+const CLOUD_HEIGHT: f32 = 140.0;
+// Blocks covered by one texel of the cloud pattern
+const CELL_SIZE: f32 = 12.0;
+// Texels along each side of the pattern, which repeats after that
+const TEXTURE_SIZE: usize = 128;
+// How far the plane reaches from the camera, in blocks
+const CLOUD_RADIUS: f32 = 600.0;
+// Drift in blocks per second, along the wind
+const WIND_SPEED: f32 = 1.5;
+// Noise values above this are cloud; lower means a more overcast sky
+const CLOUD_THRESHOLD: f64 = 0.1;
+
+/// A flat layer of blocky clouds high above the terrain, drifting with the wind.
+pub struct Clouds {
+    program: ShaderProgram,
+    vao: VertexArray,
+    _vertices: Buffer,
+    texture: gl::types::GLuint,
+    // Horizontal direction the clouds drift in
+    wind: (f32, f32),
+    offset: (f32, f32),
+}
+
+impl Clouds {
+    pub fn new(program: ShaderProgram, seed: u32) -> Option<Self> {
+        let vao = VertexArray::new()?;
+        vao.bind();
+        let vertices = Buffer::new()?;
+        vertices.bind(BufferType::Array);
+        let corners: [f32; 12] = [-1.0, -1.0, 1.0, -1.0, 1.0, 1.0, 1.0, 1.0, -1.0, 1.0, -1.0, -1.0];
+        buffer_data(BufferType::Array, bytemuck::cast_slice(&corners), gl::STATIC_DRAW);
+        unsafe {
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, (2 * std::mem::size_of::<f32>()) as i32, std::ptr::null());
+            gl::EnableVertexAttribArray(0);
+        }
+
+        // Each world gets its own wind direction
+        let angle = (seed % 360) as f32;
+        Some(Self {
+            program,
+            vao,
+            _vertices: vertices,
+            texture: cloud_texture(seed),
+            wind: (angle.to_radians().cos(), angle.to_radians().sin()),
+            offset: (0.0, 0.0),
+        })
+    }
+
+    /// Drifts the clouds along the wind.
+    pub fn update(&mut self, delta_time: f32) {
+        // Wrapped to the size of the pattern so the offset never loses precision
+        let period = CELL_SIZE * TEXTURE_SIZE as f32;
+        self.offset.0 = (self.offset.0 + self.wind.0 * WIND_SPEED * delta_time) % period;
+        self.offset.1 = (self.offset.1 + self.wind.1 * WIND_SPEED * delta_time) % period;
+    }
+
+    /// Draws the cloud layer around the camera, lit by the daylight. Call after
+    /// the opaque terrain, since the clouds blend over it.
+    pub fn draw(&self) {
+        self.program.use_program();
+        let uniform = |name: &[u8]| unsafe { gl::GetUniformLocation(self.program.0, name.as_ptr() as *const i8) };
+        GlState::bind_texture(2, gl::TEXTURE_2D, self.texture);
+        unsafe {
+            gl::Uniform1f(uniform(b"radius\0"), CLOUD_RADIUS);
+            gl::Uniform1f(uniform(b"height\0"), CLOUD_HEIGHT);
+            gl::Uniform1i(uniform(b"cloudTexture\0"), 2);
+            gl::Uniform2f(uniform(b"windOffset\0"), self.offset.0, self.offset.1);
+            gl::Uniform1f(uniform(b"cellSize\0"), CELL_SIZE);
+
+            // Seen from below and from above; translucent, so they don't hide each other
+            self.vao.bind();
+            GlState::set_capability(gl::CULL_FACE, false);
+            GlState::depth_mask(false);
+            gl::DrawArrays(gl::TRIANGLES, 0, 6);
+            GlState::depth_mask(true);
+            GlState::set_capability(gl::CULL_FACE, true);
+        }
+    }
+}
+
+impl Drop for Clouds {
+    fn drop(&mut self) {
+        GlState::delete_texture(self.texture);
+    }
+}
+
+// Cloud cover as a repeating pattern, one byte per texel: 255 for cloud, 0 for sky
+fn cloud_texture(seed: u32) -> gl::types::GLuint {
+    let noise = Perlin::new(seed.wrapping_add(1618));
+    let mut texels = Vec::with_capacity(TEXTURE_SIZE * TEXTURE_SIZE);
+    for y in 0..TEXTURE_SIZE {
+        for x in 0..TEXTURE_SIZE {
+            // Sampled on a torus in 4D so the pattern tiles seamlessly
+            let (u, v) = (x as f64 / TEXTURE_SIZE as f64, y as f64 / TEXTURE_SIZE as f64);
+            let (a, b) = (u * std::f64::consts::TAU, v * std::f64::consts::TAU);
+            let r = 2.0;
+            let value = noise.get([a.cos() * r, a.sin() * r, b.cos() * r, b.sin() * r]);
+            texels.push(if value > CLOUD_THRESHOLD { 255u8 } else { 0 });
+        }
+    }
+
+    let mut texture = 0;
+    unsafe {
+        gl::GenTextures(1, &mut texture);
+        GlState::bind_texture(0, gl::TEXTURE_2D, texture);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+        gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+        gl::TexImage2D(
+            gl::TEXTURE_2D,
+            0,
+            gl::R8 as i32,
+            TEXTURE_SIZE as i32,
+            TEXTURE_SIZE as i32,
+            0,
+            gl::RED,
+            gl::UNSIGNED_BYTE,
+            texels.as_ptr() as *const _
+        );
+    }
+    texture
+}
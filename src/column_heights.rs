@@ -0,0 +1,42 @@
+use crate::block::BlockType;
+use crate::world::{Chunk, CHUNK_SIZE};
+
+/// Highest block that isn't air at each x, z of a loaded chunk column, so
+/// looking for the surface doesn't have to scan down from the top of the world.
+#[derive(Debug, Clone)]
+pub struct ColumnHeights {
+    // World y of the highest block, by local z * CHUNK_SIZE + local x, or
+    // `None` where the loaded part of the column is all air
+    heights: [Option<i32>; CHUNK_SIZE * CHUNK_SIZE],
+}
+
+impl ColumnHeights {
+    pub fn new() -> Self {
+        Self { heights: [None; CHUNK_SIZE * CHUNK_SIZE] }
+    }
+
+    pub fn get(&self, x: usize, z: usize) -> Option<i32> {
+        self.heights[z * CHUNK_SIZE + x]
+    }
+
+    pub fn set(&mut self, x: usize, z: usize, height: Option<i32>) {
+        self.heights[z * CHUNK_SIZE + x] = height;
+    }
+
+    /// Raises the heights to the blocks of a chunk joining the column, where
+    /// there was air before.
+    pub fn add_chunk(&mut self, chunk: &Chunk) {
+        let bottom = chunk.position.1 * CHUNK_SIZE as i32;
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                let Some(top) = (0..CHUNK_SIZE).rev().find(|&y| chunk.blocks[x][y][z] != BlockType::Air) else {
+                    continue;
+                };
+                let height = bottom + top as i32;
+                if self.get(x, z).is_none_or(|current| current < height) {
+                    self.set(x, z, Some(height));
+                }
+            }
+        }
+    }
+}
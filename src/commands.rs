@@ -0,0 +1,414 @@
+use crate::block::BlockType;
+use crate::explosion::Explosion;
+use crate::export::{export_meshes, EXPORT_DIRECTORY};
+use crate::game_mode::GameMode;
+use crate::inventory::Inventory;
+use crate::language::text;
+use crate::math::Vec3;
+use crate::mesh_cache::MeshCache;
+use crate::mob::MobKind;
+use crate::player::EYE_HEIGHT;
+use crate::schematic::Schematic;
+use crate::screenshot::Timelapse;
+use crate::selection::Selection;
+use crate::spawn::SpawnPoint;
+use crate::world::World;
+use crate::world_border::{WorldBorder, MIN_BORDER_RADIUS};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Message shown in the console when a command succeeds or fails.
+pub type CommandResult = Result<String, String>;
+
+type Handler = Box<dyn Fn(&mut CommandContext, &[&str]) -> CommandResult>;
+
+// Largest number of blocks a single /fill may change
+const MAX_FILL_VOLUME: i64 = 32 * 1024;
+// Largest schematic that may be saved or pasted in one go
+const MAX_SCHEMATIC_VOLUME: i64 = 512 * 1024;
+// Radius and power of an /explode without arguments, and the largest radius allowed
+const DEFAULT_EXPLOSION_RADIUS: f32 = 4.0;
+const DEFAULT_EXPLOSION_POWER: f32 = 10.0;
+const MAX_EXPLOSION_RADIUS: f32 = 16.0;
+// Furthest from the origin along any axis that commands reach, well within
+// what block and chunk coordinates can hold
+const MAX_COORDINATE: f32 = 30_000_000.0;
+// Ticks in a full day
+pub const DAY_LENGTH: u32 = 24000;
+/// Folder screenshots and timelapses are saved in.
+pub const SCREENSHOT_DIRECTORY: &str = "screenshots";
+/// Folder `/schem` saves and loads schematics in.
+pub const SCHEMATIC_DIRECTORY: &str = "schematics";
+
+/// Game state commands can read and change.
+pub struct CommandContext<'a> {
+    pub world: &'a mut World,
+    pub player_position: &'a mut Vec3,
+    /// Time of day in ticks, `0..DAY_LENGTH`.
+    pub time: &'a mut u32,
+    pub inventory: &'a mut Inventory,
+    /// Running timelapse capture, if any.
+    pub timelapse: &'a mut Option<Timelapse>,
+    pub selection: &'a mut Selection,
+    /// Where meshes and their baked ambient occlusion are cached.
+    pub mesh_cache: &'a MeshCache,
+    pub spawn: &'a mut SpawnPoint,
+    pub game_mode: &'a mut GameMode,
+    /// The block the player is looking at, if any.
+    pub target_block: Option<(i32, i32, i32)>,
+    /// Blocks changed by the command, so their chunks can be remeshed.
+    pub edited_blocks: Vec<(i32, i32, i32)>,
+    /// Explosions set off by the command. Their blocks are remeshed with their
+    /// particles, sound and knockback, rather than as `edited_blocks`.
+    pub explosions: Vec<Explosion>,
+    /// Mobs the command asked for, spawned once it's run.
+    pub spawned_mobs: Vec<(MobKind, Vec3)>,
+    /// Where the command asked to move the player, which happens once the
+    /// chunks around it have loaded.
+    pub teleport: Option<Vec3>,
+}
+
+struct Command {
+    usage: String,
+    handler: Handler,
+}
+
+/// Named commands that gameplay systems register handlers with.
+pub struct CommandRegistry {
+    commands: BTreeMap<String, Command>,
+}
+
+impl CommandRegistry {
+    pub fn new() -> Self {
+        Self {
+            commands: BTreeMap::new(),
+        }
+    }
+
+    /// Registers a command, replacing any previous one with the same name.
+    /// `usage` describes the arguments, e.g. `<x> <y> <z>`.
+    pub fn register(&mut self, name: &str, usage: &str,
+        handler: impl Fn(&mut CommandContext, &[&str]) -> CommandResult + 'static) {
+        self.commands.insert(name.to_string(), Command { usage: usage.to_string(), handler: Box::new(handler) });
+    }
+
+    /// Parses and runs a command line such as `/tp 0 80 0`. The leading slash is optional.
+    pub fn execute(&self, context: &mut CommandContext, line: &str) -> CommandResult {
+        let mut words = line.trim().trim_start_matches('/').split_whitespace();
+        let name = words.next().ok_or_else(|| text("console.empty", &[]))?;
+        let args = words.collect::<Vec<_>>();
+
+        if name == "help" {
+            return Ok(self.commands.iter()
+                .map(|(name, command)| format!("/{} {}", name, command.usage))
+                .collect::<Vec<_>>()
+                .join("\n"));
+        }
+
+        let command = self.commands.get(name).ok_or_else(|| text("console.unknown_command", &[&name]))?;
+        (command.handler)(context, &args)
+            .map_err(|e| text("console.usage", &[&e, &name, &command.usage]))
+    }
+
+    /// Registers the built-in commands.
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+        registry.register("tp", "<x> <y> <z>", teleport);
+        registry.register("time", "set <day|noon|night|midnight|ticks>", time);
+        registry.register("seed", "", |context, _| Ok(text("command.seed", &[&context.world.seed])));
+        registry.register("fill", "<x1> <y1> <z1> <x2> <y2> <z2> <block>", fill);
+        registry.register("give", "<block> [count]", give);
+        registry.register("timelapse", "<seconds|off>", timelapse);
+        registry.register("pos1", "[x y z]", |context, args| select_corner(context, args, 1));
+        registry.register("pos2", "[x y z]", |context, args| select_corner(context, args, 2));
+        registry.register("schem", "<save|load> <name>", schematic);
+        registry.register("export", "<name>", export);
+        registry.register("explode", "[radius] [power]", explode);
+        registry.register("spawnpoint", "[x y z|clear]", spawn_point);
+        registry.register("gamemode", "[survival|creative]", game_mode);
+        registry.register("worldborder", "[radius [x z]|off]", world_border);
+        registry
+    }
+}
+
+// Parses a coordinate, where `~` and `~offset` are relative to `current`.
+// Coordinates beyond MAX_COORDINATE are brought back to it
+fn coordinate(arg: &str, current: f32) -> Result<f32, String> {
+    let invalid = || text("command.invalid_coordinate", &[&arg]);
+    let value = match arg.strip_prefix('~') {
+        Some("") => current,
+        Some(offset) => offset.parse::<f32>().map(|offset| current + offset).map_err(|_| invalid())?,
+        None => arg.parse::<f32>().map_err(|_| invalid())?,
+    };
+    // "nan" and "inf" parse, but aren't anywhere in the world
+    if !value.is_finite() {
+        return Err(invalid());
+    }
+    Ok(value.clamp(-MAX_COORDINATE, MAX_COORDINATE))
+}
+
+fn position(args: &[&str], current: Vec3) -> Result<Vec3, String> {
+    match args {
+        [x, y, z] => Ok(Vec3::new(coordinate(x, current.x)?, coordinate(y, current.y)?, coordinate(z, current.z)?)),
+        _ => Err(text("command.expected_coordinates", &[])),
+    }
+}
+
+// A position as shown in messages
+fn show_position(position: Vec3) -> String {
+    format!("{:.1} {:.1} {:.1}", position.x, position.y, position.z)
+}
+
+// Same for a column, as x and z
+fn show_column(x: f32, z: f32) -> String {
+    format!("{:.1} {:.1}", x, z)
+}
+
+fn block_name(name: &str) -> Result<BlockType, String> {
+    BlockType::from_name(name).ok_or_else(|| text("command.unknown_block", &[&name]))
+}
+
+fn teleport(context: &mut CommandContext, args: &[&str]) -> CommandResult {
+    let target = position(args, *context.player_position)?;
+    context.teleport = Some(target);
+    Ok(text("command.tp.done", &[&show_position(target)]))
+}
+
+fn time(context: &mut CommandContext, args: &[&str]) -> CommandResult {
+    let ticks = match args {
+        ["set", "day"] => 1000,
+        ["set", "noon"] => 6000,
+        ["set", "night"] => 13000,
+        ["set", "midnight"] => 18000,
+        ["set", ticks] => ticks.parse::<u32>().map_err(|_| text("command.time.invalid", &[ticks]))?,
+        _ => return Err(text("command.time.expected", &[])),
+    };
+    *context.time = ticks % DAY_LENGTH;
+    Ok(text("command.time.done", &[&*context.time]))
+}
+
+fn fill(context: &mut CommandContext, args: &[&str]) -> CommandResult {
+    let [x1, y1, z1, x2, y2, z2, block] = args else {
+        return Err(text("command.fill.expected", &[]));
+    };
+    let current = *context.player_position;
+    let from = position(&[x1, y1, z1], current)?;
+    let to = position(&[x2, y2, z2], current)?;
+    let block = block_name(block)?;
+
+    let round = |v: f32| v.round() as i32;
+    let (min_x, max_x) = (round(from.x.min(to.x)), round(from.x.max(to.x)));
+    let (min_y, max_y) = (round(from.y.min(to.y)), round(from.y.max(to.y)));
+    let (min_z, max_z) = (round(from.z.min(to.z)), round(from.z.max(to.z)));
+    let volume = (max_x as i64 - min_x as i64 + 1) * (max_y as i64 - min_y as i64 + 1) * (max_z as i64 - min_z as i64 + 1);
+    if volume > MAX_FILL_VOLUME {
+        return Err(text("command.too_many_blocks", &[&volume, &MAX_FILL_VOLUME]));
+    }
+
+    let blocks = (min_x..=max_x)
+        .flat_map(|x| (min_y..=max_y).flat_map(move |y| (min_z..=max_z).map(move |z| ((x, y, z), block))));
+    context.edited_blocks = context.world.set_blocks(blocks);
+    Ok(text("command.fill.done", &[&context.edited_blocks.len(), &block.info().name]))
+}
+
+fn give(context: &mut CommandContext, args: &[&str]) -> CommandResult {
+    let (block, count) = match args {
+        [block] => (block_name(block)?, 1),
+        [block, count] => (block_name(block)?, count.parse::<u32>().map_err(|_| text("command.give.invalid_count", &[count]))?),
+        _ => return Err(text("command.give.expected", &[])),
+    };
+    if block == BlockType::Air {
+        return Err(text("command.give.air", &[]));
+    }
+    let left_over = context.inventory.add(block, count);
+    Ok(text("command.give.done", &[&(count - left_over), &block.info().name]))
+}
+
+fn timelapse(context: &mut CommandContext, args: &[&str]) -> CommandResult {
+    match args {
+        ["off"] => match context.timelapse.take() {
+            Some(_) => Ok(text("command.timelapse.stopped", &[])),
+            None => Err(text("command.timelapse.not_running", &[])),
+        },
+        [seconds] => {
+            let interval = seconds.parse::<f32>().ok()
+                .filter(|&interval| interval > 0.0)
+                .ok_or_else(|| text("command.timelapse.invalid", &[seconds]))?;
+            *context.timelapse = Some(Timelapse::start(Path::new(SCREENSHOT_DIRECTORY), interval));
+            Ok(text("command.timelapse.started", &[&interval]))
+        },
+        _ => Err(text("command.timelapse.expected", &[])),
+    }
+}
+
+// A file in one of the game's folders, given a name typed in the console.
+// `extension` is added to names without one.
+fn file_path(directory: &str, name: &str, extension: &str) -> Result<PathBuf, String> {
+    let valid = |c: char| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.');
+    if !name.chars().all(valid) || name.starts_with('.') {
+        return Err(text("command.invalid_name", &[&name]));
+    }
+    let mut path = Path::new(directory).join(name);
+    if path.extension().is_none() {
+        path.set_extension(extension);
+    }
+    Ok(path)
+}
+
+// Sets a selection corner to the given block, or else the targeted block, or
+// else the block the player is in
+fn select_corner(context: &mut CommandContext, args: &[&str], corner: u8) -> CommandResult {
+    let round = |v: Vec3| (v.x.round() as i32, v.y.round() as i32, v.z.round() as i32);
+    let block = match args {
+        [] => context.target_block.unwrap_or_else(|| round(*context.player_position)),
+        _ => round(position(args, *context.player_position)?),
+    };
+    let (key, slot) = match corner {
+        1 => ("command.pos1.done", &mut context.selection.first),
+        _ => ("command.pos2.done", &mut context.selection.second),
+    };
+    *slot = Some(block);
+
+    let mut message = text(key, &[&format!("{} {} {}", block.0, block.1, block.2)]);
+    if let Some((min, max)) = context.selection.bounds() {
+        let (x, y, z) = extents(min, max);
+        message += &format!(" ({}x{}x{})", x, y, z);
+    }
+    Ok(message)
+}
+
+// Size of the box between two corners, counted in i64 so corners far apart
+// can't overflow
+fn extents(min: (i32, i32, i32), max: (i32, i32, i32)) -> (i64, i64, i64) {
+    (max.0 as i64 - min.0 as i64 + 1, max.1 as i64 - min.1 as i64 + 1, max.2 as i64 - min.2 as i64 + 1)
+}
+
+// Saves the selection to, or pastes at the selection's first corner from,
+// a file in the schematic folder.
+fn schematic(context: &mut CommandContext, args: &[&str]) -> CommandResult {
+    let [action, name] = args else {
+        return Err(text("command.schem.expected", &[]));
+    };
+    let path = file_path(SCHEMATIC_DIRECTORY, name, "schem")?;
+    let volume = |(x, y, z): (i64, i64, i64)| x * y * z;
+    let schematic_volume = |(x, y, z): (i32, i32, i32)| volume((x as i64, y as i64, z as i64));
+
+    match *action {
+        "save" => {
+            let (min, max) = context.selection.bounds().ok_or_else(|| text("command.schem.no_selection", &[]))?;
+            let size = volume(extents(min, max));
+            if size > MAX_SCHEMATIC_VOLUME {
+                return Err(text("command.too_many_blocks", &[&size, &MAX_SCHEMATIC_VOLUME]));
+            }
+            Schematic::copy(context.world, min, max).save(&path)?;
+            Ok(text("command.schem.saved", &[&path.display()]))
+        },
+        "load" => {
+            let origin = context.selection.first.ok_or_else(|| text("command.schem.no_corner", &[]))?;
+            let schematic = Schematic::load(&path)?;
+            if schematic_volume(schematic.size()) > MAX_SCHEMATIC_VOLUME {
+                return Err(text("command.too_many_blocks", &[&schematic_volume(schematic.size()), &MAX_SCHEMATIC_VOLUME]));
+            }
+            context.edited_blocks = schematic.paste(context.world, origin);
+            let (x, y, z) = schematic.size();
+            let (size, at) = (format!("{}x{}x{}", x, y, z), format!("{} {} {}", origin.0, origin.1, origin.2));
+            Ok(text("command.schem.pasted", &[&path.display(), &size, &at]))
+        },
+        _ => Err(text("command.schem.unknown_action", &[action])),
+    }
+}
+
+// Writes the meshes of the selection, or of every loaded chunk without one,
+// to an OBJ or glTF file in the export folder
+fn export(context: &mut CommandContext, args: &[&str]) -> CommandResult {
+    let [name] = args else {
+        return Err(text("command.export.expected", &[]));
+    };
+    let path = file_path(EXPORT_DIRECTORY, name, "obj")?;
+    if !path.extension().is_some_and(|extension| extension == "obj" || extension == "gltf") {
+        return Err(text("command.export.format", &[]));
+    }
+    // Far chunks only have their mesh on the GPU until they're meshed again
+    context.world.rebuild_evicted_meshes(context.mesh_cache);
+    let triangles = export_meshes(context.world, context.selection.bounds(), context.mesh_cache, &path)?;
+    Ok(text("command.export.done", &[&triangles, &path.display()]))
+}
+
+// Sets off an explosion at the targeted block, or else at the player
+fn explode(context: &mut CommandContext, args: &[&str]) -> CommandResult {
+    let number = |arg: &str, invalid: &str| arg.parse::<f32>().ok()
+        .filter(|&value| value > 0.0)
+        .ok_or_else(|| text(invalid, &[&arg]));
+    let (radius, power) = match args {
+        [] => (DEFAULT_EXPLOSION_RADIUS, DEFAULT_EXPLOSION_POWER),
+        [radius] => (number(radius, "command.explode.invalid_radius")?, DEFAULT_EXPLOSION_POWER),
+        [radius, power] => (number(radius, "command.explode.invalid_radius")?, number(power, "command.explode.invalid_power")?),
+        _ => return Err(text("command.explode.expected", &[])),
+    };
+    if radius > MAX_EXPLOSION_RADIUS {
+        return Err(text("command.explode.too_large", &[&radius, &MAX_EXPLOSION_RADIUS]));
+    }
+
+    let center = match context.target_block {
+        Some((x, y, z)) => Vec3::new(x as f32, y as f32, z as f32),
+        None => *context.player_position,
+    };
+    let explosion = context.world.explode(center, radius, power);
+    let message = text("command.explode.done", &[&show_position(center), &explosion.removed.len()]);
+    context.explosions.push(explosion);
+    Ok(message)
+}
+
+// Sets where the player respawns, at their feet or at the given feet position,
+// or clears it to respawn at the world's spawn
+fn spawn_point(context: &mut CommandContext, args: &[&str]) -> CommandResult {
+    let feet = *context.player_position - Vec3::new(0.0, EYE_HEIGHT, 0.0);
+    let respawn = match args {
+        ["clear"] => {
+            context.spawn.respawn = None;
+            let spawn = context.spawn.world_spawn;
+            return Ok(text("command.spawnpoint.cleared", &[&show_position(spawn)]));
+        },
+        [] => feet,
+        _ => position(args, feet)?,
+    };
+    context.spawn.respawn = Some(respawn);
+    Ok(text("command.spawnpoint.done", &[&show_position(respawn)]))
+}
+
+fn game_mode(context: &mut CommandContext, args: &[&str]) -> CommandResult {
+    let [name] = args else {
+        return Ok(text("command.gamemode.current", &[&context.game_mode.name()]));
+    };
+    let mode = GameMode::from_name(name).ok_or_else(|| text("command.gamemode.unknown", &[name]))?;
+    *context.game_mode = mode;
+    log::info!(target: "game", "Game mode: {}", mode.name());
+    Ok(text("command.gamemode.done", &[&mode.name()]))
+}
+
+// Shows the world border, removes it, or sets it around the given column or
+// around the player
+fn world_border(context: &mut CommandContext, args: &[&str]) -> CommandResult {
+    let player = *context.player_position;
+    let (radius, center_x, center_z) = match args {
+        [] => {
+            return Ok(match context.world.border {
+                Some(border) => text("command.worldborder.current", &[&format!("{:.1}", border.radius), &show_column(border.center_x, border.center_z)]),
+                None => text("command.worldborder.none", &[]),
+            });
+        },
+        ["off"] => {
+            context.world.border = None;
+            return Ok(text("command.worldborder.removed", &[]));
+        },
+        [radius] => (*radius, player.x, player.z),
+        [radius, x, z] => (*radius, coordinate(x, player.x)?, coordinate(z, player.z)?),
+        _ => return Err(text("command.worldborder.expected", &[])),
+    };
+    let radius = radius.parse::<f32>().ok()
+        .filter(|&radius| radius >= MIN_BORDER_RADIUS)
+        .ok_or_else(|| text("command.worldborder.invalid", &[&radius, &MIN_BORDER_RADIUS]))?;
+    let border = WorldBorder::new(center_x, center_z, radius);
+    context.world.border = Some(border);
+    Ok(text("command.worldborder.done", &[&format!("{:.1}", radius), &show_column(center_x, center_z)]))
+}
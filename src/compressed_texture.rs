@@ -0,0 +1,144 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Container signatures
+const DDS_MAGIC: &[u8; 4] = b"DDS ";
+const KTX2_IDENTIFIER: [u8; 12] = [0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A];
+// DDS files start with the magic and a 124-byte header, optionally followed
+// by a 20-byte DX10 extension naming the format
+const DDS_HEADER_END: usize = 128;
+const DDS_DX10_HEADER_END: usize = 148;
+// DXGI formats of the DX10 extension
+const DXGI_BC1_UNORM: u32 = 71;
+const DXGI_BC1_UNORM_SRGB: u32 = 72;
+const DXGI_BC3_UNORM: u32 = 77;
+const DXGI_BC3_UNORM_SRGB: u32 = 78;
+// KTX2 files have a fixed header, then one 24-byte entry per mip level
+const KTX2_LEVEL_INDEX: usize = 80;
+// Vulkan formats of KTX2 files
+const VK_FORMAT_BC1_RGB_UNORM_BLOCK: u32 = 131;
+const VK_FORMAT_BC1_RGBA_SRGB_BLOCK: u32 = 134;
+const VK_FORMAT_BC3_UNORM_BLOCK: u32 = 137;
+const VK_FORMAT_BC3_SRGB_BLOCK: u32 = 138;
+
+/// Block compression formats GPUs decode themselves, in 4 by 4 pixel blocks.
+/// Colors are taken as they are, like PNG textures, even when a file marks
+/// them as sRGB.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockFormat {
+    /// DXT1: 8 bytes per block, with at most a cut-out alpha.
+    Bc1,
+    /// DXT5: 16 bytes per block, with smooth alpha.
+    Bc3,
+}
+
+impl BlockFormat {
+    /// Bytes taken by one level of a texture this size.
+    pub fn level_size(self, width: u32, height: u32) -> usize {
+        let block_bytes = match self {
+            BlockFormat::Bc1 => 8,
+            BlockFormat::Bc3 => 16,
+        };
+        width.div_ceil(4).max(1) as usize * height.div_ceil(4).max(1) as usize * block_bytes
+    }
+}
+
+/// A block-compressed texture read from a DDS or KTX2 file, with whatever
+/// mip levels it came with, largest first.
+pub struct CompressedImage {
+    pub format: BlockFormat,
+    pub width: u32,
+    pub height: u32,
+    pub levels: Vec<Vec<u8>>,
+}
+
+impl CompressedImage {
+    /// Reads a DDS or KTX2 file, telling them apart by their contents.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let data = fs::read(path).map_err(|e| e.to_string())?;
+        if data.starts_with(DDS_MAGIC) {
+            parse_dds(&data)
+        } else if data.starts_with(&KTX2_IDENTIFIER) {
+            parse_ktx2(&data)
+        } else {
+            Err("Not a DDS or KTX2 file".to_string())
+        }
+    }
+}
+
+/// A compressed file standing in for an image, one with the same name and a
+/// `.ktx2` or `.dds` extension, if there is one.
+pub fn compressed_version(image_path: &Path) -> Option<PathBuf> {
+    ["ktx2", "dds"].iter()
+        .map(|extension| image_path.with_extension(extension))
+        .find(|path| path.is_file())
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Result<u32, String> {
+    let bytes = data.get(offset..offset + 4).ok_or("Truncated header")?;
+    Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn u64_at(data: &[u8], offset: usize) -> Result<usize, String> {
+    let bytes = data.get(offset..offset + 8).ok_or("Truncated header")?;
+    usize::try_from(u64::from_le_bytes(bytes.try_into().unwrap())).map_err(|e| e.to_string())
+}
+
+fn parse_dds(data: &[u8]) -> Result<CompressedImage, String> {
+    let height = u32_at(data, 12)?;
+    let width = u32_at(data, 16)?;
+    let level_count = u32_at(data, 28)?.max(1);
+    let (format, data_start) = match data.get(84..88).ok_or("Truncated header")? {
+        b"DXT1" => (BlockFormat::Bc1, DDS_HEADER_END),
+        b"DXT5" => (BlockFormat::Bc3, DDS_HEADER_END),
+        b"DX10" => match u32_at(data, DDS_HEADER_END)? {
+            DXGI_BC1_UNORM | DXGI_BC1_UNORM_SRGB => (BlockFormat::Bc1, DDS_DX10_HEADER_END),
+            DXGI_BC3_UNORM | DXGI_BC3_UNORM_SRGB => (BlockFormat::Bc3, DDS_DX10_HEADER_END),
+            other => return Err(format!("Unsupported DXGI format {}", other)),
+        },
+        other => return Err(format!("Unsupported DDS format {}", String::from_utf8_lossy(other))),
+    };
+
+    // Levels follow each other, each half the size of the one before
+    let mut levels = Vec::new();
+    let mut offset = data_start;
+    for level in 0..level_count {
+        let size = format.level_size((width >> level).max(1), (height >> level).max(1));
+        let bytes = data.get(offset..offset + size).ok_or("Truncated texture data")?;
+        levels.push(bytes.to_vec());
+        offset += size;
+    }
+    Ok(CompressedImage { format, width, height, levels })
+}
+
+fn parse_ktx2(data: &[u8]) -> Result<CompressedImage, String> {
+    let format = match u32_at(data, 12)? {
+        VK_FORMAT_BC1_RGB_UNORM_BLOCK..=VK_FORMAT_BC1_RGBA_SRGB_BLOCK => BlockFormat::Bc1,
+        VK_FORMAT_BC3_UNORM_BLOCK | VK_FORMAT_BC3_SRGB_BLOCK => BlockFormat::Bc3,
+        other => return Err(format!("Unsupported Vulkan format {}", other)),
+    };
+    let width = u32_at(data, 20)?;
+    let height = u32_at(data, 24)?;
+    if u32_at(data, 28)? > 1 || u32_at(data, 32)? > 1 || u32_at(data, 36)? > 1 {
+        return Err("Only flat 2D textures are supported".to_string());
+    }
+    // A count of 0 asks for the levels to be generated, which isn't done here
+    let level_count = u32_at(data, 40)?.max(1);
+    if u32_at(data, 44)? != 0 {
+        return Err("Supercompressed textures are not supported".to_string());
+    }
+
+    // The index gives each level's place in the file, smallest level last
+    let mut levels = Vec::new();
+    for level in 0..level_count {
+        let entry = KTX2_LEVEL_INDEX + level as usize * 24;
+        let (offset, length) = (u64_at(data, entry)?, u64_at(data, entry + 8)?);
+        let expected = format.level_size((width >> level).max(1), (height >> level).max(1));
+        if length != expected {
+            return Err(format!("Level {} holds {} bytes, expected {}", level, length, expected));
+        }
+        let bytes = data.get(offset..offset + length).ok_or("Truncated texture data")?;
+        levels.push(bytes.to_vec());
+    }
+    Ok(CompressedImage { format, width, height, levels })
+}
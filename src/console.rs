@@ -0,0 +1,120 @@
+use crate::commands::{CommandContext, CommandRegistry};
+use crate::hud::Hud;
+
+// Lines kept in the scrollback
+const MAX_LOG_LINES: usize = 100;
+// Share of the screen height covered by the console
+const HEIGHT_FRACTION: f32 = 0.4;
+// Height of a line of text, in pixels
+const LINE_HEIGHT: f32 = 16.0;
+const MARGIN: f32 = 8.0;
+
+const BACKGROUND_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.7];
+const TEXT_COLOR: [f32; 4] = [0.9, 0.9, 0.9, 1.0];
+
+/// Drop-down console where commands are typed and their output is shown.
+pub struct Console {
+    open: bool,
+    input: String,
+    // Output lines, with whether each one reports an error
+    log: Vec<(String, bool)>,
+    // Previously run commands, oldest first
+    history: Vec<String>,
+    // Position while browsing the history, None when editing a new line
+    history_index: Option<usize>,
+}
+
+impl Console {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            input: String::new(),
+            log: Vec::new(),
+            history: Vec::new(),
+            history_index: None,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn set_open(&mut self, open: bool) {
+        self.open = open;
+    }
+
+    /// Appends typed text to the input line.
+    pub fn type_text(&mut self, text: &str) {
+        // The key that toggles the console also produces text
+        self.input.extend(text.chars().filter(|&c| c != '`'));
+    }
+
+    pub fn backspace(&mut self) {
+        self.input.pop();
+    }
+
+    /// Replaces the input line with an older (`-1`) or newer (`1`) history entry.
+    pub fn browse_history(&mut self, direction: i32) {
+        if self.history.is_empty() {
+            return;
+        }
+        let index = match (self.history_index, direction < 0) {
+            (None, true) => Some(self.history.len() - 1),
+            (None, false) => None,
+            (Some(index), true) => Some(index.saturating_sub(1)),
+            (Some(index), false) if index + 1 < self.history.len() => Some(index + 1),
+            (Some(_), false) => None,
+        };
+        self.history_index = index;
+        self.input = index.map_or_else(String::new, |index| self.history[index].clone());
+    }
+
+    /// Adds a line of output.
+    pub fn print(&mut self, text: &str, error: bool) {
+        for line in text.lines() {
+            self.log.push((line.to_string(), error));
+        }
+        if self.log.len() > MAX_LOG_LINES {
+            self.log.drain(..self.log.len() - MAX_LOG_LINES);
+        }
+    }
+
+    /// Runs the input line through the command registry and logs the result.
+    pub fn submit(&mut self, commands: &CommandRegistry, context: &mut CommandContext) {
+        let line = std::mem::take(&mut self.input);
+        self.history_index = None;
+        if line.trim().is_empty() {
+            return;
+        }
+
+        self.print(&format!("> {}", line), false);
+        match commands.execute(context, &line) {
+            Ok(message) => self.print(&message, false),
+            Err(message) => self.print(&message, true),
+        }
+        self.history.push(line);
+    }
+
+    /// Queues the console at the top of the screen, if it is open.
+    pub fn draw(&self, hud: &mut Hud, screen_width: f32, screen_height: f32) {
+        if !self.open {
+            return;
+        }
+
+        let height = (screen_height * HEIGHT_FRACTION).floor();
+        hud.rect(0.0, 0.0, screen_width, height, BACKGROUND_COLOR);
+
+        // Input line at the bottom, with the most recent output above it
+        let input_y = height - MARGIN - LINE_HEIGHT;
+        hud.draw_text(MARGIN, input_y, LINE_HEIGHT, hud.palette.colors().input, &format!("> {}_", self.input));
+
+        let mut y = input_y - LINE_HEIGHT;
+        for (line, error) in self.log.iter().rev() {
+            if y < MARGIN {
+                break;
+            }
+            hud.draw_text(MARGIN, y, LINE_HEIGHT, if *error { hud.palette.colors().error } else { TEXT_COLOR }, line);
+            y -= LINE_HEIGHT;
+        }
+    }
+}
@@ -0,0 +1,259 @@
+use crate::block::BlockType;
+use crate::inventory::{ItemStack, MAX_STACK};
+use crate::json::Json;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where the built-in recipes are read from, one `.json` file each.
+pub const RECIPE_DIRECTORY: &str = "src/assets/recipes";
+/// Where packs are installed, one folder each. A pack's `recipes` folder adds
+/// recipes, and replaces built-in ones with the same name.
+pub const PACK_DIRECTORY: &str = "packs";
+/// Slots along each side of the crafting grid.
+pub const GRID_SIZE: usize = 3;
+
+/// What a recipe takes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ingredients {
+    /// Blocks laid out in this shape anywhere on the grid, or mirrored. Rows
+    /// run top to bottom, and `None` is an empty slot.
+    Shaped(Vec<Vec<Option<BlockType>>>),
+    /// These blocks anywhere on the grid, in any order.
+    Shapeless(Vec<BlockType>),
+}
+
+/// Blocks on the crafting grid that make another block.
+///
+/// Recipes are JSON objects with a `result` of `{"block": name, "count": n}`
+/// (count defaulting to 1), and either a `pattern` of rows with a `key` from
+/// the pattern's characters to block names, spaces being empty slots, or a
+/// list of `ingredients`:
+///
+/// ```json
+/// {"pattern": ["SSS"], "key": {"S": "stone"}, "result": {"block": "smooth_stone_slab", "count": 6}}
+/// {"ingredients": ["grass"], "result": {"block": "dirt"}}
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recipe {
+    pub ingredients: Ingredients,
+    pub result: ItemStack,
+}
+
+impl Recipe {
+    pub fn parse(text: &str) -> Result<Recipe, String> {
+        let json = Json::parse(text)?;
+        let block = |value: &Json| -> Result<BlockType, String> {
+            let name = value.as_str().ok_or("Blocks are given by name")?;
+            match BlockType::from_name(name) {
+                Some(BlockType::Air) | None => Err(format!("Unknown block: {}", name)),
+                Some(block) => Ok(block),
+            }
+        };
+
+        let result = json.get("result").ok_or("Missing `result`")?;
+        let count = match result.get("count") {
+            Some(count) => count.as_f64()
+                .filter(|&count| count.fract() == 0.0 && (1.0..=MAX_STACK as f64).contains(&count))
+                .ok_or_else(|| format!("The count must be from 1 to {}", MAX_STACK))? as u32,
+            None => 1,
+        };
+        let result = ItemStack { block: block(result.get("block").ok_or("Missing the result's `block`")?)?, count };
+
+        let ingredients = match (json.get("pattern"), json.get("ingredients")) {
+            (Some(pattern), None) => {
+                let key = json.get("key").and_then(Json::as_object).ok_or("Shaped recipes need a `key`")?;
+                let key = key.iter()
+                    .map(|(symbol, value)| {
+                        let mut chars = symbol.chars();
+                        match (chars.next(), chars.next()) {
+                            (Some(' '), None) => Err("Spaces are empty slots, not keys".to_string()),
+                            (Some(symbol), None) => Ok((symbol, block(value)?)),
+                            _ => Err(format!("Keys are a single character: {}", symbol)),
+                        }
+                    })
+                    .collect::<Result<BTreeMap<_, _>, _>>()?;
+                let rows = pattern.as_array().ok_or("The `pattern` is a list of rows")?
+                    .iter()
+                    .map(|row| {
+                        row.as_str().ok_or("Pattern rows are strings")?
+                            .chars()
+                            .map(|symbol| match symbol {
+                                ' ' => Ok(None),
+                                _ => key.get(&symbol).copied().map(Some).ok_or(format!("`{}` isn't in the key", symbol)),
+                            })
+                            .collect::<Result<Vec<_>, _>>()
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ingredients::Shaped(rows)
+            },
+            (None, Some(ingredients)) => Ingredients::Shapeless(
+                ingredients.as_array().ok_or("The `ingredients` are a list of blocks")?
+                    .iter()
+                    .map(block)
+                    .collect::<Result<_, _>>()?,
+            ),
+            (Some(_), Some(_)) => return Err("A recipe has a `pattern` or `ingredients`, not both".to_string()),
+            (None, None) => return Err("Missing a `pattern` or `ingredients`".to_string()),
+        };
+
+        let recipe = Recipe { ingredients, result };
+        recipe.validate()?;
+        Ok(recipe)
+    }
+
+    // Checks the recipe fits on the grid and takes something
+    fn validate(&self) -> Result<(), String> {
+        match &self.ingredients {
+            Ingredients::Shaped(rows) => {
+                let width = rows.first().map_or(0, Vec::len);
+                if rows.iter().any(|row| row.len() != width) {
+                    return Err("Pattern rows must all be the same width".to_string());
+                }
+                if rows.len() > GRID_SIZE || width > GRID_SIZE {
+                    return Err(format!("Patterns are at most {}x{}", GRID_SIZE, GRID_SIZE));
+                }
+                if rows.iter().flatten().all(Option::is_none) {
+                    return Err("The pattern is empty".to_string());
+                }
+                if trim(rows.clone()) != *rows {
+                    return Err("The pattern has empty rows or columns around it".to_string());
+                }
+            },
+            Ingredients::Shapeless(blocks) => {
+                if blocks.is_empty() || blocks.len() > GRID_SIZE * GRID_SIZE {
+                    return Err(format!("Recipes take from 1 to {} ingredients", GRID_SIZE * GRID_SIZE));
+                }
+            },
+        }
+        Ok(())
+    }
+
+    /// Whether the blocks on the grid, row by row, make this recipe.
+    pub fn matches(&self, grid: &[Option<BlockType>]) -> bool {
+        match &self.ingredients {
+            Ingredients::Shaped(pattern) => {
+                let rows = trim(grid.chunks(GRID_SIZE).map(<[_]>::to_vec).collect());
+                let mirrored: Vec<Vec<_>> = pattern.iter().map(|row| row.iter().rev().copied().collect()).collect();
+                rows == *pattern || rows == mirrored
+            },
+            Ingredients::Shapeless(blocks) => {
+                let mut wanted = blocks.clone();
+                let mut placed: Vec<_> = grid.iter().flatten().copied().collect();
+                wanted.sort_by_key(|block| block.id());
+                placed.sort_by_key(|block| block.id());
+                wanted == placed
+            },
+        }
+    }
+}
+
+// Cuts the empty rows and columns around a grid's blocks
+fn trim(rows: Vec<Vec<Option<BlockType>>>) -> Vec<Vec<Option<BlockType>>> {
+    let filled = |row: &Vec<Option<BlockType>>| row.iter().any(Option::is_some);
+    let Some(top) = rows.iter().position(filled) else {
+        return Vec::new();
+    };
+    let bottom = rows.iter().rposition(filled).unwrap_or(top);
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let column_filled = |column: &usize| rows.iter().any(|row| row.get(*column).copied().flatten().is_some());
+    let left = (0..width).find(column_filled).unwrap_or(0);
+    let right = (0..width).rev().find(column_filled).unwrap_or(left);
+    rows[top..=bottom].iter().map(|row| row[left..=right].to_vec()).collect()
+}
+
+/// Every known recipe by name: the built-in ones, then those of packs.
+#[derive(Default)]
+pub struct RecipeRegistry {
+    recipes: BTreeMap<String, Recipe>,
+}
+
+impl RecipeRegistry {
+    /// The built-in recipes, extended by every installed pack.
+    pub fn load() -> Self {
+        let mut registry = Self::default();
+        for directory in Self::directories() {
+            registry.load_directory(&directory);
+        }
+        log::info!(target: "game", "Loaded {} recipes", registry.recipes.len());
+        registry
+    }
+
+    /// The folders `load` reads, in order: the built-in recipes, then those of
+    /// each installed pack by name.
+    pub fn directories() -> Vec<PathBuf> {
+        let mut packs: Vec<_> = fs::read_dir(PACK_DIRECTORY).into_iter().flatten().flatten().map(|entry| entry.path()).collect();
+        packs.sort();
+        std::iter::once(PathBuf::from(RECIPE_DIRECTORY))
+            .chain(packs.into_iter().map(|pack| pack.join("recipes")))
+            .collect()
+    }
+
+    /// Reads every recipe in a directory, named after its file. Recipes that
+    /// can't be read or clash with another are left out.
+    pub fn load_directory(&mut self, directory: &Path) {
+        let mut paths: Vec<_> = fs::read_dir(directory)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "json"))
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            let result = fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|text| Recipe::parse(&text))
+                .and_then(|recipe| self.register(name, recipe));
+            if let Err(e) = result {
+                log::warn!(target: "game", "Skipping recipe {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    /// Adds a recipe, replacing any of the same name. Fails when another
+    /// recipe takes the same ingredients, so the grid is never ambiguous.
+    pub fn register(&mut self, name: &str, recipe: Recipe) -> Result<(), String> {
+        recipe.validate()?;
+        let clash = self.recipes.iter().find(|&(other, existing)| other != name && clashes(existing, &recipe));
+        if let Some((other, _)) = clash {
+            return Err(format!("Takes the same ingredients as {}", other));
+        }
+        if self.recipes.insert(name.to_string(), recipe).is_some() {
+            log::debug!(target: "game", "Recipe {} replaced", name);
+        }
+        Ok(())
+    }
+
+    /// The recipe the blocks on the grid, row by row, make, if any.
+    pub fn find(&self, grid: &[Option<BlockType>]) -> Option<&Recipe> {
+        self.recipes.values().find(|recipe| recipe.matches(grid))
+    }
+}
+
+// Whether a grid laid out for one recipe would make the other
+fn clashes(a: &Recipe, b: &Recipe) -> bool {
+    let layout = |recipe: &Recipe| -> Vec<Option<BlockType>> {
+        let mut grid = vec![None; GRID_SIZE * GRID_SIZE];
+        match &recipe.ingredients {
+            Ingredients::Shaped(rows) => {
+                for (y, row) in rows.iter().enumerate() {
+                    for (x, &block) in row.iter().enumerate() {
+                        grid[y * GRID_SIZE + x] = block;
+                    }
+                }
+            },
+            Ingredients::Shapeless(blocks) => {
+                for (slot, &block) in blocks.iter().enumerate() {
+                    grid[slot] = Some(block);
+                }
+            },
+        }
+        grid
+    };
+    b.matches(&layout(a)) || a.matches(&layout(b))
+}
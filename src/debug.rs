@@ -0,0 +1,185 @@
+use crate::debug_lines::DebugLines;
+use crate::hud::Hud;
+use crate::math::Vec3;
+use crate::renderer::Renderer;
+use crate::streaming::{column_bounds, ChunkStreamer};
+use crate::world::{World, CHUNK_SIZE, WORLD_HEIGHT_CHUNKS};
+
+// Height of a line of text, in pixels
+const LINE_HEIGHT: f32 = 16.0;
+// Background showing on each side of a line
+const PADDING: f32 = 2.0;
+const MARGIN: f32 = 6.0;
+
+const BACKGROUND_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.5];
+const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+// Columns outlined on each side of the camera's column
+const CHUNK_BORDER_RADIUS: i32 = 1;
+// Columns on each side of the camera's column with chunk overlay boxes
+const CHUNK_OVERLAY_RADIUS: i32 = 4;
+// Boxes are this much smaller than their chunk on each side, so neighbours'
+// faces don't fight
+const CHUNK_OVERLAY_INSET: f32 = 0.25;
+const STATE_ALPHA: f32 = 0.2;
+// See-through of the cheapest and the costliest meshes of the heatmap
+const COST_ALPHA: (f32, f32) = (0.1, 0.45);
+
+/// Boxes over the chunks around the camera, cycled with F8, showing where
+/// streaming and meshing spend their time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChunkOverlay {
+    #[default]
+    Off,
+    /// Each chunk colored by how far along it is: waiting to generate,
+    /// meshed but waiting to be uploaded, drawn, or edited since it was saved.
+    State,
+    /// Each chunk colored from cheap to costly by the vertices in its mesh,
+    /// against the costliest one around.
+    Cost,
+}
+
+impl ChunkOverlay {
+    /// The overlay after this one, wrapping back to none.
+    pub fn next(self) -> Self {
+        match self {
+            ChunkOverlay::Off => ChunkOverlay::State,
+            ChunkOverlay::State => ChunkOverlay::Cost,
+            ChunkOverlay::Cost => ChunkOverlay::Off,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ChunkOverlay::Off => "off",
+            ChunkOverlay::State => "chunk state",
+            ChunkOverlay::Cost => "mesh cost",
+        }
+    }
+}
+
+/// Render modes for seeing how the world is built, toggled with function keys.
+#[derive(Default)]
+pub struct DebugModes {
+    /// F4: polygons drawn as outlines.
+    pub wireframe: bool,
+    /// F5: outlines of the chunks around the camera.
+    pub chunk_borders: bool,
+    /// F8: boxes over the chunks around the camera.
+    pub chunk_overlay: ChunkOverlay,
+}
+
+/// Queues the outlines of every chunk in the columns around `position`, with
+/// the chunk it is in highlighted.
+pub fn queue_chunk_borders(lines: &mut DebugLines, position: Vec3) {
+    let size = CHUNK_SIZE as f32;
+    // Blocks are centered on whole coordinates, so chunks start half a block before their first one
+    let chunk_of = |value: f32| ((value + 0.5) / size).floor() as i32;
+    let current = (chunk_of(position.x), chunk_of(position.y), chunk_of(position.z));
+    let chunk_min = |(x, y, z): (i32, i32, i32)| Vec3::new(x as f32 * size - 0.5, y as f32 * size - 0.5, z as f32 * size - 0.5);
+    let extent = Vec3::new(size, size, size);
+
+    // Queued first so its edges win the depth test where they overlap a neighbour's
+    lines.cuboid(chunk_min(current), chunk_min(current) + extent, lines.palette.colors().current_chunk);
+    for x in current.0 - CHUNK_BORDER_RADIUS..=current.0 + CHUNK_BORDER_RADIUS {
+        for z in current.2 - CHUNK_BORDER_RADIUS..=current.2 + CHUNK_BORDER_RADIUS {
+            for y in (0..WORLD_HEIGHT_CHUNKS).filter(|&y| (x, y, z) != current) {
+                lines.cuboid(chunk_min((x, y, z)), chunk_min((x, y, z)) + extent, lines.palette.colors().chunk_border);
+            }
+        }
+    }
+}
+
+/// Queues the boxes of a chunk overlay over the chunks in the columns around
+/// `position`. Chunks with nothing to draw are left out unless they were
+/// edited, and columns waiting to generate get one box for the whole column.
+pub fn queue_chunk_overlay(lines: &mut DebugLines, overlay: ChunkOverlay, world: &World, streamer: &ChunkStreamer, renderer: &dyn Renderer, position: Vec3) {
+    if overlay == ChunkOverlay::Off {
+        return;
+    }
+    let colors = lines.palette.colors();
+    let size = CHUNK_SIZE as f32;
+    let column_of = |value: f32| ((value + 0.5) / size).floor() as i32;
+    let (center_x, center_z) = (column_of(position.x), column_of(position.z));
+    let near = |x: i32, z: i32| (x - center_x).abs() <= CHUNK_OVERLAY_RADIUS && (z - center_z).abs() <= CHUNK_OVERLAY_RADIUS;
+    let inset = Vec3::new(CHUNK_OVERLAY_INSET, CHUNK_OVERLAY_INSET, CHUNK_OVERLAY_INSET);
+    let chunk_min = |(x, y, z): (i32, i32, i32)| Vec3::new(x as f32 * size - 0.5, y as f32 * size - 0.5, z as f32 * size - 0.5);
+    let extent = Vec3::new(size, size, size);
+
+    let chunks = world.chunks()
+        .filter(|chunk| near(chunk.position.0, chunk.position.2))
+        .filter(|chunk| !chunk.mesh.vertices.is_empty() || world.is_unsaved(chunk.position))
+        .collect::<Vec<_>>();
+    let costliest = chunks.iter().map(|chunk| chunk.mesh.vertices.len()).max().unwrap_or(0).max(1);
+    for chunk in chunks {
+        let position = chunk.position;
+        let color = match overlay {
+            ChunkOverlay::State => {
+                let [r, g, b] = if world.is_unsaved(position) {
+                    colors.dirty
+                } else if renderer.is_upload_queued(position) {
+                    colors.meshed
+                } else {
+                    colors.uploaded
+                };
+                [r, g, b, STATE_ALPHA]
+            },
+            _ => {
+                let cost = chunk.mesh.vertices.len() as f32 / costliest as f32;
+                let [low, high] = [colors.cost_low, colors.cost_high];
+                let mix = |channel: usize| low[channel] + (high[channel] - low[channel]) * cost;
+                [mix(0), mix(1), mix(2), COST_ALPHA.0 + (COST_ALPHA.1 - COST_ALPHA.0) * cost]
+            },
+        };
+        lines.filled_cuboid(chunk_min(position) + inset, chunk_min(position) + extent - inset, color);
+    }
+    if overlay == ChunkOverlay::State {
+        let [r, g, b] = colors.queued;
+        for (x, z) in streamer.queued_columns().filter(|&(x, z)| near(x, z)) {
+            let (min, max) = column_bounds(x, z);
+            lines.filled_cuboid(min + inset, max - inset, [r, g, b, STATE_ALPHA]);
+        }
+    }
+}
+
+/// Lines of diagnostics in the top left corner, toggled with F3.
+pub struct DebugOverlay {
+    visible: bool,
+    // Frames counted over the last full second
+    fps: u32,
+}
+
+impl DebugOverlay {
+    pub fn new() -> Self {
+        Self { visible: false, fps: 0 }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    pub fn fps(&self) -> u32 {
+        self.fps
+    }
+
+    pub fn set_fps(&mut self, fps: u32) {
+        self.fps = fps;
+    }
+
+    /// Queues the given lines, each on a dark background, if the overlay is visible.
+    pub fn draw(&self, hud: &mut Hud, lines: &[String]) {
+        if !self.visible {
+            return;
+        }
+        for (index, line) in lines.iter().enumerate() {
+            let y = MARGIN + index as f32 * LINE_HEIGHT;
+            let width = hud.measure_text(LINE_HEIGHT, line) + 2.0 * PADDING;
+            hud.rect(MARGIN - PADDING, y, width, LINE_HEIGHT, BACKGROUND_COLOR);
+            hud.draw_text(MARGIN, y, LINE_HEIGHT, TEXT_COLOR, line);
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use crate::gl_utils::{buffer_data, Buffer, BufferType, GlState, ShaderProgram, VertexArray};
+use crate::math::Vec3;
+use crate::palette::Palette;
+
+type LineVertex = [f32; 7];  // x, y, z, r, g, b, a
+
+// Corners of a box, by which of x, y and z are at the max, two triangles per
+// face wound counter-clockwise seen from outside
+const BOX_TRIANGLES: [usize; 36] = [
+    0, 2, 3, 0, 3, 1, // -z
+    4, 5, 7, 4, 7, 6, // +z
+    0, 4, 6, 0, 6, 2, // -x
+    1, 3, 7, 1, 7, 5, // +x
+    0, 1, 5, 0, 5, 4, // -y
+    2, 6, 7, 2, 7, 3, // +y
+];
+
+/// Colored lines and translucent boxes drawn in the world for debugging,
+/// collected over a frame and drawn in one batch.
+pub struct DebugLines {
+    program: ShaderProgram,
+    vao: VertexArray,
+    vertices: Buffer,
+    batch: Vec<LineVertex>,
+    // Triangles of the boxes, drawn before the lines
+    triangles: Vec<LineVertex>,
+    /// Colors of the highlights and debug views queued on it.
+    pub palette: Palette,
+}
+
+impl DebugLines {
+    pub fn new(program: ShaderProgram) -> Option<Self> {
+        let vao = VertexArray::new()?;
+        vao.bind();
+        let vertices = Buffer::new()?;
+        vertices.bind(BufferType::Array);
+        unsafe {
+            let stride = std::mem::size_of::<LineVertex>() as gl::types::GLsizei;
+            let float = std::mem::size_of::<f32>();
+            // (location, components, offset in floats): position, color
+            for (location, components, offset) in [(0, 3, 0), (1, 4, 3)] {
+                gl::VertexAttribPointer(location, components, gl::FLOAT, gl::FALSE, stride, (offset * float) as *const _);
+                gl::EnableVertexAttribArray(location);
+            }
+        }
+
+        Some(Self {
+            program,
+            vao,
+            vertices,
+            batch: Vec::new(),
+            triangles: Vec::new(),
+            palette: Palette::Default,
+        })
+    }
+
+    /// Queues a line segment.
+    pub fn line(&mut self, from: Vec3, to: Vec3, color: [f32; 3]) {
+        let [r, g, b] = color;
+        self.batch.push([from.x, from.y, from.z, r, g, b, 1.0]);
+        self.batch.push([to.x, to.y, to.z, r, g, b, 1.0]);
+    }
+
+    /// Queues the twelve edges of an axis-aligned box.
+    pub fn cuboid(&mut self, min: Vec3, max: Vec3, color: [f32; 3]) {
+        let corner = |i: usize| Vec3::new(
+            if i & 1 == 0 { min.x } else { max.x },
+            if i & 2 == 0 { min.y } else { max.y },
+            if i & 4 == 0 { min.z } else { max.z },
+        );
+        // Corners one bit apart share an edge
+        for i in 0..8 {
+            for bit in [1, 2, 4] {
+                if i & bit == 0 {
+                    self.line(corner(i), corner(i | bit), color);
+                }
+            }
+        }
+    }
+
+    /// Queues an axis-aligned box filled with a color, usually see-through.
+    pub fn filled_cuboid(&mut self, min: Vec3, max: Vec3, color: [f32; 4]) {
+        let [r, g, b, a] = color;
+        for corner in BOX_TRIANGLES {
+            let x = if corner & 1 == 0 { min.x } else { max.x };
+            let y = if corner & 2 == 0 { min.y } else { max.y };
+            let z = if corner & 4 == 0 { min.z } else { max.z };
+            self.triangles.push([x, y, z, r, g, b, a]);
+        }
+    }
+
+    /// Draws everything queued since the last call.
+    pub fn draw(&mut self) {
+        if self.batch.is_empty() && self.triangles.is_empty() {
+            return;
+        }
+
+        self.program.use_program();
+        self.vao.bind();
+        self.vertices.bind(BufferType::Array);
+        if !self.triangles.is_empty() {
+            // Boxes are see-through, so they leave the depth alone to not hide
+            // each other or the lines
+            GlState::depth_mask(false);
+            buffer_data(BufferType::Array, bytemuck::cast_slice(&self.triangles), gl::STREAM_DRAW);
+            unsafe {
+                gl::DrawArrays(gl::TRIANGLES, 0, self.triangles.len() as i32);
+            }
+            GlState::depth_mask(true);
+            self.triangles.clear();
+        }
+        if self.batch.is_empty() {
+            return;
+        }
+        buffer_data(BufferType::Array, bytemuck::cast_slice(&self.batch), gl::STREAM_DRAW);
+        unsafe {
+            gl::DrawArrays(gl::LINES, 0, self.batch.len() as i32);
+        }
+        self.batch.clear();
+    }
+}
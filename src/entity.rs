@@ -0,0 +1,229 @@
+use crate::item::DroppedItem;
+use crate::math::{Transform, Vec3};
+use crate::mob::Mob;
+use crate::projectile::Projectile;
+use crate::tnt::PrimedTnt;
+
+/// Handle to an entity. The generation tells a reused slot apart from the
+/// entity that held it before, so stale handles never alias a new entity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Entity {
+    index: u32,
+    generation: u32,
+}
+
+/// Collision box of an entity, relative to its position.
+#[derive(Debug, Clone, Copy)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    /// A box centered on the entity's position horizontally, standing on it vertically.
+    pub fn standing(width: f32, height: f32) -> Self {
+        Self {
+            min: Vec3::new(-width / 2.0, 0.0, -width / 2.0),
+            max: Vec3::new(width / 2.0, height, width / 2.0),
+        }
+    }
+
+    /// The box moved by `position`, such as an entity's box placed in the world.
+    pub fn at(&self, position: Vec3) -> Self {
+        Self { min: self.min + position, max: self.max + position }
+    }
+
+    /// How far along a ray it enters the box, if within `max_distance`, or 0
+    /// if it starts inside. `direction` must be normalized.
+    pub fn ray_distance(&self, origin: Vec3, direction: Vec3, max_distance: f32) -> Option<f32> {
+        let (mut near, mut far) = (0.0f32, max_distance);
+        let axes = [
+            (origin.x, direction.x, self.min.x, self.max.x),
+            (origin.y, direction.y, self.min.y, self.max.y),
+            (origin.z, direction.z, self.min.z, self.max.z),
+        ];
+        for (origin, direction, min, max) in axes {
+            if direction == 0.0 {
+                // Parallel to this pair of faces: only hits if between them
+                if origin < min || origin > max {
+                    return None;
+                }
+                continue;
+            }
+            let (a, b) = ((min - origin) / direction, (max - origin) / direction);
+            near = near.max(a.min(b));
+            far = far.min(a.max(b));
+            if near > far {
+                return None;
+            }
+        }
+        Some(near)
+    }
+}
+
+/// Index of a mesh registered with the entity renderer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MeshHandle(pub usize);
+
+/// Sparse storage for one kind of component, indexed by entity slot.
+pub struct ComponentStorage<T> {
+    // Generation of the owning entity along with the component
+    slots: Vec<Option<(u32, T)>>,
+}
+
+impl<T> ComponentStorage<T> {
+    fn new() -> Self {
+        Self { slots: Vec::new() }
+    }
+
+    pub fn insert(&mut self, entity: Entity, component: T) {
+        let index = entity.index as usize;
+        if index >= self.slots.len() {
+            self.slots.resize_with(index + 1, || None);
+        }
+        self.slots[index] = Some((entity.generation, component));
+    }
+
+    pub fn remove(&mut self, entity: Entity) -> Option<T> {
+        let slot = self.slots.get_mut(entity.index as usize)?;
+        match slot {
+            Some((generation, _)) if *generation == entity.generation => slot.take().map(|(_, c)| c),
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<&T> {
+        match self.slots.get(entity.index as usize)? {
+            Some((generation, component)) if *generation == entity.generation => Some(component),
+            _ => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+        match self.slots.get_mut(entity.index as usize)? {
+            Some((generation, component)) if *generation == entity.generation => Some(component),
+            _ => None,
+        }
+    }
+
+    /// Iterates over every entity that has this component.
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, &T)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            slot.as_ref().map(|(generation, component)| {
+                (Entity { index: index as u32, generation: *generation }, component)
+            })
+        })
+    }
+
+    /// Iterates mutably over every entity that has this component.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Entity, &mut T)> {
+        self.slots.iter_mut().enumerate().filter_map(|(index, slot)| {
+            slot.as_mut().map(|(generation, component)| {
+                (Entity { index: index as u32, generation: *generation }, component)
+            })
+        })
+    }
+}
+
+/// Every non-voxel object in the world, stored as a generational-index ECS:
+/// entities are plain handles and each kind of component lives in its own storage.
+pub struct Entities {
+    generations: Vec<u32>,
+    alive: Vec<bool>,
+    free: Vec<u32>,
+    pub transforms: ComponentStorage<Transform>,
+    /// Translation before the last physics step, to draw in between the two.
+    pub previous_positions: ComponentStorage<Vec3>,
+    pub velocities: ComponentStorage<Vec3>,
+    pub bounds: ComponentStorage<Aabb>,
+    pub meshes: ComponentStorage<MeshHandle>,
+    pub items: ComponentStorage<DroppedItem>,
+    pub mobs: ComponentStorage<Mob>,
+    pub primed_tnt: ComponentStorage<PrimedTnt>,
+    pub projectiles: ComponentStorage<Projectile>,
+}
+
+impl Entities {
+    pub fn new() -> Self {
+        Self {
+            generations: Vec::new(),
+            alive: Vec::new(),
+            free: Vec::new(),
+            transforms: ComponentStorage::new(),
+            previous_positions: ComponentStorage::new(),
+            velocities: ComponentStorage::new(),
+            bounds: ComponentStorage::new(),
+            meshes: ComponentStorage::new(),
+            items: ComponentStorage::new(),
+            mobs: ComponentStorage::new(),
+            primed_tnt: ComponentStorage::new(),
+            projectiles: ComponentStorage::new(),
+        }
+    }
+
+    /// Creates an entity without any components, reusing a free slot if possible.
+    pub fn spawn(&mut self) -> Entity {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.generations.push(0);
+                self.alive.push(false);
+                (self.generations.len() - 1) as u32
+            },
+        };
+        self.alive[index as usize] = true;
+        Entity { index, generation: self.generations[index as usize] }
+    }
+
+    /// Removes an entity and all of its components. Returns false if it was already gone.
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        if !self.is_alive(entity) {
+            return false;
+        }
+        self.transforms.remove(entity);
+        self.previous_positions.remove(entity);
+        self.velocities.remove(entity);
+        self.bounds.remove(entity);
+        self.meshes.remove(entity);
+        self.items.remove(entity);
+        self.mobs.remove(entity);
+        self.primed_tnt.remove(entity);
+        self.projectiles.remove(entity);
+
+        let index = entity.index as usize;
+        self.alive[index] = false;
+        self.generations[index] = self.generations[index].wrapping_add(1);
+        self.free.push(entity.index);
+        true
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        let index = entity.index as usize;
+        self.alive.get(index) == Some(&true) && self.generations[index] == entity.generation
+    }
+
+    /// Remembers where every entity is before a physics step moves it.
+    pub fn store_previous_positions(&mut self) {
+        for (entity, transform) in self.transforms.iter() {
+            self.previous_positions.insert(entity, transform.translation);
+        }
+    }
+
+    /// Where to draw an entity, `alpha` of the way from its previous position
+    /// to its current one. Entities spawned since the last physics step are
+    /// drawn where they are.
+    pub fn interpolated_transform(&self, entity: Entity, alpha: f32) -> Option<Transform> {
+        let transform = *self.transforms.get(entity)?;
+        let previous = self.previous_positions.get(entity).copied().unwrap_or(transform.translation);
+        Some(Transform { translation: previous.lerp(&transform.translation, alpha), ..transform })
+    }
+
+    /// Moves every entity with a velocity.
+    pub fn integrate(&mut self, delta_time: f32) {
+        for (entity, &velocity) in self.velocities.iter() {
+            if let Some(transform) = self.transforms.get_mut(entity) {
+                transform.translation = transform.translation + velocity * delta_time;
+            }
+        }
+    }
+}
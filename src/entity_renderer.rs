@@ -0,0 +1,186 @@
+use crate::block::FaceTextures;
+use crate::entity::{Entities, MeshHandle};
+use crate::gl_utils::{buffer_data, Buffer, BufferType, ShaderProgram, VertexArray};
+use crate::math::Mat4;
+
+pub type EntityVertex = [f32; 9];  // x, y, z, s, t, textureIndex (negative for none), r, g, b
+
+// Corners of each face of a unit cube centered horizontally and standing on
+// y = 0, counter-clockwise seen from outside, in front, back, top, bottom,
+// right, left order
+const CUBE_FACES: [[[f32; 3]; 4]; 6] = [
+    [[-0.5, 0.0, 0.5], [0.5, 0.0, 0.5], [0.5, 1.0, 0.5], [-0.5, 1.0, 0.5]],
+    [[0.5, 0.0, -0.5], [-0.5, 0.0, -0.5], [-0.5, 1.0, -0.5], [0.5, 1.0, -0.5]],
+    [[-0.5, 1.0, 0.5], [0.5, 1.0, 0.5], [0.5, 1.0, -0.5], [-0.5, 1.0, -0.5]],
+    [[-0.5, 0.0, -0.5], [0.5, 0.0, -0.5], [0.5, 0.0, 0.5], [-0.5, 0.0, 0.5]],
+    [[0.5, 0.0, 0.5], [0.5, 0.0, -0.5], [0.5, 1.0, -0.5], [0.5, 1.0, 0.5]],
+    [[-0.5, 0.0, -0.5], [-0.5, 0.0, 0.5], [-0.5, 1.0, 0.5], [-0.5, 1.0, -0.5]],
+];
+// Darkens the sides a little so shapes read without lighting
+const FACE_SHADES: [f32; 6] = [0.8, 0.8, 1.0, 0.5, 0.7, 0.7];
+
+// A mesh uploaded once and drawn for every entity that uses it
+struct EntityMesh {
+    vao: VertexArray,
+    _vertices: Buffer,
+    _indices: Buffer,
+    index_count: i32,
+}
+
+/// Draws entities in their own pass, separate from the chunk geometry.
+pub struct EntityRenderer {
+    // Disabled renderers have no program, and no mesh behind their handles
+    program: Option<ShaderProgram>,
+    meshes: Vec<Option<EntityMesh>>,
+}
+
+impl EntityRenderer {
+    pub fn new(program: ShaderProgram) -> Self {
+        Self {
+            program: Some(program),
+            meshes: Vec::new(),
+        }
+    }
+
+    /// A renderer that hands out mesh handles without uploading anything and
+    /// never draws, for backends without an OpenGL context.
+    pub fn disabled() -> Self {
+        Self {
+            program: None,
+            meshes: Vec::new(),
+        }
+    }
+
+    /// Uploads a mesh and returns the handle entities refer to it by.
+    pub fn add_mesh(&mut self, vertices: &[EntityVertex], indices: &[u32]) -> Option<MeshHandle> {
+        if self.program.is_none() {
+            self.meshes.push(None);
+            return Some(MeshHandle(self.meshes.len() - 1));
+        }
+        let vao = VertexArray::new()?;
+        vao.bind();
+
+        let vbo = Buffer::new()?;
+        vbo.bind(BufferType::Array);
+        buffer_data(BufferType::Array, bytemuck::cast_slice(vertices), gl::STATIC_DRAW);
+
+        let ebo = Buffer::new()?;
+        ebo.bind(BufferType::ElementArray);
+        buffer_data(BufferType::ElementArray, bytemuck::cast_slice(indices), gl::STATIC_DRAW);
+
+        unsafe {
+            let stride = std::mem::size_of::<EntityVertex>() as gl::types::GLsizei;
+
+            // Position attribute
+            gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, stride, std::ptr::null());
+            gl::EnableVertexAttribArray(0);
+
+            // Texture coordinate attribute
+            gl::VertexAttribPointer(1, 2, gl::FLOAT, gl::FALSE, stride, (3 * std::mem::size_of::<f32>()) as *const _);
+            gl::EnableVertexAttribArray(1);
+
+            // Texture index attribute
+            gl::VertexAttribPointer(2, 1, gl::FLOAT, gl::FALSE, stride, (5 * std::mem::size_of::<f32>()) as *const _);
+            gl::EnableVertexAttribArray(2);
+
+            // Color attribute
+            gl::VertexAttribPointer(3, 3, gl::FLOAT, gl::FALSE, stride, (6 * std::mem::size_of::<f32>()) as *const _);
+            gl::EnableVertexAttribArray(3);
+        }
+
+        self.meshes.push(Some(EntityMesh {
+            vao,
+            _vertices: vbo,
+            _indices: ebo,
+            index_count: indices.len() as i32,
+        }));
+        Some(MeshHandle(self.meshes.len() - 1))
+    }
+
+    /// Uploads a unit cube of a single color, centered horizontally and standing on y = 0.
+    pub fn add_cube(&mut self, color: [f32; 3]) -> Option<MeshHandle> {
+        self.add_cube_faces(|_| (-1.0, color))
+    }
+
+    /// Uploads a unit cube textured like a block, centered horizontally and standing on y = 0.
+    pub fn add_block_cube(&mut self, textures: FaceTextures) -> Option<MeshHandle> {
+        self.add_cube_faces(|face| {
+            let texture = match face {
+                2 => textures.top,
+                3 => textures.bottom,
+                _ => textures.side,
+            };
+            (texture.layer(), [1.0, 1.0, 1.0])
+        })
+    }
+
+    // Uploads a cube, taking the texture layer and color of each face from `face_style`
+    fn add_cube_faces(&mut self, face_style: impl Fn(usize) -> (f32, [f32; 3])) -> Option<MeshHandle> {
+        let tex_coords = [[0.0, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0]];
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        for (face, corners) in CUBE_FACES.iter().enumerate() {
+            let (layer, [r, g, b]) = face_style(face);
+            let shade = FACE_SHADES[face];
+            let base = vertices.len() as u32;
+            for ([x, y, z], [s, t]) in corners.iter().zip(tex_coords) {
+                vertices.push([*x, *y, *z, s, t, layer, r * shade, g * shade, b * shade]);
+            }
+            indices.extend_from_slice(&[base, base + 1, base + 2, base + 2, base + 3, base]);
+        }
+        self.add_mesh(&vertices, &indices)
+    }
+
+    /// Draws every entity that has both a transform and a mesh, `alpha` of the
+    /// way between their last two physics steps. The block texture array and the
+    /// colormap must be bound to texture units 0 and 1.
+    pub fn draw(&self, entities: &Entities, view_projection: &Mat4, alpha: f32) {
+        let Some(model_loc) = self.use_program(view_projection) else {
+            return;
+        };
+        for (entity, handle) in entities.meshes.iter() {
+            let (Some(transform), Some(mesh)) = (entities.interpolated_transform(entity, alpha), self.mesh(*handle)) else {
+                continue;
+            };
+            mesh.vao.bind();
+            unsafe {
+                gl::UniformMatrix4fv(model_loc, 1, gl::FALSE, transform.to_mat4().as_ptr());
+                gl::DrawElements(gl::TRIANGLES, mesh.index_count, gl::UNSIGNED_INT, std::ptr::null());
+            }
+        }
+    }
+
+    /// Draws a single mesh that doesn't belong to an entity, with the same
+    /// textures bound as for `draw`.
+    pub fn draw_mesh(&self, handle: MeshHandle, view_projection: &Mat4, model: &Mat4) {
+        let Some(mesh) = self.mesh(handle) else {
+            return;
+        };
+        let Some(model_loc) = self.use_program(view_projection) else {
+            return;
+        };
+        mesh.vao.bind();
+        unsafe {
+            gl::UniformMatrix4fv(model_loc, 1, gl::FALSE, model.as_ptr());
+            gl::DrawElements(gl::TRIANGLES, mesh.index_count, gl::UNSIGNED_INT, std::ptr::null());
+        }
+    }
+
+    fn mesh(&self, handle: MeshHandle) -> Option<&EntityMesh> {
+        self.meshes.get(handle.0)?.as_ref()
+    }
+
+    // Sets up the program for drawing and returns the location of the model
+    // matrix, or nothing for a disabled renderer
+    fn use_program(&self, view_projection: &Mat4) -> Option<gl::types::GLint> {
+        let program = self.program.as_ref()?;
+        program.use_program();
+        unsafe {
+            gl::Uniform1i(gl::GetUniformLocation(program.0, b"blockTextures\0".as_ptr() as *const i8), 0);
+            gl::Uniform1i(gl::GetUniformLocation(program.0, b"colormapTexture\0".as_ptr() as *const i8), 1);
+            let loc = gl::GetUniformLocation(program.0, b"viewProjection\0".as_ptr() as *const i8);
+            gl::UniformMatrix4fv(loc, 1, gl::FALSE, view_projection.as_ptr());
+            Some(gl::GetUniformLocation(program.0, b"model\0".as_ptr() as *const i8))
+        }
+    }
+}
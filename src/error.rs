@@ -0,0 +1,59 @@
+use std::io;
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// Anything that stops the game from starting.
+#[derive(Debug, Error)]
+pub enum EngineError {
+    #[error("Failed to build the {name} shader: {message}")]
+    Shader { name: String, message: String },
+    #[error("Failed to load texture {}: {source}", path.display())]
+    Texture {
+        path: PathBuf,
+        #[source]
+        source: image::ImageError,
+    },
+    #[error("{context}: {source}")]
+    Io {
+        context: String,
+        #[source]
+        source: io::Error,
+    },
+    #[error("Window error: {0}")]
+    Window(String),
+    #[error("OpenGL error: {0}")]
+    Gl(String),
+    #[cfg(feature = "wgpu")]
+    #[error("wgpu error: {0}")]
+    Wgpu(String),
+}
+
+impl EngineError {
+    /// Wraps an IO error with what was being done when it happened.
+    pub fn io(context: impl Into<String>) -> impl FnOnce(io::Error) -> Self {
+        let context = context.into();
+        move |source| EngineError::Io { context, source }
+    }
+
+    /// The log category the error belongs to.
+    pub fn category(&self) -> &'static str {
+        match self {
+            EngineError::Io { .. } => "io",
+            _ => "render",
+        }
+    }
+}
+
+// The platforms report most failures as plain strings
+impl From<String> for EngineError {
+    fn from(message: String) -> Self {
+        EngineError::Window(message)
+    }
+}
+
+#[cfg(feature = "sdl2")]
+impl From<sdl2::video::WindowBuildError> for EngineError {
+    fn from(error: sdl2::video::WindowBuildError) -> Self {
+        EngineError::Window(error.to_string())
+    }
+}
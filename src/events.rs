@@ -0,0 +1,227 @@
+use crate::block::BlockType;
+use crate::health::DamageCause;
+use crate::math::Vec3;
+use crate::platform::Key;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+/// What changed a block, so each system can pick the changes it cares about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockChangeCause {
+    /// Placed or broken by the player.
+    Player,
+    /// TNT taken out of the world as the player lit it.
+    Ignition,
+    /// An arrow that landed and became a block.
+    Projectile,
+    Explosion,
+    /// Water flow, growth and the other block updates.
+    Tick,
+    Command,
+    Plugin,
+    /// Another player's edit, received from the server.
+    Remote,
+}
+
+impl BlockChangeCause {
+    /// Name scripts see the cause by.
+    pub fn name(self) -> &'static str {
+        match self {
+            BlockChangeCause::Player => "player",
+            BlockChangeCause::Ignition => "ignition",
+            BlockChangeCause::Projectile => "projectile",
+            BlockChangeCause::Explosion => "explosion",
+            BlockChangeCause::Tick => "tick",
+            BlockChangeCause::Command => "command",
+            BlockChangeCause::Plugin => "plugin",
+            BlockChangeCause::Remote => "remote",
+        }
+    }
+
+    /// Whether the change is heard as a block being placed or broken.
+    /// Explosions make a sound of their own instead.
+    pub fn is_audible(self) -> bool {
+        matches!(self, BlockChangeCause::Player | BlockChangeCause::Projectile | BlockChangeCause::Remote)
+    }
+}
+
+/// A block in a loaded chunk was set.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockChanged {
+    pub position: (i32, i32, i32),
+    /// What is there now.
+    pub block: BlockType,
+    /// What was there before, when whatever changed it knew.
+    pub previous: Option<BlockType>,
+    pub cause: BlockChangeCause,
+}
+
+/// A chunk was added to the world.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChunkLoaded {
+    pub position: (i32, i32, i32),
+    /// Whether it was meshed as it loaded, as streamed chunks are. Chunks
+    /// received from the server still need their mesh.
+    pub meshed: bool,
+}
+
+/// A stage chunks go through on their way from nothing to the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkStage {
+    /// Terrain shaped from noise, or read from a save.
+    Generating,
+    /// Structures stamped in and edits applied.
+    Decorating,
+    Meshing,
+    /// Meshes sent to the graphics card.
+    Uploading,
+}
+
+impl ChunkStage {
+    pub const ALL: [ChunkStage; 4] = [ChunkStage::Generating, ChunkStage::Decorating, ChunkStage::Meshing, ChunkStage::Uploading];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ChunkStage::Generating => "generating",
+            ChunkStage::Decorating => "decorating",
+            ChunkStage::Meshing => "meshing",
+            ChunkStage::Uploading => "uploading",
+        }
+    }
+}
+
+/// A batch of chunks went through a stage of the chunk pipeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkProgress {
+    pub stage: ChunkStage,
+    pub chunks: usize,
+}
+
+/// The player's feet moved, by walking, physics or teleporting.
+#[derive(Debug, Clone, Copy)]
+pub struct PlayerMoved {
+    pub from: Vec3,
+    pub to: Vec3,
+}
+
+/// The player took damage.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlayerHurt {
+    pub amount: u32,
+    pub cause: DamageCause,
+}
+
+/// Something blew up.
+#[derive(Debug, Clone, Copy)]
+pub struct Exploded {
+    pub center: Vec3,
+    /// How hard it shook the player, from 0 out of range to 1 at the center.
+    pub shake: f32,
+}
+
+/// A key was pressed while playing, with no text field taking the keyboard.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeyAction {
+    pub key: Key,
+}
+
+// The events of one type, numbered from the first ever published
+struct Queue<E> {
+    events: Vec<E>,
+    // Number of the first event in `events`
+    first: u64,
+}
+
+// A queue whatever its event type, so the bus can clear them all
+trait AnyQueue {
+    fn clear(&mut self);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<E: 'static> AnyQueue for Queue<E> {
+    fn clear(&mut self) {
+        self.first += self.events.len() as u64;
+        self.events.clear();
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Where a subscriber got to in the events of type `E`. Each system keeps its
+/// own and reads with `EventBus::read`; a new one starts at the oldest event
+/// still queued.
+pub struct Subscriber<E> {
+    next: u64,
+    _event: PhantomData<fn() -> E>,
+}
+
+impl<E> Subscriber<E> {
+    pub fn new() -> Self {
+        Self { next: 0, _event: PhantomData }
+    }
+}
+
+impl<E> Default for Subscriber<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Events systems publish for others to follow, without knowing who does.
+/// Events are queued by type until `clear`, once per frame, so every
+/// subscriber has to read within the frame they were published in.
+#[derive(Default)]
+pub struct EventBus {
+    // A `Queue<E>` for each type `E` published so far
+    queues: HashMap<TypeId, Box<dyn AnyQueue>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn publish<E: 'static>(&mut self, event: E) {
+        self.queue_mut::<E>().events.push(event);
+    }
+
+    pub fn publish_all<E: 'static>(&mut self, events: impl IntoIterator<Item = E>) {
+        self.queue_mut::<E>().events.extend(events);
+    }
+
+    /// The events of type `E` published since `subscriber` last read, oldest
+    /// first.
+    pub fn read<E: 'static>(&self, subscriber: &mut Subscriber<E>) -> &[E] {
+        let Some(queue) = self.queues.get(&TypeId::of::<E>()).and_then(|queue| queue.as_any().downcast_ref::<Queue<E>>()) else {
+            return &[];
+        };
+        // Events cleared before the subscriber got to them are gone
+        let start = subscriber.next.saturating_sub(queue.first).min(queue.events.len() as u64) as usize;
+        subscriber.next = queue.first + queue.events.len() as u64;
+        &queue.events[start..]
+    }
+
+    /// Drops every queued event, once every subscriber has had the chance to
+    /// read them.
+    pub fn clear(&mut self) {
+        for queue in self.queues.values_mut() {
+            queue.clear();
+        }
+    }
+
+    fn queue_mut<E: 'static>(&mut self) -> &mut Queue<E> {
+        self.queues.entry(TypeId::of::<E>())
+            .or_insert_with(|| Box::new(Queue::<E> { events: Vec::new(), first: 0 }))
+            .as_any_mut()
+            .downcast_mut()
+            .expect("queues are keyed by their event type")
+    }
+}
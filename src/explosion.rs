@@ -0,0 +1,78 @@
+use crate::block::BlockType;
+use crate::entity::Entities;
+use crate::math::Vec3;
+
+// Entities and the player are pushed and hurt from up to this many radii away
+const KNOCKBACK_RANGE: f32 = 2.0;
+// Speed given at the center per unit of power, in blocks per second
+const KNOCKBACK_PER_POWER: f32 = 1.5;
+// Extra upwards share of the push, so things are thrown rather than slid
+const KNOCKBACK_LIFT: f32 = 0.4;
+// The view shakes from up to this many radii away
+const SHAKE_RANGE: f32 = 6.0;
+
+/// What `World::explode` did, for the effects that follow: particles, sound,
+/// knockback and screen shake.
+#[derive(Debug, Clone)]
+pub struct Explosion {
+    pub center: Vec3,
+    pub radius: f32,
+    pub power: f32,
+    /// Blocks blown away, and what they were.
+    pub removed: Vec<((i32, i32, i32), BlockType)>,
+}
+
+impl Explosion {
+    /// Velocity given to something at `position`, away from the center and
+    /// weaker with distance, or zero out of range.
+    pub fn knockback(&self, position: Vec3) -> Vec3 {
+        let offset = position - self.center;
+        let distance = offset.length();
+        let falloff = self.falloff(position);
+        if falloff <= 0.0 {
+            return Vec3::zero();
+        }
+        // Straight up when right at the center
+        let direction = if distance > 0.01 { offset * (1.0 / distance) } else { Vec3::new(0.0, 1.0, 0.0) };
+        let direction = (direction + Vec3::new(0.0, KNOCKBACK_LIFT, 0.0)).normalize();
+        direction * (self.power * KNOCKBACK_PER_POWER * falloff)
+    }
+
+    /// Points of damage done to something at `position`: the explosion's
+    /// power right at the center, less further away.
+    pub fn damage(&self, position: Vec3) -> u32 {
+        (self.power * self.falloff(position)).round() as u32
+    }
+
+    // How much of the explosion's force reaches `position`, from 1 at the
+    // center to 0 at the edge of its reach
+    fn falloff(&self, position: Vec3) -> f32 {
+        let distance = (position - self.center).length();
+        (1.0 - distance / (self.radius * KNOCKBACK_RANGE)).max(0.0)
+    }
+
+    /// How hard the view shakes for a camera at `position`, from 0 to 1.
+    pub fn shake(&self, position: Vec3) -> f32 {
+        let distance = (position - self.center).length();
+        (1.0 - distance / (self.radius * SHAKE_RANGE)).clamp(0.0, 1.0)
+    }
+
+    /// Pushes every mob, lit TNT and moving entity in range away from the center.
+    pub fn push_entities(&self, entities: &mut Entities) {
+        for (entity, mob) in entities.mobs.iter_mut() {
+            if let Some(transform) = entities.transforms.get(entity) {
+                mob.push(self.knockback(transform.translation));
+            }
+        }
+        for (entity, tnt) in entities.primed_tnt.iter_mut() {
+            if let Some(transform) = entities.transforms.get(entity) {
+                tnt.push(self.knockback(transform.translation));
+            }
+        }
+        for (entity, velocity) in entities.velocities.iter_mut() {
+            if let Some(transform) = entities.transforms.get(entity) {
+                *velocity = *velocity + self.knockback(transform.translation);
+            }
+        }
+    }
+}
@@ -0,0 +1,342 @@
+use crate::ao_bake::bake_key;
+use crate::block::{BlockTexture, BIOME_COLORMAP_PATH};
+use crate::mesh_cache::MeshCache;
+use crate::mesher::unpack_vertex;
+use crate::selection::Bounds;
+use crate::world::World;
+use image::{Rgba, RgbaImage};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+/// Folder `/export` writes meshes to, with the textures they use in a
+/// `textures` folder next to them.
+pub const EXPORT_DIRECTORY: &str = "exports";
+// The tint the block shader gives water
+const WATER_TINT: [f32; 4] = [0.0, 0.3, 0.8, 0.6];
+// Texels more transparent than this are cut out, as in the block shader
+const ALPHA_CUTOFF: f32 = 0.1;
+// glTF constants
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const NEAREST: u32 = 9728;
+
+// The triangles drawn with one texture layer, with their own vertices
+#[derive(Default)]
+struct Part {
+    positions: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    tex_coords: Vec<[f32; 2]>,
+    // Baked ambient occlusion, see `ao_bake`: how much sky each vertex sees,
+    // and the way it sees the most of it
+    visibility: Vec<f32>,
+    bent_normals: Vec<[f32; 3]>,
+    indices: Vec<u32>,
+}
+
+// How a texture's transparency is drawn
+#[derive(PartialEq)]
+enum AlphaMode {
+    Opaque,
+    Cutout,
+    Blend,
+}
+
+struct Material {
+    name: &'static str,
+    alpha: AlphaMode,
+}
+
+/// Writes the meshes of the loaded chunks to an OBJ file (with its MTL) or,
+/// for paths ending in `.gltf`, a glTF file with its binary buffer. With
+/// `bounds`, only the faces of the blocks between the two corners are kept.
+/// Textures are written tinted as the game draws them, and ambient occlusion
+/// baked into `cache` with `--bake-ao` becomes vertex colors. Returns how many
+/// triangles were written.
+pub fn export_meshes(world: &World, bounds: Option<Bounds>, cache: &MeshCache, path: &Path) -> Result<usize, String> {
+    let (parts, baked) = collect_parts(world, bounds, cache);
+    if parts.is_empty() {
+        return Err("Nothing to export".to_string());
+    }
+
+    let directory = path.parent().unwrap_or(Path::new("."));
+    fs::create_dir_all(directory.join("textures")).map_err(|e| e.to_string())?;
+    let biome = biome_color()?;
+    let mut materials = BTreeMap::new();
+    for &layer in parts.keys() {
+        let texture = BlockTexture::ALL[layer];
+        let image = bake_texture(texture, biome)?;
+        let alpha = if texture == BlockTexture::Water {
+            AlphaMode::Blend
+        } else if image.pixels().any(|pixel| pixel[3] < 255) {
+            AlphaMode::Cutout
+        } else {
+            AlphaMode::Opaque
+        };
+        let name = texture_name(texture);
+        image.save(directory.join("textures").join(format!("{}.png", name))).map_err(|e| e.to_string())?;
+        materials.insert(layer, Material { name, alpha });
+    }
+
+    let stem = path.file_stem().and_then(|stem| stem.to_str()).ok_or("Invalid file name")?;
+    if path.extension().is_some_and(|extension| extension == "gltf") {
+        write_gltf(&parts, &materials, baked, directory, stem)?;
+    } else {
+        write_obj(&parts, &materials, baked, directory, stem)?;
+    }
+    Ok(parts.values().map(|part| part.indices.len() / 3).sum())
+}
+
+// Gathers the triangles of every loaded chunk by texture layer, in chunk order
+// so the same world always exports the same file. Also returns whether any
+// chunk had its ambient occlusion baked; the others are left unshaded.
+fn collect_parts(world: &World, bounds: Option<Bounds>, cache: &MeshCache) -> (BTreeMap<usize, Part>, bool) {
+    let mut chunks: Vec<_> = world.chunks().collect();
+    chunks.sort_unstable_by_key(|chunk| chunk.position);
+
+    let mut parts: BTreeMap<usize, Part> = BTreeMap::new();
+    let mut any_baked = false;
+    for chunk in chunks {
+        let (position, mesh) = (chunk.position, &chunk.mesh);
+        let baked = bake_key(world, position)
+            .and_then(|key| cache.load_occlusion(world.seed, position, key, mesh.vertices.len()));
+        any_baked |= baked.is_some();
+        // Index of each mesh vertex in its part, once used
+        let mut remap: Vec<Option<u32>> = vec![None; mesh.vertices.len()];
+        for triangle in mesh.indices.iter().chain(&mesh.water_indices) {
+            let corners = triangle.map(|index| {
+                let (position, tex_coord, layer) = unpack_vertex(mesh.vertices[index as usize]);
+                // Meshes are chunk-local
+                ([0, 1, 2].map(|axis| mesh.origin[axis] + position[axis]), tex_coord, layer)
+            });
+            let [a, b, c] = corners.map(|(position, _, _)| position);
+
+            if let Some((min, max)) = bounds {
+                // Blocks are centered on integer coordinates, so a face belongs
+                // to the block its center is nearest to
+                let center = [(a[0] + b[0] + c[0]) / 3.0, (a[1] + b[1] + c[1]) / 3.0, (a[2] + b[2] + c[2]) / 3.0];
+                let inside = |value: f32, min: i32, max: i32| value >= min as f32 - 0.5 && value <= max as f32 + 0.5;
+                if !inside(center[0], min.0, max.0) || !inside(center[1], min.1, max.1) || !inside(center[2], min.2, max.2) {
+                    continue;
+                }
+            }
+
+            let edge1 = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+            let edge2 = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+            let normal = [
+                edge1[1] * edge2[2] - edge1[2] * edge2[1],
+                edge1[2] * edge2[0] - edge1[0] * edge2[2],
+                edge1[0] * edge2[1] - edge1[1] * edge2[0],
+            ];
+            let length = (normal[0] * normal[0] + normal[1] * normal[1] + normal[2] * normal[2]).sqrt().max(f32::EPSILON);
+            let normal = normal.map(|v| v / length);
+
+            let layer = corners[0].2 as usize;
+            let part = parts.entry(layer).or_default();
+            for (&index, (position, tex_coord, _)) in triangle.iter().zip(corners) {
+                let mapped = *remap[index as usize].get_or_insert_with(|| {
+                    part.positions.push(position);
+                    part.normals.push(normal);
+                    part.tex_coords.push(tex_coord);
+                    match &baked {
+                        Some(baked) => {
+                            part.visibility.push(baked.visibility[index as usize]);
+                            part.bent_normals.push(baked.bent_normals[index as usize]);
+                        },
+                        None => {
+                            part.visibility.push(1.0);
+                            part.bent_normals.push(normal);
+                        },
+                    }
+                    part.positions.len() as u32 - 1
+                });
+                part.indices.push(mapped);
+            }
+        }
+    }
+    (parts, any_baked)
+}
+
+// The biome color the block shader tints grass and leaves with, from the
+// center of the colormap
+fn biome_color() -> Result<[f32; 3], String> {
+    let colormap = image::open(BIOME_COLORMAP_PATH).map_err(|e| e.to_string())?.to_rgba8();
+    let pixel = colormap.get_pixel(colormap.width() / 2, colormap.height() / 2);
+    Ok([0, 1, 2].map(|i| pixel[i] as f32 / 255.0))
+}
+
+fn texture_name(texture: BlockTexture) -> &'static str {
+    let path = texture.path();
+    let file = path.rsplit('/').next().unwrap_or(path);
+    file.strip_suffix(".png").unwrap_or(file)
+}
+
+// The first frame of a texture, colored the way the block shader colors it
+fn bake_texture(texture: BlockTexture, biome: [f32; 3]) -> Result<RgbaImage, String> {
+    let load = |texture: BlockTexture| {
+        let image = image::open(texture.path()).map_err(|e| format!("{}: {}", texture.path(), e))?;
+        Ok::<_, String>(image.crop_imm(0, 0, image.width(), image.width().min(image.height())).to_rgba8())
+    };
+    let mut image = load(texture)?;
+    let overlay = match texture {
+        BlockTexture::GrassSide => Some(load(BlockTexture::GrassSideOverlay)?),
+        _ => None,
+    };
+
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        let mut color = pixel.0.map(|v| v as f32 / 255.0);
+        match texture {
+            BlockTexture::GrassTop | BlockTexture::ShortGrass | BlockTexture::OakLeaves => {
+                for i in 0..3 {
+                    color[i] *= biome[i];
+                }
+            },
+            BlockTexture::GrassSide => {
+                if let Some(overlay) = &overlay {
+                    let over = overlay.get_pixel(x % overlay.width(), y % overlay.height()).0.map(|v| v as f32 / 255.0);
+                    for i in 0..3 {
+                        color[i] += (over[i] * biome[i] - color[i]) * over[3];
+                    }
+                }
+            },
+            BlockTexture::Water => {
+                for i in 0..3 {
+                    color[i] *= WATER_TINT[i];
+                }
+                color[3] = WATER_TINT[3];
+            },
+            _ => {},
+        }
+        if color[3] < ALPHA_CUTOFF {
+            color[3] = 0.0;
+        }
+        *pixel = Rgba(color.map(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8));
+    }
+    Ok(image)
+}
+
+// Baked ambient occlusion goes in vertex colors after the positions, which
+// OBJ readers commonly understand
+fn write_obj(parts: &BTreeMap<usize, Part>, materials: &BTreeMap<usize, Material>, baked: bool, directory: &Path, stem: &str) -> Result<(), String> {
+    let mut mtl = String::new();
+    for material in materials.values() {
+        let _ = writeln!(mtl, "newmtl {}\nKd 1 1 1\nKs 0 0 0\nmap_Kd textures/{}.png", material.name, material.name);
+        if material.alpha != AlphaMode::Opaque {
+            let _ = writeln!(mtl, "map_d textures/{}.png", material.name);
+        }
+        mtl.push('\n');
+    }
+
+    let mut obj = format!("mtllib {}.mtl\no {}\n", stem, stem);
+    // OBJ indices count from 1 across the whole file
+    let mut base = 1;
+    for (layer, part) in parts {
+        for (p, &visibility) in part.positions.iter().zip(&part.visibility) {
+            if baked {
+                let _ = writeln!(obj, "v {} {} {} {v} {v} {v}", p[0], p[1], p[2], v = visibility);
+            } else {
+                let _ = writeln!(obj, "v {} {} {}", p[0], p[1], p[2]);
+            }
+        }
+        for n in &part.normals {
+            let _ = writeln!(obj, "vn {} {} {}", n[0], n[1], n[2]);
+        }
+        // OBJ texture coordinates start at the bottom of the image
+        for t in &part.tex_coords {
+            let _ = writeln!(obj, "vt {} {}", t[0], 1.0 - t[1]);
+        }
+        let _ = writeln!(obj, "usemtl {}", materials[layer].name);
+        for triangle in part.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| triangle[i] + base);
+            let _ = writeln!(obj, "f {a}/{a}/{a} {b}/{b}/{b} {c}/{c}/{c}");
+        }
+        base += part.positions.len() as u32;
+    }
+
+    fs::write(directory.join(format!("{}.mtl", stem)), mtl).map_err(|e| e.to_string())?;
+    fs::write(directory.join(format!("{}.obj", stem)), obj).map_err(|e| e.to_string())
+}
+
+// One mesh with a primitive per texture layer, its data in a separate .bin file.
+// Baked ambient occlusion darkens the base color through COLOR_0, and its bent
+// normals are kept in an application-specific attribute
+fn write_gltf(parts: &BTreeMap<usize, Part>, materials: &BTreeMap<usize, Material>, baked: bool, directory: &Path, stem: &str) -> Result<(), String> {
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut views = Vec::new();
+    let mut accessors = Vec::new();
+    let mut primitives = Vec::new();
+    let mut material_json = Vec::new();
+    let mut textures = Vec::new();
+
+    // Appends data as a buffer view read through one accessor, returning the accessor's index
+    let mut add = |bytes: &[u8], target: u32, component: u32, kind: &str, count: usize, bounds: String| {
+        views.push(format!(r#"{{"buffer":0,"byteOffset":{},"byteLength":{},"target":{}}}"#, buffer.len(), bytes.len(), target));
+        buffer.extend_from_slice(bytes);
+        accessors.push(format!(r#"{{"bufferView":{},"componentType":{},"count":{},"type":"{}"{}}}"#,
+            views.len() - 1, component, count, kind, bounds));
+        accessors.len() - 1
+    };
+
+    for (index, (layer, part)) in parts.iter().enumerate() {
+        let (mut min, mut max) = ([f32::MAX; 3], [f32::MIN; 3]);
+        for p in &part.positions {
+            for i in 0..3 {
+                min[i] = min[i].min(p[i]);
+                max[i] = max[i].max(p[i]);
+            }
+        }
+        let count = part.positions.len();
+        let bounds = format!(r#","min":[{},{},{}],"max":[{},{},{}]"#, min[0], min[1], min[2], max[0], max[1], max[2]);
+        let position = add(bytemuck::cast_slice(&part.positions), ARRAY_BUFFER, FLOAT, "VEC3", count, bounds);
+        let normal = add(bytemuck::cast_slice(&part.normals), ARRAY_BUFFER, FLOAT, "VEC3", count, String::new());
+        let tex_coord = add(bytemuck::cast_slice(&part.tex_coords), ARRAY_BUFFER, FLOAT, "VEC2", count, String::new());
+        let occlusion = if baked {
+            let colors: Vec<[f32; 3]> = part.visibility.iter().map(|&visibility| [visibility; 3]).collect();
+            let color = add(bytemuck::cast_slice(&colors), ARRAY_BUFFER, FLOAT, "VEC3", count, String::new());
+            let bent_normal = add(bytemuck::cast_slice(&part.bent_normals), ARRAY_BUFFER, FLOAT, "VEC3", count, String::new());
+            format!(r#","COLOR_0":{},"_BENT_NORMAL":{}"#, color, bent_normal)
+        } else {
+            String::new()
+        };
+        let indices = add(bytemuck::cast_slice(&part.indices), ELEMENT_ARRAY_BUFFER, UNSIGNED_INT, "SCALAR", part.indices.len(), String::new());
+        primitives.push(format!(r#"{{"attributes":{{"POSITION":{},"NORMAL":{},"TEXCOORD_0":{}{}}},"indices":{},"material":{}}}"#,
+            position, normal, tex_coord, occlusion, indices, index));
+
+        let material = &materials[layer];
+        let alpha = match material.alpha {
+            AlphaMode::Opaque => String::new(),
+            AlphaMode::Cutout => format!(r#","alphaMode":"MASK","alphaCutoff":{},"doubleSided":true"#, ALPHA_CUTOFF),
+            AlphaMode::Blend => r#","alphaMode":"BLEND""#.to_string(),
+        };
+        material_json.push(format!(
+            r#"{{"name":"{}","pbrMetallicRoughness":{{"baseColorTexture":{{"index":{}}},"metallicFactor":0,"roughnessFactor":1}}{}}}"#,
+            material.name, index, alpha));
+        textures.push(format!(r#"{{"sampler":0,"source":{}}}"#, index));
+    }
+
+    let images: Vec<_> = materials.values().map(|material| format!(r#"{{"uri":"textures/{}.png"}}"#, material.name)).collect();
+    let json = format!(
+        concat!(
+            r#"{{"asset":{{"version":"2.0","generator":"learn_opengl_rust"}},"scene":0,"scenes":[{{"nodes":[0]}}],"#,
+            r#""nodes":[{{"name":"{stem}","mesh":0}}],"meshes":[{{"name":"{stem}","primitives":[{primitives}]}}],"#,
+            r#""materials":[{materials}],"textures":[{textures}],"images":[{images}],"#,
+            r#""samplers":[{{"magFilter":{nearest},"minFilter":{nearest}}}],"#,
+            r#""buffers":[{{"uri":"{stem}.bin","byteLength":{length}}}],"bufferViews":[{views}],"accessors":[{accessors}]}}"#,
+        ),
+        stem = stem,
+        primitives = primitives.join(","),
+        materials = material_json.join(","),
+        textures = textures.join(","),
+        images = images.join(","),
+        nearest = NEAREST,
+        length = buffer.len(),
+        views = views.join(","),
+        accessors = accessors.join(","),
+    );
+
+    fs::write(directory.join(format!("{}.bin", stem)), &buffer).map_err(|e| e.to_string())?;
+    fs::write(directory.join(format!("{}.gltf", stem)), json).map_err(|e| e.to_string())
+}
@@ -0,0 +1,72 @@
+use crate::block::BlockType;
+use crate::events::{BlockChangeCause, BlockChanged, EventBus, Exploded, PlayerHurt, Subscriber};
+use std::time::Duration;
+
+// A broken block gives a short tick of the light motor
+const BREAK_STRENGTH: f32 = 0.35;
+const BREAK_TIME: Duration = Duration::from_millis(60);
+// Getting hurt thumps the heavy motor, harder for more damage: a point of
+// damage at the base strength, and stronger with each further point
+const HURT_STRENGTH: f32 = 0.3;
+const HURT_STRENGTH_PER_POINT: f32 = 0.07;
+const HURT_TIME: Duration = Duration::from_millis(200);
+// Explosions rumble as hard and up to as long as they shake the view
+const EXPLOSION_TIME: Duration = Duration::from_millis(500);
+
+/// How hard and how long to rumble a controller, each motor from 0 to 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rumble {
+    /// The heavy, low frequency motor, felt as a thump.
+    pub low: f32,
+    /// The light, high frequency motor, felt as a buzz.
+    pub high: f32,
+    pub duration: Duration,
+}
+
+impl Rumble {
+    // The stronger of each motor and the longer of the durations, so
+    // several things in one frame don't cut each other short
+    fn max(self, other: Rumble) -> Rumble {
+        Rumble {
+            low: self.low.max(other.low),
+            high: self.high.max(other.high),
+            duration: self.duration.max(other.duration),
+        }
+    }
+}
+
+/// Turns gameplay into controller rumble: breaking a block, getting hurt and
+/// explosions nearby. What it asks for goes to `Platform::rumble`.
+#[derive(Default)]
+pub struct Feedback {
+    block_changes: Subscriber<BlockChanged>,
+    hurts: Subscriber<PlayerHurt>,
+    explosions: Subscriber<Exploded>,
+}
+
+impl Feedback {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The rumble the frame's events call for, the strongest of them all, or
+    /// None when nothing happened worth feeling.
+    pub fn handle_events(&mut self, events: &EventBus) -> Option<Rumble> {
+        let breaks = events.read(&mut self.block_changes).iter()
+            .filter(|change| change.cause == BlockChangeCause::Player && change.block == BlockType::Air)
+            .filter(|change| change.previous.is_some_and(|previous| previous != BlockType::Air))
+            .map(|_| Rumble { low: 0.0, high: BREAK_STRENGTH, duration: BREAK_TIME });
+        let hurts = events.read(&mut self.hurts).iter().map(|hurt| {
+            let strength = HURT_STRENGTH + HURT_STRENGTH_PER_POINT * hurt.amount.saturating_sub(1) as f32;
+            Rumble { low: strength.min(1.0), high: strength.min(1.0) / 2.0, duration: HURT_TIME }
+        });
+        let explosions = events.read(&mut self.explosions).iter()
+            .filter(|explosion| explosion.shake > 0.0)
+            .map(|explosion| Rumble {
+                low: explosion.shake,
+                high: explosion.shake / 2.0,
+                duration: EXPLOSION_TIME.mul_f32(explosion.shake),
+            });
+        breaks.chain(hurts).chain(explosions).reduce(Rumble::max)
+    }
+}
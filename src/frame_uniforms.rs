@@ -0,0 +1,90 @@
+use crate::gl_utils::{buffer_data, Buffer, BufferType, ShaderProgram};
+use crate::math::{Mat4, Vec3};
+
+/// Name of the uniform block the shaders declare for the per-frame data.
+const BLOCK_NAME: &str = "FrameUniforms";
+// Binding point the block and its buffer meet at
+const BINDING: u32 = 0;
+// Size of the block in floats under std140 rules: three matrices, then four
+// vec4 slots holding the vectors with a float packed in after each
+const BLOCK_FLOATS: usize = 3 * 16 + 4 * 4;
+/// Size of the uniform block in bytes.
+pub const BLOCK_SIZE: usize = BLOCK_FLOATS * std::mem::size_of::<f32>();
+
+/// What stays the same for every draw in a frame, shared by all the programs
+/// through one uniform buffer instead of being set on each of them. Shaders
+/// read it by declaring this block, which has to match `to_std140`:
+///
+/// ```glsl
+/// layout (std140) uniform FrameUniforms {
+///     mat4 view;
+///     mat4 projection;
+///     mat4 viewProjection;
+///     vec3 cameraPosition;
+///     float time;            // seconds since the game started
+///     vec3 sunDirection;     // towards the sun
+///     float daylight;        // 1 at noon, down to 0.1 at night
+///     vec3 fogColor;
+///     float fogStart;        // distance where the fog begins, in blocks
+///     float fogEnd;          // and where it hides everything
+/// };
+/// ```
+pub struct FrameUniforms {
+    pub view: Mat4,
+    pub projection: Mat4,
+    pub camera_position: Vec3,
+    pub time: f32,
+    pub sun_direction: Vec3,
+    pub daylight: f32,
+    pub fog_color: [f32; 3],
+    pub fog_start: f32,
+    pub fog_end: f32,
+}
+
+impl FrameUniforms {
+    /// The values laid out as the uniform block, which WGSL lays out the same way.
+    pub fn to_std140(&self) -> [f32; BLOCK_FLOATS] {
+        let mut data = [0.0; BLOCK_FLOATS];
+        data[0..16].copy_from_slice(&self.view.to_array());
+        data[16..32].copy_from_slice(&self.projection.to_array());
+        data[32..48].copy_from_slice(&(self.projection * self.view).to_array());
+        let vec3 = |v: Vec3| [v.x, v.y, v.z];
+        data[48..52].copy_from_slice(&with_float(vec3(self.camera_position), self.time));
+        data[52..56].copy_from_slice(&with_float(vec3(self.sun_direction), self.daylight));
+        data[56..60].copy_from_slice(&with_float(self.fog_color, self.fog_start));
+        data[60] = self.fog_end;
+        data
+    }
+}
+
+// A vec3 and the float std140 packs into its fourth component
+fn with_float([x, y, z]: [f32; 3], w: f32) -> [f32; 4] {
+    [x, y, z, w]
+}
+
+/// The uniform buffer holding `FrameUniforms`, filled once per frame.
+pub struct FrameUniformBuffer {
+    buffer: Buffer,
+}
+
+impl FrameUniformBuffer {
+    pub fn new() -> Option<Self> {
+        let buffer = Buffer::new()?;
+        buffer.bind(BufferType::Uniform);
+        buffer_data(BufferType::Uniform, &[0u8; BLOCK_SIZE], gl::DYNAMIC_DRAW);
+        buffer.bind_uniform_base(BINDING);
+        Some(Self { buffer })
+    }
+
+    /// Points a program's `FrameUniforms` block at this buffer. Programs
+    /// without the block are left alone.
+    pub fn attach(&self, program: &ShaderProgram) {
+        program.bind_uniform_block(BLOCK_NAME, BINDING);
+    }
+
+    /// Uploads this frame's values, before anything is drawn with them.
+    pub fn update(&self, uniforms: &FrameUniforms) {
+        self.buffer.bind(BufferType::Uniform);
+        buffer_data(BufferType::Uniform, bytemuck::cast_slice(&uniforms.to_std140()), gl::DYNAMIC_DRAW);
+    }
+}
@@ -0,0 +1,112 @@
+use crate::hud::Hud;
+use crate::world::World;
+
+// The digging progress bar below the crosshair
+const PROGRESS_WIDTH: f32 = 40.0;
+const PROGRESS_HEIGHT: f32 = 4.0;
+const PROGRESS_OFFSET: f32 = 16.0;
+const PROGRESS_BACKGROUND: [f32; 4] = [0.0, 0.0, 0.0, 0.5];
+const PROGRESS_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.9];
+
+/// The rules the player plays by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GameMode {
+    /// Blocks take time to dig and come from the inventory, flying is off, and
+    /// the player can get hurt.
+    #[default]
+    Survival,
+    /// Blocks break at once and never run out, the player can fly, and nothing
+    /// hurts them.
+    Creative,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 2] = [GameMode::Survival, GameMode::Creative];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GameMode::Survival => "survival",
+            GameMode::Creative => "creative",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<GameMode> {
+        Self::ALL.into_iter().find(|mode| mode.name() == name)
+    }
+
+    /// Numeric id the mode is saved with.
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    pub fn from_id(id: u8) -> Option<GameMode> {
+        Self::ALL.get(id as usize).copied()
+    }
+
+    /// Whether blocks break with a single click rather than by digging.
+    pub fn breaks_instantly(self) -> bool {
+        self == GameMode::Creative
+    }
+
+    /// Whether placing and throwing blocks leaves the inventory as it was.
+    pub fn infinite_blocks(self) -> bool {
+        self == GameMode::Creative
+    }
+
+    /// Whether broken blocks drop as items.
+    pub fn drops_blocks(self) -> bool {
+        self == GameMode::Survival
+    }
+
+    pub fn can_fly(self) -> bool {
+        self == GameMode::Creative
+    }
+
+    /// Whether falls, drowning and explosions hurt the player.
+    pub fn takes_damage(self) -> bool {
+        self == GameMode::Survival
+    }
+}
+
+/// Digging into a block in survival: the block has to be held in the crosshair
+/// with the button down for its break time, starting over if either lets go.
+#[derive(Debug, Default)]
+pub struct Digging {
+    // Block being dug, and how far along it is from 0 to 1
+    target: Option<(i32, i32, i32)>,
+    progress: f32,
+}
+
+impl Digging {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Digs at `target` for a frame, or stops digging without one. Returns the
+    /// block once it is dug through, which the caller breaks.
+    pub fn update(&mut self, world: &World, target: Option<(i32, i32, i32)>, delta_time: f32) -> Option<(i32, i32, i32)> {
+        if target != self.target {
+            self.target = target;
+            self.progress = 0.0;
+        }
+        let (x, y, z) = target?;
+        let break_time = world.get_block(x, y, z).break_time()?;
+        self.progress += if break_time > 0.0 { delta_time / break_time } else { 1.0 };
+        if self.progress < 1.0 {
+            return None;
+        }
+        self.target = None;
+        self.progress = 0.0;
+        target
+    }
+
+    /// Queues a bar below the crosshair showing how far the current block is dug.
+    pub fn draw(&self, hud: &mut Hud, screen_width: f32, screen_height: f32) {
+        if self.target.is_none() || self.progress <= 0.0 {
+            return;
+        }
+        let (x, y) = ((screen_width - PROGRESS_WIDTH) / 2.0, screen_height / 2.0 + PROGRESS_OFFSET);
+        hud.rect(x, y, PROGRESS_WIDTH, PROGRESS_HEIGHT, PROGRESS_BACKGROUND);
+        hud.rect(x, y, PROGRESS_WIDTH * self.progress.min(1.0), PROGRESS_HEIGHT, PROGRESS_COLOR);
+    }
+}
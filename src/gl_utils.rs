@@ -1,12 +1,182 @@
 use gl::types::{GLenum, GLint, GLsizei, GLuint};
+use std::cell::RefCell;
 use std::ffi::CString;
 use image::GenericImageView;
+use crate::compressed_texture::{compressed_version, BlockFormat, CompressedImage};
+use crate::error::EngineError;
+use std::path::Path;
+
+// S3TC formats, from EXT_texture_compression_s3tc rather than core OpenGL
+const COMPRESSED_RGBA_S3TC_DXT1_EXT: GLenum = 0x83F1;
+const COMPRESSED_RGBA_S3TC_DXT5_EXT: GLenum = 0x83F3;
 
 /// Sets the color to clear to when clearing the screen.
 pub fn clear_color(r: f32, g: f32, b: f32, a: f32) {
     unsafe { gl::ClearColor(r, g, b, a) }
 }
 
+// Texture units whose bindings GlState keeps track of
+const TRACKED_TEXTURE_UNITS: usize = 8;
+
+/// The GL state last set through this module, so calls that wouldn't change
+/// anything can be skipped: the bound program, vertex array and array buffer,
+/// the texture bound to each unit, and the blend, depth and culling state.
+///
+/// The cache only knows what goes through it, so everything that touches this
+/// state has to, or it goes stale. It lives with the GL context on the thread
+/// that drew with it.
+#[derive(Default)]
+pub struct GlState {
+    program: Option<GLuint>,
+    vertex_array: Option<GLuint>,
+    array_buffer: Option<GLuint>,
+    active_texture_unit: Option<u32>,
+    // (target, texture) bound to each unit
+    textures: [Option<(GLenum, GLuint)>; TRACKED_TEXTURE_UNITS],
+    // Capabilities that were enabled or disabled, such as gl::BLEND
+    capabilities: Vec<(GLenum, bool)>,
+    blend_func: Option<(GLenum, GLenum)>,
+    depth_mask: Option<bool>,
+    // Calls skipped since the count was last taken
+    skipped_calls: u32,
+}
+
+thread_local! {
+    static GL_STATE: RefCell<GlState> = RefCell::default();
+}
+
+impl GlState {
+    fn with<R>(f: impl FnOnce(&mut GlState) -> R) -> R {
+        GL_STATE.with(|state| f(&mut state.borrow_mut()))
+    }
+
+    // Stores `value` and runs `apply` if it differs from what's cached, or
+    // counts a skipped call if it doesn't
+    fn update<T: PartialEq>(&mut self, cached: impl FnOnce(&mut Self) -> &mut Option<T>, value: T, apply: impl FnOnce()) {
+        let slot = cached(self);
+        if slot.as_ref() == Some(&value) {
+            self.skipped_calls += 1;
+        } else {
+            *slot = Some(value);
+            apply();
+        }
+    }
+
+    pub fn use_program(program: GLuint) {
+        Self::with(|state| state.update(|s| &mut s.program, program, || unsafe { gl::UseProgram(program) }));
+    }
+
+    pub fn bind_vertex_array(vertex_array: GLuint) {
+        Self::with(|state| state.update(|s| &mut s.vertex_array, vertex_array, || unsafe { gl::BindVertexArray(vertex_array) }));
+    }
+
+    /// Binds a buffer. Only array buffers are cached: element array bindings
+    /// belong to the bound vertex array, and uniform buffers are rarely bound.
+    pub fn bind_buffer(ty: BufferType, buffer: GLuint) {
+        match ty {
+            BufferType::Array => Self::with(|state| {
+                state.update(|s| &mut s.array_buffer, buffer, || unsafe { gl::BindBuffer(gl::ARRAY_BUFFER, buffer) });
+            }),
+            _ => unsafe { gl::BindBuffer(ty as GLenum, buffer) },
+        }
+    }
+
+    /// Binds a texture to a texture unit, leaving that unit active.
+    pub fn bind_texture(unit: u32, target: GLenum, texture: GLuint) {
+        Self::with(|state| {
+            let index = unit as usize;
+            if state.textures.get(index).is_some_and(|&bound| bound == Some((target, texture))) {
+                state.skipped_calls += 1;
+                return;
+            }
+            state.update(|s| &mut s.active_texture_unit, unit, || unsafe { gl::ActiveTexture(gl::TEXTURE0 + unit) });
+            unsafe { gl::BindTexture(target, texture) };
+            if let Some(bound) = state.textures.get_mut(index) {
+                *bound = Some((target, texture));
+            }
+        });
+    }
+
+    /// Enables or disables a capability such as `gl::BLEND` or `gl::DEPTH_TEST`.
+    pub fn set_capability(capability: GLenum, enabled: bool) {
+        Self::with(|state| {
+            match state.capabilities.iter_mut().find(|(cached, _)| *cached == capability) {
+                Some((_, cached)) if *cached == enabled => {
+                    state.skipped_calls += 1;
+                    return;
+                },
+                Some((_, cached)) => *cached = enabled,
+                None => state.capabilities.push((capability, enabled)),
+            }
+            unsafe {
+                if enabled {
+                    gl::Enable(capability);
+                } else {
+                    gl::Disable(capability);
+                }
+            }
+        });
+    }
+
+    pub fn blend_func(source: GLenum, destination: GLenum) {
+        Self::with(|state| {
+            state.update(|s| &mut s.blend_func, (source, destination), || unsafe { gl::BlendFunc(source, destination) });
+        });
+    }
+
+    /// Turns writing to the depth buffer on or off.
+    pub fn depth_mask(write: bool) {
+        Self::with(|state| {
+            state.update(|s| &mut s.depth_mask, write, || unsafe { gl::DepthMask(if write { gl::TRUE } else { gl::FALSE }) });
+        });
+    }
+
+    /// How many calls were skipped since the last time this was called.
+    pub fn take_skipped_calls() -> u32 {
+        Self::with(|state| std::mem::take(&mut state.skipped_calls))
+    }
+
+    // Deleting a bound object binds 0 in its place, and its name can be reused
+    fn forget_program(program: GLuint) {
+        let _ = GL_STATE.try_with(|state| {
+            let mut state = state.borrow_mut();
+            if state.program == Some(program) {
+                state.program = None;
+            }
+        });
+    }
+
+    fn forget_vertex_array(vertex_array: GLuint) {
+        let _ = GL_STATE.try_with(|state| {
+            let mut state = state.borrow_mut();
+            if state.vertex_array == Some(vertex_array) {
+                state.vertex_array = Some(0);
+            }
+        });
+    }
+
+    fn forget_buffer(buffer: GLuint) {
+        let _ = GL_STATE.try_with(|state| {
+            let mut state = state.borrow_mut();
+            if state.array_buffer == Some(buffer) {
+                state.array_buffer = Some(0);
+            }
+        });
+    }
+
+    /// Deletes a texture, unbinding it from every unit it was bound to.
+    pub fn delete_texture(texture: GLuint) {
+        let _ = GL_STATE.try_with(|state| {
+            for bound in &mut state.borrow_mut().textures {
+                if bound.is_some_and(|(_, bound_texture)| bound_texture == texture) {
+                    *bound = None;
+                }
+            }
+        });
+        unsafe { gl::DeleteTextures(1, &texture) };
+    }
+}
+
 /// The types of shader object.
 #[derive(Debug, Clone, Copy)]
 pub enum ShaderType {
@@ -23,6 +193,8 @@ pub enum BufferType {
     Array = gl::ARRAY_BUFFER as isize,
     /// Element Array Buffers hold indexes of what vertexes to use for drawing.
     ElementArray = gl::ELEMENT_ARRAY_BUFFER as isize,
+    /// Uniform Buffers hold the values of uniform blocks shared between programs.
+    Uniform = gl::UNIFORM_BUFFER as isize,
 }
 
 /// Basic wrapper for a Vertex Array Object.
@@ -41,17 +213,18 @@ impl VertexArray {
 
     /// Bind this vertex array as the current vertex array object
     pub fn bind(&self) {
-        unsafe { gl::BindVertexArray(self.0) }
+        GlState::bind_vertex_array(self.0);
     }
 
     /// Clear the current vertex array object binding.
     pub fn clear_binding() {
-        unsafe { gl::BindVertexArray(0) }
+        GlState::bind_vertex_array(0);
     }
 }
 
 impl Drop for VertexArray {
     fn drop(&mut self) {
+        GlState::forget_vertex_array(self.0);
         unsafe { gl::DeleteVertexArrays(1, &self.0) }
     }
 }
@@ -74,17 +247,24 @@ impl Buffer {
 
     /// Bind this buffer for the given type
     pub fn bind(&self, ty: BufferType) {
-        unsafe { gl::BindBuffer(ty as GLenum, self.0) }
+        GlState::bind_buffer(ty, self.0);
     }
 
     /// Clear the current buffer binding for the given type.
     pub fn clear_binding(ty: BufferType) {
-        unsafe { gl::BindBuffer(ty as GLenum, 0) }
+        GlState::bind_buffer(ty, 0);
+    }
+
+    /// Attach this buffer to a uniform block binding point, where every
+    /// program whose block was assigned that point reads it from.
+    pub fn bind_uniform_base(&self, binding: GLuint) {
+        unsafe { gl::BindBufferBase(gl::UNIFORM_BUFFER, binding, self.0) }
     }
 }
 
 impl Drop for Buffer {
     fn drop(&mut self) {
+        GlState::forget_buffer(self.0);
         unsafe { gl::DeleteBuffers(1, &self.0) }
     }
 }
@@ -101,6 +281,365 @@ pub fn buffer_data(ty: BufferType, data: &[u8], usage: GLenum) {
     }
 }
 
+/// The oldest OpenGL version the game runs on.
+pub const REQUIRED_GL_VERSION: (GLint, GLint) = (3, 3);
+
+thread_local! {
+    static GL_CAPABILITIES: RefCell<Option<GlCapabilities>> = const { RefCell::new(None) };
+}
+
+/// What the current context is and what it can do beyond GL 3.3 core, which
+/// picks between code paths: persistently mapped or orphaned stream buffers,
+/// compressed or plain textures, and whether debug output is turned on.
+#[derive(Debug, Clone)]
+pub struct GlCapabilities {
+    /// `(major, minor)`, which may be below `REQUIRED_GL_VERSION` on old drivers.
+    pub version: (GLint, GLint),
+    /// The GPU and driver, as the driver names them.
+    pub renderer: String,
+    /// Immutable buffer storage, and so persistent mapping (GL 4.4 or ARB_buffer_storage).
+    pub buffer_storage: bool,
+    /// Debug messages from the driver (GL 4.3 or KHR_debug).
+    pub debug_output: bool,
+    /// S3TC compressed textures, which nearly all desktop GPUs read.
+    pub s3tc: bool,
+}
+
+impl GlCapabilities {
+    /// Queries the current context, and keeps the result for `get`.
+    pub fn detect() -> Self {
+        let version = parse_gl_version(&gl_string(gl::VERSION)).unwrap_or((0, 0));
+        // Extensions are listed differently before GL 3, and nothing else works there anyway
+        let modern = version >= REQUIRED_GL_VERSION;
+        let capabilities = Self {
+            version,
+            renderer: gl_string(gl::RENDERER),
+            buffer_storage: modern && (version >= (4, 4) || has_extension("GL_ARB_buffer_storage")),
+            debug_output: modern && (version >= (4, 3) || has_extension("GL_KHR_debug")),
+            s3tc: modern && block_compression_supported(),
+        };
+        GL_CAPABILITIES.with(|cached| *cached.borrow_mut() = Some(capabilities.clone()));
+        capabilities
+    }
+
+    /// The capabilities detected on this thread, detecting them if that wasn't done yet.
+    pub fn get() -> Self {
+        GL_CAPABILITIES.with(|cached| cached.borrow().clone()).unwrap_or_else(Self::detect)
+    }
+
+    /// Whether the game can run on the context at all.
+    pub fn is_supported(&self) -> bool {
+        self.version >= REQUIRED_GL_VERSION
+    }
+
+    /// One line for the log saying what was found.
+    pub fn summary(&self) -> String {
+        let yes_no = |supported| if supported { "yes" } else { "no" };
+        format!(
+            "OpenGL {}.{} on {}; persistent buffers: {}, S3TC: {}, debug output: {}",
+            self.version.0,
+            self.version.1,
+            self.renderer,
+            yes_no(self.buffer_storage),
+            yes_no(self.s3tc),
+            yes_no(self.debug_output),
+        )
+    }
+}
+
+// A string the driver describes itself with, such as gl::VERSION
+fn gl_string(name: GLenum) -> String {
+    let string = unsafe { gl::GetString(name) };
+    if string.is_null() {
+        return String::new();
+    }
+    unsafe { std::ffi::CStr::from_ptr(string.cast()) }.to_string_lossy().into_owned()
+}
+
+// The version at the start of a version string, like "4.6.0 NVIDIA 535.104"
+// or "OpenGL ES 3.2 Mesa 23.1"
+fn parse_gl_version(version: &str) -> Option<(GLint, GLint)> {
+    let number = version.split(|c: char| !c.is_ascii_digit() && c != '.').find(|part| part.contains('.'))?;
+    let mut parts = number.split('.');
+    Some((parts.next()?.parse().ok()?, parts.next()?.parse().ok()?))
+}
+
+// Checks if the current context advertises the given extension
+fn has_extension(name: &str) -> bool {
+    let mut count = 0;
+    unsafe { gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut count) };
+    (0..count as GLuint).any(|i| {
+        let ext = unsafe { std::ffi::CStr::from_ptr(gl::GetStringi(gl::EXTENSIONS, i).cast()) };
+        ext.to_bytes() == name.as_bytes()
+    })
+}
+
+// A persistently mapped buffer along with fences for regions the GPU may still read
+struct PersistentMapping {
+    ptr: *mut u8,
+    // (start, generation, fence) for each fenced region, oldest first
+    fences: std::collections::VecDeque<(usize, u32, gl::types::GLsync)>,
+    fenced_up_to: usize,
+}
+
+/// A fixed-size buffer for data that is rewritten often, used as a ring.
+///
+/// Writes are appended after the previous one. When the ring runs out of room
+/// it starts over at the front: with plain buffers the storage is orphaned so
+/// the driver never waits on draws reading the old contents, and with
+/// persistently mapped storage (GL 4.4) the writer waits on the fences of the
+/// regions it is about to overwrite. Either way, everything written before the
+/// wrap is gone, which owners detect through [`StreamBuffer::generation`].
+pub struct StreamBuffer {
+    buffer: Buffer,
+    ty: BufferType,
+    capacity: usize,
+    head: usize,
+    generation: u32,
+    mapping: Option<PersistentMapping>,
+}
+
+impl StreamBuffer {
+    /// Allocates a stream buffer of `capacity` bytes, persistently mapped when
+    /// the context supports it. The buffer is left bound to `ty`.
+    pub fn new(ty: BufferType, capacity: usize) -> Option<Self> {
+        let buffer = Buffer::new()?;
+        buffer.bind(ty);
+
+        let mapping = if GlCapabilities::get().buffer_storage {
+            let flags = gl::MAP_WRITE_BIT | gl::MAP_PERSISTENT_BIT | gl::MAP_COHERENT_BIT;
+            let ptr = unsafe {
+                gl::BufferStorage(ty as GLenum, capacity as isize, std::ptr::null(), flags);
+                gl::MapBufferRange(ty as GLenum, 0, capacity as isize, flags)
+            };
+            if ptr.is_null() {
+                return None;
+            }
+            Some(PersistentMapping {
+                ptr: ptr.cast(),
+                fences: std::collections::VecDeque::new(),
+                fenced_up_to: 0,
+            })
+        } else {
+            unsafe { gl::BufferData(ty as GLenum, capacity as isize, std::ptr::null(), gl::STREAM_DRAW) };
+            None
+        };
+
+        Some(Self {
+            buffer,
+            ty,
+            capacity,
+            head: 0,
+            generation: 0,
+            mapping,
+        })
+    }
+
+    /// Bind this buffer for its type.
+    pub fn bind(&self) {
+        self.buffer.bind(self.ty);
+    }
+
+    /// Whether writes go straight into persistently mapped memory.
+    pub fn is_persistent(&self) -> bool {
+        self.mapping.is_some()
+    }
+
+    /// Size of the buffer in bytes.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Counts how many times the ring has wrapped around. Data written in an
+    /// earlier generation is no longer valid.
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    /// Writes `data` at the next offset that is a multiple of `alignment` and
+    /// returns that offset in bytes, or `None` if the data can never fit.
+    pub fn write(&mut self, data: &[u8], alignment: usize) -> Option<usize> {
+        if data.len() > self.capacity {
+            return None;
+        }
+
+        let mut offset = self.head.next_multiple_of(alignment.max(1));
+        if offset + data.len() > self.capacity {
+            self.restart();
+            offset = 0;
+        }
+        let end = offset + data.len();
+        let generation = self.generation;
+
+        match &mut self.mapping {
+            Some(mapping) => {
+                // Wait for the GPU to finish with anything from the previous lap we're about to overwrite
+                while let Some(&(start, fence_generation, fence)) = mapping.fences.front() {
+                    if fence_generation == generation || start >= end {
+                        break;
+                    }
+                    unsafe {
+                        gl::ClientWaitSync(fence, gl::SYNC_FLUSH_COMMANDS_BIT, u64::MAX);
+                        gl::DeleteSync(fence);
+                    }
+                    mapping.fences.pop_front();
+                }
+                unsafe { std::ptr::copy_nonoverlapping(data.as_ptr(), mapping.ptr.add(offset), data.len()) };
+            },
+            None => {
+                self.buffer.bind(self.ty);
+                unsafe {
+                    gl::BufferSubData(self.ty as GLenum, offset as isize, data.len() as isize, data.as_ptr().cast());
+                }
+            },
+        }
+
+        self.head = end;
+        Some(offset)
+    }
+
+    /// Marks everything written since the last call as in use by the commands
+    /// submitted so far. Call once per frame after issuing the draws.
+    pub fn fence(&mut self) {
+        if let Some(mapping) = &mut self.mapping {
+            if self.head > mapping.fenced_up_to {
+                let fence = unsafe { gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0) };
+                mapping.fences.push_back((mapping.fenced_up_to, self.generation, fence));
+                mapping.fenced_up_to = self.head;
+            }
+        }
+    }
+
+    /// Starts writing from the front of the buffer again, invalidating
+    /// everything written so far.
+    pub fn restart(&mut self) {
+        match &mut self.mapping {
+            Some(mapping) => {
+                // Fence the tail so it is waited on like any other region
+                if self.head > mapping.fenced_up_to {
+                    let fence = unsafe { gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0) };
+                    mapping.fences.push_back((mapping.fenced_up_to, self.generation, fence));
+                }
+                mapping.fenced_up_to = 0;
+            },
+            None => {
+                // Orphan the old storage, the driver keeps it alive until pending draws finish
+                self.buffer.bind(self.ty);
+                unsafe {
+                    gl::BufferData(self.ty as GLenum, self.capacity as isize, std::ptr::null(), gl::STREAM_DRAW);
+                }
+            },
+        }
+        self.head = 0;
+        self.generation += 1;
+    }
+}
+
+impl Drop for StreamBuffer {
+    fn drop(&mut self) {
+        if let Some(mapping) = &self.mapping {
+            unsafe {
+                for &(_, _, fence) in &mapping.fences {
+                    gl::DeleteSync(fence);
+                }
+                self.buffer.bind(self.ty);
+                gl::UnmapBuffer(self.ty as GLenum);
+            }
+        }
+    }
+}
+
+// Queries each GpuTimer cycles through, so results are read a few frames
+// late instead of waiting for the GPU to catch up
+const TIMER_QUERIES: usize = 4;
+// Weight of the latest result in a GpuTimer's average
+const TIMER_SMOOTHING: f32 = 0.05;
+
+/// Measures how long the GPU spends on the commands issued between `begin`
+/// and `end`, with `GL_TIME_ELAPSED` queries. Results arrive a few frames
+/// after the commands ran and are averaged over time.
+///
+/// Only one timer can be running at a time, so passes are timed one after
+/// the other rather than nested.
+pub struct GpuTimer {
+    // None for disabled timers
+    queries: Option<[GLuint; TIMER_QUERIES]>,
+    // Whether each query was ended and its result not read yet
+    pending: [bool; TIMER_QUERIES],
+    next: usize,
+    milliseconds: f32,
+}
+
+impl GpuTimer {
+    pub fn new() -> Option<Self> {
+        let mut queries = [0; TIMER_QUERIES];
+        unsafe { gl::GenQueries(TIMER_QUERIES as GLsizei, queries.as_mut_ptr()) };
+        if queries.contains(&0) {
+            return None;
+        }
+        Some(Self { queries: Some(queries), pending: [false; TIMER_QUERIES], next: 0, milliseconds: 0.0 })
+    }
+
+    /// A timer that measures nothing and always reads 0, for frames not drawn
+    /// with OpenGL.
+    pub fn disabled() -> Self {
+        Self { queries: None, pending: [false; TIMER_QUERIES], next: 0, milliseconds: 0.0 }
+    }
+
+    /// Starts timing. Skipped if the next query is still waiting for its result,
+    /// which only happens when the GPU is several frames behind.
+    pub fn begin(&mut self) {
+        self.collect();
+        if let Some(queries) = self.queries.filter(|_| !self.pending[self.next]) {
+            unsafe { gl::BeginQuery(gl::TIME_ELAPSED, queries[self.next]) };
+        }
+    }
+
+    /// Stops timing what was started with `begin`.
+    pub fn end(&mut self) {
+        if self.queries.is_some() && !self.pending[self.next] {
+            unsafe { gl::EndQuery(gl::TIME_ELAPSED) };
+            self.pending[self.next] = true;
+            self.next = (self.next + 1) % TIMER_QUERIES;
+        }
+    }
+
+    /// Average GPU time of the timed commands, in milliseconds.
+    pub fn milliseconds(&self) -> f32 {
+        self.milliseconds
+    }
+
+    // Reads the results that are ready, oldest first
+    fn collect(&mut self) {
+        let Some(queries) = self.queries else {
+            return;
+        };
+        for offset in 0..TIMER_QUERIES {
+            let index = (self.next + offset) % TIMER_QUERIES;
+            if !self.pending[index] {
+                continue;
+            }
+            let mut available = 0;
+            unsafe { gl::GetQueryObjectiv(queries[index], gl::QUERY_RESULT_AVAILABLE, &mut available) };
+            if available == 0 {
+                return;
+            }
+            let mut nanoseconds = 0u64;
+            unsafe { gl::GetQueryObjectui64v(queries[index], gl::QUERY_RESULT, &mut nanoseconds) };
+            self.pending[index] = false;
+            self.milliseconds += (nanoseconds as f32 / 1_000_000.0 - self.milliseconds) * TIMER_SMOOTHING;
+        }
+    }
+}
+
+impl Drop for GpuTimer {
+    fn drop(&mut self) {
+        if let Some(queries) = &self.queries {
+            unsafe { gl::DeleteQueries(TIMER_QUERIES as GLsizei, queries.as_ptr()) };
+        }
+    }
+}
+
 /// A handle to a Shader Object
 pub struct Shader(pub GLuint);
 impl Shader {
@@ -223,7 +762,21 @@ impl ShaderProgram {
 
     /// Sets the program as the program to use when drawing.
     pub fn use_program(&self) {
-        unsafe { gl::UseProgram(self.0) };
+        GlState::use_program(self.0);
+    }
+
+    /// Points the uniform block with this name at a binding point. Returns
+    /// false when the program has no such block, or the shaders never use it.
+    pub fn bind_uniform_block(&self, name: &str, binding: GLuint) -> bool {
+        let name = CString::new(name).expect("Uniform block name with a nul byte");
+        unsafe {
+            let index = gl::GetUniformBlockIndex(self.0, name.as_ptr());
+            if index == gl::INVALID_INDEX {
+                return false;
+            }
+            gl::UniformBlockBinding(self.0, index, binding);
+        }
+        true
     }
 
     /// Takes a vertex shader source string and a fragment shader source string
@@ -248,15 +801,107 @@ impl ShaderProgram {
 
 impl Drop for ShaderProgram {
     fn drop(&mut self) {
+        GlState::forget_program(self.0);
         unsafe { gl::DeleteProgram(self.0) }
     }
 }
 
-pub fn load_texture(path: &str) -> GLuint {
+// Whether the GPU reads S3TC compressed textures, which nearly all desktop ones do
+fn block_compression_supported() -> bool {
+    unsafe {
+        let mut count = 0;
+        gl::GetIntegerv(gl::NUM_COMPRESSED_TEXTURE_FORMATS, &mut count);
+        let mut formats = vec![0; count.max(0) as usize];
+        if count > 0 {
+            gl::GetIntegerv(gl::COMPRESSED_TEXTURE_FORMATS, formats.as_mut_ptr());
+        }
+        [COMPRESSED_RGBA_S3TC_DXT1_EXT, COMPRESSED_RGBA_S3TC_DXT5_EXT]
+            .iter()
+            .all(|&format| formats.contains(&(format as GLint)))
+    }
+}
+
+// The compressed version of an image, if it has a usable one. Broken files are
+// reported, and the image is used instead.
+fn load_compressed(image_path: &Path) -> Option<CompressedImage> {
+    let path = compressed_version(image_path)?;
+    if !GlCapabilities::get().s3tc {
+        log::debug!(target: "render", "No S3TC support, ignoring {}", path.display());
+        return None;
+    }
+    match CompressedImage::load(&path) {
+        Ok(image) => Some(image),
+        Err(e) => {
+            log::warn!(target: "render", "Failed to load compressed texture {}: {}", path.display(), e);
+            None
+        },
+    }
+}
+
+// Whether compressed images can be the layers of one array texture
+fn matching_layers(layers: &[CompressedImage]) -> bool {
+    let Some(first) = layers.first() else {
+        return false;
+    };
+    let matching = first.width == first.height && layers.iter().all(|layer| {
+        (layer.format, layer.width, layer.height, layer.levels.len()) == (first.format, first.width, first.height, first.levels.len())
+    });
+    if !matching {
+        log::warn!(target: "render", "Compressed textures differ in format, size or mip levels, using the images instead");
+    }
+    matching
+}
+
+// Creates a 2D texture from one compressed image, or a 2D array texture from
+// several, keeping the mip levels they came with
+fn upload_compressed(target: GLenum, layers: &[CompressedImage]) -> GLuint {
+    let first = &layers[0];
+    let internal_format = match first.format {
+        BlockFormat::Bc1 => COMPRESSED_RGBA_S3TC_DXT1_EXT,
+        BlockFormat::Bc3 => COMPRESSED_RGBA_S3TC_DXT5_EXT,
+    };
+    let level_count = first.levels.len();
+
     let mut texture = 0;
     unsafe {
         gl::GenTextures(1, &mut texture);
-        gl::BindTexture(gl::TEXTURE_2D, texture);
+        GlState::bind_texture(0, target, texture);
+        gl::TexParameteri(target, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
+        gl::TexParameteri(target, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
+        let min_filter = if level_count > 1 { gl::NEAREST_MIPMAP_LINEAR } else { gl::NEAREST };
+        gl::TexParameteri(target, gl::TEXTURE_MIN_FILTER, min_filter as i32);
+        gl::TexParameteri(target, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+        gl::TexParameteri(target, gl::TEXTURE_MAX_LEVEL, level_count as i32 - 1);
+
+        for level in 0..level_count {
+            let width = (first.width >> level).max(1) as i32;
+            let height = (first.height >> level).max(1) as i32;
+            if target == gl::TEXTURE_2D_ARRAY {
+                // Each level holds every layer, one after the other
+                let data: Vec<u8> = layers.iter().flat_map(|layer| layer.levels[level].iter().copied()).collect();
+                gl::CompressedTexImage3D(target, level as i32, internal_format, width, height, layers.len() as i32, 0,
+                    data.len() as i32, data.as_ptr() as *const _);
+            } else {
+                let data = &first.levels[level];
+                gl::CompressedTexImage2D(target, level as i32, internal_format, width, height, 0,
+                    data.len() as i32, data.as_ptr() as *const _);
+            }
+        }
+    }
+    texture
+}
+
+/// Loads an image into a 2D texture. A `.ktx2` or `.dds` file with the same
+/// name is used instead when there is one and the GPU can read it.
+pub fn load_texture(path: &str) -> Result<GLuint, EngineError> {
+    if let Some(image) = load_compressed(Path::new(path)) {
+        return Ok(upload_compressed(gl::TEXTURE_2D, &[image]));
+    }
+
+    let mut texture = 0;
+    unsafe {
+        gl::GenTextures(1, &mut texture);
+        GlState::bind_texture(0, gl::TEXTURE_2D, texture);
         
         // Set texture wrapping/filtering options
         gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
@@ -265,7 +910,7 @@ pub fn load_texture(path: &str) -> GLuint {
         gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
 
         // Load and generate the texture
-        let img = image::open(path).expect("Failed to load texture");
+        let img = image::open(path).map_err(|source| EngineError::Texture { path: path.into(), source })?;
         let data = img.to_rgba8();
         
         gl::TexImage2D(
@@ -280,5 +925,71 @@ pub fn load_texture(path: &str) -> GLuint {
             data.as_ptr() as *const _
         );
     }
-    texture
-} 
\ No newline at end of file
+    Ok(texture)
+}
+
+/// Loads a list of images into the layers of a 2D array texture. Every layer
+/// takes the size of the first image; animated strips only keep their first frame.
+///
+/// When every image has a compressed version, like `load_texture` looks for,
+/// and they all share a format, a square size and mip levels, those are used
+/// instead. Otherwise every layer comes from the images.
+pub fn load_texture_array(paths: &[&str]) -> Result<GLuint, EngineError> {
+    let compressed: Option<Vec<CompressedImage>> = paths.iter().map(|path| load_compressed(Path::new(path))).collect();
+    if let Some(layers) = compressed.filter(|layers| matching_layers(layers)) {
+        return Ok(upload_compressed(gl::TEXTURE_2D_ARRAY, &layers));
+    }
+
+    let mut texture = 0;
+    unsafe {
+        gl::GenTextures(1, &mut texture);
+        GlState::bind_texture(0, gl::TEXTURE_2D_ARRAY, texture);
+
+        // Set texture wrapping/filtering options
+        gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
+        gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
+        gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+        gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+
+        let mut size = 0;
+        for (layer, path) in paths.iter().enumerate() {
+            let img = image::open(path).map_err(|source| EngineError::Texture { path: path.into(), source })?;
+            let frame = img.crop_imm(0, 0, img.width(), img.width().min(img.height()));
+
+            // Allocate storage for all layers once the size is known
+            if layer == 0 {
+                size = frame.width();
+                gl::TexImage3D(
+                    gl::TEXTURE_2D_ARRAY,
+                    0,
+                    gl::RGBA as i32,
+                    size as i32,
+                    size as i32,
+                    paths.len() as i32,
+                    0,
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    std::ptr::null()
+                );
+            }
+
+            let data = frame
+                .resize_exact(size, size, image::imageops::FilterType::Nearest)
+                .to_rgba8();
+            gl::TexSubImage3D(
+                gl::TEXTURE_2D_ARRAY,
+                0,
+                0,
+                0,
+                layer as i32,
+                size as i32,
+                size as i32,
+                1,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                data.as_ptr() as *const _
+            );
+        }
+    }
+    Ok(texture)
+}
@@ -0,0 +1,186 @@
+use crate::hud::Hud;
+use crate::inventory::hotbar_position;
+
+/// Most health the player can have, in half hearts.
+pub const MAX_HEALTH: u32 = 20;
+// Falls up to this many blocks don't hurt; every block further takes a point
+const SAFE_FALL_DISTANCE: f32 = 3.0;
+// Seconds of breath under water, and how many come back per second above it
+const MAX_AIR: f32 = 10.0;
+const AIR_REFILL_RATE: f32 = 5.0;
+// Once out of breath, a point of damage is taken this often, in seconds
+const DROWN_INTERVAL: f32 = 0.5;
+// After getting hurt, further damage is ignored for this long
+const INVULNERABLE_TIME: f32 = 0.5;
+// The screen flashes red this long after getting hurt, starting this opaque
+const FLASH_TIME: f32 = 0.4;
+const FLASH_ALPHA: f32 = 0.35;
+
+// Hearts and air bubbles as pixel art: one string per row, `#` for a filled pixel
+const HEART: [&str; 6] = [
+    ".##.##.",
+    "#######",
+    "#######",
+    ".#####.",
+    "..###..",
+    "...#...",
+];
+const BUBBLE: [&str; 6] = [
+    ".####.",
+    "#..###",
+    "#...##",
+    "#....#",
+    "#....#",
+    ".####.",
+];
+// Size of a pixel of the pixel art, the gap between icons and above the hotbar
+const ICON_PIXEL: f32 = 2.5;
+const ICON_GAP: f32 = 2.0;
+const ICON_MARGIN: f32 = 6.0;
+const HEART_COLOR: [f32; 4] = [0.85, 0.1, 0.1, 1.0];
+const EMPTY_HEART_COLOR: [f32; 4] = [0.15, 0.05, 0.05, 0.8];
+const BUBBLE_COLOR: [f32; 4] = [0.6, 0.8, 1.0, 1.0];
+
+/// What hurt the player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DamageCause {
+    Fall,
+    Drowning,
+    Explosion,
+}
+
+impl DamageCause {
+    pub fn name(self) -> &'static str {
+        match self {
+            DamageCause::Fall => "fall",
+            DamageCause::Drowning => "drowning",
+            DamageCause::Explosion => "explosion",
+        }
+    }
+}
+
+/// The player's health and breath, counted in half hearts and seconds, and
+/// drawn as hearts and bubbles above the hotbar. Getting hurt flashes the
+/// screen red. Runs on physics steps, so replays hurt the player the same way.
+pub struct Health {
+    points: u32,
+    air: f32,
+    // Time towards the next point of drowning damage
+    drowning: f32,
+    // Seconds left of invulnerability and of the red flash
+    invulnerable: f32,
+    flash: f32,
+    // Damage taken since the last `take_hurts`
+    hurts: Vec<(u32, DamageCause)>,
+}
+
+impl Health {
+    pub fn new() -> Self {
+        Self {
+            points: MAX_HEALTH,
+            air: MAX_AIR,
+            drowning: 0.0,
+            invulnerable: 0.0,
+            flash: 0.0,
+            hurts: Vec::new(),
+        }
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.points == 0
+    }
+
+    /// Takes `amount` points of damage, unless just hurt. Returns whether it hurt.
+    pub fn damage(&mut self, amount: u32, cause: DamageCause) -> bool {
+        if amount == 0 || self.invulnerable > 0.0 || self.is_dead() {
+            return false;
+        }
+        self.points = self.points.saturating_sub(amount);
+        self.invulnerable = INVULNERABLE_TIME;
+        self.flash = FLASH_TIME;
+        self.hurts.push((amount, cause));
+        log::info!(target: "game", "Took {} damage from {} ({} left)", amount, cause.name(), self.points);
+        true
+    }
+
+    /// The damage taken since the last call, each as its amount and cause.
+    pub fn take_hurts(&mut self) -> Vec<(u32, DamageCause)> {
+        std::mem::take(&mut self.hurts)
+    }
+
+    /// Hurts the player for landing after falling `distance` blocks.
+    pub fn fall(&mut self, distance: f32) {
+        let amount = (distance - SAFE_FALL_DISTANCE).floor().max(0.0) as u32;
+        self.damage(amount, DamageCause::Fall);
+    }
+
+    /// Runs one step of breathing, given whether the player's head is under
+    /// water: breath runs out while it is, and then the player drowns.
+    pub fn update(&mut self, head_under_water: bool, delta_time: f32) {
+        self.invulnerable = (self.invulnerable - delta_time).max(0.0);
+        self.flash = (self.flash - delta_time).max(0.0);
+        if !head_under_water {
+            self.air = (self.air + AIR_REFILL_RATE * delta_time).min(MAX_AIR);
+            self.drowning = 0.0;
+            return;
+        }
+        self.air = (self.air - delta_time).max(0.0);
+        if self.air == 0.0 {
+            self.drowning += delta_time;
+            if self.drowning >= DROWN_INTERVAL {
+                self.drowning -= DROWN_INTERVAL;
+                self.damage(1, DamageCause::Drowning);
+            }
+        }
+    }
+
+    /// Back to full health and breath, such as after respawning.
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Queues the hearts above the left of the hotbar, the air bubbles above
+    /// its right while short of breath, and the red flash over the screen
+    /// unless `flashing` is off.
+    pub fn draw(&self, hud: &mut Hud, screen_width: f32, screen_height: f32, flashing: bool) {
+        if flashing && self.flash > 0.0 {
+            let alpha = FLASH_ALPHA * self.flash / FLASH_TIME;
+            hud.rect(0.0, 0.0, screen_width, screen_height, [0.8, 0.0, 0.0, alpha]);
+        }
+
+        let (left, hotbar_top, width) = hotbar_position(screen_width, screen_height);
+        let top = hotbar_top - ICON_MARGIN - HEART.len() as f32 * ICON_PIXEL;
+        let heart_width = HEART[0].len() as f32 * ICON_PIXEL;
+        for heart in 0..MAX_HEALTH / 2 {
+            let x = left + heart as f32 * (heart_width + ICON_GAP);
+            draw_icon(hud, &HEART, x, top, EMPTY_HEART_COLOR, usize::MAX);
+            // A half heart fills the columns up to the middle
+            let filled = match self.points.saturating_sub(heart * 2) {
+                0 => 0,
+                1 => HEART[0].len().div_ceil(2),
+                _ => usize::MAX,
+            };
+            draw_icon(hud, &HEART, x, top, HEART_COLOR, filled);
+        }
+
+        if self.air < MAX_AIR {
+            let bubble_width = BUBBLE[0].len() as f32 * ICON_PIXEL;
+            let bubbles = (self.air / MAX_AIR * 10.0).ceil() as usize;
+            for bubble in 0..bubbles {
+                let x = left + width - (bubble + 1) as f32 * (bubble_width + ICON_GAP) + ICON_GAP;
+                draw_icon(hud, &BUBBLE, x, top, BUBBLE_COLOR, usize::MAX);
+            }
+        }
+    }
+}
+
+// Draws the columns of pixel art left of `columns` at a top left corner
+fn draw_icon(hud: &mut Hud, rows: &[&str], x: f32, y: f32, color: [f32; 4], columns: usize) {
+    for (row, pixels) in rows.iter().enumerate() {
+        for (column, pixel) in pixels.chars().enumerate().take(columns) {
+            if pixel == '#' {
+                hud.rect(x + column as f32 * ICON_PIXEL, y + row as f32 * ICON_PIXEL, ICON_PIXEL, ICON_PIXEL, color);
+            }
+        }
+    }
+}
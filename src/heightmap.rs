@@ -0,0 +1,44 @@
+use crate::world::{CHUNK_SIZE, WORLD_HEIGHT_CHUNKS};
+use std::path::Path;
+
+/// Height in blocks between the darkest and brightest pixel when `--scale`
+/// isn't given.
+pub const DEFAULT_HEIGHTMAP_SCALE: f32 = 64.0;
+// Height of the ground at black pixels, below sea level so the lowest land floods
+const BASE_HEIGHT: f32 = 40.0;
+
+/// Terrain heights read from a grayscale image, one pixel per block column,
+/// used instead of noise. The image is centered on the origin, and its edge
+/// pixels stretch out forever beyond it.
+pub struct Heightmap {
+    width: usize,
+    depth: usize,
+    heights: Vec<i32>,
+}
+
+impl Heightmap {
+    /// Reads an image, taking 16-bit samples where it has them so DEM data
+    /// keeps its precision. `scale` is the height in blocks a white pixel
+    /// stands above a black one.
+    pub fn load(path: &Path, scale: f32) -> Result<Self, String> {
+        let image = image::open(path).map_err(|e| e.to_string())?.to_luma16();
+        let (width, depth) = (image.width() as usize, image.height() as usize);
+        if width == 0 || depth == 0 {
+            return Err("The heightmap is empty".to_string());
+        }
+
+        // Leave room for a layer of snow or plants under the top of the world
+        let highest = (WORLD_HEIGHT_CHUNKS * CHUNK_SIZE as i32 - 2) as f32;
+        let heights = image.pixels()
+            .map(|pixel| (BASE_HEIGHT + pixel[0] as f32 / u16::MAX as f32 * scale).clamp(1.0, highest) as i32)
+            .collect();
+        log::info!(target: "worldgen", "Loaded {}x{} heightmap {}", width, depth, path.display());
+        Ok(Self { width, depth, heights })
+    }
+
+    /// Height of the first block above the ground of a column.
+    pub fn height(&self, world_x: i32, world_z: i32) -> i32 {
+        let pixel = |value: i32, size: usize| (value + size as i32 / 2).clamp(0, size as i32 - 1) as usize;
+        self.heights[pixel(world_z, self.depth) * self.width + pixel(world_x, self.width)]
+    }
+}
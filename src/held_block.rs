@@ -0,0 +1,81 @@
+use crate::animation::Track;
+use crate::block::BlockType;
+use crate::entity::MeshHandle;
+use crate::entity_renderer::EntityRenderer;
+use crate::math::{Mat4, Quat, Transform, Vec3};
+use std::collections::HashMap;
+
+// The held block has its own projection, so it looks the same whatever the FOV setting
+const HELD_FOV_DEGREES: f32 = 70.0;
+const HELD_SIZE: f32 = 0.4;
+// Where the block rests, in view space: right of and below the crosshair
+const REST_POSITION: Vec3 = Vec3 { x: 0.55, y: -0.45, z: -0.9 };
+// Turned so two sides show, like a block seen from above a corner
+const REST_YAW: f32 = std::f32::consts::FRAC_PI_4;
+
+// Swing keyframes: the block dips, pushes forward and tips over, then comes back
+const SWING_DROP: Track = Track::new(&[(0.0, 0.0), (0.08, -0.12), (0.25, 0.0)]);
+const SWING_PUSH: Track = Track::new(&[(0.0, 0.0), (0.08, -0.2), (0.25, 0.0)]);
+const SWING_TILT: Track = Track::new(&[(0.0, 0.0), (0.08, -0.7), (0.25, 0.0)]);
+
+/// The selected block drawn in the corner of the view, swinging when a block
+/// is broken or placed.
+pub struct HeldBlock {
+    meshes: HashMap<BlockType, MeshHandle>,
+    // Seconds since the current swing started
+    swing: Option<f32>,
+}
+
+impl HeldBlock {
+    pub fn new() -> Self {
+        Self {
+            meshes: HashMap::new(),
+            swing: None,
+        }
+    }
+
+    /// Starts a swing, restarting one already under way.
+    pub fn swing(&mut self) {
+        self.swing = Some(0.0);
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        if let Some(time) = &mut self.swing {
+            *time += delta_time;
+            if *time >= SWING_TILT.duration() {
+                self.swing = None;
+            }
+        }
+    }
+
+    /// Draws `block` over the world, after clearing the depth buffer so it never
+    /// sinks into nearby terrain. Textures must be bound as for entities.
+    pub fn draw(&mut self, renderer: &mut EntityRenderer, block: BlockType, aspect: f32) {
+        let mesh = match self.meshes.get(&block) {
+            Some(&mesh) => mesh,
+            None => {
+                let Some(mesh) = renderer.add_block_cube(block.info().textures) else {
+                    return;
+                };
+                self.meshes.insert(block, mesh);
+                mesh
+            },
+        };
+
+        let time = self.swing.unwrap_or(0.0);
+        let offset = Vec3::new(0.0, SWING_DROP.sample(time), SWING_PUSH.sample(time));
+        let transform = Transform {
+            translation: REST_POSITION + offset,
+            rotation: Quat::from_axis_angle(Vec3::new(1.0, 0.0, 0.0), SWING_TILT.sample(time)) * Quat::from_yaw(REST_YAW),
+            scale: Vec3::new(HELD_SIZE, HELD_SIZE, HELD_SIZE),
+        };
+        // The cube stands on y = 0; center it on its rest position
+        let model = transform.to_mat4() * Mat4::translate(Vec3::new(0.0, -0.5, 0.0));
+        let projection = Mat4::perspective(HELD_FOV_DEGREES.to_radians(), aspect, 0.05, 10.0);
+
+        unsafe {
+            gl::Clear(gl::DEPTH_BUFFER_BIT);
+        }
+        renderer.draw_mesh(mesh, &projection, &model);
+    }
+}
@@ -0,0 +1,50 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+
+// How often watched folders are looked at
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Notices files being added to, removed from or saved in a few folders, so
+/// content can be reloaded while the game runs. Folders are polled for their
+/// files' modification times, which is cheap for the handful of files
+/// content folders hold. Subfolders aren't looked into.
+pub struct FolderWatcher {
+    directories: Vec<PathBuf>,
+    // Modification time of every file seen on the last poll
+    files: HashMap<PathBuf, SystemTime>,
+    last_poll: Instant,
+}
+
+impl FolderWatcher {
+    /// Starts watching `directories`, which don't have to exist yet.
+    pub fn new(directories: Vec<PathBuf>) -> Self {
+        let files = scan(&directories);
+        Self { directories, files, last_poll: Instant::now() }
+    }
+
+    /// Whether anything changed since the last call that said so. Looks at
+    /// most once per `POLL_INTERVAL`, answering no in between.
+    pub fn poll(&mut self) -> bool {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return false;
+        }
+        self.last_poll = Instant::now();
+        let files = scan(&self.directories);
+        let changed = files != self.files;
+        self.files = files;
+        changed
+    }
+}
+
+// The modification time of every entry of `directories`
+fn scan(directories: &[PathBuf]) -> HashMap<PathBuf, SystemTime> {
+    directories.iter()
+        .flat_map(|directory| fs::read_dir(directory).into_iter().flatten().flatten())
+        .filter_map(|entry| {
+            let modified = entry.metadata().and_then(|metadata| metadata.modified()).ok()?;
+            Some((entry.path(), modified))
+        })
+        .collect()
+}
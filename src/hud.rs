@@ -0,0 +1,275 @@
+use crate::block::BlockTexture;
+use crate::gl_utils::{buffer_data, Buffer, BufferType, GlState, ShaderProgram, VertexArray};
+use crate::palette::Palette;
+use crate::text::{GlyphAtlas, FONT_TEXTURE_UNIT};
+use crate::ttf::Font;
+
+type HudVertex = [f32; 9];  // x, y (pixels), s, t, textureIndex, r, g, b, a
+
+// Texture indices of quads that don't show a block texture, as the shader tells them apart
+const SOLID_QUAD: f32 = -1.0;
+const GLYPH_QUAD: f32 = -2.0;
+// Width of a bitmap glyph plus spacing, in cells
+const GLYPH_ADVANCE: f32 = 4.0;
+// Height of a line of bitmap glyphs, in cells: 5 rows with one free above and below
+const GLYPH_LINE_HEIGHT: f32 = 7.0;
+
+// 3x5 bitmap of a character, one bit per cell, top row in the highest bits.
+// Letters are drawn uppercase whatever their case, and without their accents.
+fn glyph(c: char) -> Option<u16> {
+    let bits = match base_letter(c).to_ascii_uppercase() {
+        '0' => 0b111_101_101_101_111,
+        '1' => 0b010_110_010_010_111,
+        '2' => 0b111_001_111_100_111,
+        '3' => 0b111_001_111_001_111,
+        '4' => 0b101_101_111_001_001,
+        '5' => 0b111_100_111_001_111,
+        '6' => 0b111_100_111_101_111,
+        '7' => 0b111_001_010_010_010,
+        '8' => 0b111_101_111_101_111,
+        '9' => 0b111_101_111_001_111,
+        'A' => 0b010_101_111_101_101,
+        'B' => 0b110_101_110_101_110,
+        'C' => 0b011_100_100_100_011,
+        'D' => 0b110_101_101_101_110,
+        'E' => 0b111_100_110_100_111,
+        'F' => 0b111_100_110_100_100,
+        'G' => 0b011_100_101_101_011,
+        'H' => 0b101_101_111_101_101,
+        'I' => 0b111_010_010_010_111,
+        'J' => 0b001_001_001_101_010,
+        'K' => 0b101_101_110_101_101,
+        'L' => 0b100_100_100_100_111,
+        'M' => 0b101_111_111_101_101,
+        'N' => 0b110_101_101_101_101,
+        'O' => 0b010_101_101_101_010,
+        'P' => 0b110_101_110_100_100,
+        'Q' => 0b010_101_101_110_011,
+        'R' => 0b110_101_110_101_101,
+        'S' => 0b011_100_010_001_110,
+        'T' => 0b111_010_010_010_010,
+        'U' => 0b101_101_101_101_111,
+        'V' => 0b101_101_101_101_010,
+        'W' => 0b101_101_111_111_101,
+        'X' => 0b101_101_010_101_101,
+        'Y' => 0b101_101_010_010_010,
+        'Z' => 0b111_001_010_100_111,
+        ' ' => 0,
+        '.' => 0b000_000_000_000_010,
+        ',' => 0b000_000_000_010_100,
+        ':' => 0b000_010_000_010_000,
+        ';' => 0b000_010_000_010_100,
+        '/' => 0b001_001_010_100_100,
+        '-' => 0b000_000_111_000_000,
+        '_' => 0b000_000_000_000_111,
+        '~' => 0b000_011_110_000_000,
+        '>' => 0b100_010_001_010_100,
+        '<' => 0b001_010_100_010_001,
+        '(' => 0b010_100_100_100_010,
+        ')' => 0b010_001_001_001_010,
+        '[' => 0b110_100_100_100_110,
+        ']' => 0b011_001_001_001_011,
+        '=' => 0b000_111_000_111_000,
+        '+' => 0b000_010_111_010_000,
+        '!' => 0b010_010_010_000_010,
+        '?' => 0b110_001_010_000_010,
+        '\'' => 0b010_010_000_000_000,
+        '"' => 0b101_101_000_000_000,
+        '|' => 0b010_010_010_010_010,
+        '*' => 0b000_101_010_101_000,
+        '#' => 0b101_111_101_111_101,
+        '%' => 0b101_001_010_100_101,
+        _ => return None,
+    };
+    Some(bits)
+}
+
+// The letter an accented Latin letter is based on, or the character itself
+fn base_letter(c: char) -> char {
+    match c {
+        'à'..='å' | 'À'..='Å' => 'a',
+        'ç' | 'Ç' => 'c',
+        'è'..='ë' | 'È'..='Ë' => 'e',
+        'ì'..='ï' | 'Ì'..='Ï' => 'i',
+        'ñ' | 'Ñ' => 'n',
+        'ò'..='ö' | 'Ò'..='Ö' | 'ø' | 'Ø' => 'o',
+        'ù'..='ü' | 'Ù'..='Ü' => 'u',
+        'ý' | 'ÿ' | 'Ý' => 'y',
+        'ß' => 's',
+        _ => c,
+    }
+}
+
+// `rect` is x, y, width and height in pixels
+fn push_quad(batch: &mut Vec<HudVertex>, rect: [f32; 4], tex_coords: [f32; 4], layer: f32, color: [f32; 4]) {
+    let [x, y, width, height] = rect;
+    let [r, g, b, a] = color;
+    let [left, top, right, bottom] = tex_coords;
+    let corners = [
+        [x, y, left, top],
+        [x, y + height, left, bottom],
+        [x + width, y + height, right, bottom],
+        [x + width, y + height, right, bottom],
+        [x + width, y, right, top],
+        [x, y, left, top],
+    ];
+    for [x, y, s, t] in corners {
+        batch.push([x, y, s, t, layer, r, g, b, a]);
+    }
+}
+
+/// Screen-space overlay drawn on top of the world. Quads are collected in pixel
+/// coordinates, with the origin at the top left, and drawn in one batch.
+///
+/// Text is drawn with a TrueType font when one was loaded, and with blocky
+/// bitmap glyphs otherwise.
+pub struct Hud {
+    program: ShaderProgram,
+    vao: VertexArray,
+    vertices: Buffer,
+    batch: Vec<HudVertex>,
+    atlas: Option<GlyphAtlas>,
+    /// Colors of the accents drawn on it.
+    pub palette: Palette,
+}
+
+impl Hud {
+    pub fn new(program: ShaderProgram, font: Option<Font>) -> Option<Self> {
+        let vao = VertexArray::new()?;
+        vao.bind();
+        let vertices = Buffer::new()?;
+        vertices.bind(BufferType::Array);
+        unsafe {
+            let stride = std::mem::size_of::<HudVertex>() as gl::types::GLsizei;
+            let float = std::mem::size_of::<f32>();
+            // (location, components, offset in floats): position, texture coordinates, texture index, color
+            for (location, components, offset) in [(0, 2, 0), (1, 2, 2), (2, 1, 4), (3, 4, 5)] {
+                gl::VertexAttribPointer(location, components, gl::FLOAT, gl::FALSE, stride, (offset * float) as *const _);
+                gl::EnableVertexAttribArray(location);
+            }
+        }
+
+        Some(Self {
+            program,
+            vao,
+            vertices,
+            batch: Vec::new(),
+            atlas: font.map(GlyphAtlas::new),
+            palette: Palette::Default,
+        })
+    }
+
+    /// Queues a solid rectangle.
+    pub fn rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: [f32; 4]) {
+        push_quad(&mut self.batch, [x, y, width, height], [0.0; 4], SOLID_QUAD, color);
+    }
+
+    /// Queues an outline of the given thickness.
+    pub fn outline(&mut self, x: f32, y: f32, width: f32, height: f32, thickness: f32, color: [f32; 4]) {
+        self.rect(x, y, width, thickness, color);
+        self.rect(x, y + height - thickness, width, thickness, color);
+        self.rect(x, y, thickness, height, color);
+        self.rect(x + width - thickness, y, thickness, height, color);
+    }
+
+    /// Queues a square showing a layer of the block texture array.
+    pub fn texture(&mut self, x: f32, y: f32, size: f32, texture: BlockTexture) {
+        push_quad(&mut self.batch, [x, y, size, size], [0.0, 0.0, 1.0, 1.0], texture.layer(), [1.0, 1.0, 1.0, 1.0]);
+    }
+
+    /// Width in pixels of the widest line of `text` drawn in lines `size`
+    /// pixels high.
+    pub fn measure_text(&self, size: f32, text: &str) -> f32 {
+        let line_width = |line: &str| match &self.atlas {
+            Some(atlas) => {
+                let font = atlas.font();
+                let em = size / font.line_height(1.0);
+                line.chars().map(|c| font.advance(font.glyph_index(c), em)).sum()
+            },
+            None => line.chars().count() as f32 * GLYPH_ADVANCE * size / GLYPH_LINE_HEIGHT,
+        };
+        text.split('\n').map(line_width).fold(0.0, f32::max)
+    }
+
+    /// Queues text in lines `size` pixels high, the first one's top left corner
+    /// at `x`, `y`. Characters the font lacks show as its placeholder glyph.
+    pub fn draw_text(&mut self, x: f32, y: f32, size: f32, color: [f32; 4], text: &str) {
+        let Some(atlas) = &mut self.atlas else {
+            self.draw_bitmap_text(x, y, size, color, text);
+            return;
+        };
+
+        let em = size / atlas.font().line_height(1.0);
+        // Glyphs are rasterized for whole pixel positions, so pens snap to them
+        let mut baseline = (y + atlas.font().ascent(em)).round();
+        for line in text.split('\n') {
+            let mut pen = x;
+            for c in line.chars() {
+                if let Some(glyph) = atlas.glyph(c, em) {
+                    let (left, top) = (pen.round() + glyph.left, baseline + glyph.top);
+                    push_quad(&mut self.batch, [left, top, glyph.width, glyph.height], glyph.tex_coords, GLYPH_QUAD, color);
+                }
+                pen += atlas.font().advance(atlas.font().glyph_index(c), em);
+            }
+            baseline += size;
+        }
+    }
+
+    // Queues text with blocky 3x5 glyphs, for when there is no font. Characters
+    // without a glyph show as `?`.
+    fn draw_bitmap_text(&mut self, x: f32, y: f32, size: f32, color: [f32; 4], text: &str) {
+        let scale = size / GLYPH_LINE_HEIGHT;
+        for (index, line) in text.split('\n').enumerate() {
+            let top = y + (index as f32 * GLYPH_LINE_HEIGHT + 1.0) * scale;
+            let mut left = x;
+            for c in line.chars() {
+                let bits = glyph(c).or(glyph('?')).unwrap_or(0);
+                for cell in 0..15 {
+                    if bits & (1 << (14 - cell)) != 0 {
+                        let (column, row) = ((cell % 3) as f32, (cell / 3) as f32);
+                        self.rect(left + column * scale, top + row * scale, scale, scale, color);
+                    }
+                }
+                left += GLYPH_ADVANCE * scale;
+            }
+        }
+    }
+
+    /// Queues a number in a line `size` pixels high, ending at `right`.
+    pub fn number(&mut self, right: f32, y: f32, size: f32, value: u32, color: [f32; 4]) {
+        let digits = value.to_string();
+        let x = right - self.measure_text(size, &digits);
+        self.draw_text(x, y, size, color, &digits);
+    }
+
+    /// Draws everything queued since the last call. The block texture array and
+    /// the colormap must be bound to texture units 0 and 1; the glyph atlas is
+    /// bound here.
+    pub fn draw(&mut self, screen_width: f32, screen_height: f32) {
+        if self.batch.is_empty() {
+            return;
+        }
+
+        self.program.use_program();
+        if let Some(atlas) = &self.atlas {
+            GlState::bind_texture(FONT_TEXTURE_UNIT, gl::TEXTURE_2D, atlas.texture());
+        }
+        self.vao.bind();
+        self.vertices.bind(BufferType::Array);
+        buffer_data(BufferType::Array, bytemuck::cast_slice(&self.batch), gl::STREAM_DRAW);
+
+        unsafe {
+            gl::Uniform1i(gl::GetUniformLocation(self.program.0, b"blockTextures\0".as_ptr() as *const i8), 0);
+            gl::Uniform1i(gl::GetUniformLocation(self.program.0, b"colormapTexture\0".as_ptr() as *const i8), 1);
+            gl::Uniform1i(gl::GetUniformLocation(self.program.0, b"fontAtlas\0".as_ptr() as *const i8), FONT_TEXTURE_UNIT as i32);
+            let loc = gl::GetUniformLocation(self.program.0, b"screenSize\0".as_ptr() as *const i8);
+            gl::Uniform2f(loc, screen_width, screen_height);
+
+            // The overlay is always on top of the world
+            GlState::set_capability(gl::DEPTH_TEST, false);
+            gl::DrawArrays(gl::TRIANGLES, 0, self.batch.len() as i32);
+            GlState::set_capability(gl::DEPTH_TEST, true);
+        }
+        self.batch.clear();
+    }
+}
@@ -0,0 +1,362 @@
+use crate::atomic_file;
+use crate::block::{BlockShape, BlockTexture, BlockType, RENAMED_BLOCKS};
+use crate::byte_reader::ByteReader;
+use crate::crafting::{RecipeRegistry, GRID_SIZE};
+use crate::hud::Hud;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+pub const HOTBAR_SLOTS: usize = 9;
+// Hotbar plus a 9x3 main grid
+const SLOT_COUNT: usize = HOTBAR_SLOTS + 27;
+pub const MAX_STACK: u32 = 64;
+// The crafting grid and its result come after the slots on the inventory screen
+const CRAFTING_SLOTS: usize = GRID_SIZE * GRID_SIZE;
+const RESULT_SLOT: usize = SLOT_COUNT + CRAFTING_SLOTS;
+
+// Identifies an inventory file
+const MAGIC: &[u8; 4] = b"INVT";
+const VERSION: u32 = 2;
+// Files from before slots were saved by block name, which saved them by id
+const VERSION_WITH_IDS: u32 = 1;
+
+// Layout of the slots on screen, in pixels
+const SLOT_SIZE: f32 = 40.0;
+const SLOT_GAP: f32 = 4.0;
+const ICON_MARGIN: f32 = 6.0;
+// Height of the stack count's line of text, in the slot's bottom right corner
+const COUNT_TEXT_SIZE: f32 = 14.0;
+const HOTBAR_BOTTOM_MARGIN: f32 = 10.0;
+// Extra space between the main grid and the hotbar on the inventory screen
+const HOTBAR_SEPARATION: f32 = 12.0;
+// Extra space between the crafting grid and the main grid, and the column of
+// the main grid the crafting grid starts at
+const CRAFTING_SEPARATION: f32 = 16.0;
+const CRAFTING_COLUMN: usize = 2;
+
+const SLOT_COLOR: [f32; 4] = [0.1, 0.1, 0.1, 0.6];
+const SELECTED_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const PANEL_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.5];
+const COUNT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const ARROW_COLOR: [f32; 4] = [0.8, 0.8, 0.8, 0.9];
+
+/// A number of blocks of the same type sharing one slot.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ItemStack {
+    pub block: BlockType,
+    pub count: u32,
+}
+
+/// The player's inventory: a hotbar, of which one slot is selected, and a main
+/// grid, plus the crafting grid shown with them on the inventory screen.
+pub struct Inventory {
+    // Hotbar first, then the main grid row by row
+    slots: [Option<ItemStack>; SLOT_COUNT],
+    selected: usize,
+    // Slot picked on the inventory screen, waiting for a second click to swap
+    held: Option<usize>,
+    // Crafting grid row by row, and what it makes. Emptied back into the
+    // slots when the screen closes, so it's never saved.
+    crafting: [Option<ItemStack>; CRAFTING_SLOTS],
+    crafted: Option<ItemStack>,
+}
+
+impl Inventory {
+    pub fn new() -> Self {
+        Self {
+            slots: [None; SLOT_COUNT],
+            selected: 0,
+            held: None,
+            crafting: [None; CRAFTING_SLOTS],
+            crafted: None,
+        }
+    }
+
+    /// Adds blocks, topping up existing stacks before filling empty slots, hotbar
+    /// first. Returns how many didn't fit.
+    pub fn add(&mut self, block: BlockType, count: u32) -> u32 {
+        let mut remaining = count;
+        for stack in self.slots.iter_mut().flatten() {
+            if stack.block == block && remaining > 0 {
+                let moved = remaining.min(MAX_STACK - stack.count);
+                stack.count += moved;
+                remaining -= moved;
+            }
+        }
+        for slot in self.slots.iter_mut() {
+            if slot.is_none() && remaining > 0 {
+                let moved = remaining.min(MAX_STACK);
+                *slot = Some(ItemStack { block, count: moved });
+                remaining -= moved;
+            }
+        }
+        remaining
+    }
+
+    // How many more blocks of a type fit in the slots
+    fn space_for(&self, block: BlockType) -> u32 {
+        self.slots.iter()
+            .map(|slot| match slot {
+                Some(stack) if stack.block == block => MAX_STACK - stack.count,
+                Some(_) => 0,
+                None => MAX_STACK,
+            })
+            .sum()
+    }
+
+    /// Total number of blocks of a type across all slots.
+    pub fn count(&self, block: BlockType) -> u32 {
+        self.slots.iter().flatten().filter(|stack| stack.block == block).map(|stack| stack.count).sum()
+    }
+
+    /// Block in the selected hotbar slot.
+    pub fn selected_block(&self) -> Option<BlockType> {
+        self.slots[self.selected].map(|stack| stack.block)
+    }
+
+    /// Takes one block out of the selected hotbar slot.
+    pub fn take_selected(&mut self) -> Option<BlockType> {
+        let slot = &mut self.slots[self.selected];
+        let stack = slot.as_mut()?;
+        let block = stack.block;
+        stack.count -= 1;
+        if stack.count == 0 {
+            *slot = None;
+        }
+        Some(block)
+    }
+
+    pub fn select(&mut self, hotbar_slot: usize) {
+        self.selected = hotbar_slot % HOTBAR_SLOTS;
+    }
+
+    /// Moves the selection along the hotbar, wrapping around.
+    pub fn scroll(&mut self, offset: i32) {
+        self.selected = (self.selected as i32 + offset).rem_euclid(HOTBAR_SLOTS as i32) as usize;
+    }
+
+    /// Handles a click on the inventory screen: the first click holds a slot, a
+    /// second one swaps it with the clicked slot, which may be on the crafting
+    /// grid. Clicking the crafting result crafts it once into the slots.
+    pub fn click(&mut self, x: f32, y: f32, screen_width: f32, screen_height: f32, recipes: &RecipeRegistry) {
+        let clicked = (0..=RESULT_SLOT).find(|&slot| {
+            let (slot_x, slot_y) = screen_slot_position(slot, screen_width, screen_height);
+            (slot_x..slot_x + SLOT_SIZE).contains(&x) && (slot_y..slot_y + SLOT_SIZE).contains(&y)
+        });
+        match (self.held.take(), clicked) {
+            (_, Some(RESULT_SLOT)) => self.craft(),
+            (Some(held), Some(clicked)) => {
+                let (a, b) = (self.screen_slot(held), self.screen_slot(clicked));
+                *self.screen_slot_mut(held) = b;
+                *self.screen_slot_mut(clicked) = a;
+            },
+            (None, Some(clicked)) if self.screen_slot(clicked).is_some() => self.held = Some(clicked),
+            _ => {},
+        }
+        let grid: Vec<_> = self.crafting.iter().map(|slot| slot.map(|stack| stack.block)).collect();
+        self.crafted = recipes.find(&grid).map(|recipe| recipe.result);
+    }
+
+    // Takes one block from every slot of the crafting grid and adds what they
+    // make, as long as it fits
+    fn craft(&mut self) {
+        let Some(result) = self.crafted else {
+            return;
+        };
+        if self.space_for(result.block) < result.count {
+            log::info!(target: "game", "No room to craft {}", result.block.info().name);
+            return;
+        }
+        for slot in self.crafting.iter_mut() {
+            if let Some(stack) = slot {
+                stack.count -= 1;
+                if stack.count == 0 {
+                    *slot = None;
+                }
+            }
+        }
+        self.add(result.block, result.count);
+        log::info!(target: "game", "Crafted {} {}", result.count, result.block.info().name);
+    }
+
+    fn screen_slot(&self, slot: usize) -> Option<ItemStack> {
+        match slot {
+            _ if slot < SLOT_COUNT => self.slots[slot],
+            _ => self.crafting[slot - SLOT_COUNT],
+        }
+    }
+
+    fn screen_slot_mut(&mut self, slot: usize) -> &mut Option<ItemStack> {
+        match slot {
+            _ if slot < SLOT_COUNT => &mut self.slots[slot],
+            _ => &mut self.crafting[slot - SLOT_COUNT],
+        }
+    }
+
+    /// Drops a held slot and empties the crafting grid back into the slots when
+    /// the inventory screen closes. Returns what didn't fit, for the caller to
+    /// drop on the ground.
+    pub fn release(&mut self) -> Vec<ItemStack> {
+        self.held = None;
+        self.crafted = None;
+        let mut left_over = Vec::new();
+        let crafting = std::mem::replace(&mut self.crafting, [None; CRAFTING_SLOTS]);
+        for stack in crafting.into_iter().flatten() {
+            let count = self.add(stack.block, stack.count);
+            if count > 0 {
+                left_over.push(ItemStack { block: stack.block, count });
+            }
+        }
+        left_over
+    }
+
+    /// Queues the hotbar at the bottom of the screen.
+    pub fn draw_hotbar(&self, hud: &mut Hud, screen_width: f32, screen_height: f32) {
+        let (left, top, _) = hotbar_position(screen_width, screen_height);
+        for slot in 0..HOTBAR_SLOTS {
+            let x = left + slot as f32 * (SLOT_SIZE + SLOT_GAP);
+            draw_stack(hud, self.slots[slot], x, top);
+            if slot == self.selected {
+                hud.outline(x - 2.0, top - 2.0, SLOT_SIZE + 4.0, SLOT_SIZE + 4.0, 2.0, SELECTED_COLOR);
+            }
+        }
+    }
+
+    /// Queues the inventory screen with every slot and the crafting grid above
+    /// them, centered on the screen.
+    pub fn draw_screen(&self, hud: &mut Hud, screen_width: f32, screen_height: f32) {
+        let (_, y) = screen_slot_position(SLOT_COUNT, screen_width, screen_height);
+        let (x, _) = screen_slot_position(HOTBAR_SLOTS, screen_width, screen_height);
+        let (_, bottom) = screen_slot_position(0, screen_width, screen_height);
+        let width = HOTBAR_SLOTS as f32 * (SLOT_SIZE + SLOT_GAP) - SLOT_GAP;
+        hud.rect(x - 10.0, y - 10.0, width + 20.0, bottom + SLOT_SIZE - y + 20.0, PANEL_COLOR);
+
+        for slot in 0..=RESULT_SLOT {
+            let (x, y) = screen_slot_position(slot, screen_width, screen_height);
+            let stack = if slot == RESULT_SLOT { self.crafted } else { self.screen_slot(slot) };
+            draw_stack(hud, stack, x, y);
+            if self.held == Some(slot) {
+                hud.outline(x - 2.0, y - 2.0, SLOT_SIZE + 4.0, SLOT_SIZE + 4.0, 2.0, hud.palette.colors().held);
+            }
+        }
+
+        // An arrow from the crafting grid to its result
+        let (result_x, result_y) = screen_slot_position(RESULT_SLOT, screen_width, screen_height);
+        let (x, y) = (result_x - SLOT_SIZE - SLOT_GAP, result_y + SLOT_SIZE / 2.0);
+        hud.rect(x + 6.0, y - 2.0, SLOT_SIZE - 18.0, 4.0, ARROW_COLOR);
+        for step in 0..6 {
+            let half = 6.0 - step as f32;
+            hud.rect(x + SLOT_SIZE - 12.0 + step as f32, y - half, 1.0, half * 2.0, ARROW_COLOR);
+        }
+    }
+
+    /// Writes the slots to a file, creating its directory if needed.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        atomic_file::write(path, &self.encode())
+    }
+
+    /// The slots in the format `save` writes.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(MAGIC);
+        data.extend_from_slice(&VERSION.to_le_bytes());
+        data.push(self.selected as u8);
+        // Each slot as the name of its block after the name's length, then the count
+        for slot in &self.slots {
+            let (block, count) = slot.map_or((BlockType::Air, 0), |stack| (stack.block, stack.count as u8));
+            let name = block.info().name;
+            data.push(name.len() as u8);
+            data.extend_from_slice(name.as_bytes());
+            data.push(count);
+        }
+        data
+    }
+
+    /// Reads an inventory written by `save`.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let data = fs::read(path)?;
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "Invalid inventory file");
+
+        let mut reader = ByteReader::new(&data);
+        if reader.take(4) != Some(MAGIC) {
+            return Err(invalid());
+        }
+        let version = reader.u32().ok_or_else(invalid)?;
+        if version != VERSION && version != VERSION_WITH_IDS {
+            return Err(invalid());
+        }
+
+        let mut inventory = Self::new();
+        inventory.select(reader.u8().ok_or_else(invalid)? as usize);
+        for slot in inventory.slots.iter_mut() {
+            let block = match version {
+                VERSION_WITH_IDS => reader.u8().and_then(BlockType::from_saved_id),
+                _ => reader.short_str().and_then(|name| BlockType::from_saved_name(name, RENAMED_BLOCKS)),
+            };
+            let (block, count) = (block.ok_or_else(invalid)?, reader.u8().ok_or_else(invalid)? as u32);
+            if block != BlockType::Air && count > 0 {
+                *slot = Some(ItemStack { block, count: count.min(MAX_STACK) });
+            }
+        }
+        if !reader.remaining().is_empty() {
+            return Err(invalid());
+        }
+        Ok(inventory)
+    }
+}
+
+/// Top left corner and width of the hotbar on screen, for things drawn around it.
+pub fn hotbar_position(screen_width: f32, screen_height: f32) -> (f32, f32, f32) {
+    let width = HOTBAR_SLOTS as f32 * (SLOT_SIZE + SLOT_GAP) - SLOT_GAP;
+    (row_left(screen_width), screen_height - SLOT_SIZE - HOTBAR_BOTTOM_MARGIN, width)
+}
+
+// Left edge of a centered row of slots
+fn row_left(screen_width: f32) -> f32 {
+    let width = HOTBAR_SLOTS as f32 * (SLOT_SIZE + SLOT_GAP) - SLOT_GAP;
+    (screen_width - width) / 2.0
+}
+
+// Top left corner of a slot on the inventory screen: the crafting grid with its
+// result to the right, then the main grid, with the hotbar below it
+fn screen_slot_position(slot: usize, screen_width: f32, screen_height: f32) -> (f32, f32) {
+    let left = row_left(screen_width);
+    let step = SLOT_SIZE + SLOT_GAP;
+    let crafting_height = GRID_SIZE as f32 * step + CRAFTING_SEPARATION;
+    let rows = (SLOT_COUNT / HOTBAR_SLOTS) as f32;
+    let height = crafting_height + rows * step - SLOT_GAP + HOTBAR_SEPARATION;
+    let top = (screen_height - height) / 2.0;
+
+    let (column, y) = if slot == RESULT_SLOT {
+        (CRAFTING_COLUMN + GRID_SIZE + 1, top + (GRID_SIZE / 2) as f32 * step)
+    } else if slot >= SLOT_COUNT {
+        let cell = slot - SLOT_COUNT;
+        (CRAFTING_COLUMN + cell % GRID_SIZE, top + (cell / GRID_SIZE) as f32 * step)
+    } else if slot < HOTBAR_SLOTS {
+        (slot, top + height - SLOT_SIZE)
+    } else {
+        (slot % HOTBAR_SLOTS, top + crafting_height + ((slot / HOTBAR_SLOTS) - 1) as f32 * step)
+    };
+    (left + column as f32 * step, y)
+}
+
+// Queues a slot with the stack in it, if any
+fn draw_stack(hud: &mut Hud, stack: Option<ItemStack>, x: f32, y: f32) {
+    hud.rect(x, y, SLOT_SIZE, SLOT_SIZE, SLOT_COLOR);
+    if let Some(stack) = stack {
+        hud.texture(x + ICON_MARGIN, y + ICON_MARGIN, SLOT_SIZE - 2.0 * ICON_MARGIN, icon_texture(stack.block));
+        if stack.count > 1 {
+            hud.number(x + SLOT_SIZE - 2.0, y + SLOT_SIZE - COUNT_TEXT_SIZE, COUNT_TEXT_SIZE, stack.count, COUNT_COLOR);
+        }
+    }
+}
+
+// Texture shown for a block in its slot
+fn icon_texture(block: BlockType) -> BlockTexture {
+    let textures = block.info().textures;
+    match block.info().shape {
+        BlockShape::Cross => textures.side,
+        _ => textures.top,
+    }
+}
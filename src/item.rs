@@ -0,0 +1,96 @@
+use crate::block::BlockType;
+use crate::entity::{Aabb, Entities, Entity, MeshHandle};
+use crate::entity_renderer::EntityRenderer;
+use crate::inventory::Inventory;
+use crate::math::{Quat, Transform, Vec3};
+use std::collections::HashMap;
+
+// Edge length of a dropped item cube
+const ITEM_SIZE: f32 = 0.25;
+// Radians per second
+const SPIN_SPEED: f32 = 1.5;
+const BOB_HEIGHT: f32 = 0.1;
+// Bobbing cycles per second, in radians
+const BOB_SPEED: f32 = 2.5;
+// How close the player has to get to collect an item
+const PICKUP_RADIUS: f32 = 2.0;
+// Seconds before an uncollected item disappears
+const DESPAWN_AFTER: f32 = 300.0;
+
+/// Component of a block lying on the ground, waiting to be collected.
+pub struct DroppedItem {
+    pub block: BlockType,
+    /// Seconds since the item was dropped.
+    pub age: f32,
+    // Height the item bobs around
+    rest_height: f32,
+}
+
+/// Spawns dropped items and keeps one mesh per block type for them.
+pub struct DroppedItems {
+    meshes: HashMap<BlockType, MeshHandle>,
+}
+
+impl DroppedItems {
+    pub fn new() -> Self {
+        Self {
+            meshes: HashMap::new(),
+        }
+    }
+
+    /// Drops a block item centered on `position`.
+    pub fn spawn(&mut self, entities: &mut Entities, renderer: &mut EntityRenderer, block: BlockType, position: Vec3) -> Option<Entity> {
+        let mesh = match self.meshes.get(&block) {
+            Some(&mesh) => mesh,
+            None => {
+                let mesh = renderer.add_block_cube(block.info().textures)?;
+                self.meshes.insert(block, mesh);
+                mesh
+            },
+        };
+
+        let rest_height = position.y - ITEM_SIZE / 2.0;
+        let mut transform = Transform::at(Vec3::new(position.x, rest_height, position.z));
+        transform.scale = Vec3::new(ITEM_SIZE, ITEM_SIZE, ITEM_SIZE);
+
+        let entity = entities.spawn();
+        entities.transforms.insert(entity, transform);
+        entities.bounds.insert(entity, Aabb::standing(ITEM_SIZE, ITEM_SIZE));
+        entities.meshes.insert(entity, mesh);
+        entities.items.insert(entity, DroppedItem { block, age: 0.0, rest_height });
+        Some(entity)
+    }
+
+    /// Spins and bobs every dropped item, moves those near the player into the
+    /// inventory and removes those that have been lying around too long.
+    pub fn update(&self, entities: &mut Entities, player_position: Vec3, inventory: &mut Inventory, delta_time: f32) {
+        let mut collected = Vec::new();
+        let mut expired = Vec::new();
+
+        for (entity, item) in entities.items.iter_mut() {
+            item.age += delta_time;
+            let Some(transform) = entities.transforms.get_mut(entity) else {
+                continue;
+            };
+            transform.rotation = Quat::from_yaw(item.age * SPIN_SPEED);
+            transform.translation.y = item.rest_height + (item.age * BOB_SPEED).sin() * BOB_HEIGHT;
+
+            if (transform.translation - player_position).length() < PICKUP_RADIUS {
+                collected.push((entity, item.block));
+            } else if item.age > DESPAWN_AFTER {
+                expired.push(entity);
+            }
+        }
+
+        for (entity, block) in collected {
+            // Items stay on the ground while the inventory is full
+            if inventory.add(block, 1) == 0 {
+                log::info!(target: "game", "Picked up {} ({} total)", block.info().name, inventory.count(block));
+                entities.despawn(entity);
+            }
+        }
+        for entity in expired {
+            entities.despawn(entity);
+        }
+    }
+}
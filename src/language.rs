@@ -0,0 +1,128 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+
+/// Where language files are read from, one `<code>.lang` file each.
+pub const LANGUAGE_DIRECTORY: &str = "src/assets/lang";
+/// The language text missing from the selected one falls back to.
+pub const DEFAULT_LANGUAGE: &str = "en_us";
+// Built in, so there is always text to fall back to
+const ENGLISH: &str = include_str!("assets/lang/en_us.lang");
+
+/// The UI text of one language by key, read from `key = value` lines, `#`
+/// starting a comment. In values, `\n` is a line break and each `{}` is
+/// filled with the next argument.
+pub struct Language {
+    code: String,
+    strings: HashMap<String, String>,
+}
+
+impl Language {
+    /// Reads `<code>.lang` from `LANGUAGE_DIRECTORY`.
+    pub fn load(code: &str) -> Result<Self, String> {
+        // Codes name a file in the folder, and nothing outside it
+        if code.is_empty() || !code.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(format!("Invalid language code: {}", code));
+        }
+        let path = Path::new(LANGUAGE_DIRECTORY).join(format!("{}.lang", code));
+        let text = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Self::parse(code, &text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn parse(code: &str, text: &str) -> Result<Self, String> {
+        let mut strings = HashMap::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line.split_once('=').ok_or_else(|| format!("line {}: Expected `key = value`", number + 1))?;
+            strings.insert(key.trim().to_string(), value.trim().replace("\\n", "\n"));
+        }
+        Ok(Self { code: code.to_string(), strings })
+    }
+
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    /// The language's own name for itself, or its code if the file has none.
+    pub fn name(&self) -> &str {
+        self.strings.get("language.name").map_or(&self.code, String::as_str)
+    }
+
+    fn get(&self, key: &str) -> Option<&str> {
+        self.strings.get(key).map(String::as_str)
+    }
+}
+
+thread_local! {
+    // English, then the selected language if it's another one
+    static LANGUAGES: RefCell<(Language, Option<Language>)> = RefCell::new((
+        Language::parse(DEFAULT_LANGUAGE, ENGLISH).expect("the built-in English text parses"),
+        None,
+    ));
+}
+
+/// Every language there is a file for, as its code and name, by code.
+pub fn available() -> Vec<(String, String)> {
+    let mut languages = fs::read_dir(LANGUAGE_DIRECTORY).into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let code = path.file_stem()?.to_str()?;
+            (path.extension()? == "lang").then(|| Language::load(code).ok()).flatten()
+        })
+        .map(|language| (language.code().to_string(), language.name().to_string()))
+        .collect::<Vec<_>>();
+    languages.sort();
+    languages
+}
+
+/// Switches the UI text to the language `code`. Text it lacks, or all of it
+/// if the language can't be loaded, stays English.
+pub fn select(code: &str) {
+    let language = match code {
+        DEFAULT_LANGUAGE => None,
+        _ => Language::load(code)
+            .inspect_err(|e| log::warn!(target: "io", "Keeping English text: {}", e))
+            .ok(),
+    };
+    if let Some(language) = &language {
+        log::info!(target: "io", "Language: {}", language.name());
+    }
+    LANGUAGES.with(|languages| languages.borrow_mut().1 = language);
+}
+
+/// The code of the language text is shown in.
+pub fn selected() -> String {
+    LANGUAGES.with(|languages| {
+        let (english, selected) = &*languages.borrow();
+        selected.as_ref().unwrap_or(english).code().to_string()
+    })
+}
+
+/// The text for `key` in the selected language, or English if it lacks it,
+/// with each `{}` replaced by the next of `args`. Unknown keys show as
+/// themselves, so they are easy to spot.
+pub fn text(key: &str, args: &[&dyn Display]) -> String {
+    LANGUAGES.with(|languages| {
+        let (english, selected) = &*languages.borrow();
+        let template = selected.as_ref().and_then(|language| language.get(key)).or_else(|| english.get(key)).unwrap_or(key);
+        let mut text = String::with_capacity(template.len());
+        let mut args = args.iter();
+        let mut pieces = template.split("{}");
+        text.push_str(pieces.next().unwrap_or_default());
+        for piece in pieces {
+            match args.next() {
+                Some(arg) => text.push_str(&arg.to_string()),
+                None => text.push_str("{}"),
+            }
+            text.push_str(piece);
+        }
+        text
+    })
+}
@@ -0,0 +1,57 @@
+use crate::commands::DAY_LENGTH;
+use crate::world::{World, CHUNK_SIZE};
+
+/// Brightest light level, in full daylight or right next to a glowing block.
+pub const MAX_LIGHT: u8 = 15;
+
+/// Angle of the sun from noon (tick 6000), in radians.
+pub fn sun_angle(time_of_day: u32) -> f32 {
+    (time_of_day as f32 - 6000.0) / DAY_LENGTH as f32 * std::f32::consts::TAU
+}
+
+/// Brightness of the daylight at a time of day, from 0.1 at midnight to 1 at noon.
+pub fn daylight(time_of_day: u32) -> f32 {
+    (sun_angle(time_of_day).cos() * 0.5 + 0.5).max(0.1)
+}
+
+/// Light given to a block by glowing blocks around it, from `MAX_LIGHT` right
+/// next to one down to nothing at its light radius. Glowing blocks come from
+/// the chunk meshes like point lights do, so buried ones give no light.
+pub fn block_light(world: &World, (x, y, z): (i32, i32, i32)) -> u8 {
+    let size = CHUNK_SIZE as i32;
+    let chunk = (x.div_euclid(size), y.div_euclid(size), z.div_euclid(size));
+    // Light radii are shorter than a chunk, so only the chunks around can reach
+    let mut brightest = 0.0f32;
+    for dx in -1..=1 {
+        for dy in -1..=1 {
+            for dz in -1..=1 {
+                let Some(neighbor) = world.chunk((chunk.0 + dx, chunk.1 + dy, chunk.2 + dz)) else {
+                    continue;
+                };
+                for &[lx, ly, lz] in &neighbor.mesh.lights {
+                    let Some(light) = world.get_block(lx, ly, lz).info().light else {
+                        continue;
+                    };
+                    let distance = (((lx - x).pow(2) + (ly - y).pow(2) + (lz - z).pow(2)) as f32).sqrt();
+                    brightest = brightest.max(1.0 - distance / light.radius);
+                }
+            }
+        }
+    }
+    (brightest * MAX_LIGHT as f32).round() as u8
+}
+
+/// Light given to a block by the sky: all of the daylight out in the open,
+/// none with anything above it.
+pub fn sky_light(world: &World, (x, y, z): (i32, i32, i32), daylight: f32) -> u8 {
+    match world.highest_block(x, z) {
+        Some(top) if top >= y => 0,
+        _ => (daylight * MAX_LIGHT as f32).round() as u8,
+    }
+}
+
+/// Light level of a block, from 0 in the dark to `MAX_LIGHT`: the brighter
+/// of its block and sky light.
+pub fn light_level(world: &World, position: (i32, i32, i32), daylight: f32) -> u8 {
+    block_light(world, position).max(sky_light(world, position, daylight))
+}
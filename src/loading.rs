@@ -0,0 +1,100 @@
+use crate::events::{ChunkProgress, ChunkStage, EventBus, Subscriber};
+use crate::hud::Hud;
+use crate::language::text;
+
+const BACKGROUND: [f32; 4] = [0.08, 0.08, 0.1, 1.0];
+const TITLE_SIZE: f32 = 24.0;
+const TEXT_SIZE: f32 = 16.0;
+const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const STAGE_COLOR: [f32; 4] = [0.7, 0.7, 0.7, 1.0];
+const BAR_WIDTH: f32 = 320.0;
+const BAR_HEIGHT: f32 = 10.0;
+const BAR_BACKGROUND: [f32; 4] = [0.25, 0.25, 0.3, 1.0];
+const BAR_COLOR: [f32; 4] = [0.35, 0.75, 0.35, 1.0];
+const GAP: f32 = 12.0;
+
+/// Covers the screen while the chunks around the player load, at startup and
+/// after a teleport, showing how far along they are. It follows the
+/// `ChunkProgress` events of the chunk pipeline, so it moves as the chunks do.
+#[derive(Default)]
+pub struct LoadingScreen {
+    progress: Subscriber<ChunkProgress>,
+    // Chunks to load while open
+    total: Option<usize>,
+    // Chunks through each stage since it opened, in `ChunkStage::ALL` order
+    done: [usize; 4],
+}
+
+impl LoadingScreen {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.total.is_some()
+    }
+
+    /// Opens the screen to follow `chunks` chunks through the pipeline,
+    /// counting the progress events still queued this frame.
+    pub fn open(&mut self, chunks: usize) {
+        self.total = Some(chunks.max(1));
+        self.done = [0; 4];
+    }
+
+    pub fn close(&mut self) {
+        self.total = None;
+    }
+
+    /// Counts the chunks that went through each stage, while open.
+    pub fn handle_events(&mut self, events: &EventBus) {
+        for progress in events.read(&mut self.progress) {
+            if self.is_open() {
+                let stage = ChunkStage::ALL.iter().position(|&stage| stage == progress.stage).unwrap_or(0);
+                self.done[stage] += progress.chunks;
+            }
+        }
+    }
+
+    /// How far loading is, from 0 to 1, with every stage counting the same.
+    /// Chunks loaded again, like neighbours remeshed, count only once.
+    pub fn fraction(&self) -> f32 {
+        let Some(total) = self.total else {
+            return 1.0;
+        };
+        let done: usize = self.done.iter().map(|&done| done.min(total)).sum();
+        done as f32 / (total * ChunkStage::ALL.len()) as f32
+    }
+
+    /// The first stage that some of the chunks still have to go through.
+    pub fn stage(&self) -> ChunkStage {
+        let total = self.total.unwrap_or(0);
+        ChunkStage::ALL.into_iter()
+            .zip(self.done)
+            .find(|&(_, done)| done < total)
+            .map_or(ChunkStage::Uploading, |(stage, _)| stage)
+    }
+
+    /// Queues the screen over everything else, while open.
+    pub fn draw(&self, hud: &mut Hud, screen_width: f32, screen_height: f32) {
+        if !self.is_open() {
+            return;
+        }
+        hud.rect(0.0, 0.0, screen_width, screen_height, BACKGROUND);
+        let center_x = screen_width / 2.0;
+        let bar_y = screen_height / 2.0;
+
+        let title = text("loading.title", &[]);
+        let title_y = bar_y - GAP - TITLE_SIZE;
+        hud.draw_text(center_x - hud.measure_text(TITLE_SIZE, &title) / 2.0, title_y, TITLE_SIZE, TEXT_COLOR, &title);
+
+        let bar_x = center_x - BAR_WIDTH / 2.0;
+        hud.rect(bar_x, bar_y, BAR_WIDTH, BAR_HEIGHT, BAR_BACKGROUND);
+        hud.rect(bar_x, bar_y, BAR_WIDTH * self.fraction(), BAR_HEIGHT, BAR_COLOR);
+
+        let stage = text(&format!("loading.stage.{}", self.stage().name()), &[]);
+        let percent = (self.fraction() * 100.0).floor() as u32;
+        let label = text("loading.progress", &[&stage, &percent]);
+        let label_y = bar_y + BAR_HEIGHT + GAP;
+        hud.draw_text(center_x - hud.measure_text(TEXT_SIZE, &label) / 2.0, label_y, TEXT_SIZE, STAGE_COLOR, &label);
+    }
+}
@@ -0,0 +1,72 @@
+use crate::clock::Instant;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::fmt::Display;
+use std::io::Write;
+use std::sync::OnceLock;
+
+static LOGGER: Logger = Logger;
+static START: OnceLock<Instant> = OnceLock::new();
+
+struct Logger;
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let elapsed = START.get_or_init(Instant::now).elapsed().as_secs_f32();
+        write_line(record.level(), &format!("[{:9.3}s {:<5} {}] {}", elapsed, record.level(), record.target(), record.args()));
+    }
+
+    fn flush(&self) {
+        let _ = std::io::stderr().flush();
+    }
+}
+
+#[cfg(not(feature = "web"))]
+fn write_line(_level: Level, line: &str) {
+    // A failed write to stderr has nowhere better to be reported
+    let _ = writeln!(std::io::stderr().lock(), "{}", line);
+}
+
+// The browser has no stderr, only its console
+#[cfg(feature = "web")]
+fn write_line(level: Level, line: &str) {
+    let line = line.into();
+    match level {
+        Level::Error => web_sys::console::error_1(&line),
+        Level::Warn => web_sys::console::warn_1(&line),
+        _ => web_sys::console::log_1(&line),
+    }
+}
+
+/// Installs the logger for the `log` macros. Messages are written to stderr, or
+/// the console in the browser, with the time since startup, and their target
+/// names their category:
+///
+/// - `worldgen`: terrain generation and chunk streaming
+/// - `meshing`: chunk meshes and the mesh cache
+/// - `render`: GL resources, frame rate and display settings
+/// - `io`: saves, screenshots, replays and reports
+/// - `network`: the multiplayer server and client
+/// - `audio`: the sound device
+/// - `game`: gameplay events
+///
+/// Debug messages, such as the frame rate, are only shown when verbose.
+pub fn init(verbose: bool) {
+    START.get_or_init(Instant::now);
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(if verbose { LevelFilter::Debug } else { LevelFilter::Info });
+    }
+}
+
+/// Logs an error the game can't go on without, and exits.
+pub fn fatal(target: &str, message: impl Display) -> ! {
+    log::log!(target: target, Level::Error, "{}", message);
+    log::logger().flush();
+    std::process::exit(1);
+}
@@ -0,0 +1,1686 @@
+use crate::plugins::PluginContext;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+// Deeper nesting than any script needs, so broken input can't overflow the stack
+const MAX_DEPTH: usize = 100;
+// Calls a script may nest, for the same reason
+const MAX_CALL_DEPTH: usize = 100;
+// Statements and calls one call from the game may run, so a script stuck in a
+// loop can't freeze the game
+const MAX_STEPS: usize = 1_000_000;
+const KEYWORDS: [&str; 21] = [
+    "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "if", "in",
+    "local", "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+];
+// The longest that fits is taken, so `..` isn't read as two `.`
+const SYMBOLS: [&str; 27] = [
+    "==", "~=", "<=", ">=", "//", "..", "+", "-", "*", "/", "%", "^", "#", "<", ">", "=",
+    "(", ")", "{", "}", "[", "]", ";", ":", ",", ".", "...",
+];
+
+/// One of the game's functions, given to scripts to call.
+pub type NativeFunction = fn(&mut PluginContext, &[Value]) -> Result<Vec<Value>, String>;
+
+/// A value in a script.
+#[derive(Clone)]
+pub enum Value {
+    Nil,
+    Bool(bool),
+    Number(f64),
+    String(Rc<str>),
+    Table(Rc<RefCell<Table>>),
+    Function(Rc<Function>),
+}
+
+impl Value {
+    pub fn string(text: &str) -> Value {
+        Value::String(Rc::from(text))
+    }
+
+    pub fn native(function: NativeFunction) -> Value {
+        Value::Function(Rc::new(Function::Native(function)))
+    }
+
+    /// A table of named entries, such as the functions of a library.
+    pub fn table<'a>(entries: impl IntoIterator<Item = (&'a str, Value)>) -> Value {
+        let mut table = Table::default();
+        for (name, value) in entries {
+            table.insert(Key::String(Rc::from(name)), value);
+        }
+        Value::Table(Rc::new(RefCell::new(table)))
+    }
+
+    /// The name of the value's type, as `type()` gives it.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Nil => "nil",
+            Value::Bool(_) => "boolean",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Table(_) => "table",
+            Value::Function(_) => "function",
+        }
+    }
+
+    /// Whether the value counts as true: anything but `nil` and `false`.
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Value::Nil | Value::Bool(false))
+    }
+
+    /// The value as `tostring()` writes it.
+    pub fn to_text(&self) -> String {
+        match self {
+            Value::Nil => "nil".to_string(),
+            Value::Bool(b) => b.to_string(),
+            Value::Number(n) => number_text(*n),
+            Value::String(s) => s.to_string(),
+            Value::Table(table) => format!("table: {:p}", Rc::as_ptr(table)),
+            Value::Function(function) => format!("function: {:p}", Rc::as_ptr(function)),
+        }
+    }
+
+    fn equals(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Nil, Value::Nil) => true,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Table(a), Value::Table(b)) => Rc::ptr_eq(a, b),
+            (Value::Function(a), Value::Function(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+/// A function scripts can call: one of their own, or one of the game's.
+pub enum Function {
+    Script { body: Rc<FunctionBody>, scope: Rc<Scope> },
+    Native(NativeFunction),
+}
+
+/// The argument at `index`, counting from 0, as a number.
+pub fn number_argument(arguments: &[Value], index: usize) -> Result<f64, String> {
+    match arguments.get(index) {
+        Some(&Value::Number(n)) => Ok(n),
+        other => Err(bad_argument(index, "number", other)),
+    }
+}
+
+/// The argument at `index`, counting from 0, as a string.
+pub fn string_argument(arguments: &[Value], index: usize) -> Result<Rc<str>, String> {
+    match arguments.get(index) {
+        Some(Value::String(s)) => Ok(s.clone()),
+        other => Err(bad_argument(index, "string", other)),
+    }
+}
+
+/// The argument at `index`, counting from 0, which must be a function.
+pub fn function_argument(arguments: &[Value], index: usize) -> Result<Value, String> {
+    match arguments.get(index) {
+        Some(function @ Value::Function(_)) => Ok(function.clone()),
+        other => Err(bad_argument(index, "function", other)),
+    }
+}
+
+fn bad_argument(index: usize, expected: &str, got: Option<&Value>) -> String {
+    let got = got.map_or("no value", Value::type_name);
+    format!("bad argument #{} ({} expected, got {})", index + 1, expected, got)
+}
+
+// Whole numbers are written without a fraction, as Lua's integers are
+fn number_text(n: f64) -> String {
+    if n.fract() == 0.0 && n.abs() < 1e15 {
+        (n as i64).to_string()
+    } else {
+        n.to_string()
+    }
+}
+
+// What tables can be indexed by
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum Key {
+    Bool(bool),
+    // The bits of the number, with -0 as 0 so both find the same entry
+    Number(u64),
+    String(Rc<str>),
+}
+
+impl Key {
+    fn from_value(value: &Value) -> Result<Key, String> {
+        match value {
+            Value::Bool(b) => Ok(Key::Bool(*b)),
+            Value::Number(n) if n.is_nan() => Err("table index is NaN".to_string()),
+            Value::Number(n) => Ok(Key::Number((if *n == 0.0 { 0.0f64 } else { *n }).to_bits())),
+            Value::String(s) => Ok(Key::String(s.clone())),
+            Value::Nil => Err("table index is nil".to_string()),
+            other => Err(format!("{} keys aren't supported", other.type_name())),
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        match self {
+            Key::Bool(b) => Value::Bool(*b),
+            Key::Number(bits) => Value::Number(f64::from_bits(*bits)),
+            Key::String(s) => Value::String(s.clone()),
+        }
+    }
+}
+
+/// A script's table. Entries keep the order their keys were first set in,
+/// so iterating over one goes the same way every run. Only booleans, numbers
+/// and strings can be keys.
+#[derive(Default)]
+pub struct Table {
+    entries: Vec<(Key, Value)>,
+    indices: HashMap<Key, usize>,
+    // Entries set to nil, left in place so `next` can carry on past them
+    removed: usize,
+}
+
+impl Table {
+    pub fn get(&self, key: &Value) -> Value {
+        Key::from_value(key).ok()
+            .and_then(|key| self.indices.get(&key))
+            .map_or(Value::Nil, |&index| self.entries[index].1.clone())
+    }
+
+    pub fn set(&mut self, key: &Value, value: Value) -> Result<(), String> {
+        self.insert(Key::from_value(key)?, value);
+        Ok(())
+    }
+
+    fn insert(&mut self, key: Key, value: Value) {
+        if let Some(&index) = self.indices.get(&key) {
+            let entry = &mut self.entries[index].1;
+            match (matches!(entry, Value::Nil), matches!(value, Value::Nil)) {
+                (false, true) => self.removed += 1,
+                (true, false) => self.removed -= 1,
+                _ => {},
+            }
+            *entry = value;
+            return;
+        }
+        if matches!(value, Value::Nil) {
+            return;
+        }
+        // Adding keys while iterating isn't allowed, so it's safe to drop
+        // removed entries now
+        if self.removed > self.entries.len() / 2 {
+            self.entries.retain(|(_, value)| !matches!(value, Value::Nil));
+            self.indices = self.entries.iter().enumerate().map(|(index, (key, _))| (key.clone(), index)).collect();
+            self.removed = 0;
+        }
+        self.indices.insert(key.clone(), self.entries.len());
+        self.entries.push((key, value));
+    }
+
+    /// The entry after `key`, or the first for `nil`, as `next()` gives it.
+    fn next(&self, key: &Value) -> Result<Option<(Value, Value)>, String> {
+        let start = match key {
+            Value::Nil => 0,
+            key => Key::from_value(key).ok()
+                .and_then(|key| self.indices.get(&key))
+                .ok_or("invalid key to 'next'")? + 1,
+        };
+        Ok(self.entries[start..].iter()
+            .find(|(_, value)| !matches!(value, Value::Nil))
+            .map(|(key, value)| (key.to_value(), value.clone())))
+    }
+
+    /// The `#` of the table: how many entries run from 1 without a gap.
+    fn length(&self) -> usize {
+        let mut length = 0;
+        while !matches!(self.get(&Value::Number((length + 1) as f64)), Value::Nil) {
+            length += 1;
+        }
+        length
+    }
+}
+
+/// The local variables of a block, and the blocks around it.
+pub struct Scope {
+    variables: RefCell<Vec<(Rc<str>, Value)>>,
+    parent: Option<Rc<Scope>>,
+}
+
+impl Scope {
+    fn new(parent: Option<&Rc<Scope>>) -> Rc<Scope> {
+        Rc::new(Scope { variables: RefCell::new(Vec::new()), parent: parent.cloned() })
+    }
+
+    fn declare(&self, name: &Rc<str>, value: Value) {
+        self.variables.borrow_mut().push((name.clone(), value));
+    }
+
+    fn get(&self, name: &str) -> Option<Value> {
+        // The latest declaration hides earlier ones of the same name
+        match self.variables.borrow().iter().rev().find(|(variable, _)| &**variable == name) {
+            Some((_, value)) => Some(value.clone()),
+            None => self.parent.as_ref()?.get(name),
+        }
+    }
+
+    // Sets a local variable, returning false if there's none of that name
+    fn set(&self, name: &str, value: Value) -> bool {
+        match self.variables.borrow_mut().iter_mut().rev().find(|(variable, _)| &**variable == name) {
+            Some((_, variable)) => {
+                *variable = value;
+                true
+            },
+            None => self.parent.as_ref().is_some_and(|parent| parent.set(name, value)),
+        }
+    }
+}
+
+/// The parameters and statements of a function in a script.
+pub struct FunctionBody {
+    parameters: Vec<Rc<str>>,
+    body: Vec<Statement>,
+}
+
+struct Statement {
+    kind: StatementKind,
+    line: usize,
+}
+
+enum StatementKind {
+    Local(Vec<Rc<str>>, Vec<Expr>),
+    LocalFunction(Rc<str>, Rc<FunctionBody>),
+    Assign(Vec<Expr>, Vec<Expr>),
+    Call(Expr),
+    Do(Vec<Statement>),
+    While(Expr, Vec<Statement>),
+    Repeat(Vec<Statement>, Expr),
+    If(Vec<(Expr, Vec<Statement>)>, Option<Vec<Statement>>),
+    NumericFor { variable: Rc<str>, start: Expr, end: Expr, step: Option<Expr>, body: Vec<Statement> },
+    GenericFor { variables: Vec<Rc<str>>, values: Vec<Expr>, body: Vec<Statement> },
+    Return(Vec<Expr>),
+    Break,
+}
+
+enum Expr {
+    Nil,
+    Bool(bool),
+    Number(f64),
+    String(Rc<str>),
+    Name(Rc<str>),
+    Index(Box<Expr>, Box<Expr>),
+    Call(Box<Expr>, Vec<Expr>),
+    // `object:name(arguments)`, passing the object first
+    MethodCall(Box<Expr>, Rc<str>, Vec<Expr>),
+    Function(Rc<FunctionBody>),
+    // Entries with their key, or none for the next position in the list
+    Table(Vec<(Option<Expr>, Expr)>),
+    // In brackets, which keeps only the first value of a call
+    Paren(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Unary(UnaryOp, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+#[derive(Clone, Copy)]
+enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    FloorDivide,
+    Modulo,
+    Power,
+    Concat,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+#[derive(Clone, Copy)]
+enum UnaryOp {
+    Negate,
+    Not,
+    Length,
+}
+
+// Operator precedence, as (left, right): a higher right than left makes an
+// operator group to the right. Unary operators bind at `UNARY_PRIORITY`
+const UNARY_PRIORITY: u8 = 12;
+const BINARY_OPERATORS: [(&str, u8, u8); 16] = [
+    ("or", 1, 1),
+    ("and", 2, 2),
+    ("<", 3, 3),
+    (">", 3, 3),
+    ("<=", 3, 3),
+    (">=", 3, 3),
+    ("~=", 3, 3),
+    ("==", 3, 3),
+    ("..", 9, 8),
+    ("+", 10, 10),
+    ("-", 10, 10),
+    ("*", 11, 11),
+    ("/", 11, 11),
+    ("//", 11, 11),
+    ("%", 11, 11),
+    ("^", 14, 13),
+];
+
+#[derive(Clone, PartialEq)]
+enum Token {
+    Name(Rc<str>),
+    Number(f64),
+    String(Rc<str>),
+    // Keywords and punctuation
+    Symbol(&'static str),
+    End,
+}
+
+// Splits a script into tokens, each with its line
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, String> {
+    let text = source.as_bytes();
+    let mut tokens = Vec::new();
+    let mut position = 0;
+    let mut line = 1;
+    let error = |line: usize, message: &str| format!("line {}: {}", line, message);
+
+    while position < text.len() {
+        let byte = text[position];
+        if byte == b'\n' {
+            line += 1;
+            position += 1;
+        } else if byte.is_ascii_whitespace() {
+            position += 1;
+        } else if text[position..].starts_with(b"--") {
+            position += 2;
+            match long_bracket(text, position) {
+                Some((level, start)) => {
+                    let (_, end) = long_bracket_end(text, start, level).ok_or_else(|| error(line, "Unfinished comment"))?;
+                    line += text[position..end].iter().filter(|&&byte| byte == b'\n').count();
+                    position = end;
+                },
+                None => {
+                    while position < text.len() && text[position] != b'\n' {
+                        position += 1;
+                    }
+                },
+            }
+        } else if byte.is_ascii_alphabetic() || byte == b'_' {
+            let start = position;
+            while position < text.len() && (text[position].is_ascii_alphanumeric() || text[position] == b'_') {
+                position += 1;
+            }
+            let word = &source[start..position];
+            let token = match KEYWORDS.iter().find(|&&keyword| keyword == word) {
+                Some(&keyword) => Token::Symbol(keyword),
+                None => Token::Name(Rc::from(word)),
+            };
+            tokens.push((token, line));
+        } else if byte.is_ascii_digit() || (byte == b'.' && text.get(position + 1).is_some_and(u8::is_ascii_digit)) {
+            let start = position;
+            let number = if text[position..].starts_with(b"0x") || text[position..].starts_with(b"0X") {
+                position += 2;
+                while position < text.len() && text[position].is_ascii_hexdigit() {
+                    position += 1;
+                }
+                u64::from_str_radix(&source[start + 2..position], 16).ok().map(|n| n as f64)
+            } else {
+                while position < text.len() && (text[position].is_ascii_alphanumeric() || text[position] == b'.'
+                    || (matches!(text[position], b'+' | b'-') && matches!(text[position - 1], b'e' | b'E'))) {
+                    position += 1;
+                }
+                source[start..position].parse().ok()
+            };
+            tokens.push((Token::Number(number.ok_or_else(|| error(line, "Invalid number"))?), line));
+        } else if byte == b'"' || byte == b'\'' {
+            let mut bytes = Vec::new();
+            position += 1;
+            loop {
+                match text.get(position) {
+                    None | Some(b'\n') => return Err(error(line, "Unfinished string")),
+                    Some(&quote) if quote == byte => break,
+                    Some(b'\\') => {
+                        let decoded = match text.get(position + 1) {
+                            Some(b'n') => b'\n',
+                            Some(b't') => b'\t',
+                            Some(b'r') => b'\r',
+                            Some(b'0') => 0,
+                            Some(&escaped @ (b'\\' | b'"' | b'\'' | b'\n')) => escaped,
+                            _ => return Err(error(line, "Invalid escape")),
+                        };
+                        if decoded == b'\n' {
+                            line += 1;
+                        }
+                        bytes.push(decoded);
+                        position += 2;
+                    },
+                    Some(&other) => {
+                        bytes.push(other);
+                        position += 1;
+                    },
+                }
+            }
+            position += 1;
+            let string = String::from_utf8(bytes).map_err(|_| error(line, "Invalid UTF-8 in string"))?;
+            tokens.push((Token::String(Rc::from(string)), line));
+        } else if let Some((level, start)) = long_bracket(text, position) {
+            let (content_end, end) = long_bracket_end(text, start, level).ok_or_else(|| error(line, "Unfinished string"))?;
+            // A line break right after the opening bracket isn't part of the string
+            let content_start = if text.get(start) == Some(&b'\n') { start + 1 } else { start };
+            let string = std::str::from_utf8(&text[content_start..content_end]).map_err(|_| error(line, "Invalid UTF-8 in string"))?;
+            tokens.push((Token::String(Rc::from(string)), line));
+            line += text[position..end].iter().filter(|&&byte| byte == b'\n').count();
+            position = end;
+        } else {
+            let symbol = SYMBOLS.iter().filter(|symbol| text[position..].starts_with(symbol.as_bytes()))
+                .max_by_key(|symbol| symbol.len())
+                .ok_or_else(|| error(line, &format!("Unexpected `{}`", source[position..].chars().next().unwrap_or('?'))))?;
+            tokens.push((Token::Symbol(symbol), line));
+            position += symbol.len();
+        }
+    }
+    tokens.push((Token::End, line));
+    Ok(tokens)
+}
+
+// A long bracket such as `[[` or `[==[` at `position`, as its level (the
+// number of `=`) and where what it encloses starts
+fn long_bracket(text: &[u8], position: usize) -> Option<(usize, usize)> {
+    if text.get(position) != Some(&b'[') {
+        return None;
+    }
+    let level = text[position + 1..].iter().take_while(|&&byte| byte == b'=').count();
+    (text.get(position + 1 + level) == Some(&b'[')).then_some((level, position + level + 2))
+}
+
+// Where the long bracket of `level` closing what starts at `start` is, as
+// the end of what it encloses and the end of the bracket
+fn long_bracket_end(text: &[u8], start: usize, level: usize) -> Option<(usize, usize)> {
+    let mut closing = vec![b'='; level + 2];
+    closing[0] = b']';
+    closing[level + 1] = b']';
+    let end = text[start..].windows(closing.len()).position(|window| window == closing)? + start;
+    Some((end, end + closing.len()))
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    depth: usize,
+    // Loops around the statement being parsed, which `break` needs
+    loops: usize,
+}
+
+impl Parser {
+    fn parse(source: &str) -> Result<Vec<Statement>, String> {
+        let mut parser = Parser { tokens: tokenize(source)?, position: 0, depth: 0, loops: 0 };
+        let block = parser.block()?;
+        if parser.peek() != &Token::End {
+            return Err(parser.error("Expected the end of the script"));
+        }
+        Ok(block)
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("line {}: {}", self.line(), message)
+    }
+
+    fn line(&self) -> usize {
+        self.tokens[self.position].1
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.position].0
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.tokens[self.position].0.clone();
+        if token != Token::End {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn is(&self, symbol: &str) -> bool {
+        matches!(self.peek(), Token::Symbol(s) if *s == symbol)
+    }
+
+    // Skips `symbol` if it's next, returning whether it was
+    fn accept(&mut self, symbol: &str) -> bool {
+        let found = self.is(symbol);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, symbol: &str) -> Result<(), String> {
+        if !self.accept(symbol) {
+            return Err(self.error(&format!("Expected `{}`", symbol)));
+        }
+        Ok(())
+    }
+
+    fn name(&mut self) -> Result<Rc<str>, String> {
+        let Token::Name(name) = self.peek().clone() else {
+            return Err(self.error("Expected a name"));
+        };
+        self.position += 1;
+        Ok(name)
+    }
+
+    fn enter(&mut self) -> Result<(), String> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(self.error("Nested too deeply"));
+        }
+        Ok(())
+    }
+
+    fn block(&mut self) -> Result<Vec<Statement>, String> {
+        self.enter()?;
+        let mut statements = Vec::new();
+        while !matches!(self.peek(), Token::End) && !["end", "else", "elseif", "until"].iter().any(|end| self.is(end)) {
+            if self.accept(";") {
+                continue;
+            }
+            let statement = self.statement()?;
+            let returns = matches!(statement.kind, StatementKind::Return(_));
+            statements.push(statement);
+            // Nothing can follow a return in its block
+            if returns {
+                self.accept(";");
+                break;
+            }
+        }
+        self.depth -= 1;
+        Ok(statements)
+    }
+
+    fn loop_body(&mut self) -> Result<Vec<Statement>, String> {
+        self.loops += 1;
+        let body = self.block();
+        self.loops -= 1;
+        body
+    }
+
+    fn statement(&mut self) -> Result<Statement, String> {
+        let line = self.line();
+        let kind = match self.advance() {
+            Token::Symbol("if") => {
+                let mut branches = Vec::new();
+                let mut otherwise = None;
+                loop {
+                    let condition = self.expression()?;
+                    self.expect("then")?;
+                    branches.push((condition, self.block()?));
+                    if self.accept("elseif") {
+                        continue;
+                    }
+                    if self.accept("else") {
+                        otherwise = Some(self.block()?);
+                    }
+                    self.expect("end")?;
+                    break;
+                }
+                StatementKind::If(branches, otherwise)
+            },
+            Token::Symbol("while") => {
+                let condition = self.expression()?;
+                self.expect("do")?;
+                let body = self.loop_body()?;
+                self.expect("end")?;
+                StatementKind::While(condition, body)
+            },
+            Token::Symbol("do") => {
+                let body = self.block()?;
+                self.expect("end")?;
+                StatementKind::Do(body)
+            },
+            Token::Symbol("repeat") => {
+                let body = self.loop_body()?;
+                self.expect("until")?;
+                StatementKind::Repeat(body, self.expression()?)
+            },
+            Token::Symbol("for") => {
+                let first = self.name()?;
+                if self.accept("=") {
+                    let start = self.expression()?;
+                    self.expect(",")?;
+                    let end = self.expression()?;
+                    let step = if self.accept(",") { Some(self.expression()?) } else { None };
+                    self.expect("do")?;
+                    let body = self.loop_body()?;
+                    self.expect("end")?;
+                    StatementKind::NumericFor { variable: first, start, end, step, body }
+                } else {
+                    let mut variables = vec![first];
+                    while self.accept(",") {
+                        variables.push(self.name()?);
+                    }
+                    self.expect("in")?;
+                    let values = self.expression_list()?;
+                    self.expect("do")?;
+                    let body = self.loop_body()?;
+                    self.expect("end")?;
+                    StatementKind::GenericFor { variables, values, body }
+                }
+            },
+            Token::Symbol("function") => {
+                // `function a.b.c()`, or `function a:c()` with `self` first
+                let mut target = Expr::Name(self.name()?);
+                let mut method = false;
+                while self.is(".") || self.is(":") {
+                    method = self.accept(":");
+                    if !method {
+                        self.advance();
+                    }
+                    target = Expr::Index(Box::new(target), Box::new(Expr::String(self.name()?)));
+                    if method {
+                        break;
+                    }
+                }
+                let body = self.function_body(method)?;
+                StatementKind::Assign(vec![target], vec![Expr::Function(body)])
+            },
+            Token::Symbol("local") => {
+                if self.accept("function") {
+                    let name = self.name()?;
+                    StatementKind::LocalFunction(name, self.function_body(false)?)
+                } else {
+                    let mut names = vec![self.name()?];
+                    while self.accept(",") {
+                        names.push(self.name()?);
+                    }
+                    let values = if self.accept("=") { self.expression_list()? } else { Vec::new() };
+                    StatementKind::Local(names, values)
+                }
+            },
+            Token::Symbol("return") => {
+                let ends_block = matches!(self.peek(), Token::End) || ["end", "else", "elseif", "until", ";"].iter().any(|end| self.is(end));
+                StatementKind::Return(if ends_block { Vec::new() } else { self.expression_list()? })
+            },
+            Token::Symbol("break") => {
+                if self.loops == 0 {
+                    self.position -= 1;
+                    return Err(self.error("`break` outside a loop"));
+                }
+                StatementKind::Break
+            },
+            _ => {
+                self.position -= 1;
+                let first = self.suffixed_expression()?;
+                if self.is("=") || self.is(",") {
+                    let mut targets = vec![first];
+                    while self.accept(",") {
+                        targets.push(self.suffixed_expression()?);
+                    }
+                    if !targets.iter().all(|target| matches!(target, Expr::Name(_) | Expr::Index(..))) {
+                        return Err(self.error("Can't assign to that"));
+                    }
+                    self.expect("=")?;
+                    StatementKind::Assign(targets, self.expression_list()?)
+                } else if matches!(first, Expr::Call(..) | Expr::MethodCall(..)) {
+                    StatementKind::Call(first)
+                } else {
+                    return Err(self.error("Expected a statement"));
+                }
+            },
+        };
+        Ok(Statement { kind, line })
+    }
+
+    fn function_body(&mut self, method: bool) -> Result<Rc<FunctionBody>, String> {
+        let mut parameters = if method { vec![Rc::from("self")] } else { Vec::new() };
+        self.expect("(")?;
+        if !self.accept(")") {
+            loop {
+                parameters.push(self.name()?);
+                if !self.accept(",") {
+                    break;
+                }
+            }
+            self.expect(")")?;
+        }
+        // Loops around the function don't reach into it
+        let loops = std::mem::take(&mut self.loops);
+        let body = self.block();
+        self.loops = loops;
+        let body = body?;
+        self.expect("end")?;
+        Ok(Rc::new(FunctionBody { parameters, body }))
+    }
+
+    fn expression_list(&mut self) -> Result<Vec<Expr>, String> {
+        let mut expressions = vec![self.expression()?];
+        while self.accept(",") {
+            expressions.push(self.expression()?);
+        }
+        Ok(expressions)
+    }
+
+    fn expression(&mut self) -> Result<Expr, String> {
+        self.binary_expression(0)
+    }
+
+    // An expression of operators that bind tighter than `limit`
+    fn binary_expression(&mut self, limit: u8) -> Result<Expr, String> {
+        self.enter()?;
+        let unary = [("-", UnaryOp::Negate), ("not", UnaryOp::Not), ("#", UnaryOp::Length)].into_iter()
+            .find(|&(symbol, _)| self.is(symbol));
+        let mut left = match unary {
+            Some((_, op)) => {
+                self.advance();
+                Expr::Unary(op, Box::new(self.binary_expression(UNARY_PRIORITY)?))
+            },
+            None => self.simple_expression()?,
+        };
+        while let Some(&(symbol, left_priority, right_priority)) = BINARY_OPERATORS.iter().find(|&&(symbol, _, _)| self.is(symbol)) {
+            if left_priority <= limit {
+                break;
+            }
+            self.advance();
+            let right = Box::new(self.binary_expression(right_priority)?);
+            let left_side = Box::new(left);
+            left = match symbol {
+                "or" => Expr::Or(left_side, right),
+                "and" => Expr::And(left_side, right),
+                _ => {
+                    let op = match symbol {
+                        "+" => BinaryOp::Add,
+                        "-" => BinaryOp::Subtract,
+                        "*" => BinaryOp::Multiply,
+                        "/" => BinaryOp::Divide,
+                        "//" => BinaryOp::FloorDivide,
+                        "%" => BinaryOp::Modulo,
+                        "^" => BinaryOp::Power,
+                        ".." => BinaryOp::Concat,
+                        "==" => BinaryOp::Equal,
+                        "~=" => BinaryOp::NotEqual,
+                        "<" => BinaryOp::Less,
+                        "<=" => BinaryOp::LessEqual,
+                        ">" => BinaryOp::Greater,
+                        _ => BinaryOp::GreaterEqual,
+                    };
+                    Expr::Binary(op, left_side, right)
+                },
+            };
+        }
+        self.depth -= 1;
+        Ok(left)
+    }
+
+    fn simple_expression(&mut self) -> Result<Expr, String> {
+        let expression = match self.peek().clone() {
+            Token::Number(n) => Expr::Number(n),
+            Token::String(s) => Expr::String(s),
+            Token::Symbol("nil") => Expr::Nil,
+            Token::Symbol("true") => Expr::Bool(true),
+            Token::Symbol("false") => Expr::Bool(false),
+            Token::Symbol("function") => {
+                self.advance();
+                return Ok(Expr::Function(self.function_body(false)?));
+            },
+            Token::Symbol("{") => return self.table(),
+            Token::Symbol("...") => return Err(self.error("Varargs aren't supported")),
+            _ => return self.suffixed_expression(),
+        };
+        self.advance();
+        Ok(expression)
+    }
+
+    // A name or bracketed expression followed by any fields, indexes and calls
+    fn suffixed_expression(&mut self) -> Result<Expr, String> {
+        let mut expression = match self.peek().clone() {
+            Token::Name(name) => {
+                self.advance();
+                Expr::Name(name)
+            },
+            Token::Symbol("(") => {
+                self.advance();
+                let inner = self.expression()?;
+                self.expect(")")?;
+                Expr::Paren(Box::new(inner))
+            },
+            _ => return Err(self.error("Expected an expression")),
+        };
+        loop {
+            if self.accept(".") {
+                expression = Expr::Index(Box::new(expression), Box::new(Expr::String(self.name()?)));
+            } else if self.accept("[") {
+                let key = self.expression()?;
+                self.expect("]")?;
+                expression = Expr::Index(Box::new(expression), Box::new(key));
+            } else if self.accept(":") {
+                let name = self.name()?;
+                expression = Expr::MethodCall(Box::new(expression), name, self.arguments()?);
+            } else if self.is("(") || self.is("{") || matches!(self.peek(), Token::String(_)) {
+                expression = Expr::Call(Box::new(expression), self.arguments()?);
+            } else {
+                return Ok(expression);
+            }
+        }
+    }
+
+    // The arguments of a call: in brackets, or a single table or string
+    fn arguments(&mut self) -> Result<Vec<Expr>, String> {
+        if let Token::String(s) = self.peek().clone() {
+            self.advance();
+            return Ok(vec![Expr::String(s)]);
+        }
+        if self.is("{") {
+            return Ok(vec![self.table()?]);
+        }
+        self.expect("(")?;
+        if self.accept(")") {
+            return Ok(Vec::new());
+        }
+        let arguments = self.expression_list()?;
+        self.expect(")")?;
+        Ok(arguments)
+    }
+
+    fn table(&mut self) -> Result<Expr, String> {
+        self.expect("{")?;
+        let mut fields = Vec::new();
+        while !self.accept("}") {
+            if self.accept("[") {
+                let key = self.expression()?;
+                self.expect("]")?;
+                self.expect("=")?;
+                fields.push((Some(key), self.expression()?));
+            } else if matches!(self.peek(), Token::Name(_)) && self.tokens[self.position + 1].0 == Token::Symbol("=") {
+                let name = self.name()?;
+                self.advance();
+                fields.push((Some(Expr::String(name)), self.expression()?));
+            } else {
+                fields.push((None, self.expression()?));
+            }
+            if !self.accept(",") && !self.accept(";") {
+                self.expect("}")?;
+                break;
+            }
+        }
+        Ok(Expr::Table(fields))
+    }
+}
+
+// How a block ended
+enum Flow {
+    Normal,
+    Break,
+    Return(Vec<Value>),
+}
+
+/// A small Lua for plugins: functions and closures, tables, `if`, `while`,
+/// `repeat` and both kinds of `for`, with `type`, `tostring`, `tonumber`,
+/// `pairs`, `ipairs`, `next`, `error` and some of `math`. There are no
+/// varargs, metatables, coroutines or `string` library. Every call from the
+/// game may run a million steps, so a script stuck in a loop fails instead
+/// of freezing the game.
+pub struct Lua {
+    globals: Rc<RefCell<Table>>,
+    // Steps left before the running call is stopped
+    steps: usize,
+    // Script calls nested in the running call
+    depth: usize,
+    // Line of the statement running, for errors
+    line: usize,
+}
+
+impl Lua {
+    pub fn new() -> Self {
+        let mut lua = Self { globals: Rc::default(), steps: 0, depth: 0, line: 0 };
+        let functions: [(&str, NativeFunction); 8] = [
+            ("type", |_, arguments| Ok(vec![Value::string(arguments.first().unwrap_or(&Value::Nil).type_name())])),
+            ("tostring", |_, arguments| Ok(vec![Value::string(&arguments.first().unwrap_or(&Value::Nil).to_text())])),
+            ("tonumber", |_, arguments| Ok(vec![match arguments.first() {
+                Some(&Value::Number(n)) => Value::Number(n),
+                Some(Value::String(s)) => s.trim().parse().map_or(Value::Nil, Value::Number),
+                _ => Value::Nil,
+            }])),
+            ("next", next),
+            ("pairs", |_, arguments| {
+                table_argument(arguments, 0)?;
+                Ok(vec![Value::native(next), arguments[0].clone(), Value::Nil])
+            }),
+            ("ipairs", |_, arguments| {
+                table_argument(arguments, 0)?;
+                let step: NativeFunction = |_, arguments| {
+                    let index = number_argument(arguments, 1)? + 1.0;
+                    match table_argument(arguments, 0)?.borrow().get(&Value::Number(index)) {
+                        Value::Nil => Ok(vec![Value::Nil]),
+                        value => Ok(vec![Value::Number(index), value]),
+                    }
+                };
+                Ok(vec![Value::native(step), arguments[0].clone(), Value::Number(0.0)])
+            }),
+            ("error", |_, arguments| Err(arguments.first().map_or("error".to_string(), Value::to_text))),
+            ("assert", |_, arguments| match arguments.first() {
+                Some(value) if value.is_truthy() => Ok(arguments.to_vec()),
+                _ => Err(arguments.get(1).map_or("assertion failed!".to_string(), Value::to_text)),
+            }),
+        ];
+        for (name, function) in functions {
+            lua.set_global(name, Value::native(function));
+        }
+
+        let rounding: [(&str, NativeFunction); 4] = [
+            ("floor", |_, arguments| Ok(vec![Value::Number(number_argument(arguments, 0)?.floor())])),
+            ("ceil", |_, arguments| Ok(vec![Value::Number(number_argument(arguments, 0)?.ceil())])),
+            ("abs", |_, arguments| Ok(vec![Value::Number(number_argument(arguments, 0)?.abs())])),
+            ("sqrt", |_, arguments| Ok(vec![Value::Number(number_argument(arguments, 0)?.sqrt())])),
+        ];
+        let extremes: [(&str, NativeFunction); 2] = [
+            ("min", |_, arguments| extreme(arguments, f64::min)),
+            ("max", |_, arguments| extreme(arguments, f64::max)),
+        ];
+        let math = rounding.into_iter().chain(extremes).map(|(name, function)| (name, Value::native(function)))
+            .chain([("pi", Value::Number(std::f64::consts::PI)), ("huge", Value::Number(f64::INFINITY))]);
+        lua.set_global("math", Value::table(math));
+        lua
+    }
+
+    pub fn set_global(&mut self, name: &str, value: Value) {
+        self.globals.borrow_mut().insert(Key::String(Rc::from(name)), value);
+    }
+
+    /// Runs a whole script, which can set up globals for later calls.
+    pub fn run(&mut self, source: &str, context: &mut PluginContext) -> Result<(), String> {
+        let block = Parser::parse(source)?;
+        self.steps = MAX_STEPS;
+        self.depth = 0;
+        self.exec_block(&block, &Scope::new(None), context)?;
+        Ok(())
+    }
+
+    /// Calls a function the script handed to the game, returning its results.
+    pub fn call(&mut self, function: &Value, arguments: &[Value], context: &mut PluginContext) -> Result<Vec<Value>, String> {
+        self.steps = MAX_STEPS;
+        self.depth = 0;
+        self.call_function(function, arguments.to_vec(), context)
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("line {}: {}", self.line, message)
+    }
+
+    fn step(&mut self) -> Result<(), String> {
+        if self.steps == 0 {
+            return Err(self.error("Ran for too long"));
+        }
+        self.steps -= 1;
+        Ok(())
+    }
+
+    fn exec_block(&mut self, block: &[Statement], scope: &Rc<Scope>, context: &mut PluginContext) -> Result<Flow, String> {
+        self.exec_statements(block, &Scope::new(Some(scope)), context)
+    }
+
+    fn exec_statements(&mut self, block: &[Statement], scope: &Rc<Scope>, context: &mut PluginContext) -> Result<Flow, String> {
+        // Counted even when empty, so `while true do end` runs out too
+        self.step()?;
+        for statement in block {
+            match self.exec(statement, scope, context)? {
+                Flow::Normal => {},
+                flow => return Ok(flow),
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    fn exec(&mut self, statement: &Statement, scope: &Rc<Scope>, context: &mut PluginContext) -> Result<Flow, String> {
+        self.line = statement.line;
+        self.step()?;
+        match &statement.kind {
+            StatementKind::Local(names, expressions) => {
+                let mut values = self.eval_list(expressions, scope, context)?.into_iter();
+                for name in names {
+                    scope.declare(name, values.next().unwrap_or(Value::Nil));
+                }
+            },
+            StatementKind::LocalFunction(name, body) => {
+                // Declared first, so the function can call itself
+                scope.declare(name, Value::Nil);
+                scope.set(name, closure(body, scope));
+            },
+            StatementKind::Assign(targets, expressions) => {
+                let mut values = self.eval_list(expressions, scope, context)?.into_iter();
+                for target in targets {
+                    self.assign(target, values.next().unwrap_or(Value::Nil), scope, context)?;
+                }
+            },
+            StatementKind::Call(expression) => {
+                self.eval_multiple(expression, scope, context)?;
+            },
+            StatementKind::Do(body) => return self.exec_block(body, scope, context),
+            StatementKind::While(condition, body) => {
+                while self.eval(condition, scope, context)?.is_truthy() {
+                    match self.exec_block(body, scope, context)? {
+                        Flow::Normal => {},
+                        Flow::Break => break,
+                        flow => return Ok(flow),
+                    }
+                }
+            },
+            StatementKind::Repeat(body, condition) => loop {
+                // The condition sees the body's locals
+                let inner = Scope::new(Some(scope));
+                match self.exec_statements(body, &inner, context)? {
+                    Flow::Normal => {},
+                    Flow::Break => break,
+                    flow => return Ok(flow),
+                }
+                if self.eval(condition, &inner, context)?.is_truthy() {
+                    break;
+                }
+            },
+            StatementKind::If(branches, otherwise) => {
+                for (condition, body) in branches {
+                    if self.eval(condition, scope, context)?.is_truthy() {
+                        return self.exec_block(body, scope, context);
+                    }
+                }
+                if let Some(body) = otherwise {
+                    return self.exec_block(body, scope, context);
+                }
+            },
+            StatementKind::NumericFor { variable, start, end, step, body } => {
+                let mut number = |expression: &Expr, what: &str| match self.eval(expression, scope, context)? {
+                    Value::Number(n) => Ok(n),
+                    _ => Err(self.error(&format!("'for' {} must be a number", what))),
+                };
+                let start = number(start, "initial value")?;
+                let end = number(end, "limit")?;
+                let step = match step {
+                    Some(step) => number(step, "step")?,
+                    None => 1.0,
+                };
+                if step == 0.0 {
+                    return Err(self.error("'for' step is zero"));
+                }
+                let mut value = start;
+                while (step > 0.0 && value <= end) || (step < 0.0 && value >= end) {
+                    let inner = Scope::new(Some(scope));
+                    inner.declare(variable, Value::Number(value));
+                    match self.exec_statements(body, &inner, context)? {
+                        Flow::Normal => {},
+                        Flow::Break => break,
+                        flow => return Ok(flow),
+                    }
+                    value += step;
+                }
+            },
+            StatementKind::GenericFor { variables, values, body } => {
+                let mut values = self.eval_list(values, scope, context)?.into_iter();
+                let function = values.next().unwrap_or(Value::Nil);
+                let state = values.next().unwrap_or(Value::Nil);
+                let mut control = values.next().unwrap_or(Value::Nil);
+                loop {
+                    let results = self.call_function(&function, vec![state.clone(), control.clone()], context)?;
+                    control = results.first().cloned().unwrap_or(Value::Nil);
+                    if matches!(control, Value::Nil) {
+                        break;
+                    }
+                    let inner = Scope::new(Some(scope));
+                    let mut results = results.into_iter();
+                    for variable in variables {
+                        inner.declare(variable, results.next().unwrap_or(Value::Nil));
+                    }
+                    match self.exec_statements(body, &inner, context)? {
+                        Flow::Normal => {},
+                        Flow::Break => break,
+                        flow => return Ok(flow),
+                    }
+                }
+            },
+            StatementKind::Return(expressions) => return Ok(Flow::Return(self.eval_list(expressions, scope, context)?)),
+            StatementKind::Break => return Ok(Flow::Break),
+        }
+        Ok(Flow::Normal)
+    }
+
+    fn assign(&mut self, target: &Expr, value: Value, scope: &Rc<Scope>, context: &mut PluginContext) -> Result<(), String> {
+        match target {
+            Expr::Name(name) => {
+                if !scope.set(name, value.clone()) {
+                    self.globals.borrow_mut().insert(Key::String(name.clone()), value);
+                }
+                Ok(())
+            },
+            Expr::Index(table, key) => {
+                let table = self.eval(table, scope, context)?;
+                let key = self.eval(key, scope, context)?;
+                match table {
+                    Value::Table(table) => table.borrow_mut().set(&key, value).map_err(|e| self.error(&e)),
+                    other => Err(self.error(&format!("attempt to index a {} value", other.type_name()))),
+                }
+            },
+            _ => Err(self.error("Can't assign to that")),
+        }
+    }
+
+    // Every value of a list of expressions, where a call at the end gives
+    // all of its results and anything else gives one
+    fn eval_list(&mut self, expressions: &[Expr], scope: &Rc<Scope>, context: &mut PluginContext) -> Result<Vec<Value>, String> {
+        let mut values = Vec::with_capacity(expressions.len());
+        for (index, expression) in expressions.iter().enumerate() {
+            if index + 1 == expressions.len() {
+                values.extend(self.eval_multiple(expression, scope, context)?);
+            } else {
+                values.push(self.eval(expression, scope, context)?);
+            }
+        }
+        Ok(values)
+    }
+
+    // All the results of a call, or the one value of anything else
+    fn eval_multiple(&mut self, expression: &Expr, scope: &Rc<Scope>, context: &mut PluginContext) -> Result<Vec<Value>, String> {
+        match expression {
+            Expr::Call(function, arguments) => {
+                let function = self.eval(function, scope, context)?;
+                let arguments = self.eval_list(arguments, scope, context)?;
+                self.call_function(&function, arguments, context)
+            },
+            Expr::MethodCall(object, name, arguments) => {
+                let object = self.eval(object, scope, context)?;
+                let function = self.index(&object, &Value::String(name.clone()))?;
+                let mut values = vec![object];
+                values.extend(self.eval_list(arguments, scope, context)?);
+                self.call_function(&function, values, context)
+            },
+            _ => Ok(vec![self.eval(expression, scope, context)?]),
+        }
+    }
+
+    fn eval(&mut self, expression: &Expr, scope: &Rc<Scope>, context: &mut PluginContext) -> Result<Value, String> {
+        Ok(match expression {
+            Expr::Nil => Value::Nil,
+            Expr::Bool(b) => Value::Bool(*b),
+            Expr::Number(n) => Value::Number(*n),
+            Expr::String(s) => Value::String(s.clone()),
+            Expr::Name(name) => match scope.get(name) {
+                Some(value) => value,
+                None => self.globals.borrow().get(&Value::String(name.clone())),
+            },
+            Expr::Index(table, key) => {
+                let table = self.eval(table, scope, context)?;
+                let key = self.eval(key, scope, context)?;
+                self.index(&table, &key)?
+            },
+            Expr::Call(..) | Expr::MethodCall(..) => {
+                self.eval_multiple(expression, scope, context)?.into_iter().next().unwrap_or(Value::Nil)
+            },
+            Expr::Function(body) => closure(body, scope),
+            Expr::Table(fields) => {
+                let mut table = Table::default();
+                let mut position = 1.0;
+                for (index, (key, value)) in fields.iter().enumerate() {
+                    match key {
+                        Some(key) => {
+                            let key = self.eval(key, scope, context)?;
+                            let value = self.eval(value, scope, context)?;
+                            table.set(&key, value).map_err(|e| self.error(&e))?;
+                        },
+                        None => {
+                            let values = if index + 1 == fields.len() {
+                                self.eval_multiple(value, scope, context)?
+                            } else {
+                                vec![self.eval(value, scope, context)?]
+                            };
+                            for value in values {
+                                table.set(&Value::Number(position), value)?;
+                                position += 1.0;
+                            }
+                        },
+                    }
+                }
+                Value::Table(Rc::new(RefCell::new(table)))
+            },
+            Expr::Paren(inner) => self.eval(inner, scope, context)?,
+            Expr::Binary(op, left, right) => {
+                let left = self.eval(left, scope, context)?;
+                let right = self.eval(right, scope, context)?;
+                self.binary(*op, left, right)?
+            },
+            Expr::Unary(op, operand) => match (op, self.eval(operand, scope, context)?) {
+                (UnaryOp::Not, value) => Value::Bool(!value.is_truthy()),
+                (UnaryOp::Negate, Value::Number(n)) => Value::Number(-n),
+                (UnaryOp::Length, Value::String(s)) => Value::Number(s.len() as f64),
+                (UnaryOp::Length, Value::Table(table)) => Value::Number(table.borrow().length() as f64),
+                (UnaryOp::Negate, value) => return Err(self.error(&format!("attempt to do arithmetic on a {} value", value.type_name()))),
+                (UnaryOp::Length, value) => return Err(self.error(&format!("attempt to get length of a {} value", value.type_name()))),
+            },
+            Expr::And(left, right) => {
+                let left = self.eval(left, scope, context)?;
+                if left.is_truthy() { self.eval(right, scope, context)? } else { left }
+            },
+            Expr::Or(left, right) => {
+                let left = self.eval(left, scope, context)?;
+                if left.is_truthy() { left } else { self.eval(right, scope, context)? }
+            },
+        })
+    }
+
+    fn index(&self, table: &Value, key: &Value) -> Result<Value, String> {
+        match table {
+            Value::Table(table) => Ok(table.borrow().get(key)),
+            other => Err(self.error(&format!("attempt to index a {} value", other.type_name()))),
+        }
+    }
+
+    fn binary(&self, op: BinaryOp, left: Value, right: Value) -> Result<Value, String> {
+        let comparison = |ordering: fn(Ordering) -> bool| {
+            let order = match (&left, &right) {
+                (Value::Number(a), Value::Number(b)) => a.partial_cmp(b),
+                (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+                _ => return Err(self.error(&format!("attempt to compare {} with {}", left.type_name(), right.type_name()))),
+            };
+            // Comparisons with NaN are all false
+            Ok(Value::Bool(order.is_some_and(ordering)))
+        };
+        match op {
+            BinaryOp::Equal => return Ok(Value::Bool(left.equals(&right))),
+            BinaryOp::NotEqual => return Ok(Value::Bool(!left.equals(&right))),
+            BinaryOp::Less => return comparison(Ordering::is_lt),
+            BinaryOp::LessEqual => return comparison(Ordering::is_le),
+            BinaryOp::Greater => return comparison(Ordering::is_gt),
+            BinaryOp::GreaterEqual => return comparison(Ordering::is_ge),
+            BinaryOp::Concat => {
+                return match (&left, &right) {
+                    (Value::String(_) | Value::Number(_), Value::String(_) | Value::Number(_)) => {
+                        Ok(Value::string(&(left.to_text() + &right.to_text())))
+                    },
+                    _ => {
+                        let culprit = if matches!(left, Value::String(_) | Value::Number(_)) { &right } else { &left };
+                        Err(self.error(&format!("attempt to concatenate a {} value", culprit.type_name())))
+                    },
+                };
+            },
+            _ => {},
+        }
+        let (a, b) = match (&left, &right) {
+            (&Value::Number(a), &Value::Number(b)) => (a, b),
+            _ => {
+                let culprit = if matches!(left, Value::Number(_)) { &right } else { &left };
+                return Err(self.error(&format!("attempt to do arithmetic on a {} value", culprit.type_name())));
+            },
+        };
+        Ok(Value::Number(match op {
+            BinaryOp::Add => a + b,
+            BinaryOp::Subtract => a - b,
+            BinaryOp::Multiply => a * b,
+            BinaryOp::Divide => a / b,
+            BinaryOp::FloorDivide => (a / b).floor(),
+            // Takes the sign of the divisor, unlike Rust's %
+            BinaryOp::Modulo => a - (a / b).floor() * b,
+            _ => a.powf(b),
+        }))
+    }
+
+    fn call_function(&mut self, function: &Value, arguments: Vec<Value>, context: &mut PluginContext) -> Result<Vec<Value>, String> {
+        self.step()?;
+        let Value::Function(function) = function else {
+            return Err(self.error(&format!("attempt to call a {} value", function.type_name())));
+        };
+        match &**function {
+            Function::Native(native) => native(context, &arguments).map_err(|e| self.error(&e)),
+            Function::Script { body, scope } => {
+                if self.depth >= MAX_CALL_DEPTH {
+                    return Err(self.error("Calls nested too deeply"));
+                }
+                self.depth += 1;
+                let line = self.line;
+                let inner = Scope::new(Some(scope));
+                let mut arguments = arguments.into_iter();
+                for parameter in &body.parameters {
+                    inner.declare(parameter, arguments.next().unwrap_or(Value::Nil));
+                }
+                let flow = self.exec_statements(&body.body, &inner, context);
+                self.depth -= 1;
+                self.line = line;
+                match flow? {
+                    Flow::Return(values) => Ok(values),
+                    _ => Ok(Vec::new()),
+                }
+            },
+        }
+    }
+}
+
+fn closure(body: &Rc<FunctionBody>, scope: &Rc<Scope>) -> Value {
+    Value::Function(Rc::new(Function::Script { body: body.clone(), scope: scope.clone() }))
+}
+
+fn table_argument(arguments: &[Value], index: usize) -> Result<&Rc<RefCell<Table>>, String> {
+    match arguments.get(index) {
+        Some(Value::Table(table)) => Ok(table),
+        other => Err(bad_argument(index, "table", other)),
+    }
+}
+
+fn next(_: &mut PluginContext, arguments: &[Value]) -> Result<Vec<Value>, String> {
+    let table = table_argument(arguments, 0)?;
+    match table.borrow().next(arguments.get(1).unwrap_or(&Value::Nil))? {
+        Some((key, value)) => Ok(vec![key, value]),
+        None => Ok(vec![Value::Nil]),
+    }
+}
+
+// The smallest or largest of the arguments, by `pick`
+fn extreme(arguments: &[Value], pick: fn(f64, f64) -> f64) -> Result<Vec<Value>, String> {
+    let mut result = number_argument(arguments, 0)?;
+    for index in 1..arguments.len() {
+        result = pick(result, number_argument(arguments, index)?);
+    }
+    Ok(vec![Value::Number(result)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Vec3;
+    use crate::world::World;
+
+    // Runs a script in a fresh interpreter, with an empty world to run it on
+    fn run(source: &str) -> Result<Lua, String> {
+        let mut world = World::new(0);
+        let mut context = PluginContext::new(&mut world, Vec3::new(0.0, 0.0, 0.0), "test");
+        let mut lua = Lua::new();
+        lua.run(source, &mut context)?;
+        Ok(lua)
+    }
+
+    // A global of a script that ran, as `tostring` writes it
+    fn global(lua: &Lua, name: &str) -> String {
+        lua.globals.borrow().get(&Value::string(name)).to_text()
+    }
+
+    fn assert_globals(source: &str, expected: &[(&str, &str)]) {
+        let lua = run(source).unwrap_or_else(|e| panic!("{} failed: {}", source, e));
+        for &(name, value) in expected {
+            assert_eq!(global(&lua, name), value, "{} in {}", name, source);
+        }
+    }
+
+    #[test]
+    fn arithmetic_follows_lua_precedence() {
+        assert_globals(
+            "a = 1 + 2 * 3 ^ 2  b = -2 ^ 2  c = 7 // 2  d = -7 % 3  e = 3 / 2  f = 2 ^ 3 ^ 2",
+            &[("a", "19"), ("b", "-4"), ("c", "3"), ("d", "2"), ("e", "1.5"), ("f", "512")],
+        );
+    }
+
+    #[test]
+    fn strings_concatenate_and_compare() {
+        assert_globals(
+            r#"a = "x" .. 1 .. "y"  b = #"hello"  c = "abc" < "abd"  d = [[long
+string]]  e = 'it\'s'"#,
+            &[("a", "x1y"), ("b", "5"), ("c", "true"), ("d", "long\nstring"), ("e", "it's")],
+        );
+    }
+
+    #[test]
+    fn logic_short_circuits() {
+        assert_globals(
+            "a = nil or 3  b = false and error('evaluated')  c = not nil  d = 1 and 2  e = 1 == 1.0  f = 'a' ~= 'a'",
+            &[("a", "3"), ("b", "false"), ("c", "true"), ("d", "2"), ("e", "true"), ("f", "false")],
+        );
+    }
+
+    #[test]
+    fn numbers_are_written_like_lua() {
+        assert_globals(
+            "a = tostring(3)  b = tostring(-0.5)  c = tostring(10 / 2)  d = tonumber(' 12 ')  e = tonumber('twelve')  f = math.floor(-1.5)",
+            &[("a", "3"), ("b", "-0.5"), ("c", "5"), ("d", "12"), ("e", "nil"), ("f", "-2")],
+        );
+    }
+
+    #[test]
+    fn control_flow_statements_run() {
+        assert_globals(
+            "
+            sum = 0
+            for i = 1, 10 do sum = sum + i end
+            down = ''
+            for i = 3, 1, -1 do down = down .. i end
+            n = 0
+            while true do n = n + 1 if n == 5 then break end end
+            r = 0
+            repeat local done = r >= 3 r = r + 1 until done
+            if sum > 100 then size = 'big' elseif sum > 50 then size = 'medium' else size = 'small' end
+            ",
+            &[("sum", "55"), ("down", "321"), ("n", "5"), ("r", "4"), ("size", "medium")],
+        );
+    }
+
+    #[test]
+    fn tables_hold_lists_and_fields() {
+        assert_globals(
+            "
+            t = {10, 20, 30, name = 'list', [6] = 'six'}
+            count = #t
+            t[4] = 40
+            longer = #t
+            field = t.name .. t['name']
+            float_key = t[2.0]
+            total = 0
+            for i, v in ipairs(t) do total = total + v end
+            keys = 0
+            for k, v in pairs(t) do keys = keys + 1 end
+            first = next({})
+            ",
+            &[
+                ("count", "3"), ("longer", "4"), ("field", "listlist"), ("float_key", "20"),
+                ("total", "100"), ("keys", "6"), ("first", "nil"),
+            ],
+        );
+    }
+
+    #[test]
+    fn functions_return_several_values() {
+        assert_globals(
+            "
+            local function pair() return 1, 2 end
+            a, b = pair()
+            c, d = pair(), 3
+            t = {pair(), pair()}
+            size = #t
+            m = math.max(pair())
+            ",
+            &[("a", "1"), ("b", "2"), ("c", "1"), ("d", "3"), ("size", "3"), ("m", "2")],
+        );
+    }
+
+    #[test]
+    fn methods_get_their_object() {
+        assert_globals(
+            "
+            account = {balance = 10}
+            function account.deposit(self, amount) self.balance = self.balance + amount end
+            account:deposit(5)
+            balance = account.balance
+            ",
+            &[("balance", "15")],
+        );
+    }
+
+    #[test]
+    fn closures_keep_their_upvalues() {
+        assert_globals(
+            "
+            local function counter()
+                local count = 0
+                return function() count = count + 1 return count end
+            end
+            local a, b = counter(), counter()
+            a() a()
+            first, second = a(), b()
+            ",
+            &[("first", "3"), ("second", "1")],
+        );
+    }
+
+    #[test]
+    fn closures_share_the_variables_they_capture() {
+        assert_globals(
+            "
+            local value = 1
+            local function get() return value end
+            local function set(v) value = v end
+            set(7)
+            shared = get()
+            outer = value
+            ",
+            &[("shared", "7"), ("outer", "7")],
+        );
+    }
+
+    #[test]
+    fn loops_give_each_iteration_its_own_variable() {
+        assert_globals(
+            "
+            local functions = {}
+            for i = 1, 3 do functions[i] = function() return i end end
+            first, last = functions[1](), functions[3]()
+            ",
+            &[("first", "1"), ("last", "3")],
+        );
+    }
+
+    #[test]
+    fn local_functions_can_recurse() {
+        assert_globals(
+            "local function fib(n) if n < 2 then return n end return fib(n - 1) + fib(n - 2) end  f = fib(15)",
+            &[("f", "610")],
+        );
+    }
+
+    #[test]
+    fn locals_stay_in_their_block() {
+        assert_globals(
+            "x = 1  do local x = 2  inner = x end  outer = x",
+            &[("inner", "2"), ("outer", "1")],
+        );
+    }
+
+    #[test]
+    fn the_game_can_call_script_functions() {
+        let mut world = World::new(0);
+        let mut context = PluginContext::new(&mut world, Vec3::new(0.0, 0.0, 0.0), "test");
+        let mut lua = Lua::new();
+        lua.run("function add(a, b) return a + b end", &mut context).unwrap();
+        let add = lua.globals.borrow().get(&Value::string("add"));
+        let results = lua.call(&add, &[Value::Number(2.0), Value::Number(3.0)], &mut context).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].to_text(), "5");
+    }
+
+    #[test]
+    fn endless_loops_run_out_of_steps() {
+        for source in ["while true do end", "repeat until false", "for i = 1, math.huge do end", "local function f() end while true do f() end"] {
+            let error = run(source).err().unwrap_or_else(|| panic!("{} finished", source));
+            assert!(error.contains("too long"), "{}: {}", source, error);
+        }
+    }
+
+    #[test]
+    fn steps_are_counted_again_for_every_call() {
+        let mut world = World::new(0);
+        let mut context = PluginContext::new(&mut world, Vec3::new(0.0, 0.0, 0.0), "test");
+        let mut lua = Lua::new();
+        lua.run("function spin(n) for i = 1, n do end end", &mut context).unwrap();
+        let spin = lua.globals.borrow().get(&Value::string("spin"));
+        // Each call gets its own budget, so many short calls never run out
+        for _ in 0..5 {
+            lua.call(&spin, &[Value::Number(100_000.0)], &mut context).unwrap();
+        }
+        assert!(lua.call(&spin, &[Value::Number(1e9)], &mut context).is_err());
+    }
+
+    #[test]
+    fn deep_recursion_fails_instead_of_overflowing() {
+        let error = run("local function f(n) return f(n + 1) end f(0)").err().unwrap();
+        assert!(error.contains("nested too deeply"), "{}", error);
+        // The interpreter is still usable after the failed call
+        assert_globals("local function f(n) if n == 0 then return 0 end return f(n - 1) end x = f(50)", &[("x", "0")]);
+    }
+
+    #[test]
+    fn deeply_nested_source_fails_to_parse() {
+        let depth = MAX_DEPTH * 10;
+        let sources = [
+            format!("x = {}1{}", "(".repeat(depth), ")".repeat(depth)),
+            format!("x = {}{}", "{".repeat(depth), "}".repeat(depth)),
+            format!("x = {}1", "-".repeat(depth)),
+            format!("{}{}", "do ".repeat(depth), "end ".repeat(depth)),
+            format!("x = 1{}", " .. 1".repeat(depth)),
+        ];
+        for source in &sources {
+            assert!(run(source).is_err(), "{}...", &source[..20]);
+        }
+    }
+
+    #[test]
+    fn malformed_scripts_are_errors() {
+        let sources = [
+            "x =",
+            "x = 1 +",
+            "if true then",
+            "if true x = 1 end",
+            "local = 3",
+            "local 1 = 3",
+            "x = 'unterminated",
+            "x = \"line\nbreak\"",
+            "x = [[never closed",
+            "--[[ never closed",
+            "for i = 1 do end",
+            "for do end",
+            "function (",
+            "function f(a, ) end",
+            "return return",
+            "a.b. = 1",
+            "x = 1 y",
+            "1 = x",
+            "f() = 1",
+            "x = {1, 2",
+            ")",
+            "end",
+            "@",
+            "x = 0x",
+            "x = 1e",
+            "x = ...",
+            "break break",
+            "x = t[",
+            "\u{0}",
+        ];
+        for source in sources {
+            assert!(run(source).is_err(), "{:?} ran", source);
+        }
+    }
+
+    #[test]
+    fn runtime_errors_are_reported() {
+        let cases = [
+            ("missing()", "call a nil value"),
+            ("local t = nil  x = t.field", "index a nil value"),
+            ("x = {} + 1", "arithmetic on a table value"),
+            ("x = 'a' .. {}", "concatenate a table value"),
+            ("x = 1 < 'a'", "compare number with string"),
+            ("x = #5", "length of a number value"),
+            ("t = {}  t[nil] = 1", ""),
+            ("for i = 1, 10, 0 do end", "step is zero"),
+            ("error('boom')", "boom"),
+            ("assert(false, 'nope')", "nope"),
+            ("math.floor('x')", "bad argument #1"),
+        ];
+        for (source, message) in cases {
+            let error = run(source).err().unwrap_or_else(|| panic!("{} ran", source));
+            assert!(error.contains(message), "{}: {}", source, error);
+            assert!(error.starts_with("line "), "{}: {}", source, error);
+        }
+    }
+}
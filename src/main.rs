@@ -1,765 +1,1174 @@
+// The browser build only runs the terrain demo in `web`, since the game's own
+// loop blocks and draws with OpenGL, which the browser has neither of, so most
+// of the game goes unused there
+#![cfg_attr(feature = "web", allow(dead_code))]
+
+mod animation;
+mod anvil;
+mod ao_bake;
+#[cfg(feature = "wgpu")]
+mod asset_files;
+mod atomic_file;
+mod autosave;
+mod benchmark;
+mod block;
+mod block_entity;
+mod byte_reader;
+mod camera_effects;
+mod chunk_column;
+mod chunk_data;
+mod chunk_renderer;
+mod circuits;
+mod clock;
+mod clouds;
+mod column_heights;
+mod commands;
+mod compressed_texture;
+mod console;
+mod crafting;
+mod debug;
+mod debug_lines;
+mod entity;
+mod entity_renderer;
+mod error;
+mod events;
+mod explosion;
+mod export;
+mod feedback;
+mod frame_uniforms;
+mod game_mode;
 mod gl_utils;
+mod health;
+mod heightmap;
+mod held_block;
+mod hot_reload;
+mod hud;
+mod inventory;
+mod item;
+mod json;
+mod language;
+mod light;
+mod loading;
+mod logging;
+mod lua;
+mod map_screen;
 mod math;
-
-use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
-use sdl2::video::GLProfile;
-use math::{Mat4, Vec3};
+mod memory;
+mod mesh_cache;
+mod mesh_pool;
+mod mesher;
+mod minimap;
+mod mob;
+mod music;
+mod nbt;
+mod network;
+mod observer;
+mod palette;
+mod particles;
+mod platform;
+#[cfg(feature = "sdl2")]
+mod platform_sdl;
+#[cfg(feature = "winit")]
+mod platform_winit;
+#[cfg(feature = "web")]
+mod platform_web;
+mod player;
+mod plugins;
+mod point_lights;
+mod profiler;
+mod projectile;
+mod protocol;
+mod random;
+mod renderer;
+mod replay;
+mod schematic;
+mod screenshot;
+mod selection;
+mod settings;
+mod sign;
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+mod simd;
+mod sky;
+mod sound;
+mod spawn;
+mod spawning;
+mod streaming;
+mod structures;
+mod teleport;
+mod terrain_noise;
+mod text;
+mod ticks;
+mod timestep;
+mod tnt;
+mod ttf;
+mod view_mode;
+mod wav;
+#[cfg(feature = "web")]
+mod web;
+mod world;
+mod world_border;
+mod world_generator;
+mod world_io;
+mod world_metadata;
+#[cfg(feature = "wgpu")]
+mod wgpu_renderer;
+
+use anvil::AnvilWorld;
+use autosave::Autosaver;
+use benchmark::{Benchmark, DEFAULT_BENCHMARK_DURATION};
+use block::{BlockTexture, BlockType, BIOME_COLORMAP_PATH};
+use camera_effects::CameraEffects;
+use chunk_data::ChunkStore;
+use clouds::Clouds;
+use commands::{CommandContext, CommandRegistry, DAY_LENGTH, SCREENSHOT_DIRECTORY};
+use console::Console;
+use crafting::{RecipeRegistry, PACK_DIRECTORY};
+use debug::{DebugModes, DebugOverlay};
+use debug_lines::DebugLines;
+use entity::Entities;
+use entity_renderer::EntityRenderer;
+use error::EngineError;
+use events::{BlockChangeCause, BlockChanged, ChunkLoaded, ChunkProgress, ChunkStage, EventBus, Exploded, KeyAction, PlayerHurt, PlayerMoved, Subscriber};
+use feedback::Feedback;
+use frame_uniforms::{FrameUniformBuffer, FrameUniforms};
+use game_mode::Digging;
+use gl_utils::{GlCapabilities, REQUIRED_GL_VERSION};
+use health::{DamageCause, Health};
+use heightmap::{Heightmap, DEFAULT_HEIGHTMAP_SCALE};
+use held_block::HeldBlock;
+use hot_reload::FolderWatcher;
+use hud::Hud;
+use inventory::Inventory;
+use item::DroppedItems;
+use loading::LoadingScreen;
+use map_screen::MapScreen;
+use math::{Frustum, Mat4, Quat, Transform, Vec3};
+use memory::MemoryUsage;
+use mesh_cache::MeshCache;
+use minimap::{MapColors, Minimap};
+use mob::Mobs;
+use music::{Mood, MusicPlayer, MUSIC_DIRECTORY};
+use network::{Client, DEFAULT_PORT};
+use observer::OBSERVER_SIZE;
+use particles::ParticleSystem;
+use platform::{ActivePlatform, Event, Graphics, Key, MouseButton, Platform};
+use player::{MoveInput, MoveMode, Player, EYE_HEIGHT, PLAYER_WIDTH};
+use plugins::{Plugins, PLUGIN_DIRECTORY};
+use point_lights::PointLights;
+use profiler::{Profiler, System};
+use projectile::{ProjectileKind, Projectiles};
+use protocol::Message;
+use renderer::{GlRenderer, Renderer};
+use replay::{Replay, ReplayFrame, HELD_KEYS};
+use selection::{queue_target_outline, Selection};
+use settings::{parse_limit, Settings, SettingsScreen, SETTINGS_PATH};
+use sign::{Sign, SignEditor, SignRenderer};
+use sky::Sky;
+use sound::SoundManager;
+use spawn::{SpawnPoint, SPAWN_SEARCH_RADIUS};
+use spawning::SpawnCycle;
+use streaming::{ChunkStreamer, StreamUpdate, Viewer};
+use teleport::PendingTeleport;
+use text::FONT_PATH;
+use ticks::TickScheduler;
+use timestep::FixedTimestep;
+use tnt::{Tnts, FUSE_TIME};
+use ttf::Font;
+use view_mode::ViewMode;
+use world::{World, CHUNK_SIZE, WORLD_HEIGHT_CHUNKS, WORLD_SEED};
+use world_border::BorderRenderer;
+use world_generator::{HeightmapGenerator, Preset, DEFAULT_SNOW_ALTITUDE};
+use world_io::{SaveJob, WorldIo};
+use world_metadata::WorldMetadata;
+#[cfg(feature = "wgpu")]
+use wgpu_renderer::WgpuRenderer;
+use std::collections::HashMap;
 use std::thread;
-use std::time::Duration;
+use std::time::Instant;
 use std::fs;
-use std::collections::HashMap;
-use noise::{NoiseFn, Perlin};
+use std::path::{Path, PathBuf};
 
-type Vertex = [f32; 8];  // x, y, z, s, t, position, textureIndex, textSize
+type Vertex = [u32; 2];  // packed position, packed texture coordinates and layer; see mesher::pack_vertex
 type TriIndexes = [u32; 3];
 
-const CHUNK_SIZE: usize = 16;
-
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
-struct BlockPosition {
-    x: usize,
-    y: usize,
-    z: usize,
-}
-
-#[derive(Clone, Copy, PartialEq)]
-enum BlockType {
-    Air,
-    Grass,
-    Dirt,
-    Stone,
-    Water,
-}
+const WINDOW_WIDTH: u32 = 800;
+const WINDOW_HEIGHT: u32 = 600;
+
+// Chunk columns generated per frame while streaming, to keep frame times smooth
+const COLUMNS_PER_FRAME: usize = 4;
+// Chunk columns generated between frames of the loading screen at startup
+const COLUMNS_PER_LOADING_STEP: usize = 16;
+// How far away the player can break blocks
+const REACH_DISTANCE: f32 = 6.0;
+// Chunks further than this, in chunks, keep their mesh only on the GPU. The
+// chunk overlay's columns are all within it
+const MESH_KEEP_DISTANCE: i32 = 6;
+// How often the player's position is sent to the server, in seconds
+const POSITION_SEND_INTERVAL: f32 = 0.05;
+// Fraction of the render distance where the fog starts thickening
+const FOG_START: f32 = 0.75;
+// How far the player sees with their head under water, and the colour of the
+// water fog and tint at noon
+const UNDERWATER_FOG_END: f32 = 16.0;
+const UNDERWATER_COLOR: [f32; 3] = [0.05, 0.15, 0.35];
 
-struct Chunk {
-    position: (i32, i32, i32),  // Chunk position in world space
-    blocks: Vec<Vec<Vec<BlockType>>>,
-    visible_blocks: HashMap<BlockPosition, BlockType>,
-    vertices: Vec<Vertex>,
-    indices: Vec<TriIndexes>,
-    vertex_count: u32,
+// Add camera struct
+struct Camera {
+    position: Vec3,
+    // Position before the last physics step, to draw in between the two
+    previous_position: Vec3,
+    front: Vec3,
+    up: Vec3,
+    yaw: f32,
+    pitch: f32,
 }
 
-impl Chunk {
-    fn new(position: (i32, i32, i32)) -> Self {
-        let mut chunk = Self {
-            position,
-            blocks: vec![vec![vec![BlockType::Air; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE],
-            visible_blocks: HashMap::new(),
-            vertices: Vec::new(),
-            indices: Vec::new(),
-            vertex_count: 0,
-        };
-        chunk.generate_terrain();
-        chunk
-    }
-
-    fn generate_terrain(&mut self) {
-        // Create noise generators
-        let terrain_noise = Perlin::new(42);  // Base terrain height
-        let detail_noise = Perlin::new(123);  // Additional detail
-        let cave_noise = Perlin::new(666);    // Cave system
-
-        for x in 0..CHUNK_SIZE {
-            for z in 0..CHUNK_SIZE {
-                // Convert local coordinates to global coordinates
-                let world_x = self.position.0 * CHUNK_SIZE as i32 + x as i32;
-                let world_z = self.position.2 * CHUNK_SIZE as i32 + z as i32;
-                
-                // Generate base terrain height
-                let nx = world_x as f64 * 0.02;
-                let nz = world_z as f64 * 0.02;
-                
-                // Combine different noise layers for more interesting terrain
-                let base_height = terrain_noise.get([nx, nz]) * 32.0 + 64.0;  // Base terrain
-                let detail = detail_noise.get([nx * 4.0, nz * 4.0]) * 8.0;    // Small details
-                let height = (base_height + detail) as i32;
-
-                for y in 0..CHUNK_SIZE {
-                    let world_y = self.position.1 * CHUNK_SIZE as i32 + y as i32;
-                    
-                    // Cave generation
-                    let cave_value = cave_noise.get([
-                        world_x as f64 * 0.05,
-                        world_y as f64 * 0.05,
-                        world_z as f64 * 0.05
-                    ]);
-
-                    // Determine block type based on height and noise values
-                    if world_y < height {
-                        // Cave generation
-                        if cave_value > 0.6 {
-                            self.blocks[x][y][z] = BlockType::Air;
-                        } else {
-                            // Normal terrain
-                            if world_y == height - 1 {
-                                self.blocks[x][y][z] = BlockType::Grass;
-                            } else if world_y > height - 4 {
-                                self.blocks[x][y][z] = BlockType::Dirt;
-                            } else {
-                                self.blocks[x][y][z] = BlockType::Stone;
-                            }
-                        }
-                    } else if world_y < 60 { // Water level
-                        self.blocks[x][y][z] = BlockType::Water;
-                    } else {
-                        self.blocks[x][y][z] = BlockType::Air;
-                    }
-                }
-            }
+impl Camera {
+    fn new() -> Self {
+        Self {
+            position: Vec3::new(0.0, 100.0, 0.0),  // Moved back and up to see the chunks
+            previous_position: Vec3::new(0.0, 100.0, 0.0),
+            front: Vec3::new(0.0, -0.3, -1.0),      // Looking slightly down
+            up: Vec3::new(0.0, 1.0, 0.0),
+            yaw: -90.0,
+            pitch: -15.0,
         }
     }
 
-    fn update(&mut self, world: &World) {
-        // Clear previous data
-        self.visible_blocks.clear();
-        self.vertices.clear();
-        self.indices.clear();
-        self.vertex_count = 0;
+    // Where the camera is and which way it looks, `alpha` of the way between
+    // the last two physics steps, moved by `offset` for camera effects, or
+    // where `view_mode` puts the camera around there. It looks down its -Z axis
+    fn transform(&self, alpha: f32, offset: Vec3, view_mode: ViewMode) -> Transform {
+        let eye = self.previous_position.lerp(&self.position, alpha) + offset;
+        let (eye, front) = view_mode.camera(eye, self.front);
+        Transform::looking_at(eye, eye + front, self.up)
+    }
 
-        // Identify visible blocks
-        for x in 0..CHUNK_SIZE {
-            for y in 0..CHUNK_SIZE {
-                for z in 0..CHUNK_SIZE {
-                    let block_type = self.blocks[x][y][z];
-                    if block_type != BlockType::Air {
-                        // Convert to world coordinates
-                        let world_x = self.position.0 * CHUNK_SIZE as i32 + x as i32;
-                        let world_y = self.position.1 * CHUNK_SIZE as i32 + y as i32;
-                        let world_z = self.position.2 * CHUNK_SIZE as i32 + z as i32;
+    fn get_view_matrix(&self, alpha: f32, offset: Vec3, view_mode: ViewMode) -> Mat4 {
+        self.transform(alpha, offset, view_mode).inverse().to_mat4()
+    }
 
-                        // Check if any face is visible using world coordinates
-                        if should_render_face(world, world_x, world_y, world_z, "front") ||
-                           should_render_face(world, world_x, world_y, world_z, "back") ||
-                           should_render_face(world, world_x, world_y, world_z, "top") ||
-                           should_render_face(world, world_x, world_y, world_z, "bottom") ||
-                           should_render_face(world, world_x, world_y, world_z, "right") ||
-                           should_render_face(world, world_x, world_y, world_z, "left") {
-                            self.visible_blocks.insert(BlockPosition { x, y, z }, block_type);
-                        }
-                    }
-                }
-            }
+    // Where the chunks stream around, seen through the projection of `view_mode`
+    fn viewer(&self, view_mode: ViewMode, fov_degrees: f32) -> Viewer {
+        let transform = self.transform(1.0, Vec3::zero(), view_mode);
+        Viewer {
+            position: self.position,
+            direction: transform.rotation.rotate(Vec3::new(0.0, 0.0, -1.0)),
+            frustum: Frustum::from_matrix(&(projection_matrix(fov_degrees, view_mode) * transform.inverse().to_mat4())),
         }
+    }
 
-        // Generate vertices and indices for visible blocks
-        for (&block_pos, &block_type) in &self.visible_blocks {
-            let world_x = (self.position.0 * CHUNK_SIZE as i32) as f32 + block_pos.x as f32;
-            let world_y = (self.position.1 * CHUNK_SIZE as i32) as f32 + block_pos.y as f32;
-            let world_z = (self.position.2 * CHUNK_SIZE as i32) as f32 + block_pos.z as f32;
-
-            let cube_vertices = generate_cube_vertices(
-                world_x,
-                world_y,
-                world_z,
-                block_type,
-                world,
-                world_x as i32,
-                world_y as i32,
-                world_z as i32
-            );
-            
-            if !cube_vertices.is_empty() {
-                let cube_indices = generate_indices_for_vertices(self.vertex_count, cube_vertices.len() as u32);
-                self.vertices.extend_from_slice(&cube_vertices);
-                self.indices.extend_from_slice(&cube_indices);
-                self.vertex_count += cube_vertices.len() as u32;
-            }
-        }
+    fn update_camera_vectors(&mut self) {
+        let front = Vec3::new(
+            self.yaw.to_radians().cos() * self.pitch.to_radians().cos(),
+            self.pitch.to_radians().sin(),
+            self.yaw.to_radians().sin() * self.pitch.to_radians().cos()
+        );
+        self.front = front.normalize();
     }
 }
 
-struct World {
-    chunks: HashMap<(i32, i32, i32), Chunk>,
+fn projection_matrix(fov_degrees: f32, view_mode: ViewMode) -> Mat4 {
+    view_mode.projection(fov_degrees, WINDOW_WIDTH as f32 / WINDOW_HEIGHT as f32)
 }
 
-impl World {
-    fn new() -> Self {
-        Self {
-            chunks: HashMap::new(),
-        }
-    }
-
-    fn get_block(&self, world_x: i32, world_y: i32, world_z: i32) -> BlockType {
-        // Determine which chunk these coords belong to
-        let chunk_x = world_x.div_euclid(CHUNK_SIZE as i32);
-        let chunk_y = world_y.div_euclid(CHUNK_SIZE as i32);
-        let chunk_z = world_z.div_euclid(CHUNK_SIZE as i32);
-
-        // Find that chunk
-        if let Some(chunk) = self.chunks.get(&(chunk_x, chunk_y, chunk_z)) {
-            // Convert to local coords within chunk
-            let lx = (world_x.rem_euclid(CHUNK_SIZE as i32)) as usize;
-            let ly = (world_y.rem_euclid(CHUNK_SIZE as i32)) as usize;
-            let lz = (world_z.rem_euclid(CHUNK_SIZE as i32)) as usize;
+// Color of the sky and the fog, darker at night and brightest at noon (tick 6000)
+fn sky_color(daylight: f32) -> [f32; 3] {
+    [0.2 * daylight, 0.3 * daylight, 0.3 * daylight]
+}
 
-            chunk.blocks[lx][ly][lz]
-        } else {
-            BlockType::Air
-        }
-    }
+// Size of the screen the HUD and menus are laid out on, in their pixels.
+// They are drawn stretched to the window, so a larger scale makes it smaller
+fn ui_screen_size(ui_scale: f32) -> (f32, f32) {
+    (WINDOW_WIDTH as f32 / ui_scale, WINDOW_HEIGHT as f32 / ui_scale)
+}
 
-    fn add_chunk(&mut self, chunk: Chunk) {
-        self.chunks.insert(chunk.position, chunk);
+// Applies block edits to the meshes of the chunks they touch
+fn remesh_blocks(world: &mut World, renderer: &mut dyn Renderer, mesh_cache: &MeshCache, blocks: &[(i32, i32, i32)]) {
+    let touching = world.chunks_touching_blocks(blocks);
+    world.build_meshes(&touching, mesh_cache);
+    for pos in touching {
+        renderer.upload_chunk(world, pos);
     }
 }
 
-// Function to check if a face should be rendered based on adjacent blocks
-fn should_render_face(world: &World, world_x: i32, world_y: i32, world_z: i32, face: &str) -> bool {
-    let check_pos = match face {
-        "front" => (world_x, world_y, world_z + 1),
-        "back" => (world_x, world_y, world_z - 1),
-        "top" => (world_x, world_y + 1, world_z),
-        "bottom" => (world_x, world_y - 1, world_z),
-        "right" => (world_x + 1, world_y, world_z),
-        "left" => (world_x - 1, world_y, world_z),
-        _ => return true,
-    };
-    
-    // Special case for water: always render faces between water blocks
-    let current_block = world.get_block(world_x, world_y, world_z);
-    let neighbor_block = world.get_block(check_pos.0, check_pos.1, check_pos.2);
-    
-    match current_block {
-        BlockType::Water => {
-            // For water, only render faces between water and non-water blocks
-            // or if the neighbor is air
-            neighbor_block == BlockType::Air || neighbor_block != BlockType::Water
-        },
-        _ => {
-            // For solid blocks, render face if neighbor is air or water
-            neighbor_block == BlockType::Air || neighbor_block == BlockType::Water
-        }
-    }
+// Publishes that each of `blocks` changed to what it is now
+fn publish_block_changes(event_bus: &mut EventBus, world: &World, blocks: &[(i32, i32, i32)], cause: BlockChangeCause) {
+    event_bus.publish_all(blocks.iter().map(|&(x, y, z)| BlockChanged {
+        position: (x, y, z),
+        block: world.get_block(x, y, z),
+        previous: None,
+        cause,
+    }));
 }
 
-// Function to generate vertices for a cube at a specific position
-fn generate_cube_vertices(x: f32, y: f32, z: f32, block_type: BlockType, world: &World, 
-    world_x: i32, world_y: i32, world_z: i32) -> Vec<Vertex> {
-    let mut vertices = Vec::new();
-    
-    match block_type {
-        BlockType::Air => Vec::new(),
-        BlockType::Grass => {
-            // Front face
-            if should_render_face(world, world_x, world_y, world_z, "front") {
-                vertices.extend_from_slice(&[
-                    [x - 0.5, y - 0.5, z + 0.5,  0.0, 1.0, 0.0, 1.0, 1.0],
-                    [x + 0.5, y - 0.5, z + 0.5,  1.0, 1.0, 1.0, 1.0, 1.0],
-                    [x + 0.5, y + 0.5, z + 0.5,  1.0, 0.0, 2.0, 1.0, 1.0],
-                    [x - 0.5, y + 0.5, z + 0.5,  0.0, 0.0, 3.0, 1.0, 1.0],
-                ]);
-            }
-            
-            // Back face (grass_block_side)
-            if should_render_face(world, world_x, world_y, world_z, "back") {
-                vertices.extend_from_slice(&[
-                    [x - 0.5, y - 0.5, z - 0.5,  1.0, 1.0, 4.0, 1.0, 1.0],
-                    [x - 0.5, y + 0.5, z - 0.5,  1.0, 0.0, 5.0, 1.0, 1.0],
-                    [x + 0.5, y + 0.5, z - 0.5,  0.0, 0.0, 6.0, 1.0, 1.0],
-                    [x + 0.5, y - 0.5, z - 0.5,  0.0, 1.0, 7.0, 1.0, 1.0],
-                ]);
-            }
-            
-            // Top face (grass_block_top)
-            if should_render_face(world, world_x, world_y, world_z, "top") {
-                vertices.extend_from_slice(&[
-                    [x - 0.5, y + 0.5, z - 0.5,  0.0, 0.0, 8.0, 0.0, 1.0],
-                    [x - 0.5, y + 0.5, z + 0.5,  1.0, 0.0, 9.0, 0.0, 1.0],
-                    [x + 0.5, y + 0.5, z + 0.5,  1.0, 1.0, 10.0, 0.0, 1.0],
-                    [x + 0.5, y + 0.5, z - 0.5,  0.0, 1.0, 11.0, 0.0, 1.0],
-                ]);
-            }
-            
-            // Bottom face (dirt)
-            if should_render_face(world, world_x, world_y, world_z, "bottom") {
-                vertices.extend_from_slice(&[
-                    [x - 0.5, y - 0.5, z - 0.5,  0.0, 0.0, 12.0, 2.0, 1.0],
-                    [x + 0.5, y - 0.5, z - 0.5,  1.0, 0.0, 13.0, 2.0, 1.0],
-                    [x + 0.5, y - 0.5, z + 0.5,  1.0, 1.0, 14.0, 2.0, 1.0],
-                    [x - 0.5, y - 0.5, z + 0.5,  0.0, 1.0, 15.0, 2.0, 1.0],
-                ]);
-            }
-            
-            // Right face (grass_block_side)
-            if should_render_face(world, world_x, world_y, world_z, "right") {
-                vertices.extend_from_slice(&[
-                    [x + 0.5, y - 0.5, z - 0.5,  0.0, 1.0, 16.0, 1.0, 1.0],
-                    [x + 0.5, y + 0.5, z - 0.5,  0.0, 0.0, 17.0, 1.0, 1.0],
-                    [x + 0.5, y + 0.5, z + 0.5,  1.0, 0.0, 18.0, 1.0, 1.0],
-                    [x + 0.5, y - 0.5, z + 0.5,  1.0, 1.0, 19.0, 1.0, 1.0],
-                ]);
-            }
-            
-            // Left face (grass_block_side)
-            if should_render_face(world, world_x, world_y, world_z, "left") {
-                vertices.extend_from_slice(&[
-                    [x - 0.5, y - 0.5, z - 0.5,  1.0, 1.0, 20.0, 1.0, 1.0],
-                    [x - 0.5, y - 0.5, z + 0.5,  0.0, 1.0, 21.0, 1.0, 1.0],
-                    [x - 0.5, y + 0.5, z + 0.5,  0.0, 0.0, 22.0, 1.0, 1.0],
-                    [x - 0.5, y + 0.5, z - 0.5,  1.0, 0.0, 23.0, 1.0, 1.0],
-                ]);
-            }
-            vertices
-        },
-        BlockType::Dirt => {
-            // Front face (dirt)
-            if should_render_face(world, world_x, world_y, world_z, "front") {
-                vertices.extend_from_slice(&[
-                    [x - 0.5, y - 0.5, z + 0.5,  0.0, 1.0, 0.0, 2.0, 1.0],
-                    [x + 0.5, y - 0.5, z + 0.5,  1.0, 1.0, 1.0, 2.0, 1.0],
-                    [x + 0.5, y + 0.5, z + 0.5,  1.0, 0.0, 2.0, 2.0, 1.0],
-                    [x - 0.5, y + 0.5, z + 0.5,  0.0, 0.0, 3.0, 2.0, 1.0],
-                ]);
-            }
-            
-            // Back face (dirt)
-            if should_render_face(world, world_x, world_y, world_z, "back") {
-                vertices.extend_from_slice(&[
-                    [x - 0.5, y - 0.5, z - 0.5,  1.0, 1.0, 4.0, 2.0, 1.0],
-                    [x - 0.5, y + 0.5, z - 0.5,  1.0, 0.0, 5.0, 2.0, 1.0],
-                    [x + 0.5, y + 0.5, z - 0.5,  0.0, 0.0, 6.0, 2.0, 1.0],
-                    [x + 0.5, y - 0.5, z - 0.5,  0.0, 1.0, 7.0, 2.0, 1.0],
-                ]);
-            }
-            
-            // Top face (dirt)
-            if should_render_face(world, world_x, world_y, world_z, "top") {
-                vertices.extend_from_slice(&[
-                    [x - 0.5, y + 0.5, z - 0.5,  0.0, 0.0, 8.0, 2.0, 1.0],
-                    [x - 0.5, y + 0.5, z + 0.5,  1.0, 0.0, 9.0, 2.0, 1.0],
-                    [x + 0.5, y + 0.5, z + 0.5,  1.0, 1.0, 10.0, 2.0, 1.0],
-                    [x + 0.5, y + 0.5, z - 0.5,  0.0, 1.0, 11.0, 2.0, 1.0],
-                ]);
-            }
-            
-            // Bottom face (dirt)
-            if should_render_face(world, world_x, world_y, world_z, "bottom") {
-                vertices.extend_from_slice(&[
-                    [x - 0.5, y - 0.5, z - 0.5,  0.0, 0.0, 12.0, 2.0, 1.0],
-                    [x + 0.5, y - 0.5, z - 0.5,  1.0, 0.0, 13.0, 2.0, 1.0],
-                    [x + 0.5, y - 0.5, z + 0.5,  1.0, 1.0, 14.0, 2.0, 1.0],
-                    [x - 0.5, y - 0.5, z + 0.5,  0.0, 1.0, 15.0, 2.0, 1.0],
-                ]);
-            }
-            
-            // Right face (dirt)
-            if should_render_face(world, world_x, world_y, world_z, "right") {
-                vertices.extend_from_slice(&[
-                    [x + 0.5, y - 0.5, z - 0.5,  0.0, 1.0, 16.0, 2.0, 1.0],
-                    [x + 0.5, y + 0.5, z - 0.5,  0.0, 0.0, 17.0, 2.0, 1.0],
-                    [x + 0.5, y + 0.5, z + 0.5,  1.0, 0.0, 18.0, 2.0, 1.0],
-                    [x + 0.5, y - 0.5, z + 0.5,  1.0, 1.0, 19.0, 2.0, 1.0],
-                ]);
-            }
-            
-            // Left face (dirt)
-            if should_render_face(world, world_x, world_y, world_z, "left") {
-                vertices.extend_from_slice(&[
-                    [x - 0.5, y - 0.5, z - 0.5,  1.0, 1.0, 20.0, 2.0, 1.0],
-                    [x - 0.5, y - 0.5, z + 0.5,  0.0, 1.0, 21.0, 2.0, 1.0],
-                    [x - 0.5, y + 0.5, z + 0.5,  0.0, 0.0, 22.0, 2.0, 1.0],
-                    [x - 0.5, y + 0.5, z - 0.5,  1.0, 0.0, 23.0, 2.0, 1.0],
-                ]);
-            }
-            vertices
-        },
-        BlockType::Stone => {
-            // Front face
-            if should_render_face(world, world_x, world_y, world_z, "front") {
-                vertices.extend_from_slice(&[
-                    [x - 0.5, y - 0.5, z + 0.5,  0.0, 1.0, 0.0, 3.0, 1.0],
-                    [x + 0.5, y - 0.5, z + 0.5,  1.0, 1.0, 1.0, 3.0, 1.0],
-                    [x + 0.5, y + 0.5, z + 0.5,  1.0, 0.0, 2.0, 3.0, 1.0],
-                    [x - 0.5, y + 0.5, z + 0.5,  0.0, 0.0, 3.0, 3.0, 1.0],
-                ]);
-            }
-            
-            // Back face
-            if should_render_face(world, world_x, world_y, world_z, "back") {
-                vertices.extend_from_slice(&[
-                    [x - 0.5, y - 0.5, z - 0.5,  1.0, 1.0, 4.0, 3.0, 1.0],
-                    [x - 0.5, y + 0.5, z - 0.5,  1.0, 0.0, 5.0, 3.0, 1.0],
-                    [x + 0.5, y + 0.5, z - 0.5,  0.0, 0.0, 6.0, 3.0, 1.0],
-                    [x + 0.5, y - 0.5, z - 0.5,  0.0, 1.0, 7.0, 3.0, 1.0],
-                ]);
-            }
-            
-            // Top face
-            if should_render_face(world, world_x, world_y, world_z, "top") {
-                vertices.extend_from_slice(&[
-                    [x - 0.5, y + 0.5, z - 0.5,  0.0, 0.0, 8.0, 3.0, 1.0],
-                    [x - 0.5, y + 0.5, z + 0.5,  1.0, 0.0, 9.0, 3.0, 1.0],
-                    [x + 0.5, y + 0.5, z + 0.5,  1.0, 1.0, 10.0, 3.0, 1.0],
-                    [x + 0.5, y + 0.5, z - 0.5,  0.0, 1.0, 11.0, 3.0, 1.0],
-                ]);
-            }
-            
-            // Bottom face
-            if should_render_face(world, world_x, world_y, world_z, "bottom") {
-                vertices.extend_from_slice(&[
-                    [x - 0.5, y - 0.5, z - 0.5,  0.0, 0.0, 12.0, 3.0, 1.0],
-                    [x + 0.5, y - 0.5, z - 0.5,  1.0, 0.0, 13.0, 3.0, 1.0],
-                    [x + 0.5, y - 0.5, z + 0.5,  1.0, 1.0, 14.0, 3.0, 1.0],
-                    [x - 0.5, y - 0.5, z + 0.5,  0.0, 1.0, 15.0, 3.0, 1.0],
-                ]);
-            }
-            
-            // Right face
-            if should_render_face(world, world_x, world_y, world_z, "right") {
-                vertices.extend_from_slice(&[
-                    [x + 0.5, y - 0.5, z - 0.5,  0.0, 1.0, 16.0, 3.0, 1.0],
-                    [x + 0.5, y + 0.5, z - 0.5,  0.0, 0.0, 17.0, 3.0, 1.0],
-                    [x + 0.5, y + 0.5, z + 0.5,  1.0, 0.0, 18.0, 3.0, 1.0],
-                    [x + 0.5, y - 0.5, z + 0.5,  1.0, 1.0, 19.0, 3.0, 1.0],
-                ]);
-            }
-            
-            // Left face
-            if should_render_face(world, world_x, world_y, world_z, "left") {
-                vertices.extend_from_slice(&[
-                    [x - 0.5, y - 0.5, z - 0.5,  1.0, 1.0, 20.0, 3.0, 1.0],
-                    [x - 0.5, y - 0.5, z + 0.5,  0.0, 1.0, 21.0, 3.0, 1.0],
-                    [x - 0.5, y + 0.5, z + 0.5,  0.0, 0.0, 22.0, 3.0, 1.0],
-                    [x - 0.5, y + 0.5, z - 0.5,  1.0, 0.0, 23.0, 3.0, 1.0],
-                ]);
-            }
-            vertices
-        },
-        BlockType::Water => {
-            // Only render top face of water with transparency
-            if should_render_face(world, world_x, world_y, world_z, "top") {
-                vertices.extend_from_slice(&[
-                    [x - 0.5, y + 0.4, z - 0.5,  0.0, 0.0, 8.0, 4.0, 1.0],  // Slightly lower than full block
-                    [x - 0.5, y + 0.4, z + 0.5,  1.0, 0.0, 9.0, 4.0, 1.0],
-                    [x + 0.5, y + 0.4, z + 0.5,  1.0, 1.0, 10.0, 4.0, 1.0],
-                    [x + 0.5, y + 0.4, z - 0.5,  0.0, 1.0, 11.0, 4.0, 1.0],
-                ]);
-            }
-            vertices
-        },
+// Applies the settings that go through the platform; the others are read where they're used
+fn apply_window_settings(settings: &Settings, platform: &mut impl Platform) {
+    if let Err(e) = platform.set_vsync(settings.vsync) {
+        log::warn!(target: "render", "Failed to change vsync: {}", e);
+    }
+    if let Err(e) = platform.set_fullscreen(settings.fullscreen) {
+        log::warn!(target: "render", "Failed to change fullscreen: {}", e);
     }
 }
 
-// Function to generate indices for vertices
-fn generate_indices_for_vertices(vertex_offset: u32, vertex_count: u32) -> Vec<TriIndexes> {
-    let mut indices = Vec::new();
-    for i in (0..vertex_count).step_by(4) {
-        indices.push([
-            vertex_offset + i,
-            vertex_offset + i + 1,
-            vertex_offset + i + 2,
-        ]);
-        indices.push([
-            vertex_offset + i + 2,
-            vertex_offset + i + 3,
-            vertex_offset + i,
-        ]);
-    }
-    indices
+// Directory where the chunks of a world are saved
+fn chunk_directory(seed: u32) -> PathBuf {
+    PathBuf::from(format!("saves/{}/chunks", seed))
 }
 
-// Add camera struct
-struct Camera {
-    position: Vec3,
-    front: Vec3,
-    up: Vec3,
-    yaw: f32,
-    pitch: f32,
+// Command line options
+#[derive(Default)]
+struct LaunchOptions {
+    /// `--host [port]`: start a server and join it.
+    host: Option<u16>,
+    /// `--connect <address>`: join someone else's server.
+    connect: Option<String>,
+    /// `--record <file>`: save the session's input to replay it later.
+    record: Option<PathBuf>,
+    /// `--replay <file>`: play a recorded session instead of taking input.
+    replay: Option<PathBuf>,
+    /// `--headless`: with `--replay`, run as fast as possible without drawing.
+    headless: bool,
+    /// `--benchmark [seconds]`: fly a fixed path and write a performance report.
+    benchmark: Option<f32>,
+    /// `--vsync` or `--no-vsync`: override the vsync setting.
+    vsync: Option<bool>,
+    /// `--fps-cap <fps|off>`: override the frame rate cap setting.
+    fps_cap: Option<u32>,
+    /// `--import <save>`: explore a Minecraft Java save instead of generated terrain.
+    import: Option<PathBuf>,
+    /// `--heightmap <image>`: generate terrain heights from a grayscale image.
+    heightmap: Option<PathBuf>,
+    /// `--scale <blocks>`: with `--heightmap`, the height of white above black.
+    heightmap_scale: Option<f32>,
+    /// `--preset <name>`: the terrain a new world is generated with.
+    preset: Option<Preset>,
+    /// `--layers <layers>`: with `--preset superflat`, its blocks bottom first,
+    /// such as `3*stone,2*dirt,grass`.
+    layers: Option<Vec<BlockType>>,
+    /// `--snow-altitude <y>`: the altitude snow starts around in a new world.
+    snow_altitude: Option<i32>,
+    /// `--trace <file>`: record how long each system takes every frame, and write
+    /// it on exit as a chrome://tracing JSON file.
+    trace: Option<PathBuf>,
+    /// `--wgpu`: draw through the wgpu backend instead of OpenGL, in builds with
+    /// the `wgpu` feature. It only draws the terrain: there is no sky,
+    /// entities, HUD or text.
+    wgpu: bool,
+    /// `--bake-ao`: bake ambient occlusion for the world within the render
+    /// distance into the mesh cache, for exports, and quit.
+    bake_ao: bool,
+    /// `--repair`: check the world's saved chunks, upgrade old ones and fix
+    /// or set aside damaged ones, and quit.
+    repair: bool,
 }
 
-impl Camera {
-    fn new() -> Self {
-        Self {
-            position: Vec3::new(0.0, 100.0, 0.0),  // Moved back and up to see the chunks
-            front: Vec3::new(0.0, -0.3, -1.0),      // Looking slightly down
-            up: Vec3::new(0.0, 1.0, 0.0),
-            yaw: -90.0,
-            pitch: -15.0,
+fn parse_args() -> LaunchOptions {
+    let mut options = LaunchOptions::default();
+    let mut args = std::env::args().skip(1).peekable();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--host" => {
+                let port = args.next_if(|port| port.parse::<u16>().is_ok());
+                options.host = Some(port.map_or(DEFAULT_PORT, |port| port.parse().unwrap()));
+            },
+            "--connect" => options.connect = Some(args.next().unwrap_or_else(|| logging::fatal("io", "--connect needs an address"))),
+            "--record" => options.record = Some(args.next().unwrap_or_else(|| logging::fatal("io", "--record needs a file")).into()),
+            "--replay" => options.replay = Some(args.next().unwrap_or_else(|| logging::fatal("io", "--replay needs a file")).into()),
+            "--heightmap" => options.heightmap = Some(args.next().unwrap_or_else(|| logging::fatal("io", "--heightmap needs an image")).into()),
+            "--scale" => {
+                let scale = args.next().and_then(|scale| scale.parse::<f32>().ok()).filter(|&scale| scale > 0.0);
+                options.heightmap_scale = Some(scale.unwrap_or_else(|| logging::fatal("io", "--scale needs a height in blocks")));
+            },
+            "--preset" => {
+                let preset = args.next().as_deref().and_then(Preset::from_name);
+                let names = Preset::ALL.map(Preset::name).join(", ");
+                options.preset = Some(preset.unwrap_or_else(|| logging::fatal("io", format!("--preset needs one of {}", names))));
+            },
+            "--layers" => {
+                let layers = args.next().ok_or_else(|| "--layers needs a list of blocks".to_string()).and_then(|text| world_generator::parse_layers(&text));
+                options.layers = Some(layers.unwrap_or_else(|e| logging::fatal("io", format!("Invalid superflat layers: {}", e))));
+            },
+            "--snow-altitude" => {
+                let altitude = args.next().and_then(|altitude| altitude.parse::<i32>().ok());
+                options.snow_altitude = Some(altitude.unwrap_or_else(|| logging::fatal("io", "--snow-altitude needs a height in blocks")));
+            },
+            "--trace" => options.trace = Some(args.next().unwrap_or_else(|| logging::fatal("io", "--trace needs a file")).into()),
+            "--import" => options.import = Some(args.next().unwrap_or_else(|| logging::fatal("io", "--import needs a save folder")).into()),
+            "--headless" => options.headless = true,
+            "--bake-ao" => options.bake_ao = true,
+            "--repair" => options.repair = true,
+            "--wgpu" => options.wgpu = true,
+            // Read before anything is logged
+            "--verbose" => {},
+            "--benchmark" => {
+                let seconds = args.next_if(|seconds| seconds.parse::<f32>().is_ok());
+                options.benchmark = Some(seconds.map_or(DEFAULT_BENCHMARK_DURATION, |seconds| seconds.parse().unwrap()));
+            },
+            "--vsync" => options.vsync = Some(true),
+            "--no-vsync" => options.vsync = Some(false),
+            "--fps-cap" => {
+                let cap = args.next().as_deref().and_then(parse_limit);
+                options.fps_cap = Some(cap.unwrap_or_else(|| logging::fatal("io", "--fps-cap needs a frame rate or `off`")));
+            },
+            _ => log::warn!(target: "io", "Ignoring unknown argument: {}", arg),
         }
     }
+    options
+}
 
-    fn get_view_matrix(&self) -> Mat4 {
-        Mat4::look_at(self.position, self.position + self.front, self.up)
-    }
+// Starts a server for the world of `metadata` and joins it when hosting, or
+// joins another one. Without either the game is played alone.
+fn connect(options: &LaunchOptions, metadata: &WorldMetadata) -> Result<Option<Client>, EngineError> {
+    let address = match (options.host, &options.connect) {
+        (Some(port), _) => {
+            let address = network::start_server(port, WORLD_SEED, metadata, &ChunkStore::new(chunk_directory(WORLD_SEED)))
+                .map_err(EngineError::io(format!("Failed to start server on port {}", port)))?;
+            format!("127.0.0.1:{}", address.port())
+        },
+        (None, Some(address)) if address.contains(':') => address.clone(),
+        (None, Some(address)) => format!("{}:{}", address, DEFAULT_PORT),
+        (None, None) => return Ok(None),
+    };
+    let client = Client::connect(&address).map_err(EngineError::io(format!("Failed to connect to {}", address)))?;
+    Ok(Some(client))
+}
 
-    fn update_camera_vectors(&mut self) {
-        let front = Vec3::new(
-            self.yaw.to_radians().cos() * self.pitch.to_radians().cos(),
-            self.pitch.to_radians().sin(),
-            self.yaw.to_radians().sin() * self.pitch.to_radians().cos()
-        );
-        self.front = front.normalize();
-    }
+// Opens the wgpu renderer the terrain is drawn with, on the window the
+// platform opened for it.
+#[cfg(feature = "wgpu")]
+fn open_wgpu_renderer(platform: &ActivePlatform) -> Result<Box<dyn Renderer>, EngineError> {
+    let block_texture_paths = BlockTexture::ALL.map(|texture| texture.path());
+    let renderer = WgpuRenderer::new(platform.window(), (WINDOW_WIDTH, WINDOW_HEIGHT), &block_texture_paths, BIOME_COLORMAP_PATH)
+        .map_err(EngineError::Wgpu)?;
+    Ok(Box::new(renderer))
+}
+
+#[cfg(not(feature = "wgpu"))]
+fn open_wgpu_renderer(_platform: &ActivePlatform) -> Result<Box<dyn Renderer>, EngineError> {
+    Err(EngineError::Window("Built without the wgpu feature".to_string()))
 }
 
-fn load_shader(path: &str) -> String {
-    fs::read_to_string(path)
-        .unwrap_or_else(|_| panic!("Failed to read shader file: {}", path))
+// Reads and links a vertex and fragment shader pair from the shader folder.
+fn load_program(name: &str) -> Result<gl_utils::ShaderProgram, EngineError> {
+    let read = |extension: &str| {
+        let path = format!("src/assets/shaders/{}.{}", name, extension);
+        fs::read_to_string(&path).map_err(EngineError::io(format!("Failed to read shader file {}", path)))
+    };
+    gl_utils::ShaderProgram::from_vert_frag(&read("vert")?, &read("frag")?)
+        .map_err(|message| EngineError::Shader { name: name.to_string(), message })
 }
 
+#[cfg(feature = "web")]
 fn main() {
-    let sdl_context = sdl2::init().unwrap();
-    let video_subsystem = sdl_context.video().unwrap();
-
-    let gl_attr = video_subsystem.gl_attr();
-    gl_attr.set_context_profile(GLProfile::Core);
-    gl_attr.set_context_version(3, 3);
-    gl_attr.set_context_flags().debug().set();
-
-    let window = video_subsystem
-        .window("OpenGL Window", 800, 600)
-        .opengl()
-        .position_centered()
-        .build()
-        .unwrap();
-    
-    let _gl_context = window.gl_create_context().unwrap();
-    gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const _);
+    web::start();
+}
 
-    unsafe {
-        gl::Enable(gl::DEBUG_OUTPUT);
-        gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+#[cfg(not(feature = "web"))]
+fn main() {
+    logging::init(std::env::args().any(|arg| arg == "--verbose"));
+    let options = parse_args();
+    if let Err(e) = run(&options) {
+        log::error!(target: e.category(), "{}", e);
+        // Nobody is watching a headless run to close the box
+        if !options.headless {
+            platform::show_error("The game couldn't start", &e.to_string());
+        }
+        std::process::exit(1);
     }
+}
+
+fn run(options: &LaunchOptions) -> Result<(), EngineError> {
+    if options.repair {
+        let directory = chunk_directory(WORLD_SEED);
+        let report = ChunkStore::new(&directory).repair()
+            .map_err(EngineError::io(format!("Failed to repair the chunks in {}", directory.display())))?;
+        log::info!(
+            target: "io",
+            "Checked the saved chunks: {} intact, {} upgraded, {} salvaged, {} moved to corrupt",
+            report.intact,
+            report.upgraded,
+            report.salvaged,
+            report.quarantined,
+        );
+        return Ok(());
+    }
+    if options.wgpu && !cfg!(feature = "wgpu") {
+        log::warn!(target: "render", "Built without the wgpu feature, playing with OpenGL");
+    }
+    // Joining someone else's world leaves it to them
+    let owns_world = options.host.is_some() || options.connect.is_none();
+    // Recorded sessions start from a fresh world and leave the saves alone, so
+    // replaying them goes through the same states
+    let fresh_start = options.record.is_some() || options.replay.is_some() || options.benchmark.is_some();
+    // Only replays can skip drawing; everything else needs to be seen
+    let headless = options.headless && options.replay.is_some();
+    let mut benchmark = options.benchmark.map(Benchmark::new);
+    // Imported saves and heightmaps are only read, and edits to them aren't
+    // kept either, so they never end up in the saves of the generated world
+    let saves_world = owns_world && !fresh_start && options.import.is_none() && options.heightmap.is_none();
+    // New worlds start in survival, with the preset asked for. Saved ones keep
+    // the preset they were created with
+    let metadata_path = PathBuf::from(format!("saves/{}/world.dat", WORLD_SEED));
+    let saved_metadata = if saves_world { WorldMetadata::load(&metadata_path).ok() } else { None };
+    let metadata = match saved_metadata {
+        Some(metadata) => {
+            if options.preset.is_some_and(|preset| preset != metadata.preset) {
+                log::warn!(target: "worldgen", "Keeping the {} preset the saved world was created with", metadata.preset.name());
+            }
+            metadata
+        },
+        None => WorldMetadata {
+            preset: options.preset.unwrap_or_default(),
+            layers: options.layers.clone().unwrap_or_default(),
+            snow_altitude: options.snow_altitude.unwrap_or(DEFAULT_SNOW_ALTITUDE),
+            ..WorldMetadata::default()
+        },
+    };
+    let mut client = connect(options, &metadata)?;
+    // Someone else's world is played with the terrain of their server
+    let metadata = match &client {
+        Some(client) if !owns_world => {
+            if options.preset.is_some_and(|preset| preset != client.preset) {
+                log::warn!(target: "worldgen", "Playing the {} preset of the server's world", client.preset.name());
+            }
+            WorldMetadata {
+                preset: client.preset,
+                layers: client.layers.clone(),
+                snow_altitude: client.snow_altitude,
+                ..WorldMetadata::default()
+            }
+        },
+        _ => metadata,
+    };
+    if options.layers.is_some() && metadata.preset != Preset::Superflat {
+        log::warn!(target: "worldgen", "Ignoring the layers of a world that isn't superflat");
+    }
+    if options.snow_altitude.is_some_and(|altitude| altitude != metadata.snow_altitude) {
+        log::warn!(target: "worldgen", "Keeping the snow altitude of {} the world was created with", metadata.snow_altitude);
+    }
+    let mut recording = options.record.as_ref().map(|_| Replay::new());
+    // Replays need the settings they were recorded with, which are the defaults
+    let mut settings = if fresh_start { Settings::default() } else { Settings::load(Path::new(SETTINGS_PATH)) };
+    if let Some(vsync) = options.vsync {
+        settings.vsync = vsync;
+    }
+    if let Some(fps_cap) = options.fps_cap {
+        settings.fps_cap = fps_cap;
+    }
+    language::select(&settings.language);
+    let mut settings_screen = SettingsScreen::new();
+    let mut playback = options.replay.as_ref().map(|path| {
+        Replay::load(path)
+            .map_err(|e| EngineError::Io {
+                context: format!("Failed to load replay {}", path.display()),
+                source: std::io::Error::other(e),
+            })
+            .map(|replay| replay.frames.into_iter())
+    }).transpose()?;
+
+    // Nothing is drawn when only simulating or baking
+    let drawn = !headless && !options.bake_ao;
+    // The wgpu backend only draws the terrain. Everything else is drawn with
+    // OpenGL, and left out without its context
+    let use_gl = !(cfg!(feature = "wgpu") && options.wgpu && drawn);
+    let graphics = if use_gl { Graphics::OpenGl } else { Graphics::Surface };
+    if !use_gl {
+        log::warn!(target: "render", "wgpu only draws the terrain: the sky, entities, HUD and text are left out");
+    }
+    let mut platform = ActivePlatform::open("OpenGL Window", WINDOW_WIDTH, WINDOW_HEIGHT, !drawn, graphics)?;
+    if use_gl {
+        let capabilities = GlCapabilities::detect();
+        log::info!(target: "render", "{}", capabilities.summary());
+        if !capabilities.is_supported() {
+            let (major, minor) = REQUIRED_GL_VERSION;
+            return Err(EngineError::Gl(format!(
+                "The game needs OpenGL {}.{}, but {} only supports {}.{}. Updating the graphics driver may help.",
+                major, minor, capabilities.renderer, capabilities.version.0, capabilities.version.1,
+            )));
+        }
+        if capabilities.debug_output {
+            unsafe {
+                gl::Enable(gl::DEBUG_OUTPUT);
+                gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+            }
+        }
+    }
+    apply_window_settings(&settings, &mut platform);
 
     // Load and create shader program
-    let vertex_shader = load_shader("src/assets/shaders/block.vert");
-    let fragment_shader = load_shader("src/assets/shaders/block.frag");
-    let shader_program = gl_utils::ShaderProgram::from_vert_frag(&vertex_shader, &fragment_shader)
-        .expect("Failed to create shader program");
-
-    // Create and set up VAO, VBO, and EBO
-    let vao = gl_utils::VertexArray::new().expect("Failed to create VAO");
-    let vbo = gl_utils::Buffer::new().expect("Failed to create VBO");
-    let ebo = gl_utils::Buffer::new().expect("Failed to create EBO");
-    
-    vao.bind();
-    
-    // Generate chunks data
-    let mut world = World::new();
+    let load = |name| use_gl.then(|| load_program(name)).transpose();
+    let shader_program = load("block")?;
+    let entity_program = load("entity")?;
+    let particle_program = load("particle")?;
+    let debug_line_program = load("debug_line")?;
+    let cloud_program = load("clouds")?;
+    let sky_program = load("sky")?;
+    let hud_program = load("hud")?;
+    let sign_program = load("sign")?;
+    let border_program = load("world_border")?;
+    let minimap_program = load("minimap")?;
+    let map_program = load("world_map")?;
+    let frame_uniforms = use_gl.then(|| {
+        FrameUniformBuffer::new().ok_or_else(|| EngineError::Gl("Failed to create the frame uniform buffer".to_string()))
+    }).transpose()?;
+    if let Some(frame_uniforms) = &frame_uniforms {
+        let programs = [&shader_program, &entity_program, &particle_program, &debug_line_program, &cloud_program, &sky_program, &hud_program, &sign_program, &border_program];
+        for program in programs.into_iter().flatten() {
+            frame_uniforms.attach(program);
+        }
+    }
+
+    // Initialize camera
+    let mut camera = Camera::new();
+    let mut player = Player::new(settings.auto_jump);
+    let mut camera_effects = CameraEffects::new(settings.view_bobbing, settings.landing_dip, settings.screen_shake);
+    if let Some(benchmark) = &benchmark {
+        (camera.position, camera.yaw, camera.pitch) = benchmark.camera();
+        camera.previous_position = camera.position;
+        camera.update_camera_vectors();
+    }
 
-    // Create a larger world (8x8x8 chunks)
-    for chunk_x in -8..8 {
-        for chunk_y in 0..8 {
-            for chunk_z in -8..8 {
-                let chunk = Chunk::new((chunk_x, chunk_y, chunk_z));
-                world.add_chunk(chunk);
+    // Generate and mesh everything within the render distance up front
+    // When playing online the server decides which world is played
+    let mut world = World::new(client.as_ref().map_or(WORLD_SEED, |client| client.seed));
+    world.fancy_leaves = settings.fancy_leaves;
+    match metadata.preset {
+        Preset::Default if metadata.snow_altitude == DEFAULT_SNOW_ALTITUDE => {},
+        preset => {
+            log::info!(target: "worldgen", "Generating the {} preset", preset.name());
+            world.use_generator(preset.generator(&metadata.layers, metadata.snow_altitude));
+        },
+    }
+    match &options.import {
+        Some(path) if client.is_some() => log::warn!(target: "io", "Can't import {} when playing online", path.display()),
+        Some(path) => {
+            let save = AnvilWorld::open(path).map_err(|e| EngineError::Io {
+                context: format!("Failed to open Minecraft save {}", path.display()),
+                source: std::io::Error::other(e),
+            })?;
+            if let Some((x, y, z)) = save.spawn() {
+                camera.position = Vec3::new(x as f32, y as f32 + EYE_HEIGHT, z as f32);
+                camera.previous_position = camera.position;
+            }
+            world.use_generator(Box::new(save));
+        },
+        None => {},
+    }
+    match &options.heightmap {
+        Some(path) if client.is_some() => log::warn!(target: "io", "Can't use heightmap {} when playing online", path.display()),
+        Some(path) => {
+            let heightmap = Heightmap::load(path, options.heightmap_scale.unwrap_or(DEFAULT_HEIGHTMAP_SCALE))
+                .map_err(|e| EngineError::Io {
+                    context: format!("Failed to load heightmap {}", path.display()),
+                    source: std::io::Error::other(e),
+                })?;
+            world.use_generator(Box::new(HeightmapGenerator::new(heightmap, metadata.snow_altitude)));
+        },
+        None => {},
+    }
+    let chunk_store = ChunkStore::new(chunk_directory(world.seed));
+    let block_entity_path = PathBuf::from(format!("saves/{}/block_entities.dat", world.seed));
+    // Saved chunks are read as streaming reaches them, on the IO thread
+    let mut world_io = WorldIo::new(chunk_store.clone());
+    if saves_world {
+        world.set_unread(chunk_store.saved_chunks());
+        if block_entity_path.exists() {
+            match world.block_entities.load(&block_entity_path) {
+                Ok(count) => log::info!(target: "io", "Loaded {} block entities", count),
+                Err(e) => log::error!(target: "io", "Failed to load block entities: {}", e),
             }
         }
     }
-    
-    // Update all chunks after they're all created
-    let mut all_vertices: Vec<Vertex> = Vec::new();
-    let mut all_indices: Vec<TriIndexes> = Vec::new();
-
-    // First pass: update all chunks
-    let positions = world.chunks.keys().cloned().collect::<Vec<_>>();
-    for pos in positions {
-        // Get the blocks data
-        let blocks = world.chunks[&pos].blocks.clone();
-        
-        // Remove the chunk temporarily
-        let mut chunk = world.chunks.remove(&pos).unwrap();
-        
-        // Update the chunk
-        chunk.blocks = blocks;
-        chunk.update(&world);
-        
-        // Put the chunk back
-        world.chunks.insert(pos, chunk);
-    }
-
-    // Second pass: collect vertices and indices
-    for pos in world.chunks.keys().cloned().collect::<Vec<_>>() {
-        if let Some(chunk) = world.chunks.get(&pos) {
-            let vertex_offset = all_vertices.len() as u32;
-            all_vertices.extend_from_slice(&chunk.vertices);
-            
-            for tri in &chunk.indices {
-                all_indices.push([
-                    tri[0] + vertex_offset,
-                    tri[1] + vertex_offset,
-                    tri[2] + vertex_offset,
-                ]);
-            }
-        }
-    }
-
-    // Set up vertex buffer with all chunks data
-    vbo.bind(gl_utils::BufferType::Array);
-    gl_utils::buffer_data(
-        gl_utils::BufferType::Array,
-        bytemuck::cast_slice(&all_vertices),
-        gl::STATIC_DRAW,
-    );
-
-    // Set up element buffer with all chunks indices
-    ebo.bind(gl_utils::BufferType::ElementArray);
-    gl_utils::buffer_data(
-        gl_utils::BufferType::ElementArray,
-        bytemuck::cast_slice(&all_indices),
-        gl::STATIC_DRAW,
-    );
-
-    unsafe {
-        // Position attribute
-        gl::VertexAttribPointer(
-            0,
-            3,
-            gl::FLOAT,
-            gl::FALSE,
-            8 * std::mem::size_of::<f32>() as gl::types::GLsizei,
-            std::ptr::null(),
-        );
-        gl::EnableVertexAttribArray(0);
-
-        // Texture coordinate attribute
-        gl::VertexAttribPointer(
-            1,
-            2,
-            gl::FLOAT,
-            gl::FALSE,
-            8 * std::mem::size_of::<f32>() as gl::types::GLsizei,
-            (3 * std::mem::size_of::<f32>()) as *const _,
-        );
-        gl::EnableVertexAttribArray(1);
-
-        // Position attribute
-        gl::VertexAttribPointer(
-            2,
-            1,
-            gl::FLOAT,
-            gl::FALSE,
-            8 * std::mem::size_of::<f32>() as gl::types::GLsizei,
-            (5 * std::mem::size_of::<f32>()) as *const _,
-        );
-        gl::EnableVertexAttribArray(2);
-
-        // TextureIndex attribute
-        gl::VertexAttribPointer(
-            3,
-            1,
-            gl::FLOAT,
-            gl::FALSE,
-            8 * std::mem::size_of::<f32>() as gl::types::GLsizei,
-            (6 * std::mem::size_of::<f32>()) as *const _,
-        );
-        gl::EnableVertexAttribArray(3);
-
-        // TextSize attribute
-        gl::VertexAttribPointer(
-            4,
-            1,
-            gl::FLOAT,
-            gl::FALSE,
-            8 * std::mem::size_of::<f32>() as gl::types::GLsizei,
-            (7 * std::mem::size_of::<f32>()) as *const _,
-        );
-        gl::EnableVertexAttribArray(4);
+    // A saved world starts where its player last set their spawn
+    let spawn_path = PathBuf::from(format!("saves/{}/spawn.dat", world.seed));
+    let saved_spawn = if saves_world { SpawnPoint::load(&spawn_path).ok() } else { None };
+    let mut game_mode = metadata.game_mode;
+    world.border = metadata.border;
+    log::info!(target: "game", "Game mode: {}", game_mode.name());
+    let mut digging = Digging::new();
+    let eye_offset = Vec3::new(0.0, EYE_HEIGHT, 0.0);
+    if let Some(spawn) = &saved_spawn {
+        camera.position = spawn.position() + eye_offset;
+        camera.previous_position = camera.position;
+    }
+    let mut view_mode = ViewMode::Perspective;
+    let mut streamer = ChunkStreamer::new(settings.render_distance);
+    // Measured every frame while there's a memory budget or the debug
+    // overlay shows it
+    let mut memory = MemoryUsage::default();
+    // Benchmarks measure meshing, so they skip the cache
+    let mesh_cache = if benchmark.is_some() { MeshCache::disabled() } else { MeshCache::new("cache/meshes") };
+    // Text falls back to bitmap glyphs without the font
+    let font = Font::load(Path::new(FONT_PATH))
+        .inspect_err(|e| log::warn!(target: "io", "Failed to load font {}: {}", FONT_PATH, e))
+        .ok();
+    let mut hud = hud_program
+        .map(|program| Hud::new(program, font).ok_or_else(|| EngineError::Gl("Failed to create HUD buffers".to_string())))
+        .transpose()?;
+    if let Some(hud) = &mut hud {
+        hud.palette = settings.palette;
+    }
+    // Systems follow what the others change through events, read once a frame
+    let mut event_bus = EventBus::new();
+    // Everything within the render distance loads before play starts, a few
+    // columns at a time with the loading screen drawn in between so the window
+    // keeps responding. What isn't drawn loads in one go
+    let mut loading_screen = LoadingScreen::new();
+    let mut pending_teleport: Option<PendingTeleport> = None;
+    let columns_per_step = if drawn { COLUMNS_PER_LOADING_STEP } else { usize::MAX };
+    let mut startup = StreamUpdate::default();
+    loop {
+        world_io.update(&mut world);
+        let update = streamer.update(&mut world, &camera.viewer(view_mode, settings.fov), columns_per_step, &mesh_cache, &mut event_bus);
+        world_io.read_chunks(update.to_read.clone(), &mut world);
+        if !loading_screen.is_open() {
+            loading_screen.open(update.loaded.len() + streamer.queued() * WORLD_HEIGHT_CHUNKS as usize);
+        }
+        startup.add(update);
+        loading_screen.handle_events(&event_bus);
+        event_bus.clear();
+        if streamer.queued() == 0 {
+            break;
+        }
+        if drawn {
+            if platform.poll_events().contains(&Event::Quit) {
+                return Ok(());
+            }
+            if let Some(hud) = &mut hud {
+                let (screen_width, screen_height) = ui_screen_size(settings.ui_scale);
+                unsafe {
+                    gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+                }
+                loading_screen.draw(hud, screen_width, screen_height);
+                hud.draw(screen_width, screen_height);
+                platform.swap_buffers();
+            }
+        } else {
+            // Nothing to draw while saved chunks are read
+            world_io.wait_for_read(&mut world);
+        }
+    }
+    if let Some(benchmark) = &mut benchmark {
+        benchmark.record_startup(&startup);
+    }
+    log::info!(target: "meshing", "Meshed {} chunks ({} from cache)", startup.remeshed.len(), startup.cached);
+    if options.bake_ao {
+        let (baked, cached) = ao_bake::bake_world(&world, &mesh_cache);
+        log::info!(target: "meshing", "Baked ambient occlusion for {} chunks ({} were already baked)", baked, cached);
+        return Ok(());
     }
 
-    // Load textures
-    let grass_top_texture = gl_utils::load_texture("src/assets/textures/block/grass_block_top.png");
-    let grass_side_texture = gl_utils::load_texture("src/assets/textures/block/grass_block_side.png");
-    let grass_side_overlay_texture = gl_utils::load_texture("src/assets/textures/block/grass_block_side_overlay.png");
-    let dirt_texture = gl_utils::load_texture("src/assets/textures/block/dirt.png");
-    let colormap_texture = gl_utils::load_texture("src/assets/textures/colormap/grass.png");
-    let stone_texture = gl_utils::load_texture("src/assets/textures/block/stone.png");
-    let water_texture = gl_utils::load_texture("src/assets/textures/block/water_still.png");
-
-    shader_program.use_program();
-
-    // Set texture uniforms
-    unsafe {
-        gl::Uniform1i(gl::GetUniformLocation(shader_program.0, b"grassTopTexture\0".as_ptr() as *const i8), 0);
-        gl::Uniform1i(gl::GetUniformLocation(shader_program.0, b"grassSideTexture\0".as_ptr() as *const i8), 1);
-        gl::Uniform1i(gl::GetUniformLocation(shader_program.0, b"dirtTexture\0".as_ptr() as *const i8), 2);
-        gl::Uniform1i(gl::GetUniformLocation(shader_program.0, b"colormapTexture\0".as_ptr() as *const i8), 3);
-        gl::Uniform1i(gl::GetUniformLocation(shader_program.0, b"grassSideOverlayTexture\0".as_ptr() as *const i8), 4);
-        gl::Uniform1i(gl::GetUniformLocation(shader_program.0, b"stoneTexture\0".as_ptr() as *const i8), 5);
-        gl::Uniform1i(gl::GetUniformLocation(shader_program.0, b"waterTexture\0".as_ptr() as *const i8), 6);
+    // Upload the chunk meshes into the streaming buffers
+    let mut renderer: Box<dyn Renderer> = match (shader_program, frame_uniforms) {
+        (Some(program), Some(frame_uniforms)) => Box::new(
+            GlRenderer::new(&world, program, frame_uniforms).ok_or_else(|| EngineError::Gl("Failed to create chunk buffers".to_string()))?,
+        ),
+        _ => open_wgpu_renderer(&platform)?,
+    };
+    renderer.upload_all(&world);
+    event_bus.publish(ChunkProgress { stage: ChunkStage::Uploading, chunks: world.chunk_count() });
+
+    // New worlds find their spawn on the highest safe ground near where the
+    // camera starts, and the player comes back there after dying. Benchmarks
+    // keep their scripted camera.
+    let mut spawn = saved_spawn.unwrap_or_else(|| {
+        let start = camera.position - eye_offset;
+        let ground = world.find_spawn(start.x.round() as i32, start.z.round() as i32, SPAWN_SEARCH_RADIUS);
+        SpawnPoint::new(ground.unwrap_or(start))
+    });
+    if benchmark.is_none() {
+        camera.position = spawn.position() + eye_offset;
+        camera.previous_position = camera.position;
+    }
+    log::info!(target: "game", "Spawning at {:.1} {:.1} {:.1}", spawn.position().x, spawn.position().y, spawn.position().z);
+    let mut health = Health::new();
+
+    // Entities are drawn in their own pass; mobs come and go around the player as the world ticks
+    let mut entity_renderer = entity_program.map_or_else(EntityRenderer::disabled, EntityRenderer::new);
+    let mut entities = Entities::new();
+    let mut mobs = Mobs::new(&mut entity_renderer, world.seed)
+        .ok_or_else(|| EngineError::Gl("Failed to create mob mesh".to_string()))?;
+    let mut spawn_cycle = SpawnCycle::new(world.seed);
+    let mut tnts = Tnts::new(&mut entity_renderer, world.seed, !settings.reduce_flashing)
+        .ok_or_else(|| EngineError::Gl("Failed to create TNT meshes".to_string()))?;
+    let mut projectiles = Projectiles::new(&mut entity_renderer)
+        .ok_or_else(|| EngineError::Gl("Failed to create the arrow mesh".to_string()))?;
+    let mut dropped_items = DroppedItems::new();
+    let mut held_block = HeldBlock::new();
+    let mut sounds = if headless { SoundManager::disabled() } else { platform.open_sound() };
+    let mut music = MusicPlayer::new(Path::new(MUSIC_DIRECTORY));
+    let inventory_path = PathBuf::from(format!("saves/{}/inventory.dat", world.seed));
+    let mut inventory = if fresh_start {
+        Inventory::new()
+    } else {
+        Inventory::load(&inventory_path).unwrap_or_else(|_| Inventory::new())
+    };
+    let mut inventory_open = false;
+    let mut recipes = RecipeRegistry::load();
+    // Signs rasterize their glyphs bigger than the HUD does, so they get an atlas of their own
+    let mut sign_renderer = sign_program
+        .map(|program| SignRenderer::new(program, Font::load(Path::new(FONT_PATH)).ok()).ok_or_else(|| EngineError::Gl("Failed to create sign buffers".to_string())))
+        .transpose()?;
+    let mut sign_editor = SignEditor::new();
+    let border_renderer = border_program
+        .map(|program| BorderRenderer::new(program).ok_or_else(|| EngineError::Gl("Failed to create world border buffers".to_string())))
+        .transpose()?;
+    // Saved worlds remember the map explored in earlier sessions
+    let map_directory = PathBuf::from(format!("saves/{}/map", world.seed));
+    let mut map_colors = if saves_world { MapColors::load(&map_directory) } else { MapColors::new() };
+    let mut autosaver = (!fresh_start).then(Autosaver::new);
+    let mut minimap = minimap_program
+        .map(|program| Minimap::new(program).ok_or_else(|| EngineError::Gl("Failed to create minimap buffers".to_string())))
+        .transpose()?;
+    let mut map_screen = match map_program {
+        Some(program) => MapScreen::new(program).ok_or_else(|| EngineError::Gl("Failed to create map buffers".to_string()))?,
+        None => MapScreen::disabled(),
+    };
+    let mut sky = sky_program
+        .map(|program| Sky::new(program, world.seed).ok_or_else(|| EngineError::Gl("Failed to create sky buffers".to_string())))
+        .transpose()?;
+    let mut clouds = cloud_program
+        .map(|program| Clouds::new(program, world.seed).ok_or_else(|| EngineError::Gl("Failed to create cloud buffers".to_string())))
+        .transpose()?;
+    let mut particles = particle_program
+        .map(|program| ParticleSystem::new(program).ok_or_else(|| EngineError::Gl("Failed to create particle buffers".to_string())))
+        .transpose()?;
+    let mut commands = CommandRegistry::with_builtins();
+    let mut mesh_block_changes = Subscriber::<BlockChanged>::new();
+    let mut mesh_chunk_loads = Subscriber::<ChunkLoaded>::new();
+    let mut feedback = Feedback::new();
+    let (mut plugins, plugin_effects) = Plugins::load(Path::new(PLUGIN_DIRECTORY), &mut world, camera.position, &mut commands);
+    publish_block_changes(&mut event_bus, &world, &plugin_effects.edited_blocks, BlockChangeCause::Plugin);
+    for (kind, position) in plugin_effects.spawned_mobs {
+        mobs.spawn(&mut entities, kind, position);
     }
+    let mut console = Console::new();
+    let mut screenshot_requested = false;
+    let mut timelapse = None;
+    if let Some(client) = &client {
+        console.print(&language::text("console.joined", &[&client.player_id]), false);
+    }
+    // Time of day in ticks, starting in the morning
+    let mut time_of_day: u32 = 1000;
+    // The world ticks and physics run at their own fixed rates, whatever the frame rate
+    let mut world_timestep = FixedTimestep::world_ticks();
+    let mut physics_timestep = FixedTimestep::physics();
+    // Block updates only run where the world is owned, and are shared from there
+    let mut block_ticks = TickScheduler::with_builtins(world.seed);
+    // Other players, by the id the server gave them
+    let mut remote_players = HashMap::new();
+    let remote_player_mesh = entity_renderer.add_cube([0.2, 0.4, 0.9])
+        .ok_or_else(|| EngineError::Gl("Failed to create player mesh".to_string()))?;
+    let mut position_send_time = 0.0;
+
+    // Load textures
+    let block_texture_paths = BlockTexture::ALL.map(|texture| texture.path());
+    let mut block_textures = use_gl.then(|| gl_utils::load_texture_array(&block_texture_paths)).transpose()?;
+    let colormap_texture = use_gl.then(|| gl_utils::load_texture(BIOME_COLORMAP_PATH)).transpose()?;
+    // Recipes and block textures reload when their files change, so content
+    // can be worked on without restarting. Meshes only refer to texture
+    // layers, so new textures show without remeshing anything
+    let recipe_directories = || RecipeRegistry::directories().into_iter().chain([PathBuf::from(PACK_DIRECTORY)]).collect();
+    let mut recipe_watcher = FolderWatcher::new(recipe_directories());
+    let mut texture_directories = block_texture_paths.iter()
+        .filter_map(|path| Path::new(path).parent().map(Path::to_path_buf))
+        .collect::<Vec<_>>();
+    texture_directories.dedup();
+    let mut texture_watcher = FolderWatcher::new(texture_directories);
 
     // Enable depth testing and blending for water transparency
-    unsafe {
-        gl::Enable(gl::DEPTH_TEST);
-        gl::Enable(gl::BLEND);
-        gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
-        gl::Enable(gl::CULL_FACE);  // Enable face culling
-        gl::CullFace(gl::BACK);     // Cull back faces
-        gl::FrontFace(gl::CCW);     // Front faces are counter-clockwise
+    if use_gl {
+        gl_utils::GlState::set_capability(gl::DEPTH_TEST, true);
+        gl_utils::GlState::set_capability(gl::BLEND, true);
+        gl_utils::GlState::blend_func(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        gl_utils::GlState::set_capability(gl::CULL_FACE, true);  // Enable face culling
+        unsafe {
+            gl::CullFace(gl::BACK);     // Cull back faces
+            gl::FrontFace(gl::CCW);     // Front faces are counter-clockwise
+        }
     }
 
-    let mut event_pump = sdl_context.event_pump().unwrap();
-
-    // Initialize camera
-    let mut camera = Camera::new();
-    let projection = Mat4::perspective(45.0_f32.to_radians(), 800.0 / 600.0, 0.1, 1000.0);
+    // Text input is only needed while the console is open
+    platform.set_text_input(false);
 
     // Mouse handling setup
-    let mouse = sdl_context.mouse();
-    mouse.set_relative_mouse_mode(true);
-    let mouse_sensitivity = 0.10;
+    platform.set_relative_mouse_mode(true);
     
-    let timer = sdl_context.timer().unwrap();
-    let mut last_frame_time = timer.ticks() as f32;
+    // Milliseconds since the loop started
+    let launch = Instant::now();
+    let ticks = || launch.elapsed().as_millis() as u32;
+    let mut last_frame_time = ticks() as f32;
     let mut frame_count = 0;
-    let mut last_fps_update = timer.ticks();
-    let target_frame_time = 1000.0 / 60.0; // Target 60 FPS (in milliseconds)
-    // Movement speed (units per second instead of per frame)
-    let movement_speed = 10.5;
+    let mut last_fps_update = ticks();
+    let mut debug_overlay = DebugOverlay::new();
+    // Redundant GL calls the state cache skipped last frame
+    let mut skipped_gl_calls = 0;
+    // GPU time of the main render passes
+    let new_timer = || match use_gl {
+        true => gl_utils::GpuTimer::new().ok_or_else(|| EngineError::Gl("Failed to create timer queries".to_string())),
+        false => Ok(gl_utils::GpuTimer::disabled()),
+    };
+    let (mut sky_timer, mut opaque_timer, mut water_timer, mut hud_timer) = (new_timer()?, new_timer()?, new_timer()?, new_timer()?);
+    let mut debug_modes = DebugModes::default();
+    let mut point_lights = PointLights::default();
+    let mut selection = Selection::default();
+    let mut debug_lines = debug_line_program
+        .map(|program| DebugLines::new(program).ok_or_else(|| EngineError::Gl("Failed to create debug line buffers".to_string())))
+        .transpose()?;
+    if let Some(debug_lines) = &mut debug_lines {
+        debug_lines.palette = settings.palette;
+    }
+
+    if options.trace.is_some() {
+        Profiler::start_trace();
+    }
 
     'main_loop: loop {
-        let current_frame_time = timer.ticks() as f32;
-        let delta_time = (current_frame_time - last_frame_time) / 1000.0; // Convert to seconds
+        let current_frame_time = ticks() as f32;
+        let frame_start = Instant::now();
+        Profiler::begin_frame();
+        let input_scope = Profiler::scope(System::Input);
+
+        // Input and frame time come from the player, or from the replay being played
+        let mut frame = match &mut playback {
+            Some(frames) => match frames.next() {
+                Some(frame) => frame,
+                None => {
+                    let p = camera.position;
+                    log::info!(target: "io", "Replay finished at {:.3} {:.3} {:.3}", p.x, p.y, p.z);
+                    break 'main_loop;
+                }
+            },
+            None => {
+                let held = HELD_KEYS.iter().enumerate()
+                    .filter(|(_, &key)| platform.is_key_held(key))
+                    .fold(0, |held, (bit, _)| held | 1 << bit);
+                ReplayFrame {
+                    delta_time: (current_frame_time - last_frame_time) / 1000.0, // Convert to seconds
+                    held,
+                    left_button: platform.is_left_button_held(),
+                    events: Vec::new(),
+                }
+            }
+        };
         last_frame_time = current_frame_time;
+        let delta_time = frame.delta_time;
+
+        let mut events = Vec::new();
+        for event in platform.poll_events() {
+            match event {
+                // Window changes aren't input, so they aren't recorded
+                Event::FocusLost | Event::FocusGained | Event::SecondWindowClosed => events.push(event),
+                // Live input is ignored while replaying, except to quit
+                _ if playback.is_none() => {
+                    frame.events.push(event.clone());
+                    events.push(event);
+                }
+                Event::Quit => events.push(event),
+                _ => {}
+            }
+        }
+        if playback.is_some() {
+            events.extend(frame.events.iter().cloned());
+        }
+        if let Some(recording) = &mut recording {
+            recording.frames.push(frame.clone());
+        }
 
         // FPS Counter
         frame_count += 1;
         if current_frame_time - last_fps_update as f32 >= 1000.0 {
-            println!("FPS: {}", frame_count);
+            log::debug!(target: "render", "FPS: {}", frame_count);
+            debug_overlay.set_fps(frame_count);
             frame_count = 0;
             last_fps_update = current_frame_time as u32;
         }
 
-        // Handle keyboard state
-        let keyboard_state = event_pump.keyboard_state();
-        
-        // Camera movement with delta time
-        let camera_speed = movement_speed * delta_time;
-        let sprint = keyboard_state.is_scancode_pressed(sdl2::keyboard::Scancode::LShift);
-        if keyboard_state.is_scancode_pressed(sdl2::keyboard::Scancode::W) {
-            camera.position = camera.position + camera.front * camera_speed * if sprint { 2.0 } else { 1.0 };
-        }
-        if keyboard_state.is_scancode_pressed(sdl2::keyboard::Scancode::S) {
-            camera.position = camera.position - camera.front * camera_speed * if sprint { 2.0 } else { 1.0 };
-        }
-        if keyboard_state.is_scancode_pressed(sdl2::keyboard::Scancode::A) {
-            let right = camera.front.cross(&camera.up).normalize();
-            camera.position = camera.position - right * camera_speed * if sprint { 2.0 } else { 1.0 };
+        // Pick up recipes and block textures saved while playing
+        if recipe_watcher.poll() {
+            recipes = RecipeRegistry::load();
+            // Packs may have been added or removed
+            recipe_watcher = FolderWatcher::new(recipe_directories());
         }
-        if keyboard_state.is_scancode_pressed(sdl2::keyboard::Scancode::D) {
-            let right = camera.front.cross(&camera.up).normalize();
-            camera.position = camera.position + right * camera_speed * if sprint { 2.0 } else { 1.0 };
-        }
-        if keyboard_state.is_scancode_pressed(sdl2::keyboard::Scancode::Q) {
-            camera.position = camera.position - camera.up * camera_speed * if sprint { 2.0 } else { 1.0 };
+        if let (true, Some(block_textures)) = (texture_watcher.poll(), &mut block_textures) {
+            match gl_utils::load_texture_array(&block_texture_paths) {
+                Ok(textures) => {
+                    gl_utils::GlState::delete_texture(*block_textures);
+                    *block_textures = textures;
+                    log::info!(target: "render", "Reloaded block textures");
+                },
+                Err(e) => log::error!(target: "render", "Failed to reload block textures: {}", e),
+            }
         }
-        if keyboard_state.is_scancode_pressed(sdl2::keyboard::Scancode::E) {
-            camera.position = camera.position + camera.up * camera_speed * if sprint { 2.0 } else { 1.0 };
+
+        // Player movement, one physics step at a time. The camera sits at the
+        // player's eyes. Nobody moves while the chunks around are loading
+        let physics_steps = if loading_screen.is_open() { 0 } else { physics_timestep.advance(delta_time) };
+        let typing = console.is_open() || settings_screen.is_open() || sign_editor.is_open() || map_screen.is_open();
+        let held = |key| !typing && frame.is_held(key);
+        let axis = |positive, negative| f32::from(u8::from(held(positive))) - f32::from(u8::from(held(negative)));
+        let move_input = MoveInput {
+            forward: axis(Key::W, Key::S),
+            right: axis(Key::D, Key::A),
+            jump: held(Key::Space),
+            descend: held(Key::Q),
+            sprint: held(Key::LShift),
+        };
+        let eye_offset = Vec3::new(0.0, EYE_HEIGHT, 0.0);
+        // Where the frame started, to tell whether the player moved
+        let frame_start_position = camera.position;
+        player.can_fly = game_mode.can_fly();
+        for _ in 0..physics_steps {
+            camera.previous_position = camera.position;
+            // The benchmark flies the camera on its own
+            if benchmark.is_none() {
+                let mut feet = player.step(&world, camera.position - eye_offset, camera.front, &move_input, physics_timestep.step());
+                if let Some(border) = &world.border {
+                    feet = border.clamp(feet, PLAYER_WIDTH / 2.0);
+                }
+                camera.position = feet + eye_offset;
+                if let Some(speed) = player.take_landing() {
+                    camera_effects.land(speed);
+                }
+                if let Some(distance) = player.take_fall().filter(|_| game_mode.takes_damage()) {
+                    health.fall(distance);
+                }
+                let drowning = game_mode.takes_damage() && player.mode() != MoveMode::Noclip && world.is_fluid_at(camera.position);
+                health.update(drowning, physics_timestep.step());
+            }
         }
 
-        for event in event_pump.poll_iter() {
+        let mut settings_changed = false;
+        // Set off by commands and TNT, and shown once the frame's simulation is done
+        let mut explosions = Vec::new();
+        // Block broken this frame, by a click in creative or by digging in survival
+        let mut broken_block = None;
+        for event in events {
+            if let Event::KeyDown { key: Some(key), .. } = event {
+                if !console.is_open() && !settings_screen.is_open() && !sign_editor.is_open() && !map_screen.is_open() {
+                    event_bus.publish(KeyAction { key });
+                }
+            }
             match event {
-                Event::Quit { .. }
+                Event::KeyDown { key: Some(Key::Backquote), .. } => {
+                    console.set_open(!console.is_open());
+                    if console.is_open() {
+                        platform.set_text_input(true);
+                    } else {
+                        platform.set_text_input(false);
+                    }
+                    platform.set_relative_mouse_mode(!console.is_open() && !inventory_open && !settings_screen.is_open());
+                }
+                Event::TextInput(text) if console.is_open() => {
+                    console.type_text(&text);
+                }
+                // While the console is open it takes every key
+                Event::KeyDown { key: Some(key), .. } if console.is_open() => match key {
+                    Key::Return | Key::KpEnter => {
+                        let target_block = world.raycast(camera.position, camera.front, REACH_DISTANCE).map(|hit| hit.block);
+                        let mut context = CommandContext {
+                            world: &mut world,
+                            player_position: &mut camera.position,
+                            time: &mut time_of_day,
+                            inventory: &mut inventory,
+                            timelapse: &mut timelapse,
+                            selection: &mut selection,
+                            mesh_cache: &mesh_cache,
+                            spawn: &mut spawn,
+                            game_mode: &mut game_mode,
+                            target_block,
+                            edited_blocks: Vec::new(),
+                            explosions: Vec::new(),
+                            spawned_mobs: Vec::new(),
+                            teleport: None,
+                        };
+                        console.submit(&commands, &mut context);
+                        let edited_blocks = context.edited_blocks;
+                        explosions.extend(context.explosions);
+                        for (kind, position) in context.spawned_mobs {
+                            mobs.spawn(&mut entities, kind, position);
+                        }
+                        if let Some(destination) = context.teleport {
+                            pending_teleport = Some(PendingTeleport::start(destination, &mut streamer));
+                        }
+                        publish_block_changes(&mut event_bus, &world, &edited_blocks, BlockChangeCause::Command);
+                    }
+                    Key::Backspace => console.backspace(),
+                    Key::Up => console.browse_history(-1),
+                    Key::Down => console.browse_history(1),
+                    Key::Escape => {
+                        console.set_open(false);
+                        platform.set_text_input(false);
+                        platform.set_relative_mouse_mode(!inventory_open && !settings_screen.is_open());
+                    }
+                    _ => {}
+                },
+                // So does the settings screen
+                Event::KeyDown { key: Some(key), .. } if settings_screen.is_open() => match key {
+                    Key::Escape | Key::O => {
+                        settings_screen.set_open(false);
+                        platform.set_relative_mouse_mode(!inventory_open);
+                    }
+                    Key::Up => settings_screen.select(-1),
+                    Key::Down => settings_screen.select(1),
+                    Key::Left => settings_changed |= settings_screen.adjust(&mut settings, -1),
+                    Key::Right | Key::Return => settings_changed |= settings_screen.adjust(&mut settings, 1),
+                    _ => {}
+                },
+                Event::TextInput(text) if sign_editor.is_open() => {
+                    sign_editor.type_text(&mut world, &text);
+                }
+                // And the sign editor
+                Event::KeyDown { key: Some(key), .. } if sign_editor.is_open() => {
+                    match key {
+                        Key::Return | Key::KpEnter | Key::Down | Key::Tab => sign_editor.move_line(1),
+                        Key::Up => sign_editor.move_line(-1),
+                        Key::Backspace => sign_editor.backspace(&mut world),
+                        Key::Escape => sign_editor.close(),
+                        _ => {}
+                    }
+                    if !sign_editor.is_open() {
+                        platform.set_text_input(false);
+                    }
+                }
+                // And the map
+                Event::KeyDown { key: Some(key), .. } if map_screen.is_open() => match key {
+                    Key::Escape | Key::M => {
+                        map_screen.close();
+                        platform.set_relative_mouse_mode(!inventory_open);
+                    }
+                    Key::Up => map_screen.pan(0, -1),
+                    Key::Down => map_screen.pan(0, 1),
+                    Key::Left => map_screen.pan(-1, 0),
+                    Key::Right => map_screen.pan(1, 0),
+                    Key::Equals | Key::KpPlus => map_screen.zoom(1),
+                    Key::Minus | Key::KpMinus => map_screen.zoom(-1),
+                    _ => {}
+                },
+                Event::MouseMotion { xrel, yrel } if map_screen.is_open() && frame.left_button => {
+                    map_screen.drag(xrel as f32 / settings.ui_scale, yrel as f32 / settings.ui_scale);
+                }
+                Event::MouseWheel { y } if map_screen.is_open() => {
+                    map_screen.zoom(y);
+                }
+                Event::KeyDown { key: Some(Key::O), .. } => {
+                    settings_screen.set_open(true);
+                    platform.set_relative_mouse_mode(false);
+                }
+                Event::Quit
                 | Event::KeyDown {
-                    keycode: Some(Keycode::Escape),
+                    key: Some(Key::Escape),
                     ..
                 } => break 'main_loop,
-                Event::MouseMotion { xrel, yrel, .. } => {
-                    let xoffset = xrel as f32 * mouse_sensitivity;
-                    let yoffset = -yrel as f32 * mouse_sensitivity;  // Reversed since y-coordinates go from bottom to top
+                Event::MouseMotion { xrel, yrel }
+                    if !inventory_open && !console.is_open() && !settings_screen.is_open() && !sign_editor.is_open() && !map_screen.is_open() => {
+                    let xoffset = xrel as f32 * settings.mouse_sensitivity;
+                    let mut yoffset = -yrel as f32 * settings.mouse_sensitivity;  // Reversed since y-coordinates go from bottom to top
+                    if settings.invert_y {
+                        yoffset = -yoffset;
+                    }
 
                     camera.yaw += xoffset;
                     camera.pitch += yoffset;
@@ -774,64 +1183,763 @@ fn main() {
 
                     camera.update_camera_vectors();
                 }
-                Event::Window { win_event: sdl2::event::WindowEvent::FocusLost, .. } => {
+                Event::FocusLost => {
                     // Release mouse when window loses focus
-                    mouse.set_relative_mouse_mode(false);
+                    platform.set_relative_mouse_mode(false);
+                }
+                Event::FocusGained => {
+                    // Capture mouse when window gains focus, unless the cursor is needed for the UI
+                    platform.set_relative_mouse_mode(!inventory_open && !console.is_open() && !settings_screen.is_open() && !map_screen.is_open());
+                }
+                Event::KeyDown { key: Some(Key::F3), .. } => {
+                    debug_overlay.toggle();
+                }
+                Event::KeyDown { key: Some(Key::F4), .. } => {
+                    debug_modes.wireframe = !debug_modes.wireframe;
+                }
+                Event::KeyDown { key: Some(Key::F5), .. } => {
+                    debug_modes.chunk_borders = !debug_modes.chunk_borders;
+                }
+                Event::KeyDown { key: Some(Key::F6), .. } => {
+                    view_mode = view_mode.next();
+                    log::info!(target: "render", "View: {}", view_mode.name());
+                }
+                Event::KeyDown { key: Some(Key::F7), .. } if platform.has_second_window() => {
+                    platform.close_second_window();
+                }
+                Event::KeyDown { key: Some(Key::F7), .. } => {
+                    match platform.open_second_window("Observer", OBSERVER_SIZE, OBSERVER_SIZE) {
+                        Ok(()) => log::info!(target: "render", "Opened the observer window"),
+                        Err(e) => log::error!(target: "render", "Failed to open the observer window: {}", e),
+                    }
+                }
+                Event::SecondWindowClosed => {
+                    platform.close_second_window();
+                }
+                Event::KeyDown { key: Some(Key::F8), .. } => {
+                    debug_modes.chunk_overlay = debug_modes.chunk_overlay.next();
+                    log::info!(target: "render", "Chunk overlay: {}", debug_modes.chunk_overlay.name());
+                }
+                Event::KeyDown { key: Some(Key::F2), .. } => {
+                    // Taken once the frame is drawn
+                    screenshot_requested = true;
+                }
+                Event::KeyDown { key: Some(Key::F), .. } => {
+                    player.toggle_fly();
+                }
+                Event::KeyDown { key: Some(Key::N), .. } => {
+                    player.toggle_noclip();
+                }
+                Event::KeyDown { key: Some(Key::M), .. } => {
+                    map_screen.open(camera.position);
+                    platform.set_relative_mouse_mode(false);
+                }
+                Event::KeyDown { key: Some(Key::Z), .. } => {
+                    if let Some(minimap) = &mut minimap {
+                        minimap.toggle_zoom();
+                    }
                 }
-                Event::Window { win_event: sdl2::event::WindowEvent::FocusGained, .. } => {
-                    // Capture mouse when window gains focus
-                    mouse.set_relative_mouse_mode(true);
+                Event::KeyDown { key: Some(Key::G), .. } => {
+                    // Throw the selected block, or shoot an arrow empty-handed
+                    let thrown = if game_mode.infinite_blocks() { inventory.selected_block() } else { inventory.take_selected() };
+                    let kind = match thrown {
+                        Some(block) => ProjectileKind::Block(block),
+                        None => ProjectileKind::Arrow,
+                    };
+                    let origin = camera.position + camera.front * 0.5;
+                    projectiles.launch(&mut entities, &mut entity_renderer, kind, origin, camera.front, player.velocity());
+                    held_block.swing();
+                }
+                Event::KeyDown { key: Some(Key::Tab), .. } => {
+                    inventory_open = !inventory_open;
+                    // Whatever was left on the crafting grid and doesn't fit is dropped in front of the player
+                    for stack in inventory.release() {
+                        for _ in 0..stack.count {
+                            dropped_items.spawn(&mut entities, &mut entity_renderer, stack.block, camera.position + camera.front);
+                        }
+                    }
+                    platform.set_relative_mouse_mode(!inventory_open);
+                }
+                Event::KeyDown { physical: Some(key), .. }
+                    if (Key::Num1 as u8..=Key::Num9 as u8).contains(&(key as u8)) => {
+                    inventory.select((key as u8 - Key::Num1 as u8) as usize);
+                }
+                Event::MouseWheel { y } => {
+                    inventory.scroll(-y);
+                }
+                Event::MouseButtonDown { button: MouseButton::Left, x, y, .. } if inventory_open => {
+                    let (width, height) = ui_screen_size(settings.ui_scale);
+                    inventory.click(x as f32 / settings.ui_scale, y as f32 / settings.ui_scale, width, height, &recipes);
+                }
+                Event::MouseButtonDown { button: MouseButton::Left, x, y, .. } if settings_screen.is_open() => {
+                    let (width, height) = ui_screen_size(settings.ui_scale);
+                    let (x, y) = (x as f32 / settings.ui_scale, y as f32 / settings.ui_scale);
+                    settings_changed |= settings_screen.click(&mut settings, x, y, width, height);
+                }
+                Event::MouseButtonDown { .. } if console.is_open() || settings_screen.is_open() || sign_editor.is_open() || map_screen.is_open() => {}
+                Event::MouseButtonDown { button: MouseButton::Right, .. } if !inventory_open => {
+                    // Light targeted TNT, write on a targeted sign, or else place the selected
+                    // block against the targeted face
+                    let target = world.raycast(camera.position, camera.front, REACH_DISTANCE);
+                    if let Some(position) = target.map(|hit| hit.block).filter(|&position| world.block_entities.get::<Sign>(position).is_some()) {
+                        sign_editor.open(position);
+                        platform.set_text_input(true);
+                        continue;
+                    }
+                    if let Some((x, y, z)) = target.map(|hit| hit.block).filter(|&(x, y, z)| world.get_block(x, y, z) == BlockType::Tnt) {
+                        world.set_block(x, y, z, BlockType::Air);
+                        held_block.swing();
+                        event_bus.publish(BlockChanged {
+                            position: (x, y, z),
+                            block: BlockType::Air,
+                            previous: Some(BlockType::Tnt),
+                            cause: BlockChangeCause::Ignition,
+                        });
+                        tnts.ignite(&mut entities, (x, y, z), FUSE_TIME);
+                        continue;
+                    }
+                    let camera_cell = (camera.position.x.round() as i32, camera.position.y.round() as i32, camera.position.z.round() as i32);
+                    if let (Some(hit), Some(block)) = (target, inventory.selected_block()) {
+                        let (x, y, z) = hit.adjacent();
+                        let previous = world.get_block(x, y, z);
+                        if hit.adjacent() != hit.block && hit.adjacent() != camera_cell
+                            && previous.collision_height().is_none()
+                            && world.set_block(x, y, z, block)
+                        {
+                            if !game_mode.infinite_blocks() {
+                                inventory.take_selected();
+                            }
+                            held_block.swing();
+                            event_bus.publish(BlockChanged {
+                                position: (x, y, z),
+                                block,
+                                previous: Some(previous),
+                                cause: BlockChangeCause::Player,
+                            });
+                            // A new sign is written on straight away, on the side facing the player
+                            if block == BlockType::Sign {
+                                let sign = Sign::new(Sign::facing_for(hit.normal, camera.front));
+                                world.block_entities.insert((x, y, z), block, Box::new(sign));
+                                sign_editor.open((x, y, z));
+                                platform.set_text_input(true);
+                            }
+                        }
+                    }
+                }
+                Event::MouseButtonDown { button: MouseButton::Left, .. } if game_mode.breaks_instantly() => {
+                    broken_block = world.raycast(camera.position, camera.front, REACH_DISTANCE).map(|hit| hit.block);
+                }
+                Event::KeyDown { key: Some(Key::Equals | Key::KpPlus), .. } => {
+                    settings.render_distance += 1;
+                    settings_changed = true;
+                }
+                Event::KeyDown { key: Some(Key::Minus | Key::KpMinus), .. } => {
+                    settings.render_distance -= 1;
+                    settings_changed = true;
                 }
                 _ => {}
             }
         }
+        // In survival the targeted block is dug while the button is held
+        let digging_allowed = !game_mode.breaks_instantly() && frame.left_button && !typing && !inventory_open;
+        let dug = world.raycast(camera.position, camera.front, REACH_DISTANCE)
+            .map(|hit| hit.block)
+            .filter(|_| digging_allowed);
+        broken_block = broken_block.or(digging.update(&world, dug, delta_time));
+        // Break it, dropping it as an item in survival
+        if let Some((x, y, z)) = broken_block {
+            let block = world.get_block(x, y, z);
+            world.set_block(x, y, z, BlockType::Air);
+            held_block.swing();
+            event_bus.publish(BlockChanged {
+                position: (x, y, z),
+                block: BlockType::Air,
+                previous: Some(block),
+                cause: BlockChangeCause::Player,
+            });
+            let center = Vec3::new(x as f32, y as f32, z as f32);
+            if let Some(particles) = &mut particles {
+                particles.emit_block_debris(center, block);
+            }
+            if game_mode.drops_blocks() {
+                dropped_items.spawn(&mut entities, &mut entity_renderer, block, center);
+            }
+        }
+
+        if settings_changed {
+            if streamer.render_distance() != settings.render_distance {
+                streamer.set_render_distance(settings.render_distance);
+                settings.render_distance = streamer.render_distance();
+                log::info!(target: "render", "Render distance: {}", streamer.render_distance());
+            }
+            if world.fancy_leaves != settings.fancy_leaves {
+                // Leaves hide different faces in each mode, so every chunk needs a new mesh
+                world.fancy_leaves = settings.fancy_leaves;
+                let positions: Vec<_> = world.chunks().map(|chunk| chunk.position).collect();
+                world.build_meshes(&positions, &mesh_cache);
+                renderer.upload_all(&world);
+            }
+            if language::selected() != settings.language {
+                language::select(&settings.language);
+            }
+            player.auto_jump = settings.auto_jump;
+            camera_effects.bobbing = settings.view_bobbing;
+            camera_effects.landing_dip = settings.landing_dip;
+            camera_effects.shake = settings.screen_shake;
+            tnts.blinking = !settings.reduce_flashing;
+            if let Some(hud) = &mut hud {
+                hud.palette = settings.palette;
+            }
+            if let Some(debug_lines) = &mut debug_lines {
+                debug_lines.palette = settings.palette;
+            }
+            apply_window_settings(&settings, &mut platform);
+            if !fresh_start {
+                if let Err(e) = settings.save(Path::new(SETTINGS_PATH)) {
+                    log::error!(target: "io", "Failed to save settings: {}", e);
+                }
+            }
+        }
+
+        drop(input_scope);
+
+        // Stream chunks in and out around the camera
+        let streaming_scope = Profiler::scope(System::Streaming);
+        if let Some(benchmark) = &benchmark {
+            // The benchmark flies the camera, whatever the input
+            (camera.position, camera.yaw, camera.pitch) = benchmark.camera();
+            camera.previous_position = camera.position;
+            camera.update_camera_vectors();
+        }
+        // Teleports and respawns can land far from the loaded chunks, which
+        // then load behind the loading screen
+        let size = CHUNK_SIZE as f32;
+        let arrived_unloaded = world.column((camera.position.x / size).floor() as i32, (camera.position.z / size).floor() as i32).is_none();
+        world_io.update(&mut world);
+        let update = streamer.update(&mut world, &camera.viewer(view_mode, settings.fov), COLUMNS_PER_FRAME, &mesh_cache, &mut event_bus);
+        world_io.read_chunks(update.to_read.clone(), &mut world);
+        if arrived_unloaded && !loading_screen.is_open() {
+            loading_screen.open(update.loaded.len() + streamer.queued() * WORLD_HEIGHT_CHUNKS as usize);
+        }
+        // `/tp` moves the player once the chunks around the destination are in
+        if let Some(destination) = pending_teleport.as_mut().and_then(|teleport| teleport.update(&mut streamer, &world)) {
+            pending_teleport = None;
+            camera.position = destination;
+            camera.previous_position = destination;
+        }
+        for &pos in &update.unloaded {
+            renderer.remove_chunk(pos);
+        }
+        for &pos in &update.remeshed {
+            renderer.queue_upload(pos);
+        }
+        event_bus.publish_all(update.loaded.iter().map(|&position| ChunkLoaded { position, meshed: true }));
+        let uploaded = renderer.upload_queued(&world, settings.upload_budget_bytes());
+        if uploaded > 0 {
+            event_bus.publish(ChunkProgress { stage: ChunkStage::Uploading, chunks: uploaded });
+        }
+        // Far chunks keep their mesh only on the GPU, and are meshed again if
+        // their upload was lost since
+        let lost = renderer.take_lost_uploads();
+        if !lost.is_empty() {
+            world.build_meshes(&lost, &mesh_cache);
+            for &position in &lost {
+                renderer.queue_upload(position);
+            }
+        }
+        let camera_column = ((camera.position.x / size).floor() as i32, (camera.position.z / size).floor() as i32);
+        world.evict_meshes(camera_column, MESH_KEEP_DISTANCE, |position| renderer.is_upload_queued(position));
+        // Over the memory budget, the spare meshes go and the farthest
+        // columns are unloaded in the next steps
+        if settings.memory_budget > 0 || debug_overlay.is_visible() {
+            memory = MemoryUsage::measure(&world, &*renderer);
+        }
+        if settings.memory_budget > 0 && memory.total() > settings.memory_budget_bytes() {
+            world.release_spare_meshes();
+        }
+        streamer.fit_budget(memory.total(), settings.memory_budget_bytes());
+        drop(streaming_scope);
+
+        // Share edits and positions with the other players
+        let tick_scope = Profiler::scope(System::WorldTick);
+        if let Some(connection) = &mut client {
+            position_send_time += delta_time;
+            if position_send_time >= POSITION_SEND_INTERVAL {
+                position_send_time = 0.0;
+                let position = camera.position;
+                connection.send_position(position.x, position.y, position.z, camera.yaw, camera.pitch);
+            }
+
+            match connection.poll() {
+                Ok(messages) => for message in messages {
+                    match message {
+                        Message::SetBlock { x, y, z, block } => {
+                            let previous = world.get_block(x, y, z);
+                            if world.set_block(x, y, z, block) {
+                                event_bus.publish(BlockChanged {
+                                    position: (x, y, z),
+                                    block,
+                                    previous: Some(previous),
+                                    cause: BlockChangeCause::Remote,
+                                });
+                            }
+                        },
+                        Message::PlayerPosition { player_id, x, y, z, yaw, .. } => {
+                            let player = *remote_players.entry(player_id).or_insert_with(|| {
+                                let player = entities.spawn();
+                                let mut transform = Transform::at(Vec3::new(x, y - EYE_HEIGHT, z));
+                                transform.scale = Vec3::new(0.6, 1.8, 0.6);
+                                entities.transforms.insert(player, transform);
+                                entities.meshes.insert(player, remote_player_mesh);
+                                player
+                            });
+                            if let Some(transform) = entities.transforms.get_mut(player) {
+                                transform.translation = Vec3::new(x, y - EYE_HEIGHT, z);
+                                // Camera yaw is in degrees, measured the other way around
+                                transform.rotation = Quat::from_yaw(-yaw.to_radians());
+                            }
+                        },
+                        Message::ChunkData { data } => match chunk_data::decode(&data) {
+                            Ok((position, blocks)) => {
+                                if world.restore_chunk(position, blocks) {
+                                    event_bus.publish(ChunkLoaded { position, meshed: false });
+                                }
+                            },
+                            Err(e) => log::warn!(target: "network", "Received invalid chunk: {}", e),
+                        },
+                        Message::PlayerLeft { player_id } => {
+                            if let Some(player) = remote_players.remove(&player_id) {
+                                entities.despawn(player);
+                            }
+                        },
+                        _ => {},
+                    }
+                },
+                Err(e) => {
+                    log::error!(target: "network", "Lost connection to the server: {}", e);
+                    for (_, player) in remote_players.drain() {
+                        entities.despawn(player);
+                    }
+                    client = None;
+                },
+            }
+        }
+
+        // Advance the time of day, 20 ticks per second
+        let world_ticks = world_timestep.advance(delta_time);
+        time_of_day = (time_of_day + world_ticks) % DAY_LENGTH;
+        let daylight = light::daylight(time_of_day);
+
+        // Water flow, grass spreading and growth, on the same ticks
+        if owns_world {
+            let edited_blocks = block_ticks.update(&mut world, world_ticks);
+            publish_block_changes(&mut event_bus, &world, &edited_blocks, BlockChangeCause::Tick);
+            // Plugins change the world on its ticks too
+            let plugin_effects = plugins.tick(&mut world, camera.position, world_ticks);
+            publish_block_changes(&mut event_bus, &world, &plugin_effects.edited_blocks, BlockChangeCause::Plugin);
+            for (kind, position) in plugin_effects.spawned_mobs {
+                mobs.spawn(&mut entities, kind, position);
+            }
+            let feet = camera.position - Vec3::new(0.0, EYE_HEIGHT, 0.0);
+            spawn_cycle.update(world_ticks, &mut mobs, &mut entities, &world, feet, daylight);
+        }
+
+        for _ in 0..physics_steps {
+            let step = physics_timestep.step();
+            entities.store_previous_positions();
+            entities.integrate(step);
+            mobs.update(&mut entities, &world, step);
+            dropped_items.update(&mut entities, camera.position, &mut inventory, step);
+            explosions.extend(tnts.update(&mut entities, &mut world, step));
+            let landed = projectiles.update(&mut entities, &mut world, step);
+            publish_block_changes(&mut event_bus, &world, &landed, BlockChangeCause::Projectile);
+        }
+
+        // Publish what explosions blew away, light the TNT they caught, and
+        // show them
+        for explosion in &explosions {
+            event_bus.publish_all(explosion.removed.iter().map(|&(position, block)| BlockChanged {
+                position,
+                block: BlockType::Air,
+                previous: Some(block),
+                cause: BlockChangeCause::Explosion,
+            }));
+            for &(position, block) in &explosion.removed {
+                if block == BlockType::Tnt {
+                    let fuse = tnts.chain_fuse();
+                    tnts.ignite(&mut entities, position, fuse);
+                }
+            }
+            if let Some(particles) = &mut particles {
+                particles.emit_explosion(explosion);
+            }
+            explosion.push_entities(&mut entities);
+            // The player is pushed and hurt from their middle, and shaken at their eyes
+            let middle = camera.position - Vec3::new(0.0, EYE_HEIGHT / 2.0, 0.0);
+            player.push(explosion.knockback(middle));
+            if player.mode() != MoveMode::Noclip && game_mode.takes_damage() {
+                health.damage(explosion.damage(middle), DamageCause::Explosion);
+            }
+            let shake = explosion.shake(camera.position);
+            camera_effects.add_shake(shake);
+            event_bus.publish(Exploded { center: explosion.center, shake });
+        }
+        // Before dying resets the player's health
+        event_bus.publish_all(health.take_hurts().into_iter().map(|(amount, cause)| PlayerHurt { amount, cause }));
+
+        if health.is_dead() {
+            let respawn = spawn.position();
+            log::info!(target: "game", "Died, respawning at {:.1} {:.1} {:.1}", respawn.x, respawn.y, respawn.z);
+            camera.position = respawn + Vec3::new(0.0, EYE_HEIGHT, 0.0);
+            camera.previous_position = camera.position;
+            player = Player::new(settings.auto_jump);
+            health.reset();
+        }
+        // Particles, clouds and the held block are only for show, so they follow the frame rate
+        if let Some(particles) = &mut particles {
+            particles.update(&world, camera.position, daylight, delta_time);
+        }
+        if let Some(clouds) = &mut clouds {
+            clouds.update(delta_time);
+        }
+        held_block.update(delta_time);
+        camera_effects.update(player.walking_speed(), delta_time);
+        sounds.set_listener(camera.position, camera.front.cross(&camera.up));
+
+        // Every system follows what the frame changed, whatever changed it
+        if (camera.position - frame_start_position).length() > 0.0 {
+            event_bus.publish(PlayerMoved { from: frame_start_position - eye_offset, to: camera.position - eye_offset });
+        }
+        let plugin_effects = plugins.handle_events(&event_bus, &mut world, camera.position);
+        publish_block_changes(&mut event_bus, &world, &plugin_effects.edited_blocks, BlockChangeCause::Plugin);
+        for (kind, position) in plugin_effects.spawned_mobs {
+            mobs.spawn(&mut entities, kind, position);
+        }
+        block_ticks.handle_events(&event_bus);
+        if let Some(client) = &mut client {
+            client.handle_events(&event_bus);
+        }
+        sounds.handle_events(&event_bus, &world);
+        if let Some(rumble) = feedback.handle_events(&event_bus).filter(|_| settings.rumble) {
+            platform.rumble(rumble.low, rumble.high, rumble.duration);
+        }
+        let mut remeshed = event_bus.read(&mut mesh_block_changes).iter().map(|change| change.position).collect::<Vec<_>>();
+        for loaded in event_bus.read(&mut mesh_chunk_loads).iter().filter(|loaded| !loaded.meshed) {
+            let size = world::CHUNK_SIZE as i32;
+            let (x, y, z) = (loaded.position.0 * size, loaded.position.1 * size, loaded.position.2 * size);
+            // Opposite corners touch the chunk and all its face neighbours
+            remeshed.extend([(x, y, z), (x + size - 1, y + size - 1, z + size - 1)]);
+        }
+        remesh_blocks(&mut world, &mut *renderer, &mesh_cache, &remeshed);
+        loading_screen.handle_events(&event_bus);
+        if streamer.queued() == 0 && renderer.queued_uploads() == 0 {
+            loading_screen.close();
+        }
+        event_bus.clear();
+        drop(tick_scope);
+
+        // Every so often what changed is saved in the background, so a crash
+        // loses at most a minute of play
+        if let Some(autosaver) = &mut autosaver {
+            autosaver.update(delta_time, &world_io);
+            if autosaver.is_due(&world_io) {
+                let mut job = SaveJob::new();
+                // The crafting grid isn't saved, only emptied into the slots on exit
+                job.push((inventory_path.clone(), inventory.encode()));
+                if saves_world {
+                    job.push((spawn_path.clone(), spawn.encode()));
+                    job.push((metadata_path.clone(), WorldMetadata { game_mode, border: world.border, ..metadata.clone() }.encode()));
+                    job.push((block_entity_path.clone(), world.block_entities.encode()));
+                    for position in world.take_unsaved_chunks() {
+                        job.push((chunk_store.path(position), chunk_data::encode(position, &world.chunk_blocks(position))));
+                    }
+                    job.extend(map_colors.take_unsaved_tiles(&map_directory));
+                }
+                autosaver.save(&mut world_io, job);
+            }
+        }
+
+        // Headless replays only simulate
+        if headless {
+            continue;
+        }
 
         // Render frame
-        let view = camera.get_view_matrix();
-        let model = Mat4::scale(Vec3::new(1.0, 1.0, 1.0));  // Changed scale to 1.0
-        let transform = projection * view * model;
+        let render_scope = Profiler::scope(System::Render);
+        let alpha = physics_timestep.alpha();
+        let effects_offset = camera_effects.offset(camera.front.cross(&camera.up).normalize(), camera.up);
+        let eye = camera.previous_position.lerp(&camera.position, alpha) + effects_offset;
+        let view = camera.get_view_matrix(alpha, effects_offset, view_mode);
+        let projection = projection_matrix(settings.fov, view_mode);
+        // Where the camera actually is, away from the player in the isometric view
+        let (view_eye, view_front) = view_mode.camera(eye, camera.front);
+
+        let sun_angle = light::sun_angle(time_of_day);
+        let sky_color = sky_color(daylight);
+        // Fog hides where the loaded chunks end, further from a camera held
+        // back from the player
+        let fog_end = (streamer.loaded_distance() * CHUNK_SIZE as i32) as f32;
+        let fog_offset = view_mode.camera_distance();
+        // With the camera under water, the water's own fog closes in instead
+        let underwater = world.is_fluid_at(view_eye);
+        let (fog_color, fog_start, fog_end) = if underwater {
+            (UNDERWATER_COLOR.map(|channel| channel * daylight), 0.0, UNDERWATER_FOG_END)
+        } else {
+            (sky_color, fog_end * FOG_START + fog_offset, fog_end + fog_offset)
+        };
+        let frame_uniforms = FrameUniforms {
+            view,
+            projection,
+            camera_position: view_eye,
+            time: current_frame_time / 1000.0,
+            sun_direction: sky::sun_direction(sun_angle),
+            daylight,
+            fog_color,
+            fog_start,
+            fog_end,
+        };
+        renderer.set_frame_uniforms(&frame_uniforms);
+        // wgpu clears to the fog color as it draws the chunks
+        if use_gl {
+            gl_utils::clear_color(fog_color[0], fog_color[1], fog_color[2], 1.0);
+            unsafe {
+                gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+            }
+        }
+        sky_timer.begin();
+        // The sky is a dome around the eye, which only works with perspective
+        if let Some(sky) = sky.as_mut().filter(|_| !underwater && view_mode.is_perspective()) {
+            sky.draw(eye, sun_angle, daylight);
+        }
+        sky_timer.end();
+        music.update(Mood::at(&world, camera.position, daylight), &mut sounds, delta_time);
 
-        gl_utils::clear_color(0.2, 0.3, 0.3, 1.0);
-        unsafe {
-            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
-
-            // Bind textures
-            gl::ActiveTexture(gl::TEXTURE0);
-            gl::BindTexture(gl::TEXTURE_2D, grass_top_texture);
-            gl::ActiveTexture(gl::TEXTURE1);
-            gl::BindTexture(gl::TEXTURE_2D, grass_side_texture);
-            gl::ActiveTexture(gl::TEXTURE2);
-            gl::BindTexture(gl::TEXTURE_2D, dirt_texture);
-            gl::ActiveTexture(gl::TEXTURE3);
-            gl::BindTexture(gl::TEXTURE_2D, colormap_texture);
-            gl::ActiveTexture(gl::TEXTURE4);
-            gl::BindTexture(gl::TEXTURE_2D, grass_side_overlay_texture);
-            gl::ActiveTexture(gl::TEXTURE5);
-            gl::BindTexture(gl::TEXTURE_2D, stone_texture);
-            gl::ActiveTexture(gl::TEXTURE6);
-            gl::BindTexture(gl::TEXTURE_2D, water_texture);
-
-            let transform_loc = gl::GetUniformLocation(shader_program.0, b"transform\0".as_ptr() as *const i8);
-            gl::UniformMatrix4fv(transform_loc, 1, gl::FALSE, transform.as_ptr());
-        }
-
-        shader_program.use_program();
-        vao.bind();
-        unsafe {
-            gl::DrawElements(
-                gl::TRIANGLES,
-                (all_indices.len() * 3) as i32,
-                gl::UNSIGNED_INT,
-                std::ptr::null(),
-            );
+        // Bind textures, shared by the entity and chunk passes
+        if let (Some(block_textures), Some(colormap_texture)) = (block_textures, colormap_texture) {
+            gl_utils::GlState::bind_texture(0, gl::TEXTURE_2D_ARRAY, block_textures);
+            gl_utils::GlState::bind_texture(1, gl::TEXTURE_2D, colormap_texture);
+        }
+
+        if debug_modes.wireframe && use_gl {
+            unsafe { gl::PolygonMode(gl::FRONT_AND_BACK, gl::LINE) };
+        }
+
+        // Opaque entities first, so water blends over them
+        entity_renderer.draw(&entities, &(projection * view), alpha);
+
+        renderer.set_fast_leaves(!settings.fancy_leaves);
+        point_lights.gather(&world, eye, camera.front);
+        renderer.set_point_lights(&point_lights);
+        opaque_timer.begin();
+        renderer.draw_opaque();
+        opaque_timer.end();
+        water_timer.begin();
+        renderer.draw_water();
+        water_timer.end();
+        if let Some(clouds) = clouds.as_ref().filter(|_| settings.clouds) {
+            clouds.draw();
+        }
+        if let (Some(border), Some(border_renderer)) = (&world.border, &border_renderer) {
+            border_renderer.draw(border, camera.position);
+        }
+
+        if debug_modes.wireframe && use_gl {
+            unsafe { gl::PolygonMode(gl::FRONT_AND_BACK, gl::FILL) };
+        }
+        if let Some(debug_lines) = &mut debug_lines {
+            if debug_modes.chunk_borders {
+                debug::queue_chunk_borders(debug_lines, camera.position);
+            }
+            debug::queue_chunk_overlay(debug_lines, debug_modes.chunk_overlay, &world, &streamer, &*renderer, camera.position);
+            if let Some(hit) = world.raycast(camera.position, camera.front, REACH_DISTANCE).filter(|_| view_mode.is_perspective()) {
+                queue_target_outline(debug_lines, hit.block, settings.high_contrast_outline);
+            }
+            selection.queue_outline(debug_lines);
+            debug_lines.draw();
+        }
+        if let Some(sign_renderer) = &mut sign_renderer {
+            sign_renderer.draw(&world, camera.position);
+        }
+
+        let camera_right = view_front.cross(&camera.up).normalize();
+        if let Some(particles) = &particles {
+            particles.draw(camera_right, camera_right.cross(&view_front));
+        }
+        if let Some(block) = inventory.selected_block().filter(|_| view_mode.is_perspective()) {
+            held_block.draw(&mut entity_renderer, block, WINDOW_WIDTH as f32 / WINDOW_HEIGHT as f32);
+        }
+
+        // The map colors follow the world even while the overlay isn't drawn
+        map_colors.update(&mut world);
+        if let Some(hud) = &mut hud {
+            // Overlay, laid out on a screen as much smaller as the UI is scaled up
+            let (screen_width, screen_height) = ui_screen_size(settings.ui_scale);
+            if underwater {
+                let [r, g, b] = fog_color;
+                hud.rect(0.0, 0.0, screen_width, screen_height, [r, g, b, 0.4]);
+            }
+            if game_mode.takes_damage() {
+                health.draw(hud, screen_width, screen_height, !settings.reduce_flashing);
+            }
+            digging.draw(hud, screen_width, screen_height);
+            inventory.draw_hotbar(hud, screen_width, screen_height);
+            if inventory_open {
+                inventory.draw_screen(hud, screen_width, screen_height);
+            }
+            if debug_overlay.is_visible() {
+                let p = camera.position;
+                let mut lines = vec![
+                    format!("FPS: {} ({})", debug_overlay.fps(), settings.frame_mode()),
+                    format!("XYZ: {:.1} {:.1} {:.1} ({}, {})", p.x, p.y, p.z, game_mode.name(), player.mode().name()),
+                    format!("Temperature: {}", world.temperature(p.x.round() as i32, p.z.round() as i32).map_or("-".to_string(), |t| format!("{:.2}", t))),
+                    format!(
+                        "Chunks: {}, {} columns queued, {} uploads queued, render distance {} ({} loaded)",
+                        world.chunk_count(),
+                        streamer.queued(),
+                        renderer.queued_uploads(),
+                        streamer.render_distance(),
+                        streamer.loaded_distance(),
+                    ),
+                    memory.describe(settings.memory_budget),
+                    format!("Point lights: {}", point_lights.count()),
+                    format!("GL calls skipped: {}", skipped_gl_calls),
+                ];
+                if let Some(hit) = world.raycast(camera.position, camera.front, REACH_DISTANCE) {
+                    let (b, n, h) = (hit.block, hit.normal, hit.point);
+                    lines.push(format!(
+                        "Target: {} {} {}, face {} {} {}, hit {:.2} {:.2} {:.2} ({:.2} away)",
+                        b.0, b.1, b.2, n.0, n.1, n.2, h.x, h.y, h.z, hit.distance,
+                    ));
+                }
+                // Time per frame spent in each system, on the CPU
+                lines.extend(Profiler::averages().map(|(system, ms)| format!("{}: {:.2} ms", system.name(), ms)));
+                lines.push(format!(
+                    "GPU: sky {:.2}, opaque {:.2}, water {:.2}, HUD {:.2} ms",
+                    sky_timer.milliseconds(),
+                    opaque_timer.milliseconds(),
+                    water_timer.milliseconds(),
+                    hud_timer.milliseconds(),
+                ));
+                debug_overlay.draw(hud, &lines);
+            }
+            if sign_editor.is_open() {
+                sign_editor.draw(&mut world, hud, screen_width, screen_height);
+            }
+            settings_screen.draw(&settings, hud, screen_width, screen_height);
+            if map_screen.is_open() {
+                map_screen.draw(&map_colors, hud, camera.position, camera.yaw, screen_width, screen_height);
+            }
+            console.draw(hud, screen_width, screen_height);
+            if let Some(autosaver) = &autosaver {
+                autosaver.draw(hud, screen_width, screen_height);
+            }
+            if let Some(teleport) = &pending_teleport {
+                teleport.draw(hud, screen_width);
+            }
+            loading_screen.draw(hud, screen_width, screen_height);
+            hud_timer.begin();
+            if let Some(minimap) = minimap.as_mut().filter(|_| !map_screen.is_open()) {
+                minimap.draw(&map_colors, camera.position, camera.yaw, screen_width, screen_height);
+            }
+            hud.draw(screen_width, screen_height);
+            hud_timer.end();
+        }
+
+        if screenshot_requested {
+            screenshot_requested = false;
+            // Screenshots are read back from the OpenGL framebuffer
+            let saved = match use_gl {
+                true => screenshot::save_screenshot(Path::new(SCREENSHOT_DIRECTORY), WINDOW_WIDTH, WINDOW_HEIGHT),
+                false => Err("Screenshots need OpenGL".to_string()),
+            };
+            match saved {
+                Ok(path) => log::info!(target: "io", "Saved screenshot {}", path.display()),
+                Err(e) => log::error!(target: "io", "Failed to save screenshot: {}", e),
+            }
+        }
+        if let Some(timelapse) = timelapse.as_mut().filter(|_| use_gl) {
+            timelapse.update(delta_time, WINDOW_WIDTH, WINDOW_HEIGHT);
         }
 
-        window.gl_swap_window();
+        if let Some(debug_lines) = debug_lines.as_mut().filter(|_| platform.has_second_window()) {
+            let viewer = camera.viewer(view_mode, settings.fov);
+            platform.draw_second_window(&mut || {
+                observer::draw(&mut *renderer, debug_lines, &frame_uniforms, &streamer, &world, &viewer);
+            });
+        }
+
+        drop(render_scope);
+
+        renderer.present();
+        platform.swap_buffers();
+        skipped_gl_calls = gl_utils::GlState::take_skipped_calls();
+
+        if let Some(benchmark) = &mut benchmark {
+            benchmark.record_frame(delta_time, frame_start.elapsed(), &update);
+            if benchmark.is_finished() {
+                match benchmark.write_report(Path::new("benchmarks")) {
+                    Ok(path) => log::info!(target: "io", "Benchmark report written to {}", path.display()),
+                    Err(e) => log::error!(target: "io", "Failed to write benchmark report: {}", e),
+                }
+                break 'main_loop;
+            }
+        }
+
+        // Frame limiting, on top of vsync if both are on
+        if let Some(frame_budget) = settings.frame_budget() {
+            let frame_time = frame_start.elapsed();
+            if frame_time < frame_budget {
+                thread::sleep(frame_budget - frame_time);
+            }
+        }
+    }
 
-        // Frame limiting
-        let frame_time = timer.ticks() as f32 - current_frame_time;
-        if frame_time < target_frame_time {
-            thread::sleep(Duration::from_millis(((target_frame_time - frame_time) as u64).max(0)));
+    if let (Some(recording), Some(path)) = (&recording, &options.record) {
+        match recording.save(path) {
+            Ok(()) => log::info!(target: "io", "Recorded {} frames to {}", recording.frames.len(), path.display()),
+            Err(e) => log::error!(target: "io", "Failed to save replay {}: {}", path.display(), e),
+        }
+    }
+    if let Some(path) = &options.trace {
+        match Profiler::write_trace(path) {
+            Ok(events) => log::info!(target: "io", "Wrote {} trace events to {}", events, path.display()),
+            Err(e) => log::error!(target: "io", "Failed to write trace {}: {}", path.display(), e),
+        }
+    }
+    world_io.finish();
+    if !fresh_start {
+        // Anything left on the crafting grid goes back into the slots, or is lost when they're full
+        inventory.release();
+        if let Err(e) = inventory.save(&inventory_path) {
+            log::error!(target: "io", "Failed to save inventory: {}", e);
+        }
+    }
+    if saves_world {
+        if let Err(e) = spawn.save(&spawn_path) {
+            log::error!(target: "io", "Failed to save spawn point: {}", e);
+        }
+        if let Err(e) = (WorldMetadata { game_mode, border: world.border, ..metadata }).save(&metadata_path) {
+            log::error!(target: "io", "Failed to save world metadata: {}", e);
+        }
+        if let Err(e) = map_colors.save(&map_directory) {
+            log::error!(target: "io", "Failed to save map: {}", e);
+        }
+        if let Err(e) = world.block_entities.save(&block_entity_path) {
+            log::error!(target: "io", "Failed to save block entities: {}", e);
+        }
+        // Chunks edited before their save was read get it now, for the edits
+        // to go on top of it
+        for position in world.edited_unread_chunks() {
+            let blocks = chunk_store.load(position)
+                .inspect_err(|e| log::warn!(target: "io", "Failed to load chunk {}: {}", chunk_store.path(position).display(), e))
+                .ok();
+            world.load_saved_chunk(position, blocks);
+        }
+        for position in world.modified_chunks() {
+            if let Err(e) = chunk_store.save(position, &world.chunk_blocks(position)) {
+                log::error!(target: "io", "Failed to save chunk {:?}: {}", position, e);
+            }
         }
     }
+    Ok(())
 }
@@ -0,0 +1,251 @@
+use crate::gl_utils::{self, buffer_data, Buffer, BufferType, GlState, ShaderProgram, VertexArray};
+use crate::hud::Hud;
+use crate::language::text;
+use crate::math::Vec3;
+use crate::minimap::{MapColors, MINIMAP_TEXTURE_UNIT, TILE_COLUMNS};
+use crate::world::CHUNK_SIZE;
+use gl::types::GLuint;
+use std::collections::HashMap;
+
+// Blocks along each side of a tile, which gets a texture of its own
+const TILE_BLOCKS: i32 = TILE_COLUMNS * CHUNK_SIZE as i32;
+// Pixels per block: at first, at the most zoomed out and in, and the factor
+// for each step of the mouse wheel
+const DEFAULT_SCALE: f32 = 2.0;
+const MIN_SCALE: f32 = 0.5;
+const MAX_SCALE: f32 = 8.0;
+const ZOOM_STEP: f32 = 1.25;
+// Pixels the arrow keys move the map by
+const KEY_PAN: f32 = 64.0;
+const BACKGROUND: [f32; 3] = [0.08, 0.08, 0.1];
+const TEXT_SIZE: f32 = 16.0;
+const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const HINT_COLOR: [f32; 4] = [0.7, 0.7, 0.7, 1.0];
+// The player's marker: a square, and dots ahead of it for the way they face
+const MARKER_SIZE: f32 = 6.0;
+const MARKER_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const MARKER_OUTLINE: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+const HEADING_DOTS: usize = 3;
+const HEADING_SPACING: f32 = 5.0;
+
+type MapVertex = [f32; 4];  // x, y (pixels), s, t
+
+/// Full-screen map of everything explored so far, dragged around with the
+/// mouse and zoomed with the wheel. Explored tiles get a texture each while
+/// the screen is open.
+pub struct MapScreen {
+    // What the tiles are drawn with, which disabled screens don't have
+    drawing: Option<(ShaderProgram, VertexArray, Buffer)>,
+    // Textures of the tiles drawn since the screen opened, with the map
+    // revision each shows
+    tiles: HashMap<(i32, i32), (GLuint, u64)>,
+    texels: Vec<[u8; 4]>,
+    open: bool,
+    // Block at the middle of the screen, and pixels per block
+    center: (f32, f32),
+    scale: f32,
+}
+
+impl MapScreen {
+    pub fn new(program: ShaderProgram) -> Option<Self> {
+        let vao = VertexArray::new()?;
+        vao.bind();
+        let vertices = Buffer::new()?;
+        vertices.bind(BufferType::Array);
+        unsafe {
+            let stride = std::mem::size_of::<MapVertex>() as gl::types::GLsizei;
+            let float = std::mem::size_of::<f32>();
+            // (location, components, offset in floats): position, texture coordinates
+            for (location, components, offset) in [(0, 2, 0), (1, 2, 2)] {
+                gl::VertexAttribPointer(location, components, gl::FLOAT, gl::FALSE, stride, (offset * float) as *const _);
+                gl::EnableVertexAttribArray(location);
+            }
+        }
+
+        Some(Self::with_drawing(Some((program, vao, vertices))))
+    }
+
+    /// A map screen that never opens, for backends without an OpenGL context.
+    pub fn disabled() -> Self {
+        Self::with_drawing(None)
+    }
+
+    fn with_drawing(drawing: Option<(ShaderProgram, VertexArray, Buffer)>) -> Self {
+        Self {
+            drawing,
+            tiles: HashMap::new(),
+            texels: vec![[0; 4]; (TILE_BLOCKS * TILE_BLOCKS) as usize],
+            open: false,
+            center: (0.0, 0.0),
+            scale: DEFAULT_SCALE,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Opens the map centered on `position`.
+    pub fn open(&mut self, position: Vec3) {
+        self.open = self.drawing.is_some();
+        self.center = (position.x, position.z);
+    }
+
+    /// Closes the map, freeing the textures of its tiles.
+    pub fn close(&mut self) {
+        self.open = false;
+        for &(texture, _) in self.tiles.values() {
+            GlState::delete_texture(texture);
+        }
+        self.tiles.clear();
+    }
+
+    /// Moves the map along with the cursor, by pixels.
+    pub fn drag(&mut self, x: f32, y: f32) {
+        self.center.0 -= x / self.scale;
+        self.center.1 -= y / self.scale;
+    }
+
+    /// Moves the view by `KEY_PAN` pixels along each axis, for the arrow keys.
+    pub fn pan(&mut self, x: i32, y: i32) {
+        self.drag(-x as f32 * KEY_PAN, -y as f32 * KEY_PAN);
+    }
+
+    /// Zooms in for positive steps and out for negative ones.
+    pub fn zoom(&mut self, steps: i32) {
+        self.scale = (self.scale * ZOOM_STEP.powi(steps)).clamp(MIN_SCALE, MAX_SCALE);
+    }
+
+    /// Draws the explored tiles in view over the whole screen, and queues the
+    /// player's marker and the hints on the HUD.
+    pub fn draw(&mut self, colors: &MapColors, hud: &mut Hud, player: Vec3, yaw: f32, screen_width: f32, screen_height: f32) {
+        let [r, g, b] = BACKGROUND;
+        gl_utils::clear_color(r, g, b, 1.0);
+        unsafe { gl::Clear(gl::COLOR_BUFFER_BIT) };
+
+        // Screen position of a point of the world; blocks are centered on
+        // integer coordinates, so tiles start half a block early
+        let (center, scale) = (self.center, self.scale);
+        let to_screen = |x: f32, z: f32| (
+            screen_width / 2.0 + (x - center.0) * scale,
+            screen_height / 2.0 + (z - center.1) * scale,
+        );
+        let tile_range = |center: f32, screen: f32| {
+            let half = screen / 2.0 / scale;
+            let first = ((center - half + 0.5) / TILE_BLOCKS as f32).floor() as i32;
+            let last = ((center + half + 0.5) / TILE_BLOCKS as f32).floor() as i32;
+            first..=last
+        };
+
+        let mut quads: Vec<MapVertex> = Vec::new();
+        let mut textures = Vec::new();
+        for tile_z in tile_range(center.1, screen_height) {
+            for tile_x in tile_range(center.0, screen_width) {
+                let Some(revision) = colors.tile_revision((tile_x, tile_z)) else {
+                    continue;
+                };
+                textures.push(self.tile_texture(colors, (tile_x, tile_z), revision));
+                let (left, top) = to_screen((tile_x * TILE_BLOCKS) as f32 - 0.5, (tile_z * TILE_BLOCKS) as f32 - 0.5);
+                let size = TILE_BLOCKS as f32 * scale;
+                let (right, bottom) = (left + size, top + size);
+                quads.extend([
+                    [left, top, 0.0, 0.0],
+                    [left, bottom, 0.0, 1.0],
+                    [right, bottom, 1.0, 1.0],
+                    [right, bottom, 1.0, 1.0],
+                    [right, top, 1.0, 0.0],
+                    [left, top, 0.0, 0.0],
+                ]);
+            }
+        }
+
+        if let Some((program, vao, vertices)) = self.drawing.as_ref().filter(|_| !quads.is_empty()) {
+            program.use_program();
+            vao.bind();
+            vertices.bind(BufferType::Array);
+            buffer_data(BufferType::Array, bytemuck::cast_slice(&quads), gl::STREAM_DRAW);
+            unsafe {
+                gl::Uniform1i(gl::GetUniformLocation(program.0, b"mapTexture\0".as_ptr() as *const i8), MINIMAP_TEXTURE_UNIT as i32);
+                gl::Uniform2f(gl::GetUniformLocation(program.0, b"screenSize\0".as_ptr() as *const i8), screen_width, screen_height);
+                GlState::set_capability(gl::DEPTH_TEST, false);
+                for (index, &texture) in textures.iter().enumerate() {
+                    GlState::bind_texture(MINIMAP_TEXTURE_UNIT, gl::TEXTURE_2D, texture);
+                    gl::DrawArrays(gl::TRIANGLES, index as i32 * 6, 6);
+                }
+                GlState::set_capability(gl::DEPTH_TEST, true);
+            }
+        }
+
+        let (x, y) = to_screen(player.x, player.z);
+        let half = MARKER_SIZE / 2.0;
+        hud.rect(x - half - 1.0, y - half - 1.0, MARKER_SIZE + 2.0, MARKER_SIZE + 2.0, MARKER_OUTLINE);
+        hud.rect(x - half, y - half, MARKER_SIZE, MARKER_SIZE, MARKER_COLOR);
+        let (facing_x, facing_z) = (yaw.to_radians().cos(), yaw.to_radians().sin());
+        for dot in 1..=HEADING_DOTS {
+            let distance = half + dot as f32 * HEADING_SPACING;
+            hud.rect(x + facing_x * distance - 1.0, y + facing_z * distance - 1.0, 2.0, 2.0, MARKER_COLOR);
+        }
+
+        let position = format!("{:.0} {:.0}", center.0, center.1);
+        hud.draw_text(TEXT_SIZE, TEXT_SIZE, TEXT_SIZE, TEXT_COLOR, &position);
+        let hint = text("map.hint", &[]);
+        let hint_x = (screen_width - hud.measure_text(TEXT_SIZE, &hint)) / 2.0;
+        hud.draw_text(hint_x, screen_height - 2.0 * TEXT_SIZE, TEXT_SIZE, HINT_COLOR, &hint);
+    }
+
+    // Texture of a tile, uploaded again if the map changed since it was drawn
+    fn tile_texture(&mut self, colors: &MapColors, tile: (i32, i32), revision: u64) -> GLuint {
+        if let Some(&(texture, drawn)) = self.tiles.get(&tile) {
+            if drawn == revision {
+                return texture;
+            }
+        }
+
+        // Rows go from north to south, so north ends up at the top
+        let columns = TILE_COLUMNS as usize;
+        self.texels.fill([0; 4]);
+        for column_z in 0..columns {
+            for column_x in 0..columns {
+                let Some(column) = colors.column(tile.0 * TILE_COLUMNS + column_x as i32, tile.1 * TILE_COLUMNS + column_z as i32) else {
+                    continue;
+                };
+                for (index, &[r, g, b]) in column.iter().enumerate() {
+                    let (x, z) = (column_x * CHUNK_SIZE + index % CHUNK_SIZE, column_z * CHUNK_SIZE + index / CHUNK_SIZE);
+                    self.texels[z * TILE_BLOCKS as usize + x] = [r, g, b, 255];
+                }
+            }
+        }
+
+        let texture = match self.tiles.get(&tile) {
+            Some(&(texture, _)) => texture,
+            None => {
+                let mut texture = 0;
+                unsafe {
+                    gl::GenTextures(1, &mut texture);
+                    GlState::bind_texture(MINIMAP_TEXTURE_UNIT, gl::TEXTURE_2D, texture);
+                    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+                    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+                    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+                    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+                }
+                texture
+            },
+        };
+        GlState::bind_texture(MINIMAP_TEXTURE_UNIT, gl::TEXTURE_2D, texture);
+        unsafe {
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGBA8 as i32,
+                TILE_BLOCKS,
+                TILE_BLOCKS,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                self.texels.as_ptr() as *const _
+            );
+        }
+        self.tiles.insert(tile, (texture, revision));
+        texture
+    }
+}
@@ -1,7 +1,9 @@
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+use crate::simd;
 use std::ops::{Add, Mul, Sub};
 
 /// A 3D vector type
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default)]
 pub struct Vec3 {
     pub x: f32,
     pub y: f32,
@@ -33,6 +35,11 @@ impl Vec3 {
         (self.x * self.x + self.y * self.y + self.z * self.z).sqrt()
     }
 
+    /// The point `t` of the way from this vector to `other`.
+    pub fn lerp(&self, other: &Vec3, t: f32) -> Vec3 {
+        *self + (*other - *self) * t
+    }
+
     pub fn normalize(&self) -> Vec3 {
         let len = self.length();
         if len != 0.0 {
@@ -83,7 +90,23 @@ impl Mul<f32> for Vec3 {
     }
 }
 
-/// A 4x4 matrix stored in column-major order
+/// A 4D vector, for points and directions in homogeneous coordinates.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Vec4 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+impl Vec4 {
+    pub fn new(x: f32, y: f32, z: f32, w: f32) -> Self {
+        Self { x, y, z, w }
+    }
+}
+
+/// A 4x4 matrix stored in column-major order. With the `simd` feature on
+/// x86_64, multiplications use SSE.
 #[derive(Debug, Clone, Copy)]
 pub struct Mat4 {
     data: [f32; 16],
@@ -156,6 +179,21 @@ impl Mat4 {
         result
     }
 
+    /// Maps the box between the given planes, in view space, to clip space
+    /// without perspective. `near` and `far` are distances in front of the camera.
+    pub fn orthographic(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> Self {
+        let mut result = Self::identity();
+
+        result.data[0] = 2.0 / (right - left);
+        result.data[5] = 2.0 / (top - bottom);
+        result.data[10] = -2.0 / (far - near);
+        result.data[12] = -(right + left) / (right - left);
+        result.data[13] = -(top + bottom) / (top - bottom);
+        result.data[14] = -(far + near) / (far - near);
+
+        result
+    }
+
     pub fn look_at(position: Vec3, target: Vec3, up: Vec3) -> Self {
         let z = (position - target).normalize();
         let x = up.cross(&z).normalize();
@@ -184,14 +222,278 @@ impl Mat4 {
         result
     }
 
+    /// Applies the rotation and scale of the matrix to a direction, ignoring translation.
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        let d = &self.data;
+        Vec3::new(
+            d[0] * v.x + d[4] * v.y + d[8] * v.z,
+            d[1] * v.x + d[5] * v.y + d[9] * v.z,
+            d[2] * v.x + d[6] * v.y + d[10] * v.z,
+        )
+    }
+
     pub fn as_ptr(&self) -> *const f32 {
         self.data.as_ptr()
     }
+
+    /// The 16 values, column by column, as the shaders expect them.
+    pub fn to_array(self) -> [f32; 16] {
+        self.data
+    }
+
+    /// Splits a matrix made of a translation, rotation and scale back into
+    /// them, the opposite of `Transform::to_mat4`. A mirroring shows as a
+    /// negative X scale. Shear and projection are lost.
+    pub fn decompose(&self) -> Transform {
+        let d = &self.data;
+        let column = |i: usize| Vec3::new(d[i * 4], d[i * 4 + 1], d[i * 4 + 2]);
+        let (x, y, z) = (column(0), column(1), column(2));
+        let mut scale = Vec3::new(x.length(), y.length(), z.length());
+        if x.cross(&y).dot(&z) < 0.0 {
+            scale.x = -scale.x;
+        }
+        // A flattened axis has no direction left to take the rotation from
+        let unscaled = |axis: Vec3, scale: f32| if scale != 0.0 { axis * (1.0 / scale) } else { Vec3::zero() };
+        let (x, y, z) = (unscaled(x, scale.x), unscaled(y, scale.y), unscaled(z, scale.z));
+        Transform {
+            translation: column(3),
+            rotation: Quat::from_axes(x, y, z),
+            scale,
+        }
+    }
+}
+
+/// A rotation, as a unit quaternion.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quat {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+impl Quat {
+    pub fn identity() -> Self {
+        Self { x: 0.0, y: 0.0, z: 0.0, w: 1.0 }
+    }
+
+    /// Turns counterclockwise around `axis` when looking down it, like `Mat4::rotate`.
+    pub fn from_axis_angle(axis: Vec3, angle_radians: f32) -> Self {
+        let axis = axis.normalize() * (angle_radians / 2.0).sin();
+        Self { x: axis.x, y: axis.y, z: axis.z, w: (angle_radians / 2.0).cos() }
+    }
+
+    /// Turns around the Y axis, as entities face where they go.
+    pub fn from_yaw(angle_radians: f32) -> Self {
+        Self::from_axis_angle(Vec3::new(0.0, 1.0, 0.0), angle_radians)
+    }
+
+    // The rotation turning the X, Y and Z axes into the given ones, which must
+    // be unit length and square to each other (Shepperd's method)
+    fn from_axes(x: Vec3, y: Vec3, z: Vec3) -> Self {
+        let trace = x.x + y.y + z.z;
+        let (qx, qy, qz, qw) = if trace > 0.0 {
+            let s = (trace + 1.0).sqrt() * 2.0;
+            ((y.z - z.y) / s, (z.x - x.z) / s, (x.y - y.x) / s, s / 4.0)
+        } else if x.x > y.y && x.x > z.z {
+            let s = (1.0 + x.x - y.y - z.z).sqrt() * 2.0;
+            (s / 4.0, (y.x + x.y) / s, (z.x + x.z) / s, (y.z - z.y) / s)
+        } else if y.y > z.z {
+            let s = (1.0 + y.y - x.x - z.z).sqrt() * 2.0;
+            ((y.x + x.y) / s, s / 4.0, (z.y + y.z) / s, (z.x - x.z) / s)
+        } else {
+            let s = (1.0 + z.z - x.x - y.y).sqrt() * 2.0;
+            ((z.x + x.z) / s, (z.y + y.z) / s, s / 4.0, (x.y - y.x) / s)
+        };
+        Self { x: qx, y: qy, z: qz, w: qw }.normalize()
+    }
+
+    fn normalize(self) -> Self {
+        let length = (self.x * self.x + self.y * self.y + self.z * self.z + self.w * self.w).sqrt();
+        if length == 0.0 {
+            return Self::identity();
+        }
+        Self { x: self.x / length, y: self.y / length, z: self.z / length, w: self.w / length }
+    }
+
+    /// The opposite rotation.
+    pub fn inverse(self) -> Self {
+        Self { x: -self.x, y: -self.y, z: -self.z, w: self.w }
+    }
+
+    pub fn rotate(self, v: Vec3) -> Vec3 {
+        let axis = Vec3::new(self.x, self.y, self.z);
+        let t = axis.cross(&v) * 2.0;
+        v + t * self.w + axis.cross(&t)
+    }
+
+    pub fn to_mat4(self) -> Mat4 {
+        let Quat { x, y, z, w } = self;
+        let mut result = Mat4::identity();
+
+        result.data[0] = 1.0 - 2.0 * (y * y + z * z);
+        result.data[1] = 2.0 * (x * y + w * z);
+        result.data[2] = 2.0 * (x * z - w * y);
+
+        result.data[4] = 2.0 * (x * y - w * z);
+        result.data[5] = 1.0 - 2.0 * (x * x + z * z);
+        result.data[6] = 2.0 * (y * z + w * x);
+
+        result.data[8] = 2.0 * (x * z + w * y);
+        result.data[9] = 2.0 * (y * z - w * x);
+        result.data[10] = 1.0 - 2.0 * (x * x + y * y);
+
+        result
+    }
+}
+
+// Rotates by `other`, then by this
+impl Mul for Quat {
+    type Output = Quat;
+
+    fn mul(self, other: Quat) -> Quat {
+        Quat {
+            x: self.w * other.x + self.x * other.w + self.y * other.z - self.z * other.y,
+            y: self.w * other.y - self.x * other.z + self.y * other.w + self.z * other.x,
+            z: self.w * other.z + self.x * other.y - self.y * other.x + self.z * other.w,
+            w: self.w * other.w - self.x * other.x - self.y * other.y - self.z * other.z,
+        }
+    }
+}
+
+/// Where something is, how it's turned and how big it is, applied in the
+/// order scale, rotation, translation. Build matrices from these rather than
+/// by multiplying `Mat4::translate`, `rotate` and `scale` by hand.
+#[derive(Debug, Clone, Copy)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Transform {
+    /// Unrotated and at full size, at `translation`.
+    pub fn at(translation: Vec3) -> Self {
+        Self { translation, rotation: Quat::identity(), scale: Vec3::new(1.0, 1.0, 1.0) }
+    }
+
+    /// A camera at `position` looking at `target`, its top towards `up`, like
+    /// the view matrix of `Mat4::look_at` places it.
+    pub fn looking_at(position: Vec3, target: Vec3, up: Vec3) -> Self {
+        Mat4::look_at(position, target, up).decompose().inverse()
+    }
+
+    pub fn to_mat4(self) -> Mat4 {
+        Mat4::translate(self.translation) * self.rotation.to_mat4() * Mat4::scale(self.scale)
+    }
+
+    /// The transform that undoes this one, such as a camera's view. Exact
+    /// when the scale is the same along every axis, which other scales can't
+    /// be undone in this form.
+    pub fn inverse(self) -> Self {
+        let rotation = self.rotation.inverse();
+        let scale = Vec3::new(1.0 / self.scale.x, 1.0 / self.scale.y, 1.0 / self.scale.z);
+        let translation = rotation.rotate(self.translation * -1.0);
+        Self {
+            translation: Vec3::new(translation.x * scale.x, translation.y * scale.y, translation.z * scale.z),
+            rotation,
+            scale,
+        }
+    }
+}
+
+/// What a camera can see, as six planes facing inwards, for telling whether a
+/// box could be on screen.
+#[derive(Debug, Clone, Copy)]
+pub struct Frustum {
+    // Normal and distance of each plane: a point is on the inside when its dot
+    // product with the normal plus the distance is positive
+    planes: [(Vec3, f32); 6],
+}
+
+impl Frustum {
+    /// Takes the planes from a view-projection matrix, by adding and
+    /// subtracting its rows (Gribb and Hartmann's method).
+    pub fn from_matrix(matrix: &Mat4) -> Self {
+        let d = &matrix.data;
+        let row = |i: usize| [d[i], d[4 + i], d[8 + i], d[12 + i]];
+        let w = row(3);
+        let plane = |row: [f32; 4], sign: f32| {
+            let normal = Vec3::new(w[0] + sign * row[0], w[1] + sign * row[1], w[2] + sign * row[2]);
+            let length = normal.length();
+            (normal * (1.0 / length), (w[3] + sign * row[3]) / length)
+        };
+        Self {
+            planes: [
+                plane(row(0), 1.0),
+                plane(row(0), -1.0),
+                plane(row(1), 1.0),
+                plane(row(1), -1.0),
+                plane(row(2), 1.0),
+                plane(row(2), -1.0),
+            ],
+        }
+    }
+
+    /// Whether any of a box could be inside. Some boxes just outside a corner
+    /// count as inside too, which is fine for culling.
+    pub fn intersects_aabb(&self, min: Vec3, max: Vec3) -> bool {
+        self.planes.iter().all(|(normal, distance)| {
+            // The corner furthest along the normal is the last to leave the inside
+            let corner = Vec3::new(
+                if normal.x >= 0.0 { max.x } else { min.x },
+                if normal.y >= 0.0 { max.y } else { min.y },
+                if normal.z >= 0.0 { max.z } else { min.z },
+            );
+            normal.dot(&corner) + distance >= 0.0
+        })
+    }
+
+    /// Whether any of each box could be inside, like `intersects_aabb`, for
+    /// testing many boxes at once. With the `simd` feature on x86_64, four
+    /// boxes are tested at a time.
+    pub fn intersects_aabbs(&self, boxes: &[(Vec3, Vec3)]) -> Vec<bool> {
+        let mut visible = Vec::with_capacity(boxes.len());
+        #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+        let boxes = simd::intersects_aabbs(&self.planes, boxes, &mut visible);
+        visible.extend(boxes.iter().map(|&(min, max)| self.intersects_aabb(min, max)));
+        visible
+    }
+}
+
+/// Right and up vectors of a quad facing a viewer who looks along `direction`.
+/// Straight up or down, where the world's up is no help, the X axis is right.
+pub fn billboard_axes(direction: Vec3) -> (Vec3, Vec3) {
+    let forward = direction.normalize();
+    let mut right = forward.cross(&Vec3::new(0.0, 1.0, 0.0));
+    if right.length() < 1e-4 {
+        right = Vec3::new(1.0, 0.0, 0.0);
+    }
+    let right = right.normalize();
+    (right, right.cross(&forward))
+}
+
+/// Corners of a square billboard of side `size` centered on `center`, counter-
+/// clockwise from the bottom left as seen by the viewer.
+pub fn billboard_corners(center: Vec3, right: Vec3, up: Vec3, size: f32) -> [Vec3; 4] {
+    let (right, up) = (right * (size / 2.0), up * (size / 2.0));
+    [
+        center - right - up,
+        center + right - up,
+        center + right + up,
+        center - right + up,
+    ]
 }
 
 impl Mul for Mat4 {
     type Output = Mat4;
 
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    fn mul(self, other: Mat4) -> Mat4 {
+        Mat4 { data: simd::mul_mat4(&self.data, &other.data) }
+    }
+
+    #[cfg(not(all(feature = "simd", target_arch = "x86_64")))]
     fn mul(self, other: Mat4) -> Mat4 {
         let mut result = Mat4::identity();
         for i in 0..4 {
@@ -207,14 +509,32 @@ impl Mul for Mat4 {
     }
 }
 
+impl Mul<Vec4> for Mat4 {
+    type Output = Vec4;
+
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    fn mul(self, vec: Vec4) -> Vec4 {
+        let [x, y, z, w] = simd::mul_vec4(&self.data, [vec.x, vec.y, vec.z, vec.w]);
+        Vec4::new(x, y, z, w)
+    }
+
+    #[cfg(not(all(feature = "simd", target_arch = "x86_64")))]
+    fn mul(self, vec: Vec4) -> Vec4 {
+        let d = &self.data;
+        Vec4::new(
+            d[0] * vec.x + d[4] * vec.y + d[8] * vec.z + d[12] * vec.w,
+            d[1] * vec.x + d[5] * vec.y + d[9] * vec.z + d[13] * vec.w,
+            d[2] * vec.x + d[6] * vec.y + d[10] * vec.z + d[14] * vec.w,
+            d[3] * vec.x + d[7] * vec.y + d[11] * vec.z + d[15] * vec.w,
+        )
+    }
+}
+
 impl Mul<Vec3> for Mat4 {
     type Output = Vec3;
 
     fn mul(self, vec: Vec3) -> Vec3 {
-        let x = self.data[0] * vec.x + self.data[4] * vec.y + self.data[8] * vec.z + self.data[12];
-        let y = self.data[1] * vec.x + self.data[5] * vec.y + self.data[9] * vec.z + self.data[13];
-        let z = self.data[2] * vec.x + self.data[6] * vec.y + self.data[10] * vec.z + self.data[14];
-        let w = self.data[3] * vec.x + self.data[7] * vec.y + self.data[11] * vec.z + self.data[15];
+        let Vec4 { x, y, z, w } = self * Vec4::new(vec.x, vec.y, vec.z, 1.0);
 
         if w != 1.0 && w != 0.0 {
             Vec3::new(x / w, y / w, z / w)
@@ -224,3 +544,375 @@ impl Mul<Vec3> for Mat4 {
     }
 } 
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::Random;
+    use std::f32::consts::{FRAC_PI_2, PI};
+
+    const EPSILON: f32 = 1e-4;
+
+    fn assert_near(actual: Vec3, expected: Vec3) {
+        let error = (actual - expected).length();
+        assert!(error < EPSILON, "expected {:?}, got {:?}", expected, actual);
+    }
+
+    fn assert_matrix_near(actual: Mat4, expected: Mat4) {
+        let close = actual.data.iter().zip(expected.data).all(|(a, e)| (a - e).abs() < EPSILON * e.abs().max(1.0));
+        assert!(close, "expected {:?}, got {:?}", expected.data, actual.data);
+    }
+
+    // Gauss-Jordan elimination with partial pivoting, only needed to check the
+    // matrices the game builds can be undone
+    fn inverse(matrix: Mat4) -> Option<Mat4> {
+        let mut rows = [[0.0f64; 8]; 4];
+        for (i, row) in rows.iter_mut().enumerate() {
+            for (j, value) in row[..4].iter_mut().enumerate() {
+                *value = matrix.data[i + j * 4] as f64;
+            }
+            row[4 + i] = 1.0;
+        }
+        for column in 0..4 {
+            let pivot = (column..4).max_by(|&a, &b| rows[a][column].abs().total_cmp(&rows[b][column].abs()))?;
+            if rows[pivot][column].abs() < 1e-9 {
+                return None;
+            }
+            rows.swap(column, pivot);
+            let scale = rows[column][column];
+            rows[column].iter_mut().for_each(|value| *value /= scale);
+            for other in 0..4 {
+                if other != column {
+                    let factor = rows[other][column];
+                    let pivot_row = rows[column];
+                    rows[other].iter_mut().zip(pivot_row).for_each(|(value, pivot)| *value -= factor * pivot);
+                }
+            }
+        }
+        let mut data = [0.0; 16];
+        for (i, row) in rows.iter().enumerate() {
+            for j in 0..4 {
+                data[i + j * 4] = row[4 + j] as f32;
+            }
+        }
+        Some(Mat4::new(data))
+    }
+
+    // Random vectors and transforms for the property checks, seeded so they
+    // see the same values on every run
+    trait RandomShapes {
+        fn vector(&mut self, min: f32, max: f32) -> Vec3;
+        fn transform(&mut self) -> Mat4;
+    }
+
+    impl RandomShapes for Random {
+        fn vector(&mut self, min: f32, max: f32) -> Vec3 {
+            Vec3::new(self.range(min, max), self.range(min, max), self.range(min, max))
+        }
+
+        // A translation, rotation and scale, like the model matrices of entities
+        fn transform(&mut self) -> Mat4 {
+            let axis = self.vector(-1.0, 1.0) + Vec3::new(0.0, 0.01, 0.0);
+            Mat4::translate(self.vector(-100.0, 100.0))
+                * Mat4::rotate(self.range(-PI, PI), axis)
+                * Mat4::scale(self.vector(0.2, 5.0))
+        }
+    }
+
+    const CASES: usize = 200;
+
+    #[test]
+    fn multiplication_matches_reference_values() {
+        let a = Mat4::new(std::array::from_fn(|i| (i + 1) as f32));
+        let b = Mat4::new(std::array::from_fn(|i| (i + 17) as f32));
+        let expected = [
+            538.0, 612.0, 686.0, 760.0,
+            650.0, 740.0, 830.0, 920.0,
+            762.0, 868.0, 974.0, 1080.0,
+            874.0, 996.0, 1118.0, 1240.0,
+        ];
+        assert_eq!((a * b).to_array(), expected);
+        assert_eq!((a * Mat4::identity()).to_array(), a.to_array());
+        assert_eq!((Mat4::identity() * a).to_array(), a.to_array());
+    }
+
+    #[test]
+    fn matrices_apply_right_to_left() {
+        let point = Vec3::new(1.0, 0.0, 0.0);
+        let rotate = Mat4::rotate(FRAC_PI_2, Vec3::new(0.0, 0.0, 1.0));
+        let translate = Mat4::translate(Vec3::new(10.0, 0.0, 0.0));
+        assert_near(rotate * point, Vec3::new(0.0, 1.0, 0.0));
+        assert_near((translate * rotate) * point, Vec3::new(10.0, 1.0, 0.0));
+        assert_near((rotate * translate) * point, Vec3::new(0.0, 11.0, 0.0));
+        assert_near(Mat4::scale(Vec3::new(2.0, 3.0, 4.0)) * Vec3::new(1.0, 1.0, 1.0), Vec3::new(2.0, 3.0, 4.0));
+    }
+
+    #[test]
+    fn transform_vector_ignores_translation() {
+        let matrix = Mat4::translate(Vec3::new(5.0, 6.0, 7.0)) * Mat4::scale(Vec3::new(2.0, 2.0, 2.0));
+        assert_near(matrix.transform_vector(Vec3::new(1.0, 0.0, 0.0)), Vec3::new(2.0, 0.0, 0.0));
+        assert_near(matrix * Vec3::new(1.0, 0.0, 0.0), Vec3::new(7.0, 6.0, 7.0));
+    }
+
+    #[test]
+    fn vec4_products_tell_points_from_directions() {
+        let mut random = Random::new(0xf00d);
+        for _ in 0..CASES {
+            let matrix = random.transform();
+            let v = random.vector(-10.0, 10.0);
+            let point = matrix * Vec4::new(v.x, v.y, v.z, 1.0);
+            assert_near(Vec3::new(point.x, point.y, point.z), matrix * v);
+            assert_eq!(point.w, 1.0);
+            let direction = matrix * Vec4::new(v.x, v.y, v.z, 0.0);
+            assert_near(Vec3::new(direction.x, direction.y, direction.z), matrix.transform_vector(v));
+            assert_eq!(direction.w, 0.0);
+        }
+    }
+
+    #[test]
+    fn rotations_undo_each_other() {
+        let axis = Vec3::new(1.0, 2.0, -0.5);
+        let there = Mat4::rotate(1.2, axis);
+        let back = Mat4::rotate(-1.2, axis);
+        assert_matrix_near(there * back, Mat4::identity());
+        // Points on the axis stay put
+        assert_near(there * axis, axis);
+    }
+
+    #[test]
+    fn perspective_maps_near_and_far_to_the_clip_range() {
+        let (near, far) = (0.1, 100.0);
+        let fov = 70.0f32.to_radians();
+        let aspect = 16.0 / 9.0;
+        let projection = Mat4::perspective(fov, aspect, near, far);
+        assert!(((projection * Vec3::new(0.0, 0.0, -near)).z + 1.0).abs() < EPSILON);
+        assert!(((projection * Vec3::new(0.0, 0.0, -far)).z - 1.0).abs() < EPSILON);
+
+        // A point on the top edge of the field of view lands on the top of the screen
+        let depth = 10.0;
+        let half_height = depth * (fov / 2.0).tan();
+        let top_right = projection * Vec3::new(half_height * aspect, half_height, -depth);
+        assert!((top_right.x - 1.0).abs() < EPSILON && (top_right.y - 1.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn orthographic_maps_its_box_to_the_clip_cube() {
+        let projection = Mat4::orthographic(-4.0, 6.0, -2.0, 3.0, 1.0, 11.0);
+        assert_near(projection * Vec3::new(-4.0, -2.0, -1.0), Vec3::new(-1.0, -1.0, -1.0));
+        assert_near(projection * Vec3::new(6.0, 3.0, -11.0), Vec3::new(1.0, 1.0, 1.0));
+        assert_near(projection * Vec3::new(1.0, 0.5, -6.0), Vec3::zero());
+    }
+
+    #[test]
+    fn look_at_puts_the_eye_at_the_origin_looking_down_negative_z() {
+        let mut random = Random::new(0x5eed);
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        for _ in 0..CASES {
+            let eye = random.vector(-50.0, 50.0);
+            let target = eye + random.vector(-10.0, 10.0) + Vec3::new(0.5, 0.0, 0.0);
+            let view = Mat4::look_at(eye, target, up);
+            assert_near(view * eye, Vec3::zero());
+            let distance = (target - eye).length();
+            assert_near(view * target, Vec3::new(0.0, 0.0, -distance));
+
+            // Only rotates: axes stay unit length and square to each other
+            let x = view.transform_vector(Vec3::new(1.0, 0.0, 0.0));
+            let y = view.transform_vector(Vec3::new(0.0, 1.0, 0.0));
+            let z = view.transform_vector(Vec3::new(0.0, 0.0, 1.0));
+            for (a, b) in [(x, y), (y, z), (z, x)] {
+                assert!(a.dot(&b).abs() < EPSILON);
+                assert!((a.length() - 1.0).abs() < EPSILON);
+            }
+            // Up in the world is never down on screen
+            assert!((view.transform_vector(up)).y >= 0.0);
+        }
+    }
+
+    #[test]
+    fn normalizing_zero_gives_zero() {
+        let normalized = Vec3::zero().normalize();
+        assert!(!normalized.x.is_nan() && !normalized.y.is_nan() && !normalized.z.is_nan());
+        assert_near(normalized, Vec3::zero());
+        assert!((Vec3::new(3.0, -4.0, 12.0).normalize().length() - 1.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn cross_product_is_right_handed() {
+        let (x, y, z) = (Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        assert_near(x.cross(&y), z);
+        assert_near(y.cross(&z), x);
+        assert_near(z.cross(&x), y);
+        assert_near(y.cross(&x), z * -1.0);
+    }
+
+    #[test]
+    fn frustum_keeps_boxes_in_front_only() {
+        let projection = Mat4::perspective(FRAC_PI_2, 1.0, 0.1, 100.0);
+        let view = Mat4::look_at(Vec3::zero(), Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0));
+        let frustum = Frustum::from_matrix(&(projection * view));
+        let cube = |center: Vec3| (center - Vec3::new(0.5, 0.5, 0.5), center + Vec3::new(0.5, 0.5, 0.5));
+
+        let (min, max) = cube(Vec3::new(0.0, 0.0, -10.0));
+        assert!(frustum.intersects_aabb(min, max));
+        let (min, max) = cube(Vec3::new(0.0, 0.0, 10.0));
+        assert!(!frustum.intersects_aabb(min, max));
+        let (min, max) = cube(Vec3::new(0.0, 0.0, -200.0));
+        assert!(!frustum.intersects_aabb(min, max));
+        let (min, max) = cube(Vec3::new(30.0, 0.0, -10.0));
+        assert!(!frustum.intersects_aabb(min, max));
+        // Straddling the edge of the view still counts
+        let (min, max) = cube(Vec3::new(10.2, 0.0, -10.0));
+        assert!(frustum.intersects_aabb(min, max));
+    }
+
+    #[test]
+    fn batched_frustum_tests_match_single_ones() {
+        let mut random = Random::new(0xc011);
+        for _ in 0..20 {
+            let eye = random.vector(-50.0, 50.0);
+            let view = Mat4::look_at(eye, eye + random.vector(-1.0, 1.0), Vec3::new(0.0, 1.0, 0.0));
+            let frustum = Frustum::from_matrix(&(Mat4::perspective(1.2, 1.5, 0.1, 80.0) * view));
+            // Not a multiple of four, so some are left over after the groups
+            let boxes = (0..CASES + 3)
+                .map(|_| {
+                    let min = random.vector(-100.0, 100.0);
+                    (min, min + random.vector(0.0, 16.0))
+                })
+                .collect::<Vec<_>>();
+            let single = boxes.iter().map(|&(min, max)| frustum.intersects_aabb(min, max)).collect::<Vec<_>>();
+            assert_eq!(frustum.intersects_aabbs(&boxes), single);
+            assert!(single.contains(&true) && single.contains(&false));
+        }
+        assert!(Frustum::from_matrix(&Mat4::identity()).intersects_aabbs(&[]).is_empty());
+    }
+
+    #[test]
+    fn billboard_axes_are_orthonormal_in_every_direction() {
+        let mut random = Random::new(0xb111);
+        let directions = (0..CASES).map(|_| random.vector(-1.0, 1.0))
+            .chain([Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0)]);
+        for direction in directions {
+            let (right, up) = billboard_axes(direction);
+            let forward = direction.normalize();
+            assert!((right.length() - 1.0).abs() < EPSILON, "{:?}", direction);
+            assert!((up.length() - 1.0).abs() < EPSILON, "{:?}", direction);
+            assert!(right.dot(&up).abs() < EPSILON);
+            assert!(right.dot(&forward).abs() < EPSILON);
+            assert!(up.dot(&forward).abs() < EPSILON);
+        }
+    }
+
+    #[test]
+    fn inverse_undoes_transforms() {
+        let mut random = Random::new(0x1234_5678);
+        for _ in 0..CASES {
+            let matrix = random.transform();
+            let inverted = inverse(matrix).expect("transforms with a scale are invertible");
+            assert_matrix_near(inverted * matrix, Mat4::identity());
+            assert_matrix_near(matrix * inverted, Mat4::identity());
+
+            let point = random.vector(-10.0, 10.0);
+            let error = (inverted * (matrix * point) - point).length();
+            assert!(error < 1e-3, "{:?} came back {} away", point, error);
+        }
+        assert!(inverse(Mat4::scale(Vec3::new(1.0, 0.0, 1.0))).is_none());
+    }
+
+    #[test]
+    fn quaternions_rotate_like_matrices() {
+        let mut random = Random::new(0x9a7);
+        for _ in 0..CASES {
+            let axis = random.vector(-1.0, 1.0) + Vec3::new(0.0, 0.01, 0.0);
+            let angle = random.range(-PI, PI);
+            let rotation = Quat::from_axis_angle(axis, angle);
+            assert_matrix_near(rotation.to_mat4(), Mat4::rotate(angle, axis));
+
+            let v = random.vector(-10.0, 10.0);
+            assert_near(rotation.rotate(v), Mat4::rotate(angle, axis) * v);
+            assert_near(rotation.inverse().rotate(rotation.rotate(v)), v);
+
+            let other = Quat::from_axis_angle(random.vector(-1.0, 1.0), random.range(-PI, PI));
+            assert_matrix_near((rotation * other).to_mat4(), rotation.to_mat4() * other.to_mat4());
+        }
+    }
+
+    #[test]
+    fn decompose_undoes_to_mat4() {
+        let mut random = Random::new(0xdec0);
+        for _ in 0..CASES {
+            let transform = Transform {
+                translation: random.vector(-100.0, 100.0),
+                rotation: Quat::from_axis_angle(random.vector(-1.0, 1.0), random.range(-PI, PI)),
+                scale: random.vector(0.2, 5.0),
+            };
+            let matrix = transform.to_mat4();
+            let decomposed = matrix.decompose();
+            assert_near(decomposed.translation, transform.translation);
+            assert_near(decomposed.scale, transform.scale);
+            assert_matrix_near(decomposed.to_mat4(), matrix);
+            // Opposite quaternions are the same rotation
+            let dot = decomposed.rotation.x * transform.rotation.x
+                + decomposed.rotation.y * transform.rotation.y
+                + decomposed.rotation.z * transform.rotation.z
+                + decomposed.rotation.w * transform.rotation.w;
+            assert!((dot.abs() - 1.0).abs() < EPSILON);
+
+            // Matrices built by hand come apart the same way
+            let by_hand = random.transform();
+            assert_matrix_near(by_hand.decompose().to_mat4(), by_hand);
+        }
+    }
+
+    #[test]
+    fn decompose_keeps_mirroring_in_the_scale() {
+        let mirrored = Mat4::rotate(0.7, Vec3::new(0.0, 1.0, 0.0)) * Mat4::scale(Vec3::new(-2.0, 3.0, 4.0));
+        let decomposed = mirrored.decompose();
+        assert_near(decomposed.scale, Vec3::new(-2.0, 3.0, 4.0));
+        assert_matrix_near(decomposed.to_mat4(), mirrored);
+        assert_matrix_near(Mat4::identity().decompose().to_mat4(), Mat4::identity());
+    }
+
+    #[test]
+    fn inverse_transforms_undo_uniformly_scaled_ones() {
+        let mut random = Random::new(0x1e55);
+        for _ in 0..CASES {
+            let size = random.range(0.2, 5.0);
+            let transform = Transform {
+                translation: random.vector(-100.0, 100.0),
+                rotation: Quat::from_axis_angle(random.vector(-1.0, 1.0), random.range(-PI, PI)),
+                scale: Vec3::new(size, size, size),
+            };
+            assert_matrix_near(transform.inverse().to_mat4() * transform.to_mat4(), Mat4::identity());
+        }
+    }
+
+    #[test]
+    fn looking_at_places_the_camera_of_look_at() {
+        let mut random = Random::new(0x100c);
+        for _ in 0..CASES {
+            let eye = random.vector(-50.0, 50.0);
+            let target = eye + random.vector(-10.0, 10.0) + Vec3::new(0.5, 0.0, 0.0);
+            let camera = Transform::looking_at(eye, target, Vec3::new(0.0, 1.0, 0.0));
+            assert_near(camera.translation, eye);
+            assert_near(camera.rotation.rotate(Vec3::new(0.0, 0.0, -1.0)), (target - eye).normalize());
+            assert_matrix_near(camera.inverse().to_mat4(), Mat4::look_at(eye, target, Vec3::new(0.0, 1.0, 0.0)));
+        }
+    }
+
+    #[test]
+    fn multiplication_is_associative() {
+        let mut random = Random::new(0xace);
+        for _ in 0..CASES {
+            let (a, b, c) = (random.transform(), random.transform(), random.transform());
+            let left = ((a * b) * c).to_array();
+            let right = (a * (b * c)).to_array();
+            let scale = left.iter().fold(1.0f32, |max, value| max.max(value.abs()));
+            assert!(left.iter().zip(right).all(|(l, r)| (l - r).abs() < 1e-4 * scale), "{:?} != {:?}", left, right);
+
+            let point = random.vector(-10.0, 10.0);
+            let combined = (a * b) * point;
+            let error = (combined - a * (b * point)).length();
+            assert!(error < 1e-4 * combined.length().max(1.0));
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use crate::renderer::Renderer;
+use crate::world::World;
+
+const MIB: f32 = 1024.0 * 1024.0;
+
+/// Memory taken by the world's chunks, by where it's kept. Only the data
+/// itself is counted, so the allocator's and driver's overhead comes on top.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MemoryUsage {
+    /// Blocks of the loaded chunks, and of those kept from saves and edits.
+    pub blocks: usize,
+    /// CPU copies of the chunk meshes, with the spare ones.
+    pub meshes: usize,
+    /// Chunk meshes on the GPU, estimated from their sizes.
+    pub gpu: usize,
+}
+
+impl MemoryUsage {
+    pub fn measure(world: &World, renderer: &dyn Renderer) -> Self {
+        Self {
+            blocks: world.block_bytes(),
+            meshes: world.mesh_bytes(),
+            gpu: renderer.gpu_bytes(),
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.blocks + self.meshes + self.gpu
+    }
+
+    /// Describes the usage in MiB for the debug overlay, against the budget
+    /// in MiB if there is one.
+    pub fn describe(&self, budget: u32) -> String {
+        let mib = |bytes: usize| bytes as f32 / MIB;
+        let usage = format!(
+            "Memory: {:.1} MiB (blocks {:.1}, meshes {:.1}, GPU {:.1})",
+            mib(self.total()),
+            mib(self.blocks),
+            mib(self.meshes),
+            mib(self.gpu),
+        );
+        match budget {
+            0 => usage,
+            budget => format!("{} of {} MiB", usage, budget),
+        }
+    }
+}
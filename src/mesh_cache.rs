@@ -0,0 +1,176 @@
+use crate::ao_bake::BakedOcclusion;
+use crate::byte_reader::ByteReader;
+use crate::mesh_pool::MeshPool;
+use crate::mesher::ChunkMesh;
+use crate::{TriIndexes, Vertex};
+use std::fs;
+use std::path::PathBuf;
+
+// Identifies a mesh cache file
+const MAGIC: &[u8; 4] = b"CMSH";
+// Bump whenever the mesher or vertex layout changes so stale meshes are rebuilt
+const VERSION: u32 = 6;
+const HEADER_SIZE: usize = 4 + 4 + 8 + 12 + 4 + 4 + 4 + 4;
+// Identifies a file of ambient occlusion baked for a cached mesh. Bakes carry
+// the mesh version too, so they go stale along with the meshes.
+const OCCLUSION_MAGIC: &[u8; 4] = b"CAOB";
+
+/// On-disk cache of chunk meshes.
+///
+/// Each mesh is stored in its own file named after the world seed and chunk
+/// position, and tagged with the hash of the blocks it was built from, so a
+/// chunk whose blocks changed is simply meshed again and overwritten.
+pub struct MeshCache {
+    // None when caching is disabled
+    directory: Option<PathBuf>,
+}
+
+impl MeshCache {
+    /// Uses the given directory to store meshes, creating it on the first write.
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self { directory: Some(directory.into()) }
+    }
+
+    /// A cache that never has anything, so every chunk is meshed.
+    pub fn disabled() -> Self {
+        Self { directory: None }
+    }
+
+    /// Loads the cached mesh of a chunk if one was built from the same blocks.
+    pub fn load(&self, seed: u32, position: (i32, i32, i32), block_hash: u64, pool: &MeshPool) -> Option<ChunkMesh> {
+        let data = fs::read(self.path(seed, position)?).ok()?;
+        decode(&data, block_hash, pool)
+    }
+
+    /// Stores a freshly built chunk mesh. Failing to write only costs a remesh
+    /// next time, so errors are reported and otherwise ignored.
+    pub fn store(&self, seed: u32, position: (i32, i32, i32), block_hash: u64, mesh: &ChunkMesh) {
+        let (Some(directory), Some(path)) = (&self.directory, self.path(seed, position)) else {
+            return;
+        };
+        let result = fs::create_dir_all(directory)
+            .and_then(|_| fs::write(path, encode(mesh, block_hash)));
+        if let Err(e) = result {
+            log::warn!(target: "meshing", "Failed to cache mesh of chunk {:?}: {}", position, e);
+        }
+    }
+
+    /// Loads the ambient occlusion baked for a chunk's mesh if it was baked
+    /// with the same key (see `ao_bake::bake_key`) for as many vertices.
+    pub fn load_occlusion(&self, seed: u32, position: (i32, i32, i32), key: u64, vertex_count: usize) -> Option<BakedOcclusion> {
+        let data = fs::read(self.occlusion_path(seed, position)?).ok()?;
+        decode_occlusion(&data, key, vertex_count)
+    }
+
+    /// Stores the ambient occlusion baked for a chunk's mesh next to the mesh.
+    pub fn store_occlusion(&self, seed: u32, position: (i32, i32, i32), key: u64, baked: &BakedOcclusion) {
+        let (Some(directory), Some(path)) = (&self.directory, self.occlusion_path(seed, position)) else {
+            return;
+        };
+        let result = fs::create_dir_all(directory)
+            .and_then(|_| fs::write(path, encode_occlusion(baked, key)));
+        if let Err(e) = result {
+            log::warn!(target: "meshing", "Failed to store the ambient occlusion of chunk {:?}: {}", position, e);
+        }
+    }
+
+    fn path(&self, seed: u32, position: (i32, i32, i32)) -> Option<PathBuf> {
+        let name = format!("{}_{}_{}_{}.mesh", seed, position.0, position.1, position.2);
+        Some(self.directory.as_ref()?.join(name))
+    }
+
+    fn occlusion_path(&self, seed: u32, position: (i32, i32, i32)) -> Option<PathBuf> {
+        Some(self.path(seed, position)?.with_extension("ao"))
+    }
+}
+
+fn encode(mesh: &ChunkMesh, block_hash: u64) -> Vec<u8> {
+    let vertex_bytes: &[u8] = bytemuck::cast_slice(&mesh.vertices);
+    let index_bytes: &[u8] = bytemuck::cast_slice(&mesh.indices);
+    let water_index_bytes: &[u8] = bytemuck::cast_slice(&mesh.water_indices);
+    let light_bytes: &[u8] = bytemuck::cast_slice(&mesh.lights);
+
+    let mut data = Vec::with_capacity(HEADER_SIZE + vertex_bytes.len() + index_bytes.len() + water_index_bytes.len() + light_bytes.len());
+    data.extend_from_slice(MAGIC);
+    data.extend_from_slice(&VERSION.to_le_bytes());
+    data.extend_from_slice(&block_hash.to_le_bytes());
+    for coordinate in mesh.origin {
+        data.extend_from_slice(&coordinate.to_le_bytes());
+    }
+    data.extend_from_slice(&(mesh.vertices.len() as u32).to_le_bytes());
+    data.extend_from_slice(&(mesh.indices.len() as u32).to_le_bytes());
+    data.extend_from_slice(&(mesh.water_indices.len() as u32).to_le_bytes());
+    data.extend_from_slice(&(mesh.lights.len() as u32).to_le_bytes());
+    data.extend_from_slice(vertex_bytes);
+    data.extend_from_slice(index_bytes);
+    data.extend_from_slice(water_index_bytes);
+    data.extend_from_slice(light_bytes);
+    data
+}
+
+fn decode(data: &[u8], block_hash: u64, pool: &MeshPool) -> Option<ChunkMesh> {
+    let mut reader = ByteReader::new(data);
+    if reader.take(4)? != MAGIC
+        || reader.u32()? != VERSION
+        || reader.u64()? != block_hash
+    {
+        return None;
+    }
+    let origin = [reader.f32()?, reader.f32()?, reader.f32()?];
+    let vertex_count = reader.u32()? as usize;
+    let index_count = reader.u32()? as usize;
+    let water_index_count = reader.u32()? as usize;
+    let light_count = reader.u32()? as usize;
+
+    // The payload may not be aligned for f32/u32, so copy it out
+    let vertex_bytes = reader.take(vertex_count * std::mem::size_of::<Vertex>())?;
+    let index_bytes = reader.take(index_count * std::mem::size_of::<TriIndexes>())?;
+    let water_index_bytes = reader.take(water_index_count * std::mem::size_of::<TriIndexes>())?;
+    let light_bytes = reader.take(light_count * std::mem::size_of::<[i32; 3]>())?;
+    let mut mesh = pool.take();
+    mesh.vertices.resize(vertex_count, [0; 2]);
+    mesh.indices.resize(index_count, [0; 3]);
+    mesh.water_indices.resize(water_index_count, [0; 3]);
+    mesh.lights.resize(light_count, [0; 3]);
+    bytemuck::cast_slice_mut(&mut mesh.vertices).copy_from_slice(vertex_bytes);
+    bytemuck::cast_slice_mut(&mut mesh.indices).copy_from_slice(index_bytes);
+    bytemuck::cast_slice_mut(&mut mesh.water_indices).copy_from_slice(water_index_bytes);
+    bytemuck::cast_slice_mut(&mut mesh.lights).copy_from_slice(light_bytes);
+    mesh.origin = origin;
+    Some(mesh)
+}
+
+fn encode_occlusion(baked: &BakedOcclusion, key: u64) -> Vec<u8> {
+    let visibility_bytes: &[u8] = bytemuck::cast_slice(&baked.visibility);
+    let normal_bytes: &[u8] = bytemuck::cast_slice(&baked.bent_normals);
+
+    let mut data = Vec::with_capacity(4 + 4 + 8 + 4 + visibility_bytes.len() + normal_bytes.len());
+    data.extend_from_slice(OCCLUSION_MAGIC);
+    data.extend_from_slice(&VERSION.to_le_bytes());
+    data.extend_from_slice(&key.to_le_bytes());
+    data.extend_from_slice(&(baked.visibility.len() as u32).to_le_bytes());
+    data.extend_from_slice(visibility_bytes);
+    data.extend_from_slice(normal_bytes);
+    data
+}
+
+fn decode_occlusion(data: &[u8], key: u64, vertex_count: usize) -> Option<BakedOcclusion> {
+    let mut reader = ByteReader::new(data);
+    if reader.take(4)? != OCCLUSION_MAGIC
+        || reader.u32()? != VERSION
+        || reader.u64()? != key
+        || reader.u32()? as usize != vertex_count
+    {
+        return None;
+    }
+    let visibility_bytes = reader.take(vertex_count * std::mem::size_of::<f32>())?;
+    let normal_bytes = reader.take(vertex_count * std::mem::size_of::<[f32; 3]>())?;
+    let mut baked = BakedOcclusion {
+        visibility: vec![0.0; vertex_count],
+        bent_normals: vec![[0.0; 3]; vertex_count],
+    };
+    bytemuck::cast_slice_mut(&mut baked.visibility).copy_from_slice(visibility_bytes);
+    bytemuck::cast_slice_mut(&mut baked.bent_normals).copy_from_slice(normal_bytes);
+    Some(baked)
+}
+
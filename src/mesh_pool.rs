@@ -0,0 +1,51 @@
+use crate::mesher::ChunkMesh;
+use std::sync::Mutex;
+
+// Spare meshes kept at most; beyond this the memory is given back
+const MAX_SPARE_MESHES: usize = 256;
+
+/// Meshes of chunks that were remeshed or unloaded, kept with their buffers'
+/// capacity and handed out again to build new meshes in. Streaming rebuilds
+/// meshes constantly, and growing fresh vertex and index lists from nothing
+/// for each one keeps the allocator busy for no reason.
+///
+/// Safe to share between meshing threads.
+#[derive(Default)]
+pub struct MeshPool {
+    spare: Mutex<Vec<ChunkMesh>>,
+}
+
+impl MeshPool {
+    /// An empty mesh, with room left over from an earlier one when there is one.
+    pub fn take(&self) -> ChunkMesh {
+        self.spare.lock().unwrap().pop().unwrap_or_default()
+    }
+
+    /// Bytes of memory held by the spare meshes.
+    pub fn spare_bytes(&self) -> usize {
+        self.spare.lock().unwrap().iter().map(ChunkMesh::heap_bytes).sum()
+    }
+
+    /// Gives back the memory of every spare mesh.
+    pub fn clear(&self) {
+        self.spare.lock().unwrap().clear();
+    }
+
+    /// Takes back a mesh that is no longer used, because a newer one replaced
+    /// it or its chunk was unloaded.
+    pub fn recycle(&self, mut mesh: ChunkMesh) {
+        if mesh.vertices.capacity() == 0 {
+            return;
+        }
+        mesh.vertices.clear();
+        mesh.indices.clear();
+        mesh.water_indices.clear();
+        mesh.lights.clear();
+        mesh.origin = [0.0; 3];
+
+        let mut spare = self.spare.lock().unwrap();
+        if spare.len() < MAX_SPARE_MESHES {
+            spare.push(mesh);
+        }
+    }
+}
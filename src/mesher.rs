@@ -0,0 +1,271 @@
+use crate::block::{BlockShape, BlockTexture, BlockType, Face, FaceTextures};
+use crate::mesh_pool::MeshPool;
+use crate::world::{ChunkNeighborhood, CHUNK_SIZE};
+use crate::{TriIndexes, Vertex};
+
+// Chunk vertex positions are stored in steps of 1/32 block, shifted up one
+// block so the slightly negative corners of the first blocks stay positive.
+// Texture coordinates use the same steps. Keep in sync with block.vert.
+const POSITION_STEPS: f32 = 32.0;
+const POSITION_OFFSET: f32 = 1.0;
+const TEX_COORD_STEPS: f32 = 32.0;
+
+/// CPU-side mesh data of one chunk.
+#[derive(Default)]
+pub struct ChunkMesh {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<TriIndexes>,
+    /// Triangles of fluid faces, drawn after every chunk's opaque geometry so
+    /// they blend over it.
+    pub water_indices: Vec<TriIndexes>,
+    /// World positions of the glowing blocks with at least one visible face.
+    pub lights: Vec<[i32; 3]>,
+    /// World position of the chunk's first block. Vertex positions are
+    /// relative to it, so they stay small and precise however far the chunk
+    /// is from the world origin; the chunk's model matrix moves them back.
+    pub origin: [f32; 3],
+}
+
+impl ChunkMesh {
+    /// Bytes of memory its lists hold, including room not filled yet.
+    pub fn heap_bytes(&self) -> usize {
+        self.vertices.capacity() * std::mem::size_of::<Vertex>()
+            + (self.indices.capacity() + self.water_indices.capacity()) * std::mem::size_of::<TriIndexes>()
+            + self.lights.capacity() * std::mem::size_of::<[i32; 3]>()
+    }
+}
+
+/// Builds the mesh of the center chunk of a neighbourhood, in a mesh taken
+/// from the pool.
+pub fn build_chunk_mesh(chunk: &ChunkNeighborhood, pool: &MeshPool) -> ChunkMesh {
+    let origin = chunk.origin();
+    let mut mesh = pool.take();
+    mesh.origin = [origin.0 as f32, origin.1 as f32, origin.2 as f32];
+
+    for x in 0..CHUNK_SIZE as i32 {
+        for y in 0..CHUNK_SIZE as i32 {
+            for z in 0..CHUNK_SIZE as i32 {
+                let block_type = chunk.get_block(x, y, z);
+                if block_type == BlockType::Air {
+                    continue;
+                }
+
+                // Skip blocks that are hidden on every side
+                if !Face::ALL.iter().any(|&face| should_render_face(chunk, x, y, z, face)) {
+                    continue;
+                }
+
+                if block_type.info().light.is_some() {
+                    let (world_x, world_y, world_z) = world_position(chunk, x, y, z);
+                    mesh.lights.push([world_x, world_y, world_z]);
+                }
+
+                let vertex_offset = mesh.vertices.len() as u32;
+                generate_block_vertices(&mut mesh.vertices, chunk, x, y, z, block_type);
+                let vertex_count = mesh.vertices.len() as u32 - vertex_offset;
+                let indices = match block_type.info().shape {
+                    BlockShape::Fluid => &mut mesh.water_indices,
+                    _ => &mut mesh.indices,
+                };
+                push_quad_indices(indices, vertex_offset, vertex_count);
+            }
+        }
+    }
+    mesh
+}
+
+/// Whether a face of the block at a chunk-local position shows, rather than
+/// being hidden by the neighbour on that side.
+pub fn should_render_face(chunk: &ChunkNeighborhood, x: i32, y: i32, z: i32, face: Face) -> bool {
+    let (dx, dy, dz) = face.offset();
+    let neighbor = chunk.get_block(x + dx, y + dy, z + dz);
+    !chunk.get_block(x, y, z).cull_against(face, neighbor, chunk.fancy_leaves())
+}
+
+/// Appends the vertices of the block at a chunk-local position, made by the
+/// mesh generator matching its shape in the block registry. Vertex positions
+/// are chunk-local too.
+pub fn generate_block_vertices(vertices: &mut Vec<Vertex>, chunk: &ChunkNeighborhood, x: i32, y: i32, z: i32, block_type: BlockType) {
+    let info = block_type.info();
+    let mut textures = info.textures;
+
+    // Grass under a snow layer uses the snowy side texture
+    if block_type == BlockType::Grass && chunk.get_block(x, y + 1, z) == BlockType::Snow {
+        textures.side = BlockTexture::GrassSnow;
+    }
+
+    match info.shape {
+        BlockShape::Empty => {},
+        BlockShape::Cube | BlockShape::CutoutCube => {
+            generate_box_vertices(vertices, chunk, x, y, z,
+                [0.0, 0.0, 0.0], [1.0, 1.0, 1.0], textures);
+        },
+        BlockShape::Slab { height } => {
+            generate_box_vertices(vertices, chunk, x, y, z,
+                [0.0, 0.0, 0.0], [1.0, height, 1.0], textures);
+        },
+        BlockShape::Stairs => {
+            // Bottom half plus a step on the back half
+            generate_box_vertices(vertices, chunk, x, y, z,
+                [0.0, 0.0, 0.0], [1.0, 0.5, 1.0], textures);
+            generate_box_vertices(vertices, chunk, x, y, z,
+                [0.0, 0.5, 0.0], [1.0, 1.0, 0.5], textures);
+        },
+        BlockShape::Cross => {
+            generate_cross_vertices(vertices, x, y, z, textures.side);
+        },
+        BlockShape::Fluid => {
+            generate_fluid_vertices(vertices, chunk, x, y, z, textures.top);
+        },
+    }
+}
+
+// Converts chunk-local block coordinates to world coordinates
+fn world_position(chunk: &ChunkNeighborhood, x: i32, y: i32, z: i32) -> (i32, i32, i32) {
+    let origin = chunk.origin();
+    (origin.0 + x, origin.1 + y, origin.2 + z)
+}
+
+/// Packs a chunk vertex into 8 bytes. The first word holds the chunk-local
+/// position, 10 bits per axis; the second the texture coordinates, 6 bits
+/// each, followed by the texture layer.
+pub fn pack_vertex(position: [f32; 3], tex_coord: [f32; 2], layer: u32) -> Vertex {
+    let [x, y, z] = position.map(|v| ((v + POSITION_OFFSET) * POSITION_STEPS).round().clamp(0.0, 1023.0) as u32);
+    let [s, t] = tex_coord.map(|v| (v * TEX_COORD_STEPS).round().clamp(0.0, 63.0) as u32);
+    [x | y << 10 | z << 20, s | t << 6 | layer << 12]
+}
+
+/// The chunk-local position, texture coordinates and texture layer of a
+/// packed vertex.
+pub fn unpack_vertex([position, texture]: Vertex) -> ([f32; 3], [f32; 2], u32) {
+    let position = [0, 10, 20].map(|shift| (position >> shift & 1023) as f32 / POSITION_STEPS - POSITION_OFFSET);
+    let tex_coord = [0, 6].map(|shift| (texture >> shift & 63) as f32 / TEX_COORD_STEPS);
+    (position, tex_coord, texture >> 12)
+}
+
+// Appends a quad given its corners in counter-clockwise order, as seen from the front
+fn push_quad(vertices: &mut Vec<Vertex>, corners: [[f32; 3]; 4], tex_coords: [[f32; 2]; 4], texture: BlockTexture) {
+    for (&corner, &tex_coord) in corners.iter().zip(tex_coords.iter()) {
+        vertices.push(pack_vertex(corner, tex_coord, texture as u32));
+    }
+}
+
+// Generates an axis-aligned box inside the block cell. `min` and `max` are local
+// coordinates in the 0..1 range; faces on the cell boundary are culled against
+// the neighbouring block, inner faces are always emitted.
+#[allow(clippy::too_many_arguments)]
+fn generate_box_vertices(vertices: &mut Vec<Vertex>, chunk: &ChunkNeighborhood, x: i32, y: i32, z: i32,
+    min: [f32; 3], max: [f32; 3], textures: FaceTextures) {
+    let visible = |on_boundary: bool, face: Face| {
+        !on_boundary || should_render_face(chunk, x, y, z, face)
+    };
+    let (x, y, z) = (x as f32, y as f32, z as f32);
+
+    // Corner coordinates in chunk space
+    let (x0, y0, z0) = (x - 0.5 + min[0], y - 0.5 + min[1], z - 0.5 + min[2]);
+    let (x1, y1, z1) = (x - 0.5 + max[0], y - 0.5 + max[1], z - 0.5 + max[2]);
+
+    // Texture coordinates follow the local position so partial boxes show the matching part of the texture
+    let (s0, s1) = (min[0], max[0]);
+    let (t0, t1) = (1.0 - min[1], 1.0 - max[1]);
+    let (u0, u1) = (min[2], max[2]);
+
+    // Front face
+    if visible(max[2] == 1.0, Face::Front) {
+        push_quad(vertices,
+            [[x0, y0, z1], [x1, y0, z1], [x1, y1, z1], [x0, y1, z1]],
+            [[s0, t0], [s1, t0], [s1, t1], [s0, t1]],
+            textures.side);
+    }
+
+    // Back face
+    if visible(min[2] == 0.0, Face::Back) {
+        push_quad(vertices,
+            [[x0, y0, z0], [x0, y1, z0], [x1, y1, z0], [x1, y0, z0]],
+            [[1.0 - s0, t0], [1.0 - s0, t1], [1.0 - s1, t1], [1.0 - s1, t0]],
+            textures.side);
+    }
+
+    // Top face
+    if visible(max[1] == 1.0, Face::Top) {
+        push_quad(vertices,
+            [[x0, y1, z0], [x0, y1, z1], [x1, y1, z1], [x1, y1, z0]],
+            [[u0, s0], [u1, s0], [u1, s1], [u0, s1]],
+            textures.top);
+    }
+
+    // Bottom face
+    if visible(min[1] == 0.0, Face::Bottom) {
+        push_quad(vertices,
+            [[x0, y0, z0], [x1, y0, z0], [x1, y0, z1], [x0, y0, z1]],
+            [[s0, u0], [s1, u0], [s1, u1], [s0, u1]],
+            textures.bottom);
+    }
+
+    // Right face
+    if visible(max[0] == 1.0, Face::Right) {
+        push_quad(vertices,
+            [[x1, y0, z0], [x1, y1, z0], [x1, y1, z1], [x1, y0, z1]],
+            [[u0, t0], [u0, t1], [u1, t1], [u1, t0]],
+            textures.side);
+    }
+
+    // Left face
+    if visible(min[0] == 0.0, Face::Left) {
+        push_quad(vertices,
+            [[x0, y0, z0], [x0, y0, z1], [x0, y1, z1], [x0, y1, z0]],
+            [[1.0 - u0, t0], [1.0 - u1, t0], [1.0 - u1, t1], [1.0 - u0, t1]],
+            textures.side);
+    }
+}
+
+// Generates two crossing diagonal quads, each emitted in both windings so they
+// survive back-face culling from either side
+fn generate_cross_vertices(vertices: &mut Vec<Vertex>, x: i32, y: i32, z: i32, texture: BlockTexture) {
+    let (x, y, z) = (x as f32, y as f32, z as f32);
+    let tex_coords = [[0.0, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0]];
+    let diagonals = [
+        [[x - 0.5, z - 0.5], [x + 0.5, z + 0.5]],
+        [[x - 0.5, z + 0.5], [x + 0.5, z - 0.5]],
+    ];
+
+    for [start, end] in diagonals {
+        push_quad(vertices,
+            [[start[0], y - 0.5, start[1]], [end[0], y - 0.5, end[1]],
+             [end[0], y + 0.5, end[1]], [start[0], y + 0.5, start[1]]],
+            tex_coords,
+            texture);
+        push_quad(vertices,
+            [[end[0], y - 0.5, end[1]], [start[0], y - 0.5, start[1]],
+             [start[0], y + 0.5, start[1]], [end[0], y + 0.5, end[1]]],
+            tex_coords,
+            texture);
+    }
+}
+
+// Generates the surface of a fluid, slightly lower than a full block
+fn generate_fluid_vertices(vertices: &mut Vec<Vertex>, chunk: &ChunkNeighborhood, x: i32, y: i32, z: i32, texture: BlockTexture) {
+    if should_render_face(chunk, x, y, z, Face::Top) {
+        let (x, y, z) = (x as f32, y as f32 + 0.4, z as f32);
+        push_quad(vertices,
+            [[x - 0.5, y, z - 0.5], [x - 0.5, y, z + 0.5], [x + 0.5, y, z + 0.5], [x + 0.5, y, z - 0.5]],
+            [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]],
+            texture);
+    }
+}
+
+// Appends two triangles for each quad among the given vertices
+fn push_quad_indices(indices: &mut Vec<TriIndexes>, vertex_offset: u32, vertex_count: u32) {
+    for i in (0..vertex_count).step_by(4) {
+        indices.push([
+            vertex_offset + i,
+            vertex_offset + i + 1,
+            vertex_offset + i + 2,
+        ]);
+        indices.push([
+            vertex_offset + i + 2,
+            vertex_offset + i + 3,
+            vertex_offset + i,
+        ]);
+    }
+}
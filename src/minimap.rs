@@ -0,0 +1,362 @@
+use crate::atomic_file;
+use crate::gl_utils::{buffer_data, Buffer, BufferType, GlState, ShaderProgram, VertexArray};
+use crate::math::Vec3;
+use crate::world::{World, CHUNK_SIZE, SEA_LEVEL};
+use crate::world_io::SaveJob;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use gl::types::GLuint;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+/// Texture unit the minimap's image is bound to while it is drawn.
+pub const MINIMAP_TEXTURE_UNIT: u32 = 4;
+/// Columns along each side of a map tile, the squares the map is saved in.
+pub const TILE_COLUMNS: i32 = 8;
+// Identifies a saved map tile
+const TILE_MAGIC: &[u8; 4] = b"MAPT";
+const TILE_VERSION: u32 = 1;
+const COLUMN_BLOCKS: usize = CHUNK_SIZE * CHUNK_SIZE;
+// Texels across the minimap's image, and its size and distance from the top
+// right corner of the screen in pixels
+const MAP_TEXELS: usize = 128;
+const MAP_SIZE: f32 = 160.0;
+const MAP_MARGIN: f32 = 12.0;
+// Blocks per texel at each zoom level, the first one shown at start
+const ZOOM_LEVELS: [f32; 2] = [1.0, 0.5];
+// Brightness of a block higher or lower than the one north of it, which makes
+// slopes facing north dark and the others light
+const SLOPE_LIGHT: f32 = 1.1;
+const SLOPE_DARK: f32 = 0.85;
+// Brightness gained per block above sea level, and lost per block below it
+const ALTITUDE_SHADING: f32 = 0.004;
+
+type MapVertex = [f32; 4];  // x, y (pixels), s, t
+
+/// The world seen from above, a color per block, kept up to date a column at
+/// a time as chunks load and change. Columns stay after their chunks unload,
+/// and are saved in tiles of `TILE_COLUMNS` by `TILE_COLUMNS` columns, so the
+/// map remembers where the player has been.
+#[derive(Default)]
+pub struct MapColors {
+    // Colors of each column's blocks, indexed by local z * CHUNK_SIZE + local x
+    columns: HashMap<(i32, i32), Vec<[u8; 3]>>,
+    // Bumped on every change, so views of the map know to redraw
+    revision: u64,
+    // Revision each tile last changed at, and the tiles changed since the last save
+    tile_revisions: HashMap<(i32, i32), u64>,
+    unsaved: HashSet<(i32, i32)>,
+}
+
+impl MapColors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the tiles saved in a directory. Unreadable tiles are reported and
+    /// skipped, and a missing directory gives an empty map.
+    pub fn load(directory: &Path) -> Self {
+        let mut colors = Self::new();
+        let Ok(entries) = fs::read_dir(directory) else {
+            return colors;
+        };
+        let paths = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "tile"));
+        for path in paths {
+            match fs::read(&path).map_err(|e| e.to_string()).and_then(|data| decode_tile(&data)) {
+                Ok((tile, columns)) => {
+                    colors.columns.extend(columns);
+                    colors.tile_revisions.insert(tile, 0);
+                },
+                Err(e) => log::warn!(target: "io", "Failed to load map tile {}: {}", path.display(), e),
+            }
+        }
+        log::info!(target: "io", "Loaded {} map tiles", colors.tile_revisions.len());
+        colors
+    }
+
+    /// Writes the tiles that changed since they were last saved.
+    pub fn save(&mut self, directory: &Path) -> io::Result<()> {
+        for (path, data) in self.take_unsaved_tiles(directory) {
+            atomic_file::write(&path, &data)?;
+        }
+        Ok(())
+    }
+
+    /// The files of the tiles that changed since they were last saved or
+    /// taken, with what to write to them, for writing in the background.
+    pub fn take_unsaved_tiles(&mut self, directory: &Path) -> SaveJob {
+        let tiles = self.unsaved.drain().collect::<Vec<_>>();
+        tiles.into_iter()
+            .map(|tile| (directory.join(format!("{}_{}.tile", tile.0, tile.1)), self.encode_tile(tile)))
+            .collect()
+    }
+
+    /// Recolors the columns that were loaded or changed since the last update.
+    pub fn update(&mut self, world: &mut World) {
+        let changed = world.take_changed_columns();
+        if changed.is_empty() {
+            return;
+        }
+        // A column's north edge is shaded against the column north of it, so
+        // the column to its south changes with it
+        let columns = changed.iter()
+            .flat_map(|&(x, z)| [(x, z), (x, z + 1)])
+            .filter(|&(x, z)| world.column(x, z).is_some())
+            .collect::<HashSet<_>>();
+        self.revision += 1;
+        for (x, z) in columns {
+            self.columns.insert((x, z), column_colors(world, x, z));
+            let tile = (x.div_euclid(TILE_COLUMNS), z.div_euclid(TILE_COLUMNS));
+            self.tile_revisions.insert(tile, self.revision);
+            self.unsaved.insert(tile);
+        }
+    }
+
+    /// Color of the block seen from above at `x`, `z`, or `None` where the
+    /// world hasn't been loaded.
+    pub fn color(&self, x: i32, z: i32) -> Option<[u8; 3]> {
+        let size = CHUNK_SIZE as i32;
+        let column = self.columns.get(&(x.div_euclid(size), z.div_euclid(size)))?;
+        Some(column[(z.rem_euclid(size) * size + x.rem_euclid(size)) as usize])
+    }
+
+    /// Colors of a column's blocks, by local z * `CHUNK_SIZE` + local x.
+    pub fn column(&self, x: i32, z: i32) -> Option<&[[u8; 3]]> {
+        self.columns.get(&(x, z)).map(Vec::as_slice)
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Revision at which a tile last changed, or `None` if none of it has been seen.
+    pub fn tile_revision(&self, tile: (i32, i32)) -> Option<u64> {
+        self.tile_revisions.get(&tile).copied()
+    }
+
+    // After the magic and version come the tile position, then for every
+    // column by z and x a byte telling whether it has been seen, followed by
+    // the colors of its blocks if so. Numbers are little-endian, and the whole
+    // is gzipped.
+    fn encode_tile(&self, (tile_x, tile_z): (i32, i32)) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(TILE_MAGIC);
+        data.extend_from_slice(&TILE_VERSION.to_le_bytes());
+        data.extend_from_slice(&tile_x.to_le_bytes());
+        data.extend_from_slice(&tile_z.to_le_bytes());
+        for z in tile_z * TILE_COLUMNS..(tile_z + 1) * TILE_COLUMNS {
+            for x in tile_x * TILE_COLUMNS..(tile_x + 1) * TILE_COLUMNS {
+                match self.columns.get(&(x, z)) {
+                    Some(colors) => {
+                        data.push(1);
+                        data.extend(colors.iter().flatten());
+                    },
+                    None => data.push(0),
+                }
+            }
+        }
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        // Writing to a Vec can't fail
+        encoder.write_all(&data).unwrap();
+        encoder.finish().unwrap()
+    }
+}
+
+type TileColumns = Vec<((i32, i32), Vec<[u8; 3]>)>;
+
+// Reads a tile written by `encode_tile`, returning its position and the columns seen in it
+fn decode_tile(compressed: &[u8]) -> Result<((i32, i32), TileColumns), String> {
+    let mut data = Vec::new();
+    GzDecoder::new(compressed).read_to_end(&mut data).map_err(|e| e.to_string())?;
+    let mut offset = 0;
+    let mut take = |count: usize| {
+        let bytes = data.get(offset..offset + count).ok_or("Truncated map tile")?;
+        offset += count;
+        Ok::<_, String>(bytes)
+    };
+
+    if take(4)? != TILE_MAGIC {
+        return Err("Not a map tile".to_string());
+    }
+    let version = u32::from_le_bytes(take(4)?.try_into().unwrap());
+    if version != TILE_VERSION {
+        return Err(format!("Unsupported map tile version: {}", version));
+    }
+    let tile_x = i32::from_le_bytes(take(4)?.try_into().unwrap());
+    let tile_z = i32::from_le_bytes(take(4)?.try_into().unwrap());
+
+    let mut columns = Vec::new();
+    for z in tile_z * TILE_COLUMNS..(tile_z + 1) * TILE_COLUMNS {
+        for x in tile_x * TILE_COLUMNS..(tile_x + 1) * TILE_COLUMNS {
+            if take(1)?[0] == 0 {
+                continue;
+            }
+            let colors = take(COLUMN_BLOCKS * 3)?.chunks(3).map(|rgb| [rgb[0], rgb[1], rgb[2]]).collect();
+            columns.push(((x, z), colors));
+        }
+    }
+    Ok(((tile_x, tile_z), columns))
+}
+
+// Height and map color of the highest block at `x`, `z`, if its column is loaded
+fn surface(world: &World, x: i32, z: i32) -> Option<(i32, [u8; 3])> {
+    let y = world.highest_block(x, z)?;
+    Some((y, world.get_block(x, y, z).map_color()?))
+}
+
+// Shaded colors of every block of a loaded column, seen from above
+fn column_colors(world: &World, column_x: i32, column_z: i32) -> Vec<[u8; 3]> {
+    let size = CHUNK_SIZE as i32;
+    let mut colors = Vec::with_capacity(COLUMN_BLOCKS);
+    for z in column_z * size..(column_z + 1) * size {
+        for x in column_x * size..(column_x + 1) * size {
+            let Some((height, color)) = surface(world, x, z) else {
+                colors.push([0, 0, 0]);
+                continue;
+            };
+            let slope = match surface(world, x, z - 1) {
+                Some((north, _)) if height > north => SLOPE_LIGHT,
+                Some((north, _)) if height < north => SLOPE_DARK,
+                _ => 1.0,
+            };
+            let brightness = slope * (1.0 + (height - SEA_LEVEL) as f32 * ALTITUDE_SHADING);
+            colors.push(color.map(|channel| (channel as f32 * brightness).clamp(0.0, 255.0) as u8));
+        }
+    }
+    colors
+}
+
+/// Map of the surroundings in the top right corner of the screen, north up,
+/// with an arrow for the way the player faces.
+pub struct Minimap {
+    program: ShaderProgram,
+    vao: VertexArray,
+    vertices: Buffer,
+    texture: GLuint,
+    texels: Vec<[u8; 4]>,
+    zoom: usize,
+    // Center block, zoom level and map revision the image was last drawn for
+    drawn_for: Option<(i32, i32, usize, u64)>,
+}
+
+impl Minimap {
+    pub fn new(program: ShaderProgram) -> Option<Self> {
+        let vao = VertexArray::new()?;
+        vao.bind();
+        let vertices = Buffer::new()?;
+        vertices.bind(BufferType::Array);
+        unsafe {
+            let stride = std::mem::size_of::<MapVertex>() as gl::types::GLsizei;
+            let float = std::mem::size_of::<f32>();
+            // (location, components, offset in floats): position, texture coordinates
+            for (location, components, offset) in [(0, 2, 0), (1, 2, 2)] {
+                gl::VertexAttribPointer(location, components, gl::FLOAT, gl::FALSE, stride, (offset * float) as *const _);
+                gl::EnableVertexAttribArray(location);
+            }
+        }
+
+        let mut texture = 0;
+        unsafe {
+            gl::GenTextures(1, &mut texture);
+            GlState::bind_texture(MINIMAP_TEXTURE_UNIT, gl::TEXTURE_2D, texture);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGBA8 as i32,
+                MAP_TEXELS as i32,
+                MAP_TEXELS as i32,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                std::ptr::null()
+            );
+        }
+
+        Some(Self {
+            program,
+            vao,
+            vertices,
+            texture,
+            texels: vec![[0; 4]; MAP_TEXELS * MAP_TEXELS],
+            zoom: 0,
+            drawn_for: None,
+        })
+    }
+
+    /// Switches to the next zoom level, going back to the first after the last.
+    pub fn toggle_zoom(&mut self) {
+        self.zoom = (self.zoom + 1) % ZOOM_LEVELS.len();
+    }
+
+    /// Draws the map centered on `position`, with the arrow pointing along
+    /// `yaw` in degrees. Call with the rest of the overlay, depth testing off.
+    pub fn draw(&mut self, colors: &MapColors, position: Vec3, yaw: f32, screen_width: f32, screen_height: f32) {
+        let blocks_per_texel = ZOOM_LEVELS[self.zoom];
+        let center = (position.x.round() as i32, position.z.round() as i32);
+        let drawn_for = Some((center.0, center.1, self.zoom, colors.revision()));
+        if self.drawn_for != drawn_for {
+            self.drawn_for = drawn_for;
+            // Rows go from north to south, so north ends up at the top
+            let half = MAP_TEXELS as f32 / 2.0;
+            let block = |texel: usize, center: f32| (center + (texel as f32 + 0.5 - half) * blocks_per_texel).round() as i32;
+            for row in 0..MAP_TEXELS {
+                for column in 0..MAP_TEXELS {
+                    let color = colors.color(block(column, position.x), block(row, position.z));
+                    self.texels[row * MAP_TEXELS + column] = color.map_or([0; 4], |[r, g, b]| [r, g, b, 255]);
+                }
+            }
+            GlState::bind_texture(MINIMAP_TEXTURE_UNIT, gl::TEXTURE_2D, self.texture);
+            unsafe {
+                gl::TexSubImage2D(
+                    gl::TEXTURE_2D,
+                    0,
+                    0,
+                    0,
+                    MAP_TEXELS as i32,
+                    MAP_TEXELS as i32,
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    self.texels.as_ptr() as *const _
+                );
+            }
+        }
+
+        let (left, top) = (screen_width - MAP_MARGIN - MAP_SIZE, MAP_MARGIN);
+        let (right, bottom) = (left + MAP_SIZE, top + MAP_SIZE);
+        let quad: [MapVertex; 6] = [
+            [left, top, 0.0, 0.0],
+            [left, bottom, 0.0, 1.0],
+            [right, bottom, 1.0, 1.0],
+            [right, bottom, 1.0, 1.0],
+            [right, top, 1.0, 0.0],
+            [left, top, 0.0, 0.0],
+        ];
+
+        self.program.use_program();
+        GlState::bind_texture(MINIMAP_TEXTURE_UNIT, gl::TEXTURE_2D, self.texture);
+        self.vao.bind();
+        self.vertices.bind(BufferType::Array);
+        buffer_data(BufferType::Array, bytemuck::cast_slice(&quad), gl::STREAM_DRAW);
+        unsafe {
+            gl::Uniform1i(gl::GetUniformLocation(self.program.0, b"mapTexture\0".as_ptr() as *const i8), MINIMAP_TEXTURE_UNIT as i32);
+            gl::Uniform2f(gl::GetUniformLocation(self.program.0, b"screenSize\0".as_ptr() as *const i8), screen_width, screen_height);
+            // The map's x and y run along the world's x and z
+            let yaw = yaw.to_radians();
+            gl::Uniform2f(gl::GetUniformLocation(self.program.0, b"facing\0".as_ptr() as *const i8), yaw.cos(), yaw.sin());
+            GlState::set_capability(gl::DEPTH_TEST, false);
+            gl::DrawArrays(gl::TRIANGLES, 0, quad.len() as i32);
+            GlState::set_capability(gl::DEPTH_TEST, true);
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use crate::entity::{Aabb, Entities, Entity, MeshHandle};
+use crate::entity_renderer::EntityRenderer;
+use crate::math::{Quat, Transform, Vec3};
+use crate::random::Random;
+use crate::world::World;
+
+const MOB_WIDTH: f32 = 0.8;
+const MOB_HEIGHT: f32 = 0.6;
+// Blocks per second
+const WALK_SPEED: f32 = 1.5;
+const GRAVITY: f32 = 25.0;
+// Vertical speed of a hop, enough to climb one block
+const JUMP_SPEED: f32 = 8.0;
+// Range of the time spent on each wandering decision, in seconds
+const MIN_WANDER_TIME: f32 = 1.0;
+const MAX_WANDER_TIME: f32 = 4.0;
+// Hits a mob takes, in points of damage
+const MOB_HEALTH: f32 = 4.0;
+// Fraction of a knockback's horizontal speed lost per second
+const KNOCKBACK_DRAG: f32 = 4.0;
+
+/// The kinds of mob, which spawn in different places.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MobKind {
+    /// Spawns on lit grass.
+    Passive,
+    /// Spawns in the dark. A placeholder for now, wandering around like
+    /// passive mobs.
+    Hostile,
+}
+
+impl MobKind {
+    pub const ALL: [MobKind; 2] = [MobKind::Passive, MobKind::Hostile];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MobKind::Passive => "passive",
+            MobKind::Hostile => "hostile",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<MobKind> {
+        Self::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+}
+
+/// Component of a mob wandering around on its own.
+pub struct Mob {
+    pub kind: MobKind,
+    // Horizontal walking direction, zero while idle
+    direction: Vec3,
+    vertical_speed: f32,
+    // Horizontal speed from being pushed, dying down over time
+    knockback: Vec3,
+    health: f32,
+    on_ground: bool,
+    // Seconds until the next wandering decision
+    decision_timer: f32,
+    // Drives the waddle animation
+    walk_phase: f32,
+}
+
+impl Mob {
+    /// Throws the mob with the given velocity, such as away from an explosion.
+    pub fn push(&mut self, velocity: Vec3) {
+        self.knockback = self.knockback + Vec3::new(velocity.x, 0.0, velocity.z);
+        self.vertical_speed += velocity.y;
+    }
+
+    /// Takes `amount` points of damage. Returns whether that killed the mob,
+    /// which is then for the caller to despawn.
+    pub fn damage(&mut self, amount: f32) -> bool {
+        self.health -= amount;
+        self.health <= 0.0
+    }
+}
+
+/// Spawns cube critters and runs their wandering AI.
+pub struct Mobs {
+    passive_mesh: MeshHandle,
+    hostile_mesh: MeshHandle,
+    // Draws the wandering decisions
+    random: Random,
+}
+
+impl Mobs {
+    pub fn new(renderer: &mut EntityRenderer, seed: u32) -> Option<Self> {
+        Some(Self {
+            passive_mesh: renderer.add_cube([0.95, 0.75, 0.7])?,
+            hostile_mesh: renderer.add_cube([0.3, 0.5, 0.25])?,
+            random: Random::new(seed),
+        })
+    }
+
+    /// Spawns a mob standing at `position`.
+    pub fn spawn(&mut self, entities: &mut Entities, kind: MobKind, position: Vec3) -> Entity {
+        let mut transform = Transform::at(position);
+        transform.scale = Vec3::new(MOB_WIDTH, MOB_HEIGHT, MOB_WIDTH);
+
+        let entity = entities.spawn();
+        entities.transforms.insert(entity, transform);
+        entities.bounds.insert(entity, Aabb::standing(MOB_WIDTH, MOB_HEIGHT));
+        let mesh = match kind {
+            MobKind::Passive => self.passive_mesh,
+            MobKind::Hostile => self.hostile_mesh,
+        };
+        entities.meshes.insert(entity, mesh);
+        entities.mobs.insert(entity, Mob {
+            kind,
+            direction: Vec3::zero(),
+            vertical_speed: 0.0,
+            knockback: Vec3::zero(),
+            health: MOB_HEALTH,
+            on_ground: false,
+            decision_timer: 0.0,
+            walk_phase: 0.0,
+        });
+        entity
+    }
+
+    /// Runs one step of wandering, gravity and terrain collision for every mob.
+    /// Mobs whose chunk got unloaded are despawned.
+    pub fn update(&mut self, entities: &mut Entities, world: &World, delta_time: f32) {
+        let mut lost = Vec::new();
+        let mobs = entities.mobs.iter().map(|(entity, _)| entity).collect::<Vec<_>>();
+
+        for entity in mobs {
+            // New wandering decisions draw from the shared generator
+            let decision = (self.random.unit(), self.random.unit(), self.random.unit());
+
+            let (Some(mob), Some(transform), Some(bounds)) = (
+                entities.mobs.get_mut(entity),
+                entities.transforms.get_mut(entity),
+                entities.bounds.get(entity),
+            ) else {
+                continue;
+            };
+
+            if !world.is_loaded(transform.translation) {
+                lost.push(entity);
+                continue;
+            }
+
+            mob.decision_timer -= delta_time;
+            if mob.decision_timer <= 0.0 {
+                let (walk, angle, duration) = decision;
+                mob.decision_timer = MIN_WANDER_TIME + duration * (MAX_WANDER_TIME - MIN_WANDER_TIME);
+                // Idle about a third of the time
+                mob.direction = if walk < 0.35 {
+                    Vec3::zero()
+                } else {
+                    let angle = angle * std::f32::consts::TAU;
+                    Vec3::new(angle.cos(), 0.0, angle.sin())
+                };
+            }
+
+            // Walk, get thrown and fall, landing on the block below
+            mob.vertical_speed -= GRAVITY * delta_time;
+            let velocity = mob.direction * WALK_SPEED + mob.knockback + Vec3::new(0.0, mob.vertical_speed, 0.0);
+            let step = velocity * delta_time;
+            mob.knockback = mob.knockback * (1.0 - KNOCKBACK_DRAG * delta_time).max(0.0);
+            let collision = world.sweep_aabb(bounds.at(transform.translation), step);
+            transform.translation = transform.translation + collision.movement;
+            if collision.normal.1 != 0 {
+                mob.vertical_speed = 0.0;
+            }
+            if collision.normal.0 != 0 {
+                mob.knockback.x = 0.0;
+            }
+            if collision.normal.2 != 0 {
+                mob.knockback.z = 0.0;
+            }
+            // Hop up single-block steps
+            let blocked = collision.normal.0 != 0 || collision.normal.2 != 0;
+            if blocked && mob.on_ground {
+                mob.vertical_speed = JUMP_SPEED;
+            }
+            mob.on_ground = collision.grounded;
+
+            // Face the walking direction and waddle while moving
+            if mob.direction.length() > 0.0 {
+                transform.rotation = Quat::from_yaw((-mob.direction.z).atan2(mob.direction.x));
+                mob.walk_phase += delta_time * 10.0;
+            } else {
+                mob.walk_phase = 0.0;
+            }
+            let squash = mob.walk_phase.sin().abs() * 0.1;
+            transform.scale = Vec3::new(MOB_WIDTH * (1.0 + squash / 2.0), MOB_HEIGHT * (1.0 - squash), MOB_WIDTH);
+        }
+
+        for entity in lost {
+            entities.despawn(entity);
+        }
+    }
+}
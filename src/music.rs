@@ -0,0 +1,155 @@
+use crate::block::BlockType;
+use crate::math::Vec3;
+use crate::random::Random;
+use crate::sound::SoundManager;
+use crate::world::World;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where the tracks are found, in one folder per mood.
+pub const MUSIC_DIRECTORY: &str = "src/assets/music";
+// How long the camera has to stay in a new mood before the music follows, so
+// walking in and out of a cave mouth doesn't keep switching tracks
+const MOOD_SETTLE_TIME: f32 = 3.0;
+// Quiet between two tracks of the same mood, in seconds
+const MIN_PAUSE: f32 = 20.0;
+const MAX_PAUSE: f32 = 60.0;
+// Daylight under which the night music plays
+const NIGHT_DAYLIGHT: f32 = 0.35;
+
+/// The kind of place the camera is in, which picks the music.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Mood {
+    Day,
+    Night,
+    Underground,
+    Underwater,
+}
+
+impl Mood {
+    const ALL: [Mood; 4] = [Mood::Day, Mood::Night, Mood::Underground, Mood::Underwater];
+
+    /// The mood at the camera's position, at the given daylight.
+    pub fn at(world: &World, position: Vec3, daylight: f32) -> Mood {
+        let (x, y, z) = (position.x.round() as i32, position.y.round() as i32, position.z.round() as i32);
+        if world.get_block(x, y, z) == BlockType::Water {
+            Mood::Underwater
+        } else if world.is_in_cave(position) {
+            Mood::Underground
+        } else if daylight < NIGHT_DAYLIGHT {
+            Mood::Night
+        } else {
+            Mood::Day
+        }
+    }
+
+    fn directory(self) -> &'static str {
+        match self {
+            Mood::Day => "day",
+            Mood::Night => "night",
+            Mood::Underground => "underground",
+            Mood::Underwater => "underwater",
+        }
+    }
+}
+
+/// Picks music to suit the mood, crossfading when it changes and leaving a
+/// pause between tracks. A mood without tracks plays nothing.
+pub struct MusicPlayer {
+    tracks: HashMap<Mood, Vec<PathBuf>>,
+    // Mood the music follows, None until the first update
+    mood: Option<Mood>,
+    // A different mood the camera is in, and for how many seconds
+    pending: Option<(Mood, f32)>,
+    // Seconds left before the next track, once the last one has ended
+    pause: f32,
+    last_track: Option<PathBuf>,
+    random: Random,
+}
+
+impl MusicPlayer {
+    /// Finds the WAV files in each mood's folder under `directory`.
+    pub fn new(directory: &Path) -> Self {
+        let tracks: HashMap<_, _> = Mood::ALL.iter()
+            .map(|&mood| {
+                let mut files: Vec<PathBuf> = fs::read_dir(directory.join(mood.directory()))
+                    .into_iter()
+                    .flatten()
+                    .flatten()
+                    .map(|entry| entry.path())
+                    .filter(|path| path.extension().is_some_and(|extension| extension == "wav"))
+                    .collect();
+                files.sort();
+                (mood, files)
+            })
+            .collect();
+        let count: usize = tracks.values().map(Vec::len).sum();
+        log::debug!(target: "audio", "Found {} music tracks in {}", count, directory.display());
+
+        Self {
+            tracks,
+            mood: None,
+            pending: None,
+            pause: 0.0,
+            last_track: None,
+            random: Random::new(0x6d2b_79f5),
+        }
+    }
+
+    /// Follows the camera's mood, switching tracks once a new one has settled.
+    pub fn update(&mut self, mood: Mood, sounds: &mut SoundManager, delta_time: f32) {
+        if self.mood != Some(mood) {
+            let settled = match &mut self.pending {
+                Some((pending, time)) if *pending == mood => {
+                    *time += delta_time;
+                    *time >= MOOD_SETTLE_TIME
+                },
+                _ => {
+                    self.pending = Some((mood, 0.0));
+                    false
+                },
+            };
+            // The first mood is taken at once
+            if settled || self.mood.is_none() {
+                log::debug!(target: "audio", "Music mood: {:?}", mood);
+                self.mood = Some(mood);
+                self.pending = None;
+                self.start_track(sounds);
+            }
+            return;
+        }
+
+        self.pending = None;
+        if !sounds.music_playing() {
+            self.pause -= delta_time;
+            if self.pause <= 0.0 {
+                self.start_track(sounds);
+            }
+        }
+    }
+
+    // Crossfades to a random track of the current mood, other than the last
+    // one if there is a choice, or fades out if the mood has none
+    fn start_track(&mut self, sounds: &mut SoundManager) {
+        self.pause = self.random.range(MIN_PAUSE, MAX_PAUSE);
+        let Some(mood) = self.mood else {
+            return;
+        };
+        let tracks = &self.tracks[&mood];
+        let choices: Vec<PathBuf> = tracks.iter()
+            .filter(|&track| tracks.len() == 1 || Some(track) != self.last_track.as_ref())
+            .cloned()
+            .collect();
+        if choices.is_empty() {
+            sounds.stop_music();
+            return;
+        }
+
+        let track = choices[self.random.below(choices.len() as u32) as usize].clone();
+        if let Err(e) = sounds.play_music(&track) {
+            log::warn!(target: "audio", "Failed to play {}: {}", track.display(), e);
+        }
+        self.last_track = Some(track);
+    }
+}
@@ -0,0 +1,274 @@
+use crate::byte_reader::ByteReader;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+
+// Tag type ids
+const END: u8 = 0;
+const BYTE: u8 = 1;
+const SHORT: u8 = 2;
+const INT: u8 = 3;
+const LONG: u8 = 4;
+const FLOAT: u8 = 5;
+const DOUBLE: u8 = 6;
+const BYTE_ARRAY: u8 = 7;
+const STRING: u8 = 8;
+const LIST: u8 = 9;
+const COMPOUND: u8 = 10;
+const INT_ARRAY: u8 = 11;
+const LONG_ARRAY: u8 = 12;
+// Deeper nesting than any real file uses, so broken data can't overflow the stack
+const MAX_DEPTH: usize = 512;
+
+/// A value in Minecraft's Named Binary Tag format, which schematics, structures
+/// and saves are stored in.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Tag {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<i8>),
+    String(String),
+    List(Vec<Tag>),
+    Compound(BTreeMap<String, Tag>),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+impl Tag {
+    /// A compound built from name and value pairs.
+    pub fn compound<const N: usize>(entries: [(&str, Tag); N]) -> Tag {
+        Tag::Compound(entries.into_iter().map(|(name, tag)| (name.to_string(), tag)).collect())
+    }
+
+    /// The named entry of a compound.
+    pub fn get(&self, name: &str) -> Option<&Tag> {
+        match self {
+            Tag::Compound(entries) => entries.get(name),
+            _ => None,
+        }
+    }
+
+    /// Any integer tag, widened.
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Tag::Byte(v) => Some(v.into()),
+            Tag::Short(v) => Some(v.into()),
+            Tag::Int(v) => Some(v.into()),
+            Tag::Long(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Tag::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Tag]> {
+        match self {
+            Tag::List(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_compound(&self) -> Option<&BTreeMap<String, Tag>> {
+        match self {
+            Tag::Compound(entries) => Some(entries),
+            _ => None,
+        }
+    }
+
+    /// The integers of an int array, or of a list of integer tags.
+    pub fn as_ints(&self) -> Option<Vec<i32>> {
+        match self {
+            Tag::IntArray(values) => Some(values.clone()),
+            Tag::List(items) => items.iter().map(|item| item.as_i64().map(|v| v as i32)).collect(),
+            _ => None,
+        }
+    }
+
+    fn id(&self) -> u8 {
+        match self {
+            Tag::Byte(_) => BYTE,
+            Tag::Short(_) => SHORT,
+            Tag::Int(_) => INT,
+            Tag::Long(_) => LONG,
+            Tag::Float(_) => FLOAT,
+            Tag::Double(_) => DOUBLE,
+            Tag::ByteArray(_) => BYTE_ARRAY,
+            Tag::String(_) => STRING,
+            Tag::List(_) => LIST,
+            Tag::Compound(_) => COMPOUND,
+            Tag::IntArray(_) => INT_ARRAY,
+            Tag::LongArray(_) => LONG_ARRAY,
+        }
+    }
+}
+
+/// Reads a named root tag, gunzipping the data first if it is compressed.
+/// Returns the root's name and value.
+pub fn read(data: &[u8]) -> Result<(String, Tag), String> {
+    if data.starts_with(&[0x1f, 0x8b]) {
+        let mut decompressed = Vec::new();
+        GzDecoder::new(data).read_to_end(&mut decompressed).map_err(|e| e.to_string())?;
+        return read(&decompressed);
+    }
+
+    let mut reader = Reader { bytes: ByteReader::new(data) };
+    let id = reader.take(1)?[0];
+    if id != COMPOUND {
+        return Err(format!("Root tag has type {}, expected a compound", id));
+    }
+    let name = reader.string()?;
+    let root = reader.payload(id, 0)?;
+    Ok((name, root))
+}
+
+/// Writes a named root tag, gzipped as Minecraft's own files are.
+pub fn write(name: &str, root: &Tag) -> Vec<u8> {
+    let mut data = vec![root.id()];
+    write_string(&mut data, name);
+    write_payload(&mut data, root);
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    // Writing to a Vec can't fail
+    encoder.write_all(&data).unwrap();
+    encoder.finish().unwrap()
+}
+
+// Reads tags, whose numbers are big-endian
+struct Reader<'a> {
+    bytes: ByteReader<'a>,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], String> {
+        self.bytes.take(count).ok_or_else(|| "Truncated NBT data".to_string())
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        self.bytes.array().ok_or_else(|| "Truncated NBT data".to_string())
+    }
+
+    fn length(&mut self) -> Result<usize, String> {
+        let length = i32::from_be_bytes(self.array()?);
+        // Every element takes at least a byte, which catches absurd lengths before allocating
+        if length < 0 || length as usize > self.bytes.remaining().len() {
+            return Err(format!("Invalid NBT length: {}", length));
+        }
+        Ok(length as usize)
+    }
+
+    // Java writes "modified UTF-8", which matches UTF-8 for everything but
+    // nulls and characters outside the basic plane
+    fn string(&mut self) -> Result<String, String> {
+        let length = u16::from_be_bytes(self.array()?) as usize;
+        Ok(String::from_utf8_lossy(self.take(length)?).into_owned())
+    }
+
+    fn payload(&mut self, id: u8, depth: usize) -> Result<Tag, String> {
+        if depth > MAX_DEPTH {
+            return Err("NBT nested too deeply".to_string());
+        }
+        Ok(match id {
+            BYTE => Tag::Byte(self.take(1)?[0] as i8),
+            SHORT => Tag::Short(i16::from_be_bytes(self.array()?)),
+            INT => Tag::Int(i32::from_be_bytes(self.array()?)),
+            LONG => Tag::Long(i64::from_be_bytes(self.array()?)),
+            FLOAT => Tag::Float(f32::from_be_bytes(self.array()?)),
+            DOUBLE => Tag::Double(f64::from_be_bytes(self.array()?)),
+            BYTE_ARRAY => {
+                let length = self.length()?;
+                Tag::ByteArray(self.take(length)?.iter().map(|&b| b as i8).collect())
+            },
+            STRING => Tag::String(self.string()?),
+            LIST => {
+                let item_id = self.take(1)?[0];
+                let length = self.length()?;
+                let mut items = Vec::with_capacity(length);
+                for _ in 0..length {
+                    items.push(self.payload(item_id, depth + 1)?);
+                }
+                Tag::List(items)
+            },
+            COMPOUND => {
+                let mut entries = BTreeMap::new();
+                loop {
+                    let entry_id = self.take(1)?[0];
+                    if entry_id == END {
+                        break;
+                    }
+                    let name = self.string()?;
+                    entries.insert(name, self.payload(entry_id, depth + 1)?);
+                }
+                Tag::Compound(entries)
+            },
+            INT_ARRAY => {
+                let length = self.length()?;
+                Tag::IntArray((0..length).map(|_| self.array().map(i32::from_be_bytes)).collect::<Result<_, _>>()?)
+            },
+            LONG_ARRAY => {
+                let length = self.length()?;
+                Tag::LongArray((0..length).map(|_| self.array().map(i64::from_be_bytes)).collect::<Result<_, _>>()?)
+            },
+            _ => return Err(format!("Unknown NBT tag type: {}", id)),
+        })
+    }
+}
+
+fn write_string(data: &mut Vec<u8>, s: &str) {
+    data.extend_from_slice(&(s.len() as u16).to_be_bytes());
+    data.extend_from_slice(s.as_bytes());
+}
+
+fn write_payload(data: &mut Vec<u8>, tag: &Tag) {
+    match tag {
+        Tag::Byte(v) => data.push(*v as u8),
+        Tag::Short(v) => data.extend_from_slice(&v.to_be_bytes()),
+        Tag::Int(v) => data.extend_from_slice(&v.to_be_bytes()),
+        Tag::Long(v) => data.extend_from_slice(&v.to_be_bytes()),
+        Tag::Float(v) => data.extend_from_slice(&v.to_be_bytes()),
+        Tag::Double(v) => data.extend_from_slice(&v.to_be_bytes()),
+        Tag::ByteArray(values) => {
+            data.extend_from_slice(&(values.len() as i32).to_be_bytes());
+            data.extend(values.iter().map(|&v| v as u8));
+        },
+        Tag::String(s) => write_string(data, s),
+        Tag::List(items) => {
+            // Lists hold a single type; an empty one is written as a list of End
+            data.push(items.first().map_or(END, Tag::id));
+            data.extend_from_slice(&(items.len() as i32).to_be_bytes());
+            for item in items {
+                write_payload(data, item);
+            }
+        },
+        Tag::Compound(entries) => {
+            for (name, entry) in entries {
+                data.push(entry.id());
+                write_string(data, name);
+                write_payload(data, entry);
+            }
+            data.push(END);
+        },
+        Tag::IntArray(values) => {
+            data.extend_from_slice(&(values.len() as i32).to_be_bytes());
+            for v in values {
+                data.extend_from_slice(&v.to_be_bytes());
+            }
+        },
+        Tag::LongArray(values) => {
+            data.extend_from_slice(&(values.len() as i32).to_be_bytes());
+            for v in values {
+                data.extend_from_slice(&v.to_be_bytes());
+            }
+        },
+    }
+}
@@ -0,0 +1,318 @@
+use crate::block::BlockType;
+use crate::chunk_data::{self, ChunkStore};
+use crate::events::{BlockChangeCause, BlockChanged, EventBus, Subscriber};
+use crate::protocol::{Message, PROTOCOL_VERSION};
+use crate::world::World;
+use crate::world_generator::Preset;
+use crate::world_metadata::WorldMetadata;
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_PORT: u16 = 25575;
+// How long the server sleeps between polls of its connections
+const SERVER_POLL_INTERVAL: Duration = Duration::from_millis(5);
+// How long a client waits for the server to welcome it
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A non-blocking TCP stream that sends and receives framed messages.
+struct Connection {
+    stream: TcpStream,
+    incoming: Vec<u8>,
+    outgoing: Vec<u8>,
+}
+
+impl Connection {
+    fn new(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        Ok(Self {
+            stream,
+            incoming: Vec::new(),
+            outgoing: Vec::new(),
+        })
+    }
+
+    fn send(&mut self, message: &Message) {
+        message.encode(&mut self.outgoing);
+    }
+
+    /// Writes as much queued data as the socket takes without blocking.
+    fn flush(&mut self) -> io::Result<()> {
+        while !self.outgoing.is_empty() {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(written) => {
+                    self.outgoing.drain(..written);
+                },
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads everything available and returns the complete messages received.
+    /// Fails once the peer has disconnected or sent something invalid.
+    fn receive(&mut self) -> io::Result<Vec<Message>> {
+        let mut buffer = [0; 4096];
+        loop {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(read) => self.incoming.extend_from_slice(&buffer[..read]),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            }
+        }
+
+        let mut messages = Vec::new();
+        let mut consumed = 0;
+        while let Some((message, used)) = Message::decode(&self.incoming[consumed..])
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+        {
+            messages.push(message);
+            consumed += used;
+        }
+        self.incoming.drain(..consumed);
+        Ok(messages)
+    }
+}
+
+// A connected player as seen by the server
+struct RemotePlayer {
+    connection: Connection,
+    // Set once the client said hello with a matching version
+    joined: bool,
+    last_position: Option<Message>,
+}
+
+/// Authoritative game state shared between players: the world with every block
+/// edit made to it, and where each player is. Chunks are only generated to send
+/// the edited ones to joining players.
+struct Server {
+    listener: TcpListener,
+    world: World,
+    // The host's world, whose terrain settings are sent to joining players
+    metadata: WorldMetadata,
+    players: HashMap<u32, RemotePlayer>,
+    next_player_id: u32,
+}
+
+impl Server {
+    fn run(mut self) {
+        loop {
+            self.accept();
+            self.poll_players();
+            thread::sleep(SERVER_POLL_INTERVAL);
+        }
+    }
+
+    fn accept(&mut self) {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, address)) => match Connection::new(stream) {
+                    Ok(connection) => {
+                        log::info!(target: "network", "Player {} connected from {}", self.next_player_id, address);
+                        self.players.insert(self.next_player_id, RemotePlayer {
+                            connection,
+                            joined: false,
+                            last_position: None,
+                        });
+                        self.next_player_id += 1;
+                    },
+                    Err(e) => log::warn!(target: "network", "Failed to set up connection from {}: {}", address, e),
+                },
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    log::warn!(target: "network", "Failed to accept connection: {}", e);
+                    break;
+                },
+            }
+        }
+    }
+
+    fn poll_players(&mut self) {
+        let mut broadcasts = Vec::new();
+        let mut left = Vec::new();
+        // Where everyone was last seen, for players joining now
+        let positions = self.players.values()
+            .filter_map(|player| player.last_position.clone())
+            .collect::<Vec<_>>();
+
+        for (&player_id, player) in &mut self.players {
+            let messages = match player.connection.receive() {
+                Ok(messages) => messages,
+                Err(_) => {
+                    left.push(player_id);
+                    continue;
+                },
+            };
+
+            for message in messages {
+                match message {
+                    Message::Hello { version } if !player.joined => {
+                        if version != PROTOCOL_VERSION {
+                            log::warn!(target: "network", "Player {} uses protocol {}, expected {}", player_id, version, PROTOCOL_VERSION);
+                            left.push(player_id);
+                            break;
+                        }
+                        player.joined = true;
+                        player.connection.send(&Message::Welcome {
+                            player_id,
+                            seed: self.world.seed,
+                            preset: self.metadata.preset,
+                            layers: self.metadata.layers.clone(),
+                            snow_altitude: self.metadata.snow_altitude,
+                        });
+                        for position in self.world.modified_chunks() {
+                            let data = chunk_data::encode(position, &self.world.chunk_blocks(position));
+                            player.connection.send(&Message::ChunkData { data });
+                        }
+                        for position in &positions {
+                            player.connection.send(position);
+                        }
+                    },
+                    Message::SetBlock { x, y, z, block } if player.joined => {
+                        self.world.set_block(x, y, z, block);
+                        broadcasts.push((player_id, message));
+                    },
+                    Message::PlayerPosition { x, y, z, yaw, pitch, .. } if player.joined => {
+                        let position = Message::PlayerPosition { player_id, x, y, z, yaw, pitch };
+                        player.last_position = Some(position.clone());
+                        broadcasts.push((player_id, position));
+                    },
+                    _ => {},
+                }
+            }
+        }
+
+        for player_id in left {
+            if self.players.remove(&player_id).is_some() {
+                log::info!(target: "network", "Player {} disconnected", player_id);
+                broadcasts.push((player_id, Message::PlayerLeft { player_id }));
+            }
+        }
+
+        // Share everything with every other player
+        for (sender, message) in &broadcasts {
+            for (player_id, player) in &mut self.players {
+                if player_id != sender && player.joined {
+                    player.connection.send(message);
+                }
+            }
+        }
+
+        let mut failed = Vec::new();
+        for (&player_id, player) in &mut self.players {
+            if player.connection.flush().is_err() {
+                failed.push(player_id);
+            }
+        }
+        for player_id in failed {
+            self.players.remove(&player_id);
+            log::info!(target: "network", "Player {} disconnected", player_id);
+        }
+    }
+}
+
+/// Starts a server for a world with the given seed on a background thread,
+/// with the terrain of `metadata` and the chunks saved in `store`. Returns the
+/// address it listens on.
+pub fn start_server(port: u16, seed: u32, metadata: &WorldMetadata, store: &ChunkStore) -> io::Result<SocketAddr> {
+    let listener = TcpListener::bind(("0.0.0.0", port))?;
+    listener.set_nonblocking(true)?;
+    let address = listener.local_addr()?;
+
+    let mut world = World::new(seed);
+    world.use_generator(metadata.preset.generator(&metadata.layers, metadata.snow_altitude));
+    for (position, blocks) in store.load_all() {
+        world.restore_chunk(position, blocks);
+    }
+    let server = Server {
+        listener,
+        world,
+        metadata: metadata.clone(),
+        players: HashMap::new(),
+        next_player_id: 1,
+    };
+    thread::spawn(move || server.run());
+    log::info!(target: "network", "Server listening on {}", address);
+    Ok(address)
+}
+
+/// A connection to a server, from the player's side.
+pub struct Client {
+    connection: Connection,
+    pub player_id: u32,
+    /// Seed of the server's world.
+    pub seed: u32,
+    /// Terrain settings of the server's world, to generate the same terrain.
+    pub preset: Preset,
+    pub layers: Vec<BlockType>,
+    pub snow_altitude: i32,
+    // Messages that arrived along with the welcome
+    pending: Vec<Message>,
+    changes: Subscriber<BlockChanged>,
+}
+
+impl Client {
+    /// Connects and waits for the server to welcome this player.
+    pub fn connect(address: impl ToSocketAddrs) -> io::Result<Self> {
+        let mut connection = Connection::new(TcpStream::connect(address)?)?;
+        connection.send(&Message::Hello { version: PROTOCOL_VERSION });
+
+        let started = Instant::now();
+        loop {
+            connection.flush()?;
+            let mut messages = connection.receive()?;
+            if let Some(index) = messages.iter().position(|m| matches!(m, Message::Welcome { .. })) {
+                let Message::Welcome { player_id, seed, preset, layers, snow_altitude } = messages.remove(index) else {
+                    unreachable!();
+                };
+                return Ok(Self {
+                    connection,
+                    player_id,
+                    seed,
+                    preset,
+                    layers,
+                    snow_altitude,
+                    pending: messages,
+                    changes: Subscriber::new(),
+                });
+            }
+            if started.elapsed() > HANDSHAKE_TIMEOUT {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "Server didn't answer"));
+            }
+            thread::sleep(SERVER_POLL_INTERVAL);
+        }
+    }
+
+    fn send_block(&mut self, x: i32, y: i32, z: i32, block: BlockType) {
+        self.connection.send(&Message::SetBlock { x, y, z, block });
+    }
+
+    /// Sends the server every block changed here, leaving out the edits that
+    /// came from it.
+    pub fn handle_events(&mut self, events: &EventBus) {
+        for change in events.read(&mut self.changes) {
+            if change.cause != BlockChangeCause::Remote {
+                let (x, y, z) = change.position;
+                self.send_block(x, y, z, change.block);
+            }
+        }
+    }
+
+    pub fn send_position(&mut self, x: f32, y: f32, z: f32, yaw: f32, pitch: f32) {
+        self.connection.send(&Message::PlayerPosition { player_id: 0, x, y, z, yaw, pitch });
+    }
+
+    /// Sends queued messages and returns those received since the last call.
+    pub fn poll(&mut self) -> io::Result<Vec<Message>> {
+        self.connection.flush()?;
+        let mut messages = std::mem::take(&mut self.pending);
+        messages.extend(self.connection.receive()?);
+        Ok(messages)
+    }
+}
@@ -0,0 +1,100 @@
+use crate::debug_lines::DebugLines;
+use crate::frame_uniforms::FrameUniforms;
+use crate::gl_utils::{self, GlState};
+use crate::math::{Mat4, Vec3};
+use crate::renderer::Renderer;
+use crate::streaming::{column_bounds, ChunkStreamer, Viewer};
+use crate::world::{World, CHUNK_SIZE, WORLD_HEIGHT_CHUNKS};
+use crate::{WINDOW_HEIGHT, WINDOW_WIDTH};
+
+/// Width and height in pixels of the observer window.
+pub const OBSERVER_SIZE: u32 = 512;
+// Blocks column outlines are drawn inside their edges, so neighbors' don't overlap
+const OUTLINE_INSET: f32 = 1.0;
+// Segments of the render distance circle
+const CIRCLE_SEGMENTS: usize = 64;
+// Blocks above the top of the world the camera looks down from
+const EYE_CLEARANCE: f32 = 16.0;
+// Blocks the line showing where the player looks is long
+const DIRECTION_LENGTH: f32 = 24.0;
+
+/// Draws the loaded chunks from straight above the player, with -z up, for
+/// the observer window. Over them go the outline of every loaded column,
+/// colored by whether the player's view reaches it or culls it, the queued
+/// columns, the render distance and the player with the way they look, in
+/// the debug lines' palette.
+pub fn draw(renderer: &mut dyn Renderer, lines: &mut DebugLines, frame: &FrameUniforms, streamer: &ChunkStreamer, world: &World, viewer: &Viewer) {
+    let colors = lines.palette.colors();
+    let size = CHUNK_SIZE as f32;
+    let top = (WORLD_HEIGHT_CHUNKS * CHUNK_SIZE as i32) as f32;
+    let center = viewer.position;
+    let eye = Vec3::new(center.x, top + EYE_CLEARANCE, center.z);
+    // Shows the render distance with a chunk to spare on each side
+    let half_width = (streamer.render_distance() + 1) as f32 * size;
+    let far = top + 2.0 * EYE_CLEARANCE;
+    renderer.set_frame_uniforms(&FrameUniforms {
+        view: Mat4::look_at(eye, Vec3::new(center.x, 0.0, center.z), Vec3::new(0.0, 0.0, -1.0)),
+        projection: Mat4::orthographic(-half_width, half_width, -half_width, half_width, 0.1, far),
+        camera_position: eye,
+        // No fog, which would cover everything from this far up
+        fog_start: 2.0 * far,
+        fog_end: 3.0 * far,
+        ..*frame
+    });
+    let [r, g, b] = frame.fog_color;
+    gl_utils::clear_color(r, g, b, 1.0);
+    unsafe {
+        gl::Viewport(0, 0, OBSERVER_SIZE as i32, OBSERVER_SIZE as i32);
+        gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+    }
+    renderer.draw_opaque();
+    renderer.draw_water();
+
+    let columns = world.loaded_columns()
+        .map(|(x, z)| column_bounds(x, z))
+        .collect::<Vec<_>>();
+    for (&(min, max), visible) in columns.iter().zip(viewer.frustum.intersects_aabbs(&columns)) {
+        let color = if visible { colors.in_view } else { colors.culled };
+        outline(lines, min, max, top, color);
+    }
+    for (x, z) in streamer.queued_columns() {
+        let (min, max) = column_bounds(x, z);
+        outline(lines, min, max, top, colors.queued);
+    }
+
+    // Columns load when their middle is within the render distance of the middle of the player's column
+    let middle = |v: f32| ((v / size).floor() + 0.5) * size - 0.5;
+    let (middle_x, middle_z) = (middle(center.x), middle(center.z));
+    let radius = streamer.render_distance() as f32 * size;
+    let on_circle = |segment: usize| {
+        let angle = segment as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+        Vec3::new(middle_x + radius * angle.cos(), top, middle_z + radius * angle.sin())
+    };
+    for segment in 0..CIRCLE_SEGMENTS {
+        lines.line(on_circle(segment), on_circle(segment + 1), colors.render_distance);
+    }
+
+    let player = Vec3::new(center.x, top, center.z);
+    lines.line(player - Vec3::new(1.0, 0.0, 0.0), player + Vec3::new(1.0, 0.0, 0.0), colors.player);
+    lines.line(player - Vec3::new(0.0, 0.0, 1.0), player + Vec3::new(0.0, 0.0, 1.0), colors.player);
+    let ahead = Vec3::new(viewer.direction.x, 0.0, viewer.direction.z);
+    lines.line(player, player + ahead * DIRECTION_LENGTH, colors.player);
+
+    // The lines go over the terrain, whatever its height
+    GlState::set_capability(gl::DEPTH_TEST, false);
+    lines.draw();
+    GlState::set_capability(gl::DEPTH_TEST, true);
+    unsafe {
+        gl::Viewport(0, 0, WINDOW_WIDTH as i32, WINDOW_HEIGHT as i32);
+    }
+}
+
+// Queues the outline of a column at height `y`, inset from its edges
+fn outline(lines: &mut DebugLines, min: Vec3, max: Vec3, y: f32, color: [f32; 3]) {
+    let (x0, z0) = (min.x + OUTLINE_INSET, min.z + OUTLINE_INSET);
+    let (x1, z1) = (max.x - OUTLINE_INSET, max.z - OUTLINE_INSET);
+    let corners = [Vec3::new(x0, y, z0), Vec3::new(x1, y, z0), Vec3::new(x1, y, z1), Vec3::new(x0, y, z1)];
+    for i in 0..4 {
+        lines.line(corners[i], corners[(i + 1) % 4], color);
+    }
+}
@@ -0,0 +1,156 @@
+/// Colors of highlights, HUD accents and debug views, as the HUD and debug
+/// lines are told to use them. Colors are RGB from 0 to 1, with alpha for
+/// those drawn on the HUD.
+#[derive(Debug)]
+pub struct PaletteColors {
+    /// The box picked with `/pos1` and `/pos2`.
+    pub selection: [f32; 3],
+    /// The high-contrast outline of the block being looked at.
+    pub target: [f32; 3],
+    /// The stack held on the cursor in the inventory.
+    pub held: [f32; 4],
+    /// Console lines that are errors, and the line being typed.
+    pub error: [f32; 4],
+    pub input: [f32; 4],
+    /// F5: chunk borders, and the chunk the camera is in.
+    pub chunk_border: [f32; 3],
+    pub current_chunk: [f32; 3],
+    /// F7: columns in view, culled and queued for loading, the render
+    /// distance and the player.
+    pub in_view: [f32; 3],
+    pub culled: [f32; 3],
+    pub queued: [f32; 3],
+    pub render_distance: [f32; 3],
+    pub player: [f32; 3],
+    /// F8: chunks meshed and waiting to be uploaded, drawn, and with unsaved
+    /// edits. Chunks waiting to generate use `queued`.
+    pub meshed: [f32; 3],
+    pub uploaded: [f32; 3],
+    pub dirty: [f32; 3],
+    /// F8 again: the cheapest and the costliest meshes of the heatmap.
+    pub cost_low: [f32; 3],
+    pub cost_high: [f32; 3],
+}
+
+const DEFAULT: PaletteColors = PaletteColors {
+    selection: [1.0, 0.4, 0.1],
+    target: [1.0, 1.0, 0.0],
+    held: [1.0, 0.85, 0.3, 1.0],
+    error: [1.0, 0.4, 0.4, 1.0],
+    input: [1.0, 1.0, 0.6, 1.0],
+    chunk_border: [0.2, 0.4, 1.0],
+    current_chunk: [1.0, 0.9, 0.1],
+    in_view: [0.2, 0.9, 0.2],
+    culled: [0.5, 0.5, 0.5],
+    queued: [1.0, 0.6, 0.1],
+    render_distance: [0.3, 0.5, 1.0],
+    player: [1.0, 0.2, 0.2],
+    meshed: [0.95, 0.95, 0.2],
+    uploaded: [0.2, 0.8, 0.3],
+    dirty: [1.0, 0.2, 0.8],
+    cost_low: [0.1, 0.3, 1.0],
+    cost_high: [1.0, 0.15, 0.1],
+};
+
+// Without red or green to tell apart, colors differ in blue and brightness.
+// Red looks dark to protanopes, so it is left out altogether
+const PROTANOPIA: PaletteColors = PaletteColors {
+    selection: [0.9, 0.6, 0.0],
+    target: [1.0, 1.0, 0.3],
+    held: [0.95, 0.9, 0.25, 1.0],
+    error: [0.95, 0.7, 0.1, 1.0],
+    input: [0.55, 0.8, 1.0, 1.0],
+    chunk_border: [0.35, 0.7, 0.9],
+    current_chunk: [0.95, 0.9, 0.25],
+    in_view: [0.35, 0.7, 0.9],
+    culled: [0.45, 0.45, 0.45],
+    queued: [0.9, 0.6, 0.0],
+    render_distance: [0.8, 0.6, 0.7],
+    player: [1.0, 1.0, 1.0],
+    meshed: [0.95, 0.9, 0.25],
+    uploaded: [0.35, 0.7, 0.9],
+    dirty: [0.8, 0.6, 0.7],
+    cost_low: [0.0, 0.45, 0.7],
+    cost_high: [0.95, 0.9, 0.25],
+};
+
+const DEUTERANOPIA: PaletteColors = PaletteColors {
+    selection: [0.85, 0.4, 0.0],
+    target: [1.0, 1.0, 0.3],
+    held: [0.95, 0.9, 0.25, 1.0],
+    error: [0.85, 0.4, 0.0, 1.0],
+    input: [0.55, 0.8, 1.0, 1.0],
+    chunk_border: [0.0, 0.45, 0.7],
+    current_chunk: [0.95, 0.9, 0.25],
+    in_view: [0.35, 0.7, 0.9],
+    culled: [0.45, 0.45, 0.45],
+    queued: [0.9, 0.6, 0.0],
+    render_distance: [0.8, 0.47, 0.65],
+    player: [1.0, 1.0, 1.0],
+    meshed: [0.95, 0.9, 0.25],
+    uploaded: [0.35, 0.7, 0.9],
+    dirty: [0.8, 0.47, 0.65],
+    cost_low: [0.0, 0.45, 0.7],
+    cost_high: [0.95, 0.9, 0.25],
+};
+
+// Without blue or yellow to tell apart, colors differ between red and cyan
+const TRITANOPIA: PaletteColors = PaletteColors {
+    selection: [0.9, 0.2, 0.2],
+    target: [1.0, 0.4, 0.7],
+    held: [1.0, 0.45, 0.6, 1.0],
+    error: [1.0, 0.3, 0.3, 1.0],
+    input: [0.4, 0.9, 0.9, 1.0],
+    chunk_border: [0.0, 0.62, 0.62],
+    current_chunk: [0.95, 0.25, 0.25],
+    in_view: [0.0, 0.75, 0.75],
+    culled: [0.45, 0.45, 0.45],
+    queued: [0.95, 0.25, 0.25],
+    render_distance: [1.0, 0.55, 0.75],
+    player: [1.0, 1.0, 1.0],
+    meshed: [1.0, 0.55, 0.75],
+    uploaded: [0.0, 0.75, 0.75],
+    dirty: [1.0, 1.0, 1.0],
+    cost_low: [0.0, 0.62, 0.62],
+    cost_high: [0.95, 0.25, 0.25],
+};
+
+/// A set of colors for highlights and debug views, including presets that
+/// stay distinct with the common kinds of color blindness.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Palette {
+    Default,
+    /// For red blindness.
+    Protanopia,
+    /// For green blindness.
+    Deuteranopia,
+    /// For blue blindness.
+    Tritanopia,
+}
+
+impl Palette {
+    pub const ALL: [Palette; 4] = [Palette::Default, Palette::Protanopia, Palette::Deuteranopia, Palette::Tritanopia];
+
+    /// Name in the settings file.
+    pub fn name(self) -> &'static str {
+        match self {
+            Palette::Default => "default",
+            Palette::Protanopia => "protanopia",
+            Palette::Deuteranopia => "deuteranopia",
+            Palette::Tritanopia => "tritanopia",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Palette> {
+        Palette::ALL.into_iter().find(|palette| palette.name() == name)
+    }
+
+    pub fn colors(self) -> &'static PaletteColors {
+        match self {
+            Palette::Default => &DEFAULT,
+            Palette::Protanopia => &PROTANOPIA,
+            Palette::Deuteranopia => &DEUTERANOPIA,
+            Palette::Tritanopia => &TRITANOPIA,
+        }
+    }
+}
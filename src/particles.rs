@@ -0,0 +1,293 @@
+use crate::block::BlockType;
+use crate::explosion::Explosion;
+use crate::gl_utils::{buffer_data, Buffer, BufferType, GlState, ShaderProgram, VertexArray};
+use crate::light::light_level;
+use crate::math::Vec3;
+use crate::random::Random;
+use crate::world::World;
+
+type ParticleInstance = [f32; 12];  // x, y, z, size, textureIndex (negative for none), u, v, uvSpan, r, g, b, a
+
+// Upper bound on live particles, so emitters can't grow the buffer without limit
+const MAX_PARTICLES: usize = 4096;
+const DEBRIS_PER_BLOCK: usize = 24;
+const DEBRIS_GRAVITY: f32 = 20.0;
+// Explosions: blocks that burst into debris at most, the rest vanishing
+// unseen in the smoke, and puffs of smoke per block of radius
+const MAX_EXPLOSION_DEBRIS: usize = 24;
+const SMOKE_PER_RADIUS: f32 = 10.0;
+// Fraction of its speed smoke loses per second, and how fast it then rises
+const SMOKE_DRAG: f32 = 3.0;
+const SMOKE_RISE: f32 = 0.8;
+// Ambient particles spawned per second around the camera
+const BUBBLES_PER_SECOND: f32 = 6.0;
+const SPORES_PER_SECOND: f32 = 10.0;
+// How far around the camera ambient particles appear
+const AMBIENT_RADIUS: f32 = 8.0;
+// Spores only drift where the light level is at most this
+const SPORE_MAX_LIGHT: u8 = 6;
+
+#[derive(Clone, Copy, PartialEq)]
+enum ParticleKind {
+    Debris,
+    Bubble,
+    Spore,
+    Smoke,
+}
+
+struct Particle {
+    kind: ParticleKind,
+    position: Vec3,
+    velocity: Vec3,
+    age: f32,
+    lifetime: f32,
+    size: f32,
+    layer: f32,
+    uv: [f32; 2],
+    color: [f32; 3],
+}
+
+/// Simulates short-lived particles on the CPU and draws them as instanced,
+/// camera-facing quads.
+pub struct ParticleSystem {
+    program: ShaderProgram,
+    vao: VertexArray,
+    _quad: Buffer,
+    instances: Buffer,
+    particles: Vec<Particle>,
+    // Fractional ambient particles carried over between frames
+    bubble_budget: f32,
+    spore_budget: f32,
+    // Draws the particle spread
+    random: Random,
+}
+
+impl ParticleSystem {
+    pub fn new(program: ShaderProgram) -> Option<Self> {
+        let vao = VertexArray::new()?;
+        vao.bind();
+
+        // Corners of the quad every particle is drawn with, as a triangle strip
+        let quad = Buffer::new()?;
+        quad.bind(BufferType::Array);
+        let corners: [f32; 8] = [-0.5, -0.5, 0.5, -0.5, -0.5, 0.5, 0.5, 0.5];
+        buffer_data(BufferType::Array, bytemuck::cast_slice(&corners), gl::STATIC_DRAW);
+        unsafe {
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, 2 * std::mem::size_of::<f32>() as gl::types::GLsizei, std::ptr::null());
+            gl::EnableVertexAttribArray(0);
+        }
+
+        // Per-particle attributes, advancing once per instance
+        let instances = Buffer::new()?;
+        instances.bind(BufferType::Array);
+        unsafe {
+            let stride = std::mem::size_of::<ParticleInstance>() as gl::types::GLsizei;
+            let float = std::mem::size_of::<f32>();
+            // (location, components, offset in floats): position, size, texture index, uv, color
+            for (location, components, offset) in [(1, 3, 0), (2, 1, 3), (3, 1, 4), (4, 3, 5), (5, 4, 8)] {
+                gl::VertexAttribPointer(location, components, gl::FLOAT, gl::FALSE, stride, (offset * float) as *const _);
+                gl::EnableVertexAttribArray(location);
+                gl::VertexAttribDivisor(location, 1);
+            }
+        }
+
+        Some(Self {
+            program,
+            vao,
+            _quad: quad,
+            instances,
+            particles: Vec::new(),
+            bubble_budget: 0.0,
+            spore_budget: 0.0,
+            random: Random::new(0x9E3779B9),
+        })
+    }
+
+    fn emit(&mut self, particle: Particle) {
+        if self.particles.len() < MAX_PARTICLES {
+            self.particles.push(particle);
+        }
+    }
+
+    /// Bursts textured debris out of a broken block centered on `position`.
+    pub fn emit_block_debris(&mut self, position: Vec3, block: BlockType) {
+        let layer = block.info().textures.side.layer();
+        for _ in 0..DEBRIS_PER_BLOCK {
+            let offset = Vec3::new(self.random.signed(), self.random.signed(), self.random.signed()) * 0.4;
+            let velocity = Vec3::new(self.random.signed() * 2.0, 2.0 + self.random.signed() * 1.5, self.random.signed() * 2.0);
+            // Each piece shows a random quarter of the block's texture
+            let uv = [(self.random.signed() + 1.0) * 0.375, (self.random.signed() + 1.0) * 0.375];
+            let lifetime = 0.6 + self.random.signed().abs() * 0.6;
+            self.emit(Particle {
+                kind: ParticleKind::Debris,
+                position: position + offset,
+                velocity,
+                age: 0.0,
+                lifetime,
+                size: 0.12,
+                layer,
+                uv,
+                color: [1.0, 1.0, 1.0],
+            });
+        }
+    }
+
+    /// Billows smoke out of an explosion, with debris from some of the blocks
+    /// it blew away.
+    pub fn emit_explosion(&mut self, explosion: &Explosion) {
+        let every = explosion.removed.len().div_ceil(MAX_EXPLOSION_DEBRIS).max(1);
+        for &((x, y, z), block) in explosion.removed.iter().step_by(every) {
+            self.emit_block_debris(Vec3::new(x as f32, y as f32, z as f32), block);
+        }
+
+        let puffs = (explosion.radius * SMOKE_PER_RADIUS) as usize;
+        for _ in 0..puffs {
+            let direction = Vec3::new(self.random.signed(), self.random.signed(), self.random.signed()).normalize();
+            let speed = explosion.radius * (1.5 + self.random.signed());
+            let shade = 0.35 + self.random.signed() * 0.1;
+            let lifetime = 1.5 + self.random.signed().abs();
+            let size = 0.8 + self.random.signed().abs() * 0.6;
+            self.emit(Particle {
+                kind: ParticleKind::Smoke,
+                position: explosion.center + direction * (explosion.radius * 0.3),
+                velocity: direction * speed,
+                age: 0.0,
+                lifetime,
+                size,
+                layer: -1.0,
+                uv: [0.0, 0.0],
+                color: [shade, shade, shade],
+            });
+        }
+    }
+
+    /// Advances every particle and spawns ambient ones around the camera: bubbles
+    /// underwater and spores in the dark parts of caves.
+    pub fn update(&mut self, world: &World, camera_position: Vec3, daylight: f32, delta_time: f32) {
+        let block_at = |position: Vec3| {
+            world.get_block(position.x.round() as i32, position.y.round() as i32, position.z.round() as i32)
+        };
+
+        for particle in &mut self.particles {
+            particle.age += delta_time;
+            match particle.kind {
+                ParticleKind::Debris => {
+                    particle.velocity.y -= DEBRIS_GRAVITY * delta_time;
+                    let moved = particle.position + particle.velocity * delta_time;
+                    if block_at(moved).collision_height().is_some() {
+                        // Come to rest on whatever was hit
+                        particle.velocity = Vec3::zero();
+                    } else {
+                        particle.position = moved;
+                    }
+                },
+                ParticleKind::Bubble => {
+                    // Rise while wobbling from side to side
+                    let wobble = (particle.age * 6.0).sin();
+                    let drift = Vec3::new(particle.velocity.x * wobble, particle.velocity.y, particle.velocity.z * wobble);
+                    particle.position = particle.position + drift * delta_time;
+                    // Bubbles pop at the surface
+                    if block_at(particle.position) != BlockType::Water {
+                        particle.age = particle.lifetime;
+                    }
+                },
+                ParticleKind::Spore => {
+                    particle.position = particle.position + particle.velocity * delta_time;
+                },
+                ParticleKind::Smoke => {
+                    // Burst out, slow down and drift upwards
+                    particle.velocity = particle.velocity * (1.0 - SMOKE_DRAG * delta_time).max(0.0);
+                    particle.velocity.y += SMOKE_RISE * SMOKE_DRAG * delta_time;
+                    particle.position = particle.position + particle.velocity * delta_time;
+                },
+            }
+        }
+        self.particles.retain(|particle| particle.age < particle.lifetime);
+
+        let camera_block = block_at(camera_position);
+        if camera_block == BlockType::Water {
+            self.bubble_budget += BUBBLES_PER_SECOND * delta_time;
+            while self.bubble_budget >= 1.0 {
+                self.bubble_budget -= 1.0;
+                let offset = Vec3::new(self.random.signed(), self.random.signed() - 0.5, self.random.signed()) * (AMBIENT_RADIUS / 2.0);
+                let velocity = Vec3::new(self.random.signed() * 0.4, 1.5, self.random.signed() * 0.4);
+                self.emit(Particle {
+                    kind: ParticleKind::Bubble,
+                    position: camera_position + offset,
+                    velocity,
+                    age: 0.0,
+                    lifetime: 3.0,
+                    size: 0.1,
+                    layer: -1.0,
+                    uv: [0.0, 0.0],
+                    color: [0.8, 0.9, 1.0],
+                });
+            }
+        } else if world.is_in_cave(camera_position) {
+            self.spore_budget += SPORES_PER_SECOND * delta_time;
+            while self.spore_budget >= 1.0 {
+                self.spore_budget -= 1.0;
+                let offset = Vec3::new(self.random.signed(), self.random.signed(), self.random.signed()) * AMBIENT_RADIUS;
+                let velocity = Vec3::new(self.random.signed(), self.random.signed() * 0.5, self.random.signed()) * 0.3;
+                let lifetime = 4.0 + self.random.signed().abs() * 3.0;
+                // Torches and cave openings keep the spores away
+                let position = camera_position + offset;
+                let block = (position.x.round() as i32, position.y.round() as i32, position.z.round() as i32);
+                if light_level(world, block, daylight) > SPORE_MAX_LIGHT {
+                    continue;
+                }
+                self.emit(Particle {
+                    kind: ParticleKind::Spore,
+                    position,
+                    velocity,
+                    age: 0.0,
+                    lifetime,
+                    size: 0.05,
+                    layer: -1.0,
+                    uv: [0.0, 0.0],
+                    color: [0.7, 0.9, 0.5],
+                });
+            }
+        }
+    }
+
+    /// Draws every live particle facing the camera. `right` and `up` are the
+    /// camera's axes; the block texture array must be bound to texture unit 0.
+    pub fn draw(&self, right: Vec3, up: Vec3) {
+        if self.particles.is_empty() {
+            return;
+        }
+
+        let instances = self.particles.iter().map(|particle| {
+            // Fade in and out over the first and last fifth of the lifetime
+            let life = particle.age / particle.lifetime;
+            let alpha = match particle.kind {
+                ParticleKind::Debris => 1.0,
+                _ => (life.min(1.0 - life) * 5.0).min(1.0),
+            };
+            let [r, g, b] = particle.color;
+            let [u, v] = particle.uv;
+            let p = particle.position;
+            [p.x, p.y, p.z, particle.size, particle.layer, u, v, 0.25, r, g, b, alpha]
+        }).collect::<Vec<ParticleInstance>>();
+
+        self.program.use_program();
+        self.vao.bind();
+        self.instances.bind(BufferType::Array);
+        // Orphan last frame's data rather than waiting for the GPU to finish with it
+        buffer_data(BufferType::Array, bytemuck::cast_slice(&instances), gl::STREAM_DRAW);
+
+        unsafe {
+            gl::Uniform1i(gl::GetUniformLocation(self.program.0, b"blockTextures\0".as_ptr() as *const i8), 0);
+            let loc = gl::GetUniformLocation(self.program.0, b"cameraRight\0".as_ptr() as *const i8);
+            gl::Uniform3f(loc, right.x, right.y, right.z);
+            let loc = gl::GetUniformLocation(self.program.0, b"cameraUp\0".as_ptr() as *const i8);
+            gl::Uniform3f(loc, up.x, up.y, up.z);
+
+            // Particles are small and mostly translucent: test depth, but don't write it
+            GlState::depth_mask(false);
+            gl::DrawArraysInstanced(gl::TRIANGLE_STRIP, 0, 4, instances.len() as i32);
+            GlState::depth_mask(true);
+        }
+    }
+}
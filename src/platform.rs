@@ -0,0 +1,169 @@
+use crate::error::EngineError;
+use crate::sound::SoundManager;
+use std::time::Duration;
+
+#[cfg(feature = "sdl2")]
+pub use crate::platform_sdl::{show_error, SdlPlatform as ActivePlatform};
+#[cfg(feature = "winit")]
+pub use crate::platform_winit::{show_error, WinitPlatform as ActivePlatform};
+#[cfg(feature = "web")]
+pub use crate::platform_web::{show_error, WebPlatform as ActivePlatform};
+
+#[cfg(any(all(feature = "sdl2", feature = "winit"), all(feature = "sdl2", feature = "web"), all(feature = "winit", feature = "web")))]
+compile_error!("the sdl2, winit and web features each pick the platform, enable only one (winit and web builds need --no-default-features)");
+#[cfg(not(any(feature = "sdl2", feature = "winit", feature = "web")))]
+compile_error!("enable the sdl2, winit or web feature to pick a platform");
+
+/// The keys the game can bind, out of a full keyboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum Key {
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    Num0, Num1, Num2, Num3, Num4, Num5, Num6, Num7, Num8, Num9,
+    F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+    Escape,
+    Return,
+    Backspace,
+    Tab,
+    Space,
+    LShift,
+    Up,
+    Down,
+    Left,
+    Right,
+    Minus,
+    Equals,
+    Backquote,
+    KpEnter,
+    KpPlus,
+    KpMinus,
+}
+
+impl Key {
+    /// All keys, indexed by their numeric id.
+    pub const ALL: [Key; 64] = [
+        Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H, Key::I, Key::J, Key::K,
+        Key::L, Key::M, Key::N, Key::O, Key::P, Key::Q, Key::R, Key::S, Key::T, Key::U, Key::V,
+        Key::W, Key::X, Key::Y, Key::Z, Key::Num0, Key::Num1, Key::Num2, Key::Num3, Key::Num4,
+        Key::Num5, Key::Num6, Key::Num7, Key::Num8, Key::Num9, Key::F1, Key::F2, Key::F3, Key::F4,
+        Key::F5, Key::F6, Key::F7, Key::F8, Key::F9, Key::F10, Key::F11, Key::F12, Key::Escape,
+        Key::Return, Key::Backspace, Key::Tab, Key::Space, Key::LShift, Key::Up, Key::Down,
+        Key::Left, Key::Right, Key::Minus, Key::Equals, Key::Backquote, Key::KpEnter, Key::KpPlus,
+        Key::KpMinus,
+    ];
+
+    /// Looks up a key by the numeric id it is recorded with.
+    pub fn from_id(id: u8) -> Option<Key> {
+        Self::ALL.get(id as usize).copied()
+    }
+
+    /// Numeric id used to record this key in replays.
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseButton {
+    Left,
+    Right,
+}
+
+/// The window and input events the game reacts to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// `key` is the key by what it types on the keyboard layout, and `physical`
+    /// by where it is, for bindings like the number row that go by position.
+    /// Either is `None` for keys without bindings.
+    KeyDown { key: Option<Key>, physical: Option<Key> },
+    /// Text typed while text input is on.
+    TextInput(String),
+    /// How far the mouse moved, in pixels.
+    MouseMotion { xrel: i32, yrel: i32 },
+    /// A click at a point in the window.
+    MouseButtonDown { button: MouseButton, x: i32, y: i32 },
+    /// Wheel notches, positive away from the player.
+    MouseWheel { y: i32 },
+    FocusLost,
+    FocusGained,
+    /// The second window's close button was clicked. It stays open until
+    /// `close_second_window`.
+    SecondWindowClosed,
+    Quit,
+}
+
+/// What the game window is drawn with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Graphics {
+    /// An OpenGL 3.3 core context, current on this thread.
+    OpenGl,
+    /// Nothing yet: the renderer makes a surface of its own on the window,
+    /// as the wgpu backend does.
+    Surface,
+}
+
+/// The game window, with an OpenGL 3.3 core context current on this thread
+/// unless it was opened for a surface, and the keyboard, mouse and sound that
+/// come with it. SDL2 provides it by default, winit with the `winit` feature
+/// for builds without SDL2's system library, or a canvas of the page with the
+/// `web` feature in the browser; `ActivePlatform` is whichever was picked.
+pub trait Platform: Sized {
+    /// The window itself, for renderers that make a surface on it.
+    #[cfg(feature = "wgpu")]
+    type Window: wgpu::rwh::HasWindowHandle + wgpu::rwh::HasDisplayHandle;
+
+    /// Opens a window of `width` by `height` pixels drawn with `graphics`,
+    /// loading the GL functions for OpenGL. A `hidden` window is never shown,
+    /// for runs that draw nothing.
+    fn open(title: &str, width: u32, height: u32, hidden: bool, graphics: Graphics) -> Result<Self, EngineError>;
+
+    #[cfg(feature = "wgpu")]
+    fn window(&self) -> &Self::Window;
+
+    /// Events since the last call, oldest first.
+    fn poll_events(&mut self) -> Vec<Event>;
+
+    /// Whether a key is down, by its position on the keyboard.
+    fn is_key_held(&self, key: Key) -> bool;
+
+    fn is_left_button_held(&self) -> bool;
+
+    /// Hides the cursor and keeps it in the window, reporting only motion,
+    /// or gives it back for menus.
+    fn set_relative_mouse_mode(&mut self, enabled: bool);
+
+    /// Turns `Event::TextInput` on or off, for text fields.
+    fn set_text_input(&mut self, enabled: bool);
+
+    /// Waits for the display when swapping buffers. Windows without OpenGL
+    /// leave it to their renderer.
+    fn set_vsync(&mut self, enabled: bool) -> Result<(), String>;
+
+    fn set_fullscreen(&mut self, enabled: bool) -> Result<(), String>;
+
+    /// Shows what was drawn with OpenGL since the last call. Does nothing
+    /// for windows without it, whose renderer presents its frames.
+    fn swap_buffers(&mut self);
+
+    /// Opens the default audio device. Without one the game runs silently.
+    fn open_sound(&self) -> SoundManager;
+
+    /// Rumbles every connected controller that can, its heavy low frequency
+    /// motor at `low` and its light high frequency one at `high`, both from 0
+    /// to 1, for `duration`. Replaces any rumble still going.
+    fn rumble(&mut self, low: f32, high: f32, duration: Duration);
+
+    /// Opens a second window sharing the GL context, for debug views, which
+    /// fails for windows without one. Its input isn't reported, except for
+    /// `Event::SecondWindowClosed`.
+    fn open_second_window(&mut self, title: &str, width: u32, height: u32) -> Result<(), String>;
+
+    fn close_second_window(&mut self);
+
+    fn has_second_window(&self) -> bool;
+
+    /// Runs `draw` with the second window's framebuffer bound and shows what
+    /// it drew, then goes back to the game window. Does nothing without a
+    /// second window.
+    fn draw_second_window(&mut self, draw: &mut dyn FnMut());
+}
@@ -0,0 +1,309 @@
+use crate::error::EngineError;
+use crate::platform::{Event, Graphics, Key, MouseButton, Platform};
+use crate::sound::SoundManager;
+use sdl2::controller::GameController;
+use sdl2::event::{Event as SdlEvent, WindowEvent};
+use sdl2::keyboard::{Keycode, Scancode};
+use sdl2::messagebox::{show_simple_message_box, MessageBoxFlag};
+use sdl2::mouse::MouseButton as SdlMouseButton;
+use sdl2::video::{FullscreenType, GLContext, GLProfile, SwapInterval, Window};
+use sdl2::{EventPump, GameControllerSubsystem, Sdl, VideoSubsystem};
+use std::time::Duration;
+
+// Each key with what SDL calls it by meaning and by position
+const KEYS: [(Key, Keycode, Scancode); 64] = [
+    (Key::A, Keycode::A, Scancode::A),
+    (Key::B, Keycode::B, Scancode::B),
+    (Key::C, Keycode::C, Scancode::C),
+    (Key::D, Keycode::D, Scancode::D),
+    (Key::E, Keycode::E, Scancode::E),
+    (Key::F, Keycode::F, Scancode::F),
+    (Key::G, Keycode::G, Scancode::G),
+    (Key::H, Keycode::H, Scancode::H),
+    (Key::I, Keycode::I, Scancode::I),
+    (Key::J, Keycode::J, Scancode::J),
+    (Key::K, Keycode::K, Scancode::K),
+    (Key::L, Keycode::L, Scancode::L),
+    (Key::M, Keycode::M, Scancode::M),
+    (Key::N, Keycode::N, Scancode::N),
+    (Key::O, Keycode::O, Scancode::O),
+    (Key::P, Keycode::P, Scancode::P),
+    (Key::Q, Keycode::Q, Scancode::Q),
+    (Key::R, Keycode::R, Scancode::R),
+    (Key::S, Keycode::S, Scancode::S),
+    (Key::T, Keycode::T, Scancode::T),
+    (Key::U, Keycode::U, Scancode::U),
+    (Key::V, Keycode::V, Scancode::V),
+    (Key::W, Keycode::W, Scancode::W),
+    (Key::X, Keycode::X, Scancode::X),
+    (Key::Y, Keycode::Y, Scancode::Y),
+    (Key::Z, Keycode::Z, Scancode::Z),
+    (Key::Num0, Keycode::Num0, Scancode::Num0),
+    (Key::Num1, Keycode::Num1, Scancode::Num1),
+    (Key::Num2, Keycode::Num2, Scancode::Num2),
+    (Key::Num3, Keycode::Num3, Scancode::Num3),
+    (Key::Num4, Keycode::Num4, Scancode::Num4),
+    (Key::Num5, Keycode::Num5, Scancode::Num5),
+    (Key::Num6, Keycode::Num6, Scancode::Num6),
+    (Key::Num7, Keycode::Num7, Scancode::Num7),
+    (Key::Num8, Keycode::Num8, Scancode::Num8),
+    (Key::Num9, Keycode::Num9, Scancode::Num9),
+    (Key::F1, Keycode::F1, Scancode::F1),
+    (Key::F2, Keycode::F2, Scancode::F2),
+    (Key::F3, Keycode::F3, Scancode::F3),
+    (Key::F4, Keycode::F4, Scancode::F4),
+    (Key::F5, Keycode::F5, Scancode::F5),
+    (Key::F6, Keycode::F6, Scancode::F6),
+    (Key::F7, Keycode::F7, Scancode::F7),
+    (Key::F8, Keycode::F8, Scancode::F8),
+    (Key::F9, Keycode::F9, Scancode::F9),
+    (Key::F10, Keycode::F10, Scancode::F10),
+    (Key::F11, Keycode::F11, Scancode::F11),
+    (Key::F12, Keycode::F12, Scancode::F12),
+    (Key::Escape, Keycode::Escape, Scancode::Escape),
+    (Key::Return, Keycode::Return, Scancode::Return),
+    (Key::Backspace, Keycode::Backspace, Scancode::Backspace),
+    (Key::Tab, Keycode::Tab, Scancode::Tab),
+    (Key::Space, Keycode::Space, Scancode::Space),
+    (Key::LShift, Keycode::LShift, Scancode::LShift),
+    (Key::Up, Keycode::Up, Scancode::Up),
+    (Key::Down, Keycode::Down, Scancode::Down),
+    (Key::Left, Keycode::Left, Scancode::Left),
+    (Key::Right, Keycode::Right, Scancode::Right),
+    (Key::Minus, Keycode::Minus, Scancode::Minus),
+    (Key::Equals, Keycode::Equals, Scancode::Equals),
+    (Key::Backquote, Keycode::Backquote, Scancode::Grave),
+    (Key::KpEnter, Keycode::KpEnter, Scancode::KpEnter),
+    (Key::KpPlus, Keycode::KpPlus, Scancode::KpPlus),
+    (Key::KpMinus, Keycode::KpMinus, Scancode::KpMinus),
+];
+
+/// The platform on SDL2, which also provides the sound.
+pub struct SdlPlatform {
+    sdl: Sdl,
+    video: VideoSubsystem,
+    // Fields drop in order, so the context goes before its windows. Windows
+    // opened for a surface have none
+    gl_context: Option<GLContext>,
+    window: Window,
+    second_window: Option<Window>,
+    event_pump: EventPump,
+    vsync: bool,
+    // Controllers go before their subsystem too
+    controllers: Vec<GameController>,
+    controller_subsystem: Option<GameControllerSubsystem>,
+}
+
+impl Platform for SdlPlatform {
+    #[cfg(feature = "wgpu")]
+    type Window = Window;
+
+    fn open(title: &str, width: u32, height: u32, hidden: bool, graphics: Graphics) -> Result<Self, EngineError> {
+        let sdl = sdl2::init()?;
+        let video = sdl.video()?;
+
+        let gl_attr = video.gl_attr();
+        gl_attr.set_context_profile(GLProfile::Core);
+        gl_attr.set_context_version(3, 3);
+        gl_attr.set_context_flags().debug().set();
+
+        let mut window_builder = video.window(title, width, height);
+        window_builder.position_centered();
+        if graphics == Graphics::OpenGl {
+            window_builder.opengl();
+        }
+        if hidden {
+            window_builder.hidden();
+        }
+        let window = window_builder.build()?;
+
+        let gl_context = if graphics == Graphics::OpenGl {
+            let gl_context = match window.gl_create_context() {
+                Ok(context) => context,
+                // Drivers older than GL 3.3 can't make the context at all. A legacy
+                // one still says which version they have, for the error about it
+                Err(e) => {
+                    log::warn!(target: "render", "No OpenGL 3.3 core context: {}", e);
+                    gl_attr.set_context_profile(GLProfile::Compatibility);
+                    gl_attr.set_context_version(2, 1);
+                    gl_attr.set_context_flags().set();
+                    window.gl_create_context().map_err(|_| e)?
+                },
+            };
+            gl::load_with(|s| video.gl_get_proc_address(s) as *const _);
+            Some(gl_context)
+        } else {
+            None
+        };
+        let event_pump = sdl.event_pump()?;
+        // Controllers only rumble, so the game plays on without them. Ones
+        // already plugged in are reported as added on the first poll
+        let controller_subsystem = sdl.game_controller()
+            .inspect_err(|e| log::warn!(target: "game", "No controller support: {}", e))
+            .ok();
+        Ok(Self {
+            sdl,
+            video,
+            gl_context,
+            window,
+            second_window: None,
+            event_pump,
+            vsync: false,
+            controllers: Vec::new(),
+            controller_subsystem,
+        })
+    }
+
+    #[cfg(feature = "wgpu")]
+    fn window(&self) -> &Window {
+        &self.window
+    }
+
+    fn poll_events(&mut self) -> Vec<Event> {
+        let main_window = self.window.id();
+        let events = self.event_pump.poll_iter().collect::<Vec<_>>();
+        events.into_iter().filter_map(|event| Some(match event {
+            SdlEvent::ControllerDeviceAdded { which, .. } => {
+                self.connect_controller(which);
+                return None;
+            },
+            SdlEvent::ControllerDeviceRemoved { which, .. } => {
+                self.controllers.retain(|controller| controller.instance_id() != which);
+                return None;
+            },
+            SdlEvent::Window { window_id, win_event: WindowEvent::Close, .. } if window_id != main_window => Event::SecondWindowClosed,
+            _ if event.get_window_id().is_some_and(|id| id != main_window) => return None,
+            SdlEvent::KeyDown { keycode, scancode, .. } => Event::KeyDown {
+                key: keycode.and_then(|keycode| KEYS.iter().find(|&&(_, k, _)| k == keycode)).map(|&(key, _, _)| key),
+                physical: scancode.and_then(|scancode| KEYS.iter().find(|&&(_, _, s)| s == scancode)).map(|&(key, _, _)| key),
+            },
+            SdlEvent::TextInput { text, .. } => Event::TextInput(text),
+            SdlEvent::MouseMotion { xrel, yrel, .. } => Event::MouseMotion { xrel, yrel },
+            SdlEvent::MouseButtonDown { mouse_btn, x, y, .. } => Event::MouseButtonDown {
+                button: match mouse_btn {
+                    SdlMouseButton::Left => MouseButton::Left,
+                    SdlMouseButton::Right => MouseButton::Right,
+                    _ => return None,
+                },
+                x,
+                y,
+            },
+            SdlEvent::MouseWheel { y, .. } => Event::MouseWheel { y },
+            // SDL only quits by itself once every window is closed
+            SdlEvent::Window { win_event: WindowEvent::Close, .. } => Event::Quit,
+            SdlEvent::Window { win_event: WindowEvent::FocusLost, .. } => Event::FocusLost,
+            SdlEvent::Window { win_event: WindowEvent::FocusGained, .. } => Event::FocusGained,
+            SdlEvent::Quit { .. } => Event::Quit,
+            _ => return None,
+        })).collect()
+    }
+
+    fn is_key_held(&self, key: Key) -> bool {
+        KEYS.iter().find(|&&(k, _, _)| k == key)
+            .is_some_and(|&(_, _, scancode)| self.event_pump.keyboard_state().is_scancode_pressed(scancode))
+    }
+
+    fn is_left_button_held(&self) -> bool {
+        self.event_pump.mouse_state().left()
+    }
+
+    fn set_relative_mouse_mode(&mut self, enabled: bool) {
+        self.sdl.mouse().set_relative_mouse_mode(enabled);
+    }
+
+    fn set_text_input(&mut self, enabled: bool) {
+        if enabled {
+            self.video.text_input().start();
+        } else {
+            self.video.text_input().stop();
+        }
+    }
+
+    fn set_vsync(&mut self, enabled: bool) -> Result<(), String> {
+        self.vsync = enabled;
+        if self.gl_context.is_none() {
+            return Ok(());
+        }
+        let interval = if enabled { SwapInterval::VSync } else { SwapInterval::Immediate };
+        self.video.gl_set_swap_interval(interval)
+    }
+
+    fn set_fullscreen(&mut self, enabled: bool) -> Result<(), String> {
+        self.window.set_fullscreen(if enabled { FullscreenType::True } else { FullscreenType::Off })
+    }
+
+    fn swap_buffers(&mut self) {
+        if self.gl_context.is_some() {
+            self.window.gl_swap_window();
+        }
+    }
+
+    fn open_sound(&self) -> SoundManager {
+        SoundManager::new(&self.sdl)
+    }
+
+    fn rumble(&mut self, low: f32, high: f32, duration: Duration) {
+        let strength = |amount: f32| (amount.clamp(0.0, 1.0) * u16::MAX as f32) as u16;
+        let milliseconds = duration.as_millis().min(u32::MAX as u128) as u32;
+        for controller in self.controllers.iter_mut().filter(|controller| controller.has_rumble()) {
+            if let Err(e) = controller.set_rumble(strength(low), strength(high), milliseconds) {
+                log::warn!(target: "game", "Failed to rumble {}: {}", controller.name(), e);
+            }
+        }
+    }
+
+    fn open_second_window(&mut self, title: &str, width: u32, height: u32) -> Result<(), String> {
+        if self.gl_context.is_none() {
+            return Err("The second window needs OpenGL".to_string());
+        }
+        let window = self.video.window(title, width, height).opengl().build().map_err(|e| e.to_string())?;
+        self.second_window = Some(window);
+        Ok(())
+    }
+
+    fn close_second_window(&mut self) {
+        self.second_window = None;
+    }
+
+    fn has_second_window(&self) -> bool {
+        self.second_window.is_some()
+    }
+
+    fn draw_second_window(&mut self, draw: &mut dyn FnMut()) {
+        let (Some(second_window), Some(gl_context)) = (&self.second_window, &self.gl_context) else {
+            return;
+        };
+        if let Err(e) = second_window.gl_make_current(gl_context) {
+            log::error!(target: "render", "Failed to draw the second window: {}", e);
+            return;
+        }
+        // Waiting for vsync on both windows would halve the frame rate
+        let _ = self.video.gl_set_swap_interval(SwapInterval::Immediate);
+        draw();
+        second_window.gl_swap_window();
+        if let Err(e) = self.window.gl_make_current(gl_context) {
+            log::error!(target: "render", "Failed to go back to the game window: {}", e);
+        }
+        let _ = self.set_vsync(self.vsync);
+    }
+}
+
+impl SdlPlatform {
+    fn connect_controller(&mut self, joystick_index: u32) {
+        let Some(subsystem) = &self.controller_subsystem else {
+            return;
+        };
+        match subsystem.open(joystick_index) {
+            Ok(controller) => {
+                log::info!(target: "game", "Controller connected: {}", controller.name());
+                self.controllers.push(controller);
+            },
+            Err(e) => log::warn!(target: "game", "Failed to open controller {}: {}", joystick_index, e),
+        }
+    }
+}
+
+/// Shows an error in a message box, for when there's no game window to show it in.
+pub fn show_error(title: &str, message: &str) {
+    let _ = show_simple_message_box(MessageBoxFlag::ERROR, title, message, None);
+}
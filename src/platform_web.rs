@@ -0,0 +1,393 @@
+use crate::error::EngineError;
+use crate::platform::{Event, Graphics, Key, MouseButton, Platform};
+use crate::sound::SoundManager;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::ptr::NonNull;
+use std::rc::Rc;
+use std::time::Duration;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{Document, EventTarget, HtmlCanvasElement, KeyboardEvent, MouseEvent, WheelEvent};
+use wgpu::rwh::{DisplayHandle, HandleError, HasDisplayHandle, HasWindowHandle, WebCanvasWindowHandle, WindowHandle};
+
+/// Id of the canvas the game draws in. Pages without one get a new canvas at
+/// the end of their body.
+pub const CANVAS_ID: &str = "game";
+// Pixels of smooth scrolling that make up one wheel notch
+const PIXELS_PER_NOTCH: f64 = 40.0;
+// Lines of scrolling that make up one wheel notch, as browsers scroll by lines
+const LINES_PER_NOTCH: f64 = 3.0;
+// Where keypad keys are, in a keyboard event
+const NUMPAD_LOCATION: u32 = 3;
+
+// Each key with its code, which says where it is, and the key value it types
+// without modifiers. Keypad keys type the same as their twins elsewhere, so
+// they go by position
+const KEYS: [(Key, &str, &str); 64] = [
+    (Key::A, "KeyA", "a"),
+    (Key::B, "KeyB", "b"),
+    (Key::C, "KeyC", "c"),
+    (Key::D, "KeyD", "d"),
+    (Key::E, "KeyE", "e"),
+    (Key::F, "KeyF", "f"),
+    (Key::G, "KeyG", "g"),
+    (Key::H, "KeyH", "h"),
+    (Key::I, "KeyI", "i"),
+    (Key::J, "KeyJ", "j"),
+    (Key::K, "KeyK", "k"),
+    (Key::L, "KeyL", "l"),
+    (Key::M, "KeyM", "m"),
+    (Key::N, "KeyN", "n"),
+    (Key::O, "KeyO", "o"),
+    (Key::P, "KeyP", "p"),
+    (Key::Q, "KeyQ", "q"),
+    (Key::R, "KeyR", "r"),
+    (Key::S, "KeyS", "s"),
+    (Key::T, "KeyT", "t"),
+    (Key::U, "KeyU", "u"),
+    (Key::V, "KeyV", "v"),
+    (Key::W, "KeyW", "w"),
+    (Key::X, "KeyX", "x"),
+    (Key::Y, "KeyY", "y"),
+    (Key::Z, "KeyZ", "z"),
+    (Key::Num0, "Digit0", "0"),
+    (Key::Num1, "Digit1", "1"),
+    (Key::Num2, "Digit2", "2"),
+    (Key::Num3, "Digit3", "3"),
+    (Key::Num4, "Digit4", "4"),
+    (Key::Num5, "Digit5", "5"),
+    (Key::Num6, "Digit6", "6"),
+    (Key::Num7, "Digit7", "7"),
+    (Key::Num8, "Digit8", "8"),
+    (Key::Num9, "Digit9", "9"),
+    (Key::F1, "F1", "F1"),
+    (Key::F2, "F2", "F2"),
+    (Key::F3, "F3", "F3"),
+    (Key::F4, "F4", "F4"),
+    (Key::F5, "F5", "F5"),
+    (Key::F6, "F6", "F6"),
+    (Key::F7, "F7", "F7"),
+    (Key::F8, "F8", "F8"),
+    (Key::F9, "F9", "F9"),
+    (Key::F10, "F10", "F10"),
+    (Key::F11, "F11", "F11"),
+    (Key::F12, "F12", "F12"),
+    (Key::Escape, "Escape", "Escape"),
+    (Key::Return, "Enter", "Enter"),
+    (Key::Backspace, "Backspace", "Backspace"),
+    (Key::Tab, "Tab", "Tab"),
+    (Key::Space, "Space", " "),
+    (Key::LShift, "ShiftLeft", "Shift"),
+    (Key::Up, "ArrowUp", "ArrowUp"),
+    (Key::Down, "ArrowDown", "ArrowDown"),
+    (Key::Left, "ArrowLeft", "ArrowLeft"),
+    (Key::Right, "ArrowRight", "ArrowRight"),
+    (Key::Minus, "Minus", "-"),
+    (Key::Equals, "Equal", "="),
+    (Key::Backquote, "Backquote", "`"),
+    (Key::KpEnter, "NumpadEnter", ""),
+    (Key::KpPlus, "NumpadAdd", ""),
+    (Key::KpMinus, "NumpadSubtract", ""),
+];
+
+/// The canvas the game draws in, standing in for a window. wgpu draws on it
+/// through WebGL2.
+pub struct CanvasWindow {
+    canvas: HtmlCanvasElement,
+}
+
+impl HasWindowHandle for CanvasWindow {
+    fn window_handle(&self) -> Result<WindowHandle<'_>, HandleError> {
+        let value: &JsValue = self.canvas.as_ref();
+        let handle = WebCanvasWindowHandle::new(NonNull::from(value).cast());
+        // The handle points at the canvas, which lives as long as the borrow
+        Ok(unsafe { WindowHandle::borrow_raw(handle.into()) })
+    }
+}
+
+impl HasDisplayHandle for CanvasWindow {
+    fn display_handle(&self) -> Result<DisplayHandle<'_>, HandleError> {
+        Ok(DisplayHandle::web())
+    }
+}
+
+/// The platform in the browser, on a canvas of the page, for the `web`
+/// feature. It only has a surface for wgpu to draw on, since the `gl` crate
+/// can't reach WebGL2, and no sound, controllers or second window.
+pub struct WebPlatform {
+    window: CanvasWindow,
+    document: Document,
+    input: Rc<RefCell<Input>>,
+    // Taken off the page again along with the platform
+    _listeners: Vec<Listener>,
+}
+
+// An event listener added to the page
+struct Listener {
+    target: EventTarget,
+    kind: &'static str,
+    callback: Closure<dyn FnMut(web_sys::Event)>,
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        let _ = self.target.remove_event_listener_with_callback(self.kind, self.callback.as_ref().unchecked_ref());
+    }
+}
+
+impl Platform for WebPlatform {
+    type Window = CanvasWindow;
+
+    fn open(title: &str, width: u32, height: u32, hidden: bool, graphics: Graphics) -> Result<Self, EngineError> {
+        if graphics == Graphics::OpenGl {
+            return Err(EngineError::Window("The browser only draws with wgpu".to_string()));
+        }
+        let window = web_sys::window().ok_or_else(|| "No browser window".to_string())?;
+        let document = window.document().ok_or_else(|| "No document to draw in".to_string())?;
+        let describe = |e: JsValue| format!("{:?}", e);
+        let canvas = match document.get_element_by_id(CANVAS_ID) {
+            Some(element) => element.dyn_into::<HtmlCanvasElement>()
+                .map_err(|_| format!("#{} isn't a canvas", CANVAS_ID))?,
+            None => {
+                let canvas = document.create_element("canvas").map_err(describe)?.unchecked_into::<HtmlCanvasElement>();
+                canvas.set_id(CANVAS_ID);
+                let body = document.body().ok_or_else(|| "The page has no body".to_string())?;
+                body.append_child(&canvas).map_err(describe)?;
+                canvas
+            },
+        };
+        canvas.set_width(width);
+        canvas.set_height(height);
+        if hidden {
+            canvas.set_attribute("hidden", "").map_err(describe)?;
+        }
+        document.set_title(title);
+
+        let input = Rc::new(RefCell::new(Input::default()));
+        let window_target: &EventTarget = window.as_ref();
+        let canvas_target: &EventTarget = canvas.as_ref();
+        let document_target: &EventTarget = document.as_ref();
+        let listeners = vec![
+            listen(&input, window_target, "keydown", Input::key_down)?,
+            listen(&input, window_target, "keyup", Input::key_up)?,
+            listen(&input, window_target, "blur", |input, _| input.focus_lost())?,
+            listen(&input, window_target, "focus", |input, _| input.events.push(Event::FocusGained))?,
+            listen(&input, canvas_target, "mousemove", Input::mouse_move)?,
+            listen(&input, canvas_target, "mousedown", Input::mouse_down)?,
+            // Buttons let go of off the canvas still count
+            listen(&input, window_target, "mouseup", Input::mouse_up)?,
+            listen(&input, canvas_target, "wheel", Input::wheel)?,
+            // Right clicks are the game's, not the page's menu
+            listen(&input, canvas_target, "contextmenu", |_, event| event.prevent_default())?,
+            listen(&input, document_target, "pointerlockchange", {
+                let document = document.clone();
+                move |input, _| input.pointer_lock_changed(document.pointer_lock_element().is_some())
+            })?,
+        ];
+        Ok(Self { window: CanvasWindow { canvas }, document, input, _listeners: listeners })
+    }
+
+    fn window(&self) -> &CanvasWindow {
+        &self.window
+    }
+
+    fn poll_events(&mut self) -> Vec<Event> {
+        std::mem::take(&mut self.input.borrow_mut().events)
+    }
+
+    fn is_key_held(&self, key: Key) -> bool {
+        self.input.borrow().held.contains(&key)
+    }
+
+    fn is_left_button_held(&self) -> bool {
+        self.input.borrow().left_button
+    }
+
+    // Browsers only lock the mouse while handling a click or key press, so the
+    // game asks for it when one comes in
+    fn set_relative_mouse_mode(&mut self, enabled: bool) {
+        self.input.borrow_mut().relative_mouse = enabled;
+        if enabled {
+            self.window.canvas.request_pointer_lock();
+        } else {
+            self.document.exit_pointer_lock();
+        }
+    }
+
+    fn set_text_input(&mut self, enabled: bool) {
+        self.input.borrow_mut().text_input = enabled;
+    }
+
+    // The browser shows frames at the display's rate, which can't be changed
+    fn set_vsync(&mut self, _enabled: bool) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn set_fullscreen(&mut self, enabled: bool) -> Result<(), String> {
+        if enabled {
+            self.window.canvas.request_fullscreen().map_err(|e| format!("{:?}", e))
+        } else {
+            self.document.exit_fullscreen();
+            Ok(())
+        }
+    }
+
+    // wgpu presents its own frames
+    fn swap_buffers(&mut self) {}
+
+    fn open_sound(&self) -> SoundManager {
+        log::warn!(target: "audio", "No sound: it needs the sdl2 feature");
+        SoundManager::disabled()
+    }
+
+    fn rumble(&mut self, _low: f32, _high: f32, _duration: Duration) {}
+
+    fn open_second_window(&mut self, _title: &str, _width: u32, _height: u32) -> Result<(), String> {
+        Err("The browser has no second window".to_string())
+    }
+
+    fn close_second_window(&mut self) {}
+
+    fn has_second_window(&self) -> bool {
+        false
+    }
+
+    fn draw_second_window(&mut self, _draw: &mut dyn FnMut()) {}
+}
+
+/// Shows an error in an alert, the browser's message box.
+pub fn show_error(title: &str, message: &str) {
+    if let Some(window) = web_sys::window() {
+        let _ = window.alert_with_message(&format!("{}\n\n{}", title, message));
+    }
+}
+
+// Adds a listener handing the page's events of a kind to `handle`
+fn listen(input: &Rc<RefCell<Input>>, target: &EventTarget, kind: &'static str, mut handle: impl FnMut(&mut Input, &web_sys::Event) + 'static) -> Result<Listener, String> {
+    let input = Rc::clone(input);
+    let callback = Closure::<dyn FnMut(web_sys::Event)>::new(move |event: web_sys::Event| handle(&mut input.borrow_mut(), &event));
+    target.add_event_listener_with_callback(kind, callback.as_ref().unchecked_ref())
+        .map_err(|e| format!("Failed to listen for {} events: {:?}", kind, e))?;
+    Ok(Listener { target: target.clone(), kind, callback })
+}
+
+// The input the page's listeners collect between polls
+#[derive(Default)]
+struct Input {
+    events: Vec<Event>,
+    held: HashSet<Key>,
+    left_button: bool,
+    relative_mouse: bool,
+    text_input: bool,
+    // Part of a wheel notch left over from smooth scrolling
+    scroll: f64,
+}
+
+impl Input {
+    fn key_down(&mut self, event: &web_sys::Event) {
+        let Some(event) = event.dyn_ref::<KeyboardEvent>() else {
+            return;
+        };
+        let code = event.code();
+        let physical = KEYS.iter().find(|&&(_, c, _)| c == code).map(|&(key, _, _)| key);
+        if let Some(key) = physical {
+            self.held.insert(key);
+            // Keys the game binds don't scroll the page or move the focus.
+            // Function keys stay the browser's, for reloading and its tools
+            if !code.starts_with('F') {
+                event.prevent_default();
+            }
+        }
+        // Key values longer than a character name keys that type nothing
+        let typed = event.key();
+        let mut chars = typed.chars();
+        let character = match (chars.next(), chars.next()) {
+            (Some(c), None) if !c.is_control() => Some(c),
+            _ => None,
+        };
+        let key = if event.location() == NUMPAD_LOCATION {
+            physical
+        } else {
+            // Shifted letters go by their key all the same
+            let unshifted = character.map_or(typed.clone(), |c| c.to_lowercase().collect());
+            KEYS.iter().find(|&&(_, _, t)| !t.is_empty() && t == unshifted).map(|&(key, _, _)| key)
+        };
+        self.events.push(Event::KeyDown { key, physical });
+        if self.text_input && character.is_some() && !event.ctrl_key() && !event.meta_key() {
+            self.events.push(Event::TextInput(typed));
+        }
+    }
+
+    fn key_up(&mut self, event: &web_sys::Event) {
+        let Some(event) = event.dyn_ref::<KeyboardEvent>() else {
+            return;
+        };
+        let code = event.code();
+        if let Some(&(key, _, _)) = KEYS.iter().find(|&&(_, c, _)| c == code) {
+            self.held.remove(&key);
+        }
+    }
+
+    fn focus_lost(&mut self) {
+        // Keys let go of elsewhere never report it
+        self.held.clear();
+        self.left_button = false;
+        self.events.push(Event::FocusLost);
+    }
+
+    // Pressing Escape makes the browser let go of the mouse without asking the
+    // game, which hears it as losing focus, as it does when the page loses it
+    fn pointer_lock_changed(&mut self, locked: bool) {
+        if !locked && self.relative_mouse {
+            self.relative_mouse = false;
+            self.focus_lost();
+        }
+    }
+
+    fn mouse_move(&mut self, event: &web_sys::Event) {
+        if let Some(event) = event.dyn_ref::<MouseEvent>() {
+            self.events.push(Event::MouseMotion { xrel: event.movement_x(), yrel: event.movement_y() });
+        }
+    }
+
+    fn mouse_down(&mut self, event: &web_sys::Event) {
+        let Some(event) = event.dyn_ref::<MouseEvent>() else {
+            return;
+        };
+        let button = match event.button() {
+            0 => MouseButton::Left,
+            2 => MouseButton::Right,
+            _ => return,
+        };
+        if button == MouseButton::Left {
+            self.left_button = true;
+        }
+        self.events.push(Event::MouseButtonDown { button, x: event.offset_x(), y: event.offset_y() });
+    }
+
+    fn mouse_up(&mut self, event: &web_sys::Event) {
+        if event.dyn_ref::<MouseEvent>().is_some_and(|event| event.button() == 0) {
+            self.left_button = false;
+        }
+    }
+
+    fn wheel(&mut self, event: &web_sys::Event) {
+        let Some(event) = event.dyn_ref::<WheelEvent>() else {
+            return;
+        };
+        // The game's wheel is positive away from the player, the page's towards
+        let notches = match event.delta_mode() {
+            WheelEvent::DOM_DELTA_PIXEL => -event.delta_y() / PIXELS_PER_NOTCH,
+            WheelEvent::DOM_DELTA_LINE => -event.delta_y() / LINES_PER_NOTCH,
+            _ => -event.delta_y().signum(),
+        };
+        event.prevent_default();
+        self.scroll += notches;
+        let notches = self.scroll.trunc();
+        self.scroll -= notches;
+        if notches != 0.0 {
+            self.events.push(Event::MouseWheel { y: notches as i32 });
+        }
+    }
+}
@@ -0,0 +1,420 @@
+use crate::error::EngineError;
+use crate::platform::{Event, Graphics, Key, MouseButton, Platform};
+use crate::sound::SoundManager;
+use glutin::config::{Config, ConfigTemplateBuilder, GlConfig};
+use glutin::context::{ContextApi, ContextAttributesBuilder, GlProfile, NotCurrentGlContext, PossiblyCurrentContext, PossiblyCurrentGlContext, Version};
+use glutin::display::{GetGlDisplay, GlDisplay};
+use glutin::surface::{GlSurface, Surface, SwapInterval, WindowSurface};
+use glutin_winit::{finalize_window, DisplayBuilder, GlWindow};
+use raw_window_handle::HasWindowHandle;
+use std::collections::HashSet;
+use std::ffi::CString;
+use std::num::NonZeroU32;
+use std::time::Duration;
+use winit::application::ApplicationHandler;
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::event::{DeviceEvent, DeviceId, ElementState, KeyEvent, MouseButton as WinitMouseButton, MouseScrollDelta, WindowEvent};
+use winit::event_loop::{ActiveEventLoop, EventLoop};
+use winit::keyboard::{Key as LogicalKey, KeyCode, KeyLocation, NamedKey, PhysicalKey};
+use winit::platform::modifier_supplement::KeyEventExtModifierSupplement;
+use winit::platform::pump_events::{EventLoopExtPumpEvents, PumpStatus};
+use winit::window::{CursorGrabMode, Fullscreen, Window, WindowId};
+
+// Pixels of smooth scrolling that make up one wheel notch
+const PIXELS_PER_NOTCH: f32 = 40.0;
+
+// What a key types, as winit reports it
+#[derive(Clone, Copy, PartialEq)]
+enum Typed {
+    Char(char),
+    Named(NamedKey),
+    // Keypad keys type the same as their twins elsewhere, so they go by position
+    Keypad,
+}
+
+// Each key with where winit finds it and what it types
+const KEYS: [(Key, KeyCode, Typed); 64] = [
+    (Key::A, KeyCode::KeyA, Typed::Char('a')),
+    (Key::B, KeyCode::KeyB, Typed::Char('b')),
+    (Key::C, KeyCode::KeyC, Typed::Char('c')),
+    (Key::D, KeyCode::KeyD, Typed::Char('d')),
+    (Key::E, KeyCode::KeyE, Typed::Char('e')),
+    (Key::F, KeyCode::KeyF, Typed::Char('f')),
+    (Key::G, KeyCode::KeyG, Typed::Char('g')),
+    (Key::H, KeyCode::KeyH, Typed::Char('h')),
+    (Key::I, KeyCode::KeyI, Typed::Char('i')),
+    (Key::J, KeyCode::KeyJ, Typed::Char('j')),
+    (Key::K, KeyCode::KeyK, Typed::Char('k')),
+    (Key::L, KeyCode::KeyL, Typed::Char('l')),
+    (Key::M, KeyCode::KeyM, Typed::Char('m')),
+    (Key::N, KeyCode::KeyN, Typed::Char('n')),
+    (Key::O, KeyCode::KeyO, Typed::Char('o')),
+    (Key::P, KeyCode::KeyP, Typed::Char('p')),
+    (Key::Q, KeyCode::KeyQ, Typed::Char('q')),
+    (Key::R, KeyCode::KeyR, Typed::Char('r')),
+    (Key::S, KeyCode::KeyS, Typed::Char('s')),
+    (Key::T, KeyCode::KeyT, Typed::Char('t')),
+    (Key::U, KeyCode::KeyU, Typed::Char('u')),
+    (Key::V, KeyCode::KeyV, Typed::Char('v')),
+    (Key::W, KeyCode::KeyW, Typed::Char('w')),
+    (Key::X, KeyCode::KeyX, Typed::Char('x')),
+    (Key::Y, KeyCode::KeyY, Typed::Char('y')),
+    (Key::Z, KeyCode::KeyZ, Typed::Char('z')),
+    (Key::Num0, KeyCode::Digit0, Typed::Char('0')),
+    (Key::Num1, KeyCode::Digit1, Typed::Char('1')),
+    (Key::Num2, KeyCode::Digit2, Typed::Char('2')),
+    (Key::Num3, KeyCode::Digit3, Typed::Char('3')),
+    (Key::Num4, KeyCode::Digit4, Typed::Char('4')),
+    (Key::Num5, KeyCode::Digit5, Typed::Char('5')),
+    (Key::Num6, KeyCode::Digit6, Typed::Char('6')),
+    (Key::Num7, KeyCode::Digit7, Typed::Char('7')),
+    (Key::Num8, KeyCode::Digit8, Typed::Char('8')),
+    (Key::Num9, KeyCode::Digit9, Typed::Char('9')),
+    (Key::F1, KeyCode::F1, Typed::Named(NamedKey::F1)),
+    (Key::F2, KeyCode::F2, Typed::Named(NamedKey::F2)),
+    (Key::F3, KeyCode::F3, Typed::Named(NamedKey::F3)),
+    (Key::F4, KeyCode::F4, Typed::Named(NamedKey::F4)),
+    (Key::F5, KeyCode::F5, Typed::Named(NamedKey::F5)),
+    (Key::F6, KeyCode::F6, Typed::Named(NamedKey::F6)),
+    (Key::F7, KeyCode::F7, Typed::Named(NamedKey::F7)),
+    (Key::F8, KeyCode::F8, Typed::Named(NamedKey::F8)),
+    (Key::F9, KeyCode::F9, Typed::Named(NamedKey::F9)),
+    (Key::F10, KeyCode::F10, Typed::Named(NamedKey::F10)),
+    (Key::F11, KeyCode::F11, Typed::Named(NamedKey::F11)),
+    (Key::F12, KeyCode::F12, Typed::Named(NamedKey::F12)),
+    (Key::Escape, KeyCode::Escape, Typed::Named(NamedKey::Escape)),
+    (Key::Return, KeyCode::Enter, Typed::Named(NamedKey::Enter)),
+    (Key::Backspace, KeyCode::Backspace, Typed::Named(NamedKey::Backspace)),
+    (Key::Tab, KeyCode::Tab, Typed::Named(NamedKey::Tab)),
+    (Key::Space, KeyCode::Space, Typed::Named(NamedKey::Space)),
+    (Key::LShift, KeyCode::ShiftLeft, Typed::Named(NamedKey::Shift)),
+    (Key::Up, KeyCode::ArrowUp, Typed::Named(NamedKey::ArrowUp)),
+    (Key::Down, KeyCode::ArrowDown, Typed::Named(NamedKey::ArrowDown)),
+    (Key::Left, KeyCode::ArrowLeft, Typed::Named(NamedKey::ArrowLeft)),
+    (Key::Right, KeyCode::ArrowRight, Typed::Named(NamedKey::ArrowRight)),
+    (Key::Minus, KeyCode::Minus, Typed::Char('-')),
+    (Key::Equals, KeyCode::Equal, Typed::Char('=')),
+    (Key::Backquote, KeyCode::Backquote, Typed::Char('`')),
+    (Key::KpEnter, KeyCode::NumpadEnter, Typed::Keypad),
+    (Key::KpPlus, KeyCode::NumpadAdd, Typed::Keypad),
+    (Key::KpMinus, KeyCode::NumpadSubtract, Typed::Keypad),
+];
+
+/// The platform on winit and glutin, which need no system libraries beyond
+/// the OS's own. There's no sound yet: it plays through SDL2.
+pub struct WinitPlatform {
+    // Fields drop in order, so the context and surfaces go before their windows.
+    // Windows opened for a surface have none
+    gl: Option<GlWindowContext>,
+    window: Window,
+    second_window: Option<(Surface<WindowSurface>, Window)>,
+    event_loop: EventLoop<()>,
+    input: Input,
+}
+
+// The GL context of a window opened with OpenGL, and its surface
+struct GlWindowContext {
+    context: PossiblyCurrentContext,
+    surface: Surface<WindowSurface>,
+    // Second windows are made from it, so the context works on them
+    config: Config,
+}
+
+impl Platform for WinitPlatform {
+    #[cfg(feature = "wgpu")]
+    type Window = Window;
+
+    fn open(title: &str, width: u32, height: u32, hidden: bool, graphics: Graphics) -> Result<Self, EngineError> {
+        let event_loop = EventLoop::new().map_err(|e| e.to_string())?;
+        let attributes = Window::default_attributes()
+            .with_title(title)
+            .with_inner_size(PhysicalSize::new(width, height))
+            .with_resizable(false)
+            .with_visible(!hidden);
+        if graphics == Graphics::Surface {
+            // The window is made before the event loop runs, which winit only
+            // allows through this
+            #[allow(deprecated)]
+            let window = event_loop.create_window(attributes).map_err(|e| e.to_string())?;
+            let input = Input { main_window: Some(window.id()), ..Input::default() };
+            return Ok(Self { gl: None, window, second_window: None, event_loop, input });
+        }
+        let template = ConfigTemplateBuilder::new().with_depth_size(24);
+        let (window, config) = DisplayBuilder::new()
+            .with_window_attributes(Some(attributes))
+            .build(&event_loop, template, |configs| {
+                configs.max_by_key(|config| config.depth_size()).expect("the display offers no GL configs")
+            })
+            .map_err(|e| e.to_string())?;
+        let window = window.ok_or_else(|| "Failed to create the window".to_string())?;
+
+        let display = config.display();
+        let window_handle = window.window_handle().map_err(|e| e.to_string())?.as_raw();
+        let context_attributes = ContextAttributesBuilder::new()
+            .with_profile(GlProfile::Core)
+            .with_context_api(ContextApi::OpenGl(Some(Version::new(3, 3))))
+            .with_debug(true)
+            .build(Some(window_handle));
+        let surface_attributes = window.build_surface_attributes(Default::default()).map_err(|e| e.to_string())?;
+        // Safe as long as the window outlives both, which field order sees to
+        let (context, surface) = unsafe {
+            // Drivers older than GL 3.3 can't make the context at all. A legacy
+            // one still says which version they have, for the error about it
+            let context = display.create_context(&config, &context_attributes).or_else(|e| {
+                log::warn!(target: "render", "No OpenGL 3.3 core context: {}", e);
+                display.create_context(&config, &ContextAttributesBuilder::new().build(Some(window_handle))).map_err(|_| e)
+            });
+            let context = context.map_err(|e| EngineError::Gl(e.to_string()))?;
+            let surface = display.create_window_surface(&config, &surface_attributes).map_err(|e| EngineError::Gl(e.to_string()))?;
+            (context, surface)
+        };
+        let context = context.make_current(&surface).map_err(|e| EngineError::Gl(e.to_string()))?;
+        gl::load_with(|s| {
+            let name = CString::new(s).expect("GL function names have no nul bytes");
+            display.get_proc_address(&name)
+        });
+
+        let input = Input { main_window: Some(window.id()), ..Input::default() };
+        let gl = GlWindowContext { context, surface, config };
+        Ok(Self { gl: Some(gl), window, second_window: None, event_loop, input })
+    }
+
+    #[cfg(feature = "wgpu")]
+    fn window(&self) -> &Window {
+        &self.window
+    }
+
+    fn poll_events(&mut self) -> Vec<Event> {
+        if let PumpStatus::Exit(_) = self.event_loop.pump_app_events(Some(Duration::ZERO), &mut self.input) {
+            self.input.events.push(Event::Quit);
+        }
+        std::mem::take(&mut self.input.events)
+    }
+
+    fn is_key_held(&self, key: Key) -> bool {
+        self.input.held.contains(&key)
+    }
+
+    fn is_left_button_held(&self) -> bool {
+        self.input.left_button
+    }
+
+    fn set_relative_mouse_mode(&mut self, enabled: bool) {
+        self.input.relative_mouse = enabled;
+        self.window.set_cursor_visible(!enabled);
+        // Some systems can only lock the cursor in place and others only keep it in the window
+        let grabbed = if enabled {
+            self.window.set_cursor_grab(CursorGrabMode::Locked)
+                .or_else(|_| self.window.set_cursor_grab(CursorGrabMode::Confined))
+        } else {
+            self.window.set_cursor_grab(CursorGrabMode::None)
+        };
+        if let Err(e) = grabbed {
+            log::warn!(target: "render", "Failed to grab the mouse: {}", e);
+        }
+    }
+
+    fn set_text_input(&mut self, enabled: bool) {
+        self.input.text_input = enabled;
+    }
+
+    fn set_vsync(&mut self, enabled: bool) -> Result<(), String> {
+        let Some(gl) = &self.gl else {
+            return Ok(());
+        };
+        let interval = if enabled { SwapInterval::Wait(NonZeroU32::MIN) } else { SwapInterval::DontWait };
+        gl.surface.set_swap_interval(&gl.context, interval).map_err(|e| e.to_string())
+    }
+
+    fn set_fullscreen(&mut self, enabled: bool) -> Result<(), String> {
+        self.window.set_fullscreen(enabled.then_some(Fullscreen::Borderless(None)));
+        Ok(())
+    }
+
+    fn swap_buffers(&mut self) {
+        let Some(gl) = &self.gl else {
+            return;
+        };
+        if let Err(e) = gl.surface.swap_buffers(&gl.context) {
+            log::error!(target: "render", "Failed to swap buffers: {}", e);
+        }
+    }
+
+    fn open_sound(&self) -> SoundManager {
+        log::warn!(target: "audio", "No sound: it needs the sdl2 feature");
+        SoundManager::disabled()
+    }
+
+    // winit has no controllers to rumble
+    fn rumble(&mut self, _low: f32, _high: f32, _duration: Duration) {}
+
+    fn open_second_window(&mut self, title: &str, width: u32, height: u32) -> Result<(), String> {
+        let Some(gl) = &self.gl else {
+            return Err("The second window needs OpenGL".to_string());
+        };
+        let attributes = Window::default_attributes()
+            .with_title(title)
+            .with_inner_size(PhysicalSize::new(width, height))
+            .with_resizable(false);
+        let window = finalize_window(&self.event_loop, attributes, &gl.config).map_err(|e| e.to_string())?;
+        let surface_attributes = window.build_surface_attributes(Default::default()).map_err(|e| e.to_string())?;
+        // Safe as long as the window outlives the surface, which tuple order sees to
+        let surface = unsafe { gl.config.display().create_window_surface(&gl.config, &surface_attributes) }
+            .map_err(|e| e.to_string())?;
+        // Waiting for vsync on both windows would halve the frame rate
+        gl.context.make_current(&surface).map_err(|e| e.to_string())?;
+        let interval = surface.set_swap_interval(&gl.context, SwapInterval::DontWait);
+        gl.context.make_current(&gl.surface).map_err(|e| e.to_string())?;
+        interval.map_err(|e| e.to_string())?;
+        self.second_window = Some((surface, window));
+        Ok(())
+    }
+
+    fn close_second_window(&mut self) {
+        self.second_window = None;
+    }
+
+    fn has_second_window(&self) -> bool {
+        self.second_window.is_some()
+    }
+
+    fn draw_second_window(&mut self, draw: &mut dyn FnMut()) {
+        let (Some((surface, _)), Some(gl)) = (&self.second_window, &self.gl) else {
+            return;
+        };
+        if let Err(e) = gl.context.make_current(surface) {
+            log::error!(target: "render", "Failed to draw the second window: {}", e);
+            return;
+        }
+        draw();
+        if let Err(e) = surface.swap_buffers(&gl.context) {
+            log::error!(target: "render", "Failed to swap buffers: {}", e);
+        }
+        if let Err(e) = gl.context.make_current(&gl.surface) {
+            log::error!(target: "render", "Failed to go back to the game window: {}", e);
+        }
+    }
+}
+
+/// Errors only go to the log without SDL2, which has the message boxes.
+pub fn show_error(_title: &str, _message: &str) {}
+
+// The input winit hands over while its events are pumped
+#[derive(Default)]
+struct Input {
+    // Input to other windows isn't reported
+    main_window: Option<WindowId>,
+    events: Vec<Event>,
+    held: HashSet<Key>,
+    left_button: bool,
+    cursor: Option<PhysicalPosition<f64>>,
+    relative_mouse: bool,
+    text_input: bool,
+    // Part of a wheel notch left over from smooth scrolling
+    scroll: f32,
+}
+
+impl ApplicationHandler for Input {
+    // The window is made up front, so there's nothing to do once the loop starts
+    fn resumed(&mut self, _event_loop: &ActiveEventLoop) {}
+
+    fn window_event(&mut self, _event_loop: &ActiveEventLoop, window_id: WindowId, event: WindowEvent) {
+        if Some(window_id) != self.main_window {
+            if let WindowEvent::CloseRequested = event {
+                self.events.push(Event::SecondWindowClosed);
+            }
+            return;
+        }
+        match event {
+            WindowEvent::CloseRequested => self.events.push(Event::Quit),
+            WindowEvent::Focused(true) => self.events.push(Event::FocusGained),
+            WindowEvent::Focused(false) => {
+                // Keys let go of elsewhere never report it
+                self.held.clear();
+                self.left_button = false;
+                self.events.push(Event::FocusLost);
+            },
+            WindowEvent::KeyboardInput { event, .. } => {
+                let physical = match event.physical_key {
+                    PhysicalKey::Code(code) => KEYS.iter().find(|&&(_, c, _)| c == code).map(|&(key, _, _)| key),
+                    PhysicalKey::Unidentified(_) => None,
+                };
+                if event.state == ElementState::Released {
+                    if let Some(key) = physical {
+                        self.held.remove(&key);
+                    }
+                    return;
+                }
+                if let Some(key) = physical {
+                    self.held.insert(key);
+                }
+                let key = if event.location == KeyLocation::Numpad { physical } else { typed_key(&event) };
+                self.events.push(Event::KeyDown { key, physical });
+                let text = event.text.filter(|text| !text.chars().any(char::is_control));
+                if let Some(text) = text.filter(|_| self.text_input) {
+                    self.events.push(Event::TextInput(text.to_string()));
+                }
+            },
+            WindowEvent::CursorMoved { position, .. } => {
+                // Relative motion comes from the device instead, with the cursor held still
+                if let Some(last) = self.cursor.filter(|_| !self.relative_mouse) {
+                    self.events.push(Event::MouseMotion {
+                        xrel: (position.x - last.x).round() as i32,
+                        yrel: (position.y - last.y).round() as i32,
+                    });
+                }
+                self.cursor = Some(position);
+            },
+            WindowEvent::MouseInput { state, button, .. } => {
+                if button == WinitMouseButton::Left {
+                    self.left_button = state == ElementState::Pressed;
+                }
+                let button = match button {
+                    WinitMouseButton::Left => MouseButton::Left,
+                    WinitMouseButton::Right => MouseButton::Right,
+                    _ => return,
+                };
+                if state == ElementState::Pressed {
+                    let cursor = self.cursor.unwrap_or_default();
+                    self.events.push(Event::MouseButtonDown { button, x: cursor.x as i32, y: cursor.y as i32 });
+                }
+            },
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.scroll += match delta {
+                    MouseScrollDelta::LineDelta(_, y) => y,
+                    MouseScrollDelta::PixelDelta(position) => position.y as f32 / PIXELS_PER_NOTCH,
+                };
+                let notches = self.scroll.trunc();
+                self.scroll -= notches;
+                if notches != 0.0 {
+                    self.events.push(Event::MouseWheel { y: notches as i32 });
+                }
+            },
+            _ => {},
+        }
+    }
+
+    fn device_event(&mut self, _event_loop: &ActiveEventLoop, _device_id: DeviceId, event: DeviceEvent) {
+        if let DeviceEvent::MouseMotion { delta: (x, y) } = event {
+            if self.relative_mouse {
+                self.events.push(Event::MouseMotion { xrel: x.round() as i32, yrel: y.round() as i32 });
+            }
+        }
+    }
+}
+
+// The key by what it types without modifiers on the keyboard layout
+fn typed_key(event: &KeyEvent) -> Option<Key> {
+    let typed = match event.key_without_modifiers() {
+        LogicalKey::Character(text) => {
+            let mut chars = text.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Typed::Char(c.to_ascii_lowercase()),
+                _ => return None,
+            }
+        },
+        LogicalKey::Named(named) => Typed::Named(named),
+        _ => return None,
+    };
+    KEYS.iter().find(|&&(_, _, t)| t == typed).map(|&(key, _, _)| key)
+}
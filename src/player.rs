@@ -0,0 +1,324 @@
+use crate::entity::Aabb;
+use crate::math::Vec3;
+use crate::world::World;
+
+/// Height of the camera above the player's feet.
+pub const EYE_HEIGHT: f32 = 1.6;
+/// Width of the player's collision box, in blocks.
+pub const PLAYER_WIDTH: f32 = 0.6;
+const PLAYER_HEIGHT: f32 = 1.8;
+const GRAVITY: f32 = 32.0;
+// Enough to jump onto a block
+const JUMP_SPEED: f32 = 9.0;
+// Tallest ledge walked up without jumping, and the same with auto-jump on,
+// which still steps onto slabs
+const STEP_HEIGHT: f32 = 1.0;
+const AUTO_JUMP_STEP_HEIGHT: f32 = 0.5;
+// Fastest fall, in blocks per second
+const TERMINAL_SPEED: f32 = 60.0;
+
+// In water, gravity is mostly cancelled out and drag slows everything down,
+// so the player sinks slowly and swims up at a steady speed
+const WATER_GRAVITY: f32 = 4.0;
+// Fraction of the speed lost per second
+const WATER_DRAG: f32 = 4.0;
+const SWIM_SPEED: f32 = 2.2;
+// Upwards acceleration while holding jump in water: rising at
+// (SWIM_UP_ACCELERATION - WATER_GRAVITY) / WATER_DRAG blocks per second
+const SWIM_UP_ACCELERATION: f32 = 14.0;
+// Swimming up only lifts the player while they're deeper than this, so they
+// float with their head out of the water
+const SURFACE_SUBMERSION: f32 = 0.6;
+// Speed given when jumping off the bottom or swimming against a bank, to
+// climb out of the water
+const WATER_EXIT_SPEED: f32 = 6.0;
+// Fraction of a knockback's horizontal speed lost per second. Kept apart
+// from the walking speed, which would otherwise cancel it out at once
+const KNOCKBACK_DRAG: f32 = 3.0;
+// Longest gap between two presses of jump that toggles flying, in seconds
+const DOUBLE_TAP_TIME: f32 = 0.3;
+
+// How fast a movement mode goes, in blocks per second, how much faster when
+// sprinting, and how quickly it gets up to speed, as the fraction of the
+// difference made up per second
+struct Movement {
+    speed: f32,
+    sprint_multiplier: f32,
+    acceleration: f32,
+}
+
+const WALK: Movement = Movement { speed: 4.3, sprint_multiplier: 1.3, acceleration: 30.0 };
+const FLY: Movement = Movement { speed: 10.5, sprint_multiplier: 2.0, acceleration: 8.0 };
+const NOCLIP: Movement = Movement { speed: 15.0, sprint_multiplier: 3.0, acceleration: 8.0 };
+
+/// How the player gets around.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MoveMode {
+    /// Walking and swimming, with gravity and collisions.
+    #[default]
+    Walk,
+    /// Flying freely but stopped by blocks, until landing on the ground.
+    Fly,
+    /// Flying through everything in the direction the camera looks.
+    Noclip,
+}
+
+impl MoveMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            MoveMode::Walk => "walk",
+            MoveMode::Fly => "fly",
+            MoveMode::Noclip => "noclip",
+        }
+    }
+
+    fn movement(self) -> &'static Movement {
+        match self {
+            MoveMode::Walk => &WALK,
+            MoveMode::Fly => &FLY,
+            MoveMode::Noclip => &NOCLIP,
+        }
+    }
+}
+
+/// What the player asked for in one physics step.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MoveInput {
+    /// Forwards (1) or backwards (-1) along the way the camera faces.
+    pub forward: f32,
+    /// Right (1) or left (-1).
+    pub right: f32,
+    /// Jumping, swimming up, or rising when flying.
+    pub jump: bool,
+    /// Sinking when flying.
+    pub descend: bool,
+    pub sprint: bool,
+}
+
+/// Walking, falling, swimming and flying for the local player. The player's
+/// position stays with the camera, at its feet; this only keeps what carries
+/// over from one step to the next.
+#[derive(Debug, Default)]
+pub struct Player {
+    /// Whether walking into a ledge jumps up it, rather than stepping onto it.
+    pub auto_jump: bool,
+    /// Whether the player may fly and noclip. Taking it away brings them back
+    /// to walking on their next step.
+    pub can_fly: bool,
+    mode: MoveMode,
+    velocity: Vec3,
+    // Horizontal speed from being pushed, dying down over time
+    knockback: Vec3,
+    on_ground: bool,
+    // Fraction of the player's height under water, from the last step
+    submersion: f32,
+    // Whether jump was held last step, and the time since it was pressed if a
+    // second press would still toggle flying
+    jump_held: bool,
+    since_jump_tap: Option<f32>,
+    // How fast the player was falling when they last landed, until taken
+    landing: Option<f32>,
+    // Highest the feet got since leaving the ground, while walking and out of
+    // water, and how far the player fell when they last landed, until taken
+    fall_from: Option<f32>,
+    fall: Option<f32>,
+}
+
+impl Player {
+    pub fn new(auto_jump: bool) -> Self {
+        Self { auto_jump, ..Self::default() }
+    }
+
+    pub fn mode(&self) -> MoveMode {
+        self.mode
+    }
+
+    /// Starts or stops flying, like double-tapping jump.
+    pub fn toggle_fly(&mut self) {
+        if !self.can_fly {
+            return;
+        }
+        self.set_mode(if self.mode == MoveMode::Walk { MoveMode::Fly } else { MoveMode::Walk });
+    }
+
+    /// Switches noclip on, or back to flying where the player is.
+    pub fn toggle_noclip(&mut self) {
+        if !self.can_fly {
+            return;
+        }
+        self.set_mode(if self.mode == MoveMode::Noclip { MoveMode::Fly } else { MoveMode::Noclip });
+    }
+
+    /// How fast the player is moving, in blocks per second.
+    pub fn velocity(&self) -> Vec3 {
+        self.velocity + self.knockback
+    }
+
+    /// How fast the player moves along the ground while walking on it, or 0
+    /// in the air, in water and when flying.
+    pub fn walking_speed(&self) -> f32 {
+        if self.mode == MoveMode::Walk && self.on_ground && !self.in_water() {
+            Vec3::new(self.velocity.x, 0.0, self.velocity.z).length()
+        } else {
+            0.0
+        }
+    }
+
+    /// The speed of the last landing since this was called, in blocks per second.
+    pub fn take_landing(&mut self) -> Option<f32> {
+        self.landing.take()
+    }
+
+    /// Throws the player with the given velocity, such as away from an
+    /// explosion. Doesn't move them in noclip.
+    pub fn push(&mut self, velocity: Vec3) {
+        if self.mode == MoveMode::Noclip {
+            return;
+        }
+        self.knockback = self.knockback + Vec3::new(velocity.x, 0.0, velocity.z);
+        self.velocity.y += velocity.y;
+    }
+
+    /// How far the player fell before their last landing since this was
+    /// called, in blocks. Falls into water or while flying don't count.
+    pub fn take_fall(&mut self) -> Option<f32> {
+        self.fall.take()
+    }
+
+    fn set_mode(&mut self, mode: MoveMode) {
+        log::info!(target: "game", "Movement mode: {}", mode.name());
+        self.mode = mode;
+        // Don't carry a jump or a fall into flight
+        self.velocity.y = 0.0;
+    }
+
+    // Whether the player is touching water
+    fn in_water(&self) -> bool {
+        self.submersion > 0.0
+    }
+
+    /// Runs one physics step for a player standing at `feet` and looking
+    /// towards `front`, and returns where their feet end up. Outside noclip,
+    /// the player waits in place while the chunk they're in isn't loaded.
+    pub fn step(&mut self, world: &World, feet: Vec3, front: Vec3, input: &MoveInput, delta_time: f32) -> Vec3 {
+        if !self.can_fly && self.mode != MoveMode::Walk {
+            self.set_mode(MoveMode::Walk);
+        }
+        // Double-tapping jump toggles flying
+        let tapped = input.jump && !self.jump_held;
+        self.jump_held = input.jump;
+        self.since_jump_tap = self.since_jump_tap.map(|time| time + delta_time).filter(|&time| time < DOUBLE_TAP_TIME);
+        if tapped {
+            if self.since_jump_tap.take().is_some() && self.mode != MoveMode::Noclip {
+                self.toggle_fly();
+            } else {
+                self.since_jump_tap = Some(0.0);
+            }
+        }
+
+        let movement = self.mode.movement();
+        let sprint = if input.sprint { movement.sprint_multiplier } else { 1.0 };
+        let blend = (movement.acceleration * delta_time).min(1.0);
+        let vertical = f32::from(u8::from(input.jump)) - f32::from(u8::from(input.descend));
+
+        if self.mode == MoveMode::Noclip {
+            // Straight where the camera looks, through anything
+            let right = front.cross(&Vec3::new(0.0, 1.0, 0.0));
+            let right = if right.length() > 0.0 { right.normalize() } else { right };
+            let wish = front * input.forward + right * input.right + Vec3::new(0.0, vertical, 0.0);
+            let wish = if wish.length() > 1.0 { wish.normalize() } else { wish };
+            self.velocity = self.velocity + (wish * movement.speed * sprint - self.velocity) * blend;
+            self.knockback = Vec3::zero();
+            self.submersion = 0.0;
+            self.on_ground = false;
+            return feet + self.velocity * delta_time;
+        }
+
+        if !world.is_loaded(feet) {
+            self.velocity = Vec3::zero();
+            return feet;
+        }
+
+        let bounds = Aabb::standing(PLAYER_WIDTH, PLAYER_HEIGHT);
+        self.submersion = world.submersion(bounds.at(feet));
+
+        // Walk along the ground, whichever way the camera is pitched
+        let forward = Vec3::new(front.x, 0.0, front.z);
+        let forward = if forward.length() > 0.0 { forward.normalize() } else { forward };
+        let right = Vec3::new(-forward.z, 0.0, forward.x);
+        let wish = forward * input.forward + right * input.right;
+        let wish = if wish.length() > 1.0 { wish.normalize() } else { wish };
+
+        if self.mode == MoveMode::Fly {
+            let target = (wish + Vec3::new(0.0, vertical, 0.0)) * movement.speed * sprint;
+            self.velocity = self.velocity + (target - self.velocity) * blend;
+        } else if self.in_water() {
+            // Ease towards swimming speed, and let drag hold back rising and sinking
+            let target = wish * SWIM_SPEED * sprint;
+            let blend = (WATER_DRAG * delta_time).min(1.0);
+            self.velocity.x += (target.x - self.velocity.x) * blend;
+            self.velocity.z += (target.z - self.velocity.z) * blend;
+            let lift = if input.jump && self.submersion > SURFACE_SUBMERSION { SWIM_UP_ACCELERATION } else { 0.0 };
+            self.velocity.y += (lift - WATER_GRAVITY - self.velocity.y * WATER_DRAG) * delta_time;
+            if input.jump && self.on_ground {
+                self.velocity.y = WATER_EXIT_SPEED;
+            }
+        } else {
+            let target = wish * movement.speed * sprint;
+            self.velocity.x += (target.x - self.velocity.x) * blend;
+            self.velocity.z += (target.z - self.velocity.z) * blend;
+            if input.jump && self.on_ground {
+                self.velocity.y = JUMP_SPEED;
+            }
+            self.velocity.y = (self.velocity.y - GRAVITY * delta_time).max(-TERMINAL_SPEED);
+        }
+
+        let step = (self.velocity + self.knockback) * delta_time;
+        self.knockback = self.knockback * (1.0 - KNOCKBACK_DRAG * delta_time).max(0.0);
+        let collision = match self.mode {
+            MoveMode::Walk => {
+                let step_height = if self.auto_jump { AUTO_JUMP_STEP_HEIGHT } else { STEP_HEIGHT };
+                world.sweep_aabb_stepping(bounds.at(feet), step, step_height)
+            },
+            _ => world.sweep_aabb(bounds.at(feet), step),
+        };
+        let (blocked_x, blocked_y, blocked_z) = (collision.normal.0 != 0, collision.normal.1 != 0, collision.normal.2 != 0);
+        if collision.grounded && !self.on_ground {
+            self.landing = Some(-self.velocity.y);
+        }
+        if blocked_x {
+            self.velocity.x = 0.0;
+            self.knockback.x = 0.0;
+        }
+        if blocked_y {
+            self.velocity.y = 0.0;
+        }
+        if blocked_z {
+            self.velocity.z = 0.0;
+            self.knockback.z = 0.0;
+        }
+        // Swimming into a bank lifts the player out onto it too, and with
+        // auto-jump so does walking into a ledge
+        let blocked_sideways = blocked_x || blocked_z;
+        if self.mode == MoveMode::Walk && self.in_water() && input.jump && blocked_sideways {
+            self.velocity.y = WATER_EXIT_SPEED;
+        } else if self.mode == MoveMode::Walk && self.auto_jump && self.on_ground && blocked_sideways && wish.length() > 0.0 {
+            self.velocity.y = JUMP_SPEED;
+        }
+        self.on_ground = collision.grounded;
+        // Landing ends a flight
+        if self.mode == MoveMode::Fly && self.on_ground {
+            self.mode = MoveMode::Walk;
+        }
+
+        let feet = feet + collision.movement;
+        if self.mode != MoveMode::Walk || self.in_water() {
+            self.fall_from = None;
+        } else if !self.on_ground {
+            self.fall_from = Some(self.fall_from.map_or(feet.y, |from| from.max(feet.y)));
+        } else if let Some(from) = self.fall_from.take() {
+            self.fall = Some(from - feet.y);
+        }
+        feet
+    }
+}
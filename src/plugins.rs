@@ -0,0 +1,299 @@
+use crate::block::BlockType;
+use crate::commands::CommandRegistry;
+use crate::events::{BlockChangeCause, BlockChanged, EventBus, KeyAction, Subscriber};
+use crate::lua::{function_argument, number_argument, string_argument, Lua, Value};
+use crate::math::Vec3;
+use crate::mob::MobKind;
+use crate::world::World;
+use std::cell::RefCell;
+use std::path::Path;
+use std::rc::Rc;
+
+/// Folder plugins are loaded from, as one `.lua` script each.
+pub const PLUGIN_DIRECTORY: &str = "plugins";
+
+/// What plugins changed while they ran, for the game to follow up on.
+#[derive(Default)]
+pub struct PluginEffects {
+    /// Blocks changed by scripts, so their chunks can be remeshed.
+    pub edited_blocks: Vec<(i32, i32, i32)>,
+    /// Mobs scripts asked for, spawned once they're done.
+    pub spawned_mobs: Vec<(MobKind, Vec3)>,
+}
+
+impl PluginEffects {
+    fn extend(&mut self, other: PluginEffects) {
+        self.edited_blocks.extend(other.edited_blocks);
+        self.spawned_mobs.extend(other.spawned_mobs);
+    }
+}
+
+/// Game state a script can reach while it runs.
+pub struct PluginContext<'a> {
+    world: &'a mut World,
+    player_position: Vec3,
+    // The plugin running, for what it prints
+    plugin: &'a str,
+    effects: PluginEffects,
+    // What the plugin registers while it loads, `None` once it has
+    registrations: Option<Registrations>,
+}
+
+impl<'a> PluginContext<'a> {
+    pub(crate) fn new(world: &'a mut World, player_position: Vec3, plugin: &'a str) -> Self {
+        Self { world, player_position, plugin, effects: PluginEffects::default(), registrations: None }
+    }
+
+    fn registrations(&mut self, what: &str) -> Result<&mut Registrations, String> {
+        self.registrations.as_mut().ok_or_else(|| format!("{} can only be registered while the plugin loads", what))
+    }
+}
+
+#[derive(Default)]
+struct Registrations {
+    // Name, usage and handler of each command
+    commands: Vec<(String, String, Value)>,
+    hooks: Hooks,
+}
+
+// The handlers a plugin registered for each event
+#[derive(Default)]
+struct Hooks {
+    tick: Vec<Value>,
+    block_changed: Vec<Value>,
+    key: Vec<Value>,
+}
+
+// A plugin that loaded
+struct Plugin {
+    name: String,
+    lua: Lua,
+    hooks: Hooks,
+}
+
+/// Lua scripts from `PLUGIN_DIRECTORY` that add commands and run on world
+/// ticks. Besides the language's own functions, they get:
+///
+/// - `print(...)`, to the log
+/// - `world.get_block(x, y, z)`, the block's name
+/// - `world.set_block(x, y, z, name)`, whether a loaded block changed
+/// - `player.position()`, the player's eye as `x, y, z`
+/// - `entities.spawn_mob(kind, x, y, z)`, with kind `"passive"` or `"hostile"`
+/// - `commands.register(name, usage, handler)`: `handler` gets the words
+///   typed after `/name` and may return the message to show
+/// - `events.on_tick(handler)`: `handler` gets the number of the tick
+/// - `events.on_block_changed(handler)`: `handler` gets `x, y, z`, the new
+///   block's name and what changed it, such as `"player"` or `"tick"`, for
+///   every change but those made by plugins
+/// - `events.on_key(handler)`: `handler` gets the name of a key pressed
+///   while playing, such as `"F"` or `"Num1"`
+///
+/// Commands and hooks can only be registered while a script loads.
+pub struct Plugins {
+    loaded: Vec<Rc<RefCell<Plugin>>>,
+    // World ticks since the plugins loaded
+    ticks: u64,
+    block_changes: Subscriber<BlockChanged>,
+    keys: Subscriber<KeyAction>,
+}
+
+impl Plugins {
+    /// Runs every `.lua` script in `directory`, in name order, and registers
+    /// the commands they add. Scripts that fail are left out, with the error
+    /// in the log.
+    pub fn load(directory: &Path, world: &mut World, player_position: Vec3, commands: &mut CommandRegistry) -> (Self, PluginEffects) {
+        let mut plugins = Self { loaded: Vec::new(), ticks: 0, block_changes: Subscriber::new(), keys: Subscriber::new() };
+        let mut effects = PluginEffects::default();
+        // No folder just means no plugins
+        let Ok(entries) = std::fs::read_dir(directory) else {
+            return (plugins, effects);
+        };
+        let mut paths = entries.filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| path.extension().is_some_and(|extension| extension == "lua"))
+            .collect::<Vec<_>>();
+        paths.sort();
+
+        for path in paths {
+            let name = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+            let source = match std::fs::read_to_string(&path) {
+                Ok(source) => source,
+                Err(e) => {
+                    log::error!(target: "game", "Failed to read plugin {}: {}", path.display(), e);
+                    continue;
+                },
+            };
+            let mut lua = Lua::new();
+            let api: [(&str, Value); 6] = [
+                ("print", Value::native(print)),
+                ("world", Value::table([("get_block", Value::native(get_block)), ("set_block", Value::native(set_block))])),
+                ("player", Value::table([("position", Value::native(position))])),
+                ("entities", Value::table([("spawn_mob", Value::native(spawn_mob))])),
+                ("commands", Value::table([("register", Value::native(register_command))])),
+                ("events", Value::table([
+                    ("on_tick", Value::native(on_tick)),
+                    ("on_block_changed", Value::native(on_block_changed)),
+                    ("on_key", Value::native(on_key)),
+                ])),
+            ];
+            for (global, value) in api {
+                lua.set_global(global, value);
+            }
+
+            let mut context = PluginContext::new(world, player_position, &name);
+            context.registrations = Some(Registrations::default());
+            let result = lua.run(&source, &mut context);
+            let registrations = context.registrations.take().unwrap_or_default();
+            effects.extend(context.effects);
+            if let Err(e) = result {
+                log::error!(target: "game", "Plugin {} failed to load: {}", name, e);
+                continue;
+            }
+
+            let plugin = Rc::new(RefCell::new(Plugin { name: name.clone(), lua, hooks: registrations.hooks }));
+            for (command, usage, handler) in registrations.commands {
+                let plugin = plugin.clone();
+                commands.register(&command, &usage, move |context, args| {
+                    let mut plugin = plugin.borrow_mut();
+                    let Plugin { name, lua, .. } = &mut *plugin;
+                    let mut plugin_context = PluginContext::new(context.world, *context.player_position, name);
+                    let arguments = args.iter().map(|arg| Value::string(arg)).collect::<Vec<_>>();
+                    let result = lua.call(&handler, &arguments, &mut plugin_context);
+                    let PluginEffects { edited_blocks, spawned_mobs } = plugin_context.effects;
+                    context.edited_blocks.extend(edited_blocks);
+                    context.spawned_mobs.extend(spawned_mobs);
+                    Ok(match result?.first() {
+                        None | Some(Value::Nil) => String::new(),
+                        Some(message) => message.to_text(),
+                    })
+                });
+            }
+            log::info!(target: "game", "Loaded plugin {}", name);
+            plugins.loaded.push(plugin);
+        }
+        (plugins, effects)
+    }
+
+    /// Runs every tick hook once for each of `ticks` world ticks. Hooks that
+    /// fail are dropped, with the error in the log.
+    pub fn tick(&mut self, world: &mut World, player_position: Vec3, ticks: u32) -> PluginEffects {
+        let mut effects = PluginEffects::default();
+        for _ in 0..ticks {
+            let arguments = [Value::Number(self.ticks as f64)];
+            effects.extend(self.run_hooks(world, player_position, "tick", |hooks| &mut hooks.tick, &arguments));
+            self.ticks += 1;
+        }
+        effects
+    }
+
+    /// Runs the block change and key hooks on the frame's events. Hooks that
+    /// fail are dropped, with the error in the log.
+    pub fn handle_events(&mut self, events: &EventBus, world: &mut World, player_position: Vec3) -> PluginEffects {
+        let mut effects = PluginEffects::default();
+        // What plugins change themselves isn't passed back to them, so hooks can't feed each other forever
+        for change in events.read(&mut self.block_changes).iter().filter(|change| change.cause != BlockChangeCause::Plugin) {
+            let (x, y, z) = change.position;
+            let arguments = [
+                Value::Number(x as f64),
+                Value::Number(y as f64),
+                Value::Number(z as f64),
+                Value::string(change.block.info().name),
+                Value::string(change.cause.name()),
+            ];
+            effects.extend(self.run_hooks(world, player_position, "block change", |hooks| &mut hooks.block_changed, &arguments));
+        }
+        for action in events.read(&mut self.keys) {
+            let arguments = [Value::string(&format!("{:?}", action.key))];
+            effects.extend(self.run_hooks(world, player_position, "key", |hooks| &mut hooks.key, &arguments));
+        }
+        effects
+    }
+
+    // Calls the hooks `hooks` picks out of every plugin with `arguments`
+    fn run_hooks(&self, world: &mut World, player_position: Vec3, event: &str, hooks: fn(&mut Hooks) -> &mut Vec<Value>, arguments: &[Value]) -> PluginEffects {
+        let mut effects = PluginEffects::default();
+        for plugin in &self.loaded {
+            let mut plugin = plugin.borrow_mut();
+            let Plugin { name, lua, hooks: plugin_hooks } = &mut *plugin;
+            let mut context = PluginContext::new(world, player_position, name);
+            hooks(plugin_hooks).retain(|hook| match lua.call(hook, arguments, &mut context) {
+                Ok(_) => true,
+                Err(e) => {
+                    log::error!(target: "game", "Plugin {}: a {} hook failed and was removed: {}", context.plugin, event, e);
+                    false
+                },
+            });
+            effects.extend(context.effects);
+        }
+        effects
+    }
+}
+
+// A block position from three number arguments, starting at `first`
+fn block_position(arguments: &[Value], first: usize) -> Result<(i32, i32, i32), String> {
+    let coordinate = |index| number_argument(arguments, index).map(|v| v.round() as i32);
+    Ok((coordinate(first)?, coordinate(first + 1)?, coordinate(first + 2)?))
+}
+
+fn print(context: &mut PluginContext, arguments: &[Value]) -> Result<Vec<Value>, String> {
+    let text = arguments.iter().map(Value::to_text).collect::<Vec<_>>().join("\t");
+    log::info!(target: "game", "[{}] {}", context.plugin, text);
+    Ok(Vec::new())
+}
+
+fn get_block(context: &mut PluginContext, arguments: &[Value]) -> Result<Vec<Value>, String> {
+    let (x, y, z) = block_position(arguments, 0)?;
+    Ok(vec![Value::string(context.world.get_block(x, y, z).info().name)])
+}
+
+fn set_block(context: &mut PluginContext, arguments: &[Value]) -> Result<Vec<Value>, String> {
+    let position = block_position(arguments, 0)?;
+    let name = string_argument(arguments, 3)?;
+    let block = BlockType::from_name(&name).ok_or_else(|| format!("Unknown block: {}", name))?;
+    let changed = context.world.set_blocks([(position, block)]);
+    let any = !changed.is_empty();
+    context.effects.edited_blocks.extend(changed);
+    Ok(vec![Value::Bool(any)])
+}
+
+fn position(context: &mut PluginContext, _: &[Value]) -> Result<Vec<Value>, String> {
+    let Vec3 { x, y, z } = context.player_position;
+    Ok(vec![Value::Number(x as f64), Value::Number(y as f64), Value::Number(z as f64)])
+}
+
+fn spawn_mob(context: &mut PluginContext, arguments: &[Value]) -> Result<Vec<Value>, String> {
+    let name = string_argument(arguments, 0)?;
+    let kind = MobKind::from_name(&name).ok_or_else(|| format!("Unknown mob: {}", name))?;
+    let coordinate = |index| number_argument(arguments, index).map(|v| v as f32);
+    let position = Vec3::new(coordinate(1)?, coordinate(2)?, coordinate(3)?);
+    context.effects.spawned_mobs.push((kind, position));
+    Ok(Vec::new())
+}
+
+fn register_command(context: &mut PluginContext, arguments: &[Value]) -> Result<Vec<Value>, String> {
+    let name = string_argument(arguments, 0)?;
+    let usage = string_argument(arguments, 1)?;
+    let handler = function_argument(arguments, 2)?;
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(format!("Invalid command name: {}", name));
+    }
+    context.registrations("Commands")?.commands.push((name.to_string(), usage.to_string(), handler));
+    Ok(Vec::new())
+}
+
+fn on_tick(context: &mut PluginContext, arguments: &[Value]) -> Result<Vec<Value>, String> {
+    let handler = function_argument(arguments, 0)?;
+    context.registrations("Hooks")?.hooks.tick.push(handler);
+    Ok(Vec::new())
+}
+
+fn on_block_changed(context: &mut PluginContext, arguments: &[Value]) -> Result<Vec<Value>, String> {
+    let handler = function_argument(arguments, 0)?;
+    context.registrations("Hooks")?.hooks.block_changed.push(handler);
+    Ok(Vec::new())
+}
+
+fn on_key(context: &mut PluginContext, arguments: &[Value]) -> Result<Vec<Value>, String> {
+    let handler = function_argument(arguments, 0)?;
+    context.registrations("Hooks")?.hooks.key.push(handler);
+    Ok(Vec::new())
+}
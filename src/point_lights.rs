@@ -0,0 +1,82 @@
+use crate::gl_utils::ShaderProgram;
+use crate::math::Vec3;
+use crate::world::World;
+
+/// Most lights the block shader takes at once, matching MAX_LIGHTS in block.frag and block.wgsl.
+pub const MAX_POINT_LIGHTS: usize = 16;
+// Glowing blocks further than this from the camera are not considered
+const LIGHT_RANGE: f32 = 64.0;
+
+#[derive(Clone, Copy)]
+struct PointLight {
+    position: Vec3,
+    color: [f32; 3],
+    radius: f32,
+}
+
+/// Colored lights given off by glowing blocks, added on top of the daylight
+/// when drawing chunks. Only the lights nearest to the camera are used.
+#[derive(Default)]
+pub struct PointLights {
+    lights: Vec<PointLight>,
+}
+
+impl PointLights {
+    /// Picks the glowing blocks that can light what the camera sees, nearest
+    /// first. Blocks come from the chunk meshes, so ones buried under other
+    /// blocks are already left out.
+    pub fn gather(&mut self, world: &World, camera_position: Vec3, camera_front: Vec3) {
+        self.lights.clear();
+        for chunk in world.chunks() {
+            for &[x, y, z] in &chunk.mesh.lights {
+                let Some(light) = world.get_block(x, y, z).info().light else {
+                    continue;
+                };
+                let position = Vec3::new(x as f32, y as f32, z as f32);
+                let offset = position - camera_position;
+                // Too far away, or behind the camera with nothing in view in reach
+                if offset.length() > LIGHT_RANGE || offset.dot(&camera_front) < -light.radius {
+                    continue;
+                }
+                self.lights.push(PointLight { position, color: light.color, radius: light.radius });
+            }
+        }
+
+        let distance = |light: &PointLight| (light.position - camera_position).length();
+        self.lights.sort_by(|a, b| distance(a).total_cmp(&distance(b)));
+        self.lights.truncate(MAX_POINT_LIGHTS);
+    }
+
+    /// How many lights were picked by the last `gather`.
+    pub fn count(&self) -> usize {
+        self.lights.len()
+    }
+
+    /// The lights as the block programs take them, nearest first: their
+    /// positions, and their colors in rgb with the radius in the last component.
+    pub fn uniforms(&self) -> (Vec<[f32; 3]>, Vec<[f32; 4]>) {
+        let positions = self.lights.iter()
+            .map(|light| [light.position.x, light.position.y, light.position.z])
+            .collect();
+        let colors = self.lights.iter()
+            .map(|light| [light.color[0], light.color[1], light.color[2], light.radius])
+            .collect();
+        (positions, colors)
+    }
+
+    /// Sets the light uniforms of the block program, which must be in use.
+    pub fn apply(&self, program: &ShaderProgram) {
+        let (positions, colors) = self.uniforms();
+        unsafe {
+            let count_loc = gl::GetUniformLocation(program.0, b"lightCount\0".as_ptr() as *const i8);
+            gl::Uniform1i(count_loc, self.lights.len() as i32);
+            if self.lights.is_empty() {
+                return;
+            }
+            let positions_loc = gl::GetUniformLocation(program.0, b"lightPositions\0".as_ptr() as *const i8);
+            gl::Uniform3fv(positions_loc, positions.len() as i32, positions.as_ptr() as *const f32);
+            let colors_loc = gl::GetUniformLocation(program.0, b"lightColors\0".as_ptr() as *const i8);
+            gl::Uniform4fv(colors_loc, colors.len() as i32, colors.as_ptr() as *const f32);
+        }
+    }
+}
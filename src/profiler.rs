@@ -0,0 +1,178 @@
+use crate::clock::Instant;
+use std::cell::RefCell;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+// Weight of the latest frame in the averages shown on the overlay
+const SMOOTHING: f32 = 0.05;
+// Events kept in a trace, several minutes' worth; later ones are left out
+const MAX_TRACE_EVENTS: usize = 1 << 20;
+
+/// The parts of a frame whose time is measured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum System {
+    Input,
+    WorldTick,
+    Streaming,
+    Meshing,
+    Upload,
+    Render,
+}
+
+impl System {
+    pub const ALL: [System; 6] = [
+        System::Input,
+        System::WorldTick,
+        System::Streaming,
+        System::Meshing,
+        System::Upload,
+        System::Render,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            System::Input => "Input",
+            System::WorldTick => "World tick",
+            System::Streaming => "Streaming",
+            System::Meshing => "Meshing",
+            System::Upload => "Upload",
+            System::Render => "Render",
+        }
+    }
+}
+
+// A span of time spent in a system, relative to when tracing started
+struct TraceEvent {
+    name: &'static str,
+    start: Duration,
+    duration: Duration,
+}
+
+struct OpenScope {
+    system: System,
+    start: Instant,
+    // Time spent in scopes opened inside this one
+    nested: Duration,
+}
+
+/// Measures how long each system takes per frame, for the debug overlay and,
+/// when tracing, for a chrome://tracing file.
+///
+/// Scopes can nest, such as meshing while streaming; time spent in the inner
+/// scope only counts towards its own system. Like `GlState`, the profiler
+/// lives on the thread it's used from, so systems deep in the frame can be
+/// scoped without passing it around.
+pub struct Profiler {
+    frame_start: Instant,
+    // Time each system has taken so far this frame
+    frame: [Duration; System::ALL.len()],
+    // Smoothed milliseconds per frame for each system
+    averages: [f32; System::ALL.len()],
+    open: Vec<OpenScope>,
+    // When tracing started, and everything recorded since
+    trace: Option<(Instant, Vec<TraceEvent>)>,
+}
+
+thread_local! {
+    static PROFILER: RefCell<Profiler> = RefCell::new(Profiler {
+        frame_start: Instant::now(),
+        frame: [Duration::ZERO; System::ALL.len()],
+        averages: [0.0; System::ALL.len()],
+        open: Vec::new(),
+        trace: None,
+    });
+}
+
+/// Times a system until dropped.
+#[must_use = "the scope ends when dropped"]
+pub struct ProfileScope(());
+
+impl Drop for ProfileScope {
+    fn drop(&mut self) {
+        Profiler::with(Profiler::close);
+    }
+}
+
+impl Profiler {
+    fn with<R>(f: impl FnOnce(&mut Profiler) -> R) -> R {
+        PROFILER.with(|profiler| f(&mut profiler.borrow_mut()))
+    }
+
+    /// Starts timing a system, until the returned scope is dropped.
+    pub fn scope(system: System) -> ProfileScope {
+        Self::with(|profiler| profiler.open.push(OpenScope { system, start: Instant::now(), nested: Duration::ZERO }));
+        ProfileScope(())
+    }
+
+    fn close(&mut self) {
+        let Some(scope) = self.open.pop() else {
+            return;
+        };
+        let elapsed = scope.start.elapsed();
+        self.frame[scope.system as usize] += elapsed.saturating_sub(scope.nested);
+        if let Some(parent) = self.open.last_mut() {
+            parent.nested += elapsed;
+        }
+        self.record_event(scope.system.name(), scope.start, elapsed);
+    }
+
+    fn record_event(&mut self, name: &'static str, start: Instant, duration: Duration) {
+        if let Some((trace_start, events)) = &mut self.trace {
+            if events.len() < MAX_TRACE_EVENTS {
+                events.push(TraceEvent { name, start: start.saturating_duration_since(*trace_start), duration });
+            }
+        }
+    }
+
+    /// Folds the last frame's times into the averages and starts timing a new one.
+    pub fn begin_frame() {
+        Self::with(|profiler| {
+            for (average, time) in profiler.averages.iter_mut().zip(&mut profiler.frame) {
+                *average += (time.as_secs_f32() * 1000.0 - *average) * SMOOTHING;
+                *time = Duration::ZERO;
+            }
+            let frame_start = std::mem::replace(&mut profiler.frame_start, Instant::now());
+            profiler.record_event("Frame", frame_start, frame_start.elapsed());
+        });
+    }
+
+    /// Average milliseconds per frame spent in each system.
+    pub fn averages() -> [(System, f32); System::ALL.len()] {
+        Self::with(|profiler| System::ALL.map(|system| (system, profiler.averages[system as usize])))
+    }
+
+    /// Starts recording every scope for a trace, dropping any earlier recording.
+    pub fn start_trace() {
+        Self::with(|profiler| profiler.trace = Some((Instant::now(), Vec::new())));
+    }
+
+    /// Writes what was recorded since `start_trace` as a JSON file chrome://tracing
+    /// and Perfetto can open, and stops recording.
+    pub fn write_trace(path: &Path) -> Result<usize, String> {
+        let Some((_, events)) = Self::with(|profiler| profiler.trace.take()) else {
+            return Err("No trace was recorded".to_string());
+        };
+
+        let mut json = String::from("{\"traceEvents\":[\n");
+        for (index, event) in events.iter().enumerate() {
+            let separator = if index + 1 < events.len() { "," } else { "" };
+            let _ = writeln!(
+                json,
+                "{{\"name\":\"{}\",\"ph\":\"X\",\"ts\":{:.1},\"dur\":{:.1},\"pid\":1,\"tid\":1}}{}",
+                event.name,
+                event.start.as_secs_f64() * 1e6,
+                event.duration.as_secs_f64() * 1e6,
+                separator,
+            );
+        }
+        json.push_str("],\"displayTimeUnit\":\"ms\"}\n");
+
+        if let Some(directory) = path.parent().filter(|directory| !directory.as_os_str().is_empty()) {
+            fs::create_dir_all(directory).map_err(|e| e.to_string())?;
+        }
+        fs::write(path, json).map_err(|e| e.to_string())?;
+        Ok(events.len())
+    }
+}
@@ -0,0 +1,241 @@
+use crate::block::BlockType;
+use crate::entity::{Aabb, Entities, Entity, MeshHandle};
+use crate::entity_renderer::EntityRenderer;
+use crate::math::{Quat, Transform, Vec3};
+use crate::world::World;
+use std::collections::HashMap;
+
+// Arrows: launch speed in blocks per second, how long and thick they are,
+// and the damage they do
+const ARROW_SPEED: f32 = 30.0;
+const ARROW_LENGTH: f32 = 0.5;
+const ARROW_THICKNESS: f32 = 0.06;
+const ARROW_DAMAGE: f32 = 2.0;
+// Thrown blocks: launch speed, edge length and damage
+const THROW_SPEED: f32 = 14.0;
+const THROWN_BLOCK_SIZE: f32 = 0.3;
+const THROWN_BLOCK_DAMAGE: f32 = 1.0;
+const GRAVITY: f32 = 20.0;
+// How hard a hit pushes a mob, as a fraction of the projectile's velocity
+const HIT_KNOCKBACK: f32 = 0.2;
+// Seconds an arrow stays stuck in the ground, and the longest any
+// projectile flies before it's removed
+const STUCK_LIFETIME: f32 = 30.0;
+const MAX_FLIGHT_TIME: f32 = 20.0;
+
+/// What was launched, which decides what happens when it hits something.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProjectileKind {
+    /// Sticks into the ground, and hurts mobs.
+    Arrow,
+    /// Lands as a block where it hits the ground, and knocks mobs over.
+    Block(BlockType),
+}
+
+impl ProjectileKind {
+    fn speed(self) -> f32 {
+        match self {
+            ProjectileKind::Arrow => ARROW_SPEED,
+            ProjectileKind::Block(_) => THROW_SPEED,
+        }
+    }
+
+    fn damage(self) -> f32 {
+        match self {
+            ProjectileKind::Arrow => ARROW_DAMAGE,
+            ProjectileKind::Block(_) => THROWN_BLOCK_DAMAGE,
+        }
+    }
+
+    // Width and height of the entity's box, which its mesh is scaled to
+    fn size(self) -> (f32, f32) {
+        match self {
+            ProjectileKind::Arrow => (ARROW_THICKNESS, ARROW_THICKNESS),
+            ProjectileKind::Block(_) => (THROWN_BLOCK_SIZE, THROWN_BLOCK_SIZE),
+        }
+    }
+}
+
+/// Component of something flying through the air, or stuck where it landed.
+pub struct Projectile {
+    kind: ProjectileKind,
+    velocity: Vec3,
+    // Seconds since launch, or since sticking into the ground once stuck
+    age: f32,
+    stuck: bool,
+    // The mob it last hit, which it can't hit again while falling off it
+    last_hit: Option<Entity>,
+}
+
+// What a projectile ran into this step
+enum Hit {
+    Terrain { block: (i32, i32, i32), adjacent: (i32, i32, i32), distance: f32 },
+    Mob { entity: Entity, distance: f32 },
+}
+
+/// Launches arrows and thrown blocks, flies them along ballistic arcs and
+/// decides what happens when they hit the terrain or a mob.
+pub struct Projectiles {
+    arrow_mesh: MeshHandle,
+    block_meshes: HashMap<BlockType, MeshHandle>,
+}
+
+impl Projectiles {
+    pub fn new(renderer: &mut EntityRenderer) -> Option<Self> {
+        Some(Self {
+            arrow_mesh: renderer.add_cube([0.55, 0.4, 0.25])?,
+            block_meshes: HashMap::new(),
+        })
+    }
+
+    /// Launches a projectile from `origin` (its center) towards `direction`,
+    /// at the speed of its kind on top of the thrower's `velocity`.
+    pub fn launch(&mut self, entities: &mut Entities, renderer: &mut EntityRenderer, kind: ProjectileKind,
+        origin: Vec3, direction: Vec3, velocity: Vec3) -> Option<Entity> {
+        let mesh = match kind {
+            ProjectileKind::Arrow => self.arrow_mesh,
+            ProjectileKind::Block(block) => match self.block_meshes.get(&block) {
+                Some(&mesh) => mesh,
+                None => {
+                    let mesh = renderer.add_block_cube(block.info().textures)?;
+                    self.block_meshes.insert(block, mesh);
+                    mesh
+                },
+            },
+        };
+
+        let (width, height) = kind.size();
+        let velocity = velocity + direction.normalize() * kind.speed();
+        let mut transform = Transform::at(origin - Vec3::new(0.0, height / 2.0, 0.0));
+        transform.scale = Vec3::new(width, height, width);
+        orient(&mut transform, kind, velocity);
+
+        let entity = entities.spawn();
+        entities.transforms.insert(entity, transform);
+        entities.bounds.insert(entity, Aabb::standing(width, height));
+        entities.meshes.insert(entity, mesh);
+        entities.projectiles.insert(entity, Projectile { kind, velocity, age: 0.0, stuck: false, last_hit: None });
+        Some(entity)
+    }
+
+    /// Runs one step of flight for every projectile, sweeping a ray along its
+    /// path so fast ones can't pass through thin walls or mobs. Returns the
+    /// blocks thrown blocks landed as, for the caller to remesh.
+    pub fn update(&mut self, entities: &mut Entities, world: &mut World, delta_time: f32) -> Vec<(i32, i32, i32)> {
+        let mut placed = Vec::new();
+        let mut removed = Vec::new();
+        let mut mob_hits = Vec::new();
+
+        for (entity, projectile) in entities.projectiles.iter_mut() {
+            let Some(position) = entities.transforms.get(entity).map(|transform| transform.translation) else {
+                continue;
+            };
+            projectile.age += delta_time;
+            if projectile.stuck {
+                if projectile.age > STUCK_LIFETIME {
+                    removed.push(entity);
+                }
+                continue;
+            }
+            if projectile.age > MAX_FLIGHT_TIME || !world.is_loaded(position) {
+                removed.push(entity);
+                continue;
+            }
+
+            projectile.velocity.y -= GRAVITY * delta_time;
+            let (_, height) = projectile.kind.size();
+            let center = position + Vec3::new(0.0, height / 2.0, 0.0);
+            let step = projectile.velocity * delta_time;
+            let (direction, length) = (step.normalize(), step.length());
+
+            // Whichever comes first along the path: the terrain or a mob
+            let terrain = world.raycast(center, direction, length).map(|hit| Hit::Terrain {
+                block: hit.block,
+                adjacent: hit.adjacent(),
+                distance: hit.distance,
+            });
+            let mob = entities.mobs.iter()
+                .filter(|&(mob, _)| projectile.last_hit != Some(mob))
+                .filter_map(|(mob, _)| {
+                    let bounds = entities.bounds.get(mob)?.at(entities.transforms.get(mob)?.translation);
+                    bounds.ray_distance(center, direction, length).map(|distance| Hit::Mob { entity: mob, distance })
+                })
+                .min_by(|a, b| distance(a).total_cmp(&distance(b)));
+            let hit = match (terrain, mob) {
+                (Some(terrain), Some(mob)) => Some(if distance(&mob) < distance(&terrain) { mob } else { terrain }),
+                (terrain, mob) => terrain.or(mob),
+            };
+
+            let Some(transform) = entities.transforms.get_mut(entity) else {
+                continue;
+            };
+            match hit {
+                None => transform.translation = position + step,
+                Some(Hit::Terrain { block, adjacent, distance }) => match projectile.kind {
+                    // Stick into the block, its tip just inside
+                    ProjectileKind::Arrow => {
+                        transform.translation = position + direction * (distance + ARROW_LENGTH / 4.0);
+                        projectile.stuck = true;
+                        projectile.age = 0.0;
+                    },
+                    ProjectileKind::Block(thrown) => {
+                        // Land in the cell in front of the face hit, if it's free
+                        let (x, y, z) = adjacent;
+                        if adjacent != block && world.get_block(x, y, z) == BlockType::Air && world.set_block(x, y, z, thrown) {
+                            placed.push(adjacent);
+                        }
+                        removed.push(entity);
+                    },
+                },
+                Some(Hit::Mob { entity: mob, distance }) => {
+                    transform.translation = position + direction * distance;
+                    mob_hits.push((mob, projectile.kind.damage(), projectile.velocity * HIT_KNOCKBACK));
+                    projectile.last_hit = Some(mob);
+                    match projectile.kind {
+                        ProjectileKind::Arrow => removed.push(entity),
+                        // Bounce off and drop
+                        ProjectileKind::Block(_) => projectile.velocity = Vec3::zero(),
+                    }
+                },
+            }
+            orient(transform, projectile.kind, projectile.velocity);
+        }
+
+        for (mob, damage, knockback) in mob_hits {
+            let Some(state) = entities.mobs.get_mut(mob) else {
+                continue;
+            };
+            state.push(knockback);
+            if state.damage(damage) {
+                log::debug!(target: "game", "A mob was killed by a projectile");
+                removed.push(mob);
+            }
+        }
+        for entity in removed {
+            entities.despawn(entity);
+        }
+        placed
+    }
+}
+
+fn distance(hit: &Hit) -> f32 {
+    match *hit {
+        Hit::Terrain { distance, .. } | Hit::Mob { distance, .. } => distance,
+    }
+}
+
+// Points an arrow along its flight, as far as turning around the vertical
+// axis goes. Thrown blocks tumble instead.
+fn orient(transform: &mut Transform, kind: ProjectileKind, velocity: Vec3) {
+    match kind {
+        ProjectileKind::Arrow => {
+            if velocity.x != 0.0 || velocity.z != 0.0 {
+                transform.rotation = Quat::from_yaw((-velocity.z).atan2(velocity.x));
+            }
+            transform.scale = Vec3::new(ARROW_LENGTH, ARROW_THICKNESS, ARROW_THICKNESS);
+        },
+        ProjectileKind::Block(_) => {
+            transform.rotation = transform.rotation * Quat::from_yaw(0.1);
+        },
+    }
+}
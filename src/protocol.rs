@@ -0,0 +1,147 @@
+use crate::block::BlockType;
+use crate::byte_reader::ByteReader;
+use crate::world_generator::Preset;
+
+/// Bumped whenever a message layout changes; peers with another version are refused.
+pub const PROTOCOL_VERSION: u32 = 3;
+// Upper bound on a single frame, to reject garbage before allocating for it
+const MAX_FRAME_SIZE: usize = 1024 * 1024;
+
+const HELLO: u8 = 0;
+const WELCOME: u8 = 1;
+const SET_BLOCK: u8 = 2;
+const PLAYER_POSITION: u8 = 3;
+const PLAYER_LEFT: u8 = 4;
+const CHUNK_DATA: u8 = 5;
+
+/// Messages exchanged between the server and its clients.
+///
+/// On the wire each message is a frame: a little-endian `u32` payload length,
+/// then a tag byte and the fields in order, all little-endian.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    /// First message from a client.
+    Hello { version: u32 },
+    /// The server's answer to `Hello`, before any other message, with what the
+    /// client needs to generate the same terrain as the server. The layers are
+    /// sent as a count in a `u16`, then the id of each block.
+    Welcome { player_id: u32, seed: u32, preset: Preset, layers: Vec<BlockType>, snow_altitude: i32 },
+    /// A block changed. Sent by clients for their own edits and by the server
+    /// to share every edit.
+    SetBlock { x: i32, y: i32, z: i32, block: BlockType },
+    /// Where a player is. The server fills in `player_id`; clients send 0.
+    PlayerPosition { player_id: u32, x: f32, y: f32, z: f32, yaw: f32, pitch: f32 },
+    /// A player disconnected.
+    PlayerLeft { player_id: u32 },
+    /// Every block of a chunk, in the chunk data format. Sent by the server for
+    /// the chunks that differ from generated terrain when a player joins.
+    ChunkData { data: Vec<u8> },
+}
+
+impl Message {
+    /// Appends the framed message to `out`.
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        let start = out.len();
+        out.extend_from_slice(&[0; 4]);
+        match self {
+            &Message::Hello { version } => {
+                out.push(HELLO);
+                out.extend_from_slice(&version.to_le_bytes());
+            },
+            Message::Welcome { player_id, seed, preset, layers, snow_altitude } => {
+                out.push(WELCOME);
+                out.extend_from_slice(&player_id.to_le_bytes());
+                out.extend_from_slice(&seed.to_le_bytes());
+                out.push(preset.id());
+                out.extend_from_slice(&(layers.len() as u16).to_le_bytes());
+                out.extend(layers.iter().map(|block| block.id()));
+                out.extend_from_slice(&snow_altitude.to_le_bytes());
+            },
+            &Message::SetBlock { x, y, z, block } => {
+                out.push(SET_BLOCK);
+                for v in [x, y, z] {
+                    out.extend_from_slice(&v.to_le_bytes());
+                }
+                out.push(block.id());
+            },
+            &Message::PlayerPosition { player_id, x, y, z, yaw, pitch } => {
+                out.push(PLAYER_POSITION);
+                out.extend_from_slice(&player_id.to_le_bytes());
+                for v in [x, y, z, yaw, pitch] {
+                    out.extend_from_slice(&v.to_le_bytes());
+                }
+            },
+            &Message::PlayerLeft { player_id } => {
+                out.push(PLAYER_LEFT);
+                out.extend_from_slice(&player_id.to_le_bytes());
+            },
+            Message::ChunkData { data } => {
+                out.push(CHUNK_DATA);
+                out.extend_from_slice(data);
+            },
+        }
+        let length = (out.len() - start - 4) as u32;
+        out[start..start + 4].copy_from_slice(&length.to_le_bytes());
+    }
+
+    /// Decodes the first complete frame in `data`, returning the message and the
+    /// number of bytes it used. `Ok(None)` means more data is needed.
+    pub fn decode(data: &[u8]) -> Result<Option<(Message, usize)>, String> {
+        let Some(header) = data.get(..4) else {
+            return Ok(None);
+        };
+        let length = u32::from_le_bytes(header.try_into().unwrap()) as usize;
+        if length > MAX_FRAME_SIZE {
+            return Err(format!("Frame too large: {} bytes", length));
+        }
+        let Some(payload) = data.get(4..4 + length) else {
+            return Ok(None);
+        };
+
+        let mut reader = ByteReader::new(payload);
+        let invalid = || "Truncated message".to_string();
+        let message = match reader.u8().ok_or_else(invalid)? {
+            HELLO => Message::Hello { version: reader.u32().ok_or_else(invalid)? },
+            WELCOME => {
+                let player_id = reader.u32().ok_or_else(invalid)?;
+                let seed = reader.u32().ok_or_else(invalid)?;
+                let id = reader.u8().ok_or_else(invalid)?;
+                let preset = Preset::from_id(id).ok_or_else(|| format!("Unknown preset id: {}", id))?;
+                let count = reader.u16().ok_or_else(invalid)?;
+                let layers = (0..count)
+                    .map(|_| {
+                        let id = reader.u8().ok_or_else(invalid)?;
+                        BlockType::from_id(id).ok_or_else(|| format!("Unknown block id: {}", id))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                let snow_altitude = reader.i32().ok_or_else(invalid)?;
+                Message::Welcome { player_id, seed, preset, layers, snow_altitude }
+            },
+            SET_BLOCK => {
+                let (x, y, z) = (reader.i32(), reader.i32(), reader.i32());
+                let id = reader.u8().ok_or_else(invalid)?;
+                let block = BlockType::from_id(id).ok_or_else(|| format!("Unknown block id: {}", id))?;
+                Message::SetBlock {
+                    x: x.ok_or_else(invalid)?,
+                    y: y.ok_or_else(invalid)?,
+                    z: z.ok_or_else(invalid)?,
+                    block,
+                }
+            },
+            PLAYER_POSITION => Message::PlayerPosition {
+                player_id: reader.u32().ok_or_else(invalid)?,
+                x: reader.f32().ok_or_else(invalid)?,
+                y: reader.f32().ok_or_else(invalid)?,
+                z: reader.f32().ok_or_else(invalid)?,
+                yaw: reader.f32().ok_or_else(invalid)?,
+                pitch: reader.f32().ok_or_else(invalid)?,
+            },
+            PLAYER_LEFT => Message::PlayerLeft { player_id: reader.u32().ok_or_else(invalid)? },
+            // The chunk data fills the rest of the frame
+            CHUNK_DATA => Message::ChunkData { data: reader.remaining().to_vec() },
+            tag => return Err(format!("Unknown message tag: {}", tag)),
+        };
+        Ok(Some((message, 4 + length)))
+    }
+}
+
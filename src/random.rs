@@ -0,0 +1,40 @@
+/// A small xorshift generator, for the game's variety: where mobs wander and
+/// spawn, particle spread, sounds and stars. Not for anything that needs good
+/// randomness, but the same seed always gives the same values, so seeded
+/// worlds and replays play out the same.
+#[derive(Debug, Clone)]
+pub struct Random(u32);
+
+impl Random {
+    /// Starts from `seed`, made odd since xorshift never leaves zero.
+    pub fn new(seed: u32) -> Self {
+        Self(seed | 1)
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0
+    }
+
+    /// A number below `bound`, which must not be 0.
+    pub fn below(&mut self, bound: u32) -> u32 {
+        self.next_u32() % bound
+    }
+
+    /// A number in `0.0..1.0`.
+    pub fn unit(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1 << 24) as f32
+    }
+
+    /// A number in `-1.0..1.0`.
+    pub fn signed(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1 << 23) as f32 - 1.0
+    }
+
+    /// A number in `min..max`.
+    pub fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.unit()
+    }
+}
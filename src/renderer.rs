@@ -0,0 +1,150 @@
+use crate::chunk_renderer::ChunkRenderer;
+use crate::frame_uniforms::{FrameUniformBuffer, FrameUniforms};
+use crate::gl_utils::ShaderProgram;
+use crate::point_lights::PointLights;
+use crate::world::World;
+
+/// Draws the chunks of the world on one graphics API. The game talks to its
+/// backend through this, so the world can be drawn with OpenGL (`GlRenderer`)
+/// or, with the `wgpu` feature, Vulkan, Metal or DX12 (`WgpuRenderer`).
+pub trait Renderer {
+    /// Sets what stays the same for every draw of the frame.
+    fn set_frame_uniforms(&mut self, uniforms: &FrameUniforms);
+
+    /// Sets the point lights the chunks are lit by this frame.
+    fn set_point_lights(&mut self, lights: &PointLights);
+
+    /// Draws leaves as solid cubes with their gaps filled in, or see-through
+    /// for fancy leaves.
+    fn set_fast_leaves(&mut self, fast: bool);
+
+    /// Uploads the mesh of every chunk in the world, replacing all previous uploads.
+    fn upload_all(&mut self, world: &World);
+
+    /// Uploads the current mesh of one chunk, or stops drawing it if it was unloaded.
+    fn upload_chunk(&mut self, world: &World, position: (i32, i32, i32));
+
+    /// Queues a chunk for `upload_queued`, once however many times it's queued.
+    fn queue_upload(&mut self, position: (i32, i32, i32));
+
+    /// How many chunks are waiting to be uploaded.
+    fn queued_uploads(&self) -> usize;
+
+    /// Whether a chunk is waiting to be uploaded, so what's drawn of it is
+    /// an older mesh or nothing.
+    fn is_upload_queued(&self, position: (i32, i32, i32)) -> bool;
+
+    /// Uploads queued chunks until the meshes sent add up to `byte_budget`
+    /// bytes, or all of them for a budget of 0. Returns how many were uploaded.
+    fn upload_queued(&mut self, world: &World, byte_budget: usize) -> usize;
+
+    /// Stops drawing a chunk that was unloaded.
+    fn remove_chunk(&mut self, position: (i32, i32, i32));
+
+    /// Chunks no longer drawn because their mesh was evicted by the world
+    /// when it was needed again, for them to be remeshed and queued.
+    fn take_lost_uploads(&mut self) -> Vec<(i32, i32, i32)>;
+
+    /// Bytes of GPU memory taken by the chunk meshes uploaded, estimated
+    /// from their sizes.
+    fn gpu_bytes(&self) -> usize;
+
+    /// Draws the opaque faces of every uploaded chunk.
+    fn draw_opaque(&mut self);
+
+    /// Draws the water of every uploaded chunk, over the opaque faces.
+    fn draw_water(&mut self);
+
+    /// Shows what was drawn this frame, for backends drawing to a surface of
+    /// their own. OpenGL frames are shown when the platform swaps buffers.
+    fn present(&mut self);
+}
+
+/// The OpenGL backend, drawing chunks from streaming buffers with the block
+/// program. The frame uniform buffer is shared with the other programs, which
+/// read it from its binding point, and the block textures and the colormap
+/// are bound to texture units 0 and 1 by the rest of the frame.
+pub struct GlRenderer {
+    chunks: ChunkRenderer,
+    program: ShaderProgram,
+    frame_uniforms: FrameUniformBuffer,
+}
+
+impl GlRenderer {
+    /// Creates the chunk buffers, sized for the world as it is.
+    pub fn new(world: &World, program: ShaderProgram, frame_uniforms: FrameUniformBuffer) -> Option<Self> {
+        program.use_program();
+        unsafe {
+            gl::Uniform1i(gl::GetUniformLocation(program.0, b"blockTextures\0".as_ptr() as *const i8), 0);
+            gl::Uniform1i(gl::GetUniformLocation(program.0, b"colormapTexture\0".as_ptr() as *const i8), 1);
+        }
+        Some(Self { chunks: ChunkRenderer::new(world)?, program, frame_uniforms })
+    }
+}
+
+impl Renderer for GlRenderer {
+    fn set_frame_uniforms(&mut self, uniforms: &FrameUniforms) {
+        self.frame_uniforms.update(uniforms);
+    }
+
+    fn set_point_lights(&mut self, lights: &PointLights) {
+        self.program.use_program();
+        lights.apply(&self.program);
+    }
+
+    fn set_fast_leaves(&mut self, fast: bool) {
+        self.program.use_program();
+        unsafe {
+            let fast_leaves_loc = gl::GetUniformLocation(self.program.0, b"fastLeaves\0".as_ptr() as *const i8);
+            gl::Uniform1i(fast_leaves_loc, i32::from(fast));
+        }
+    }
+
+    fn upload_all(&mut self, world: &World) {
+        self.chunks.upload_all(world);
+    }
+
+    fn upload_chunk(&mut self, world: &World, position: (i32, i32, i32)) {
+        self.chunks.upload_chunk(world, position);
+    }
+
+    fn queue_upload(&mut self, position: (i32, i32, i32)) {
+        self.chunks.queue_upload(position);
+    }
+
+    fn queued_uploads(&self) -> usize {
+        self.chunks.queued_uploads()
+    }
+
+    fn is_upload_queued(&self, position: (i32, i32, i32)) -> bool {
+        self.chunks.is_upload_queued(position)
+    }
+
+    fn upload_queued(&mut self, world: &World, byte_budget: usize) -> usize {
+        self.chunks.upload_queued(world, byte_budget)
+    }
+
+    fn remove_chunk(&mut self, position: (i32, i32, i32)) {
+        self.chunks.remove_chunk(position);
+    }
+
+    fn take_lost_uploads(&mut self) -> Vec<(i32, i32, i32)> {
+        self.chunks.take_lost_uploads()
+    }
+
+    fn gpu_bytes(&self) -> usize {
+        self.chunks.uploaded_bytes()
+    }
+
+    fn draw_opaque(&mut self) {
+        self.program.use_program();
+        self.chunks.draw_opaque(&self.program);
+    }
+
+    fn draw_water(&mut self) {
+        self.program.use_program();
+        self.chunks.draw_water(&self.program);
+    }
+
+    fn present(&mut self) {}
+}
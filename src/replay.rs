@@ -0,0 +1,170 @@
+use crate::platform::{Event, Key, MouseButton};
+use std::fs;
+use std::path::Path;
+
+// Identifies a replay file
+const MAGIC: &[u8; 4] = b"RPLY";
+// Bump whenever the frame layout changes
+const VERSION: u32 = 3;
+
+/// Keys polled every frame for movement, in the order of the bits of `ReplayFrame::held`.
+pub const HELD_KEYS: [Key; 8] = [
+    Key::W,
+    Key::S,
+    Key::A,
+    Key::D,
+    Key::Q,
+    Key::E,
+    Key::LShift,
+    Key::Space,
+];
+
+// Event tags in the file
+const KEY_DOWN: u8 = 0;
+const TEXT_INPUT: u8 = 1;
+const MOUSE_MOTION: u8 = 2;
+const MOUSE_BUTTON: u8 = 3;
+const MOUSE_WHEEL: u8 = 4;
+const QUIT: u8 = 5;
+// Stands in for a key without a binding
+const NO_KEY: u8 = u8::MAX;
+// Mouse buttons in the file, numbered like SDL's
+const LEFT_BUTTON: u8 = 1;
+const RIGHT_BUTTON: u8 = 3;
+
+/// Everything the game loop takes from the player and the clock in one frame.
+#[derive(Debug, Clone, Default)]
+pub struct ReplayFrame {
+    pub delta_time: f32,
+    /// One bit per key of `HELD_KEYS` that was down.
+    pub held: u8,
+    /// Whether the left mouse button was down, for digging.
+    pub left_button: bool,
+    /// The frame's input events. Window focus changes aren't input and are left out.
+    pub events: Vec<Event>,
+}
+
+impl ReplayFrame {
+    pub fn is_held(&self, key: Key) -> bool {
+        HELD_KEYS.iter().position(|&k| k == key).is_some_and(|bit| self.held & (1 << bit) != 0)
+    }
+}
+
+/// A recorded play session. Replaying its frames with their original timings
+/// drives the game through exactly the same states, since every system only
+/// depends on input, frame times and seeded random numbers.
+#[derive(Debug, Default)]
+pub struct Replay {
+    pub frames: Vec<ReplayFrame>,
+}
+
+impl Replay {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let mut data = Vec::new();
+        data.extend_from_slice(MAGIC);
+        data.extend_from_slice(&VERSION.to_le_bytes());
+        data.extend_from_slice(&(self.frames.len() as u32).to_le_bytes());
+        for frame in &self.frames {
+            data.extend_from_slice(&frame.delta_time.to_le_bytes());
+            data.push(frame.held);
+            data.push(frame.left_button as u8);
+            let events: Vec<_> = frame.events.iter()
+                .filter(|event| !matches!(event, Event::FocusLost | Event::FocusGained | Event::SecondWindowClosed))
+                .collect();
+            data.extend_from_slice(&(events.len() as u16).to_le_bytes());
+            for event in events {
+                match event {
+                    Event::KeyDown { key, physical } => {
+                        data.push(KEY_DOWN);
+                        data.push(key.map_or(NO_KEY, Key::id));
+                        data.push(physical.map_or(NO_KEY, Key::id));
+                    },
+                    Event::TextInput(text) => {
+                        data.push(TEXT_INPUT);
+                        data.push(text.len() as u8);
+                        data.extend_from_slice(text.as_bytes());
+                    },
+                    Event::MouseMotion { xrel, yrel } => {
+                        data.push(MOUSE_MOTION);
+                        data.extend_from_slice(&xrel.to_le_bytes());
+                        data.extend_from_slice(&yrel.to_le_bytes());
+                    },
+                    Event::MouseButtonDown { button, x, y } => {
+                        data.push(MOUSE_BUTTON);
+                        data.push(match button {
+                            MouseButton::Left => LEFT_BUTTON,
+                            MouseButton::Right => RIGHT_BUTTON,
+                        });
+                        data.extend_from_slice(&x.to_le_bytes());
+                        data.extend_from_slice(&y.to_le_bytes());
+                    },
+                    Event::MouseWheel { y } => {
+                        data.push(MOUSE_WHEEL);
+                        data.extend_from_slice(&y.to_le_bytes());
+                    },
+                    Event::Quit => data.push(QUIT),
+                    Event::FocusLost | Event::FocusGained | Event::SecondWindowClosed => {},
+                }
+            }
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        fs::write(path, data).map_err(|e| e.to_string())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let data = fs::read(path).map_err(|e| e.to_string())?;
+        let mut offset = 0;
+        let mut take = |count: usize| {
+            let bytes = data.get(offset..offset + count).ok_or("Truncated replay")?;
+            offset += count;
+            Ok::<_, String>(bytes)
+        };
+        let i32_from = |bytes: &[u8]| i32::from_le_bytes(bytes.try_into().unwrap());
+
+        if take(4)? != MAGIC {
+            return Err("Not a replay file".to_string());
+        }
+        let version = u32::from_le_bytes(take(4)?.try_into().unwrap());
+        if version != VERSION {
+            return Err(format!("Unsupported replay version: {}", version));
+        }
+
+        let frame_count = u32::from_le_bytes(take(4)?.try_into().unwrap());
+        let mut frames = Vec::with_capacity(frame_count as usize);
+        for _ in 0..frame_count {
+            let delta_time = f32::from_le_bytes(take(4)?.try_into().unwrap());
+            let held = take(1)?[0];
+            let left_button = take(1)?[0] != 0;
+            let event_count = u16::from_le_bytes(take(2)?.try_into().unwrap());
+            let mut events = Vec::with_capacity(event_count as usize);
+            for _ in 0..event_count {
+                events.push(match take(1)?[0] {
+                    KEY_DOWN => Event::KeyDown { key: Key::from_id(take(1)?[0]), physical: Key::from_id(take(1)?[0]) },
+                    TEXT_INPUT => {
+                        let length = take(1)?[0] as usize;
+                        let text = String::from_utf8(take(length)?.to_vec()).map_err(|e| e.to_string())?;
+                        Event::TextInput(text)
+                    },
+                    MOUSE_MOTION => Event::MouseMotion { xrel: i32_from(take(4)?), yrel: i32_from(take(4)?) },
+                    MOUSE_BUTTON => Event::MouseButtonDown {
+                        button: if take(1)?[0] == LEFT_BUTTON { MouseButton::Left } else { MouseButton::Right },
+                        x: i32_from(take(4)?),
+                        y: i32_from(take(4)?),
+                    },
+                    MOUSE_WHEEL => Event::MouseWheel { y: i32_from(take(4)?) },
+                    QUIT => Event::Quit,
+                    tag => return Err(format!("Unknown replay event: {}", tag)),
+                });
+            }
+            frames.push(ReplayFrame { delta_time, held, left_button, events });
+        }
+        Ok(Self { frames })
+    }
+}
@@ -0,0 +1,303 @@
+use crate::block::BlockType;
+use crate::nbt::{self, Tag};
+use crate::world::World;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+// Minecraft 1.20.1, the version exported files claim to come from
+const DATA_VERSION: i32 = 3465;
+const SPONGE_VERSION: i32 = 2;
+// Refuse anything bigger than this many blocks before allocating for it
+const MAX_VOLUME: i64 = 1 << 24;
+// Marks cells of a structure file that keep whatever is already in the world
+const STRUCTURE_VOID: &str = "minecraft:structure_void";
+
+/// The Minecraft name of a block, as written to schematics.
+pub fn minecraft_name(block: BlockType) -> String {
+    match block {
+        BlockType::Grass => "minecraft:grass_block".to_string(),
+        BlockType::PowerSource => "minecraft:redstone_block".to_string(),
+        BlockType::Wire => "minecraft:redstone_wire".to_string(),
+        BlockType::Lamp | BlockType::LitLamp => "minecraft:redstone_lamp".to_string(),
+        _ => format!("minecraft:{}", block.info().name),
+    }
+}
+
+/// The block for a Minecraft block state such as
+/// `minecraft:oak_stairs[facing=east]`, or the closest one this engine has.
+/// The properties are ignored.
+pub fn from_minecraft_name(state: &str) -> Option<BlockType> {
+    let name = state.split('[').next().unwrap_or(state);
+    let name = name.strip_prefix("minecraft:").unwrap_or(name);
+    match name {
+        "grass_block" => Some(BlockType::Grass),
+        // Short grass was called grass before 1.20.3
+        "grass" | "tall_grass" | "fern" | "large_fern" => Some(BlockType::ShortGrass),
+        "cave_air" | "void_air" => Some(BlockType::Air),
+        "granite" | "diorite" | "andesite" | "deepslate" | "tuff" | "calcite" | "cobblestone"
+            | "cobbled_deepslate" | "bedrock" | "smooth_stone" => Some(BlockType::Stone),
+        "coarse_dirt" | "rooted_dirt" | "podzol" | "mycelium" | "dirt_path" | "farmland" => Some(BlockType::Dirt),
+        "packed_ice" | "blue_ice" | "frosted_ice" => Some(BlockType::Ice),
+        "bubble_column" => Some(BlockType::Water),
+        "redstone_block" => Some(BlockType::PowerSource),
+        "redstone_wire" => Some(BlockType::Wire),
+        // Lamps light up again once their circuit is updated
+        "redstone_lamp" => Some(BlockType::Lamp),
+        _ if name.ends_with("_leaves") => Some(BlockType::OakLeaves),
+        _ if name.ends_with("_glass") => Some(BlockType::Glass),
+        _ if name.ends_with("_log") || name.ends_with("_wood") => Some(BlockType::OakLog),
+        _ if name.ends_with("_sapling") => Some(BlockType::OakSapling),
+        _ => BlockType::from_name(name),
+    }
+}
+
+/// A box of blocks copied out of the world or read from a file, in either the
+/// Sponge schematic format (`.schem`) or Minecraft's structure format (`.nbt`).
+pub struct Schematic {
+    size: (i32, i32, i32),
+    // Indexed by (y * depth + z) * width + x, the Sponge order; None keeps
+    // the block already in the world
+    blocks: Vec<Option<BlockType>>,
+}
+
+impl Schematic {
+    /// Copies the blocks between two corners, inclusive.
+    pub fn copy(world: &World, min: (i32, i32, i32), max: (i32, i32, i32)) -> Self {
+        let size = (max.0 - min.0 + 1, max.1 - min.1 + 1, max.2 - min.2 + 1);
+        let mut blocks = Vec::with_capacity((size.0 * size.1 * size.2) as usize);
+        for y in min.1..=max.1 {
+            for z in min.2..=max.2 {
+                for x in min.0..=max.0 {
+                    blocks.push(Some(world.get_block(x, y, z)));
+                }
+            }
+        }
+        Self { size, blocks }
+    }
+
+    pub fn size(&self) -> (i32, i32, i32) {
+        self.size
+    }
+
+    /// Writes the blocks into the world with their lowest corner at `origin`,
+    /// and returns the loaded blocks that changed.
+    pub fn paste(&self, world: &mut World, origin: (i32, i32, i32)) -> Vec<(i32, i32, i32)> {
+        let (width, height, depth) = self.size;
+        let mut blocks = Vec::new();
+        for y in 0..height {
+            for z in 0..depth {
+                for x in 0..width {
+                    if let Some(block) = self.blocks[((y * depth + z) * width + x) as usize] {
+                        blocks.push(((origin.0 + x, origin.1 + y, origin.2 + z), block));
+                    }
+                }
+            }
+        }
+        world.set_blocks(blocks)
+    }
+
+    /// Reads a schematic or structure file, telling them apart by their
+    /// contents. Blocks this engine has nothing like become placeholders.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let data = fs::read(path).map_err(|e| e.to_string())?;
+        let (_, root) = nbt::read(&data)?;
+        let mut unknown = BTreeSet::new();
+        let schematic = if root.get("palette").is_some() || root.get("palettes").is_some() {
+            Self::from_structure(&root, &mut unknown)?
+        } else {
+            Self::from_sponge(&root, &mut unknown)?
+        };
+        if !unknown.is_empty() {
+            let names: Vec<_> = unknown.into_iter().collect();
+            log::warn!(target: "io", "Replaced unknown blocks in {} with placeholders: {}", path.display(), names.join(", "));
+        }
+        Ok(schematic)
+    }
+
+    /// Writes a structure file if the path ends in `.nbt`, and a Sponge schematic otherwise.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let data = if path.extension().is_some_and(|extension| extension == "nbt") {
+            nbt::write("", &self.to_structure())
+        } else {
+            nbt::write("Schematic", &self.to_sponge())
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        fs::write(path, data).map_err(|e| e.to_string())
+    }
+
+    fn empty(size: (i32, i32, i32)) -> Result<Self, String> {
+        let volume = size.0 as i64 * size.1 as i64 * size.2 as i64;
+        if size.0 <= 0 || size.1 <= 0 || size.2 <= 0 || volume > MAX_VOLUME {
+            return Err(format!("Unsupported size: {}x{}x{}", size.0, size.1, size.2));
+        }
+        Ok(Self { size, blocks: vec![None; volume as usize] })
+    }
+
+    // Sponge schematics, versions 2 and 3: a palette from block states to
+    // indices, and the index of every block as varints
+    fn from_sponge(root: &Tag, unknown: &mut BTreeSet<String>) -> Result<Self, String> {
+        // Version 3 wraps everything in a Schematic compound
+        let root = root.get("Schematic").unwrap_or(root);
+        let dimension = |name: &str| root.get(name).and_then(Tag::as_i64).map(|v| v as u16 as i32)
+            .ok_or_else(|| format!("Missing {}", name));
+        let mut schematic = Self::empty((dimension("Width")?, dimension("Height")?, dimension("Length")?))?;
+
+        let (palette, data) = match root.get("Blocks") {
+            Some(blocks) => (blocks.get("Palette"), blocks.get("Data")),
+            None => (root.get("Palette"), root.get("BlockData")),
+        };
+        let palette = palette.and_then(Tag::as_compound).ok_or("Missing block palette")?;
+        let Some(Tag::ByteArray(data)) = data else {
+            return Err("Missing block data".to_string());
+        };
+
+        let mut lookup = BTreeMap::new();
+        for (state, index) in palette {
+            let index = index.as_i64().ok_or("Invalid palette index")?;
+            let block = from_minecraft_name(state).unwrap_or_else(|| {
+                unknown.insert(state.clone());
+                BlockType::Placeholder
+            });
+            lookup.insert(index, block);
+        }
+
+        let mut bytes = data.iter().map(|&b| b as u8);
+        for block in &mut schematic.blocks {
+            let index = read_varint(&mut bytes).ok_or("Truncated block data")?;
+            *block = Some(*lookup.get(&index).ok_or_else(|| format!("Palette index out of range: {}", index))?);
+        }
+        Ok(schematic)
+    }
+
+    fn to_sponge(&self) -> Tag {
+        let mut palette: Vec<BlockType> = Vec::new();
+        let mut data = Vec::new();
+        for block in &self.blocks {
+            let block = block.unwrap_or(BlockType::Air);
+            let index = match palette.iter().position(|&b| b == block) {
+                Some(index) => index,
+                None => {
+                    palette.push(block);
+                    palette.len() - 1
+                },
+            };
+            write_varint(&mut data, index as u32);
+        }
+
+        Tag::compound([
+            ("Version", Tag::Int(SPONGE_VERSION)),
+            ("DataVersion", Tag::Int(DATA_VERSION)),
+            ("Width", Tag::Short(self.size.0 as i16)),
+            ("Height", Tag::Short(self.size.1 as i16)),
+            ("Length", Tag::Short(self.size.2 as i16)),
+            ("Offset", Tag::IntArray(vec![0, 0, 0])),
+            ("PaletteMax", Tag::Int(palette.len() as i32)),
+            ("Palette", Tag::Compound(palette.iter()
+                .enumerate()
+                .map(|(index, &block)| (minecraft_name(block), Tag::Int(index as i32)))
+                .collect())),
+            ("BlockData", Tag::ByteArray(data.into_iter().map(|b| b as i8).collect())),
+        ])
+    }
+
+    // Minecraft structures: a palette of block states, and a list of blocks
+    // with their position and palette index. Cells left out keep the world's block.
+    fn from_structure(root: &Tag, unknown: &mut BTreeSet<String>) -> Result<Self, String> {
+        let size = root.get("size").and_then(Tag::as_ints).ok_or("Missing size")?;
+        let [width, height, depth] = size[..] else {
+            return Err("Invalid size".to_string());
+        };
+        let mut schematic = Self::empty((width, height, depth))?;
+
+        // Structures with random variants have several palettes; the first will do
+        let palette = root.get("palette")
+            .or_else(|| root.get("palettes").and_then(Tag::as_list).and_then(|palettes| palettes.first()))
+            .and_then(Tag::as_list)
+            .ok_or("Missing block palette")?;
+        let palette = palette.iter()
+            .map(|state| {
+                let name = state.get("Name").and_then(Tag::as_str).ok_or("Palette entry without a name")?;
+                Ok(if name == STRUCTURE_VOID {
+                    None
+                } else {
+                    Some(from_minecraft_name(name).unwrap_or_else(|| {
+                        unknown.insert(name.to_string());
+                        BlockType::Placeholder
+                    }))
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        for entry in root.get("blocks").and_then(Tag::as_list).ok_or("Missing blocks")? {
+            let position = entry.get("pos").and_then(Tag::as_ints).ok_or("Block without a position")?;
+            let [x, y, z] = position[..] else {
+                return Err("Invalid block position".to_string());
+            };
+            if !(0..width).contains(&x) || !(0..height).contains(&y) || !(0..depth).contains(&z) {
+                return Err(format!("Block outside the structure: {} {} {}", x, y, z));
+            }
+            let state = entry.get("state").and_then(Tag::as_i64).ok_or("Block without a state")?;
+            let block = *palette.get(state as usize).ok_or_else(|| format!("Palette index out of range: {}", state))?;
+            schematic.blocks[((y * depth + z) * width + x) as usize] = block;
+        }
+        Ok(schematic)
+    }
+
+    fn to_structure(&self) -> Tag {
+        let (width, _, depth) = self.size;
+        let mut palette: Vec<BlockType> = Vec::new();
+        let mut blocks = Vec::new();
+        for (i, block) in self.blocks.iter().enumerate() {
+            let Some(block) = *block else {
+                continue;
+            };
+            let index = match palette.iter().position(|&b| b == block) {
+                Some(index) => index,
+                None => {
+                    palette.push(block);
+                    palette.len() - 1
+                },
+            };
+            let i = i as i32;
+            let (x, y, z) = (i % width, i / (width * depth), i / width % depth);
+            blocks.push(Tag::compound([
+                ("pos", Tag::List(vec![Tag::Int(x), Tag::Int(y), Tag::Int(z)])),
+                ("state", Tag::Int(index as i32)),
+            ]));
+        }
+
+        Tag::compound([
+            ("DataVersion", Tag::Int(DATA_VERSION)),
+            ("size", Tag::List(vec![Tag::Int(self.size.0), Tag::Int(self.size.1), Tag::Int(self.size.2)])),
+            ("palette", Tag::List(palette.iter()
+                .map(|&block| Tag::compound([("Name", Tag::String(minecraft_name(block)))]))
+                .collect())),
+            ("blocks", Tag::List(blocks)),
+            ("entities", Tag::List(Vec::new())),
+        ])
+    }
+}
+
+// Seven bits at a time, lowest first, with the top bit set on all but the last byte
+fn read_varint(bytes: &mut impl Iterator<Item = u8>) -> Option<i64> {
+    let mut value = 0i64;
+    for shift in (0..35).step_by(7) {
+        let byte = bytes.next()?;
+        value |= i64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+fn write_varint(data: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        data.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    data.push(value as u8);
+}
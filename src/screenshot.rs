@@ -0,0 +1,98 @@
+use image::RgbaImage;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Reads back the current framebuffer.
+pub fn capture(width: u32, height: u32) -> RgbaImage {
+    let mut pixels = vec![0u8; (width * height * 4) as usize];
+    unsafe {
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl::ReadPixels(0, 0, width as i32, height as i32, gl::RGBA, gl::UNSIGNED_BYTE, pixels.as_mut_ptr() as *mut _);
+    }
+
+    // OpenGL rows start at the bottom, image rows at the top
+    let row = (width * 4) as usize;
+    let flipped = pixels.chunks_exact(row).rev().flatten().copied().collect();
+    let mut image = RgbaImage::from_raw(width, height, flipped).expect("Framebuffer size mismatch");
+    // Blending leaves the alpha channel meaningless for the saved picture
+    for pixel in image.pixels_mut() {
+        pixel[3] = 255;
+    }
+    image
+}
+
+/// Saves the current framebuffer as a PNG in `directory`, named after the date
+/// and time. Returns the path written.
+pub fn save_screenshot(directory: &Path, width: u32, height: u32) -> Result<PathBuf, String> {
+    fs::create_dir_all(directory).map_err(|e| e.to_string())?;
+    let name = timestamp();
+    // Several screenshots within a second get a counter
+    let mut path = directory.join(format!("{}.png", name));
+    let mut count = 1;
+    while path.exists() {
+        count += 1;
+        path = directory.join(format!("{}_{}.png", name, count));
+    }
+    capture(width, height).save(&path).map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+/// Captures a frame every few seconds into a folder of numbered PNGs.
+pub struct Timelapse {
+    directory: PathBuf,
+    interval: f32,
+    elapsed: f32,
+    frame: u32,
+}
+
+impl Timelapse {
+    /// Starts a timelapse in a new timestamped folder inside `directory`.
+    pub fn start(directory: &Path, interval: f32) -> Self {
+        Self {
+            directory: directory.join(format!("timelapse_{}", timestamp())),
+            interval,
+            // The first frame is captured right away
+            elapsed: interval,
+            frame: 0,
+        }
+    }
+
+    /// Counts time and captures the framebuffer whenever the interval has passed.
+    /// Call after the frame is drawn, before swapping buffers.
+    pub fn update(&mut self, delta_time: f32, width: u32, height: u32) {
+        self.elapsed += delta_time;
+        if self.elapsed < self.interval {
+            return;
+        }
+        self.elapsed -= self.interval;
+
+        let path = self.directory.join(format!("{:05}.png", self.frame));
+        let result = fs::create_dir_all(&self.directory)
+            .map_err(|e| e.to_string())
+            .and_then(|_| capture(width, height).save(&path).map_err(|e| e.to_string()));
+        match result {
+            Ok(()) => self.frame += 1,
+            Err(e) => log::warn!(target: "io", "Failed to save timelapse frame {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// Current UTC date and time as `YYYY-MM-DD_HH-MM-SS`, for naming saved files.
+pub fn timestamp() -> String {
+    let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()) as i64;
+    let (days, time) = (seconds.div_euclid(86400), seconds.rem_euclid(86400));
+
+    // Civil date from days since 1970-01-01, in 400-year eras starting in March
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}_{:02}-{:02}-{:02}", year, month, day, time / 3600, time / 60 % 60, time % 60)
+}
@@ -0,0 +1,54 @@
+use crate::debug_lines::DebugLines;
+use crate::math::Vec3;
+
+/// Lowest and highest block of a box.
+pub type Bounds = ((i32, i32, i32), (i32, i32, i32));
+
+// Drawn a little outside the blocks so the lines don't fight with their faces
+const OUTLINE_MARGIN: f32 = 0.51;
+// The block being looked at is outlined in thin dark lines, or in several
+// nested bright ones that read as a thick edge
+const TARGET_COLOR: [f32; 3] = [0.1, 0.1, 0.1];
+const TARGET_MARGIN: f32 = 0.502;
+const HIGH_CONTRAST_LINES: usize = 4;
+const HIGH_CONTRAST_SPACING: f32 = 0.004;
+
+/// A box of blocks picked with `/pos1` and `/pos2`, for commands that work on
+/// a region such as saving a schematic.
+#[derive(Default)]
+pub struct Selection {
+    pub first: Option<(i32, i32, i32)>,
+    pub second: Option<(i32, i32, i32)>,
+}
+
+impl Selection {
+    /// The lowest and highest blocks of the box, once both corners are set.
+    pub fn bounds(&self) -> Option<Bounds> {
+        let (a, b) = (self.first?, self.second?);
+        Some(((a.0.min(b.0), a.1.min(b.1), a.2.min(b.2)), (a.0.max(b.0), a.1.max(b.1), a.2.max(b.2))))
+    }
+
+    /// Queues the outline of the box, or of whichever corner is set so far.
+    pub fn queue_outline(&self, lines: &mut DebugLines) {
+        let (min, max) = match (self.bounds(), self.first.or(self.second)) {
+            (Some(bounds), _) => bounds,
+            (None, Some(corner)) => (corner, corner),
+            (None, None) => return,
+        };
+        let margin = Vec3::new(OUTLINE_MARGIN, OUTLINE_MARGIN, OUTLINE_MARGIN);
+        let to_vec = |(x, y, z): (i32, i32, i32)| Vec3::new(x as f32, y as f32, z as f32);
+        lines.cuboid(to_vec(min) - margin, to_vec(max) + margin, lines.palette.colors().selection);
+    }
+}
+
+/// Queues the outline of the block being looked at, thick and in the
+/// palette's target color when `high_contrast`.
+pub fn queue_target_outline(lines: &mut DebugLines, block: (i32, i32, i32), high_contrast: bool) {
+    let center = Vec3::new(block.0 as f32, block.1 as f32, block.2 as f32);
+    let (count, color) = if high_contrast { (HIGH_CONTRAST_LINES, lines.palette.colors().target) } else { (1, TARGET_COLOR) };
+    for line in 0..count {
+        let margin = TARGET_MARGIN + line as f32 * HIGH_CONTRAST_SPACING;
+        let margin = Vec3::new(margin, margin, margin);
+        lines.cuboid(center - margin, center + margin, color);
+    }
+}
@@ -0,0 +1,525 @@
+use crate::atomic_file;
+use crate::hud::Hud;
+use crate::language::{self, text, DEFAULT_LANGUAGE};
+use crate::palette::Palette;
+use crate::streaming::{DEFAULT_RENDER_DISTANCE, MAX_RENDER_DISTANCE, MIN_RENDER_DISTANCE};
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+/// Where the settings are kept between sessions.
+pub const SETTINGS_PATH: &str = "settings.cfg";
+
+const MIN_FOV: f32 = 30.0;
+const MAX_FOV: f32 = 110.0;
+const FOV_STEP: f32 = 5.0;
+const MIN_SENSITIVITY: f32 = 0.02;
+const MAX_SENSITIVITY: f32 = 0.5;
+const SENSITIVITY_STEP: f32 = 0.02;
+// Frame rate caps to step through on the settings screen, 0 for none
+const FPS_CAPS: [u32; 6] = [30, 60, 120, 144, 240, 0];
+// Same for the chunk upload budget, in KiB per frame
+const UPLOAD_BUDGETS: [u32; 6] = [256, 512, 1024, 2048, 4096, 0];
+// And for the memory budget, in MiB
+const MEMORY_BUDGETS: [u32; 6] = [256, 512, 1024, 2048, 4096, 0];
+// And for the UI scale. Every screen still fits the window at the largest
+const UI_SCALES: [f32; 4] = [0.75, 1.0, 1.25, 1.5];
+
+const ROW_WIDTH: f32 = 360.0;
+const ROW_HEIGHT: f32 = 28.0;
+const ROW_GAP: f32 = 6.0;
+// Height of a line of text, in pixels
+const TEXT_SIZE: f32 = 18.0;
+const PANEL_MARGIN: f32 = 14.0;
+
+const PANEL_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+const ROW_COLOR: [f32; 4] = [0.2, 0.2, 0.2, 0.8];
+const SELECTED_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const TEXT_COLOR: [f32; 4] = [0.9, 0.9, 0.9, 1.0];
+const HINT_COLOR: [f32; 4] = [0.6, 0.6, 0.6, 1.0];
+
+/// The player's preferences, read at startup and written whenever one changes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    pub render_distance: i32,
+    /// Vertical field of view, in degrees.
+    pub fov: f32,
+    /// Degrees turned per pixel of mouse movement.
+    pub mouse_sensitivity: f32,
+    pub vsync: bool,
+    /// Most frames drawn per second, 0 for no limit.
+    pub fps_cap: u32,
+    pub fullscreen: bool,
+    pub invert_y: bool,
+    pub clouds: bool,
+    /// Leaves drawn with see-through gaps rather than as solid cubes.
+    pub fancy_leaves: bool,
+    /// Jumping up ledges automatically rather than stepping onto them.
+    pub auto_jump: bool,
+    pub view_bobbing: bool,
+    /// The view dipping when landing from a fall.
+    pub landing_dip: bool,
+    pub screen_shake: bool,
+    /// Controllers rumbling as blocks break, the player is hurt and things explode.
+    pub rumble: bool,
+    /// The outline around the block being looked at drawn thick and bright,
+    /// rather than as thin dark lines.
+    pub high_contrast_outline: bool,
+    /// Size of the HUD and menus, 1 for their normal size.
+    pub ui_scale: f32,
+    /// No rapid flashing, such as of lit TNT or the screen when hurt.
+    pub reduce_flashing: bool,
+    /// Colors of highlights, HUD accents and debug views.
+    pub palette: Palette,
+    /// Most chunk mesh data uploaded to the GPU per frame while streaming, in
+    /// KiB, 0 for no limit.
+    pub upload_budget: u32,
+    /// Most memory the chunks may take, in MiB, 0 for no limit. Past it the
+    /// farthest columns are unloaded, as if the render distance was shorter.
+    pub memory_budget: u32,
+    /// Code of the language the UI text is in, such as `en_us`.
+    pub language: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            render_distance: DEFAULT_RENDER_DISTANCE,
+            fov: 45.0,
+            mouse_sensitivity: 0.10,
+            vsync: false,
+            fps_cap: 60,
+            fullscreen: false,
+            invert_y: false,
+            clouds: true,
+            fancy_leaves: true,
+            auto_jump: false,
+            view_bobbing: true,
+            landing_dip: true,
+            screen_shake: true,
+            rumble: true,
+            high_contrast_outline: false,
+            ui_scale: 1.0,
+            reduce_flashing: false,
+            palette: Palette::Default,
+            upload_budget: 1024,
+            memory_budget: 0,
+            language: DEFAULT_LANGUAGE.to_string(),
+        }
+    }
+}
+
+impl Settings {
+    /// Reads `key = value` lines written by `save`. Settings missing from the
+    /// file, or that can't be read, keep their default.
+    pub fn load(path: &Path) -> Self {
+        let mut settings = Self::default();
+        let Ok(text) = fs::read_to_string(path) else {
+            return settings;
+        };
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let result = match line.split_once('=') {
+                Some((key, value)) => settings.set(key.trim(), value.trim()),
+                None => Err(format!("Expected `key = value`, found `{}`", line)),
+            };
+            if let Err(e) = result {
+                log::warn!(target: "io", "{}: {}", path.display(), e);
+            }
+        }
+        settings
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let invalid = || format!("Invalid value for {}: {}", key, value);
+        let number = || value.parse::<f32>().map_err(|_| invalid());
+        let flag = || value.parse::<bool>().map_err(|_| invalid());
+        match key {
+            "render_distance" => {
+                let distance = value.parse::<i32>().map_err(|_| invalid())?;
+                self.render_distance = distance.clamp(MIN_RENDER_DISTANCE, MAX_RENDER_DISTANCE);
+            },
+            "fov" => self.fov = number()?.clamp(MIN_FOV, MAX_FOV),
+            "mouse_sensitivity" => self.mouse_sensitivity = number()?.clamp(MIN_SENSITIVITY, MAX_SENSITIVITY),
+            "vsync" => self.vsync = flag()?,
+            "fps_cap" => self.fps_cap = parse_limit(value).ok_or_else(invalid)?,
+            "fullscreen" => self.fullscreen = flag()?,
+            "invert_y" => self.invert_y = flag()?,
+            "clouds" => self.clouds = flag()?,
+            "fancy_leaves" => self.fancy_leaves = flag()?,
+            "auto_jump" => self.auto_jump = flag()?,
+            "view_bobbing" => self.view_bobbing = flag()?,
+            "landing_dip" => self.landing_dip = flag()?,
+            "screen_shake" => self.screen_shake = flag()?,
+            "rumble" => self.rumble = flag()?,
+            "high_contrast_outline" => self.high_contrast_outline = flag()?,
+            "ui_scale" => self.ui_scale = number()?.clamp(UI_SCALES[0], UI_SCALES[UI_SCALES.len() - 1]),
+            "reduce_flashing" => self.reduce_flashing = flag()?,
+            "palette" => self.palette = Palette::from_name(value).ok_or_else(invalid)?,
+            "upload_budget" => self.upload_budget = parse_limit(value).ok_or_else(invalid)?,
+            "memory_budget" => self.memory_budget = parse_limit(value).ok_or_else(invalid)?,
+            "language" => self.language = value.to_string(),
+            _ => return Err(format!("Unknown setting: {}", key)),
+        }
+        Ok(())
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut text = String::new();
+        let _ = writeln!(text, "render_distance = {}", self.render_distance);
+        let _ = writeln!(text, "fov = {}", self.fov);
+        let _ = writeln!(text, "mouse_sensitivity = {}", self.mouse_sensitivity);
+        let _ = writeln!(text, "vsync = {}", self.vsync);
+        let _ = writeln!(text, "fps_cap = {}", limit_label(self.fps_cap));
+        let _ = writeln!(text, "fullscreen = {}", self.fullscreen);
+        let _ = writeln!(text, "invert_y = {}", self.invert_y);
+        let _ = writeln!(text, "clouds = {}", self.clouds);
+        let _ = writeln!(text, "fancy_leaves = {}", self.fancy_leaves);
+        let _ = writeln!(text, "auto_jump = {}", self.auto_jump);
+        let _ = writeln!(text, "view_bobbing = {}", self.view_bobbing);
+        let _ = writeln!(text, "landing_dip = {}", self.landing_dip);
+        let _ = writeln!(text, "screen_shake = {}", self.screen_shake);
+        let _ = writeln!(text, "rumble = {}", self.rumble);
+        let _ = writeln!(text, "high_contrast_outline = {}", self.high_contrast_outline);
+        let _ = writeln!(text, "ui_scale = {}", self.ui_scale);
+        let _ = writeln!(text, "reduce_flashing = {}", self.reduce_flashing);
+        let _ = writeln!(text, "palette = {}", self.palette.name());
+        let _ = writeln!(text, "upload_budget = {}", limit_label(self.upload_budget));
+        let _ = writeln!(text, "memory_budget = {}", limit_label(self.memory_budget));
+        let _ = writeln!(text, "language = {}", self.language);
+        atomic_file::write(path, text.as_bytes())
+    }
+
+    /// Shortest time a frame may take under the frame rate cap, if there is one.
+    pub fn frame_budget(&self) -> Option<Duration> {
+        (self.fps_cap > 0).then(|| Duration::from_secs_f64(1.0 / self.fps_cap as f64))
+    }
+
+    /// Bytes of chunk meshes to upload per frame, 0 for no limit.
+    pub fn upload_budget_bytes(&self) -> usize {
+        self.upload_budget as usize * 1024
+    }
+
+    /// Bytes of memory the chunks may take, 0 for no limit.
+    pub fn memory_budget_bytes(&self) -> usize {
+        self.memory_budget as usize * 1024 * 1024
+    }
+
+    /// Describes how the frame rate is limited, for the debug overlay.
+    pub fn frame_mode(&self) -> String {
+        match (self.vsync, self.fps_cap) {
+            (true, 0) => "VSync".to_string(),
+            (true, cap) => format!("VSync, capped at {}", cap),
+            (false, 0) => "Uncapped".to_string(),
+            (false, cap) => format!("Capped at {}", cap),
+        }
+    }
+}
+
+/// Reads a limit such as the frame rate cap: a number, or `off` for none (0).
+pub fn parse_limit(value: &str) -> Option<u32> {
+    match value {
+        "off" => Some(0),
+        _ => value.parse().ok(),
+    }
+}
+
+fn limit_label(limit: u32) -> String {
+    match limit {
+        0 => "off".to_string(),
+        limit => limit.to_string(),
+    }
+}
+
+// Rows of the settings screen, top to bottom
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Row {
+    // Switches between pages
+    Page,
+    RenderDistance,
+    Fov,
+    Sensitivity,
+    Vsync,
+    FpsCap,
+    Fullscreen,
+    InvertY,
+    Clouds,
+    Leaves,
+    AutoJump,
+    ViewBobbing,
+    LandingDip,
+    ScreenShake,
+    Rumble,
+    HighContrastOutline,
+    UiScale,
+    ReduceFlashing,
+    Palette,
+    UploadBudget,
+    MemoryBudget,
+    Language,
+}
+
+// Groups of settings shown one at a time, each starting with the row that
+// switches between them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Page {
+    Video,
+    Controls,
+    Accessibility,
+}
+
+impl Page {
+    const ALL: [Page; 3] = [Page::Video, Page::Controls, Page::Accessibility];
+
+    fn rows(self) -> &'static [Row] {
+        match self {
+            Page::Video => &[
+                Row::Page,
+                Row::RenderDistance,
+                Row::Vsync,
+                Row::FpsCap,
+                Row::Fullscreen,
+                Row::Clouds,
+                Row::Leaves,
+                Row::UploadBudget,
+                Row::MemoryBudget,
+            ],
+            Page::Controls => &[
+                Row::Page,
+                Row::Sensitivity,
+                Row::InvertY,
+                Row::AutoJump,
+                Row::Rumble,
+                Row::Language,
+            ],
+            Page::Accessibility => &[
+                Row::Page,
+                Row::Fov,
+                Row::ViewBobbing,
+                Row::LandingDip,
+                Row::ScreenShake,
+                Row::HighContrastOutline,
+                Row::UiScale,
+                Row::ReduceFlashing,
+                Row::Palette,
+            ],
+        }
+    }
+
+    fn label(self) -> String {
+        match self {
+            Page::Video => text("settings.page.video", &[]),
+            Page::Controls => text("settings.page.controls", &[]),
+            Page::Accessibility => text("settings.page.accessibility", &[]),
+        }
+    }
+}
+
+impl Row {
+    fn label(self, settings: &Settings, page: Page) -> String {
+        let on_off = |on: bool| text(if on { "options.on" } else { "options.off" }, &[]);
+        let limit = |limit: u32| match limit {
+            0 => text("options.off", &[]),
+            limit => limit.to_string(),
+        };
+        match self {
+            Row::Page => page.label(),
+            Row::RenderDistance => text("settings.render_distance", &[&settings.render_distance]),
+            Row::Fov => text("settings.fov", &[&settings.fov.round()]),
+            Row::Sensitivity => text("settings.sensitivity", &[&format!("{:.2}", settings.mouse_sensitivity)]),
+            Row::Vsync => text("settings.vsync", &[&on_off(settings.vsync)]),
+            Row::FpsCap => text("settings.fps_cap", &[&limit(settings.fps_cap)]),
+            Row::Fullscreen => text("settings.fullscreen", &[&on_off(settings.fullscreen)]),
+            Row::InvertY => text("settings.invert_y", &[&on_off(settings.invert_y)]),
+            Row::Clouds => text("settings.clouds", &[&on_off(settings.clouds)]),
+            Row::Leaves => text("settings.leaves", &[&text(if settings.fancy_leaves { "options.fancy" } else { "options.fast" }, &[])]),
+            Row::AutoJump => text("settings.auto_jump", &[&on_off(settings.auto_jump)]),
+            Row::ViewBobbing => text("settings.view_bobbing", &[&on_off(settings.view_bobbing)]),
+            Row::LandingDip => text("settings.landing_dip", &[&on_off(settings.landing_dip)]),
+            Row::ScreenShake => text("settings.screen_shake", &[&on_off(settings.screen_shake)]),
+            Row::Rumble => text("settings.rumble", &[&on_off(settings.rumble)]),
+            Row::HighContrastOutline => text("settings.high_contrast_outline", &[&on_off(settings.high_contrast_outline)]),
+            Row::UiScale => text("settings.ui_scale", &[&(settings.ui_scale * 100.0).round()]),
+            Row::ReduceFlashing => text("settings.reduce_flashing", &[&on_off(settings.reduce_flashing)]),
+            Row::Palette => text("settings.palette", &[&text(&format!("settings.palette.{}", settings.palette.name()), &[])]),
+            Row::UploadBudget => match settings.upload_budget {
+                0 => text("settings.upload_budget", &[&limit(0)]),
+                budget => text("settings.upload_budget", &[&text("settings.kib", &[&budget])]),
+            },
+            Row::MemoryBudget => match settings.memory_budget {
+                0 => text("settings.memory_budget", &[&limit(0)]),
+                budget => text("settings.memory_budget", &[&text("settings.mib", &[&budget])]),
+            },
+            Row::Language => {
+                let name = language::available().into_iter()
+                    .find(|(code, _)| *code == settings.language)
+                    .map_or_else(|| settings.language.clone(), |(_, name)| name);
+                text("settings.language", &[&name])
+            },
+        }
+    }
+
+    // Steps the value up or down; toggles flip either way. The page row is
+    // left to the screen
+    fn adjust(self, settings: &mut Settings, direction: i32) {
+        let step = direction.signum();
+        match self {
+            Row::Page => {},
+            Row::RenderDistance => {
+                settings.render_distance = (settings.render_distance + step).clamp(MIN_RENDER_DISTANCE, MAX_RENDER_DISTANCE);
+            },
+            Row::Fov => settings.fov = (settings.fov + step as f32 * FOV_STEP).clamp(MIN_FOV, MAX_FOV),
+            Row::Sensitivity => {
+                let sensitivity = settings.mouse_sensitivity + step as f32 * SENSITIVITY_STEP;
+                settings.mouse_sensitivity = sensitivity.clamp(MIN_SENSITIVITY, MAX_SENSITIVITY);
+            },
+            Row::Vsync => settings.vsync = !settings.vsync,
+            Row::FpsCap => {
+                // Caps set by hand in the file step on from the default
+                let index = FPS_CAPS.iter().position(|&cap| cap == settings.fps_cap).unwrap_or(1) as i32;
+                settings.fps_cap = FPS_CAPS[(index + step).rem_euclid(FPS_CAPS.len() as i32) as usize];
+            },
+            Row::Fullscreen => settings.fullscreen = !settings.fullscreen,
+            Row::InvertY => settings.invert_y = !settings.invert_y,
+            Row::Clouds => settings.clouds = !settings.clouds,
+            Row::Leaves => settings.fancy_leaves = !settings.fancy_leaves,
+            Row::AutoJump => settings.auto_jump = !settings.auto_jump,
+            Row::ViewBobbing => settings.view_bobbing = !settings.view_bobbing,
+            Row::LandingDip => settings.landing_dip = !settings.landing_dip,
+            Row::ScreenShake => settings.screen_shake = !settings.screen_shake,
+            Row::Rumble => settings.rumble = !settings.rumble,
+            Row::HighContrastOutline => settings.high_contrast_outline = !settings.high_contrast_outline,
+            Row::UiScale => {
+                let index = UI_SCALES.iter().position(|&scale| scale == settings.ui_scale).unwrap_or(1) as i32;
+                settings.ui_scale = UI_SCALES[(index + step).rem_euclid(UI_SCALES.len() as i32) as usize];
+            },
+            Row::ReduceFlashing => settings.reduce_flashing = !settings.reduce_flashing,
+            Row::Palette => {
+                let index = Palette::ALL.iter().position(|&palette| palette == settings.palette).unwrap_or(0) as i32;
+                settings.palette = Palette::ALL[(index + step).rem_euclid(Palette::ALL.len() as i32) as usize];
+            },
+            Row::UploadBudget => {
+                let index = UPLOAD_BUDGETS.iter().position(|&budget| budget == settings.upload_budget).unwrap_or(2) as i32;
+                settings.upload_budget = UPLOAD_BUDGETS[(index + step).rem_euclid(UPLOAD_BUDGETS.len() as i32) as usize];
+            },
+            Row::MemoryBudget => {
+                let index = MEMORY_BUDGETS.iter().position(|&budget| budget == settings.memory_budget).unwrap_or(5) as i32;
+                settings.memory_budget = MEMORY_BUDGETS[(index + step).rem_euclid(MEMORY_BUDGETS.len() as i32) as usize];
+            },
+            Row::Language => {
+                let codes = language::available().into_iter().map(|(code, _)| code).collect::<Vec<_>>();
+                if !codes.is_empty() {
+                    let index = codes.iter().position(|code| *code == settings.language).map_or(0, |index| index as i32 + step);
+                    settings.language = codes[index.rem_euclid(codes.len() as i32) as usize].clone();
+                }
+            },
+        }
+    }
+}
+
+/// Menu listing the settings a page at a time, changed with the arrow keys
+/// or by clicking.
+pub struct SettingsScreen {
+    open: bool,
+    page: Page,
+    // Index of the selected row on the page
+    selected: usize,
+}
+
+impl SettingsScreen {
+    pub fn new() -> Self {
+        Self { open: false, page: Page::Video, selected: 0 }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn set_open(&mut self, open: bool) {
+        self.open = open;
+    }
+
+    /// Moves the selection up (`-1`) or down (`1`), wrapping around.
+    pub fn select(&mut self, offset: i32) {
+        let rows = self.page.rows().len() as i32;
+        self.selected = (self.selected as i32 + offset).rem_euclid(rows) as usize;
+    }
+
+    /// Steps the selected setting up (`1`) or down (`-1`), or turns the page.
+    /// Returns whether a setting changed.
+    pub fn adjust(&mut self, settings: &mut Settings, direction: i32) -> bool {
+        let row = self.page.rows()[self.selected];
+        if row == Row::Page {
+            let index = Page::ALL.iter().position(|&page| page == self.page).unwrap_or(0) as i32 + direction.signum();
+            self.page = Page::ALL[index.rem_euclid(Page::ALL.len() as i32) as usize];
+            return false;
+        }
+        let previous = settings.clone();
+        row.adjust(settings, direction);
+        *settings != previous
+    }
+
+    /// Selects the clicked row and steps its value down on its left half, up on
+    /// its right half. Returns whether a setting changed.
+    pub fn click(&mut self, settings: &mut Settings, x: f32, y: f32, screen_width: f32, screen_height: f32) -> bool {
+        let left = (screen_width - ROW_WIDTH) / 2.0;
+        if !(left..left + ROW_WIDTH).contains(&x) {
+            return false;
+        }
+        let clicked = (0..self.page.rows().len()).find(|&row| {
+            let top = row_top(row, screen_height);
+            (top..top + ROW_HEIGHT).contains(&y)
+        });
+        match clicked {
+            Some(row) => {
+                self.selected = row;
+                self.adjust(settings, if x < screen_width / 2.0 { -1 } else { 1 })
+            },
+            None => false,
+        }
+    }
+
+    /// Queues the settings screen centered on the screen, if it is open.
+    pub fn draw(&self, settings: &Settings, hud: &mut Hud, screen_width: f32, screen_height: f32) {
+        if !self.open {
+            return;
+        }
+
+        let left = (screen_width - ROW_WIDTH) / 2.0;
+        let top = row_top(0, screen_height);
+        let rows = self.page.rows();
+        let bottom = row_top(rows.len() - 1, screen_height) + ROW_HEIGHT;
+        let hint_height = TEXT_SIZE + ROW_GAP;
+        hud.rect(
+            left - PANEL_MARGIN,
+            top - PANEL_MARGIN,
+            ROW_WIDTH + 2.0 * PANEL_MARGIN,
+            bottom - top + hint_height + 2.0 * PANEL_MARGIN,
+            PANEL_COLOR,
+        );
+
+        for (index, row) in rows.iter().enumerate() {
+            let y = row_top(index, screen_height);
+            hud.rect(left, y, ROW_WIDTH, ROW_HEIGHT, ROW_COLOR);
+            if index == self.selected {
+                hud.outline(left - 2.0, y - 2.0, ROW_WIDTH + 4.0, ROW_HEIGHT + 4.0, 2.0, SELECTED_COLOR);
+            }
+            let label = format!("< {} >", row.label(settings, self.page));
+            let x = left + (ROW_WIDTH - hud.measure_text(TEXT_SIZE, &label)) / 2.0;
+            hud.draw_text(x, y + (ROW_HEIGHT - TEXT_SIZE) / 2.0, TEXT_SIZE, TEXT_COLOR, &label);
+        }
+
+        let hint = text("settings.hint", &[]);
+        let x = left + (ROW_WIDTH - hud.measure_text(TEXT_SIZE, &hint)) / 2.0;
+        hud.draw_text(x, bottom + ROW_GAP, TEXT_SIZE, HINT_COLOR, &hint);
+    }
+}
+
+// Top of a row of the settings screen. Rows are centered vertically as the
+// longest page's would be, so the page row stays put when turning pages
+fn row_top(row: usize, screen_height: f32) -> f32 {
+    let rows = Page::ALL.iter().map(|page| page.rows().len()).max().unwrap_or(0);
+    let height = rows as f32 * (ROW_HEIGHT + ROW_GAP) - ROW_GAP;
+    (screen_height - height) / 2.0 + row as f32 * (ROW_HEIGHT + ROW_GAP)
+}
@@ -0,0 +1,278 @@
+use crate::block_entity::BlockEntity;
+use crate::gl_utils::{buffer_data, Buffer, BufferType, GlState, ShaderProgram, VertexArray};
+use crate::hud::Hud;
+use crate::math::Vec3;
+use crate::nbt::Tag;
+use crate::text::{GlyphAtlas, FONT_TEXTURE_UNIT};
+use crate::ttf::Font;
+use crate::world::World;
+use std::any::Any;
+
+/// Name sign block entities are saved under.
+pub const SIGN_KIND: &str = "sign";
+/// Lines of text on a sign, and the most characters each holds.
+pub const SIGN_LINES: usize = 4;
+pub const MAX_LINE_LENGTH: usize = 12;
+// Height of a line of text on the sign, in blocks, and how far in front of
+// the face it floats so it doesn't fight with it
+const LINE_HEIGHT: f32 = 0.14;
+const TEXT_OFFSET: f32 = 0.02;
+// Pixels per em glyphs are rasterized at for drawing in the world
+const GLYPH_SIZE: f32 = 32.0;
+// Text is drawn on signs within this many blocks
+const VIEW_DISTANCE: f32 = 24.0;
+const TEXT_COLOR: [f32; 3] = [0.1, 0.07, 0.03];
+
+// The sign editor
+const EDITOR_LINE_HEIGHT: f32 = 24.0;
+const EDITOR_PADDING: f32 = 16.0;
+const EDITOR_BACKGROUND: [f32; 4] = [0.45, 0.33, 0.18, 0.95];
+const EDITOR_TEXT_COLOR: [f32; 4] = [0.1, 0.07, 0.03, 1.0];
+const EDITOR_CURSOR_COLOR: [f32; 4] = [1.0, 1.0, 0.6, 1.0];
+
+type TextVertex = [f32; 5];  // x, y, z, s, t
+
+/// Block entity of a sign: its text, and the way the written face points.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sign {
+    /// Outward normal of the face the text is on, always horizontal.
+    pub facing: (i32, i32, i32),
+    pub lines: [String; SIGN_LINES],
+}
+
+impl Sign {
+    /// A blank sign written on the face pointing along `facing`.
+    pub fn new(facing: (i32, i32, i32)) -> Self {
+        Self { facing, lines: Default::default() }
+    }
+
+    /// The horizontal face of a sign placed against a face with the given
+    /// normal, by someone looking along `front`: the face placed against, or
+    /// the one towards them when placed on top of or under something.
+    pub fn facing_for(normal: (i32, i32, i32), front: Vec3) -> (i32, i32, i32) {
+        if normal.1 == 0 && normal != (0, 0, 0) {
+            return normal;
+        }
+        if front.x.abs() > front.z.abs() {
+            (-front.x.signum() as i32, 0, 0)
+        } else {
+            (0, 0, -front.z.signum() as i32)
+        }
+    }
+
+    /// Reads a sign back from what `save` wrote.
+    pub fn load(tag: &Tag) -> Option<Box<dyn BlockEntity>> {
+        let facing = tag.get("Facing")?.as_ints().filter(|facing| facing.len() == 3)?;
+        let mut sign = Sign::new((facing[0], facing[1], facing[2]));
+        for (line, text) in sign.lines.iter_mut().zip(tag.get("Lines")?.as_list()?) {
+            *line = text.as_str()?.chars().take(MAX_LINE_LENGTH).collect();
+        }
+        Some(Box::new(sign))
+    }
+}
+
+impl BlockEntity for Sign {
+    fn kind(&self) -> &'static str {
+        SIGN_KIND
+    }
+
+    fn save(&self) -> Tag {
+        let (x, y, z) = self.facing;
+        Tag::compound([
+            ("Facing", Tag::IntArray(vec![x, y, z])),
+            ("Lines", Tag::List(self.lines.iter().map(|line| Tag::String(line.clone())).collect())),
+        ])
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Screen for writing on a sign, a line at a time. Typing goes straight
+/// into the sign's block entity.
+pub struct SignEditor {
+    // Block of the sign being written on, and the line being typed into
+    editing: Option<(i32, i32, i32)>,
+    line: usize,
+}
+
+impl SignEditor {
+    pub fn new() -> Self {
+        Self { editing: None, line: 0 }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.editing.is_some()
+    }
+
+    /// Starts writing on the sign at `position`, from its first line.
+    pub fn open(&mut self, position: (i32, i32, i32)) {
+        self.editing = Some(position);
+        self.line = 0;
+    }
+
+    pub fn close(&mut self) {
+        self.editing = None;
+    }
+
+    // The sign being written on, closing the editor if it's gone
+    fn sign<'a>(&mut self, world: &'a mut World) -> Option<&'a mut Sign> {
+        let sign = world.block_entities.get_mut::<Sign>(self.editing?);
+        if sign.is_none() {
+            self.editing = None;
+        }
+        sign
+    }
+
+    /// Appends typed text to the current line, up to its length limit.
+    pub fn type_text(&mut self, world: &mut World, text: &str) {
+        let line = self.line;
+        if let Some(sign) = self.sign(world) {
+            let room = MAX_LINE_LENGTH.saturating_sub(sign.lines[line].chars().count());
+            sign.lines[line].extend(text.chars().filter(|c| !c.is_control()).take(room));
+        }
+    }
+
+    pub fn backspace(&mut self, world: &mut World) {
+        let line = self.line;
+        if let Some(sign) = self.sign(world) {
+            sign.lines[line].pop();
+        }
+    }
+
+    /// Moves to the previous (`-1`) or next (`1`) line. Going past the last
+    /// line closes the editor.
+    pub fn move_line(&mut self, direction: i32) {
+        let line = self.line as i32 + direction;
+        if line >= SIGN_LINES as i32 {
+            self.close();
+        } else {
+            self.line = line.max(0) as usize;
+        }
+    }
+
+    /// Queues the sign's face in the middle of the screen, with a cursor on the
+    /// line being typed into.
+    pub fn draw(&mut self, world: &mut World, hud: &mut Hud, screen_width: f32, screen_height: f32) {
+        let line = self.line;
+        let Some(sign) = self.sign(world) else {
+            return;
+        };
+        let text_width = hud.measure_text(EDITOR_LINE_HEIGHT, &"M".repeat(MAX_LINE_LENGTH + 1));
+        let (width, height) = (text_width + 2.0 * EDITOR_PADDING, SIGN_LINES as f32 * EDITOR_LINE_HEIGHT + 2.0 * EDITOR_PADDING);
+        let (left, top) = ((screen_width - width) / 2.0, (screen_height - height) / 2.0);
+        hud.rect(left, top, width, height, EDITOR_BACKGROUND);
+        for (index, text) in sign.lines.iter().enumerate() {
+            let text = if index == line { format!("{}_", text) } else { text.clone() };
+            let color = if index == line { EDITOR_CURSOR_COLOR } else { EDITOR_TEXT_COLOR };
+            let x = (screen_width - hud.measure_text(EDITOR_LINE_HEIGHT, &text)) / 2.0;
+            hud.draw_text(x, top + EDITOR_PADDING + index as f32 * EDITOR_LINE_HEIGHT, EDITOR_LINE_HEIGHT, color, &text);
+        }
+    }
+}
+
+/// Draws the text of nearby signs in the world, as quads textured from a
+/// glyph atlas of its own.
+pub struct SignRenderer {
+    program: ShaderProgram,
+    vao: VertexArray,
+    vertices: Buffer,
+    atlas: Option<GlyphAtlas>,
+}
+
+impl SignRenderer {
+    /// Without a font, signs are drawn blank.
+    pub fn new(program: ShaderProgram, font: Option<Font>) -> Option<Self> {
+        let vao = VertexArray::new()?;
+        vao.bind();
+        let vertices = Buffer::new()?;
+        vertices.bind(BufferType::Array);
+        unsafe {
+            let stride = std::mem::size_of::<TextVertex>() as gl::types::GLsizei;
+            let float = std::mem::size_of::<f32>();
+            // (location, components, offset in floats): position, atlas coordinates
+            for (location, components, offset) in [(0, 3, 0), (1, 2, 3)] {
+                gl::VertexAttribPointer(location, components, gl::FLOAT, gl::FALSE, stride, (offset * float) as *const _);
+                gl::EnableVertexAttribArray(location);
+            }
+        }
+
+        Some(Self {
+            program,
+            vao,
+            vertices,
+            atlas: font.map(GlyphAtlas::new),
+        })
+    }
+
+    /// Draws the text of every sign within view of `eye`.
+    pub fn draw(&mut self, world: &World, eye: Vec3) {
+        let Some(atlas) = &mut self.atlas else {
+            return;
+        };
+
+        let mut batch: Vec<TextVertex> = Vec::new();
+        let em = GLYPH_SIZE / atlas.font().line_height(1.0);
+        // Blocks per pixel of the rasterized glyphs
+        let scale = LINE_HEIGHT / GLYPH_SIZE;
+        let ascent = atlas.font().ascent(em) * scale;
+        for ((x, y, z), sign) in world.block_entities.iter::<Sign>() {
+            let center = Vec3::new(x as f32, y as f32, z as f32);
+            let normal = Vec3::new(sign.facing.0 as f32, sign.facing.1 as f32, sign.facing.2 as f32);
+            // Only the written face's side can see the text
+            if (center - eye).length() > VIEW_DISTANCE || (eye - center).dot(&normal) < 0.5 {
+                continue;
+            }
+            let face = center + normal * (0.5 + TEXT_OFFSET);
+            let right = Vec3::new(0.0, 1.0, 0.0).cross(&normal);
+
+            let mut line_top = face.y + LINE_HEIGHT * SIGN_LINES as f32 / 2.0;
+            for line in &sign.lines {
+                let advance = |atlas: &GlyphAtlas, c: char| atlas.font().advance(atlas.font().glyph_index(c), em) * scale;
+                let width: f32 = line.chars().map(|c| advance(atlas, c)).sum();
+                let baseline = line_top - ascent;
+                let mut pen = -width / 2.0;
+                for c in line.chars() {
+                    if let Some(glyph) = atlas.glyph(c, em) {
+                        let left = pen + glyph.left * scale;
+                        let top = baseline - glyph.top * scale;
+                        let (w, h) = (glyph.width * scale, glyph.height * scale);
+                        let [s0, t0, s1, t1] = glyph.tex_coords;
+                        let corner = |across: f32, height: f32, s: f32, t: f32| {
+                            let p = Vec3::new(face.x, height, face.z) + right * across;
+                            [p.x, p.y, p.z, s, t]
+                        };
+                        let (tl, bl) = (corner(left, top, s0, t0), corner(left, top - h, s0, t1));
+                        let (br, tr) = (corner(left + w, top - h, s1, t1), corner(left + w, top, s1, t0));
+                        batch.extend([tl, bl, br, br, tr, tl]);
+                    }
+                    pen += advance(atlas, c);
+                }
+                line_top -= LINE_HEIGHT;
+            }
+        }
+        if batch.is_empty() {
+            return;
+        }
+
+        self.program.use_program();
+        GlState::bind_texture(FONT_TEXTURE_UNIT, gl::TEXTURE_2D, atlas.texture());
+        self.vao.bind();
+        self.vertices.bind(BufferType::Array);
+        buffer_data(BufferType::Array, bytemuck::cast_slice(&batch), gl::STREAM_DRAW);
+        unsafe {
+            gl::Uniform1i(gl::GetUniformLocation(self.program.0, b"fontAtlas\0".as_ptr() as *const i8), FONT_TEXTURE_UNIT as i32);
+            let [r, g, b] = TEXT_COLOR;
+            gl::Uniform3f(gl::GetUniformLocation(self.program.0, b"textColor\0".as_ptr() as *const i8), r, g, b);
+            // Glyph edges blend over the face, so they don't write depth
+            GlState::depth_mask(false);
+            gl::DrawArrays(gl::TRIANGLES, 0, batch.len() as i32);
+            GlState::depth_mask(true);
+        }
+    }
+}
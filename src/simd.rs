@@ -0,0 +1,73 @@
+use crate::math::Vec3;
+use std::arch::x86_64::*;
+
+// SSE versions of the hottest math, built with the `simd` feature on x86_64.
+// SSE2 is part of every x86_64 CPU, so they need no check at runtime. Each
+// adds and multiplies in the same order as the scalar code in `math`, so both
+// give the same values.
+
+/// Multiplies two column-major matrices: each column of the result is the
+/// columns of `a` weighted by a column of `b`.
+pub fn mul_mat4(a: &[f32; 16], b: &[f32; 16]) -> [f32; 16] {
+    let mut result = [0.0; 16];
+    unsafe {
+        let columns = load_columns(a);
+        for (j, weights) in b.chunks_exact(4).enumerate() {
+            _mm_storeu_ps(result.as_mut_ptr().add(j * 4), weigh_columns(&columns, weights));
+        }
+    }
+    result
+}
+
+/// Multiplies a column-major matrix by a vector.
+pub fn mul_vec4(m: &[f32; 16], v: [f32; 4]) -> [f32; 4] {
+    let mut result = [0.0; 4];
+    unsafe {
+        let column = weigh_columns(&load_columns(m), &v);
+        _mm_storeu_ps(result.as_mut_ptr(), column);
+    }
+    result
+}
+
+unsafe fn load_columns(m: &[f32; 16]) -> [__m128; 4] {
+    let p = m.as_ptr();
+    [_mm_loadu_ps(p), _mm_loadu_ps(p.add(4)), _mm_loadu_ps(p.add(8)), _mm_loadu_ps(p.add(12))]
+}
+
+unsafe fn weigh_columns(columns: &[__m128; 4], weights: &[f32]) -> __m128 {
+    let weighted = |k: usize| _mm_mul_ps(columns[k], _mm_set1_ps(weights[k]));
+    _mm_add_ps(_mm_add_ps(_mm_add_ps(weighted(0), weighted(1)), weighted(2)), weighted(3))
+}
+
+/// Tests four boxes at a time against each plane of a frustum, with their
+/// coordinates laid out one per lane, and appends whether each could be
+/// inside. Returns the last few boxes that didn't make a group of four.
+pub fn intersects_aabbs<'a>(planes: &[(Vec3, f32); 6], boxes: &'a [(Vec3, Vec3)], visible: &mut Vec<bool>) -> &'a [(Vec3, Vec3)] {
+    let groups = boxes.chunks_exact(4);
+    let rest = groups.remainder();
+    for group in groups {
+        let mask = unsafe {
+            let gather = |coordinate: fn(&(Vec3, Vec3)) -> f32| {
+                _mm_setr_ps(coordinate(&group[0]), coordinate(&group[1]), coordinate(&group[2]), coordinate(&group[3]))
+            };
+            let (min_x, min_y, min_z) = (gather(|b| b.0.x), gather(|b| b.0.y), gather(|b| b.0.z));
+            let (max_x, max_y, max_z) = (gather(|b| b.1.x), gather(|b| b.1.y), gather(|b| b.1.z));
+
+            let mut inside = _mm_castsi128_ps(_mm_set1_epi32(-1));
+            for (normal, distance) in planes {
+                // The corner furthest along the normal is the last to leave the inside
+                let x = if normal.x >= 0.0 { max_x } else { min_x };
+                let y = if normal.y >= 0.0 { max_y } else { min_y };
+                let z = if normal.z >= 0.0 { max_z } else { min_z };
+                let mut dot = _mm_mul_ps(x, _mm_set1_ps(normal.x));
+                dot = _mm_add_ps(dot, _mm_mul_ps(y, _mm_set1_ps(normal.y)));
+                dot = _mm_add_ps(dot, _mm_mul_ps(z, _mm_set1_ps(normal.z)));
+                dot = _mm_add_ps(dot, _mm_set1_ps(*distance));
+                inside = _mm_and_ps(inside, _mm_cmpge_ps(dot, _mm_setzero_ps()));
+            }
+            _mm_movemask_ps(inside)
+        };
+        visible.extend((0..4).map(|i| mask & (1 << i) != 0));
+    }
+    rest
+}
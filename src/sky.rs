@@ -0,0 +1,126 @@
+use crate::gl_utils::{buffer_data, Buffer, BufferType, GlState, ShaderProgram, VertexArray};
+use crate::math::{billboard_axes, billboard_corners, Mat4, Vec3};
+use crate::random::Random;
+
+type SkyVertex = [f32; 10];  // x, y, z, s, t, r, g, b, a, glow
+
+// How far the sky objects are drawn from the camera; anything inside the far plane works
+const SKY_DISTANCE: f32 = 400.0;
+const SUN_SIZE: f32 = 50.0;
+const SUN_GLOW_SIZE: f32 = 160.0;
+const MOON_SIZE: f32 = 35.0;
+const STAR_COUNT: usize = 800;
+const STAR_SIZE: f32 = 1.2;
+// Daylight below which stars start to show, and the span over which they fade in
+const STARS_APPEAR: f32 = 0.5;
+const STARS_FADE: f32 = 0.3;
+
+const SUN_COLOR: [f32; 4] = [1.0, 0.95, 0.7, 1.0];
+const SUN_GLOW_COLOR: [f32; 4] = [1.0, 0.8, 0.4, 0.4];
+const MOON_COLOR: [f32; 4] = [0.8, 0.85, 0.95, 1.0];
+
+/// The sun, the moon and the stars, turning around the world with the time of day.
+pub struct Sky {
+    program: ShaderProgram,
+    vao: VertexArray,
+    vertices: Buffer,
+    // Direction and brightness of every star, as seen at noon
+    stars: Vec<(Vec3, f32)>,
+    batch: Vec<SkyVertex>,
+}
+
+impl Sky {
+    pub fn new(program: ShaderProgram, seed: u32) -> Option<Self> {
+        let vao = VertexArray::new()?;
+        vao.bind();
+        let vertices = Buffer::new()?;
+        vertices.bind(BufferType::Array);
+        unsafe {
+            let stride = std::mem::size_of::<SkyVertex>() as gl::types::GLsizei;
+            let float = std::mem::size_of::<f32>();
+            // (location, components, offset in floats): position, texture coordinates, color, glow
+            for (location, components, offset) in [(0, 3, 0), (1, 2, 3), (2, 4, 5), (3, 1, 9)] {
+                gl::VertexAttribPointer(location, components, gl::FLOAT, gl::FALSE, stride, (offset * float) as *const _);
+                gl::EnableVertexAttribArray(location);
+            }
+        }
+
+        // Stars spread evenly over the sphere, each world with its own sky
+        let mut random = Random::new(seed);
+        let stars = (0..STAR_COUNT)
+            .map(|_| {
+                let y = random.signed();
+                let angle = random.range(0.0, std::f32::consts::TAU);
+                let ring = (1.0 - y * y).sqrt();
+                (Vec3::new(ring * angle.cos(), y, ring * angle.sin()), random.range(0.4, 1.0))
+            })
+            .collect();
+
+        Some(Self {
+            program,
+            vao,
+            vertices,
+            stars,
+            batch: Vec::new(),
+        })
+    }
+
+    fn push_billboard(&mut self, center: Vec3, direction: Vec3, size: f32, color: [f32; 4], glow: bool) {
+        let (right, up) = billboard_axes(direction);
+        let corners = billboard_corners(center, right, up, size);
+        let [r, g, b, a] = color;
+        let glow = if glow { 1.0 } else { 0.0 };
+        for (corner, (s, t)) in [(0, (0.0, 0.0)), (1, (1.0, 0.0)), (2, (1.0, 1.0)), (2, (1.0, 1.0)), (3, (0.0, 1.0)), (0, (0.0, 0.0))] {
+            let p = corners[corner];
+            self.batch.push([p.x, p.y, p.z, s, t, r, g, b, a, glow]);
+        }
+    }
+
+    /// Draws the sky objects around the camera. `sun_angle` is 0 at noon and
+    /// grows through the day; stars fade in as the daylight drops. Call right
+    /// after clearing, before the terrain covers what is below the horizon.
+    pub fn draw(&mut self, camera_position: Vec3, sun_angle: f32, daylight: f32) {
+        let rotation = sky_rotation(sun_angle);
+        let sun = sun_direction(sun_angle);
+        let moon = sun * -1.0;
+
+        let star_alpha = ((STARS_APPEAR - daylight) / STARS_FADE).clamp(0.0, 1.0);
+        if star_alpha > 0.0 {
+            for index in 0..self.stars.len() {
+                let (direction, brightness) = self.stars[index];
+                let direction = rotation.transform_vector(direction);
+                let color = [1.0, 1.0, 1.0, brightness * star_alpha];
+                self.push_billboard(camera_position + direction * SKY_DISTANCE, direction, STAR_SIZE, color, false);
+            }
+        }
+        self.push_billboard(camera_position + sun * SKY_DISTANCE, sun, SUN_GLOW_SIZE, SUN_GLOW_COLOR, true);
+        self.push_billboard(camera_position + sun * SKY_DISTANCE, sun, SUN_SIZE, SUN_COLOR, false);
+        self.push_billboard(camera_position + moon * SKY_DISTANCE, moon, MOON_SIZE, MOON_COLOR, false);
+
+        self.program.use_program();
+        self.vao.bind();
+        self.vertices.bind(BufferType::Array);
+        buffer_data(BufferType::Array, bytemuck::cast_slice(&self.batch), gl::STREAM_DRAW);
+        unsafe {
+            // Behind everything, and adding light to the sky color rather than covering it
+            GlState::set_capability(gl::DEPTH_TEST, false);
+            GlState::set_capability(gl::CULL_FACE, false);
+            GlState::blend_func(gl::SRC_ALPHA, gl::ONE);
+            gl::DrawArrays(gl::TRIANGLES, 0, self.batch.len() as i32);
+            GlState::blend_func(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            GlState::set_capability(gl::CULL_FACE, true);
+            GlState::set_capability(gl::DEPTH_TEST, true);
+        }
+        self.batch.clear();
+    }
+}
+
+// The sky turns around the Z axis, so the sun rises in the east (-X) and sets in the west
+fn sky_rotation(sun_angle: f32) -> Mat4 {
+    Mat4::rotate(-sun_angle, Vec3::new(0.0, 0.0, 1.0))
+}
+
+/// Direction from the camera towards the sun at a `sun_angle`.
+pub fn sun_direction(sun_angle: f32) -> Vec3 {
+    sky_rotation(sun_angle).transform_vector(Vec3::new(0.0, 1.0, 0.0))
+}
@@ -0,0 +1,502 @@
+use crate::block::{BlockSound, BlockType};
+use crate::events::{BlockChanged, EventBus, Exploded, PlayerMoved, Subscriber};
+use crate::math::Vec3;
+use crate::random::Random;
+use crate::wav::WavStream;
+use crate::world::World;
+#[cfg(feature = "sdl2")]
+use sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired};
+#[cfg(feature = "sdl2")]
+use sdl2::Sdl;
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, SyncSender, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+
+const SAMPLE_RATE: i32 = 44100;
+// Samples per channel the audio thread mixes at a time; smaller is more responsive
+#[cfg(feature = "sdl2")]
+const BUFFER_SAMPLES: u16 = 1024;
+// Sounds further away than this are not heard at all
+const HEARING_DISTANCE: f32 = 32.0;
+// Explosions carry further than anything else
+const EXPLOSION_HEARING_DISTANCE: f32 = 96.0;
+// Most sounds playing at once; the oldest is cut off to make room
+const MAX_VOICES: usize = 32;
+// Takes of each sound, picked at random so repeats don't sound mechanical
+const VARIANTS: u32 = 3;
+// Distance walked between footsteps
+const STEP_LENGTH: f32 = 1.8;
+// How far above the ground the feet may be and still count as walking on it
+const GROUND_TOLERANCE: f32 = 0.2;
+// Moves longer than this in one frame are teleports, not steps
+const TELEPORT_DISTANCE: f32 = 8.0;
+// Seconds for a track to fade in, and for the one it replaces to fade out
+const CROSSFADE_TIME: f32 = 4.0;
+const MUSIC_VOLUME: f32 = 0.4;
+// Frames of music decoded at a time, and how many such blocks are kept ready
+const MUSIC_BLOCK_FRAMES: usize = 8192;
+const MUSIC_BLOCKS_AHEAD: usize = 4;
+
+/// What happened to or on a block; together with its material it picks the sound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SoundEvent {
+    Break,
+    Place,
+    Step,
+}
+
+// Mono samples at SAMPLE_RATE, shared with the audio thread while playing
+type Clip = Arc<[f32]>;
+
+struct Voice {
+    clip: Clip,
+    position: usize,
+    // Left and right volume
+    gains: [f32; 2],
+}
+
+// A track streamed in blocks of interleaved stereo from its decoder thread
+struct MusicVoice {
+    blocks: Receiver<Vec<f32>>,
+    block: Vec<f32>,
+    position: usize,
+    volume: f32,
+    // Volume change per frame: positive while fading in, negative while fading out
+    fade: f32,
+    finished: bool,
+}
+
+impl MusicVoice {
+    fn fading_out(&self) -> bool {
+        self.fade < 0.0
+    }
+}
+
+// Volume change per frame of a crossfade
+fn fade_step() -> f32 {
+    1.0 / (CROSSFADE_TIME * SAMPLE_RATE as f32)
+}
+
+// Runs on SDL's audio thread and adds up every playing sound
+struct Mixer {
+    voices: Vec<Voice>,
+    music: Vec<MusicVoice>,
+}
+
+#[cfg(feature = "sdl2")]
+impl AudioCallback for Mixer {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        self.mix(out);
+    }
+}
+
+// The device sounds play on. Only SDL2 opens one so far, so without it there
+// are none and the game is silent
+#[cfg(feature = "sdl2")]
+type Device = AudioDevice<Mixer>;
+#[cfg(not(feature = "sdl2"))]
+enum Device {}
+
+#[cfg(not(feature = "sdl2"))]
+impl Device {
+    // Locks like SDL's device
+    fn lock(&mut self) -> std::sync::MutexGuard<'_, Mixer> {
+        match *self {}
+    }
+}
+
+impl Mixer {
+    // Fills `out` with interleaved stereo
+    #[cfg_attr(not(feature = "sdl2"), allow(dead_code))]
+    fn mix(&mut self, out: &mut [f32]) {
+        out.fill(0.0);
+        for voice in &mut self.voices {
+            // Interleaved stereo
+            for frame in out.chunks_exact_mut(2) {
+                let Some(&sample) = voice.clip.get(voice.position) else {
+                    break;
+                };
+                frame[0] += sample * voice.gains[0];
+                frame[1] += sample * voice.gains[1];
+                voice.position += 1;
+            }
+        }
+        self.voices.retain(|voice| voice.position < voice.clip.len());
+
+        for music in &mut self.music {
+            for frame in out.chunks_exact_mut(2) {
+                if music.position >= music.block.len() {
+                    match music.blocks.try_recv() {
+                        Ok(block) => {
+                            music.block = block;
+                            music.position = 0;
+                        },
+                        // The decoder fell behind; better a gap than a stall
+                        Err(TryRecvError::Empty) => break,
+                        Err(TryRecvError::Disconnected) => {
+                            music.finished = true;
+                            break;
+                        },
+                    }
+                }
+                music.volume = (music.volume + music.fade).clamp(0.0, 1.0);
+                let gain = music.volume * MUSIC_VOLUME;
+                frame[0] += music.block[music.position] * gain;
+                frame[1] += music.block[music.position + 1] * gain;
+                music.position += 2;
+            }
+        }
+        // Drop tracks that ended or have faded out
+        self.music.retain(|music| !music.finished && (music.volume > 0.0 || !music.fading_out()));
+
+        for sample in out {
+            *sample = sample.clamp(-1.0, 1.0);
+        }
+    }
+}
+
+/// Plays block sounds and explosions around the listener, quieter with distance and panned
+/// to the side they come from, over music streamed from disk.
+///
+/// There are no sound files yet, so every sound is synthesized from filtered
+/// noise and a tone chosen by the block's material, the first time it plays.
+pub struct SoundManager {
+    // None when there is no audio device, in which case nothing plays
+    device: Option<Device>,
+    clips: HashMap<(BlockSound, SoundEvent), Vec<Clip>>,
+    // Takes of the explosion sound, made the first time one goes off
+    explosion_clips: Vec<Clip>,
+    listener: Vec3,
+    listener_right: Vec3,
+    block_changes: Subscriber<BlockChanged>,
+    // The player's moves, for footsteps and splashes
+    moves: Subscriber<PlayerMoved>,
+    explosions: Subscriber<Exploded>,
+    walked: f32,
+    in_water: bool,
+    // Picks the takes
+    random: Random,
+}
+
+impl SoundManager {
+    /// Opens the default audio device. Without one the game runs silently.
+    #[cfg(feature = "sdl2")]
+    pub fn new(sdl: &Sdl) -> Self {
+        let desired = AudioSpecDesired {
+            freq: Some(SAMPLE_RATE),
+            channels: Some(2),
+            samples: Some(BUFFER_SAMPLES),
+        };
+        let device = sdl.audio()
+            .and_then(|audio| audio.open_playback(None, &desired, |_| Mixer { voices: Vec::new(), music: Vec::new() }));
+        match device {
+            Ok(device) => {
+                device.resume();
+                Self::with_device(Some(device))
+            },
+            Err(e) => {
+                log::warn!(target: "audio", "No sound: {}", e);
+                Self::with_device(None)
+            },
+        }
+    }
+
+    /// A sound manager that never plays anything.
+    pub fn disabled() -> Self {
+        Self::with_device(None)
+    }
+
+    fn with_device(device: Option<Device>) -> Self {
+        Self {
+            device,
+            clips: HashMap::new(),
+            explosion_clips: Vec::new(),
+            listener: Vec3::zero(),
+            listener_right: Vec3::new(1.0, 0.0, 0.0),
+            block_changes: Subscriber::new(),
+            moves: Subscriber::new(),
+            explosions: Subscriber::new(),
+            walked: 0.0,
+            in_water: false,
+            random: Random::new(0x9e37_79b9),
+        }
+    }
+
+    /// Moves the ears sounds are heard from. `right` points out of the right ear.
+    pub fn set_listener(&mut self, position: Vec3, right: Vec3) {
+        self.listener = position;
+        self.listener_right = right.normalize();
+    }
+
+    /// Plays the sound of `event` happening to `block`, centered on `position`.
+    pub fn play_block(&mut self, block: BlockType, event: SoundEvent, position: Vec3) {
+        if block == BlockType::Air {
+            return;
+        }
+        self.play(block.info().sound, event, position);
+    }
+
+    // Plays the boom of an explosion centered on `position`
+    fn play_explosion(&mut self, position: Vec3) {
+        if self.device.is_none() {
+            return;
+        }
+        if self.explosion_clips.is_empty() {
+            self.explosion_clips = (0..VARIANTS).map(synthesize_explosion).collect();
+        }
+        let variant = self.variant();
+        let clip = self.explosion_clips[variant].clone();
+        self.play_clip(clip, position, EXPLOSION_HEARING_DISTANCE);
+    }
+
+    fn play(&mut self, sound: BlockSound, event: SoundEvent, position: Vec3) {
+        if self.device.is_none() {
+            return;
+        }
+        let variant = self.variant();
+        let takes = self.clips.entry((sound, event))
+            .or_insert_with(|| (0..VARIANTS).map(|seed| synthesize(sound, event, seed)).collect());
+        let clip = takes[variant].clone();
+        self.play_clip(clip, position, HEARING_DISTANCE);
+    }
+
+    // Picks one of the takes of a sound at random
+    fn variant(&mut self) -> usize {
+        self.random.below(VARIANTS) as usize
+    }
+
+    // Mixes in a clip, fading out to nothing `hearing_distance` blocks away
+    fn play_clip(&mut self, clip: Clip, position: Vec3, hearing_distance: f32) {
+        let offset = position - self.listener;
+        let distance = offset.length();
+        if distance >= hearing_distance {
+            return;
+        }
+
+        let volume = (1.0 - distance / hearing_distance).powi(2);
+        // -1 fully on the left, 1 fully on the right; equal power in between
+        let pan = if distance > 0.01 { (offset * (1.0 / distance)).dot(&self.listener_right) } else { 0.0 };
+        let gains = [volume * ((1.0 - pan) / 2.0).sqrt(), volume * ((1.0 + pan) / 2.0).sqrt()];
+
+        let Some(device) = &mut self.device else {
+            return;
+        };
+        let mut mixer = device.lock();
+        if mixer.voices.len() >= MAX_VOICES {
+            mixer.voices.remove(0);
+        }
+        mixer.voices.push(Voice { clip, position: 0, gains });
+    }
+
+    /// Streams a WAV file as music, fading out the music that was playing.
+    pub fn play_music(&mut self, path: &Path) -> io::Result<()> {
+        let Some(device) = &mut self.device else {
+            return Ok(());
+        };
+        let wav = WavStream::open(path)?;
+        let (sender, blocks) = mpsc::sync_channel(MUSIC_BLOCKS_AHEAD);
+        thread::spawn(move || stream_music(wav, sender));
+
+        let mut mixer = device.lock();
+        for music in &mut mixer.music {
+            music.fade = -fade_step();
+        }
+        mixer.music.push(MusicVoice {
+            blocks,
+            block: Vec::new(),
+            position: 0,
+            volume: 0.0,
+            fade: fade_step(),
+            finished: false,
+        });
+        Ok(())
+    }
+
+    /// Fades out the music.
+    pub fn stop_music(&mut self) {
+        if let Some(device) = &mut self.device {
+            for music in &mut device.lock().music {
+                music.fade = -fade_step();
+            }
+        }
+    }
+
+    /// Whether a track is playing, not counting one that is fading out.
+    pub fn music_playing(&mut self) -> bool {
+        self.device.as_mut()
+            .is_some_and(|device| device.lock().music.iter().any(|music| !music.fading_out()))
+    }
+
+    /// Plays what the frame's events sound like: blocks placed and broken by
+    /// players and arrows, explosions, and the player's footsteps.
+    pub fn handle_events(&mut self, events: &EventBus, world: &World) {
+        for change in events.read(&mut self.block_changes) {
+            if !change.cause.is_audible() {
+                continue;
+            }
+            let (x, y, z) = change.position;
+            let position = Vec3::new(x as f32, y as f32, z as f32);
+            match (change.block, change.previous) {
+                (BlockType::Air, Some(previous)) => self.play_block(previous, SoundEvent::Break, position),
+                (block, _) => self.play_block(block, SoundEvent::Place, position),
+            }
+        }
+        for explosion in events.read(&mut self.explosions) {
+            self.play_explosion(explosion.center);
+        }
+        for &moved in events.read(&mut self.moves) {
+            self.player_moved(world, moved.from, moved.to);
+        }
+    }
+
+    // Follows the player's feet: footsteps on whatever they walk on, and a
+    // splash on stepping into water
+    fn player_moved(&mut self, world: &World, last_feet: Vec3, feet: Vec3) {
+        let cell = |v: f32| v.round() as i32;
+        let in_water = world.get_block(cell(feet.x), cell(feet.y), cell(feet.z)) == BlockType::Water;
+        if in_water && !self.in_water {
+            self.play(BlockSound::Water, SoundEvent::Break, feet);
+        }
+        self.in_water = in_water;
+
+        let moved = Vec3::new(feet.x - last_feet.x, 0.0, feet.z - last_feet.z).length();
+        if moved > TELEPORT_DISTANCE {
+            self.walked = 0.0;
+            return;
+        }
+
+        // The cell just below the feet holds whatever they stand on, full block or slab
+        let ground_y = cell(feet.y - GROUND_TOLERANCE);
+        let ground = world.get_block(cell(feet.x), ground_y, cell(feet.z));
+        let on_ground = ground.collision_height().is_some_and(|height| {
+            let top = ground_y as f32 - 0.5 + height;
+            (feet.y - top).abs() <= GROUND_TOLERANCE
+        });
+        if !on_ground || in_water {
+            self.walked = 0.0;
+            return;
+        }
+        self.walked += moved;
+        if self.walked >= STEP_LENGTH {
+            self.walked -= STEP_LENGTH;
+            self.play_block(ground, SoundEvent::Step, feet);
+        }
+    }
+}
+
+// Decodes a track on its own thread, resampled to the device rate, staying a
+// few blocks ahead of the mixer. Stops at the end of the file, or once the
+// mixer drops the track.
+fn stream_music(mut wav: WavStream, blocks: SyncSender<Vec<f32>>) {
+    let step = f64::from(wav.sample_rate()) / f64::from(SAMPLE_RATE);
+    // Decoded frames not yet resampled, and the read position in them
+    let mut source: Vec<f32> = Vec::new();
+    let mut position = 0.0;
+    let mut ended = false;
+    loop {
+        let mut block = Vec::with_capacity(MUSIC_BLOCK_FRAMES * 2);
+        while block.len() < MUSIC_BLOCK_FRAMES * 2 {
+            // Linear interpolation needs the frame after the current one too
+            let index = position as usize;
+            if index + 1 >= source.len() / 2 {
+                if ended {
+                    break;
+                }
+                source.drain(..index * 2);
+                position -= index as f64;
+                match wav.read_frames(&mut source, MUSIC_BLOCK_FRAMES) {
+                    Ok(0) => ended = true,
+                    Ok(_) => {},
+                    Err(e) => {
+                        log::warn!(target: "audio", "Failed to read music: {}", e);
+                        ended = true;
+                    },
+                }
+                continue;
+            }
+            let t = (position - index as f64) as f32;
+            for channel in 0..2 {
+                let (from, to) = (source[index * 2 + channel], source[index * 2 + 2 + channel]);
+                block.push(from + (to - from) * t);
+            }
+            position += step;
+        }
+        if block.is_empty() || blocks.send(block).is_err() {
+            return;
+        }
+    }
+}
+
+// How a material sounds: the length of a break, how much of the noise's high
+// end is kept (0 to 1), and the pitch and share of a tone mixed into it
+struct Timbre {
+    duration: f32,
+    brightness: f32,
+    tone: f32,
+    tone_mix: f32,
+}
+
+fn timbre(sound: BlockSound) -> Timbre {
+    let (duration, brightness, tone, tone_mix) = match sound {
+        BlockSound::Grass => (0.25, 0.25, 0.0, 0.0),
+        BlockSound::Gravel => (0.22, 0.45, 0.0, 0.0),
+        BlockSound::Stone => (0.18, 0.8, 180.0, 0.25),
+        BlockSound::Wood => (0.2, 0.35, 140.0, 0.45),
+        BlockSound::Snow => (0.3, 0.12, 0.0, 0.0),
+        BlockSound::Glass => (0.35, 0.9, 1800.0, 0.35),
+        BlockSound::Water => (0.6, 0.3, 0.0, 0.0),
+    };
+    Timbre { duration, brightness, tone, tone_mix }
+}
+
+// Builds one take of a sound: low-passed noise with a tone mixed in, under a
+// short attack and a fast decay, peaking at the event's volume
+fn synthesize(sound: BlockSound, event: SoundEvent, seed: u32) -> Clip {
+    let timbre = timbre(sound);
+    let (length, gain) = match event {
+        SoundEvent::Break => (1.0, 0.8),
+        SoundEvent::Place => (0.6, 0.7),
+        SoundEvent::Step => (0.4, 0.35),
+    };
+    render(&Timbre { duration: timbre.duration * length, ..timbre }, gain, seed)
+}
+
+// An explosion: a long, dull roar of noise over a deep thump
+fn synthesize_explosion(seed: u32) -> Clip {
+    render(&Timbre { duration: 1.6, brightness: 0.04, tone: 45.0, tone_mix: 0.3 }, 1.0, seed)
+}
+
+fn render(timbre: &Timbre, gain: f32, seed: u32) -> Clip {
+    const ATTACK: f32 = 0.005;
+
+    let duration = timbre.duration;
+    let samples = (duration * SAMPLE_RATE as f32) as usize;
+    // Each take is a little higher or lower
+    let pitch = timbre.tone * (1.0 + 0.08 * (seed as f32 - 1.0));
+
+    let mut random = Random::new(0x2545_f491 ^ (seed + 1).wrapping_mul(0x9e37_79b9));
+    let mut filtered = 0.0;
+    let mut clip: Vec<f32> = (0..samples)
+        .map(|index| {
+            let noise = random.signed();
+            filtered += (noise - filtered) * timbre.brightness;
+
+            let time = index as f32 / SAMPLE_RATE as f32;
+            let tone = (time * pitch * std::f32::consts::TAU).sin();
+            let envelope = (time / ATTACK).min(1.0) * (1.0 - time / duration).powi(3);
+            (filtered * (1.0 - timbre.tone_mix) + tone * timbre.tone_mix) * envelope
+        })
+        .collect();
+
+    // The low-pass filter takes more out of duller sounds, so bring every take to the same peak
+    let peak = clip.iter().fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+    if peak > 0.0 {
+        clip.iter_mut().for_each(|sample| *sample *= gain / peak);
+    }
+    clip.into()
+}
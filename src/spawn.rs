@@ -0,0 +1,83 @@
+use crate::atomic_file;
+use crate::math::Vec3;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// How many columns around the origin are searched for safe ground to spawn on.
+pub const SPAWN_SEARCH_RADIUS: i32 = 32;
+
+// Identifies a spawn file
+const MAGIC: &[u8; 4] = b"SPWN";
+const VERSION: u32 = 1;
+
+/// Where the player starts and comes back after dying, as feet positions: the
+/// world's spawn, found once when the world is made, and a respawn point the
+/// player can set to override it.
+#[derive(Debug, Clone, Copy)]
+pub struct SpawnPoint {
+    pub world_spawn: Vec3,
+    pub respawn: Option<Vec3>,
+}
+
+impl SpawnPoint {
+    pub fn new(world_spawn: Vec3) -> Self {
+        Self { world_spawn, respawn: None }
+    }
+
+    /// Where the player spawns: the respawn point if one is set, or else the
+    /// world's spawn.
+    pub fn position(&self) -> Vec3 {
+        self.respawn.unwrap_or(self.world_spawn)
+    }
+
+    /// Writes the spawn to a file, creating its directory if needed.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        atomic_file::write(path, &self.encode())
+    }
+
+    /// The spawn in the format `save` writes.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(MAGIC);
+        data.extend_from_slice(&VERSION.to_le_bytes());
+        push_position(&mut data, self.world_spawn);
+        data.push(self.respawn.is_some() as u8);
+        push_position(&mut data, self.respawn.unwrap_or(Vec3::zero()));
+        data
+    }
+
+    /// Reads a spawn written by `save`.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let data = fs::read(path)?;
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "Invalid spawn file");
+
+        let header_size = MAGIC.len() + 4;
+        if data.len() != header_size + 12 + 1 + 12
+            || &data[..4] != MAGIC
+            || data[4..8] != VERSION.to_le_bytes()
+        {
+            return Err(invalid());
+        }
+
+        let world_spawn = read_position(&data[header_size..]).ok_or_else(invalid)?;
+        let respawn = read_position(&data[header_size + 13..]).ok_or_else(invalid)?;
+        Ok(Self {
+            world_spawn,
+            respawn: (data[header_size + 12] != 0).then_some(respawn),
+        })
+    }
+}
+
+fn push_position(data: &mut Vec<u8>, position: Vec3) {
+    for value in [position.x, position.y, position.z] {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+// Reads a position from the start of `data`, if its coordinates are finite
+fn read_position(data: &[u8]) -> Option<Vec3> {
+    let value = |i: usize| f32::from_le_bytes(data[i * 4..i * 4 + 4].try_into().unwrap());
+    let position = Vec3::new(value(0), value(1), value(2));
+    [position.x, position.y, position.z].iter().all(|value| value.is_finite()).then_some(position)
+}
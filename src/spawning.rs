@@ -0,0 +1,119 @@
+use crate::block::BlockType;
+use crate::entity::Entities;
+use crate::light::light_level;
+use crate::math::Vec3;
+use crate::mob::{MobKind, Mobs};
+use crate::random::Random;
+use crate::world::World;
+
+// World ticks between spawn cycles, once a second
+const CYCLE_TICKS: u32 = 20;
+// Places tried for each kind of mob every cycle
+const ATTEMPTS_PER_CYCLE: usize = 4;
+// Mobs spawn between these distances from the player, out of the way, and
+// despawn once they are further than the last
+const MIN_SPAWN_DISTANCE: f32 = 16.0;
+const MAX_SPAWN_DISTANCE: f32 = 40.0;
+const DESPAWN_DISTANCE: f32 = 64.0;
+// Most mobs of each kind around the player at once
+const PASSIVE_CAP: usize = 8;
+const HOSTILE_CAP: usize = 6;
+// Light level passive mobs need at least, and hostile mobs at most
+const PASSIVE_MIN_LIGHT: u8 = 9;
+const HOSTILE_MAX_LIGHT: u8 = 4;
+// Blocks a hostile mob's spot is looked for below a random height
+const HOSTILE_GROUND_SEARCH: i32 = 16;
+
+/// Spawns mobs around the player on world ticks, where the light suits them:
+/// passive mobs on lit grass, hostile ones in the dark. Mobs that wander too
+/// far from the player are despawned to make room for new ones.
+pub struct SpawnCycle {
+    // World ticks until the next cycle
+    countdown: u32,
+    // Picks the places to try
+    random: Random,
+}
+
+impl SpawnCycle {
+    pub fn new(seed: u32) -> Self {
+        Self { countdown: 0, random: Random::new(seed.wrapping_mul(0x9e37_79b9)) }
+    }
+
+    /// Advances by `ticks` world ticks, running the cycles due around `player`.
+    pub fn update(&mut self, ticks: u32, mobs: &mut Mobs, entities: &mut Entities, world: &World, player: Vec3, daylight: f32) {
+        for _ in 0..ticks {
+            if self.countdown > 0 {
+                self.countdown -= 1;
+                continue;
+            }
+            self.countdown = CYCLE_TICKS - 1;
+            self.run(mobs, entities, world, player, daylight);
+        }
+    }
+
+    fn run(&mut self, mobs: &mut Mobs, entities: &mut Entities, world: &World, player: Vec3, daylight: f32) {
+        let far = entities.mobs.iter()
+            .filter(|&(entity, _)| entities.transforms.get(entity).is_some_and(|transform| {
+                (transform.translation - player).length() > DESPAWN_DISTANCE
+            }))
+            .map(|(entity, _)| entity)
+            .collect::<Vec<_>>();
+        for entity in far {
+            entities.despawn(entity);
+        }
+
+        for (kind, cap) in [(MobKind::Passive, PASSIVE_CAP), (MobKind::Hostile, HOSTILE_CAP)] {
+            let mut count = entities.mobs.iter().filter(|(_, mob)| mob.kind == kind).count();
+            for _ in 0..ATTEMPTS_PER_CYCLE {
+                if count >= cap {
+                    break;
+                }
+                // A random column in the ring around the player
+                let angle = self.random.unit() * std::f32::consts::TAU;
+                let distance = self.random.range(MIN_SPAWN_DISTANCE, MAX_SPAWN_DISTANCE);
+                let x = (player.x + angle.cos() * distance).round() as i32;
+                let z = (player.z + angle.sin() * distance).round() as i32;
+                let spot = match kind {
+                    MobKind::Passive => passive_spot(world, x, z, daylight),
+                    MobKind::Hostile => {
+                        let height = self.random.unit();
+                        hostile_spot(world, x, z, height, daylight)
+                    },
+                };
+                if let Some(position) = spot {
+                    mobs.spawn(entities, kind, position);
+                    count += 1;
+                }
+            }
+        }
+    }
+}
+
+// Feet position on top of the grass of a column, if that is its surface and
+// it is lit well enough
+fn passive_spot(world: &World, x: i32, z: i32, daylight: f32) -> Option<Vec3> {
+    let top = world.highest_block(x, z)?;
+    // Plants on the grass are stood in
+    let y = (0..=top).rev().find(|&y| world.get_block(x, y, z).collision_height().is_some())?;
+    if world.get_block(x, y, z) != BlockType::Grass || light_level(world, (x, y + 1, z), daylight) < PASSIVE_MIN_LIGHT {
+        return None;
+    }
+    Some(Vec3::new(x as f32, y as f32 + 0.5, z as f32))
+}
+
+// Feet position on the first full block below a random fraction of a column's
+// height, with two free blocks above it that are dark enough
+fn hostile_spot(world: &World, x: i32, z: i32, height: f32, daylight: f32) -> Option<Vec3> {
+    let top = world.highest_block(x, z)?;
+    let start = (height * (top + 1) as f32) as i32;
+    let free = |y: i32| {
+        let block = world.get_block(x, y, z);
+        block.collision_height().is_none() && !block.is_fluid()
+    };
+    let y = (start - HOSTILE_GROUND_SEARCH..=start).rev()
+        .find(|&y| y >= 0 && world.get_block(x, y, z).collision_height() == Some(1.0) && free(y + 1) && free(y + 2))?;
+    if light_level(world, (x, y + 1, z), daylight) > HOSTILE_MAX_LIGHT {
+        return None;
+    }
+    Some(Vec3::new(x as f32, y as f32 + 0.5, z as f32))
+}
@@ -0,0 +1,321 @@
+use crate::clock::Instant;
+use crate::events::{ChunkProgress, ChunkStage, EventBus};
+use crate::math::{Frustum, Vec3};
+use crate::mesh_cache::MeshCache;
+use crate::world::{World, CHUNK_SIZE, WORLD_HEIGHT_CHUNKS};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+use std::time::Duration;
+
+pub const DEFAULT_RENDER_DISTANCE: i32 = 8;
+pub const MIN_RENDER_DISTANCE: i32 = 2;
+pub const MAX_RENDER_DISTANCE: i32 = 32;
+// Columns out of view wait as long as visible ones this many times further
+// away, by squared distance: twice as far
+const OUT_OF_VIEW_WEIGHT: i32 = 4;
+// Columns this close, by squared distance, load first wherever the camera looks
+const SURROUNDING_DISTANCE_SQ: i32 = 2;
+// The queue is sorted again once the camera turns further than this from where
+// it looked when it was last sorted (the cosine of 20 degrees)
+const REPRIORITIZE_COS: f32 = 0.94;
+// Radius, in chunks, of the columns loaded around a destination before the
+// player is moved there. It's never more than the render distance
+const PRELOAD_RADIUS: i32 = 3;
+// The loaded area only grows back under a memory budget if the memory used
+// would stay under this fraction of it, so it doesn't shrink again right away
+const BUDGET_GROWTH_MARGIN: f64 = 0.9;
+
+/// Where the chunks are streamed around, and what can be seen from there.
+pub struct Viewer {
+    pub position: Vec3,
+    /// The way the camera looks.
+    pub direction: Vec3,
+    pub frustum: Frustum,
+}
+
+/// Changes to the loaded chunk set after a streaming step.
+#[derive(Default)]
+pub struct StreamUpdate {
+    /// Chunks with a new mesh, including freshly generated ones.
+    pub remeshed: Vec<(i32, i32, i32)>,
+    /// Chunks that were dropped from the world.
+    pub unloaded: Vec<(i32, i32, i32)>,
+    /// Chunks that were generated, all of them among the remeshed ones.
+    pub loaded: Vec<(i32, i32, i32)>,
+    /// Saved chunks whose files need reading before their columns generate.
+    pub to_read: Vec<(i32, i32, i32)>,
+    /// How many of the remeshed chunks were loaded from the mesh cache.
+    pub cached: usize,
+    /// Time spent generating the new chunks.
+    pub generation_time: Duration,
+    /// Time spent meshing chunks or loading their mesh from the cache.
+    pub meshing_time: Duration,
+}
+
+impl StreamUpdate {
+    /// Adds the changes of a later step to these.
+    pub fn add(&mut self, later: StreamUpdate) {
+        self.remeshed.extend(later.remeshed);
+        self.unloaded.extend(later.unloaded);
+        self.loaded.extend(later.loaded);
+        self.to_read.extend(later.to_read);
+        self.cached += later.cached;
+        self.generation_time += later.generation_time;
+        self.meshing_time += later.meshing_time;
+    }
+}
+
+/// Keeps the chunk columns within the render distance of the camera loaded.
+/// Missing columns wait in a queue, nearest first and those in view before
+/// those out of it, so the terrain in front of the player fills in first.
+/// Columns with saved chunks wait on the side for their files to be read.
+pub struct ChunkStreamer {
+    render_distance: i32,
+    // Farthest the loaded area may reach under the memory budget, which
+    // takes over from the render distance when it's shorter
+    budget_distance: i32,
+    // Missing columns by priority, lowest first, as (priority, x, z). Columns
+    // loaded since the queue was sorted are skipped when they come up
+    queue: BinaryHeap<Reverse<(i32, i32, i32)>>,
+    // The column and view direction the queue was sorted for
+    sorted_for: Option<((i32, i32), Vec3)>,
+    // Column whose surroundings load ahead of everything else, and stay
+    // loaded wherever the camera is
+    preload: Option<(i32, i32)>,
+    // Columns taken from the queue whose saved chunks are being read
+    reading: Vec<(i32, i32)>,
+}
+
+impl ChunkStreamer {
+    pub fn new(render_distance: i32) -> Self {
+        Self {
+            render_distance: render_distance.clamp(MIN_RENDER_DISTANCE, MAX_RENDER_DISTANCE),
+            budget_distance: MAX_RENDER_DISTANCE,
+            queue: BinaryHeap::new(),
+            sorted_for: None,
+            preload: None,
+            reading: Vec::new(),
+        }
+    }
+
+    /// How many columns are waiting to be generated, roughly: some of them may
+    /// have been loaded some other way since.
+    pub fn queued(&self) -> usize {
+        self.queue.len() + self.reading.len()
+    }
+
+    /// Where the queued columns are, as (x, z) in chunks and in no particular order.
+    pub fn queued_columns(&self) -> impl Iterator<Item = (i32, i32)> + '_ {
+        self.queue.iter().map(|&Reverse((_, x, z))| (x, z)).chain(self.reading.iter().copied())
+    }
+
+    /// Radius, in chunks, of the loaded area around the camera.
+    pub fn render_distance(&self) -> i32 {
+        self.render_distance
+    }
+
+    /// Radius, in chunks, of the area actually loaded, which the memory
+    /// budget may keep shorter than the render distance.
+    pub fn loaded_distance(&self) -> i32 {
+        self.render_distance.min(self.budget_distance)
+    }
+
+    /// Shrinks the loaded area while the memory used is over budget, for the
+    /// farthest columns to be unloaded, and grows it back a chunk at a time
+    /// once the queue is empty and the larger area would fit. Memory is taken
+    /// to grow with the area loaded. A budget of 0 is no limit.
+    pub fn fit_budget(&mut self, used: usize, budget: usize) {
+        let distance = self.loaded_distance();
+        let grown = |from: i32, to: i32| used as f64 * (to as f64 / from as f64).powi(2);
+        if budget == 0 {
+            self.budget_distance = MAX_RENDER_DISTANCE;
+        } else if used > budget && distance > MIN_RENDER_DISTANCE {
+            // Columns one chunk past the loaded area stay loaded, so the area
+            // has to fit the budget with them
+            let fitting = (MIN_RENDER_DISTANCE..distance).rev().find(|&to| grown(distance, to + 1) <= budget as f64);
+            self.budget_distance = fitting.unwrap_or(MIN_RENDER_DISTANCE);
+            self.sorted_for = None;
+            log::info!(target: "worldgen", "Memory over budget, loading {} chunks out", self.budget_distance);
+        } else if distance < self.render_distance && self.queued() == 0 && grown(distance, distance + 1) <= budget as f64 * BUDGET_GROWTH_MARGIN {
+            self.budget_distance = distance + 1;
+            self.sorted_for = None;
+        }
+    }
+
+    pub fn set_render_distance(&mut self, render_distance: i32) {
+        self.render_distance = render_distance.clamp(MIN_RENDER_DISTANCE, MAX_RENDER_DISTANCE);
+        self.sorted_for = None;
+    }
+
+    /// Loads the columns around a position too, before any others, for the
+    /// player to be moved there once they are. They stay loaded until
+    /// `clear_preload`.
+    pub fn preload(&mut self, position: Vec3) {
+        self.preload = Some(column_at(position));
+    }
+
+    pub fn clear_preload(&mut self) {
+        self.preload = None;
+    }
+
+    /// How many of the columns being preloaded are loaded, and how many there
+    /// are, or None when nothing is being preloaded. Columns beyond the world
+    /// border never load, so they aren't counted.
+    pub fn preload_progress(&self, world: &World) -> Option<(usize, usize)> {
+        let columns = self.preload_columns(world)?;
+        let loaded = columns.iter().filter(|&&(x, z)| world.column(x, z).is_some()).count();
+        Some((loaded, columns.len()))
+    }
+
+    // The columns within the preload radius of the preloaded column, nearest first
+    fn preload_columns(&self, world: &World) -> Option<Vec<(i32, i32)>> {
+        let (center_x, center_z) = self.preload?;
+        let radius = PRELOAD_RADIUS.min(self.render_distance);
+        let mut columns = Vec::new();
+        for x in center_x - radius..=center_x + radius {
+            for z in center_z - radius..=center_z + radius {
+                let beyond_border = world.border.is_some_and(|border| border.excludes_column(x, z));
+                if (x - center_x).pow(2) + (z - center_z).pow(2) <= radius * radius && !beyond_border {
+                    columns.push((x, z));
+                }
+            }
+        }
+        columns.sort_by_key(|&(x, z)| (x - center_x).pow(2) + (z - center_z).pow(2));
+        Some(columns)
+    }
+
+    /// Brings the loaded chunks in line with the render distance around the
+    /// viewer. At most `max_columns` missing columns are generated, from the
+    /// front of the queue, so streaming spreads over several frames instead of
+    /// stalling one. Columns with saved chunks are handed back to have them
+    /// read first, and generate in a later step once they are. Each stage of
+    /// the chunk pipeline publishes its progress.
+    pub fn update(&mut self, world: &mut World, viewer: &Viewer, max_columns: usize, cache: &MeshCache, events: &mut EventBus) -> StreamUpdate {
+        let (center_x, center_z) = column_at(viewer.position);
+        let distance_sq = |x: i32, z: i32| (x - center_x).pow(2) + (z - center_z).pow(2);
+        let radius = self.loaded_distance();
+
+        let border = world.border;
+        let beyond_border = |x: i32, z: i32| border.is_some_and(|border| border.excludes_column(x, z));
+
+        // Unload columns past the render distance, with one chunk of slack so
+        // moving back and forth over the edge doesn't reload the same columns,
+        // and columns left outside a border that has shrunk. Preloaded columns
+        // stay
+        let preloaded = self.preload_columns(world).unwrap_or_default();
+        let unloaded_columns = world.loaded_columns()
+            .filter(|&(x, z)| distance_sq(x, z) > (radius + 1).pow(2) || beyond_border(x, z))
+            .filter(|column| !preloaded.contains(column))
+            .collect::<Vec<_>>();
+        let unloaded = unloaded_columns.into_iter()
+            .flat_map(|(x, z)| world.unload_column(x, z))
+            .collect::<Vec<_>>();
+
+        // Sort the queue again when the camera moves to another column or turns,
+        // and look for missing columns again once it runs out
+        let resort = match self.sorted_for {
+            Some((column, direction)) => {
+                column != (center_x, center_z) || direction.dot(&viewer.direction) < REPRIORITIZE_COS || self.queue.is_empty()
+            },
+            None => true,
+        };
+        if resort {
+            self.queue.clear();
+            let missing = (center_x - radius..=center_x + radius)
+                .flat_map(|x| (center_z - radius..=center_z + radius).map(move |z| (x, z)))
+                .filter(|&(x, z)| distance_sq(x, z) <= radius * radius)
+                .filter(|&(x, z)| world.column(x, z).is_none() && !beyond_border(x, z))
+                .collect::<Vec<_>>();
+            let bounds = missing.iter().map(|&(x, z)| column_bounds(x, z)).collect::<Vec<_>>();
+            let visible = viewer.frustum.intersects_aabbs(&bounds);
+            for (&(x, z), visible) in missing.iter().zip(visible) {
+                let distance = distance_sq(x, z);
+                let in_view = distance <= SURROUNDING_DISTANCE_SQ || visible;
+                let priority = if in_view { distance } else { distance * OUT_OF_VIEW_WEIGHT };
+                self.queue.push(Reverse((priority, x, z)));
+            }
+            self.sorted_for = Some(((center_x, center_z), viewer.direction));
+        }
+
+        // Generate the columns whose saved chunks were read since, then the
+        // missing preloaded columns, then those at the front of the queue.
+        // Columns with saved chunks to read count towards the limit too, so
+        // the reads keep pace with generation
+        let mut missing = Vec::new();
+        let mut to_read = Vec::new();
+        let mut columns_to_read = 0;
+        self.reading.retain(|&(x, z)| {
+            let read = world.unread_chunks(x, z).is_empty();
+            let wanted = distance_sq(x, z) <= radius * radius || preloaded.contains(&(x, z));
+            if read && wanted && world.column(x, z).is_none() {
+                missing.push((x, z));
+            }
+            !read
+        });
+        let mut candidates = preloaded.into_iter().filter(|&(x, z)| world.column(x, z).is_none());
+        while missing.len() + columns_to_read < max_columns {
+            let Some((x, z)) = candidates.next().or_else(|| self.queue.pop().map(|Reverse((_, x, z))| (x, z))) else {
+                break;
+            };
+            // The border may have moved since the column was queued
+            if world.column(x, z).is_some() || beyond_border(x, z) || missing.contains(&(x, z)) || self.reading.contains(&(x, z)) {
+                continue;
+            }
+            let unread = world.unread_chunks(x, z);
+            if unread.is_empty() {
+                missing.push((x, z));
+            } else {
+                to_read.extend(unread);
+                self.reading.push((x, z));
+                columns_to_read += 1;
+            }
+        }
+
+        let generation_start = Instant::now();
+        let generated = world.generate_columns(&missing, events);
+        let generation_time = generation_start.elapsed();
+        if !generated.is_empty() {
+            log::debug!(target: "worldgen", "Generated {} chunks in {:.1} ms", generated.len(), generation_time.as_secs_f32() * 1000.0);
+        }
+
+        // New chunks need a mesh, and chunks next to any added or removed chunk
+        // need a new one since their border faces changed
+        let mut remesh = generated.iter().copied().collect::<HashSet<_>>();
+        for &(x, y, z) in generated.iter().chain(&unloaded) {
+            for neighbor in [(x + 1, y, z), (x - 1, y, z), (x, y, z + 1), (x, y, z - 1)] {
+                if world.chunk(neighbor).is_some() {
+                    remesh.insert(neighbor);
+                }
+            }
+        }
+        let remeshed = remesh.into_iter().collect::<Vec<_>>();
+        let meshing_start = Instant::now();
+        let cached = world.build_meshes(&remeshed, cache);
+        let meshing_time = meshing_start.elapsed();
+        if !remeshed.is_empty() {
+            log::debug!(target: "meshing", "Meshed {} chunks in {:.1} ms", remeshed.len(), meshing_time.as_secs_f32() * 1000.0);
+        }
+        // Neighbours meshed again were already counted when they loaded
+        if !generated.is_empty() {
+            events.publish(ChunkProgress { stage: ChunkStage::Meshing, chunks: generated.len() });
+        }
+
+        StreamUpdate { remeshed, unloaded, loaded: generated, to_read, cached, generation_time, meshing_time }
+    }
+}
+
+// The chunk column a position is in, as x and z in chunks
+fn column_at(position: Vec3) -> (i32, i32) {
+    let size = CHUNK_SIZE as f32;
+    ((position.x / size).floor() as i32, (position.z / size).floor() as i32)
+}
+
+/// The corners of the box around a chunk column, for checking it against the
+/// view.
+pub fn column_bounds(x: i32, z: i32) -> (Vec3, Vec3) {
+    let height = (WORLD_HEIGHT_CHUNKS * CHUNK_SIZE as i32) as f32;
+    let size = CHUNK_SIZE as f32;
+    // Blocks are centered on integer coordinates, so columns start half a block early
+    let min = Vec3::new(x as f32 * size - 0.5, -0.5, z as f32 * size - 0.5);
+    (min, Vec3::new(min.x + size, height - 0.5, min.z + size))
+}
@@ -0,0 +1,197 @@
+use crate::block::BlockType;
+use crate::world::{Chunk, CHUNK_SIZE};
+use crate::world_generator::WorldGenerator;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Where structure templates are read from, one `.txt` file each.
+pub const STRUCTURE_DIRECTORY: &str = "src/assets/structures";
+// The world is split into square regions of this many blocks, each holding at
+// most one structure. A multiple of the chunk size, so every chunk lies in one region.
+const REGION_SIZE: i32 = 64;
+// Chance out of 256 that a region holds a structure
+const STRUCTURE_CHANCE: u32 = 80;
+
+/// A prefab stamped into the terrain, read from a template file.
+///
+/// Templates are text: `sink <n>` lowers the structure `n` blocks into the
+/// ground, `block <char> <name>` adds a palette entry, and each `layer` line
+/// starts a horizontal slice, bottom to top, whose rows run along z and whose
+/// characters run along x. `.` is air, and `-` leaves the terrain as it is.
+/// Lines starting with `#` are comments.
+pub struct Template {
+    size: (i32, i32, i32),
+    sink: i32,
+    // Indexed by (x * height + y) * depth + z; None keeps the generated block
+    blocks: Vec<Option<BlockType>>,
+}
+
+impl Template {
+    pub fn parse(text: &str) -> Result<Template, String> {
+        let mut palette = HashMap::from([('.', Some(BlockType::Air)), ('-', None)]);
+        let mut sink = 0;
+        let mut layers: Vec<Vec<Vec<char>>> = Vec::new();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            let error = |message: &str| format!("line {}: {}", number + 1, message);
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("sink") => {
+                    sink = words.next().and_then(|n| n.parse().ok()).ok_or_else(|| error("Expected `sink <blocks>`"))?;
+                },
+                Some("block") => {
+                    let (Some(symbol), Some(block)) = (words.next(), words.next()) else {
+                        return Err(error("Expected `block <char> <name>`"));
+                    };
+                    let mut chars = symbol.chars();
+                    let (Some(symbol), None) = (chars.next(), chars.next()) else {
+                        return Err(error("Palette symbols are a single character"));
+                    };
+                    let block = BlockType::from_name(block).ok_or_else(|| error(&format!("Unknown block: {}", block)))?;
+                    palette.insert(symbol, Some(block));
+                },
+                Some("layer") => layers.push(Vec::new()),
+                _ => match layers.last_mut() {
+                    Some(layer) => layer.push(line.chars().collect()),
+                    None => return Err(error("Rows must come after a `layer` line")),
+                },
+            }
+        }
+
+        let width = layers.iter().flatten().map(Vec::len).max().unwrap_or(0);
+        let depth = layers.iter().map(Vec::len).max().unwrap_or(0);
+        if width == 0 || depth == 0 {
+            return Err("The template has no blocks".to_string());
+        }
+        if width > REGION_SIZE as usize || depth > REGION_SIZE as usize {
+            return Err(format!("Templates are at most {} blocks across", REGION_SIZE));
+        }
+
+        let (width, height, depth) = (width as i32, layers.len() as i32, depth as i32);
+        let mut blocks = vec![None; (width * height * depth) as usize];
+        for (y, layer) in layers.iter().enumerate() {
+            for (z, row) in layer.iter().enumerate() {
+                for (x, symbol) in row.iter().enumerate() {
+                    let block = *palette.get(symbol).ok_or_else(|| format!("Unknown palette symbol: {}", symbol))?;
+                    blocks[((x as i32 * height + y as i32) * depth + z as i32) as usize] = block;
+                }
+            }
+        }
+        Ok(Template { size: (width, height, depth), sink, blocks })
+    }
+
+    fn block(&self, x: i32, y: i32, z: i32) -> Option<BlockType> {
+        let (_, height, depth) = self.size;
+        self.blocks[((x * height + y) * depth + z) as usize]
+    }
+}
+
+/// Where a region's structure goes: which template, and its lowest corner.
+#[derive(Debug, Clone, Copy)]
+pub struct Placement {
+    template: usize,
+    origin: (i32, i32, i32),
+}
+
+/// The templates structures are built from, and the deterministic layout of
+/// structures across the world. Every chunk column works out the structure
+/// that reaches into it on its own, so structures crossing chunk borders come
+/// out whole in whatever order the chunks are generated.
+#[derive(Default)]
+pub struct StructureSet {
+    templates: Vec<Template>,
+}
+
+impl StructureSet {
+    /// Reads every template in a directory, in name order so the layout is the
+    /// same on every machine. Templates that can't be read are left out.
+    pub fn load(directory: &Path) -> Self {
+        let mut paths: Vec<_> = fs::read_dir(directory)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "txt"))
+            .collect();
+        paths.sort();
+
+        let mut templates = Vec::new();
+        for path in paths {
+            let template = fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|text| Template::parse(&text));
+            match template {
+                Ok(template) => templates.push(template),
+                Err(e) => log::warn!(target: "worldgen", "Skipping structure {}: {}", path.display(), e),
+            }
+        }
+        log::debug!(target: "worldgen", "Loaded {} structure templates", templates.len());
+        Self { templates }
+    }
+
+    // The structure of a region, if it has one. Depends only on the seed and
+    // the region, never on what has been generated so far.
+    fn placement(&self, seed: u32, generator: &dyn WorldGenerator, region: (i32, i32)) -> Option<Placement> {
+        if self.templates.is_empty() {
+            return None;
+        }
+        let mut hash = seed.wrapping_add(0x9e37_79b9) ^ (region.0 as u32).wrapping_mul(0x8da6_b343) ^ (region.1 as u32).wrapping_mul(0xd816_3841);
+        let mut next = move || {
+            hash ^= hash >> 16;
+            hash = hash.wrapping_mul(0x7feb_352d);
+            hash ^= hash >> 15;
+            hash = hash.wrapping_mul(0x846c_a68b);
+            hash ^= hash >> 16;
+            hash
+        };
+        if next() % 256 >= STRUCTURE_CHANCE {
+            return None;
+        }
+
+        let index = next() as usize % self.templates.len();
+        let template = &self.templates[index];
+        let (width, _, depth) = template.size;
+        // Anywhere the whole structure stays inside the region
+        let x = region.0 * REGION_SIZE + (next() % (REGION_SIZE - width + 1) as u32) as i32;
+        let z = region.1 * REGION_SIZE + (next() % (REGION_SIZE - depth + 1) as u32) as i32;
+
+        // Stands on the terrain at its center, where the generator allows it
+        let ground = generator.structure_ground(seed, x + width / 2, z + depth / 2)?;
+        Some(Placement { template: index, origin: (x, ground - template.sink, z) })
+    }
+
+    /// The structure that may reach into a chunk column, the one of the
+    /// region it lies in.
+    pub fn column_placement(&self, seed: u32, generator: &dyn WorldGenerator, (x, z): (i32, i32)) -> Option<Placement> {
+        let size = CHUNK_SIZE as i32;
+        self.placement(seed, generator, ((x * size).div_euclid(REGION_SIZE), (z * size).div_euclid(REGION_SIZE)))
+    }
+
+    /// Writes the part of a structure placed in its column that reaches into
+    /// a freshly generated chunk.
+    pub fn stamp(&self, chunk: &mut Chunk, placement: &Placement) {
+        let size = CHUNK_SIZE as i32;
+        let chunk_min = (chunk.position.0 * size, chunk.position.1 * size, chunk.position.2 * size);
+        let template = &self.templates[placement.template];
+        let (origin_x, origin_y, origin_z) = placement.origin;
+        let (width, height, depth) = template.size;
+        for x in 0..size {
+            for y in 0..size {
+                for z in 0..size {
+                    let (tx, ty, tz) = (chunk_min.0 + x - origin_x, chunk_min.1 + y - origin_y, chunk_min.2 + z - origin_z);
+                    if !(0..width).contains(&tx) || !(0..height).contains(&ty) || !(0..depth).contains(&tz) {
+                        continue;
+                    }
+                    if let Some(block) = template.block(tx, ty, tz) {
+                        chunk.blocks[x as usize][y as usize][z as usize] = block;
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use crate::hud::Hud;
+use crate::language::text;
+use crate::math::Vec3;
+use crate::streaming::ChunkStreamer;
+use crate::world::World;
+
+const TEXT_SIZE: f32 = 16.0;
+const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.8];
+// Below the top of the screen, where nothing else is drawn in the middle
+const TEXT_TOP: f32 = 40.0;
+
+/// A teleport waiting for the chunks around its destination to load and mesh,
+/// so the player doesn't land in a void. They load through
+/// `ChunkStreamer::preload`, a few columns a frame while the game goes on.
+pub struct PendingTeleport {
+    destination: Vec3,
+    // Columns around the destination loaded so far, and how many there are
+    progress: (usize, usize),
+}
+
+impl PendingTeleport {
+    /// Starts loading around the destination, in place of any teleport
+    /// pending before.
+    pub fn start(destination: Vec3, streamer: &mut ChunkStreamer) -> Self {
+        streamer.preload(destination);
+        Self { destination, progress: (0, 1) }
+    }
+
+    /// Follows the loading after a streaming step. Returns where to move the
+    /// player once everything around it is loaded, and lets the chunks there
+    /// stream like any others from then on.
+    pub fn update(&mut self, streamer: &mut ChunkStreamer, world: &World) -> Option<Vec3> {
+        self.progress = streamer.preload_progress(world)?;
+        let (loaded, total) = self.progress;
+        if loaded < total {
+            return None;
+        }
+        streamer.clear_preload();
+        Some(self.destination)
+    }
+
+    /// Queues how far the loading is at the top of the screen.
+    pub fn draw(&self, hud: &mut Hud, screen_width: f32) {
+        let (loaded, total) = self.progress;
+        let percent = loaded * 100 / total.max(1);
+        let label = text("hud.teleporting", &[&percent]);
+        let x = (screen_width - hud.measure_text(TEXT_SIZE, &label)) / 2.0;
+        hud.draw_text(x, TEXT_TOP, TEXT_SIZE, TEXT_COLOR, &label);
+    }
+}
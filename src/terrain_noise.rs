@@ -0,0 +1,166 @@
+use crate::world::CHUNK_SIZE;
+use noise::{NoiseFn, Perlin};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+// Blocks between the points the cave noise is sampled at; the blocks in
+// between interpolate them. Caves are tens of blocks across, so they keep
+// their shape and only lose some of their thinnest ends
+const CAVE_SPACING: usize = 2;
+// Samples across a chunk, including both ends
+const CAVE_SAMPLES: usize = CHUNK_SIZE / CAVE_SPACING + 1;
+// Layers of cave samples, with one more below the chunk for the block under
+// its lowest layer
+const CAVE_LAYERS: usize = CAVE_SAMPLES + 1;
+// Layers of blocks a cave grid covers: the chunk's and the top of the one below
+const CAVE_BLOCK_LAYERS: usize = CHUNK_SIZE + 1;
+// Columns kept before the cache is emptied. Streaming generates the chunks of
+// a column together, so only the latest few are ever asked for again
+const CACHED_COLUMNS: usize = 256;
+
+/// Height and temperature of every block column of a chunk column, shared by
+/// the chunks stacked in it.
+pub struct ColumnNoise {
+    heights: [[i32; CHUNK_SIZE]; CHUNK_SIZE],
+    temperatures: [[f64; CHUNK_SIZE]; CHUNK_SIZE],
+}
+
+impl ColumnNoise {
+    /// Height of the first block above the noise ground, at local x and z.
+    pub fn height(&self, x: usize, z: usize) -> i32 {
+        self.heights[x][z]
+    }
+
+    /// Temperature noise from -1 to 1, at local x and z.
+    pub fn temperature(&self, x: usize, z: usize) -> f64 {
+        self.temperatures[x][z]
+    }
+}
+
+/// Cave noise at every block of a chunk and the top layer of the one below,
+/// interpolated from samples every `CAVE_SPACING` blocks.
+pub struct CaveGrid {
+    // Indexed by x, then y from -1, then z
+    values: Vec<f64>,
+}
+
+impl CaveGrid {
+    /// Cave noise at local x and z and local y from -1 to 15, where -1 is the
+    /// top of the chunk below.
+    pub fn get(&self, x: usize, y: i32, z: usize) -> f64 {
+        self.values[(x * CAVE_BLOCK_LAYERS + (y + 1) as usize) * CHUNK_SIZE + z]
+    }
+}
+
+// Fills `count` values between samples `CAVE_SPACING` apart, the first of them
+// `offset` blocks past the first sample
+fn interpolate(samples: &[f64], offset: usize, count: usize) -> impl Iterator<Item = f64> + '_ {
+    (offset..offset + count).map(move |i| {
+        let (cell, t) = (i / CAVE_SPACING, (i % CAVE_SPACING) as f64 / CAVE_SPACING as f64);
+        samples[cell] + (samples[cell + 1] - samples[cell]) * t
+    })
+}
+
+/// The noise terrain is generated from, sampled a chunk or a column at a time
+/// rather than block by block. The 2D noise of a column is computed once and
+/// cached for every chunk in it, and the 3D cave noise is sampled on a coarse
+/// grid and interpolated, for far fewer `get` calls per chunk.
+pub struct TerrainNoise {
+    terrain: Perlin,
+    detail: Perlin,
+    cave: Perlin,
+    temperature: Perlin,
+    columns: Mutex<HashMap<(i32, i32), Arc<ColumnNoise>>>,
+}
+
+impl TerrainNoise {
+    pub fn new(seed: u32) -> Self {
+        Self {
+            terrain: Perlin::new(seed),
+            detail: Perlin::new(seed.wrapping_add(81)),
+            cave: Perlin::new(seed.wrapping_add(624)),
+            temperature: Perlin::new(seed.wrapping_add(735)),
+            columns: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Height of the first block above the noise ground of a block column,
+    /// before caves are carved out of it.
+    pub fn height(&self, world_x: i32, world_z: i32) -> i32 {
+        let nx = world_x as f64 * 0.02;
+        let nz = world_z as f64 * 0.02;
+
+        // Combine different noise layers for more interesting terrain
+        let base_height = self.terrain.get([nx, nz]) * 32.0 + 64.0;  // Base terrain
+        let detail = self.detail.get([nx * 4.0, nz * 4.0]) * 8.0;    // Small details
+        (base_height + detail) as i32
+    }
+
+    /// The 2D noise of the chunk column at chunk x and z, computed by the
+    /// first chunk of the column to ask.
+    pub fn column(&self, x: i32, z: i32) -> Arc<ColumnNoise> {
+        if let Some(column) = self.columns.lock().unwrap().get(&(x, z)) {
+            return column.clone();
+        }
+        // Computed without holding the lock, so other columns aren't held up
+        let mut column = ColumnNoise {
+            heights: [[0; CHUNK_SIZE]; CHUNK_SIZE],
+            temperatures: [[0.0; CHUNK_SIZE]; CHUNK_SIZE],
+        };
+        for local_x in 0..CHUNK_SIZE {
+            for local_z in 0..CHUNK_SIZE {
+                let world_x = x * CHUNK_SIZE as i32 + local_x as i32;
+                let world_z = z * CHUNK_SIZE as i32 + local_z as i32;
+                column.heights[local_x][local_z] = self.height(world_x, world_z);
+                let nx = world_x as f64 * 0.02;
+                let nz = world_z as f64 * 0.02;
+                column.temperatures[local_x][local_z] = self.temperature.get([nx * 0.5, nz * 0.5]);
+            }
+        }
+
+        let column = Arc::new(column);
+        let mut columns = self.columns.lock().unwrap();
+        if columns.len() >= CACHED_COLUMNS {
+            columns.clear();
+        }
+        columns.insert((x, z), column.clone());
+        column
+    }
+
+    /// Samples the cave noise for the chunk at a chunk position, and
+    /// interpolates it one axis at a time.
+    pub fn caves(&self, position: (i32, i32, i32)) -> CaveGrid {
+        let size = CHUNK_SIZE as i32;
+        // Layers start a whole spacing below the chunk
+        let origin = (position.0 * size, position.1 * size - CAVE_SPACING as i32, position.2 * size);
+        let sample = |x: usize, y: usize, z: usize| {
+            let world = |origin: i32, i: usize| (origin + (i * CAVE_SPACING) as i32) as f64 * 0.05;
+            self.cave.get([world(origin.0, x), world(origin.1, y), world(origin.2, z)])
+        };
+
+        // Along z between the samples, then along y, then along x
+        let mut along_z = vec![[0.0; CHUNK_SIZE]; CAVE_SAMPLES * CAVE_LAYERS];
+        for (i, row) in along_z.iter_mut().enumerate() {
+            let samples = (0..CAVE_SAMPLES).map(|z| sample(i / CAVE_LAYERS, i % CAVE_LAYERS, z)).collect::<Vec<_>>();
+            row.iter_mut().zip(interpolate(&samples, 0, CHUNK_SIZE)).for_each(|(value, sample)| *value = sample);
+        }
+        let mut along_y = vec![0.0; CAVE_SAMPLES * CAVE_BLOCK_LAYERS * CHUNK_SIZE];
+        for x in 0..CAVE_SAMPLES {
+            for z in 0..CHUNK_SIZE {
+                let samples = (0..CAVE_LAYERS).map(|y| along_z[x * CAVE_LAYERS + y][z]).collect::<Vec<_>>();
+                for (y, value) in interpolate(&samples, CAVE_SPACING - 1, CAVE_BLOCK_LAYERS).enumerate() {
+                    along_y[(x * CAVE_BLOCK_LAYERS + y) * CHUNK_SIZE + z] = value;
+                }
+            }
+        }
+        let mut values = vec![0.0; CHUNK_SIZE * CAVE_BLOCK_LAYERS * CHUNK_SIZE];
+        let stride = CAVE_BLOCK_LAYERS * CHUNK_SIZE;
+        for i in 0..stride {
+            let samples = (0..CAVE_SAMPLES).map(|x| along_y[x * stride + i]).collect::<Vec<_>>();
+            for (x, value) in interpolate(&samples, 0, CHUNK_SIZE).enumerate() {
+                values[x * stride + i] = value;
+            }
+        }
+        CaveGrid { values }
+    }
+}
@@ -0,0 +1,152 @@
+use crate::gl_utils::GlState;
+use crate::ttf::Font;
+use std::collections::HashMap;
+
+/// Font the HUD draws its text with.
+pub const FONT_PATH: &str = "src/assets/fonts/DejaVuSansMono.ttf";
+/// Texture unit the glyph atlas is bound to while the HUD draws.
+pub const FONT_TEXTURE_UNIT: u32 = 3;
+// Texels along each side of the atlas
+const ATLAS_SIZE: usize = 1024;
+// Empty texels around each glyph, so smoothing doesn't pick up its neighbours
+const PADDING: usize = 1;
+// Glyph sizes are rounded to this fraction of a pixel before rasterizing
+const SIZE_STEPS: f32 = 4.0;
+
+/// Where a rasterized glyph sits in the atlas, and where to draw it relative
+/// to the pen position on the baseline, in pixels.
+#[derive(Clone, Copy)]
+pub struct PlacedGlyph {
+    pub left: f32,
+    pub top: f32,
+    pub width: f32,
+    pub height: f32,
+    /// Corners in the atlas: left, top, right, bottom.
+    pub tex_coords: [f32; 4],
+}
+
+/// Glyphs of a font rasterized on first use into a single-channel texture,
+/// each character once per size. Glyphs are packed left to right in rows as
+/// tall as the tallest glyph in them; once the atlas is full it is emptied
+/// and filled again with whatever is drawn next.
+pub struct GlyphAtlas {
+    font: Font,
+    texture: gl::types::GLuint,
+    // None for characters without an outline, like the space
+    glyphs: HashMap<(char, u32), Option<PlacedGlyph>>,
+    // Where the next glyph goes, and the height of the row it goes in
+    cursor: (usize, usize),
+    row_height: usize,
+}
+
+impl GlyphAtlas {
+    pub fn new(font: Font) -> Self {
+        let mut texture = 0;
+        unsafe {
+            gl::GenTextures(1, &mut texture);
+            GlState::bind_texture(FONT_TEXTURE_UNIT, gl::TEXTURE_2D, texture);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            let empty = vec![0u8; ATLAS_SIZE * ATLAS_SIZE];
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::R8 as i32,
+                ATLAS_SIZE as i32,
+                ATLAS_SIZE as i32,
+                0,
+                gl::RED,
+                gl::UNSIGNED_BYTE,
+                empty.as_ptr() as *const _
+            );
+        }
+
+        Self {
+            font,
+            texture,
+            glyphs: HashMap::new(),
+            cursor: (PADDING, PADDING),
+            row_height: 0,
+        }
+    }
+
+    pub fn font(&self) -> &Font {
+        &self.font
+    }
+
+    pub fn texture(&self) -> gl::types::GLuint {
+        self.texture
+    }
+
+    /// The glyph of a character at `size` pixels per em, rasterized into the
+    /// atlas if it isn't there yet. None if there is nothing to draw.
+    pub fn glyph(&mut self, c: char, size: f32) -> Option<PlacedGlyph> {
+        let key = (c, (size * SIZE_STEPS).round() as u32);
+        if let Some(&placed) = self.glyphs.get(&key) {
+            return placed;
+        }
+
+        let bitmap = self.font.rasterize(self.font.glyph_index(c), key.1 as f32 / SIZE_STEPS);
+        if bitmap.width == 0 || bitmap.width + 2 * PADDING > ATLAS_SIZE || bitmap.height + 2 * PADDING > ATLAS_SIZE {
+            self.glyphs.insert(key, None);
+            return None;
+        }
+
+        // Start a new row when this one is full, and start over when the atlas is.
+        // Glyphs queued earlier in the frame may show the wrong shape for a frame.
+        if self.cursor.0 + bitmap.width + PADDING > ATLAS_SIZE {
+            self.cursor = (PADDING, self.cursor.1 + self.row_height + PADDING);
+            self.row_height = 0;
+        }
+        if self.cursor.1 + bitmap.height + PADDING > ATLAS_SIZE {
+            log::debug!(target: "render", "Glyph atlas full, clearing {} glyphs", self.glyphs.len());
+            self.glyphs.clear();
+            self.cursor = (PADDING, PADDING);
+            self.row_height = 0;
+        }
+
+        let (x, y) = self.cursor;
+        unsafe {
+            GlState::bind_texture(FONT_TEXTURE_UNIT, gl::TEXTURE_2D, self.texture);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::TexSubImage2D(
+                gl::TEXTURE_2D,
+                0,
+                x as i32,
+                y as i32,
+                bitmap.width as i32,
+                bitmap.height as i32,
+                gl::RED,
+                gl::UNSIGNED_BYTE,
+                bitmap.coverage.as_ptr() as *const _
+            );
+        }
+        self.cursor.0 += bitmap.width + PADDING;
+        self.row_height = self.row_height.max(bitmap.height);
+
+        let texel = 1.0 / ATLAS_SIZE as f32;
+        let placed = PlacedGlyph {
+            left: bitmap.left as f32,
+            top: bitmap.top as f32,
+            width: bitmap.width as f32,
+            height: bitmap.height as f32,
+            tex_coords: [
+                x as f32 * texel,
+                y as f32 * texel,
+                (x + bitmap.width) as f32 * texel,
+                (y + bitmap.height) as f32 * texel,
+            ],
+        };
+        self.glyphs.insert(key, Some(placed));
+        Some(placed)
+    }
+}
+
+impl Drop for GlyphAtlas {
+    fn drop(&mut self) {
+        GlState::delete_texture(self.texture);
+    }
+}
@@ -0,0 +1,335 @@
+use crate::block::{BlockShape, BlockType};
+use crate::circuits::{lamp_tick, wire_tick};
+use crate::events::{BlockChangeCause, BlockChanged, EventBus, Subscriber};
+use crate::math::Vec3;
+use crate::random::Random;
+use crate::world::{World, CHUNK_SIZE};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+/// World ticks per second, independent of the frame rate.
+pub const TICKS_PER_SECOND: f32 = 20.0;
+// Random blocks picked in every loaded chunk each tick, for slow changes like growth
+const RANDOM_TICKS_PER_CHUNK: usize = 3;
+// Scheduled updates processed per tick at most, so a flood can't stall a frame
+const MAX_UPDATES_PER_TICK: usize = 1024;
+
+// Ticks between water spreading one block
+const WATER_FLOW_DELAY: u64 = 5;
+// How many blocks water flows sideways from a source
+const WATER_SPREAD: u8 = 4;
+// One chance in this many for a sapling to grow on a random tick
+const SAPLING_GROWTH_CHANCE: u32 = 8;
+
+/// Why a block's handler is being called.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TickKind {
+    /// An update scheduled for this block, carrying a handler-defined value.
+    Scheduled(u8),
+    /// The block was picked at random, for changes that happen over time.
+    Random,
+    /// The block's block entity is being ticked, which happens every tick
+    /// while its chunk is loaded.
+    BlockEntity,
+}
+
+type Handler = Box<dyn Fn(&mut TickContext)>;
+// Due tick, order of scheduling, block position and the value passed to the handler
+type ScheduledUpdate = (u64, u64, (i32, i32, i32), u8);
+
+/// What a tick handler sees of the world while it runs.
+pub struct TickContext<'a> {
+    pub world: &'a mut World,
+    /// Block being ticked.
+    pub position: (i32, i32, i32),
+    pub kind: TickKind,
+    random: &'a mut Random,
+    scheduled: Vec<((i32, i32, i32), u64, u8)>,
+    edited_blocks: Vec<(i32, i32, i32)>,
+}
+
+impl TickContext<'_> {
+    pub fn get_block(&self, (x, y, z): (i32, i32, i32)) -> BlockType {
+        self.world.get_block(x, y, z)
+    }
+
+    /// Changes a block and remembers it for remeshing.
+    pub fn set_block(&mut self, (x, y, z): (i32, i32, i32), block: BlockType) {
+        if self.world.set_block(x, y, z, block) {
+            self.edited_blocks.push((x, y, z));
+        }
+    }
+
+    /// Asks for the block at `position` to be ticked again after `delay` ticks.
+    pub fn schedule(&mut self, position: (i32, i32, i32), delay: u64, value: u8) {
+        self.scheduled.push((position, delay, value));
+    }
+
+    /// Pseudo-random number in `0..bound`.
+    pub fn random(&mut self, bound: u32) -> u32 {
+        self.random.below(bound)
+    }
+}
+
+/// Runs block updates at a fixed rate, whatever the frame rate.
+///
+/// Each block type can register a handler. It is called for updates scheduled at
+/// a block of that type, and for blocks picked at random in loaded chunks.
+/// Block entities in loaded chunks are ticked every tick.
+pub struct TickScheduler {
+    handlers: HashMap<BlockType, Handler>,
+    tick: u64,
+    // Pending updates, earliest first; ties run in the order they were scheduled
+    queue: BinaryHeap<Reverse<ScheduledUpdate>>,
+    // Blocks with an update in the queue, so each is only scheduled once
+    pending: HashSet<(i32, i32, i32)>,
+    sequence: u64,
+    // Picks the blocks to tick at random and rolls their chances
+    random: Random,
+    changes: Subscriber<BlockChanged>,
+}
+
+impl TickScheduler {
+    pub fn new(seed: u32) -> Self {
+        Self {
+            handlers: HashMap::new(),
+            tick: 0,
+            queue: BinaryHeap::new(),
+            pending: HashSet::new(),
+            sequence: 0,
+            random: Random::new(seed),
+            changes: Subscriber::new(),
+        }
+    }
+
+    /// Registers the tick handler of a block type, replacing any previous one.
+    pub fn register(&mut self, block: BlockType, handler: impl Fn(&mut TickContext) + 'static) {
+        self.handlers.insert(block, Box::new(handler));
+    }
+
+    /// Registers the built-in block behaviours: flowing water, grass spreading
+    /// to dirt, saplings growing into trees and signals running through circuits.
+    pub fn with_builtins(seed: u32) -> Self {
+        let mut scheduler = Self::new(seed);
+        scheduler.register(BlockType::Water, water_tick);
+        scheduler.register(BlockType::Grass, grass_tick);
+        scheduler.register(BlockType::OakSapling, sapling_tick);
+        scheduler.register(BlockType::Wire, wire_tick);
+        scheduler.register(BlockType::Lamp, lamp_tick);
+        scheduler.register(BlockType::LitLamp, lamp_tick);
+        scheduler
+    }
+
+    /// Schedules an update of a block after `delay` ticks, unless one is already pending.
+    pub fn schedule(&mut self, position: (i32, i32, i32), delay: u64, value: u8) {
+        if self.pending.insert(position) {
+            self.sequence += 1;
+            self.queue.push(Reverse((self.tick + delay.max(1), self.sequence, position, value)));
+        }
+    }
+
+    /// Wakes a block that just changed and its six neighbours, so water can flow
+    /// into a hole or a sapling can react. Every block is treated as a source.
+    pub fn block_changed(&mut self, (x, y, z): (i32, i32, i32)) {
+        let neighbors = [(0, 0, 0), (1, 0, 0), (-1, 0, 0), (0, 1, 0), (0, -1, 0), (0, 0, 1), (0, 0, -1)];
+        for (dx, dy, dz) in neighbors {
+            self.schedule((x + dx, y + dy, z + dz), WATER_FLOW_DELAY, WATER_SPREAD);
+        }
+    }
+
+    /// Wakes the blocks changed by anything but the scheduler's own updates,
+    /// which schedule what they need themselves.
+    pub fn handle_events(&mut self, events: &EventBus) {
+        for change in events.read(&mut self.changes) {
+            if change.cause != BlockChangeCause::Tick {
+                self.block_changed(change.position);
+            }
+        }
+    }
+
+    /// Runs `ticks` world ticks, as counted by the world's fixed timestep.
+    /// Returns the blocks that changed, for remeshing.
+    pub fn update(&mut self, world: &mut World, ticks: u32) -> Vec<(i32, i32, i32)> {
+        let mut edited_blocks = Vec::new();
+        for _ in 0..ticks {
+            self.tick += 1;
+            self.run_tick(world, &mut edited_blocks);
+        }
+        edited_blocks.sort_unstable();
+        edited_blocks.dedup();
+        edited_blocks
+    }
+
+    fn run_tick(&mut self, world: &mut World, edited_blocks: &mut Vec<(i32, i32, i32)>) {
+        let mut due = Vec::new();
+        while let Some(&Reverse((tick, _, position, value))) = self.queue.peek() {
+            if tick > self.tick || due.len() >= MAX_UPDATES_PER_TICK {
+                break;
+            }
+            self.queue.pop();
+            self.pending.remove(&position);
+            due.push((position, TickKind::Scheduled(value)));
+        }
+
+        // A few random blocks in every loaded chunk
+        let size = CHUNK_SIZE as i32;
+        let chunks = world.chunks().map(|chunk| chunk.position).collect::<Vec<_>>();
+        for (cx, cy, cz) in chunks {
+            for _ in 0..RANDOM_TICKS_PER_CHUNK {
+                let local = self.random.next_u32() as i32 & (size * size * size - 1);
+                let position = (
+                    cx * size + local % size,
+                    cy * size + local / size % size,
+                    cz * size + local / (size * size),
+                );
+                let (x, y, z) = position;
+                if self.handlers.contains_key(&world.get_block(x, y, z)) {
+                    due.push((position, TickKind::Random));
+                }
+            }
+        }
+
+        for (position, kind) in due {
+            let (x, y, z) = position;
+            let Some(handler) = self.handlers.get(&world.get_block(x, y, z)) else {
+                continue;
+            };
+            let mut context = TickContext {
+                world,
+                position,
+                kind,
+                random: &mut self.random,
+                scheduled: Vec::new(),
+                edited_blocks: Vec::new(),
+            };
+            handler(&mut context);
+            let (scheduled, edited) = (context.scheduled, context.edited_blocks);
+            self.finish(scheduled, edited, edited_blocks);
+        }
+
+        // Block entities in loaded chunks. They're taken out of the world while
+        // they run, and put back unless a tick replaced their block.
+        let mut block_entities = world.block_entities.take();
+        block_entities.sort_unstable_by_key(|&(position, _, _)| position);
+        for (position, block, mut entity) in block_entities {
+            let (x, y, z) = position;
+            if world.is_loaded(Vec3::new(x as f32, y as f32, z as f32)) {
+                let mut context = TickContext {
+                    world,
+                    position,
+                    kind: TickKind::BlockEntity,
+                    random: &mut self.random,
+                    scheduled: Vec::new(),
+                    edited_blocks: Vec::new(),
+                };
+                entity.tick(&mut context);
+                let (scheduled, edited) = (context.scheduled, context.edited_blocks);
+                self.finish(scheduled, edited, edited_blocks);
+                if world.get_block(x, y, z) != block {
+                    continue;
+                }
+            }
+            world.block_entities.restore(position, block, entity);
+        }
+    }
+
+    // Queues the updates a handler scheduled and collects the blocks it changed
+    fn finish(&mut self, scheduled: Vec<((i32, i32, i32), u64, u8)>, edited: Vec<(i32, i32, i32)>,
+        edited_blocks: &mut Vec<(i32, i32, i32)>) {
+        edited_blocks.extend(edited);
+        for (position, delay, value) in scheduled {
+            self.schedule(position, delay, value);
+        }
+    }
+}
+
+// Water falls into empty space below it, or spreads sideways over solid ground
+// until it has spread `WATER_SPREAD` blocks from where it started
+fn water_tick(context: &mut TickContext) {
+    let TickKind::Scheduled(spread) = context.kind else {
+        return;
+    };
+    let (x, y, z) = context.position;
+
+    let below = (x, y - 1, z);
+    if context.get_block(below) == BlockType::Air {
+        context.set_block(below, BlockType::Water);
+        context.schedule(below, WATER_FLOW_DELAY, WATER_SPREAD);
+        return;
+    }
+    if spread == 0 || context.get_block(below) == BlockType::Water {
+        return;
+    }
+    for side in [(x + 1, y, z), (x - 1, y, z), (x, y, z + 1), (x, y, z - 1)] {
+        if context.get_block(side) == BlockType::Air {
+            context.set_block(side, BlockType::Water);
+            context.schedule(side, WATER_FLOW_DELAY, spread - 1);
+        }
+    }
+}
+
+// Grass smothered by a solid block dies back to dirt; otherwise it spreads to
+// nearby dirt that has open air above it
+fn grass_tick(context: &mut TickContext) {
+    if context.kind != TickKind::Random {
+        return;
+    }
+    let (x, y, z) = context.position;
+    if context.get_block((x, y + 1, z)).occludes() {
+        context.set_block((x, y, z), BlockType::Dirt);
+        return;
+    }
+
+    let target = (
+        x + context.random(3) as i32 - 1,
+        y + context.random(3) as i32 - 1,
+        z + context.random(3) as i32 - 1,
+    );
+    let above = context.get_block((target.0, target.1 + 1, target.2));
+    if context.get_block(target) == BlockType::Dirt && !above.occludes() && above != BlockType::Water {
+        context.set_block(target, BlockType::Grass);
+    }
+}
+
+// Saplings on grass or dirt sometimes grow into an oak tree, if there is room
+fn sapling_tick(context: &mut TickContext) {
+    if context.kind != TickKind::Random || context.random(SAPLING_GROWTH_CHANCE) != 0 {
+        return;
+    }
+    let (x, y, z) = context.position;
+    if !matches!(context.get_block((x, y - 1, z)), BlockType::Grass | BlockType::Dirt) {
+        return;
+    }
+
+    let trunk_height = 4 + context.random(2) as i32;
+    let replaceable = |block: BlockType| {
+        matches!(block.info().shape, BlockShape::Empty | BlockShape::Cross)
+    };
+    // With the sapling on top of its column there's nothing above to check
+    let open_sky = context.world.highest_block(x, z).is_some_and(|top| top <= y);
+    if !open_sky && (1..=trunk_height).any(|dy| !replaceable(context.get_block((x, y + dy, z)))) {
+        return;
+    }
+
+    // Two wide layers of leaves around the top of the trunk, then two narrow ones above
+    let top = y + trunk_height - 1;
+    for dy in -2..=1 {
+        let radius: i32 = if dy < 0 { 2 } else { 1 };
+        for dx in -radius..=radius {
+            for dz in -radius..=radius {
+                // Round off the corners, leaving some of them to chance
+                if dx.abs() == radius && dz.abs() == radius && (dy == 1 || context.random(2) == 0) {
+                    continue;
+                }
+                let position = (x + dx, top + dy, z + dz);
+                if replaceable(context.get_block(position)) {
+                    context.set_block(position, BlockType::OakLeaves);
+                }
+            }
+        }
+    }
+    for dy in 0..trunk_height {
+        context.set_block((x, y + dy, z), BlockType::OakLog);
+    }
+    context.set_block((x, y - 1, z), BlockType::Dirt);
+}
@@ -0,0 +1,54 @@
+use crate::ticks::TICKS_PER_SECOND;
+
+/// Physics steps per second: player movement, entities and mobs.
+pub const PHYSICS_RATE: f32 = 120.0;
+// Physics steps skipped when the game falls behind, rather than catching up all at once
+const MAX_PHYSICS_STEPS: u32 = 12;
+// Same for world ticks
+const MAX_WORLD_TICKS: u32 = 10;
+
+/// Turns variable frame times into a whole number of fixed-length steps, so a
+/// simulation behaves the same at any frame rate. Time left over carries on to
+/// the next frame.
+pub struct FixedTimestep {
+    step: f32,
+    max_steps: u32,
+    // Seconds of game time not yet turned into steps
+    accumulator: f32,
+}
+
+impl FixedTimestep {
+    /// Runs `rate` steps per second, and at most `max_steps` in one frame.
+    pub fn new(rate: f32, max_steps: u32) -> Self {
+        Self { step: 1.0 / rate, max_steps, accumulator: 0.0 }
+    }
+
+    /// The world tick timestep: time of day and block updates.
+    pub fn world_ticks() -> Self {
+        Self::new(TICKS_PER_SECOND, MAX_WORLD_TICKS)
+    }
+
+    /// The physics timestep.
+    pub fn physics() -> Self {
+        Self::new(PHYSICS_RATE, MAX_PHYSICS_STEPS)
+    }
+
+    /// Length of a step, in seconds.
+    pub fn step(&self) -> f32 {
+        self.step
+    }
+
+    /// Adds a frame's time and returns how many steps are due.
+    pub fn advance(&mut self, delta_time: f32) -> u32 {
+        self.accumulator = (self.accumulator + delta_time).min(self.step * self.max_steps as f32);
+        let steps = (self.accumulator / self.step) as u32;
+        self.accumulator -= steps as f32 * self.step;
+        steps
+    }
+
+    /// How far the frame is between the last step and the next, from 0 to 1,
+    /// for drawing moving things in between their simulated positions.
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.step).clamp(0.0, 1.0)
+    }
+}
@@ -0,0 +1,145 @@
+use crate::block::BlockType;
+use crate::entity::{Aabb, Entities, Entity, MeshHandle};
+use crate::entity_renderer::EntityRenderer;
+use crate::explosion::Explosion;
+use crate::math::{Transform, Vec3};
+use crate::random::Random;
+use crate::world::World;
+
+/// Seconds from lighting TNT to it going off.
+pub const FUSE_TIME: f32 = 4.0;
+// TNT caught in another explosion goes off sooner, somewhere in this range,
+// so a pile of it rumbles rather than going off all at once
+const MIN_CHAIN_FUSE: f32 = 0.5;
+const MAX_CHAIN_FUSE: f32 = 1.5;
+const EXPLOSION_RADIUS: f32 = 4.0;
+const EXPLOSION_POWER: f32 = 10.0;
+// A little under a block, so it drops down one-block shafts
+const SIZE: f32 = 0.98;
+const GRAVITY: f32 = 25.0;
+// Primed TNT hops up a little when lit
+const IGNITE_HOP: f32 = 4.0;
+// Fraction of its sideways speed lost per second on the ground
+const GROUND_FRICTION: f32 = 6.0;
+// Seconds between flashes, each lit for half of it
+const BLINK_PERIOD: f32 = 0.5;
+// How much bigger it grows over the last moments of the fuse
+const SWELL: f32 = 0.15;
+const SWELL_TIME: f32 = 0.3;
+
+/// Component of lit TNT, counting down to an explosion.
+pub struct PrimedTnt {
+    // Seconds left until it explodes
+    fuse: f32,
+    velocity: Vec3,
+    on_ground: bool,
+}
+
+impl PrimedTnt {
+    /// Throws the TNT with the given velocity, such as away from another explosion.
+    pub fn push(&mut self, velocity: Vec3) {
+        self.velocity = self.velocity + velocity;
+    }
+}
+
+/// Lights TNT, turning the block into an entity that falls, blinks and blows
+/// up when its fuse runs out.
+pub struct Tnts {
+    block_mesh: MeshHandle,
+    // Drawn instead of the block while blinking
+    flash_mesh: MeshHandle,
+    /// Whether lit TNT flashes white. Without, it only swells.
+    pub blinking: bool,
+    // Draws the chain fuses
+    random: Random,
+}
+
+impl Tnts {
+    pub fn new(renderer: &mut EntityRenderer, seed: u32, blinking: bool) -> Option<Self> {
+        Some(Self {
+            block_mesh: renderer.add_block_cube(BlockType::Tnt.info().textures)?,
+            flash_mesh: renderer.add_cube([1.0, 1.0, 1.0])?,
+            blinking,
+            random: Random::new(seed),
+        })
+    }
+
+    /// A fuse for TNT set off by another explosion.
+    pub fn chain_fuse(&mut self) -> f32 {
+        self.random.range(MIN_CHAIN_FUSE, MAX_CHAIN_FUSE)
+    }
+
+    /// Spawns lit TNT in the cell of block `position`, going off in `fuse`
+    /// seconds. The block itself is left to the caller to remove.
+    pub fn ignite(&mut self, entities: &mut Entities, position: (i32, i32, i32), fuse: f32) -> Entity {
+        let (x, y, z) = position;
+        let feet = Vec3::new(x as f32, y as f32 - 0.5, z as f32);
+        let entity = entities.spawn();
+        let mut transform = Transform::at(feet);
+        transform.scale = Vec3::new(SIZE, SIZE, SIZE);
+        entities.transforms.insert(entity, transform);
+        entities.bounds.insert(entity, Aabb::standing(SIZE, SIZE));
+        entities.meshes.insert(entity, self.block_mesh);
+        entities.primed_tnt.insert(entity, PrimedTnt {
+            fuse,
+            velocity: Vec3::new(0.0, IGNITE_HOP, 0.0),
+            on_ground: false,
+        });
+        log::debug!(target: "game", "Lit TNT at {} {} {}", x, y, z);
+        entity
+    }
+
+    /// Runs one step of every lit TNT's fuse, falling and blinking. Those
+    /// whose fuse ran out explode, and are returned for their effects.
+    pub fn update(&mut self, entities: &mut Entities, world: &mut World, delta_time: f32) -> Vec<Explosion> {
+        let mut exploding = Vec::new();
+        for (entity, tnt) in entities.primed_tnt.iter_mut() {
+            let (Some(transform), Some(bounds), Some(mesh)) = (
+                entities.transforms.get_mut(entity),
+                entities.bounds.get(entity),
+                entities.meshes.get_mut(entity),
+            ) else {
+                continue;
+            };
+
+            tnt.fuse -= delta_time;
+            if tnt.fuse <= 0.0 {
+                exploding.push((entity, transform.translation + Vec3::new(0.0, 0.5, 0.0)));
+                continue;
+            }
+
+            // Fall, and slide to a stop once landed
+            tnt.velocity.y -= GRAVITY * delta_time;
+            if tnt.on_ground {
+                let friction = (1.0 - GROUND_FRICTION * delta_time).max(0.0);
+                tnt.velocity.x *= friction;
+                tnt.velocity.z *= friction;
+            }
+            let collision = world.sweep_aabb(bounds.at(transform.translation), tnt.velocity * delta_time);
+            transform.translation = transform.translation + collision.movement;
+            if collision.normal.0 != 0 {
+                tnt.velocity.x = 0.0;
+            }
+            if collision.normal.1 != 0 {
+                tnt.velocity.y = 0.0;
+            }
+            if collision.normal.2 != 0 {
+                tnt.velocity.z = 0.0;
+            }
+            tnt.on_ground = collision.grounded;
+
+            // Flash white, and swell just before going off
+            let lit = self.blinking && (tnt.fuse / BLINK_PERIOD).fract() < 0.5;
+            *mesh = if lit { self.flash_mesh } else { self.block_mesh };
+            let size = SIZE * (1.0 + SWELL * (1.0 - tnt.fuse / SWELL_TIME).max(0.0));
+            transform.scale = Vec3::new(size, size, size);
+        }
+
+        exploding.into_iter()
+            .map(|(entity, center)| {
+                entities.despawn(entity);
+                world.explode(center, EXPLOSION_RADIUS, EXPLOSION_POWER)
+            })
+            .collect()
+    }
+}
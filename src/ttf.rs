@@ -0,0 +1,449 @@
+use std::fs;
+use std::path::Path;
+
+// Horizontal lines sampled per pixel row when rasterizing
+const SUBSAMPLES: usize = 5;
+// Straight segments each curve of an outline is split into
+const CURVE_STEPS: usize = 6;
+// Deepest nesting of composite glyphs followed
+const MAX_COMPOSITE_DEPTH: u32 = 8;
+
+// Composite glyph flags
+const ARGS_ARE_WORDS: u16 = 0x0001;
+const ARGS_ARE_XY_VALUES: u16 = 0x0002;
+const HAS_SCALE: u16 = 0x0008;
+const MORE_COMPONENTS: u16 = 0x0020;
+const HAS_XY_SCALE: u16 = 0x0040;
+const HAS_TWO_BY_TWO: u16 = 0x0080;
+
+// A straight piece of a flattened outline, from one pixel position to another
+type Edge = ((f32, f32), (f32, f32));
+
+// A point of a glyph outline, in font units with y up
+#[derive(Clone, Copy)]
+struct Point {
+    x: f32,
+    y: f32,
+    on_curve: bool,
+}
+
+// Where characters are looked up: a format 4 subtable covering the basic
+// multilingual plane, or a format 12 one covering all of Unicode
+enum CharacterMap {
+    Segments(usize),
+    Groups(usize),
+}
+
+/// A TrueType font, read whole into memory. Glyphs are looked up by character
+/// and rasterized on demand at any size; hinting and kerning are left out.
+pub struct Font {
+    data: Vec<u8>,
+    units_per_em: f32,
+    ascender: f32,
+    descender: f32,
+    glyph_count: u16,
+    long_offsets: bool,
+    horizontal_metric_count: u16,
+    character_map: CharacterMap,
+    // Offsets of the tables glyphs are read from
+    loca: usize,
+    glyf: usize,
+    hmtx: usize,
+}
+
+/// A rasterized glyph, its coverage from 0 to 255 in rows from the top.
+pub struct GlyphBitmap {
+    pub width: usize,
+    pub height: usize,
+    /// Pixels from the pen position on the baseline to the left edge.
+    pub left: i32,
+    /// Pixels from the baseline down to the top edge, negative above it.
+    pub top: i32,
+    pub coverage: Vec<u8>,
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+fn i16_at(data: &[u8], offset: usize) -> Option<i16> {
+    u16_at(data, offset).map(|value| value as i16)
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+// A 2.14 fixed point number, as composite glyphs are scaled by
+fn f2dot14_at(data: &[u8], offset: usize) -> Option<f32> {
+    i16_at(data, offset).map(|value| value as f32 / 16384.0)
+}
+
+impl Font {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let data = fs::read(path).map_err(|e| e.to_string())?;
+        Self::parse(data)
+    }
+
+    /// Reads the tables of a font file held in memory.
+    pub fn parse(data: Vec<u8>) -> Result<Self, String> {
+        let table_count = u16_at(&data, 4).ok_or("Truncated font")? as usize;
+        let table = |tag: &[u8; 4]| {
+            (0..table_count)
+                .map(|index| 12 + index * 16)
+                .find(|&record| data.get(record..record + 4) == Some(tag))
+                .and_then(|record| u32_at(&data, record + 8))
+                .map(|offset| offset as usize)
+                .ok_or_else(|| format!("No {} table", String::from_utf8_lossy(tag)))
+        };
+        let (head, hhea, maxp, cmap) = (table(b"head")?, table(b"hhea")?, table(b"maxp")?, table(b"cmap")?);
+        let (loca, glyf, hmtx) = (table(b"loca")?, table(b"glyf")?, table(b"hmtx")?);
+
+        let truncated = || "Truncated font header".to_string();
+        let units_per_em = u16_at(&data, head + 18).ok_or_else(truncated)?.max(1) as f32;
+        let long_offsets = i16_at(&data, head + 50).ok_or_else(truncated)? != 0;
+        let ascender = i16_at(&data, hhea + 4).ok_or_else(truncated)? as f32;
+        let descender = i16_at(&data, hhea + 6).ok_or_else(truncated)? as f32;
+        let horizontal_metric_count = u16_at(&data, hhea + 34).ok_or_else(truncated)?;
+        let glyph_count = u16_at(&data, maxp + 4).ok_or_else(truncated)?;
+        let character_map = find_character_map(&data, cmap).ok_or("No Unicode character map")?;
+
+        Ok(Self {
+            data,
+            units_per_em,
+            ascender,
+            descender,
+            glyph_count,
+            long_offsets,
+            horizontal_metric_count,
+            character_map,
+            loca,
+            glyf,
+            hmtx,
+        })
+    }
+
+    /// Height above the baseline that tall glyphs reach, in pixels at `size`
+    /// pixels per em.
+    pub fn ascent(&self, size: f32) -> f32 {
+        self.ascender * size / self.units_per_em
+    }
+
+    /// Height of a line, from the top of the tallest glyphs to the bottom of
+    /// the lowest, in pixels at `size` pixels per em.
+    pub fn line_height(&self, size: f32) -> f32 {
+        (self.ascender - self.descender) * size / self.units_per_em
+    }
+
+    /// The glyph of a character, glyph 0 (usually a box) when the font lacks it.
+    pub fn glyph_index(&self, c: char) -> u16 {
+        let code = c as u32;
+        let glyph = match self.character_map {
+            CharacterMap::Segments(subtable) => segment_glyph(&self.data, subtable, code),
+            CharacterMap::Groups(subtable) => group_glyph(&self.data, subtable, code),
+        };
+        glyph.filter(|&glyph| glyph < self.glyph_count).unwrap_or(0)
+    }
+
+    /// How far the pen moves after a glyph, in pixels at `size` pixels per em.
+    pub fn advance(&self, glyph: u16, size: f32) -> f32 {
+        // Glyphs past the last metric share its advance
+        let metric = glyph.min(self.horizontal_metric_count.saturating_sub(1)) as usize;
+        let advance = u16_at(&self.data, self.hmtx + metric * 4).unwrap_or(0);
+        advance as f32 * size / self.units_per_em
+    }
+
+    /// Draws a glyph at `size` pixels per em, with smoothed edges. Glyphs
+    /// without an outline, like the space, come out empty.
+    pub fn rasterize(&self, glyph: u16, size: f32) -> GlyphBitmap {
+        let mut contours = Vec::new();
+        if self.outline(glyph, 0, &mut contours).is_none() {
+            contours.clear();
+        }
+        let scale = size / self.units_per_em;
+        // Pixel coordinates with y down, still relative to the pen
+        let polygons: Vec<Vec<(f32, f32)>> = contours.iter()
+            .map(|contour| flatten(contour).into_iter().map(|(x, y)| (x * scale, -y * scale)).collect())
+            .collect();
+
+        let points = polygons.iter().flatten();
+        let (mut min_x, mut min_y, mut max_x, mut max_y) = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+        for &(x, y) in points {
+            (min_x, min_y, max_x, max_y) = (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y));
+        }
+        if min_x > max_x {
+            return GlyphBitmap { width: 0, height: 0, left: 0, top: 0, coverage: Vec::new() };
+        }
+        let (left, top) = (min_x.floor() as i32, min_y.floor() as i32);
+        let width = (max_x.ceil() as i32 - left).max(1) as usize;
+        let height = (max_y.ceil() as i32 - top).max(1) as usize;
+
+        let edges: Vec<Edge> = polygons.iter()
+            .flat_map(|polygon| {
+                let shift = |(x, y): (f32, f32)| (x - left as f32, y - top as f32);
+                polygon.iter().zip(polygon.iter().cycle().skip(1)).map(move |(&a, &b)| (shift(a), shift(b)))
+            })
+            .collect();
+        let coverage = fill(&edges, width, height);
+        GlyphBitmap { width, height, left, top, coverage }
+    }
+
+    // Appends the contours of a glyph, following the parts of composite ones
+    fn outline(&self, glyph: u16, depth: u32, contours: &mut Vec<Vec<Point>>) -> Option<()> {
+        let index = glyph as usize;
+        let (start, end) = if self.long_offsets {
+            (u32_at(&self.data, self.loca + index * 4)? as usize, u32_at(&self.data, self.loca + index * 4 + 4)? as usize)
+        } else {
+            (u16_at(&self.data, self.loca + index * 2)? as usize * 2, u16_at(&self.data, self.loca + index * 2 + 2)? as usize * 2)
+        };
+        if end <= start {
+            return Some(());
+        }
+        let data = self.data.get(self.glyf + start..self.glyf + end)?;
+        let contour_count = i16_at(data, 0)?;
+        if contour_count >= 0 {
+            simple_outline(data, contour_count as usize, contours)
+        } else if depth < MAX_COMPOSITE_DEPTH {
+            self.composite_outline(data, depth, contours)
+        } else {
+            None
+        }
+    }
+
+    fn composite_outline(&self, data: &[u8], depth: u32, contours: &mut Vec<Vec<Point>>) -> Option<()> {
+        let mut offset = 10;
+        loop {
+            let flags = u16_at(data, offset)?;
+            let glyph = u16_at(data, offset + 2)?;
+            offset += 4;
+            let (dx, dy) = if flags & ARGS_ARE_WORDS != 0 {
+                offset += 4;
+                (i16_at(data, offset - 4)? as f32, i16_at(data, offset - 2)? as f32)
+            } else {
+                offset += 2;
+                (*data.get(offset - 2)? as i8 as f32, *data.get(offset - 1)? as i8 as f32)
+            };
+            // Parts placed by matching points rather than by offset are left where they are
+            let (dx, dy) = if flags & ARGS_ARE_XY_VALUES != 0 { (dx, dy) } else { (0.0, 0.0) };
+            let (mut a, mut b, mut c, mut d) = (1.0, 0.0, 0.0, 1.0);
+            if flags & HAS_SCALE != 0 {
+                a = f2dot14_at(data, offset)?;
+                d = a;
+                offset += 2;
+            } else if flags & HAS_XY_SCALE != 0 {
+                (a, d) = (f2dot14_at(data, offset)?, f2dot14_at(data, offset + 2)?);
+                offset += 4;
+            } else if flags & HAS_TWO_BY_TWO != 0 {
+                (a, b) = (f2dot14_at(data, offset)?, f2dot14_at(data, offset + 2)?);
+                (c, d) = (f2dot14_at(data, offset + 4)?, f2dot14_at(data, offset + 6)?);
+                offset += 8;
+            }
+
+            let mut parts = Vec::new();
+            self.outline(glyph, depth + 1, &mut parts)?;
+            for mut contour in parts {
+                for point in &mut contour {
+                    (point.x, point.y) = (a * point.x + c * point.y + dx, b * point.x + d * point.y + dy);
+                }
+                contours.push(contour);
+            }
+
+            if flags & MORE_COMPONENTS == 0 {
+                return Some(());
+            }
+        }
+    }
+}
+
+// Picks the best Unicode subtable of the character map
+fn find_character_map(data: &[u8], cmap: usize) -> Option<CharacterMap> {
+    let count = u16_at(data, cmap + 2)? as usize;
+    let mut segments = None;
+    for record in (0..count).map(|index| cmap + 4 + index * 8) {
+        let (platform, encoding) = (u16_at(data, record)?, u16_at(data, record + 2)?);
+        let subtable = cmap + u32_at(data, record + 4)? as usize;
+        let unicode = platform == 0 || (platform == 3 && (encoding == 1 || encoding == 10));
+        match u16_at(data, subtable)? {
+            12 if unicode => return Some(CharacterMap::Groups(subtable)),
+            4 if unicode => segments = Some(CharacterMap::Segments(subtable)),
+            _ => {},
+        }
+    }
+    segments
+}
+
+fn segment_glyph(data: &[u8], subtable: usize, code: u32) -> Option<u16> {
+    let code = u16::try_from(code).ok()?;
+    let segment_count = u16_at(data, subtable + 6)? as usize / 2;
+    let end_codes = subtable + 14;
+    let start_codes = end_codes + segment_count * 2 + 2;
+    let deltas = start_codes + segment_count * 2;
+    let range_offsets = deltas + segment_count * 2;
+
+    let segment = (0..segment_count).find(|&segment| u16_at(data, end_codes + segment * 2).is_some_and(|end| end >= code))?;
+    let start = u16_at(data, start_codes + segment * 2)?;
+    if start > code {
+        return None;
+    }
+    let delta = u16_at(data, deltas + segment * 2)?;
+    let range_offset_at = range_offsets + segment * 2;
+    let range_offset = u16_at(data, range_offset_at)? as usize;
+    if range_offset == 0 {
+        return Some(code.wrapping_add(delta));
+    }
+    // The offset is relative to where it is stored, pointing into the glyph array
+    let glyph = u16_at(data, range_offset_at + range_offset + (code - start) as usize * 2)?;
+    (glyph != 0).then(|| glyph.wrapping_add(delta))
+}
+
+fn group_glyph(data: &[u8], subtable: usize, code: u32) -> Option<u16> {
+    let group_count = u32_at(data, subtable + 12)? as usize;
+    (0..group_count)
+        .map(|index| subtable + 16 + index * 12)
+        .find_map(|group| {
+            let (start, end) = (u32_at(data, group)?, u32_at(data, group + 4)?);
+            (start..=end).contains(&code).then(|| u32_at(data, group + 8).map(|glyph| (glyph + code - start) as u16))?
+        })
+}
+
+fn simple_outline(data: &[u8], contour_count: usize, contours: &mut Vec<Vec<Point>>) -> Option<()> {
+    let end_points: Vec<usize> = (0..contour_count)
+        .map(|index| u16_at(data, 10 + index * 2).map(|end| end as usize))
+        .collect::<Option<_>>()?;
+    let Some(&last) = end_points.last() else {
+        return Some(());
+    };
+    let point_count = last + 1;
+    let mut offset = 10 + contour_count * 2;
+    offset += 2 + u16_at(data, offset)? as usize;
+
+    // Flags, where a repeat flag says how many more times the flag applies
+    let mut flags = Vec::with_capacity(point_count);
+    while flags.len() < point_count {
+        let flag = *data.get(offset)?;
+        offset += 1;
+        flags.push(flag);
+        if flag & 0x08 != 0 {
+            let repeat = *data.get(offset)?;
+            offset += 1;
+            flags.extend(std::iter::repeat_n(flag, repeat as usize));
+        }
+    }
+    flags.truncate(point_count);
+
+    // Coordinates are deltas from the previous point: a byte with a sign flag,
+    // nothing when unchanged, or a full word
+    let mut read_coordinates = |short: u8, same_or_positive: u8| -> Option<Vec<f32>> {
+        let mut value = 0i32;
+        let mut values = Vec::with_capacity(point_count);
+        for &flag in &flags {
+            if flag & short != 0 {
+                let delta = *data.get(offset)? as i32;
+                offset += 1;
+                value += if flag & same_or_positive != 0 { delta } else { -delta };
+            } else if flag & same_or_positive == 0 {
+                value += i16_at(data, offset)? as i32;
+                offset += 2;
+            }
+            values.push(value as f32);
+        }
+        Some(values)
+    };
+    let xs = read_coordinates(0x02, 0x10)?;
+    let ys = read_coordinates(0x04, 0x20)?;
+
+    let mut start = 0;
+    for end in end_points {
+        if end < start || end >= point_count {
+            return None;
+        }
+        contours.push((start..=end).map(|i| Point { x: xs[i], y: ys[i], on_curve: flags[i] & 0x01 != 0 }).collect());
+        start = end + 1;
+    }
+    Some(())
+}
+
+// Turns a contour of on-curve points and quadratic control points into a
+// polygon, in font units
+fn flatten(contour: &[Point]) -> Vec<(f32, f32)> {
+    // Two control points in a row imply an on-curve point halfway between them
+    let mut points = Vec::with_capacity(contour.len() * 2);
+    for (i, &point) in contour.iter().enumerate() {
+        let next = contour[(i + 1) % contour.len()];
+        points.push(point);
+        if !point.on_curve && !next.on_curve {
+            points.push(Point { x: (point.x + next.x) / 2.0, y: (point.y + next.y) / 2.0, on_curve: true });
+        }
+    }
+    let Some(first) = points.iter().position(|point| point.on_curve) else {
+        return Vec::new();
+    };
+    points.rotate_left(first);
+
+    let count = points.len();
+    let mut polygon = vec![(points[0].x, points[0].y)];
+    let mut i = 1;
+    while i <= count {
+        let point = points[i % count];
+        if point.on_curve {
+            polygon.push((point.x, point.y));
+            i += 1;
+        } else {
+            let (start, end) = (*polygon.last().unwrap(), points[(i + 1) % count]);
+            for step in 1..=CURVE_STEPS {
+                let t = step as f32 / CURVE_STEPS as f32;
+                let (a, b, c) = ((1.0 - t) * (1.0 - t), 2.0 * (1.0 - t) * t, t * t);
+                polygon.push((a * start.0 + b * point.x + c * end.x, a * start.1 + b * point.y + c * end.y));
+            }
+            i += 2;
+        }
+    }
+    // The last point closes back onto the first
+    polygon.pop();
+    polygon
+}
+
+// Fills a shape given by its edges with the nonzero rule, measuring how much
+// of each pixel it covers on a few lines through every row
+fn fill(edges: &[Edge], width: usize, height: usize) -> Vec<u8> {
+    let mut coverage = vec![0.0f32; width * height];
+    let mut crossings: Vec<(f32, i32)> = Vec::new();
+    for row in 0..height {
+        for sample in 0..SUBSAMPLES {
+            let y = row as f32 + (sample as f32 + 0.5) / SUBSAMPLES as f32;
+            crossings.clear();
+            for &((x0, y0), (x1, y1)) in edges {
+                if (y0 <= y) != (y1 <= y) {
+                    let x = x0 + (y - y0) / (y1 - y0) * (x1 - x0);
+                    crossings.push((x, if y1 > y0 { 1 } else { -1 }));
+                }
+            }
+            crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+            let mut winding = 0;
+            let mut span_start = 0.0;
+            for &(x, direction) in &crossings {
+                let was_inside = winding != 0;
+                winding += direction;
+                if !was_inside && winding != 0 {
+                    span_start = x;
+                } else if was_inside && winding == 0 {
+                    add_span(&mut coverage[row * width..(row + 1) * width], span_start, x);
+                }
+            }
+        }
+    }
+    coverage.iter().map(|&value| (value / SUBSAMPLES as f32 * 255.0).round().min(255.0) as u8).collect()
+}
+
+// Adds the part of each pixel of a row that lies between `start` and `end`
+fn add_span(row: &mut [f32], start: f32, end: f32) {
+    let (start, end) = (start.max(0.0), end.min(row.len() as f32));
+    if end <= start {
+        return;
+    }
+    for pixel in start.floor() as usize..(end.ceil() as usize).min(row.len()) {
+        let left = pixel as f32;
+        row[pixel] += end.min(left + 1.0) - start.max(left);
+    }
+}
@@ -0,0 +1,87 @@
+use crate::math::{Mat4, Vec3};
+
+// Blocks from the middle of the screen to its top edge in the orthographic views
+const ORTHOGRAPHIC_HALF_HEIGHT: f32 = 16.0;
+const ISOMETRIC_HALF_HEIGHT: f32 = 32.0;
+// How far back from the player the isometric camera sits, so the ground around
+// them is in front of it
+const ISOMETRIC_DISTANCE: f32 = 160.0;
+// Clip planes of every view
+const NEAR: f32 = 0.1;
+const FAR: f32 = 1000.0;
+
+/// How the world is projected on screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViewMode {
+    /// The usual first-person view.
+    Perspective,
+    /// The first-person view without perspective, so sizes don't shrink with
+    /// distance.
+    Orthographic,
+    /// A fixed orthographic view from above at 45 degrees around the player,
+    /// for map-style screenshots and planning builds.
+    Isometric,
+}
+
+impl ViewMode {
+    /// The mode after this one, wrapping back to the first.
+    pub fn next(self) -> Self {
+        match self {
+            ViewMode::Perspective => ViewMode::Orthographic,
+            ViewMode::Orthographic => ViewMode::Isometric,
+            ViewMode::Isometric => ViewMode::Perspective,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ViewMode::Perspective => "perspective",
+            ViewMode::Orthographic => "orthographic",
+            ViewMode::Isometric => "isometric",
+        }
+    }
+
+    /// Whether the view is the player's own, with perspective and a sky.
+    pub fn is_perspective(self) -> bool {
+        self == ViewMode::Perspective
+    }
+
+    /// Where the camera is and the way it looks, given the player's eye and
+    /// the way they look.
+    pub fn camera(self, eye: Vec3, front: Vec3) -> (Vec3, Vec3) {
+        match self {
+            ViewMode::Perspective | ViewMode::Orthographic => (eye, front),
+            ViewMode::Isometric => {
+                let direction = isometric_direction();
+                (eye - direction * ISOMETRIC_DISTANCE, direction)
+            },
+        }
+    }
+
+    /// How much further the camera is from what it looks at than the player,
+    /// which fog distances are pushed back by.
+    pub fn camera_distance(self) -> f32 {
+        match self {
+            ViewMode::Perspective | ViewMode::Orthographic => 0.0,
+            ViewMode::Isometric => ISOMETRIC_DISTANCE,
+        }
+    }
+
+    /// The projection of the view, for a vertical field of view in degrees and
+    /// a width to height ratio.
+    pub fn projection(self, fov_degrees: f32, aspect: f32) -> Mat4 {
+        let half_height = match self {
+            ViewMode::Perspective => return Mat4::perspective(fov_degrees.to_radians(), aspect, NEAR, FAR),
+            ViewMode::Orthographic => ORTHOGRAPHIC_HALF_HEIGHT,
+            ViewMode::Isometric => ISOMETRIC_HALF_HEIGHT,
+        };
+        let half_width = half_height * aspect;
+        Mat4::orthographic(-half_width, half_width, -half_height, half_height, NEAR, FAR)
+    }
+}
+
+// The way the isometric camera looks: from the north-west, down at the angle
+// where the three axes of a block look the same length
+fn isometric_direction() -> Vec3 {
+    Vec3::new(1.0, -1.0, 1.0).normalize()
+}
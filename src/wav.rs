@@ -0,0 +1,99 @@
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Reads the samples of a WAV file a little at a time, so long tracks never
+/// have to be in memory whole. Handles 16-bit and 32-bit float PCM, mono or
+/// stereo.
+pub struct WavStream {
+    reader: BufReader<File>,
+    channels: u16,
+    float: bool,
+    sample_rate: u32,
+    // Bytes of sample data not yet read
+    remaining: u32,
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+impl WavStream {
+    /// Opens a file and reads its header, leaving it at the start of the samples.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut header = [0u8; 12];
+        reader.read_exact(&mut header)?;
+        if &header[0..4] != b"RIFF" || &header[8..12] != b"WAVE" {
+            return Err(invalid("Not a WAV file"));
+        }
+
+        // (channels, is float, sample rate), from the fmt chunk
+        let mut format = None;
+        loop {
+            let mut chunk = [0u8; 8];
+            reader.read_exact(&mut chunk)?;
+            let size = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]);
+            match &chunk[0..4] {
+                b"fmt " => {
+                    let mut fmt = vec![0u8; size as usize];
+                    reader.read_exact(&mut fmt)?;
+                    if fmt.len() < 16 {
+                        return Err(invalid("Truncated fmt chunk"));
+                    }
+                    let tag = u16::from_le_bytes([fmt[0], fmt[1]]);
+                    let channels = u16::from_le_bytes([fmt[2], fmt[3]]);
+                    let sample_rate = u32::from_le_bytes([fmt[4], fmt[5], fmt[6], fmt[7]]);
+                    let bits = u16::from_le_bytes([fmt[14], fmt[15]]);
+                    let float = match (tag, bits) {
+                        (1, 16) => false,
+                        (3, 32) => true,
+                        _ => return Err(invalid("Only 16-bit and 32-bit float PCM are supported")),
+                    };
+                    if !(1..=2).contains(&channels) {
+                        return Err(invalid("Only mono and stereo are supported"));
+                    }
+                    format = Some((channels, float, sample_rate));
+                },
+                b"data" => {
+                    let (channels, float, sample_rate) = format.ok_or_else(|| invalid("Samples before the fmt chunk"))?;
+                    return Ok(Self { reader, channels, float, sample_rate, remaining: size });
+                },
+                // Chunks are padded to an even size
+                _ => {
+                    reader.seek(SeekFrom::Current(i64::from(size + size % 2)))?;
+                },
+            }
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Appends up to `frames` frames to `out` as interleaved stereo, and returns
+    /// how many were read. Fewer than asked means the end of the file.
+    pub fn read_frames(&mut self, out: &mut Vec<f32>, frames: usize) -> io::Result<usize> {
+        let sample_size = if self.float { 4 } else { 2 };
+        let frame_size = sample_size * self.channels as usize;
+        let wanted = (frames * frame_size).min(self.remaining as usize) / frame_size * frame_size;
+        let mut bytes = vec![0u8; wanted];
+        self.reader.read_exact(&mut bytes)?;
+        self.remaining -= wanted as u32;
+
+        for frame in bytes.chunks_exact(frame_size) {
+            let sample = |index: usize| {
+                let bytes = &frame[index * sample_size..(index + 1) * sample_size];
+                if self.float {
+                    f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+                } else {
+                    i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 32768.0
+                }
+            };
+            let left = sample(0);
+            let right = if self.channels == 2 { sample(1) } else { left };
+            out.extend_from_slice(&[left, right]);
+        }
+        Ok(wanted / frame_size)
+    }
+}
@@ -0,0 +1,202 @@
+use crate::asset_files;
+use crate::block::{BlockTexture, BIOME_COLORMAP_PATH};
+use crate::clock::Instant;
+use crate::error::EngineError;
+use crate::events::EventBus;
+use crate::frame_uniforms::FrameUniforms;
+use crate::light;
+use crate::logging;
+use crate::math::Vec3;
+use crate::mesh_cache::MeshCache;
+use crate::platform::{self, ActivePlatform, Event, Graphics, Key, MouseButton, Platform};
+use crate::renderer::Renderer;
+use crate::settings::Settings;
+use crate::sky;
+use crate::streaming::ChunkStreamer;
+use crate::view_mode::ViewMode;
+use crate::wgpu_renderer::{WgpuRenderer, SHADER_PATH};
+use crate::world::{World, CHUNK_SIZE, WORLD_SEED};
+use crate::{projection_matrix, sky_color, Camera, FOG_START, WINDOW_HEIGHT, WINDOW_WIDTH};
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
+
+// Chunks are generated and meshed on the page's one thread, so fewer of them
+// load per frame, and not as far, as on the desktop
+const COLUMNS_PER_FRAME: usize = 1;
+const RENDER_DISTANCE: i32 = 6;
+// The sun stays at noon
+const TIME_OF_DAY: u32 = 6000;
+// How fast the camera flies, in blocks per second
+const FLY_SPEED: f32 = 20.0;
+// Longest frame the camera moves for, in seconds, so a stalled page doesn't
+// fling it away
+const MAX_FRAME_TIME: f32 = 0.1;
+
+/// Starts the browser build once the page has loaded it: fetches the assets,
+/// then flies over the terrain, a frame each time the page draws one. Errors
+/// are logged and shown in an alert.
+pub fn start() {
+    logging::init(false);
+    // Panics would only show as an unreachable instruction otherwise
+    std::panic::set_hook(Box::new(|info| log::error!(target: "game", "{}", info)));
+    wasm_bindgen_futures::spawn_local(async {
+        match Demo::open().await {
+            Ok(demo) => run_frames(demo),
+            Err(e) => {
+                log::error!(target: e.category(), "{}", e);
+                platform::show_error("The game couldn't start", &e.to_string());
+            },
+        }
+    });
+}
+
+// The terrain demo the browser plays: a camera flying over the generated
+// world, drawn with wgpu. The rest of the game needs a loop of its own and
+// OpenGL, neither of which the browser has
+struct Demo {
+    platform: ActivePlatform,
+    renderer: WgpuRenderer,
+    world: World,
+    streamer: ChunkStreamer,
+    event_bus: EventBus,
+    mesh_cache: MeshCache,
+    settings: Settings,
+    camera: Camera,
+    // Whether the mouse turns the camera, once a click has locked it
+    looking: bool,
+    started: Instant,
+    last_frame: Instant,
+}
+
+impl Demo {
+    async fn open() -> Result<Self, EngineError> {
+        let block_texture_paths = BlockTexture::ALL.map(|texture| texture.path());
+        let mut paths = vec![SHADER_PATH, BIOME_COLORMAP_PATH];
+        paths.extend(block_texture_paths);
+        asset_files::fetch(&paths).await.map_err(EngineError::io("Failed to fetch the assets"))?;
+
+        let platform = ActivePlatform::open("Terrain demo", WINDOW_WIDTH, WINDOW_HEIGHT, false, Graphics::Surface)?;
+        let mut renderer = WgpuRenderer::open(platform.window(), (WINDOW_WIDTH, WINDOW_HEIGHT), &block_texture_paths, BIOME_COLORMAP_PATH)
+            .await
+            .map_err(EngineError::Wgpu)?;
+        // Saves and settings are files, which the browser doesn't have
+        let settings = Settings::default();
+        let mut world = World::new(WORLD_SEED);
+        world.fancy_leaves = settings.fancy_leaves;
+        renderer.set_fast_leaves(!settings.fancy_leaves);
+        let now = Instant::now();
+        Ok(Self {
+            platform,
+            renderer,
+            world,
+            streamer: ChunkStreamer::new(RENDER_DISTANCE),
+            event_bus: EventBus::new(),
+            mesh_cache: MeshCache::disabled(),
+            settings,
+            camera: Camera::new(),
+            looking: false,
+            started: now,
+            last_frame: now,
+        })
+    }
+
+    fn frame(&mut self) {
+        let now = Instant::now();
+        let delta_time = (now - self.last_frame).as_secs_f32().min(MAX_FRAME_TIME);
+        self.last_frame = now;
+
+        for event in self.platform.poll_events() {
+            match event {
+                // The page only lets go of the mouse on a click
+                Event::MouseButtonDown { button: MouseButton::Left, .. } if !self.looking => {
+                    self.platform.set_relative_mouse_mode(true);
+                    self.looking = true;
+                },
+                Event::KeyDown { physical: Some(Key::Escape), .. } | Event::FocusLost if self.looking => {
+                    self.platform.set_relative_mouse_mode(false);
+                    self.looking = false;
+                },
+                Event::MouseMotion { xrel, yrel } if self.looking => {
+                    let camera = &mut self.camera;
+                    camera.yaw += xrel as f32 * self.settings.mouse_sensitivity;
+                    camera.pitch = (camera.pitch - yrel as f32 * self.settings.mouse_sensitivity).clamp(-89.0, 89.0);
+                    camera.update_camera_vectors();
+                },
+                _ => {},
+            }
+        }
+
+        // Flying goes where the camera looks, with space and shift for up and down
+        let camera = &mut self.camera;
+        let right = camera.front.cross(&camera.up).normalize();
+        let directions = [
+            (Key::W, camera.front),
+            (Key::S, camera.front * -1.0),
+            (Key::D, right),
+            (Key::A, right * -1.0),
+            (Key::Space, camera.up),
+            (Key::LShift, camera.up * -1.0),
+        ];
+        let direction = directions.into_iter()
+            .filter(|&(key, _)| self.platform.is_key_held(key))
+            .fold(Vec3::zero(), |sum, (_, direction)| sum + direction);
+        camera.previous_position = camera.position;
+        if direction.length() > 0.0 {
+            camera.position = camera.position + direction.normalize() * (FLY_SPEED * delta_time);
+        }
+
+        let view_mode = ViewMode::Perspective;
+        let viewer = self.camera.viewer(view_mode, self.settings.fov);
+        let update = self.streamer.update(&mut self.world, &viewer, COLUMNS_PER_FRAME, &self.mesh_cache, &mut self.event_bus);
+        // Nothing here follows the chunk pipeline's progress
+        self.event_bus.clear();
+        for &position in &update.unloaded {
+            self.renderer.remove_chunk(position);
+        }
+        for &position in &update.remeshed {
+            self.renderer.queue_upload(position);
+        }
+        self.renderer.upload_queued(&self.world, self.settings.upload_budget_bytes());
+
+        let daylight = light::daylight(TIME_OF_DAY);
+        let fog_end = (self.streamer.loaded_distance() * CHUNK_SIZE as i32) as f32;
+        self.renderer.set_frame_uniforms(&FrameUniforms {
+            view: self.camera.get_view_matrix(1.0, Vec3::zero(), view_mode),
+            projection: projection_matrix(self.settings.fov, view_mode),
+            camera_position: self.camera.position,
+            time: (now - self.started).as_secs_f32(),
+            sun_direction: sky::sun_direction(light::sun_angle(TIME_OF_DAY)),
+            daylight,
+            fog_color: sky_color(daylight),
+            fog_start: fog_end * FOG_START,
+            fog_end,
+        });
+        self.renderer.draw_opaque();
+        self.renderer.draw_water();
+        self.renderer.present();
+    }
+}
+
+// Plays a frame of the demo each time the page draws one, for as long as the
+// page is open
+fn run_frames(mut demo: Demo) {
+    // The callback asks for the next frame itself, so it holds on to itself
+    let callback = Rc::new(RefCell::new(None::<Closure<dyn FnMut()>>));
+    let next = Rc::clone(&callback);
+    *callback.borrow_mut() = Some(Closure::new(move || {
+        demo.frame();
+        request_animation_frame(next.borrow().as_ref());
+    }));
+    request_animation_frame(callback.borrow().as_ref());
+}
+
+fn request_animation_frame(callback: Option<&Closure<dyn FnMut()>>) {
+    let (Some(window), Some(callback)) = (web_sys::window(), callback) else {
+        return;
+    };
+    if let Err(e) = window.request_animation_frame(callback.as_ref().unchecked_ref()) {
+        log::error!(target: "render", "Failed to ask for the next frame: {:?}", e);
+    }
+}
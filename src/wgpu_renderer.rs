@@ -0,0 +1,540 @@
+use crate::asset_files;
+use crate::clock::Instant;
+use crate::frame_uniforms::{FrameUniforms, BLOCK_SIZE};
+use crate::point_lights::{PointLights, MAX_POINT_LIGHTS};
+use crate::renderer::Renderer;
+use crate::world::World;
+use crate::Vertex;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::Duration;
+use wgpu::rwh::{HasDisplayHandle, HasWindowHandle};
+use wgpu::util::DeviceExt;
+
+/// WGSL source of the block program, the counterpart of block.vert and block.frag.
+pub const SHADER_PATH: &str = "src/assets/shaders/block.wgsl";
+// How long a newly loaded chunk takes to fade in, as in `ChunkRenderer`
+const FADE_IN_DURATION: Duration = Duration::from_millis(400);
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+type ChunkUniforms = [f32; 4];  // origin x, y, z, alpha
+// What block.frag takes as plain uniforms, in 32-bit words: the light count
+// and whether leaves are fast, padded to a vec4, then the lights' positions
+// and their colors with the radius, a vec4 each
+const BLOCK_UNIFORM_WORDS: usize = 4 + 2 * 4 * MAX_POINT_LIGHTS;
+type BlockUniforms = [u32; BLOCK_UNIFORM_WORDS];
+// Where whether leaves are fast is, in bytes
+const FAST_LEAVES_OFFSET: u64 = 4;
+
+// A chunk's mesh on the GPU, with the uniforms placing it in the world
+struct ChunkBuffers {
+    vertices: wgpu::Buffer,
+    // Index buffers of the opaque faces and the water, with their index counts
+    opaque: Option<(wgpu::Buffer, u32)>,
+    water: Option<(wgpu::Buffer, u32)>,
+    origin: [f32; 3],
+    uniforms: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+// The surface texture being drawn this frame
+struct Frame {
+    texture: wgpu::SurfaceTexture,
+    view: wgpu::TextureView,
+}
+
+/// The wgpu backend, drawing chunks through Vulkan, Metal or DX12, or WebGL2
+/// in the browser, onto a window with no OpenGL context. Each chunk gets buffers of its own rather
+/// than a place in shared streaming buffers.
+pub struct WgpuRenderer {
+    surface: wgpu::Surface<'static>,
+    // Applied again when the surface gets out of date
+    surface_config: wgpu::SurfaceConfiguration,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::RenderPipeline,
+    chunk_layout: wgpu::BindGroupLayout,
+    frame_uniforms: wgpu::Buffer,
+    block_uniforms: wgpu::Buffer,
+    fast_leaves: bool,
+    frame_bind_group: wgpu::BindGroup,
+    depth_view: wgpu::TextureView,
+    clear_color: wgpu::Color,
+    chunks: HashMap<(i32, i32, i32), ChunkBuffers>,
+    // When each chunk was first uploaded
+    loaded_at: HashMap<(i32, i32, i32), Instant>,
+    // Chunks waiting for an upload, in the order they were queued
+    upload_queue: VecDeque<(i32, i32, i32)>,
+    queued: HashSet<(i32, i32, i32)>,
+    frame: Option<Frame>,
+}
+
+impl WgpuRenderer {
+    /// Sets up a device drawing to `window`, which is `width` by `height` pixels
+    /// and has to outlive the renderer, with the block textures as layers of one
+    /// texture array and the biome colormap.
+    pub fn new(window: &(impl HasWindowHandle + HasDisplayHandle), size: (u32, u32), block_texture_paths: &[&str], colormap_path: &str) -> Result<Self, String> {
+        pollster::block_on(Self::open(window, size, block_texture_paths, colormap_path))
+    }
+
+    /// Like `new`, waiting for the adapter and device without blocking, as the
+    /// browser needs.
+    pub async fn open(window: &(impl HasWindowHandle + HasDisplayHandle), (width, height): (u32, u32), block_texture_paths: &[&str], colormap_path: &str) -> Result<Self, String> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        // Callers keep the window alive for as long as the renderer
+        let surface = unsafe {
+            let target = wgpu::SurfaceTargetUnsafe::from_window(window).map_err(|e| e.to_string())?;
+            instance.create_surface_unsafe(target).map_err(|e| e.to_string())?
+        };
+        let adapter = instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            force_fallback_adapter: false,
+            compatible_surface: Some(&surface),
+        }).await.ok_or("No graphics adapter can draw to the window")?;
+        // The chunks need no more than WebGL2 offers, so any adapter will do
+        let descriptor = wgpu::DeviceDescriptor {
+            required_limits: wgpu::Limits::downlevel_webgl2_defaults().using_resolution(adapter.limits()),
+            ..Default::default()
+        };
+        let (device, queue) = adapter.request_device(&descriptor, None).await.map_err(|e| e.to_string())?;
+        let info = adapter.get_info();
+        log::info!(target: "render", "Drawing with {} through {:?}", info.name, info.backend);
+
+        // Colors are used as they are, like the OpenGL framebuffer does
+        let capabilities = surface.get_capabilities(&adapter);
+        let format = capabilities.formats.iter().copied()
+            .find(|format| !format.is_srgb())
+            .or(capabilities.formats.first().copied())
+            .ok_or("The window has no surface formats")?;
+        let surface_config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format,
+            width,
+            height,
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: wgpu::CompositeAlphaMode::Auto,
+            view_formats: Vec::new(),
+            desired_maximum_frame_latency: 2,
+        };
+        surface.configure(&device, &surface_config);
+        let depth_view = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("depth"),
+            size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        }).create_view(&wgpu::TextureViewDescriptor::default());
+
+        let block_textures = load_texture_array(&device, &queue, block_texture_paths)?;
+        let colormap = load_texture_array(&device, &queue, &[colormap_path])?;
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            ..Default::default()
+        });
+        let frame_uniforms = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("frame uniforms"),
+            size: BLOCK_SIZE as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let block_uniforms = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("block uniforms"),
+            contents: bytemuck::cast_slice(&[0u32; BLOCK_UNIFORM_WORDS]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let visibility = wgpu::ShaderStages::VERTEX_FRAGMENT;
+        let uniform_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: None },
+            count: None,
+        };
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2Array,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let frame_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("frame"),
+            entries: &[
+                uniform_entry(0),
+                texture_entry(1),
+                texture_entry(2),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                uniform_entry(4),
+            ],
+        });
+        let chunk_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("chunk"),
+            entries: &[uniform_entry(0)],
+        });
+        let frame_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("frame"),
+            layout: &frame_layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: frame_uniforms.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(&block_textures) },
+                wgpu::BindGroupEntry { binding: 2, resource: wgpu::BindingResource::TextureView(&colormap) },
+                wgpu::BindGroupEntry { binding: 3, resource: wgpu::BindingResource::Sampler(&sampler) },
+                wgpu::BindGroupEntry { binding: 4, resource: block_uniforms.as_entire_binding() },
+            ],
+        });
+
+        let source = asset_files::read_to_string(SHADER_PATH).map_err(|e| format!("Failed to read {}: {}", SHADER_PATH, e))?;
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("block"),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("block"),
+            bind_group_layouts: &[&frame_layout, &chunk_layout],
+            push_constant_ranges: &[],
+        });
+        // One pipeline for both passes: water blends over what's drawn before
+        // it, and new chunks blend in as they fade
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("block"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                compilation_options: Default::default(),
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<Vertex>() as u64,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Uint32x2],
+                }],
+            },
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: Default::default(),
+                bias: Default::default(),
+            }),
+            multisample: Default::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+            cache: None,
+        });
+
+        Ok(Self {
+            surface,
+            surface_config,
+            device,
+            queue,
+            pipeline,
+            chunk_layout,
+            frame_uniforms,
+            block_uniforms,
+            fast_leaves: false,
+            frame_bind_group,
+            depth_view,
+            clear_color: wgpu::Color::BLACK,
+            chunks: HashMap::new(),
+            loaded_at: HashMap::new(),
+            upload_queue: VecDeque::new(),
+            queued: HashSet::new(),
+            frame: None,
+        })
+    }
+
+    // Replaces a chunk's buffers with its current mesh
+    fn write_mesh(&mut self, world: &World, position: (i32, i32, i32)) {
+        self.chunks.remove(&position);
+        let Some(chunk) = world.chunk(position) else {
+            self.loaded_at.remove(&position);
+            return;
+        };
+        self.loaded_at.entry(position).or_insert_with(Instant::now);
+        let mesh = &chunk.mesh;
+        if mesh.indices.is_empty() && mesh.water_indices.is_empty() {
+            return;
+        }
+
+        let device = &self.device;
+        let vertices = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("chunk vertices"),
+            contents: bytemuck::cast_slice(&mesh.vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = |indices: &[crate::TriIndexes]| (!indices.is_empty()).then(|| {
+            let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("chunk indices"),
+                contents: bytemuck::cast_slice(indices),
+                usage: wgpu::BufferUsages::INDEX,
+            });
+            (buffer, (indices.len() * 3) as u32)
+        });
+        let opaque = index_buffer(&mesh.indices);
+        let water = index_buffer(&mesh.water_indices);
+        let uniforms = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("chunk uniforms"),
+            size: std::mem::size_of::<ChunkUniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("chunk"),
+            layout: &self.chunk_layout,
+            entries: &[wgpu::BindGroupEntry { binding: 0, resource: uniforms.as_entire_binding() }],
+        });
+        self.chunks.insert(position, ChunkBuffers { vertices, opaque, water, origin: mesh.origin, uniforms, bind_group });
+    }
+
+    // Draws one index buffer of every chunk in a render pass, clearing the
+    // frame first for the opaque pass
+    fn draw_pass(&mut self, clear: bool, indices: impl Fn(&ChunkBuffers) -> Option<&(wgpu::Buffer, u32)>) {
+        if self.frame.is_none() {
+            let texture = match self.surface.get_current_texture() {
+                Ok(texture) => texture,
+                // The window changed under the surface, which is set up again for the next frame
+                Err(e @ (wgpu::SurfaceError::Outdated | wgpu::SurfaceError::Lost)) => {
+                    log::warn!(target: "render", "Skipping a frame: {}", e);
+                    self.surface.configure(&self.device, &self.surface_config);
+                    return;
+                },
+                Err(e) => {
+                    log::warn!(target: "render", "Skipping a frame: {}", e);
+                    return;
+                },
+            };
+            let view = texture.texture.create_view(&wgpu::TextureViewDescriptor::default());
+            self.frame = Some(Frame { texture, view });
+        }
+        let Some(frame) = &self.frame else {
+            return;
+        };
+
+        let now = Instant::now();
+        for (position, chunk) in &self.chunks {
+            let age = self.loaded_at.get(position).map_or(FADE_IN_DURATION, |&loaded_at| now - loaded_at);
+            let alpha = (age.as_secs_f32() / FADE_IN_DURATION.as_secs_f32()).min(1.0);
+            let [x, y, z] = chunk.origin;
+            let uniforms: ChunkUniforms = [x, y, z, alpha];
+            self.queue.write_buffer(&chunk.uniforms, 0, bytemuck::cast_slice(&uniforms));
+        }
+
+        let (color_load, depth_load) = if clear {
+            (wgpu::LoadOp::Clear(self.clear_color), wgpu::LoadOp::Clear(1.0))
+        } else {
+            (wgpu::LoadOp::Load, wgpu::LoadOp::Load)
+        };
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("chunks") });
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("chunks"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &frame.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations { load: color_load, store: wgpu::StoreOp::Store },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_view,
+                    depth_ops: Some(wgpu::Operations { load: depth_load, store: wgpu::StoreOp::Store }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &self.frame_bind_group, &[]);
+            for chunk in self.chunks.values() {
+                let Some((index_buffer, count)) = indices(chunk) else {
+                    continue;
+                };
+                pass.set_bind_group(1, &chunk.bind_group, &[]);
+                pass.set_vertex_buffer(0, chunk.vertices.slice(..));
+                pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                pass.draw_indexed(0..*count, 0, 0..1);
+            }
+        }
+        self.queue.submit([encoder.finish()]);
+    }
+}
+
+impl Renderer for WgpuRenderer {
+    fn set_frame_uniforms(&mut self, uniforms: &FrameUniforms) {
+        self.queue.write_buffer(&self.frame_uniforms, 0, bytemuck::cast_slice(&uniforms.to_std140()));
+        // Whatever the chunks don't cover shows the fog
+        let [r, g, b] = uniforms.fog_color.map(f64::from);
+        self.clear_color = wgpu::Color { r, g, b, a: 1.0 };
+    }
+
+    fn set_point_lights(&mut self, lights: &PointLights) {
+        let (positions, colors) = lights.uniforms();
+        let mut uniforms: BlockUniforms = [0; BLOCK_UNIFORM_WORDS];
+        uniforms[0] = positions.len() as u32;
+        uniforms[1] = u32::from(self.fast_leaves);
+        let (position_slots, color_slots) = uniforms[4..].split_at_mut(4 * MAX_POINT_LIGHTS);
+        for (slot, position) in position_slots.chunks_exact_mut(4).zip(positions) {
+            slot[..3].copy_from_slice(&position.map(f32::to_bits));
+        }
+        for (slot, color) in color_slots.chunks_exact_mut(4).zip(colors) {
+            slot.copy_from_slice(&color.map(f32::to_bits));
+        }
+        self.queue.write_buffer(&self.block_uniforms, 0, bytemuck::cast_slice(&uniforms));
+    }
+
+    fn set_fast_leaves(&mut self, fast: bool) {
+        self.fast_leaves = fast;
+        self.queue.write_buffer(&self.block_uniforms, FAST_LEAVES_OFFSET, bytemuck::cast_slice(&[u32::from(fast)]));
+    }
+
+    fn upload_all(&mut self, world: &World) {
+        self.upload_queue.clear();
+        self.queued.clear();
+        // Chunks keep buffers of their own, so those whose mesh was evicted
+        // keep drawing what they have
+        self.chunks.retain(|&position, _| world.is_mesh_evicted(position));
+        let positions: Vec<_> = world.chunks()
+            .map(|chunk| chunk.position)
+            .filter(|&position| !world.is_mesh_evicted(position))
+            .collect();
+        for position in positions {
+            self.write_mesh(world, position);
+        }
+    }
+
+    fn upload_chunk(&mut self, world: &World, position: (i32, i32, i32)) {
+        if self.queued.remove(&position) {
+            self.upload_queue.retain(|&queued| queued != position);
+        }
+        self.write_mesh(world, position);
+    }
+
+    fn queue_upload(&mut self, position: (i32, i32, i32)) {
+        if self.queued.insert(position) {
+            self.upload_queue.push_back(position);
+        }
+    }
+
+    fn queued_uploads(&self) -> usize {
+        self.upload_queue.len()
+    }
+
+    fn is_upload_queued(&self, position: (i32, i32, i32)) -> bool {
+        self.queued.contains(&position)
+    }
+
+    fn upload_queued(&mut self, world: &World, byte_budget: usize) -> usize {
+        let mut uploaded = 0;
+        let mut bytes = 0;
+        while let Some(&position) = self.upload_queue.front() {
+            let size = world.chunk(position).map_or(0, |chunk| {
+                std::mem::size_of_val(chunk.mesh.vertices.as_slice())
+                    + std::mem::size_of_val(chunk.mesh.indices.as_slice())
+                    + std::mem::size_of_val(chunk.mesh.water_indices.as_slice())
+            });
+            if byte_budget > 0 && uploaded > 0 && bytes + size > byte_budget {
+                break;
+            }
+            self.upload_queue.pop_front();
+            self.queued.remove(&position);
+            self.write_mesh(world, position);
+            uploaded += 1;
+            bytes += size;
+        }
+        uploaded
+    }
+
+    fn remove_chunk(&mut self, position: (i32, i32, i32)) {
+        self.chunks.remove(&position);
+        self.loaded_at.remove(&position);
+        if self.queued.remove(&position) {
+            self.upload_queue.retain(|&queued| queued != position);
+        }
+    }
+
+    fn take_lost_uploads(&mut self) -> Vec<(i32, i32, i32)> {
+        Vec::new()
+    }
+
+    fn gpu_bytes(&self) -> usize {
+        let index_bytes = |indices: &Option<(wgpu::Buffer, u32)>| indices.as_ref().map_or(0, |(buffer, _)| buffer.size());
+        self.chunks.values()
+            .map(|chunk| (chunk.vertices.size() + index_bytes(&chunk.opaque) + index_bytes(&chunk.water) + chunk.uniforms.size()) as usize)
+            .sum()
+    }
+
+    fn draw_opaque(&mut self) {
+        self.draw_pass(true, |chunk| chunk.opaque.as_ref());
+    }
+
+    fn draw_water(&mut self) {
+        self.draw_pass(false, |chunk| chunk.water.as_ref());
+    }
+
+    fn present(&mut self) {
+        if let Some(frame) = self.frame.take() {
+            frame.texture.present();
+        }
+    }
+}
+
+// Uploads images as the layers of a texture array, at the size of the first
+// one. Animated textures keep their first frame.
+fn load_texture_array(device: &wgpu::Device, queue: &wgpu::Queue, paths: &[&str]) -> Result<wgpu::TextureView, String> {
+    let mut texture = None;
+    for (layer, path) in paths.iter().enumerate() {
+        let data = asset_files::read(path).map_err(|e| format!("Failed to read texture {}: {}", path, e))?;
+        let image = image::load_from_memory(&data).map_err(|e| format!("Failed to load texture {}: {}", path, e))?;
+        let frame = image.crop_imm(0, 0, image.width(), image.width().min(image.height()));
+        let (width, height) = (frame.width(), frame.height());
+        let texture = texture.get_or_insert_with(|| device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(path),
+            size: wgpu::Extent3d { width, height, depth_or_array_layers: paths.len() as u32 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        }));
+        let size = texture.size();
+        let data = frame.resize_exact(size.width, size.height, image::imageops::FilterType::Nearest).to_rgba8();
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x: 0, y: 0, z: layer as u32 },
+                aspect: wgpu::TextureAspect::All,
+            },
+            &data,
+            wgpu::TexelCopyBufferLayout { offset: 0, bytes_per_row: Some(4 * size.width), rows_per_image: Some(size.height) },
+            wgpu::Extent3d { width: size.width, height: size.height, depth_or_array_layers: 1 },
+        );
+    }
+    let texture = texture.ok_or("No textures to load")?;
+    Ok(texture.create_view(&wgpu::TextureViewDescriptor {
+        dimension: Some(wgpu::TextureViewDimension::D2Array),
+        ..Default::default()
+    }))
+}
@@ -0,0 +1,1226 @@
+use crate::block::{BlockShape, BlockType};
+use crate::block_entity::BlockEntities;
+use crate::chunk_column::ChunkColumn;
+use crate::entity::Aabb;
+use crate::events::{ChunkProgress, ChunkStage, EventBus};
+use crate::explosion::Explosion;
+use crate::math::Vec3;
+use crate::mesh_cache::MeshCache;
+use crate::mesh_pool::MeshPool;
+use crate::mesher::{build_chunk_mesh, ChunkMesh};
+use crate::profiler::{Profiler, System};
+use crate::structures::{Placement, StructureSet, STRUCTURE_DIRECTORY};
+use crate::world_border::WorldBorder;
+use crate::world_generator::{NoiseGenerator, WorldGenerator};
+use noise::{NoiseFn, Perlin};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::thread;
+
+pub const CHUNK_SIZE: usize = 16;
+pub const WORLD_SEED: u32 = 42;
+// Number of chunks stacked in every column of the world
+pub const WORLD_HEIGHT_CHUNKS: i32 = 8;
+// Checked above a position to tell a cave from open air
+const CAVE_CEILING_SEARCH: i32 = 24;
+pub const SEA_LEVEL: i32 = 60;
+// Explosion craters: how much the edge wanders in and out, as a fraction of
+// the radius, and how many blocks across its bumps are
+const EXPLOSION_ROUGHNESS: f64 = 0.25;
+const EXPLOSION_BUMP_SIZE: f64 = 3.0;
+
+/// Blocks of a chunk, indexed by local x, y and z, in a single allocation.
+pub type ChunkBlocks = Box<[[[BlockType; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE]>;
+
+/// The blocks of a chunk of air.
+pub fn empty_blocks() -> ChunkBlocks {
+    Box::new([[[BlockType::Air; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE])
+}
+
+pub struct Chunk {
+    pub position: (i32, i32, i32),  // Chunk position in world space
+    pub blocks: ChunkBlocks,
+    pub mesh: ChunkMesh,
+}
+
+impl Chunk {
+    /// A chunk with the given blocks, generated or loaded.
+    pub fn with_blocks(position: (i32, i32, i32), blocks: ChunkBlocks) -> Self {
+        Self {
+            position,
+            blocks,
+            mesh: ChunkMesh::default(),
+        }
+    }
+}
+
+/// A block found along a ray.
+#[derive(Debug, Clone, Copy)]
+pub struct RayHit {
+    /// The block that was hit.
+    pub block: (i32, i32, i32),
+    /// Outward normal of the face the ray entered the block through, or zero
+    /// if the ray started inside it.
+    pub normal: (i32, i32, i32),
+    /// Where the ray entered the block.
+    pub point: Vec3,
+    /// How far along the ray the block was entered.
+    pub distance: f32,
+}
+
+impl RayHit {
+    /// The cell in front of the hit face, where a block placed against it goes.
+    pub fn adjacent(&self) -> (i32, i32, i32) {
+        let ((x, y, z), (nx, ny, nz)) = (self.block, self.normal);
+        (x + nx, y + ny, z + nz)
+    }
+}
+
+/// How far a box could move through the world, from `World::sweep_aabb`.
+#[derive(Debug, Clone, Copy)]
+pub struct CollisionResult {
+    /// The requested movement, cut short on each axis where the box hit a block.
+    pub movement: Vec3,
+    /// Per axis, the normal of the block face the box was stopped by, or zero
+    /// if it moved freely along that axis. A box pushed into a wall while
+    /// falling onto the floor gets both, such as (-1, 1, 0).
+    pub normal: (i32, i32, i32),
+    /// Whether the box was stopped moving down, so it rests on a block.
+    pub grounded: bool,
+}
+
+// Gap under which a box counts as touching a block rather than overlapping
+// it, so rounding after a move doesn't wedge it into the surface it stopped at
+const CONTACT_EPSILON: f32 = 1e-4;
+
+// Bytes of memory the blocks of a chunk take
+const CHUNK_BLOCK_BYTES: usize = std::mem::size_of::<[[[BlockType; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE]>();
+
+// Blocks changed in one chunk, by local position
+type ChunkEdits = HashMap<(usize, usize, usize), BlockType>;
+// A block to set, by world position
+type BlockEdit = ((i32, i32, i32), BlockType);
+
+pub struct World {
+    pub seed: u32,
+    // Generates terrain from the seed
+    generator: Box<dyn WorldGenerator>,
+    // Loaded chunks, by the column they are stacked in
+    columns: HashMap<(i32, i32), ChunkColumn>,
+    /// Whether leaves are meshed with every face so they can be seen through,
+    /// or as solid cubes that hide each other.
+    pub fancy_leaves: bool,
+    /// Extra state of blocks such as signs, dropped with their block.
+    pub block_entities: BlockEntities,
+    /// Edge of the world, past which no terrain is generated.
+    pub border: Option<WorldBorder>,
+    // Blocks changed since generation, by chunk and local position, so they
+    // survive the chunk being unloaded and generated again
+    edits: HashMap<(i32, i32, i32), ChunkEdits>,
+    // Chunks loaded from a save or received from a server, used instead of
+    // generated terrain
+    restored: HashMap<(i32, i32, i32), ChunkBlocks>,
+    // Saved chunks whose file hasn't been read yet. Until it is they can't be
+    // generated, or saved over
+    unread: HashSet<(i32, i32, i32)>,
+    structures: StructureSet,
+    // Buffers of replaced and unloaded meshes, reused for the next ones
+    mesh_pool: MeshPool,
+    // Chunks whose mesh was dropped once uploaded, keeping only its lights,
+    // until it's built again
+    evicted_meshes: HashSet<(i32, i32, i32)>,
+    // Columns whose loaded blocks changed or were added since they were last
+    // taken, for maps to redraw
+    changed_columns: HashSet<(i32, i32)>,
+    // Chunks edited since they were last taken, for autosaves to write
+    unsaved_chunks: HashSet<(i32, i32, i32)>,
+}
+
+impl World {
+    pub fn new(seed: u32) -> Self {
+        Self {
+            seed,
+            generator: Box::new(NoiseGenerator::default()),
+            columns: HashMap::new(),
+            fancy_leaves: true,
+            block_entities: BlockEntities::with_builtins(),
+            border: None,
+            edits: HashMap::new(),
+            restored: HashMap::new(),
+            unread: HashSet::new(),
+            structures: StructureSet::load(Path::new(STRUCTURE_DIRECTORY)),
+            mesh_pool: MeshPool::default(),
+            evicted_meshes: HashSet::new(),
+            changed_columns: HashSet::new(),
+            unsaved_chunks: HashSet::new(),
+        }
+    }
+
+    /// Generates terrain with another generator from now on, such as the one
+    /// of the world's preset, a heightmap or an imported Minecraft save.
+    pub fn use_generator(&mut self, generator: Box<dyn WorldGenerator>) {
+        self.generator = generator;
+    }
+
+    pub fn get_block(&self, world_x: i32, world_y: i32, world_z: i32) -> BlockType {
+        // Determine which chunk these coords belong to
+        let chunk_x = world_x.div_euclid(CHUNK_SIZE as i32);
+        let chunk_y = world_y.div_euclid(CHUNK_SIZE as i32);
+        let chunk_z = world_z.div_euclid(CHUNK_SIZE as i32);
+
+        // Find that chunk
+        if let Some(chunk) = self.chunk((chunk_x, chunk_y, chunk_z)) {
+            // Convert to local coords within chunk
+            let lx = (world_x.rem_euclid(CHUNK_SIZE as i32)) as usize;
+            let ly = (world_y.rem_euclid(CHUNK_SIZE as i32)) as usize;
+            let lz = (world_z.rem_euclid(CHUNK_SIZE as i32)) as usize;
+
+            chunk.blocks[lx][ly][lz]
+        } else {
+            BlockType::Air
+        }
+    }
+
+    /// Replaces a block. The change is remembered even if its chunk isn't loaded,
+    /// and applied when it is generated. A block entity of the block replaced
+    /// goes with it. Returns whether the chunk is loaded.
+    pub fn set_block(&mut self, world_x: i32, world_y: i32, world_z: i32, block: BlockType) -> bool {
+        let size = CHUNK_SIZE as i32;
+        let chunk_pos = (world_x.div_euclid(size), world_y.div_euclid(size), world_z.div_euclid(size));
+        let (lx, ly, lz) = (world_x.rem_euclid(size) as usize, world_y.rem_euclid(size) as usize, world_z.rem_euclid(size) as usize);
+        self.edits.entry(chunk_pos).or_default().insert((lx, ly, lz), block);
+        self.unsaved_chunks.insert(chunk_pos);
+        self.block_entities.block_changed((world_x, world_y, world_z), block);
+        let column = self.columns.get_mut(&(chunk_pos.0, chunk_pos.2));
+        if column.and_then(|column| column.set_block(lx, world_y, lz, block)).is_none() {
+            return false;
+        }
+        self.changed_columns.insert((chunk_pos.0, chunk_pos.2));
+        true
+    }
+
+    /// Replaces many blocks at once, like `set_block` for each but looking up
+    /// every chunk once. Blocks that already are what they'd be set to are
+    /// left alone. Meshes aren't touched: remesh `chunks_touching_blocks` of
+    /// the result once the batch is done. Returns the loaded blocks that changed.
+    pub fn set_blocks(&mut self, blocks: impl IntoIterator<Item = BlockEdit>) -> Vec<(i32, i32, i32)> {
+        let size = CHUNK_SIZE as i32;
+        let mut by_chunk: HashMap<(i32, i32, i32), Vec<BlockEdit>> = HashMap::new();
+        for ((x, y, z), block) in blocks {
+            by_chunk.entry((x.div_euclid(size), y.div_euclid(size), z.div_euclid(size))).or_default().push(((x, y, z), block));
+        }
+
+        let mut changed = Vec::new();
+        for (chunk_pos, blocks) in by_chunk {
+            let edits = self.edits.entry(chunk_pos).or_default();
+            let mut column = self.columns.get_mut(&(chunk_pos.0, chunk_pos.2));
+            for ((x, y, z), block) in blocks {
+                let (lx, ly, lz) = (x.rem_euclid(size) as usize, y.rem_euclid(size) as usize, z.rem_euclid(size) as usize);
+                if let Some(replaced) = column.as_mut().and_then(|column| column.set_block(lx, y, lz, block)) {
+                    if replaced == block {
+                        continue;
+                    }
+                    changed.push((x, y, z));
+                    self.changed_columns.insert((chunk_pos.0, chunk_pos.2));
+                }
+                edits.insert((lx, ly, lz), block);
+                self.unsaved_chunks.insert(chunk_pos);
+                self.block_entities.block_changed((x, y, z), block);
+            }
+        }
+        changed
+    }
+
+    /// Blows away the blocks around `center`, out to `radius` blocks give or
+    /// take the roughness of the crater's edge. A block goes when the power
+    /// left at its distance, dropping off towards the edge, beats its blast
+    /// resistance; fluids stay. The blocks are changed as one batch and not
+    /// remeshed, like `set_blocks`; the result lists them for that, and for
+    /// the particles, sound and knockback that go with the explosion.
+    pub fn explode(&mut self, center: Vec3, radius: f32, power: f32) -> Explosion {
+        // Noise of its own, so craters don't follow the terrain's bumps
+        let noise = Perlin::new(self.seed.wrapping_add(1337));
+        let reach = (radius * (1.0 + EXPLOSION_ROUGHNESS as f32)).ceil() as i32;
+        let cell = |v: f32| v.round() as i32;
+        let (cx, cy, cz) = (cell(center.x), cell(center.y), cell(center.z));
+
+        let mut removed = Vec::new();
+        for x in cx - reach..=cx + reach {
+            for y in cy - reach..=cy + reach {
+                for z in cz - reach..=cz + reach {
+                    let block = self.get_block(x, y, z);
+                    let Some(resistance) = block.blast_resistance() else {
+                        continue;
+                    };
+                    let distance = (Vec3::new(x as f32, y as f32, z as f32) - center).length();
+                    let bump = noise.get([x as f64 / EXPLOSION_BUMP_SIZE, y as f64 / EXPLOSION_BUMP_SIZE, z as f64 / EXPLOSION_BUMP_SIZE]);
+                    let edge = radius * (1.0 + (bump * EXPLOSION_ROUGHNESS) as f32);
+                    let strength = power * (1.0 - (distance / edge).powi(2));
+                    if strength > resistance {
+                        removed.push(((x, y, z), block));
+                    }
+                }
+            }
+        }
+
+        self.set_blocks(removed.iter().map(|&(position, _)| (position, BlockType::Air)));
+        log::debug!(target: "game", "Explosion at {:.1} {:.1} {:.1} removed {} blocks", center.x, center.y, center.z, removed.len());
+        Explosion { center, radius, power, removed }
+    }
+
+    /// Loaded chunks whose mesh depends on any of the given blocks, each once.
+    pub fn chunks_touching_blocks(&self, blocks: &[(i32, i32, i32)]) -> Vec<(i32, i32, i32)> {
+        let mut touching = blocks.iter()
+            .flat_map(|&(x, y, z)| self.chunks_touching(x, y, z))
+            .collect::<Vec<_>>();
+        touching.sort_unstable();
+        touching.dedup();
+        touching
+    }
+
+    /// Loaded chunks whose mesh depends on the given block: its own chunk, plus
+    /// the neighbours it borders.
+    pub fn chunks_touching(&self, world_x: i32, world_y: i32, world_z: i32) -> Vec<(i32, i32, i32)> {
+        let size = CHUNK_SIZE as i32;
+        let chunk = (world_x.div_euclid(size), world_y.div_euclid(size), world_z.div_euclid(size));
+        let local = (world_x.rem_euclid(size), world_y.rem_euclid(size), world_z.rem_euclid(size));
+
+        let mut touching = vec![chunk];
+        for (offset, local) in [(0, local.0), (1, local.1), (2, local.2)] {
+            let step = if local == 0 { -1 } else if local == size - 1 { 1 } else { continue };
+            let mut neighbor = [chunk.0, chunk.1, chunk.2];
+            neighbor[offset] += step;
+            touching.push((neighbor[0], neighbor[1], neighbor[2]));
+        }
+        touching.retain(|&pos| self.chunk(pos).is_some());
+        touching
+    }
+
+    /// Whether a position is inside a cave: in open air, with solid ground
+    /// somewhere above it.
+    pub fn is_in_cave(&self, position: Vec3) -> bool {
+        let (x, y, z) = (position.x.round() as i32, position.y.round() as i32, position.z.round() as i32);
+        // Nothing above at all means open sky, without looking for a ceiling
+        let Some(top) = self.highest_block(x, z).filter(|&top| top > y) else {
+            return false;
+        };
+        self.get_block(x, y, z) == BlockType::Air
+            && (1..=CAVE_CEILING_SEARCH.min(top - y)).any(|dy| self.get_block(x, y + dy, z).occludes())
+    }
+
+    /// Whether the chunk holding a position is loaded.
+    pub fn is_loaded(&self, position: Vec3) -> bool {
+        let size = CHUNK_SIZE as i32;
+        let cell = [position.x, position.y, position.z].map(|value| (value.round() as i32).div_euclid(size));
+        self.chunk((cell[0], cell[1], cell[2])).is_some()
+    }
+
+    /// Whether a position is inside a fluid block.
+    pub fn is_fluid_at(&self, position: Vec3) -> bool {
+        self.get_block(position.x.round() as i32, position.y.round() as i32, position.z.round() as i32).is_fluid()
+    }
+
+    /// Feet position on the nearest safe ground to a column within `radius`
+    /// columns of it, searching outwards in square rings: the top solid block
+    /// of a loaded column, as long as it isn't under water or a treetop.
+    pub fn find_spawn(&self, x: i32, z: i32, radius: i32) -> Option<Vec3> {
+        (0..=radius).find_map(|ring| {
+            (-ring..=ring)
+                .flat_map(|dx| (-ring..=ring).map(move |dz| (dx, dz)))
+                .filter(|&(dx, dz)| dx.abs() == ring || dz.abs() == ring)
+                .find_map(|(dx, dz)| self.spawn_surface(x + dx, z + dz))
+        })
+    }
+
+    // Feet position on top of a column's highest block, if it's safe to stand on
+    fn spawn_surface(&self, x: i32, z: i32) -> Option<Vec3> {
+        // Plants and the like on top are passed through on the way down
+        let top = self.highest_block(x, z)?;
+        let y = (0..=top).rev().find(|&y| {
+            let block = self.get_block(x, y, z);
+            block.collision_height().is_some() || block.is_fluid()
+        })?;
+        // Everything above is open, so only the ground itself can be unsafe
+        let ground = self.get_block(x, y, z);
+        if ground.is_fluid() || ground == BlockType::OakLeaves {
+            return None;
+        }
+        let height = ground.collision_height()?;
+        Some(Vec3::new(x as f32, y as f32 - 0.5 + height, z as f32))
+    }
+
+    /// How much of a box's height is under fluid, from 0 when it's dry to 1
+    /// when it's fully submerged. A layer of cells counts as fluid when any
+    /// cell of it the box overlaps holds a fluid.
+    pub fn submersion(&self, aabb: Aabb) -> f32 {
+        let height = aabb.max.y - aabb.min.y;
+        if height <= 0.0 {
+            return 0.0;
+        }
+        // Cells the box overlaps, whose edges are halfway between block centers
+        let first = |value: f32| (value + 0.5).floor() as i32;
+        let last = |value: f32| (value + 0.5).ceil() as i32 - 1;
+
+        let mut submerged = 0.0;
+        for y in first(aabb.min.y)..=last(aabb.max.y) {
+            let wet = (first(aabb.min.x)..=last(aabb.max.x))
+                .any(|x| (first(aabb.min.z)..=last(aabb.max.z)).any(|z| self.get_block(x, y, z).is_fluid()));
+            if wet {
+                let bottom = (y as f32 - 0.5).max(aabb.min.y);
+                let top = (y as f32 + 0.5).min(aabb.max.y);
+                submerged += (top - bottom).max(0.0);
+            }
+        }
+        submerged / height
+    }
+
+    /// Finds the first block along a ray within `max_distance`, visiting every
+    /// cell the ray crosses in order (voxel DDA). Air and fluids are passed
+    /// through; any other block is hit at the edge of its cell, whatever its shape.
+    /// Rays from an origin that isn't finite hit nothing.
+    pub fn raycast(&self, origin: Vec3, direction: Vec3, max_distance: f32) -> Option<RayHit> {
+        // NaN boundaries would never get past max_distance, so the walk would never end
+        if !direction.length().is_normal() || ![origin.x, origin.y, origin.z].iter().all(|value| value.is_finite()) {
+            return None;
+        }
+        let direction = direction.normalize();
+        let start = [origin.x, origin.y, origin.z];
+        let dir = [direction.x, direction.y, direction.z];
+
+        // Blocks are centered on integer coordinates, so their cells end halfway between them
+        let mut cell = start.map(|value| (value + 0.5).floor() as i32);
+        // Per axis: which way the ray steps, the distance to the next cell boundary,
+        // and the distance between boundaries
+        let mut step = [0; 3];
+        let mut next_boundary = [f32::INFINITY; 3];
+        let mut boundary_spacing = [f32::INFINITY; 3];
+        for axis in 0..3 {
+            if dir[axis] != 0.0 {
+                step[axis] = if dir[axis] > 0.0 { 1 } else { -1 };
+                let boundary = cell[axis] as f32 + 0.5 * step[axis] as f32;
+                next_boundary[axis] = (boundary - start[axis]) / dir[axis];
+                boundary_spacing[axis] = 1.0 / dir[axis].abs();
+            }
+        }
+
+        let mut normal = [0; 3];
+        let mut distance = 0.0;
+        loop {
+            let shape = self.get_block(cell[0], cell[1], cell[2]).info().shape;
+            if shape != BlockShape::Empty && shape != BlockShape::Fluid {
+                return Some(RayHit {
+                    block: (cell[0], cell[1], cell[2]),
+                    normal: (normal[0], normal[1], normal[2]),
+                    point: origin + direction * distance,
+                    distance,
+                });
+            }
+
+            // Cross into the neighbouring cell whose boundary is nearest
+            let axis = (0..3).min_by(|&a, &b| next_boundary[a].total_cmp(&next_boundary[b])).unwrap();
+            distance = next_boundary[axis];
+            if distance > max_distance {
+                return None;
+            }
+            cell[axis] += step[axis];
+            next_boundary[axis] += boundary_spacing[axis];
+            normal = [0; 3];
+            normal[axis] = -step[axis];
+        }
+    }
+
+    /// Moves a box, given in world coordinates, as far as it can towards
+    /// `velocity` (the distance to cover this step) without entering blocks
+    /// that can't be walked through. Slabs collide up to their height.
+    ///
+    /// Axes are resolved one at a time, vertical first and then x and z, so a
+    /// box blocked on one axis still slides along the others. Blocks the box
+    /// already overlaps don't stop it, letting it get out of them.
+    pub fn sweep_aabb(&self, aabb: Aabb, velocity: Vec3) -> CollisionResult {
+        let mut min = [aabb.min.x, aabb.min.y, aabb.min.z];
+        let mut max = [aabb.max.x, aabb.max.y, aabb.max.z];
+        let wanted = [velocity.x, velocity.y, velocity.z];
+        let mut movement = [0.0; 3];
+        let mut normal = [0; 3];
+        for axis in [1, 0, 2] {
+            let (distance, blocked) = self.clip_axis(&min, &max, axis, wanted[axis]);
+            if blocked {
+                normal[axis] = if wanted[axis] > 0.0 { -1 } else { 1 };
+            }
+            min[axis] += distance;
+            max[axis] += distance;
+            movement[axis] = distance;
+        }
+
+        CollisionResult {
+            movement: Vec3::new(movement[0], movement[1], movement[2]),
+            normal: (normal[0], normal[1], normal[2]),
+            grounded: normal[1] == 1,
+        }
+    }
+
+    /// Like `sweep_aabb`, but a box resting on the ground that walks into a
+    /// ledge at most `step_height` tall climbs onto it rather than stopping,
+    /// provided there's room above. Stepping is tried by lifting the box,
+    /// moving it across and settling it back down, and is only kept if it
+    /// gets further than sliding along the ledge.
+    pub fn sweep_aabb_stepping(&self, aabb: Aabb, velocity: Vec3, step_height: f32) -> CollisionResult {
+        let direct = self.sweep_aabb(aabb, velocity);
+        let blocked_sideways = direct.normal.0 != 0 || direct.normal.2 != 0;
+        if !direct.grounded || !blocked_sideways || step_height <= 0.0 {
+            return direct;
+        }
+
+        let up = self.sweep_aabb(aabb, Vec3::new(0.0, step_height, 0.0));
+        let raised = aabb.at(up.movement);
+        let across = self.sweep_aabb(raised, Vec3::new(velocity.x, 0.0, velocity.z));
+        let down = self.sweep_aabb(raised.at(across.movement), Vec3::new(0.0, velocity.y - up.movement.y, 0.0));
+
+        let horizontal = |movement: Vec3| movement.x * movement.x + movement.z * movement.z;
+        if horizontal(across.movement) <= horizontal(direct.movement) + CONTACT_EPSILON {
+            return direct;
+        }
+        CollisionResult {
+            movement: up.movement + across.movement + down.movement,
+            normal: (across.normal.0, down.normal.1, across.normal.2),
+            grounded: down.grounded,
+        }
+    }
+
+    // How far a box can move along one axis, and whether a block stopped it short
+    fn clip_axis(&self, min: &[f32; 3], max: &[f32; 3], axis: usize, distance: f32) -> (f32, bool) {
+        if distance == 0.0 {
+            return (0.0, false);
+        }
+        // Every cell the box covers on its way; blocks are centered on integer coordinates
+        let (mut low, mut high) = (*min, *max);
+        if distance > 0.0 {
+            high[axis] += distance;
+        } else {
+            low[axis] += distance;
+        }
+        let cells = |axis: usize| (low[axis] + 0.5).floor() as i32..=(high[axis] + 0.5).floor() as i32;
+
+        let mut allowed = distance;
+        for x in cells(0) {
+            for y in cells(1) {
+                for z in cells(2) {
+                    let Some(height) = self.get_block(x, y, z).collision_height() else {
+                        continue;
+                    };
+                    let block_min = [x as f32 - 0.5, y as f32 - 0.5, z as f32 - 0.5];
+                    let block_max = [x as f32 + 0.5, y as f32 - 0.5 + height, z as f32 + 0.5];
+                    // Only blocks level with the box on the other two axes are in the way
+                    let level = (0..3).filter(|&other| other != axis).all(|other| {
+                        min[other] < block_max[other] - CONTACT_EPSILON && max[other] > block_min[other] + CONTACT_EPSILON
+                    });
+                    if !level {
+                        continue;
+                    }
+                    if distance > 0.0 && max[axis] <= block_min[axis] + CONTACT_EPSILON {
+                        allowed = allowed.min((block_min[axis] - max[axis]).max(0.0));
+                    } else if distance < 0.0 && min[axis] >= block_max[axis] - CONTACT_EPSILON {
+                        allowed = allowed.max((block_max[axis] - min[axis]).min(0.0));
+                    }
+                }
+            }
+        }
+        (allowed, allowed.abs() < distance.abs())
+    }
+
+    /// Replaces the blocks of a chunk, whether or not it is loaded. Returns
+    /// whether it is loaded, in which case it needs remeshing.
+    pub fn restore_chunk(&mut self, position: (i32, i32, i32), blocks: ChunkBlocks) -> bool {
+        self.edits.remove(&position);
+        self.unread.remove(&position);
+        let loaded = match self.columns.get_mut(&(position.0, position.2)) {
+            Some(column) => match column.chunk_mut(position.1) {
+                Some(chunk) => {
+                    // Copied into the chunk's own blocks, without allocating
+                    chunk.blocks.clone_from(&blocks);
+                    column.recompute_heights();
+                    true
+                },
+                None => false,
+            },
+            None => false,
+        };
+        if loaded {
+            self.changed_columns.insert((position.0, position.2));
+        }
+        self.restored.insert(position, blocks);
+        loaded
+    }
+
+    /// Marks chunks as saved, for their files to be read before their
+    /// columns generate.
+    pub fn set_unread(&mut self, positions: impl IntoIterator<Item = (i32, i32, i32)>) {
+        self.unread.extend(positions);
+    }
+
+    /// The saved chunks of a column whose files haven't been read yet.
+    pub fn unread_chunks(&self, x: i32, z: i32) -> Vec<(i32, i32, i32)> {
+        (0..WORLD_HEIGHT_CHUNKS).map(|y| (x, y, z)).filter(|position| self.unread.contains(position)).collect()
+    }
+
+    /// Chunks edited while their saved file was still unread, which needs
+    /// reading before they can be saved.
+    pub fn edited_unread_chunks(&self) -> Vec<(i32, i32, i32)> {
+        self.unread.iter().copied().filter(|position| self.edits.contains_key(position)).collect()
+    }
+
+    /// Gives a chunk the blocks read from its saved file, or `None` if the
+    /// file couldn't be read, for it to generate afresh. Unlike
+    /// `restore_chunk`, edits made since still apply on top.
+    pub fn load_saved_chunk(&mut self, position: (i32, i32, i32), blocks: Option<ChunkBlocks>) {
+        self.unread.remove(&position);
+        if let Some(blocks) = blocks {
+            self.restored.insert(position, blocks);
+        }
+    }
+
+    /// Positions of the chunks that differ from generated terrain.
+    pub fn modified_chunks(&self) -> Vec<(i32, i32, i32)> {
+        let mut positions = self.edits.keys().chain(self.restored.keys()).copied().collect::<Vec<_>>();
+        positions.sort_unstable();
+        positions.dedup();
+        positions
+    }
+
+    /// Whether a chunk was edited since `take_unsaved_chunks` last took it.
+    pub fn is_unsaved(&self, position: (i32, i32, i32)) -> bool {
+        self.unsaved_chunks.contains(&position)
+    }
+
+    /// Chunks edited since the last call, to be saved again. Chunks whose
+    /// saved file is still unread wait for a later call.
+    pub fn take_unsaved_chunks(&mut self) -> Vec<(i32, i32, i32)> {
+        let (waiting, unsaved) = self.unsaved_chunks.drain().partition::<Vec<_>, _>(|position| self.unread.contains(position));
+        self.unsaved_chunks.extend(waiting);
+        unsaved
+    }
+
+    /// Current blocks of a chunk, borrowed if it's loaded and generated if
+    /// it isn't.
+    pub fn chunk_blocks(&self, position: (i32, i32, i32)) -> Cow<'_, ChunkBlocks> {
+        if let Some(chunk) = self.chunk(position) {
+            return Cow::Borrowed(&chunk.blocks);
+        }
+        let (mut chunk, structures) = self.build_terrain(position);
+        let structure = self.structures.column_placement(self.seed, self.generator.as_ref(), (position.0, position.2));
+        self.decorate(&mut chunk, structure.as_ref().filter(|_| structures));
+        Cow::Owned(chunk.blocks)
+    }
+
+    // Generates a chunk's terrain, or takes its restored blocks. Also returns
+    // whether structures go in it, which only generated terrain can get
+    fn build_terrain(&self, position: (i32, i32, i32)) -> (Chunk, bool) {
+        match self.restored.get(&position) {
+            Some(blocks) => (Chunk::with_blocks(position, blocks.clone()), false),
+            None => {
+                let data = self.generator.generate_chunk(self.seed, position);
+                (Chunk::with_blocks(position, data.blocks), data.structures)
+            },
+        }
+    }
+
+    // Stamps the structure of its column into a chunk's terrain if it gets
+    // one, then applies the edits made to it
+    fn decorate(&self, chunk: &mut Chunk, structure: Option<&Placement>) {
+        if let Some(structure) = structure {
+            self.structures.stamp(chunk, structure);
+        }
+        for (&(x, y, z), &block) in self.edits.get(&chunk.position).into_iter().flatten() {
+            chunk.blocks[x][y][z] = block;
+        }
+    }
+
+    // An empty column, with the generator's biome and the structure placed in it
+    fn new_column(&self, x: i32, z: i32) -> ChunkColumn {
+        let biome = self.generator.biome(self.seed, x, z);
+        ChunkColumn::new(biome, self.structures.column_placement(self.seed, self.generator.as_ref(), (x, z)))
+    }
+
+    /// The chunk at a chunk position, if it's loaded.
+    pub fn chunk(&self, (x, y, z): (i32, i32, i32)) -> Option<&Chunk> {
+        self.columns.get(&(x, z))?.chunk(y)
+    }
+
+    /// Every loaded chunk, in no particular order.
+    pub fn chunks(&self) -> impl Iterator<Item = &Chunk> {
+        self.columns.values().flat_map(ChunkColumn::chunks)
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.columns.values().map(ChunkColumn::chunk_count).sum()
+    }
+
+    /// The column at a chunk x and z, if it's loaded.
+    pub fn column(&self, x: i32, z: i32) -> Option<&ChunkColumn> {
+        self.columns.get(&(x, z))
+    }
+
+    /// Where the loaded columns are, as x and z in chunks and in no particular order.
+    pub fn loaded_columns(&self) -> impl Iterator<Item = (i32, i32)> + '_ {
+        self.columns.keys().copied()
+    }
+
+    /// Adds a chunk, to a new column if its column isn't loaded.
+    pub fn add_chunk(&mut self, chunk: Chunk) {
+        let (x, _, z) = chunk.position;
+        self.changed_columns.insert((x, z));
+        if !self.columns.contains_key(&(x, z)) {
+            let column = self.new_column(x, z);
+            self.columns.insert((x, z), column);
+        }
+        let position = chunk.position;
+        if let Some(replaced) = self.columns.get_mut(&(x, z)).and_then(|column| column.insert(chunk)) {
+            self.mesh_pool.recycle(replaced.mesh);
+            self.evicted_meshes.remove(&position);
+        }
+    }
+
+    /// Removes a column and its chunks, keeping their meshes' buffers for
+    /// later meshes. Returns the positions of the chunks removed.
+    pub fn unload_column(&mut self, x: i32, z: i32) -> Vec<(i32, i32, i32)> {
+        let Some(column) = self.columns.remove(&(x, z)) else {
+            return Vec::new();
+        };
+        column.into_chunks()
+            .map(|chunk| {
+                self.mesh_pool.recycle(chunk.mesh);
+                self.evicted_meshes.remove(&chunk.position);
+                chunk.position
+            })
+            .collect()
+    }
+
+    /// Drops the meshes of the chunks further than `distance` chunks from
+    /// the column at `center`, keeping only their lights, so all that's left
+    /// of them is what the renderer uploaded. Chunks `keep` says still need
+    /// theirs, such as those waiting to be uploaded, keep them. Remeshing a
+    /// chunk, as edits do, builds its mesh again.
+    pub fn evict_meshes(&mut self, center: (i32, i32), distance: i32, keep: impl Fn((i32, i32, i32)) -> bool) {
+        for (&(x, z), column) in &mut self.columns {
+            if (x - center.0).pow(2) + (z - center.1).pow(2) <= distance * distance {
+                continue;
+            }
+            for chunk in column.chunks_mut() {
+                if chunk.mesh.vertices.capacity() == 0 || self.evicted_meshes.contains(&chunk.position) || keep(chunk.position) {
+                    continue;
+                }
+                // A mesh with nothing to draw has nothing to upload again
+                if !chunk.mesh.indices.is_empty() || !chunk.mesh.water_indices.is_empty() {
+                    self.evicted_meshes.insert(chunk.position);
+                }
+                let lights = std::mem::take(&mut chunk.mesh.lights);
+                chunk.mesh = ChunkMesh { lights, origin: chunk.mesh.origin, ..ChunkMesh::default() };
+            }
+        }
+    }
+
+    /// Whether a chunk's mesh was dropped by `evict_meshes`, so it has to be
+    /// built again to be uploaded.
+    pub fn is_mesh_evicted(&self, position: (i32, i32, i32)) -> bool {
+        self.evicted_meshes.contains(&position)
+    }
+
+    /// Builds the meshes `evict_meshes` dropped again, for what needs every
+    /// loaded mesh.
+    pub fn rebuild_evicted_meshes(&mut self, cache: &MeshCache) {
+        let positions = self.evicted_meshes.iter().copied().collect::<Vec<_>>();
+        self.build_meshes(&positions, cache);
+    }
+
+    /// World y of the highest block that isn't air at `x`, `z`, or `None` if
+    /// the column isn't loaded or is all air.
+    pub fn highest_block(&self, x: i32, z: i32) -> Option<i32> {
+        let size = CHUNK_SIZE as i32;
+        self.column(x.div_euclid(size), z.div_euclid(size))?
+            .heights
+            .get(x.rem_euclid(size) as usize, z.rem_euclid(size) as usize)
+    }
+
+    /// Bytes of memory held by blocks: those of the loaded chunks, those kept
+    /// from saves and servers, and the edits made since generation.
+    pub fn block_bytes(&self) -> usize {
+        let edit_bytes = std::mem::size_of::<((usize, usize, usize), BlockType)>();
+        (self.chunk_count() + self.restored.len()) * CHUNK_BLOCK_BYTES
+            + self.edits.values().map(|edits| edits.capacity() * edit_bytes).sum::<usize>()
+    }
+
+    /// Bytes of memory held by the CPU copies of chunk meshes, including the
+    /// spare ones kept for later meshes.
+    pub fn mesh_bytes(&self) -> usize {
+        self.chunks().map(|chunk| chunk.mesh.heap_bytes()).sum::<usize>() + self.mesh_pool.spare_bytes()
+    }
+
+    /// Gives back the memory of the spare meshes, when memory runs short.
+    pub fn release_spare_meshes(&self) {
+        self.mesh_pool.clear();
+    }
+
+    /// Temperature of the biome at `x`, `z`, from -1 for the coldest to 1 for
+    /// the warmest, or `None` if the column isn't loaded.
+    pub fn temperature(&self, x: i32, z: i32) -> Option<f32> {
+        let size = CHUNK_SIZE as i32;
+        let column = self.column(x.div_euclid(size), z.div_euclid(size))?;
+        Some(column.biome.temperature(x.rem_euclid(size) as usize, z.rem_euclid(size) as usize))
+    }
+
+    /// Columns whose blocks changed, or that were loaded, since the last call.
+    pub fn take_changed_columns(&mut self) -> Vec<(i32, i32)> {
+        self.changed_columns.drain().collect()
+    }
+
+    /// Borrows a chunk together with its six face neighbours for meshing.
+    pub fn neighborhood(&self, position: (i32, i32, i32)) -> Option<ChunkNeighborhood<'_>> {
+        let (x, y, z) = position;
+        let center = self.chunk(position)?;
+        Some(ChunkNeighborhood {
+            center,
+            fancy_leaves: self.fancy_leaves,
+            neighbors: [
+                self.chunk((x + 1, y, z)),
+                self.chunk((x - 1, y, z)),
+                self.chunk((x, y + 1, z)),
+                self.chunk((x, y - 1, z)),
+                self.chunk((x, y, z + 1)),
+                self.chunk((x, y, z - 1)),
+            ],
+        })
+    }
+
+    /// Generates every chunk of the given columns, spreading the work over
+    /// all available cores, and adds the columns to the world. The terrain of
+    /// all of them is generated before any is decorated, and each stage is
+    /// published as a `ChunkProgress` once done. Returns the positions of the
+    /// chunks generated.
+    pub fn generate_columns(&mut self, columns: &[(i32, i32)], events: &mut EventBus) -> Vec<(i32, i32, i32)> {
+        let columns = columns.iter().copied().filter(|&(x, z)| self.column(x, z).is_none()).collect::<Vec<_>>();
+        if columns.is_empty() {
+            return Vec::new();
+        }
+        // The columns go in first, for their chunks to be decorated with their
+        // structures
+        let world = &*self;
+        let new_columns = parallel_map(&columns, |&(x, z)| world.new_column(x, z));
+        self.columns.extend(columns.iter().copied().zip(new_columns));
+
+        let world = &*self;
+        let positions = columns.iter()
+            .flat_map(|&(x, z)| (0..WORLD_HEIGHT_CHUNKS).map(move |y| (x, y, z)))
+            .collect::<Vec<_>>();
+        let mut chunks = parallel_map(&positions, |&pos| world.build_terrain(pos));
+        events.publish(ChunkProgress { stage: ChunkStage::Generating, chunks: chunks.len() });
+        parallel_for_each(&mut chunks, |(chunk, structures)| {
+            let structure = world.column(chunk.position.0, chunk.position.2).and_then(|column| column.structure.as_ref());
+            world.decorate(chunk, structure.filter(|_| *structures));
+        });
+        events.publish(ChunkProgress { stage: ChunkStage::Decorating, chunks: chunks.len() });
+        for (chunk, _) in chunks {
+            self.add_chunk(chunk);
+        }
+        positions
+    }
+
+    /// Rebuilds the mesh of the given chunks, spreading the work over all available cores.
+    /// Meshes found in the cache are loaded instead of rebuilt, and fresh ones are
+    /// stored for the next run. Returns how many chunks came from the cache.
+    pub fn build_meshes(&mut self, positions: &[(i32, i32, i32)], cache: &MeshCache) -> usize {
+        let _scope = Profiler::scope(System::Meshing);
+        // Meshing only reads the world, so every thread can share it
+        let world = &*self;
+        let meshes = parallel_map(positions, |&pos| {
+            let neighborhood = world.neighborhood(pos)?;
+            let hash = neighborhood.block_hash();
+            match cache.load(world.seed, pos, hash, &world.mesh_pool) {
+                Some(mesh) => Some((pos, mesh, true)),
+                None => {
+                    let mesh = build_chunk_mesh(&neighborhood, &world.mesh_pool);
+                    cache.store(world.seed, pos, hash, &mesh);
+                    Some((pos, mesh, false))
+                },
+            }
+        });
+
+        let mut cached = 0;
+        for (pos, mesh, from_cache) in meshes.into_iter().flatten() {
+            if from_cache {
+                cached += 1;
+            }
+            match self.columns.get_mut(&(pos.0, pos.2)).and_then(|column| column.chunk_mut(pos.1)) {
+                Some(chunk) => {
+                    self.mesh_pool.recycle(std::mem::replace(&mut chunk.mesh, mesh));
+                    self.evicted_meshes.remove(&pos);
+                },
+                None => self.mesh_pool.recycle(mesh),
+            }
+        }
+        cached
+    }
+}
+
+/// Maps every item on a pool of scoped threads, one batch per available core.
+/// With one core, or none known as in the browser, which can't spawn threads,
+/// the items are mapped on the calling thread.
+pub fn parallel_map<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    if threads == 1 {
+        return items.iter().map(f).collect();
+    }
+    let batch_size = items.len().div_ceil(threads).max(1);
+    let f = &f;
+
+    thread::scope(|scope| {
+        let workers = items
+            .chunks(batch_size)
+            .map(|batch| scope.spawn(move || batch.iter().map(f).collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("Worker thread panicked"))
+            .collect()
+    })
+}
+
+/// Runs `f` on every item on a pool of scoped threads, like `parallel_map`
+/// but changing the items in place.
+pub fn parallel_for_each<T: Send>(items: &mut [T], f: impl Fn(&mut T) + Sync) {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    if threads == 1 {
+        items.iter_mut().for_each(f);
+        return;
+    }
+    let batch_size = items.len().div_ceil(threads).max(1);
+    let f = &f;
+
+    thread::scope(|scope| {
+        for batch in items.chunks_mut(batch_size) {
+            scope.spawn(move || batch.iter_mut().for_each(f));
+        }
+    });
+}
+
+/// Read-only view of a chunk and its six face neighbours, enough to decide face
+/// visibility for every block of the chunk without touching the rest of the world.
+pub struct ChunkNeighborhood<'a> {
+    center: &'a Chunk,
+    // Neighbouring chunks in +x, -x, +y, -y, +z, -z order
+    neighbors: [Option<&'a Chunk>; 6],
+    fancy_leaves: bool,
+}
+
+impl ChunkNeighborhood<'_> {
+    /// World coordinates of the chunk's (0, 0, 0) block.
+    pub fn origin(&self) -> (i32, i32, i32) {
+        let size = CHUNK_SIZE as i32;
+        (self.center.position.0 * size, self.center.position.1 * size, self.center.position.2 * size)
+    }
+
+    /// Whether leaves are meshed to be seen through, see `World::fancy_leaves`.
+    pub fn fancy_leaves(&self) -> bool {
+        self.fancy_leaves
+    }
+
+    /// Gets a block by coordinates local to the center chunk. Coordinates may step
+    /// one chunk out along a single axis; anything further away reads as air.
+    pub fn get_block(&self, x: i32, y: i32, z: i32) -> BlockType {
+        let size = CHUNK_SIZE as i32;
+        let (chunk, x, y, z) = if x >= size {
+            (self.neighbors[0], x - size, y, z)
+        } else if x < 0 {
+            (self.neighbors[1], x + size, y, z)
+        } else if y >= size {
+            (self.neighbors[2], x, y - size, z)
+        } else if y < 0 {
+            (self.neighbors[3], x, y + size, z)
+        } else if z >= size {
+            (self.neighbors[4], x, y, z - size)
+        } else if z < 0 {
+            (self.neighbors[5], x, y, z + size)
+        } else {
+            (Some(self.center), x, y, z)
+        };
+
+        let in_chunk = |v: i32| (0..size).contains(&v);
+        match chunk {
+            Some(chunk) if in_chunk(x) && in_chunk(y) && in_chunk(z) => {
+                chunk.blocks[x as usize][y as usize][z as usize]
+            },
+            _ => BlockType::Air,
+        }
+    }
+
+    /// Hashes everything the center chunk's mesh depends on: its own blocks, the
+    /// facing layer of each neighbour and the leaves mode. Stable across runs and
+    /// platforms.
+    pub fn block_hash(&self) -> u64 {
+        // 64-bit FNV-1a
+        const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+        const PRIME: u64 = 0x100000001b3;
+
+        let size = CHUNK_SIZE as i32;
+        let outside = |v: i32| usize::from(!(0..size).contains(&v));
+        let mut hash = OFFSET_BASIS;
+        hash ^= u64::from(self.fancy_leaves);
+        hash = hash.wrapping_mul(PRIME);
+        for x in -1..=size {
+            for y in -1..=size {
+                for z in -1..=size {
+                    // Edges and corners of the neighbourhood never affect the mesh
+                    if outside(x) + outside(y) + outside(z) > 1 {
+                        continue;
+                    }
+                    hash ^= self.get_block(x, y, z) as u64;
+                    hash = hash.wrapping_mul(PRIME);
+                }
+            }
+        }
+        hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world_generator::ChunkData;
+
+    // Generates chunks that are empty but for the given blocks
+    struct Fixture(Vec<((i32, i32, i32), BlockType)>);
+
+    impl WorldGenerator for Fixture {
+        fn generate_chunk(&self, _seed: u32, position: (i32, i32, i32)) -> ChunkData {
+            let mut blocks = empty_blocks();
+            let size = CHUNK_SIZE as i32;
+            for &((x, y, z), block) in &self.0 {
+                if (x.div_euclid(size), y.div_euclid(size), z.div_euclid(size)) == position {
+                    blocks[x.rem_euclid(size) as usize][y.rem_euclid(size) as usize][z.rem_euclid(size) as usize] = block;
+                }
+            }
+            ChunkData { blocks, structures: false }
+        }
+    }
+
+    // A world of loaded chunks around the origin, empty but for the given blocks
+    fn world_with(blocks: &[((i32, i32, i32), BlockType)]) -> World {
+        let mut world = World::new(0);
+        world.use_generator(Box::new(Fixture(blocks.to_vec())));
+        for x in -2..2 {
+            for y in -2..2 {
+                for z in -2..2 {
+                    world.add_chunk(Chunk::with_blocks((x, y, z), world.chunk_blocks((x, y, z)).into_owned()));
+                }
+            }
+        }
+        world
+    }
+
+    fn assert_near(actual: Vec3, expected: Vec3) {
+        let error = (actual - expected).length();
+        assert!(error < 1e-4, "expected {:?}, got {:?}", expected, actual);
+    }
+
+    #[test]
+    fn raycast_hits_the_face_facing_the_ray() {
+        let world = world_with(&[((5, 0, 0), BlockType::Stone)]);
+        let hit = world.raycast(Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), 10.0).unwrap();
+        assert_eq!(hit.block, (5, 0, 0));
+        assert_eq!(hit.normal, (-1, 0, 0));
+        assert_eq!(hit.adjacent(), (4, 0, 0));
+        assert_near(hit.point, Vec3::new(4.5, 0.0, 0.0));
+        assert!((hit.distance - 4.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn raycast_stops_at_max_distance() {
+        let world = world_with(&[((5, 0, 0), BlockType::Stone)]);
+        assert!(world.raycast(Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), 4.4).is_none());
+        assert!(world.raycast(Vec3::new(0.0, 0.0, 0.0), Vec3::new(-1.0, 0.0, 0.0), 10.0).is_none());
+    }
+
+    #[test]
+    fn raycast_from_inside_a_block_hits_it_without_a_face() {
+        let world = world_with(&[((0, 0, 0), BlockType::Stone)]);
+        let hit = world.raycast(Vec3::new(0.2, 0.1, -0.3), Vec3::new(0.0, 1.0, 0.0), 10.0).unwrap();
+        assert_eq!(hit.block, (0, 0, 0));
+        assert_eq!(hit.normal, (0, 0, 0));
+        assert_eq!(hit.distance, 0.0);
+        assert_eq!(hit.adjacent(), hit.block);
+    }
+
+    #[test]
+    fn raycast_passes_through_fluids_but_not_plants() {
+        let world = world_with(&[
+            ((0, -1, 0), BlockType::Water),
+            ((0, -2, 0), BlockType::Water),
+            ((0, -3, 0), BlockType::Dirt),
+            ((3, 0, 0), BlockType::ShortGrass),
+        ]);
+        let down = world.raycast(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 10.0).unwrap();
+        assert_eq!(down.block, (0, -3, 0));
+        assert_eq!(down.normal, (0, 1, 0));
+        assert_near(down.point, Vec3::new(0.0, -2.5, 0.0));
+
+        let across = world.raycast(Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), 10.0).unwrap();
+        assert_eq!(across.block, (3, 0, 0));
+    }
+
+    #[test]
+    fn raycast_follows_diagonals_across_chunks() {
+        // Crosses from the chunks at negative coordinates into positive ones
+        let world = world_with(&[((3, 2, -1), BlockType::Stone)]);
+        let origin = Vec3::new(-4.0, -1.3, -1.0);
+        let direction = Vec3::new(7.0, 3.3, 0.0);
+        let hit = world.raycast(origin, direction, 20.0).unwrap();
+        assert_eq!(hit.block, (3, 2, -1));
+        // Already level with the block when x reaches 2.5, so it enters through the side
+        assert_eq!(hit.normal, (-1, 0, 0));
+        assert_near(hit.point, Vec3::new(2.5, -1.3 + 3.3 * 6.5 / 7.0, -1.0));
+        assert_near(hit.point, origin + direction.normalize() * hit.distance);
+    }
+
+    struct SweepCase {
+        name: &'static str,
+        blocks: &'static [((i32, i32, i32), BlockType)],
+        min: [f32; 3],
+        max: [f32; 3],
+        velocity: [f32; 3],
+        movement: [f32; 3],
+        normal: (i32, i32, i32),
+        grounded: bool,
+    }
+
+    // A player-sized box standing on the ground at y = -0.5, centered on x = z = 0
+    const MIN: [f32; 3] = [-0.3, -0.5, -0.3];
+    const MAX: [f32; 3] = [0.3, 1.3, 0.3];
+    const FLOOR: ((i32, i32, i32), BlockType) = ((0, -1, 0), BlockType::Stone);
+
+    const SWEEP_CASES: &[SweepCase] = &[
+        SweepCase {
+            name: "falls freely through air",
+            blocks: &[],
+            min: MIN, max: MAX, velocity: [0.0, -0.3, 0.0],
+            movement: [0.0, -0.3, 0.0], normal: (0, 0, 0), grounded: false,
+        },
+        SweepCase {
+            name: "lands on a full block",
+            blocks: &[FLOOR],
+            min: [-0.3, -0.3, -0.3], max: [0.3, 1.5, 0.3], velocity: [0.0, -0.5, 0.0],
+            movement: [0.0, -0.2, 0.0], normal: (0, 1, 0), grounded: true,
+        },
+        SweepCase {
+            name: "lands on top of a slab",
+            blocks: &[((0, 0, 0), BlockType::SmoothStoneSlab)],
+            min: [-0.3, 0.3, -0.3], max: [0.3, 2.1, 0.3], velocity: [0.0, -1.0, 0.0],
+            movement: [0.0, -0.3, 0.0], normal: (0, 1, 0), grounded: true,
+        },
+        SweepCase {
+            name: "is stopped by the side of a slab",
+            blocks: &[FLOOR, ((1, -1, 0), BlockType::Stone), ((1, 0, 0), BlockType::SmoothStoneSlab)],
+            min: MIN, max: MAX, velocity: [0.5, -0.1, 0.0],
+            movement: [0.2, 0.0, 0.0], normal: (-1, 1, 0), grounded: true,
+        },
+        SweepCase {
+            name: "walks along slabs once on top of them",
+            blocks: &[((0, 0, 0), BlockType::SmoothStoneSlab), ((1, 0, 0), BlockType::SmoothStoneSlab)],
+            min: [-0.3, 0.0, -0.3], max: [0.3, 1.8, 0.3], velocity: [0.5, -0.1, 0.0],
+            movement: [0.5, 0.0, 0.0], normal: (0, 1, 0), grounded: true,
+        },
+        SweepCase {
+            name: "slides along a wall",
+            blocks: &[FLOOR, ((1, 0, 0), BlockType::Stone), ((1, 1, 0), BlockType::Stone), ((1, 0, 1), BlockType::Stone)],
+            min: MIN, max: MAX, velocity: [0.5, 0.0, -0.4],
+            movement: [0.2, 0.0, -0.4], normal: (-1, 0, 0), grounded: false,
+        },
+        SweepCase {
+            name: "doesn't clip through the corner of a block moving diagonally",
+            blocks: &[((1, 0, 1), BlockType::Stone)],
+            min: MIN, max: MAX, velocity: [0.5, 0.0, 0.5],
+            movement: [0.5, 0.0, 0.2], normal: (0, 0, -1), grounded: false,
+        },
+        SweepCase {
+            name: "passes beside a block it only touches",
+            blocks: &[((1, 0, 1), BlockType::Stone)],
+            min: [-0.3, -0.5, -0.1], max: [0.3, 1.3, 0.5], velocity: [1.0, 0.0, 0.0],
+            movement: [1.0, 0.0, 0.0], normal: (0, 0, 0), grounded: false,
+        },
+        SweepCase {
+            name: "bumps its head on a ceiling",
+            blocks: &[((0, 2, 0), BlockType::Stone)],
+            min: [-0.3, -0.5, -0.3], max: [0.3, 1.3, 0.3], velocity: [0.0, 0.5, 0.0],
+            movement: [0.0, 0.2, 0.0], normal: (0, -1, 0), grounded: false,
+        },
+        SweepCase {
+            name: "walks through plants and water",
+            blocks: &[FLOOR, ((1, 0, 0), BlockType::ShortGrass), ((2, 0, 0), BlockType::Water)],
+            min: MIN, max: MAX, velocity: [2.0, 0.0, 0.0],
+            movement: [2.0, 0.0, 0.0], normal: (0, 0, 0), grounded: false,
+        },
+        SweepCase {
+            name: "can move out of a block it is stuck in",
+            blocks: &[((0, 0, 0), BlockType::Stone)],
+            min: MIN, max: MAX, velocity: [0.0, 0.8, 0.0],
+            movement: [0.0, 0.8, 0.0], normal: (0, 0, 0), grounded: false,
+        },
+    ];
+
+    #[test]
+    fn sweep_aabb_cases() {
+        for case in SWEEP_CASES {
+            let world = world_with(case.blocks);
+            let aabb = Aabb {
+                min: Vec3::new(case.min[0], case.min[1], case.min[2]),
+                max: Vec3::new(case.max[0], case.max[1], case.max[2]),
+            };
+            let velocity = Vec3::new(case.velocity[0], case.velocity[1], case.velocity[2]);
+            let result = world.sweep_aabb(aabb, velocity);
+
+            let expected = Vec3::new(case.movement[0], case.movement[1], case.movement[2]);
+            assert!((result.movement - expected).length() < 1e-4, "{}: moved {:?}, expected {:?}", case.name, result.movement, expected);
+            assert_eq!(result.normal, case.normal, "{}: normal", case.name);
+            assert_eq!(result.grounded, case.grounded, "{}: grounded", case.name);
+        }
+    }
+
+    #[test]
+    fn sweep_aabb_stepping_climbs_single_ledges() {
+        let aabb = Aabb { min: Vec3::new(MIN[0], MIN[1], MIN[2]), max: Vec3::new(MAX[0], MAX[1], MAX[2]) };
+        let walk = Vec3::new(0.5, -0.1, 0.0);
+
+        // Onto a block in the way
+        let world = world_with(&[FLOOR, ((1, -1, 0), BlockType::Stone), ((1, 0, 0), BlockType::Stone)]);
+        let result = world.sweep_aabb_stepping(aabb, walk, 1.0);
+        assert!((result.movement - Vec3::new(0.5, 1.0, 0.0)).length() < 1e-4, "moved {:?}", result.movement);
+        assert!(result.grounded);
+
+        // Not up a wall two blocks high, nor under a low ceiling
+        for blocks in [
+            &[FLOOR, ((1, 0, 0), BlockType::Stone), ((1, 1, 0), BlockType::Stone)][..],
+            &[FLOOR, ((1, 0, 0), BlockType::Stone), ((0, 2, 0), BlockType::Stone), ((1, 2, 0), BlockType::Stone)][..],
+        ] {
+            let result = world_with(blocks).sweep_aabb_stepping(aabb, walk, 1.0);
+            assert!((result.movement - Vec3::new(0.2, 0.0, 0.0)).length() < 1e-4, "moved {:?}", result.movement);
+            assert_eq!(result.normal, (-1, 1, 0));
+        }
+
+        // Nor while in the air
+        let result = world.sweep_aabb_stepping(aabb.at(Vec3::new(0.0, 0.5, 0.0)), walk, 1.0);
+        assert!((result.movement - Vec3::new(0.2, -0.1, 0.0)).length() < 1e-4, "moved {:?}", result.movement);
+    }
+
+    #[test]
+    fn raycast_needs_a_direction() {
+        let world = world_with(&[((0, 0, 0), BlockType::Stone)]);
+        assert!(world.raycast(Vec3::new(0.0, 0.0, 0.0), Vec3::zero(), 10.0).is_none());
+    }
+
+    #[test]
+    fn raycast_from_a_nan_origin_hits_nothing() {
+        let world = world_with(&[((0, 0, 0), BlockType::Stone)]);
+        let direction = Vec3::new(1.0, 0.0, 0.0);
+        assert!(world.raycast(Vec3::new(f32::NAN, 0.0, 0.0), direction, 10.0).is_none());
+        assert!(world.raycast(Vec3::new(0.0, f32::INFINITY, 0.0), direction, 10.0).is_none());
+    }
+}
@@ -0,0 +1,112 @@
+use crate::gl_utils::{buffer_data, Buffer, BufferType, GlState, ShaderProgram, VertexArray};
+use crate::math::Vec3;
+use crate::world::{CHUNK_SIZE, WORLD_HEIGHT_CHUNKS};
+
+/// Smallest border allowed, in blocks from its center to each side.
+pub const MIN_BORDER_RADIUS: f32 = 8.0;
+// The wall fades in as the camera comes within this many blocks of it
+const FADE_DISTANCE: f32 = 24.0;
+
+type WallVertex = [f32; 3];  // x, y, z
+
+/// A square around a center column that the player can't leave and terrain
+/// isn't generated past.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WorldBorder {
+    pub center_x: f32,
+    pub center_z: f32,
+    /// Distance from the center to each side, in blocks.
+    pub radius: f32,
+}
+
+impl WorldBorder {
+    pub fn new(center_x: f32, center_z: f32, radius: f32) -> Self {
+        Self { center_x, center_z, radius: radius.max(MIN_BORDER_RADIUS) }
+    }
+
+    /// Moves a position back inside the border, keeping `margin` blocks from the wall.
+    pub fn clamp(&self, position: Vec3, margin: f32) -> Vec3 {
+        let limit = (self.radius - margin).max(0.0);
+        Vec3::new(
+            position.x.clamp(self.center_x - limit, self.center_x + limit),
+            position.y,
+            position.z.clamp(self.center_z - limit, self.center_z + limit),
+        )
+    }
+
+    /// Whether the chunk column at `x`, `z` lies wholly outside the border, so
+    /// it is never generated.
+    pub fn excludes_column(&self, x: i32, z: i32) -> bool {
+        let size = CHUNK_SIZE as f32;
+        // Blocks are centered on integer coordinates, so columns start half a block early
+        let outside = |column: i32, center: f32| {
+            let min = column as f32 * size - 0.5;
+            min > center + self.radius || min + size < center - self.radius
+        };
+        outside(x, self.center_x) || outside(z, self.center_z)
+    }
+}
+
+/// Draws the border as a translucent wall with moving stripes, which shows up
+/// as the camera gets close to it.
+pub struct BorderRenderer {
+    program: ShaderProgram,
+    vao: VertexArray,
+    vertices: Buffer,
+}
+
+impl BorderRenderer {
+    pub fn new(program: ShaderProgram) -> Option<Self> {
+        let vao = VertexArray::new()?;
+        vao.bind();
+        let vertices = Buffer::new()?;
+        vertices.bind(BufferType::Array);
+        unsafe {
+            gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, std::mem::size_of::<WallVertex>() as i32, std::ptr::null());
+            gl::EnableVertexAttribArray(0);
+        }
+        Some(Self { program, vao, vertices })
+    }
+
+    /// Draws the sides of the border near the camera. Call after the terrain,
+    /// since the wall blends over it.
+    pub fn draw(&self, border: &WorldBorder, eye: Vec3) {
+        let (min_x, max_x) = (border.center_x - border.radius, border.center_x + border.radius);
+        let (min_z, max_z) = (border.center_z - border.radius, border.center_z + border.radius);
+        let (bottom, top) = (-0.5, (WORLD_HEIGHT_CHUNKS * CHUNK_SIZE as i32) as f32 - 0.5);
+
+        // Each side as two corners along the ground, skipped when the camera is far from it
+        let sides = [
+            (eye.x - min_x, [min_x, min_z], [min_x, max_z]),
+            (max_x - eye.x, [max_x, min_z], [max_x, max_z]),
+            (eye.z - min_z, [min_x, min_z], [max_x, min_z]),
+            (max_z - eye.z, [min_x, max_z], [max_x, max_z]),
+        ];
+        let mut batch: Vec<WallVertex> = Vec::new();
+        for (distance, [x0, z0], [x1, z1]) in sides {
+            if distance > FADE_DISTANCE {
+                continue;
+            }
+            let (a, b) = ([x0, bottom, z0], [x1, bottom, z1]);
+            let (c, d) = ([x1, top, z1], [x0, top, z0]);
+            batch.extend([a, b, c, c, d, a]);
+        }
+        if batch.is_empty() {
+            return;
+        }
+
+        self.program.use_program();
+        self.vao.bind();
+        self.vertices.bind(BufferType::Array);
+        buffer_data(BufferType::Array, bytemuck::cast_slice(&batch), gl::STREAM_DRAW);
+        unsafe {
+            gl::Uniform1f(gl::GetUniformLocation(self.program.0, b"fadeDistance\0".as_ptr() as *const i8), FADE_DISTANCE);
+            // Seen from both sides, and translucent, so it doesn't hide what's behind it
+            GlState::set_capability(gl::CULL_FACE, false);
+            GlState::depth_mask(false);
+            gl::DrawArrays(gl::TRIANGLES, 0, batch.len() as i32);
+            GlState::depth_mask(true);
+            GlState::set_capability(gl::CULL_FACE, true);
+        }
+    }
+}
@@ -0,0 +1,461 @@
+use crate::block::BlockType;
+use crate::chunk_column::ColumnBiome;
+use crate::heightmap::Heightmap;
+use crate::terrain_noise::{CaveGrid, ColumnNoise, TerrainNoise};
+use crate::world::{empty_blocks, ChunkBlocks, CHUNK_SIZE, SEA_LEVEL, WORLD_HEIGHT_CHUNKS};
+use noise::{NoiseFn, Perlin};
+use std::sync::{Arc, Mutex};
+
+/// Altitude above which grass gets a snow layer and water freezes over, in
+/// worlds created without one of their own.
+pub const DEFAULT_SNOW_ALTITUDE: i32 = 80;
+// How far the snow line wanders up and down with the temperature noise
+const SNOW_LINE_VARIATION: f64 = 24.0;
+// Cave noise above which a block is carved out
+const CAVE_THRESHOLD: f64 = 0.6;
+// Amplified terrain climbs this many times as steeply above sea level, easing
+// off as it nears the top of the world
+const AMPLIFIED_STRETCH: f64 = 2.5;
+// Floating islands: the altitude their tops float at, and how far their flat
+// tops and their pointed undersides reach from it
+const ISLAND_LEVEL: f64 = 80.0;
+const ISLAND_TOP: f64 = 10.0;
+const ISLAND_BOTTOM: f64 = 28.0;
+// Share of the sky with islands in it, from -1 for none to 1 for all of it
+const ISLAND_COVERAGE: f64 = -0.2;
+// Blocks around the origin where an island always floats, so there is
+// somewhere to spawn
+const SPAWN_ISLAND_RADIUS: f64 = 48.0;
+// Island density can't go above this, so blocks further from the island level
+// than it allows are skipped without sampling the noise
+const MAX_ISLAND_DENSITY: f64 = 2.8;
+// Blocks of grass and dirt over the stone
+const SOIL_DEPTH: usize = 3;
+
+/// Blocks of a superflat world when no layers are given, bottom first.
+pub const DEFAULT_LAYERS: &str = "3*stone,2*dirt,grass";
+
+/// A chunk as a generator made it.
+pub struct ChunkData {
+    pub blocks: ChunkBlocks,
+    /// Whether the parts of structures reaching into the chunk are stamped
+    /// into it.
+    pub structures: bool,
+}
+
+/// Makes the terrain of a world from its seed, a chunk at a time. New worlds
+/// get one from their `Preset`, and imported ones read theirs from elsewhere.
+/// `World` asks its generator for every chunk that has no saved blocks, so
+/// swapping it changes the terrain without touching `Chunk`.
+pub trait WorldGenerator: Send + Sync {
+    /// The chunk at a chunk position of the world with a seed. It depends only
+    /// on the two, so unloaded chunks come back the same.
+    fn generate_chunk(&self, seed: u32, position: (i32, i32, i32)) -> ChunkData;
+
+    /// Height of the ground structures stand on in a block column, or `None`
+    /// where they can't go, such as in the sea. Generators without structures
+    /// keep the default.
+    fn structure_ground(&self, _seed: u32, _x: i32, _z: i32) -> Option<i32> {
+        None
+    }
+
+    /// Climate of the chunk column at chunk x and z. Generators without one
+    /// keep the default, mild everywhere.
+    fn biome(&self, _seed: u32, _x: i32, _z: i32) -> ColumnBiome {
+        ColumnBiome::uniform(0.0)
+    }
+}
+
+// Something made from a seed, such as noise, kept until a different seed
+// comes along. A world only ever asks for its own
+struct Seeded<T> {
+    current: Mutex<Option<(u32, Arc<T>)>>,
+}
+
+impl<T> Default for Seeded<T> {
+    fn default() -> Self {
+        Self { current: Mutex::new(None) }
+    }
+}
+
+impl<T> Seeded<T> {
+    fn get(&self, seed: u32, make: fn(u32) -> T) -> Arc<T> {
+        let mut current = self.current.lock().unwrap();
+        match &*current {
+            Some((current_seed, value)) if *current_seed == seed => value.clone(),
+            _ => {
+                let value = Arc::new(make(seed));
+                *current = Some((seed, value.clone()));
+                value
+            },
+        }
+    }
+}
+
+/// The kinds of terrain a new world can be generated with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Preset {
+    /// Hills, seas and caves from noise.
+    #[default]
+    Default,
+    /// Flat layers of blocks, the same everywhere.
+    Superflat,
+    /// The default terrain with its mountains stretched far higher.
+    Amplified,
+    /// Islands floating in an empty sky.
+    FloatingIslands,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 4] = [Preset::Default, Preset::Superflat, Preset::Amplified, Preset::FloatingIslands];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Preset::Default => "default",
+            Preset::Superflat => "superflat",
+            Preset::Amplified => "amplified",
+            Preset::FloatingIslands => "floating_islands",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Preset> {
+        Self::ALL.into_iter().find(|preset| preset.name() == name)
+    }
+
+    /// Numeric id the preset is saved with.
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    pub fn from_id(id: u8) -> Option<Preset> {
+        Self::ALL.get(id as usize).copied()
+    }
+
+    /// The generator of a world with this preset. `layers` are the blocks of
+    /// a superflat world, bottom first, with the default ones when empty, and
+    /// `snow_altitude` where snow starts on the presets that have any.
+    pub fn generator(self, layers: &[BlockType], snow_altitude: i32) -> Box<dyn WorldGenerator> {
+        match self {
+            Preset::Default => Box::new(NoiseGenerator::new(snow_altitude)),
+            Preset::Superflat if layers.is_empty() => Box::new(SuperflatGenerator::new(parse_layers(DEFAULT_LAYERS).unwrap())),
+            Preset::Superflat => Box::new(SuperflatGenerator::new(layers.to_vec())),
+            Preset::Amplified => Box::new(AmplifiedGenerator::new(snow_altitude)),
+            Preset::FloatingIslands => Box::new(FloatingIslandsGenerator::default()),
+        }
+    }
+}
+
+/// Reads superflat layers written bottom first and separated by commas, each
+/// a block name with an optional count in front, such as `3*stone,grass`.
+pub fn parse_layers(text: &str) -> Result<Vec<BlockType>, String> {
+    let mut layers = Vec::new();
+    for layer in text.split(',').map(str::trim) {
+        let (count, name) = match layer.split_once('*') {
+            Some((count, name)) => (count.trim().parse::<usize>().map_err(|_| format!("Invalid layer count in {}", layer))?, name.trim()),
+            None => (1, layer),
+        };
+        let block = BlockType::from_name(name).ok_or_else(|| format!("Unknown block {}", name))?;
+        layers.extend(std::iter::repeat_n(block, count));
+    }
+    let height = WORLD_HEIGHT_CHUNKS as usize * CHUNK_SIZE;
+    if layers.len() > height {
+        return Err(format!("{} layers don't fit in the world's {} blocks", layers.len(), height));
+    }
+    Ok(layers)
+}
+
+/// The default terrain: hills from noise with caves under them and the sea
+/// filling the valleys.
+pub struct NoiseGenerator {
+    noise: Seeded<TerrainNoise>,
+    snow_altitude: i32,
+}
+
+impl NoiseGenerator {
+    pub fn new(snow_altitude: i32) -> Self {
+        Self { noise: Seeded::default(), snow_altitude }
+    }
+}
+
+impl Default for NoiseGenerator {
+    fn default() -> Self {
+        Self::new(DEFAULT_SNOW_ALTITUDE)
+    }
+}
+
+impl WorldGenerator for NoiseGenerator {
+    fn generate_chunk(&self, seed: u32, position: (i32, i32, i32)) -> ChunkData {
+        let noise = self.noise.get(seed, TerrainNoise::new);
+        let column = noise.column(position.0, position.2);
+        let caves = noise.caves(position);
+        let blocks = layered_terrain(position, &column, Some(&caves), self.snow_altitude, |x, z| column.height(local(x), local(z)));
+        ChunkData { blocks, structures: true }
+    }
+
+    fn structure_ground(&self, seed: u32, x: i32, z: i32) -> Option<i32> {
+        Some(self.noise.get(seed, TerrainNoise::new).height(x, z)).filter(|&ground| ground >= SEA_LEVEL)
+    }
+
+    fn biome(&self, seed: u32, x: i32, z: i32) -> ColumnBiome {
+        noise_biome(&self.noise.get(seed, TerrainNoise::new).column(x, z))
+    }
+}
+
+/// The default terrain with the ground heights of a heightmap instead of the
+/// noise ones. Caves are left out so real landscapes aren't riddled with
+/// holes, and so are structures, which are placed on the noise terrain.
+pub struct HeightmapGenerator {
+    heightmap: Heightmap,
+    // Still gives the snow line
+    noise: Seeded<TerrainNoise>,
+    snow_altitude: i32,
+}
+
+impl HeightmapGenerator {
+    pub fn new(heightmap: Heightmap, snow_altitude: i32) -> Self {
+        Self { heightmap, noise: Seeded::default(), snow_altitude }
+    }
+}
+
+impl WorldGenerator for HeightmapGenerator {
+    fn generate_chunk(&self, seed: u32, position: (i32, i32, i32)) -> ChunkData {
+        let column = self.noise.get(seed, TerrainNoise::new).column(position.0, position.2);
+        let blocks = layered_terrain(position, &column, None, self.snow_altitude, |x, z| self.heightmap.height(x, z));
+        ChunkData { blocks, structures: false }
+    }
+
+    fn biome(&self, seed: u32, x: i32, z: i32) -> ColumnBiome {
+        noise_biome(&self.noise.get(seed, TerrainNoise::new).column(x, z))
+    }
+}
+
+/// Flat layers of blocks from the bottom of the world up, with nothing above.
+pub struct SuperflatGenerator {
+    layers: Vec<BlockType>,
+}
+
+impl SuperflatGenerator {
+    pub fn new(layers: Vec<BlockType>) -> Self {
+        Self { layers }
+    }
+}
+
+impl WorldGenerator for SuperflatGenerator {
+    fn generate_chunk(&self, _seed: u32, position: (i32, i32, i32)) -> ChunkData {
+        let mut blocks = empty_blocks();
+        for column in blocks.iter_mut() {
+            for (y, row) in column.iter_mut().enumerate() {
+                let world_y = position.1 * CHUNK_SIZE as i32 + y as i32;
+                if let Some(&block) = usize::try_from(world_y).ok().and_then(|y| self.layers.get(y)) {
+                    row.fill(block);
+                }
+            }
+        }
+        ChunkData { blocks, structures: true }
+    }
+
+    fn structure_ground(&self, _seed: u32, _x: i32, _z: i32) -> Option<i32> {
+        Some(self.layers.len() as i32)
+    }
+}
+
+/// The default terrain with its land climbing far more steeply above sea
+/// level, into mountains that reach near the top of the world.
+pub struct AmplifiedGenerator {
+    noise: Seeded<TerrainNoise>,
+    snow_altitude: i32,
+}
+
+impl AmplifiedGenerator {
+    pub fn new(snow_altitude: i32) -> Self {
+        Self { noise: Seeded::default(), snow_altitude }
+    }
+}
+
+// Stretches the land above sea level upwards, easing off so even the highest
+// noise stays under the top of the world with room for snow
+fn amplify(height: i32) -> i32 {
+    if height <= SEA_LEVEL {
+        return height;
+    }
+    let room = (WORLD_HEIGHT_CHUNKS * CHUNK_SIZE as i32 - 2 - SEA_LEVEL) as f64;
+    let rise = (height - SEA_LEVEL) as f64 * AMPLIFIED_STRETCH;
+    SEA_LEVEL + (room * (1.0 - (-rise / room).exp())).round() as i32
+}
+
+impl WorldGenerator for AmplifiedGenerator {
+    fn generate_chunk(&self, seed: u32, position: (i32, i32, i32)) -> ChunkData {
+        let noise = self.noise.get(seed, TerrainNoise::new);
+        let column = noise.column(position.0, position.2);
+        let caves = noise.caves(position);
+        let blocks = layered_terrain(position, &column, Some(&caves), self.snow_altitude, |x, z| amplify(column.height(local(x), local(z))));
+        ChunkData { blocks, structures: true }
+    }
+
+    fn structure_ground(&self, seed: u32, x: i32, z: i32) -> Option<i32> {
+        Some(amplify(self.noise.get(seed, TerrainNoise::new).height(x, z))).filter(|&ground| ground >= SEA_LEVEL)
+    }
+
+    fn biome(&self, seed: u32, x: i32, z: i32) -> ColumnBiome {
+        noise_biome(&self.noise.get(seed, TerrainNoise::new).column(x, z))
+    }
+}
+
+// The noise floating islands are shaped by
+struct IslandNoise {
+    shape: Perlin,
+    spread: Perlin,
+}
+
+impl IslandNoise {
+    fn new(seed: u32) -> Self {
+        Self {
+            shape: Perlin::new(seed.wrapping_add(913)),
+            spread: Perlin::new(seed.wrapping_add(1046)),
+        }
+    }
+
+    // How much islands grow in a block column, higher where they are larger
+    // and more common
+    fn coverage(&self, x: i32, z: i32) -> f64 {
+        let spawn_island = (1.0 - (x as f64).hypot(z as f64) / SPAWN_ISLAND_RADIUS).max(0.0);
+        self.spread.get([x as f64 * 0.01, z as f64 * 0.01]) + ISLAND_COVERAGE + spawn_island
+    }
+
+    // Whether a block is inside an island, given its column's coverage
+    fn solid(&self, x: i32, y: i32, z: i32, coverage: f64) -> bool {
+        let offset = y as f64 - ISLAND_LEVEL;
+        let thickness = if offset > 0.0 { ISLAND_TOP } else { ISLAND_BOTTOM };
+        let falloff = (offset / thickness).powi(2);
+        if falloff > MAX_ISLAND_DENSITY {
+            return false;
+        }
+        let shape = self.shape.get([x as f64 * 0.03, y as f64 * 0.06, z as f64 * 0.03]);
+        shape + coverage - falloff > 0.0
+    }
+}
+
+/// Islands of grass, dirt and stone floating in an empty sky, with flat tops
+/// and pointed undersides. There is no sea, and nothing under them.
+#[derive(Default)]
+pub struct FloatingIslandsGenerator {
+    noise: Seeded<IslandNoise>,
+}
+
+impl WorldGenerator for FloatingIslandsGenerator {
+    fn generate_chunk(&self, seed: u32, position: (i32, i32, i32)) -> ChunkData {
+        let noise = self.noise.get(seed, IslandNoise::new);
+        let mut blocks = empty_blocks();
+        let bottom = position.1 * CHUNK_SIZE as i32;
+        for (x, column) in blocks.iter_mut().enumerate() {
+            for z in 0..CHUNK_SIZE {
+                let world_x = position.0 * CHUNK_SIZE as i32 + x as i32;
+                let world_z = position.2 * CHUNK_SIZE as i32 + z as i32;
+                let coverage = noise.coverage(world_x, world_z);
+                // From the block under the chunk to the soil depth above it,
+                // so surfaces match across chunk edges
+                let solid = (-1..(CHUNK_SIZE + SOIL_DEPTH) as i32)
+                    .map(|y| noise.solid(world_x, bottom + y, world_z, coverage))
+                    .collect::<Vec<_>>();
+                for (y, row) in column.iter_mut().enumerate() {
+                    // Shifted by one for the block under the chunk
+                    let (below, here) = (solid[y], solid[y + 1]);
+                    row[z] = if !here {
+                        if below { surface_plant(world_x, world_z) } else { BlockType::Air }
+                    } else if !solid[y + 2] {
+                        BlockType::Grass
+                    } else if solid[y + 2..y + 2 + SOIL_DEPTH].contains(&false) {
+                        BlockType::Dirt
+                    } else {
+                        BlockType::Stone
+                    };
+                }
+            }
+        }
+        // Structures could hang off the edge of an island into the void
+        ChunkData { blocks, structures: false }
+    }
+}
+
+// The biome of a column of noise terrain, with its temperatures
+fn noise_biome(column: &ColumnNoise) -> ColumnBiome {
+    let mut biome = ColumnBiome::uniform(0.0);
+    for x in 0..CHUNK_SIZE {
+        for z in 0..CHUNK_SIZE {
+            biome.set_temperature(x, z, column.temperature(x, z) as f32);
+        }
+    }
+    biome
+}
+
+// Position of a world block coordinate within its chunk
+fn local(coordinate: i32) -> usize {
+    coordinate.rem_euclid(CHUNK_SIZE as i32) as usize
+}
+
+// Grass over dirt over stone up to the height of each block column, given by
+// world x and z, with the sea up to its level and snow or plants on top, the
+// snow from around `snow_altitude` up. Caves are carved out where given
+fn layered_terrain(position: (i32, i32, i32), column: &ColumnNoise, caves: Option<&CaveGrid>, snow_altitude: i32, height: impl Fn(i32, i32) -> i32) -> ChunkBlocks {
+    let mut blocks = empty_blocks();
+    let cave_value = |x: usize, y: i32, z: usize| caves.map_or(0.0, |caves| caves.get(x, y, z));
+
+    for (x, slice) in blocks.iter_mut().enumerate() {
+        for z in 0..CHUNK_SIZE {
+            // Convert local coordinates to global coordinates
+            let world_x = position.0 * CHUNK_SIZE as i32 + x as i32;
+            let world_z = position.2 * CHUNK_SIZE as i32 + z as i32;
+            let height = height(world_x, world_z);
+
+            // Snow line for this column, lower in cold regions
+            let snow_line = snow_altitude + (column.temperature(x, z) * SNOW_LINE_VARIATION) as i32;
+
+            for (y, row) in slice.iter_mut().enumerate() {
+                let world_y = position.1 * CHUNK_SIZE as i32 + y as i32;
+
+                // Determine block type based on height and noise values
+                row[z] = if world_y < height {
+                    // Cave generation
+                    if cave_value(x, y as i32, z) > CAVE_THRESHOLD {
+                        BlockType::Air
+                    } else if world_y == height - 1 {
+                        BlockType::Grass
+                    } else if world_y >= height - SOIL_DEPTH as i32 {
+                        BlockType::Dirt
+                    } else {
+                        BlockType::Stone
+                    }
+                } else if world_y < SEA_LEVEL { // Water level
+                    if world_y == SEA_LEVEL - 1 && world_y >= snow_line {
+                        BlockType::Ice
+                    } else {
+                        BlockType::Water
+                    }
+                } else if world_y == height {
+                    // Cover the grass with snow or plants, unless a cave opened up the surface
+                    if cave_value(x, y as i32 - 1, z) > CAVE_THRESHOLD {
+                        BlockType::Air
+                    } else if world_y >= snow_line {
+                        BlockType::Snow
+                    } else {
+                        surface_plant(world_x, world_z)
+                    }
+                } else {
+                    BlockType::Air
+                };
+            }
+        }
+    }
+    blocks
+}
+
+// Picks the plant growing on the grass of a column, if any
+fn surface_plant(world_x: i32, world_z: i32) -> BlockType {
+    let hash = (world_x as u32).wrapping_mul(73856093) ^ (world_z as u32).wrapping_mul(19349663);
+    match (hash.wrapping_mul(0x9E3779B1) >> 16) & 0xFF {
+        0..=24 => BlockType::ShortGrass,
+        25..=27 => BlockType::Poppy,
+        28..=30 => BlockType::Dandelion,
+        _ => BlockType::Air,
+    }
+}
@@ -0,0 +1,188 @@
+use crate::atomic_file;
+use crate::chunk_data::ChunkStore;
+use crate::world::{ChunkBlocks, World};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+
+/// Files to write, each with what to write to it.
+pub type SaveJob = Vec<(PathBuf, Vec<u8>)>;
+
+// Work for the IO thread, done in the order it was sent
+enum Request {
+    Write(SaveJob),
+    Read(Vec<(i32, i32, i32)>),
+}
+
+// What the IO thread did with a request
+enum Done {
+    // The files that failed to write, with why
+    Written(Vec<(PathBuf, String)>),
+    // The chunks asked for, without blocks where their file couldn't be read
+    Read(Vec<((i32, i32, i32), Option<ChunkBlocks>)>),
+}
+
+/// Reads and writes the world's files on a thread of its own, so a slow disk
+/// never holds up a frame or the meshing threads. Saved chunks are read as
+/// streaming reaches their column, and saves are written with
+/// `atomic_file::write`, so a crash mid-save leaves the previous save whole.
+pub struct WorldIo {
+    requests: Option<Sender<Request>>,
+    done: Receiver<Done>,
+    // Where chunks are read from here if the IO thread stops
+    store: ChunkStore,
+    worker: Option<JoinHandle<()>>,
+    // Requests sent and not yet done
+    pending_writes: usize,
+    pending_reads: usize,
+}
+
+impl WorldIo {
+    /// Starts the IO thread, which reads chunks from the given store.
+    pub fn new(store: ChunkStore) -> Self {
+        let (requests, received_requests) = mpsc::channel();
+        let (sender, done) = mpsc::channel();
+        let worker = {
+            let store = store.clone();
+            thread::spawn(move || serve(store, received_requests, sender))
+        };
+        Self {
+            requests: Some(requests),
+            done,
+            store,
+            worker: Some(worker),
+            pending_writes: 0,
+            pending_reads: 0,
+        }
+    }
+
+    /// How many writes were sent and haven't been written yet.
+    pub fn pending_writes(&self) -> usize {
+        self.pending_writes
+    }
+
+    /// Writes files in the background.
+    pub fn write(&mut self, job: SaveJob) {
+        if self.send(Request::Write(job)) {
+            self.pending_writes += 1;
+        }
+    }
+
+    /// Reads saved chunks in the background, for `update` to hand them to the
+    /// world once they are.
+    pub fn read_chunks(&mut self, positions: Vec<(i32, i32, i32)>, world: &mut World) {
+        if positions.is_empty() {
+            return;
+        }
+        if self.send(Request::Read(positions.clone())) {
+            self.pending_reads += 1;
+            return;
+        }
+        // Without the IO thread they're read here, rather than generated
+        // afresh and saved over
+        for (position, blocks) in read(&self.store, positions) {
+            world.load_saved_chunk(position, blocks);
+        }
+    }
+
+    /// Gives the world the chunks read since the last call, and reports the
+    /// writes that failed.
+    pub fn update(&mut self, world: &mut World) {
+        while let Ok(done) = self.done.try_recv() {
+            self.finish_request(done, world);
+        }
+    }
+
+    /// Waits for the next read to be done, if there's any being read, and
+    /// gives the world its chunks. For when there's nothing else to do.
+    pub fn wait_for_read(&mut self, world: &mut World) {
+        while self.pending_reads > 0 {
+            let Ok(done) = self.done.recv() else {
+                return;
+            };
+            let read = matches!(done, Done::Read(_));
+            self.finish_request(done, world);
+            if read {
+                return;
+            }
+        }
+    }
+
+    /// Waits for what's still being written, so a save made after can't be
+    /// overwritten by an older one.
+    pub fn finish(mut self) {
+        self.requests = None;
+        if let Some(worker) = self.worker.take() {
+            if worker.join().is_err() {
+                log::error!(target: "io", "World IO thread panicked");
+            }
+        }
+        for done in self.done.try_iter() {
+            if let Done::Written(failures) = done {
+                report(&failures);
+            }
+        }
+    }
+
+    fn send(&mut self, request: Request) -> bool {
+        let Some(requests) = &self.requests else {
+            return false;
+        };
+        if requests.send(request).is_ok() {
+            return true;
+        }
+        log::error!(target: "io", "World IO thread stopped, the world is only saved on exit");
+        self.requests = None;
+        false
+    }
+
+    fn finish_request(&mut self, done: Done, world: &mut World) {
+        match done {
+            Done::Written(failures) => {
+                self.pending_writes -= 1;
+                report(&failures);
+            },
+            Done::Read(chunks) => {
+                self.pending_reads -= 1;
+                for (position, blocks) in chunks {
+                    world.load_saved_chunk(position, blocks);
+                }
+            },
+        }
+    }
+}
+
+// Runs on the IO thread until the game stops sending requests
+fn serve(store: ChunkStore, requests: Receiver<Request>, done: Sender<Done>) {
+    for request in requests {
+        let result = match request {
+            Request::Write(job) => Done::Written(
+                job.into_iter()
+                    .filter_map(|(path, data)| atomic_file::write(&path, &data).err().map(|e| (path, e.to_string())))
+                    .collect(),
+            ),
+            Request::Read(positions) => Done::Read(read(&store, positions)),
+        };
+        if done.send(result).is_err() {
+            return;
+        }
+    }
+}
+
+fn read(store: &ChunkStore, positions: Vec<(i32, i32, i32)>) -> Vec<((i32, i32, i32), Option<ChunkBlocks>)> {
+    positions.into_iter()
+        .map(|position| match store.load(position) {
+            Ok(blocks) => (position, Some(blocks)),
+            Err(e) => {
+                log::warn!(target: "io", "Failed to load chunk {}: {}", store.path(position).display(), e);
+                (position, None)
+            },
+        })
+        .collect()
+}
+
+fn report(failures: &[(PathBuf, String)]) {
+    for (path, e) in failures {
+        log::error!(target: "io", "Failed to write {}: {}", path.display(), e);
+    }
+}
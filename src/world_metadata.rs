@@ -0,0 +1,127 @@
+use crate::atomic_file;
+use crate::block::{BlockType, RENAMED_BLOCKS};
+use crate::byte_reader::ByteReader;
+use crate::game_mode::GameMode;
+use crate::world_border::WorldBorder;
+use crate::world_generator::{Preset, DEFAULT_SNOW_ALTITUDE};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// Identifies a world metadata file
+const MAGIC: &[u8; 4] = b"WRLD";
+const VERSION: u32 = 5;
+// Files from before the world border have only the game mode
+const VERSION_WITHOUT_BORDER: u32 = 1;
+// Files from before presets, whose worlds all have the default one
+const VERSION_WITHOUT_PRESET: u32 = 2;
+// Files from before the snow altitude, whose worlds all have the default one
+const VERSION_WITHOUT_SNOW_ALTITUDE: u32 = 3;
+// Files from before layers were saved by block name, which saved them by id
+const VERSION_WITH_LAYER_IDS: u32 = 4;
+
+/// Settings that belong to a saved world rather than to the player's machine.
+#[derive(Debug, Clone)]
+pub struct WorldMetadata {
+    pub game_mode: GameMode,
+    pub border: Option<WorldBorder>,
+    /// The terrain the world was created with, which it keeps.
+    pub preset: Preset,
+    /// Blocks of a superflat world, bottom first, or empty for the default ones.
+    pub layers: Vec<BlockType>,
+    /// Altitude around which the terrain gets snow and frozen water, lower in
+    /// cold regions and higher in warm ones.
+    pub snow_altitude: i32,
+}
+
+impl Default for WorldMetadata {
+    fn default() -> Self {
+        Self {
+            game_mode: GameMode::default(),
+            border: None,
+            preset: Preset::default(),
+            layers: Vec::new(),
+            snow_altitude: DEFAULT_SNOW_ALTITUDE,
+        }
+    }
+}
+
+impl WorldMetadata {
+    /// Writes the metadata to a file, creating its directory if needed.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        atomic_file::write(path, &self.encode())
+    }
+
+    /// The metadata in the format `save` writes.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(MAGIC);
+        data.extend_from_slice(&VERSION.to_le_bytes());
+        data.push(self.game_mode.id());
+        // A flag for whether there is a border, then its center and radius
+        data.push(self.border.is_some() as u8);
+        let border = self.border.map_or([0.0; 3], |border| [border.center_x, border.center_z, border.radius]);
+        for value in border {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        // The preset, then the number of layers and the name of each layer's
+        // block after the name's length
+        data.push(self.preset.id());
+        data.extend_from_slice(&(self.layers.len() as u16).to_le_bytes());
+        for block in &self.layers {
+            let name = block.info().name;
+            data.push(name.len() as u8);
+            data.extend_from_slice(name.as_bytes());
+        }
+        data.extend_from_slice(&self.snow_altitude.to_le_bytes());
+        data
+    }
+
+    /// Reads metadata written by `save`.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let data = fs::read(path)?;
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "Invalid world metadata file");
+
+        let mut reader = ByteReader::new(&data);
+        if reader.take(4) != Some(MAGIC) {
+            return Err(invalid());
+        }
+        let version = reader.u32().ok_or_else(invalid)?;
+        if !(VERSION_WITHOUT_BORDER..=VERSION).contains(&version) {
+            return Err(invalid());
+        }
+
+        let game_mode = reader.u8().and_then(GameMode::from_id).ok_or_else(invalid)?;
+        let border = if version > VERSION_WITHOUT_BORDER {
+            // The center and radius are there even without a border
+            let present = reader.u8().ok_or_else(invalid)? != 0;
+            let mut value = || reader.f32().ok_or_else(invalid);
+            let (x, z, radius) = (value()?, value()?, value()?);
+            present.then(|| WorldBorder::new(x, z, radius))
+        } else {
+            None
+        };
+        let (preset, layers) = if version > VERSION_WITHOUT_PRESET {
+            let preset = reader.u8().and_then(Preset::from_id).ok_or_else(invalid)?;
+            let count = reader.u16().ok_or_else(invalid)?;
+            let layers = (0..count)
+                .map(|_| match version {
+                    VERSION_WITHOUT_SNOW_ALTITUDE | VERSION_WITH_LAYER_IDS => reader.u8().and_then(BlockType::from_saved_id),
+                    _ => reader.short_str().and_then(|name| BlockType::from_saved_name(name, RENAMED_BLOCKS)),
+                })
+                .map(|block| block.ok_or_else(invalid))
+                .collect::<io::Result<Vec<_>>>()?;
+            (preset, layers)
+        } else {
+            (Preset::Default, Vec::new())
+        };
+        let snow_altitude = match version {
+            VERSION_WITHOUT_BORDER | VERSION_WITHOUT_PRESET | VERSION_WITHOUT_SNOW_ALTITUDE => DEFAULT_SNOW_ALTITUDE,
+            _ => reader.i32().ok_or_else(invalid)?,
+        };
+        if !reader.remaining().is_empty() {
+            return Err(invalid());
+        }
+        Ok(Self { game_mode, border, preset, layers, snow_altitude })
+    }
+}